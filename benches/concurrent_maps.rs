@@ -0,0 +1,172 @@
+//! Latency of the server's task map under concurrent submit/update/read load
+//!
+//! Compares the previous global `RwLock<HashMap>` against `ShardedMap`. Writers
+//! hold their lock while "persisting" (a short sleep standing in for the sled
+//! flush), which is what made every read wait on every write before.
+//!
+//! Run with `cargo bench --bench concurrent_maps`.
+
+use dashmap::DashMap;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use task_queue::server::ShardedMap;
+use task_queue::Task;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+const PRELOADED_TASKS: usize = 5_000;
+const WRITERS: usize = 8;
+const READERS: usize = 32;
+const OPS_PER_WORKER: usize = 200;
+const PERSIST_DELAY: Duration = Duration::from_micros(200);
+
+fn sample_tasks() -> Vec<Task> {
+    (0..PRELOADED_TASKS)
+        .map(|i| Task::new(&format!("task-{}", i)).with_command("true").build())
+        .collect()
+}
+
+fn percentile(samples: &mut [Duration], p: f64) -> Duration {
+    samples.sort();
+    let idx = ((samples.len() as f64 - 1.0) * p).round() as usize;
+    samples[idx]
+}
+
+fn report(name: &str, mut reads: Vec<Duration>, mut writes: Vec<Duration>, elapsed: Duration) {
+    println!(
+        "{:<24} read p50 {:>9.1?}  p99 {:>9.1?} | write p50 {:>9.1?}  p99 {:>9.1?} | total {:?}",
+        name,
+        percentile(&mut reads, 0.50),
+        percentile(&mut reads, 0.99),
+        percentile(&mut writes, 0.50),
+        percentile(&mut writes, 0.99),
+        elapsed,
+    );
+}
+
+async fn bench_global_lock(tasks: &[Task], ids: &Arc<Vec<Uuid>>) {
+    let map: Arc<RwLock<HashMap<Uuid, Task>>> =
+        Arc::new(RwLock::new(tasks.iter().map(|t| (t.id, t.clone())).collect()));
+
+    let start = Instant::now();
+    let mut writers = Vec::new();
+    for w in 0..WRITERS {
+        let (map, ids) = (map.clone(), ids.clone());
+        writers.push(tokio::spawn(async move {
+            let mut samples = Vec::with_capacity(OPS_PER_WORKER);
+            for i in 0..OPS_PER_WORKER {
+                let id = ids[(w * OPS_PER_WORKER + i) % ids.len()];
+                let t = Instant::now();
+                let mut guard = map.write().await;
+                if let Some(task) = guard.get_mut(&id) {
+                    task.updated_at = std::time::SystemTime::now();
+                }
+                tokio::time::sleep(PERSIST_DELAY).await;
+                drop(guard);
+                samples.push(t.elapsed());
+            }
+            samples
+        }));
+    }
+
+    let mut readers = Vec::new();
+    for r in 0..READERS {
+        let (map, ids) = (map.clone(), ids.clone());
+        readers.push(tokio::spawn(async move {
+            let mut samples = Vec::with_capacity(OPS_PER_WORKER);
+            for i in 0..OPS_PER_WORKER {
+                let id = ids[(r * 7919 + i) % ids.len()];
+                let t = Instant::now();
+                let task = map.read().await.get(&id).cloned();
+                samples.push(t.elapsed());
+                std::hint::black_box(task);
+            }
+            samples
+        }));
+    }
+
+    let (reads, writes) = collect(readers, writers).await;
+    report("global RwLock<HashMap>", reads, writes, start.elapsed());
+}
+
+async fn bench_sharded(tasks: &[Task], ids: &Arc<Vec<Uuid>>) {
+    let map: ShardedMap<Task> = Arc::new(DashMap::new());
+    for task in tasks {
+        map.insert(task.id, Arc::new(RwLock::new(task.clone())));
+    }
+
+    let start = Instant::now();
+    let mut writers = Vec::new();
+    for w in 0..WRITERS {
+        let (map, ids) = (map.clone(), ids.clone());
+        writers.push(tokio::spawn(async move {
+            let mut samples = Vec::with_capacity(OPS_PER_WORKER);
+            for i in 0..OPS_PER_WORKER {
+                let id = ids[(w * OPS_PER_WORKER + i) % ids.len()];
+                let t = Instant::now();
+                let entry = map.get(&id).map(|e| e.value().clone());
+                if let Some(entry) = entry {
+                    let mut task = entry.write().await;
+                    task.updated_at = std::time::SystemTime::now();
+                    tokio::time::sleep(PERSIST_DELAY).await;
+                }
+                samples.push(t.elapsed());
+            }
+            samples
+        }));
+    }
+
+    let mut readers = Vec::new();
+    for r in 0..READERS {
+        let (map, ids) = (map.clone(), ids.clone());
+        readers.push(tokio::spawn(async move {
+            let mut samples = Vec::with_capacity(OPS_PER_WORKER);
+            for i in 0..OPS_PER_WORKER {
+                let id = ids[(r * 7919 + i) % ids.len()];
+                let t = Instant::now();
+                let entry = map.get(&id).map(|e| e.value().clone());
+                let task = match entry {
+                    Some(entry) => Some(entry.read().await.clone()),
+                    None => None,
+                };
+                samples.push(t.elapsed());
+                std::hint::black_box(task);
+            }
+            samples
+        }));
+    }
+
+    let (reads, writes) = collect(readers, writers).await;
+    report("sharded DashMap", reads, writes, start.elapsed());
+}
+
+type Samples = tokio::task::JoinHandle<Vec<Duration>>;
+
+async fn collect(readers: Vec<Samples>, writers: Vec<Samples>) -> (Vec<Duration>, Vec<Duration>) {
+    let mut reads = Vec::new();
+    for handle in readers {
+        reads.extend(handle.await.unwrap());
+    }
+    let mut writes = Vec::new();
+    for handle in writers {
+        writes.extend(handle.await.unwrap());
+    }
+    (reads, writes)
+}
+
+fn main() {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(4)
+        .enable_all()
+        .build()
+        .unwrap();
+
+    let tasks = sample_tasks();
+    let ids = Arc::new(tasks.iter().map(|t| t.id).collect::<Vec<_>>());
+
+    runtime.block_on(async {
+        bench_global_lock(&tasks, &ids).await;
+        bench_sharded(&tasks, &ids).await;
+    });
+}
@@ -2,6 +2,9 @@ use std::process::Command;
 use std::path::Path;
 
 fn main() {
+    #[cfg(feature = "grpc")]
+    compile_protos();
+
     println!("cargo:rerun-if-changed=dashboard/src");
     println!("cargo:rerun-if-changed=dashboard/index.html");
     println!("cargo:rerun-if-changed=dashboard/vite.config.js");
@@ -59,3 +62,18 @@ fn main() {
         println!("cargo:warning=Dashboard package.json not found, skipping build");
     }
 }
+
+/// Generate the gRPC service from `proto/task_queue.proto`, with the vendored
+/// `protoc` unless `PROTOC` names another
+#[cfg(feature = "grpc")]
+fn compile_protos() {
+    println!("cargo:rerun-if-changed=proto/task_queue.proto");
+    if std::env::var_os("PROTOC").is_none() {
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("no vendored protoc for this platform");
+        // SAFETY: build scripts are single-threaded
+        unsafe { std::env::set_var("PROTOC", protoc) };
+    }
+    tonic_prost_build::configure()
+        .compile_protos(&["proto/task_queue.proto"], &["proto"])
+        .expect("failed to compile proto/task_queue.proto");
+}
@@ -56,6 +56,20 @@ pub enum Commands {
     Server(ServerCommand),
     /// Configuration management
     Config(ConfigCommand),
+    /// Converge the server to the projects, tasks and workflows declared in a file
+    Apply {
+        /// Declaration file (YAML or JSON)
+        #[arg(short, long)]
+        file: PathBuf,
+        /// Print the plan without changing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Apply without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Diagnose configuration, connectivity and server problems
+    Doctor,
     /// Interactive TUI mode
     Interactive,
     /// Generate shell completion scripts
@@ -83,6 +97,9 @@ pub enum TasksAction {
         /// Filter by priority
         #[arg(long)]
         priority: Option<String>,
+        /// Show the tasks of a saved view instead of filtering here
+        #[arg(long, conflicts_with_all = ["status", "project", "priority"])]
+        view: Option<String>,
     },
     /// Create a new task
     Create {
@@ -90,8 +107,14 @@ pub enum TasksAction {
         #[arg(short, long)]
         name: String,
         /// Command to execute
-        #[arg(short, long)]
-        command: String,
+        #[arg(short, long, required_unless_present = "script")]
+        command: Option<String>,
+        /// Run this script file with --interpreter instead of a command
+        #[arg(long, requires = "interpreter", conflicts_with = "command")]
+        script: Option<std::path::PathBuf>,
+        /// Interpreter running the task as a script (--command then holds the script)
+        #[arg(long, value_enum)]
+        interpreter: Option<ScriptInterpreter>,
         /// Project ID
         #[arg(short, long)]
         project: String,
@@ -104,11 +127,18 @@ pub enum TasksAction {
         /// Working directory
         #[arg(long)]
         working_directory: Option<String>,
+        /// Keep the task scheduled until this time (RFC 3339, e.g. 2025-01-31T02:00:00Z)
+        #[arg(long)]
+        run_at: Option<String>,
     },
     /// Get task details
+    #[command(visible_alias = "show")]
     Get {
         /// Task ID
         task_id: String,
+        /// Show the task as it was at this time (RFC 3339, e.g. 2025-01-31T12:00:00Z)
+        #[arg(long)]
+        as_of: Option<String>,
     },
     /// Update task
     Update {
@@ -198,6 +228,17 @@ pub enum ProjectsAction {
         /// Project ID
         project_id: String,
     },
+    /// Write the project's task tracking manifest to a local file
+    Manifest {
+        /// Project ID
+        project_id: String,
+        /// File to write
+        #[arg(short, long, default_value = ".tasks")]
+        output: std::path::PathBuf,
+        /// Print the manifest instead of writing it
+        #[arg(long)]
+        stdout: bool,
+    },
 }
 
 #[derive(Args)]
@@ -286,6 +327,13 @@ pub enum ConfigAction {
     Reset,
 }
 
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ScriptInterpreter {
+    Bash,
+    Python,
+    Pwsh,
+}
+
 #[derive(Clone, Debug, ValueEnum)]
 pub enum TaskPriority {
     Low,
@@ -0,0 +1,705 @@
+//! Declarative apply command implementation
+//!
+//! Reads a file declaring projects, their tasks and dependencies, and
+//! workflows, diffs it against the server and converges the server to it.
+//! Only projects named in the file (or previously applied from one) are
+//! touched: missing objects are created, changed fields are updated and
+//! tasks or projects dropped from the file are archived by cancelling them.
+
+use crate::client::ApiClient;
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::Method;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Tag marking projects created or adopted by `apply`
+pub const MANAGED_TAG: &str = "managed-by:apply";
+
+/// Statuses in which a task is no longer open
+const CLOSED_TASK_STATUSES: &[&str] = &["Completed", "Finalized", "Cancelled", "Failed"];
+
+/// Root of a declaration file
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QueueSpec {
+    #[serde(default)]
+    pub projects: Vec<ProjectSpec>,
+    #[serde(default)]
+    pub workflows: Vec<WorkflowSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectSpec {
+    pub name: String,
+    pub description: Option<String>,
+    pub status: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub tasks: Vec<TaskSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskSpec {
+    pub name: String,
+    pub command: String,
+    pub description: Option<String>,
+    pub priority: Option<String>,
+    pub technical_specs: Option<String>,
+    pub acceptance_criteria: Option<Vec<String>>,
+    /// Names of tasks of the same project this task depends on
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkflowSpec {
+    pub name: String,
+    pub description: Option<String>,
+    /// Project the workflow's tasks belong to
+    pub project: String,
+    /// Task names, in the project
+    pub tasks: Vec<String>,
+}
+
+/// What currently exists on the server
+#[derive(Debug, Default)]
+pub struct ServerState {
+    pub projects: Vec<Value>,
+    /// Tasks by project ID
+    pub tasks: HashMap<String, Vec<Value>>,
+    pub workflows: Vec<Value>,
+}
+
+/// One step of the plan
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    CreateProject { project: String },
+    UpdateProject { id: String, project: String, fields: Vec<&'static str> },
+    ArchiveProject { id: String, project: String },
+    CreateTask { project: String, task: String },
+    UpdateTask { id: String, project: String, task: String, fields: Vec<&'static str> },
+    ArchiveTask { id: String, project: String, task: String },
+    AddDependency { project: String, task: String, depends_on: String },
+    CreateWorkflow { workflow: String },
+}
+
+impl Change {
+    fn symbol(&self) -> char {
+        match self {
+            Change::CreateProject { .. }
+            | Change::CreateTask { .. }
+            | Change::AddDependency { .. }
+            | Change::CreateWorkflow { .. } => '+',
+            Change::UpdateProject { .. } | Change::UpdateTask { .. } => '~',
+            Change::ArchiveProject { .. } | Change::ArchiveTask { .. } => '-',
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Change::CreateProject { project } | Change::ArchiveProject { project, .. } => {
+                format!("project \"{}\"", project)
+            }
+            Change::UpdateProject { project, fields, .. } => {
+                format!("project \"{}\" ({})", project, fields.join(", "))
+            }
+            Change::CreateTask { project, task } | Change::ArchiveTask { project, task, .. } => {
+                format!("task \"{}/{}\"", project, task)
+            }
+            Change::UpdateTask { project, task, fields, .. } => {
+                format!("task \"{}/{}\" ({})", project, task, fields.join(", "))
+            }
+            Change::AddDependency { project, task, depends_on } => {
+                format!("dependency \"{}/{}\" -> \"{}\"", project, task, depends_on)
+            }
+            Change::CreateWorkflow { workflow } => format!("workflow \"{}\"", workflow),
+        }
+    }
+}
+
+pub async fn handle_apply_command(
+    file: PathBuf,
+    dry_run: bool,
+    yes: bool,
+    api_client: ApiClient,
+) -> Result<()> {
+    let spec = load_spec(&file)?;
+    validate_spec(&spec)?;
+
+    let mut state = fetch_state(&api_client, &spec).await?;
+    let changes = plan(&spec, &state);
+
+    if changes.is_empty() {
+        println!("No changes. The server matches {}.", file.display());
+        return Ok(());
+    }
+
+    print_plan(&changes);
+    if dry_run {
+        return Ok(());
+    }
+
+    if !yes {
+        print!("Apply these changes? (y/N): ");
+        use std::io::{self, Write};
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+
+        if !input.trim().to_lowercase().starts_with('y') {
+            println!("Operation cancelled.");
+            return Ok(());
+        }
+    }
+
+    execute(&api_client, &spec, &mut state, &changes, &file).await?;
+    println!("✅ Applied {} change(s).", changes.len());
+
+    Ok(())
+}
+
+/// Parse a YAML (or JSON, which is valid YAML) declaration file
+pub fn load_spec(path: &Path) -> Result<QueueSpec> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_yaml::from_str(&content).with_context(|| format!("Invalid declaration file {}", path.display()))
+}
+
+/// Reject duplicate names and references to undeclared tasks
+pub fn validate_spec(spec: &QueueSpec) -> Result<()> {
+    let mut projects = HashSet::new();
+    for project in &spec.projects {
+        if !projects.insert(project.name.as_str()) {
+            bail!("Project \"{}\" is declared twice", project.name);
+        }
+
+        let names: HashSet<&str> = project.tasks.iter().map(|t| t.name.as_str()).collect();
+        if names.len() != project.tasks.len() {
+            bail!("Project \"{}\" declares the same task name twice", project.name);
+        }
+
+        for task in &project.tasks {
+            for dependency in &task.depends_on {
+                if !names.contains(dependency.as_str()) {
+                    bail!(
+                        "Task \"{}/{}\" depends on undeclared task \"{}\"",
+                        project.name, task.name, dependency
+                    );
+                }
+            }
+        }
+    }
+
+    let mut workflows = HashSet::new();
+    for workflow in &spec.workflows {
+        if !workflows.insert(workflow.name.as_str()) {
+            bail!("Workflow \"{}\" is declared twice", workflow.name);
+        }
+        if workflow.tasks.is_empty() {
+            bail!("Workflow \"{}\" has no tasks", workflow.name);
+        }
+
+        let project = spec.projects.iter().find(|p| p.name == workflow.project)
+            .ok_or_else(|| anyhow!("Workflow \"{}\" references undeclared project \"{}\"", workflow.name, workflow.project))?;
+        for task in &workflow.tasks {
+            if !project.tasks.iter().any(|t| &t.name == task) {
+                bail!(
+                    "Workflow \"{}\" references undeclared task \"{}/{}\"",
+                    workflow.name, workflow.project, task
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn str_field<'a>(value: &'a Value, key: &str) -> &'a str {
+    value.get(key).and_then(|v| v.as_str()).unwrap_or_default()
+}
+
+fn is_managed(project: &Value) -> bool {
+    project.get("tags")
+        .and_then(|tags| tags.as_array())
+        .is_some_and(|tags| tags.iter().any(|tag| tag.as_str() == Some(MANAGED_TAG)))
+}
+
+fn is_archived(project: &Value) -> bool {
+    str_field(project, "status") == "Cancelled"
+}
+
+/// Live project with the given name, preferring one that isn't archived
+fn find_project<'a>(state: &'a ServerState, name: &str) -> Option<&'a Value> {
+    let mut matches = state.projects.iter().filter(|p| str_field(p, "name") == name);
+    let first = matches.next()?;
+    if !is_archived(first) {
+        return Some(first);
+    }
+    matches.find(|p| !is_archived(p)).or(Some(first))
+}
+
+/// Open task with the given name in a project
+fn find_task<'a>(state: &'a ServerState, project_id: &str, name: &str) -> Option<&'a Value> {
+    state.tasks.get(project_id)?
+        .iter()
+        .find(|t| str_field(t, "name") == name && str_field(t, "status") != "Cancelled")
+}
+
+async fn fetch_state(api_client: &ApiClient, spec: &QueueSpec) -> Result<ServerState> {
+    let mut state = ServerState {
+        projects: as_array(api_client.request_json(Method::GET, "/projects", None).await?),
+        workflows: as_array(api_client.request_json(Method::GET, "/workflows", None).await?),
+        ..Default::default()
+    };
+
+    let mut wanted: Vec<String> = spec.projects.iter()
+        .filter_map(|p| find_project(&state, &p.name))
+        .chain(state.projects.iter().filter(|p| is_managed(p)))
+        .map(|p| str_field(p, "id").to_string())
+        .collect();
+    wanted.sort();
+    wanted.dedup();
+
+    for project_id in wanted {
+        let tasks = api_client
+            .request_json(Method::GET, &format!("/projects/{}/tasks", project_id), None)
+            .await?;
+        state.tasks.insert(project_id, as_array(tasks));
+    }
+
+    Ok(state)
+}
+
+fn as_array(value: Value) -> Vec<Value> {
+    match value {
+        Value::Array(items) => items,
+        _ => Vec::new(),
+    }
+}
+
+fn task_fields(task: &TaskSpec, current: &Value) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+    if str_field(current, "command") != task.command {
+        fields.push("command");
+    }
+    if let Some(description) = &task.description {
+        if str_field(current, "description") != description {
+            fields.push("description");
+        }
+    }
+    if let Some(priority) = &task.priority {
+        if str_field(current, "priority") != priority {
+            fields.push("priority");
+        }
+    }
+    if let Some(specs) = &task.technical_specs {
+        if str_field(current, "technical_specs") != specs {
+            fields.push("technical_specs");
+        }
+    }
+    if let Some(criteria) = &task.acceptance_criteria {
+        if current.get("acceptance_criteria") != Some(&json!(criteria)) {
+            fields.push("acceptance_criteria");
+        }
+    }
+    fields
+}
+
+fn project_fields(project: &ProjectSpec, current: &Value) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+    if let Some(description) = &project.description {
+        if str_field(current, "description") != description {
+            fields.push("description");
+        }
+    }
+    match &project.status {
+        Some(status) if str_field(current, "status") != status => fields.push("status"),
+        None if is_archived(current) => fields.push("status"),
+        _ => {}
+    }
+    let current_tags: HashSet<&str> = current.get("tags")
+        .and_then(|tags| tags.as_array())
+        .map(|tags| tags.iter().filter_map(|t| t.as_str()).collect())
+        .unwrap_or_default();
+    let wanted_tags: HashSet<&str> = project.tags.iter().map(String::as_str)
+        .chain(std::iter::once(MANAGED_TAG))
+        .collect();
+    if current_tags != wanted_tags {
+        fields.push("tags");
+    }
+    fields
+}
+
+/// Compute the changes that converge the server to the declaration
+pub fn plan(spec: &QueueSpec, state: &ServerState) -> Vec<Change> {
+    let mut projects = Vec::new();
+    let mut tasks = Vec::new();
+    let mut dependencies = Vec::new();
+    let mut archives = Vec::new();
+    let mut workflows = Vec::new();
+    let mut declared_ids = HashSet::new();
+
+    for project in &spec.projects {
+        let current = find_project(state, &project.name);
+        let project_id = current.map(|p| str_field(p, "id").to_string());
+
+        match current {
+            None => projects.push(Change::CreateProject { project: project.name.clone() }),
+            Some(current) => {
+                declared_ids.insert(str_field(current, "id"));
+                let fields = project_fields(project, current);
+                if !fields.is_empty() {
+                    projects.push(Change::UpdateProject {
+                        id: str_field(current, "id").to_string(),
+                        project: project.name.clone(),
+                        fields,
+                    });
+                }
+            }
+        }
+
+        let mut ids: HashMap<&str, &str> = HashMap::new();
+        for task in &project.tasks {
+            let current = project_id.as_deref().and_then(|id| find_task(state, id, &task.name));
+            match current {
+                None => tasks.push(Change::CreateTask {
+                    project: project.name.clone(),
+                    task: task.name.clone(),
+                }),
+                Some(current) => {
+                    ids.insert(task.name.as_str(), str_field(current, "id"));
+                    let fields = task_fields(task, current);
+                    if !fields.is_empty() {
+                        tasks.push(Change::UpdateTask {
+                            id: str_field(current, "id").to_string(),
+                            project: project.name.clone(),
+                            task: task.name.clone(),
+                            fields,
+                        });
+                    }
+                }
+            }
+        }
+
+        for task in &project.tasks {
+            let existing: HashSet<&str> = project_id.as_deref()
+                .and_then(|id| find_task(state, id, &task.name))
+                .and_then(|t| t.get("dependencies"))
+                .and_then(|deps| deps.as_array())
+                .map(|deps| deps.iter().map(|d| str_field(d, "task_id")).collect())
+                .unwrap_or_default();
+
+            for dependency in &task.depends_on {
+                let satisfied = ids.get(dependency.as_str()).is_some_and(|id| existing.contains(id));
+                if !satisfied {
+                    dependencies.push(Change::AddDependency {
+                        project: project.name.clone(),
+                        task: task.name.clone(),
+                        depends_on: dependency.clone(),
+                    });
+                }
+            }
+        }
+
+        if let Some(project_id) = &project_id {
+            let declared: HashSet<&str> = project.tasks.iter().map(|t| t.name.as_str()).collect();
+            for task in state.tasks.get(project_id).into_iter().flatten() {
+                let name = str_field(task, "name");
+                if !declared.contains(name) && !CLOSED_TASK_STATUSES.contains(&str_field(task, "status")) {
+                    archives.push(Change::ArchiveTask {
+                        id: str_field(task, "id").to_string(),
+                        project: project.name.clone(),
+                        task: name.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    for project in state.projects.iter().filter(|p| is_managed(p) && !is_archived(p)) {
+        let id = str_field(project, "id");
+        if declared_ids.contains(id) {
+            continue;
+        }
+        let name = str_field(project, "name").to_string();
+        for task in state.tasks.get(id).into_iter().flatten() {
+            if !CLOSED_TASK_STATUSES.contains(&str_field(task, "status")) {
+                archives.push(Change::ArchiveTask {
+                    id: str_field(task, "id").to_string(),
+                    project: name.clone(),
+                    task: str_field(task, "name").to_string(),
+                });
+            }
+        }
+        archives.push(Change::ArchiveProject { id: id.to_string(), project: name });
+    }
+
+    // Workflows cannot be updated through the API, so existing ones are left as they are
+    for workflow in &spec.workflows {
+        if !state.workflows.iter().any(|w| str_field(w, "name") == workflow.name) {
+            workflows.push(Change::CreateWorkflow { workflow: workflow.name.clone() });
+        }
+    }
+
+    projects.into_iter()
+        .chain(tasks)
+        .chain(dependencies)
+        .chain(archives)
+        .chain(workflows)
+        .collect()
+}
+
+fn print_plan(changes: &[Change]) {
+    let count = |symbol: char| changes.iter().filter(|c| c.symbol() == symbol).count();
+
+    for change in changes {
+        println!("  {} {}", change.symbol(), change.describe());
+    }
+    println!();
+    println!(
+        "Plan: {} to add, {} to change, {} to archive.",
+        count('+'), count('~'), count('-')
+    );
+}
+
+fn project_spec<'a>(spec: &'a QueueSpec, name: &str) -> Result<&'a ProjectSpec> {
+    spec.projects.iter().find(|p| p.name == name)
+        .ok_or_else(|| anyhow!("Project \"{}\" is not declared", name))
+}
+
+fn task_spec<'a>(spec: &'a QueueSpec, project: &str, name: &str) -> Result<&'a TaskSpec> {
+    project_spec(spec, project)?.tasks.iter().find(|t| t.name == name)
+        .ok_or_else(|| anyhow!("Task \"{}/{}\" is not declared", project, name))
+}
+
+fn project_body(project: &ProjectSpec) -> Value {
+    let mut tags = project.tags.clone();
+    tags.push(MANAGED_TAG.to_string());
+    let mut body = json!({ "tags": tags });
+    if let Some(description) = &project.description {
+        body["description"] = json!(description);
+    }
+    body["status"] = json!(project.status.as_deref().unwrap_or("Planning"));
+    body
+}
+
+fn task_body(task: &TaskSpec) -> Value {
+    let mut body = json!({ "command": task.command });
+    if let Some(description) = &task.description {
+        body["description"] = json!(description);
+    }
+    if let Some(priority) = &task.priority {
+        body["priority"] = json!(priority);
+    }
+    if let Some(specs) = &task.technical_specs {
+        body["technical_specs"] = json!(specs);
+    }
+    if let Some(criteria) = &task.acceptance_criteria {
+        body["acceptance_criteria"] = json!(criteria);
+    }
+    body
+}
+
+fn system_time_now() -> Value {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    json!({ "secs_since_epoch": now.as_secs(), "nanos_since_epoch": now.subsec_nanos() })
+}
+
+/// Run the planned changes in order, resolving the IDs of objects created along the way
+async fn execute(
+    api_client: &ApiClient,
+    spec: &QueueSpec,
+    state: &mut ServerState,
+    changes: &[Change],
+    file: &Path,
+) -> Result<()> {
+    let mut project_ids: HashMap<String, String> = spec.projects.iter()
+        .filter_map(|p| find_project(state, &p.name).map(|c| (p.name.clone(), str_field(c, "id").to_string())))
+        .collect();
+    let mut task_ids: HashMap<(String, String), String> = HashMap::new();
+    for project in &spec.projects {
+        if let Some(project_id) = project_ids.get(&project.name) {
+            for task in &project.tasks {
+                if let Some(current) = find_task(state, project_id, &task.name) {
+                    task_ids.insert((project.name.clone(), task.name.clone()), str_field(current, "id").to_string());
+                }
+            }
+        }
+    }
+    let reason = format!("Removed from {}", file.display());
+
+    for change in changes {
+        match change {
+            Change::CreateProject { project } => {
+                let declared = project_spec(spec, project)?;
+                let created = api_client.request_json(Method::POST, "/projects", Some(json!({
+                    "name": declared.name,
+                    "description": declared.description.clone().unwrap_or_default(),
+                }))).await?;
+                let id = str_field(&created, "id").to_string();
+                api_client.request_json(Method::PUT, &format!("/projects/{}", id), Some(project_body(declared))).await?;
+                project_ids.insert(project.clone(), id);
+            }
+            Change::UpdateProject { id, project, .. } => {
+                let declared = project_spec(spec, project)?;
+                api_client.request_json(Method::PUT, &format!("/projects/{}", id), Some(project_body(declared))).await?;
+            }
+            Change::ArchiveProject { id, .. } => {
+                api_client.request_json(Method::PUT, &format!("/projects/{}", id), Some(json!({ "status": "Cancelled" }))).await?;
+            }
+            Change::CreateTask { project, task } => {
+                let declared = task_spec(spec, project, task)?;
+                let project_id = project_ids.get(project)
+                    .ok_or_else(|| anyhow!("Project \"{}\" was not created", project))?;
+                let created = api_client.request_json(Method::POST, "/tasks", Some(json!({
+                    "name": declared.name,
+                    "command": declared.command,
+                    "description": declared.description.clone().unwrap_or_else(|| declared.name.clone()),
+                    "task_type": "Simple",
+                    "priority": declared.priority.as_deref().unwrap_or("Normal"),
+                    "technical_specs": declared.technical_specs,
+                    "acceptance_criteria": declared.acceptance_criteria,
+                    "project_id": project_id,
+                }))).await?;
+                task_ids.insert((project.clone(), task.clone()), str_field(&created, "task_id").to_string());
+            }
+            Change::UpdateTask { id, project, task, .. } => {
+                let declared = task_spec(spec, project, task)?;
+                api_client.request_json(Method::PUT, &format!("/tasks/{}", id), Some(task_body(declared))).await?;
+            }
+            Change::ArchiveTask { id, .. } => {
+                api_client.cancel_task(id, &reason).await?;
+            }
+            Change::AddDependency { project, task, depends_on } => {
+                let task_id = &task_ids[&(project.clone(), task.clone())];
+                let dependency_id = &task_ids[&(project.clone(), depends_on.clone())];
+                api_client.request_json(Method::POST, &format!("/tasks/{}/dependencies", task_id), Some(json!({
+                    "dependency_task_id": dependency_id,
+                    "task_name": depends_on,
+                    "condition": "Success",
+                    "required": true,
+                }))).await?;
+            }
+            Change::CreateWorkflow { workflow } => {
+                let declared = spec.workflows.iter().find(|w| &w.name == workflow)
+                    .ok_or_else(|| anyhow!("Workflow \"{}\" is not declared", workflow))?;
+                let project = project_spec(spec, &declared.project)?;
+
+                let mut tasks = Vec::new();
+                let mut dependencies = Vec::new();
+                for name in &declared.tasks {
+                    let id = &task_ids[&(declared.project.clone(), name.clone())];
+                    tasks.push(api_client.request_json(Method::GET, &format!("/tasks/{}", id), None).await?);
+                    for dependency in &task_spec(spec, &project.name, name)?.depends_on {
+                        if declared.tasks.contains(dependency) {
+                            dependencies.push(json!({
+                                "from_task": task_ids[&(declared.project.clone(), dependency.clone())],
+                                "to_task": id,
+                                "condition": "Success",
+                            }));
+                        }
+                    }
+                }
+
+                let now = system_time_now();
+                api_client.request_json(Method::POST, "/workflows", Some(json!({
+                    "id": uuid::Uuid::new_v4(),
+                    "name": declared.name,
+                    "description": declared.description,
+                    "tasks": tasks,
+                    "dependencies": dependencies,
+                    "created_at": now,
+                    "updated_at": now,
+                    "status": "Pending",
+                }))).await?;
+            }
+        }
+        println!("  {} {}", change.symbol(), change.describe());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> QueueSpec {
+        serde_yaml::from_str(r#"
+projects:
+  - name: backend
+    description: API service
+    tasks:
+      - name: build
+        command: cargo build
+      - name: deploy
+        command: ./deploy.sh
+        priority: High
+        depends_on: [build]
+workflows:
+  - name: release
+    project: backend
+    tasks: [build, deploy]
+"#).unwrap()
+    }
+
+    #[test]
+    fn test_plan_creates_everything_on_empty_server() {
+        let spec = spec();
+        validate_spec(&spec).unwrap();
+
+        let changes = plan(&spec, &ServerState::default());
+        assert_eq!(changes, vec![
+            Change::CreateProject { project: "backend".into() },
+            Change::CreateTask { project: "backend".into(), task: "build".into() },
+            Change::CreateTask { project: "backend".into(), task: "deploy".into() },
+            Change::AddDependency { project: "backend".into(), task: "deploy".into(), depends_on: "build".into() },
+            Change::CreateWorkflow { workflow: "release".into() },
+        ]);
+    }
+
+    #[test]
+    fn test_plan_updates_and_archives_drift() {
+        let spec = spec();
+        let state = ServerState {
+            projects: vec![json!({
+                "id": "p1", "name": "backend", "description": "API service",
+                "status": "Planning", "tags": [MANAGED_TAG],
+            })],
+            tasks: HashMap::from([("p1".to_string(), vec![
+                json!({ "id": "t1", "name": "build", "command": "cargo build", "status": "Planning", "dependencies": [] }),
+                json!({ "id": "t2", "name": "deploy", "command": "./deploy.sh", "priority": "Normal",
+                        "status": "Planning", "dependencies": [{ "task_id": "t1" }] }),
+                json!({ "id": "t3", "name": "legacy", "command": "make", "status": "Planning" }),
+            ])]),
+            workflows: vec![json!({ "name": "release" })],
+        };
+
+        let changes = plan(&spec, &state);
+        assert_eq!(changes, vec![
+            Change::UpdateTask { id: "t2".into(), project: "backend".into(), task: "deploy".into(), fields: vec!["priority"] },
+            Change::ArchiveTask { id: "t3".into(), project: "backend".into(), task: "legacy".into() },
+        ]);
+    }
+
+    #[test]
+    fn test_validate_rejects_unknown_dependency() {
+        let spec: QueueSpec = serde_yaml::from_str(r#"
+projects:
+  - name: backend
+    tasks:
+      - name: deploy
+        command: ./deploy.sh
+        depends_on: [build]
+"#).unwrap();
+        assert!(validate_spec(&spec).is_err());
+    }
+}
@@ -0,0 +1,375 @@
+//! Doctor command implementation
+//!
+//! Runs a series of independent checks against the local configuration and
+//! the server, and prints what is wrong together with how to fix it. Meant as
+//! the first thing to run when the CLI "doesn't work".
+
+use crate::cli::args::GlobalArgs;
+use crate::client::API_PREFIX;
+use crate::config::{CliConfig, ConfigManager};
+use crate::OutputFormat;
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Timeout for each request the doctor makes
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+/// Clock skew above which token expiry and timestamps start to misbehave
+const SKEW_WARN_SECS: i64 = 5;
+const SKEW_FAIL_SECS: i64 = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+    /// Not run because an earlier check failed
+    Skip,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self { name, status: CheckStatus::Pass, detail: detail.into(), fix: None }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self { name, status: CheckStatus::Warn, detail: detail.into(), fix: Some(fix.into()) }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self { name, status: CheckStatus::Fail, detail: detail.into(), fix: Some(fix.into()) }
+    }
+
+    fn skip(name: &'static str) -> Self {
+        Self { name, status: CheckStatus::Skip, detail: "server unreachable".to_string(), fix: None }
+    }
+}
+
+pub async fn handle_doctor_command(global: &GlobalArgs) -> Result<()> {
+    let checks = run_checks(global).await;
+
+    match global.format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&checks)?),
+        OutputFormat::Yaml => println!("{}", serde_yaml::to_string(&checks)?),
+        OutputFormat::Table => print_checks(&checks),
+    }
+
+    let failed = checks.iter().filter(|c| c.status == CheckStatus::Fail).count();
+    if failed > 0 {
+        bail!("{} check(s) failed", failed);
+    }
+    Ok(())
+}
+
+async fn run_checks(global: &GlobalArgs) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    let (config_check, config) = match ConfigManager::new(global.config.clone()) {
+        Ok(manager) => check_config(manager.config_path()),
+        Err(e) => (Check::fail("config", e.to_string(), "Pass a configuration file with --config"), None),
+    };
+    checks.push(config_check);
+
+    let server_url = global.server_url.trim_end_matches('/').to_string();
+    let api_key = global.api_key.clone()
+        .or_else(|| config.as_ref().and_then(|c| c.server.api_key.clone()));
+    let client = match reqwest::Client::builder().timeout(CHECK_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            checks.push(Check::fail("server", e.to_string(), "Check the system's TLS configuration"));
+            return checks;
+        }
+    };
+
+    let sent_at = Utc::now();
+    let started = Instant::now();
+    let health = client.get(format!("{}{}/health", server_url, API_PREFIX)).send().await;
+    let round_trip = started.elapsed();
+
+    let health: Value = match health {
+        Ok(response) if response.status().is_success() => match response.json().await {
+            Ok(health) => health,
+            Err(e) => {
+                checks.push(Check::fail(
+                    "server",
+                    format!("{} answered /health with something other than JSON: {}", server_url, e),
+                    "Make sure --server-url points at the task queue server and not another service",
+                ));
+                return skip_remaining(checks);
+            }
+        },
+        Ok(response) => {
+            checks.push(Check::fail(
+                "server",
+                format!("{}{}/health returned {}", server_url, API_PREFIX, response.status()),
+                "Check the server logs (logs/task-queue.log) for errors",
+            ));
+            return skip_remaining(checks);
+        }
+        Err(e) => {
+            checks.push(Check::fail(
+                "server",
+                format!("Cannot reach {}: {}", server_url, e),
+                "Start the server, or point the CLI at it with --server-url",
+            ));
+            return skip_remaining(checks);
+        }
+    };
+    checks.push(Check::pass("server", format!("{} reachable in {} ms", server_url, round_trip.as_millis())));
+
+    checks.push(check_version(env!("CARGO_PKG_VERSION"), health.get("version").and_then(Value::as_str)));
+
+    let server_time = health.get("timestamp")
+        .and_then(Value::as_str)
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| t.with_timezone(&Utc));
+    checks.push(match server_time {
+        // Compare against the middle of the request to cancel out network latency
+        Some(server_time) => {
+            let local_time = sent_at + chrono::Duration::from_std(round_trip / 2).unwrap_or_default();
+            check_clock_skew((server_time - local_time).num_seconds())
+        }
+        None => Check::warn("clock", "Server did not report its time", "Upgrade the server"),
+    });
+
+    checks.push(check_storage(health.get("storage")));
+    checks.push(check_auth(&client, &server_url, api_key.as_deref()).await);
+    checks.push(check_mcp(&client, &server_url, api_key.as_deref()).await);
+
+    checks
+}
+
+fn skip_remaining(mut checks: Vec<Check>) -> Vec<Check> {
+    for name in ["version", "clock", "storage", "auth", "mcp"] {
+        checks.push(Check::skip(name));
+    }
+    checks
+}
+
+/// Read the configuration without `ConfigManager::load_config`, which would
+/// write a default file as a side effect
+fn check_config(path: &Path) -> (Check, Option<CliConfig>) {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return (Check::pass("config", format!("No file at {}; using defaults", path.display())), None);
+        }
+        Err(e) => {
+            return (
+                Check::fail("config", format!("Cannot read {}: {}", path.display(), e), "Fix the file's permissions"),
+                None,
+            );
+        }
+    };
+
+    let config: CliConfig = match serde_yaml::from_str(&content) {
+        Ok(config) => config,
+        Err(e) => {
+            return (
+                Check::fail(
+                    "config",
+                    format!("{} is invalid: {}", path.display(), e),
+                    "Fix the file, or run `task-queue config reset` to start over",
+                ),
+                None,
+            );
+        }
+    };
+
+    let check = if reqwest::Url::parse(&config.server.url).is_err() {
+        Check::fail(
+            "config",
+            format!("server.url {:?} is not a valid URL", config.server.url),
+            format!("Edit server.url in {}, e.g. http://localhost:16080", path.display()),
+        )
+    } else if config.server.timeout == 0 {
+        Check::warn(
+            "config",
+            "server.timeout is 0, so every request times out immediately",
+            format!("Edit server.timeout in {}, e.g. 30", path.display()),
+        )
+    } else {
+        Check::pass("config", format!("{} is valid", path.display()))
+    };
+    (check, Some(config))
+}
+
+/// `major.minor` must match; before 1.0 the minor version carries breaking changes
+fn check_version(cli: &str, server: Option<&str>) -> Check {
+    let Some(server) = server else {
+        return Check::warn("version", "Server did not report its version", "Upgrade the server");
+    };
+
+    let release = |version: &str| version.split('.').take(2).collect::<Vec<_>>().join(".");
+    if release(cli) == release(server) {
+        Check::pass("version", format!("CLI {} and server {} are compatible", cli, server))
+    } else {
+        Check::warn(
+            "version",
+            format!("CLI {} and server {} may be incompatible", cli, server),
+            format!("Install a {}.x CLI to match the server", release(server)),
+        )
+    }
+}
+
+fn check_clock_skew(skew_secs: i64) -> Check {
+    let detail = format!("Server clock is {}s {} this machine", skew_secs.abs(), if skew_secs < 0 { "behind" } else { "ahead of" });
+    let fix = "Synchronize both clocks with NTP (e.g. `timedatectl set-ntp true`)";
+
+    match skew_secs.abs() {
+        skew if skew > SKEW_FAIL_SECS => Check::fail("clock", detail, fix),
+        skew if skew > SKEW_WARN_SECS => Check::warn("clock", detail, fix),
+        _ => Check::pass("clock", "Clocks are in sync"),
+    }
+}
+
+fn check_storage(storage: Option<&Value>) -> Check {
+    let Some(storage) = storage else {
+        return Check::warn("storage", "Server did not report storage health", "Upgrade the server");
+    };
+
+    match storage.get("status").and_then(Value::as_str) {
+        Some("ok") => Check::pass(
+            "storage",
+            format!("Writable, probe took {} ms", storage.get("latency_ms").and_then(Value::as_u64).unwrap_or(0)),
+        ),
+        _ => Check::fail(
+            "storage",
+            storage.get("error").and_then(Value::as_str).unwrap_or("Storage probe failed").to_string(),
+            "Check free disk space and permissions of the server's data directory",
+        ),
+    }
+}
+
+async fn check_auth(client: &reqwest::Client, server_url: &str, api_key: Option<&str>) -> Check {
+    let mut request = client.get(format!("{}{}/projects", server_url, API_PREFIX));
+    if let Some(api_key) = api_key {
+        request = request.header("Authorization", format!("Bearer {}", api_key));
+    }
+
+    match request.send().await {
+        Ok(response) if response.status().is_success() => Check::pass(
+            "auth",
+            if api_key.is_some() { "API key accepted" } else { "No API key required" },
+        ),
+        Ok(response) if matches!(response.status().as_u16(), 401 | 403) => Check::fail(
+            "auth",
+            format!("Server refused the request ({})", response.status()),
+            if api_key.is_some() {
+                "The API key is wrong or expired; pass a valid one with --api-key"
+            } else {
+                "The server requires an API key; pass one with --api-key or set server.api_key"
+            },
+        ),
+        Ok(response) => Check::warn(
+            "auth",
+            format!("Unexpected status {} listing projects", response.status()),
+            "Check the server logs (logs/task-queue.log) for errors",
+        ),
+        Err(e) => Check::fail("auth", e.to_string(), "Retry; the server stopped responding during the checks"),
+    }
+}
+
+/// Open the MCP SSE stream and close it as soon as the headers arrive
+async fn check_mcp(client: &reqwest::Client, server_url: &str, api_key: Option<&str>) -> Check {
+    let fix = "Restart the server; MCP clients connect to /mcp/sse";
+    let mut request = client.get(format!("{}/mcp/sse", server_url));
+    if let Some(api_key) = api_key {
+        request = request.header("Authorization", format!("Bearer {}", api_key));
+    }
+
+    match request.send().await {
+        Ok(response) if response.status().as_u16() == 401 => Check::fail(
+            "mcp",
+            "/mcp/sse needs an API key",
+            "Pass a valid one with --api-key, or set server.tenancy.mcp_anonymous on the server",
+        ),
+        Ok(response) => {
+            let is_stream = response.headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .is_some_and(|v| v.starts_with("text/event-stream"));
+            if response.status().is_success() && is_stream {
+                Check::pass("mcp", format!("{}/mcp/sse is streaming", server_url))
+            } else {
+                Check::fail("mcp", format!("/mcp/sse returned {}", response.status()), fix)
+            }
+        }
+        Err(e) => Check::fail("mcp", format!("/mcp/sse did not respond: {}", e), fix),
+    }
+}
+
+fn print_checks(checks: &[Check]) {
+    for check in checks {
+        let marker = match check.status {
+            CheckStatus::Pass => "[ OK ]",
+            CheckStatus::Warn => "[WARN]",
+            CheckStatus::Fail => "[FAIL]",
+            CheckStatus::Skip => "[SKIP]",
+        };
+        println!("{} {:<8} {}", marker, check.name, check.detail);
+        if let Some(fix) = &check.fix {
+            println!("       {:<8} fix: {}", "", fix);
+        }
+    }
+
+    let count = |status| checks.iter().filter(|c| c.status == status).count();
+    println!();
+    println!(
+        "{} passed, {} warnings, {} failed, {} skipped",
+        count(CheckStatus::Pass),
+        count(CheckStatus::Warn),
+        count(CheckStatus::Fail),
+        count(CheckStatus::Skip)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_check_reports_invalid_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+
+        let (check, config) = check_config(&path);
+        assert_eq!(check.status, CheckStatus::Pass);
+        assert!(config.is_none());
+        assert!(!path.exists());
+
+        std::fs::write(&path, "server: [not, a, map]").unwrap();
+        let (check, _) = check_config(&path);
+        assert_eq!(check.status, CheckStatus::Fail);
+        assert!(check.fix.unwrap().contains("config reset"));
+
+        let mut config = CliConfig::default();
+        config.server.url = "localhost 16080".to_string();
+        std::fs::write(&path, serde_yaml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(check_config(&path).0.status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn test_version_and_clock_thresholds() {
+        assert_eq!(check_version("0.1.0", Some("0.1.7")).status, CheckStatus::Pass);
+        assert_eq!(check_version("0.1.0", Some("0.2.0")).status, CheckStatus::Warn);
+        assert_eq!(check_version("0.1.0", None).status, CheckStatus::Warn);
+
+        assert_eq!(check_clock_skew(2).status, CheckStatus::Pass);
+        assert_eq!(check_clock_skew(-30).status, CheckStatus::Warn);
+        assert_eq!(check_clock_skew(600).status, CheckStatus::Fail);
+    }
+}
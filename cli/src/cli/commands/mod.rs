@@ -5,3 +5,5 @@ pub mod projects;
 pub mod workflows;
 pub mod server;
 pub mod config;
+pub mod apply;
+pub mod doctor;
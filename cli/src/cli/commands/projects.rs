@@ -1,10 +1,11 @@
 //! Project management commands implementation
 
 use crate::cli::args::ProjectsAction;
-use crate::client::ApiClient;
+use crate::client::{ApiClient, ProjectManifest};
 use crate::output::OutputFormatter;
 use crate::OutputFormat;
 use anyhow::Result;
+use std::path::PathBuf;
 
 pub async fn handle_projects_command(
     command: crate::cli::args::ProjectsCommand,
@@ -30,6 +31,9 @@ pub async fn handle_projects_command(
         ProjectsAction::Tasks { project_id } => {
             list_project_tasks(api_client, format, project_id).await
         }
+        ProjectsAction::Manifest { project_id, output, stdout } => {
+            write_project_manifest(api_client, project_id, output, stdout).await
+        }
     }
 }
 
@@ -122,3 +126,50 @@ async fn list_project_tasks(api_client: ApiClient, format: OutputFormat, project
     
     Ok(())
 }
+
+async fn write_project_manifest(
+    api_client: ApiClient,
+    project_id: String,
+    output: PathBuf,
+    stdout: bool,
+) -> Result<()> {
+    let manifest = api_client.get_project_manifest(&project_id).await?;
+    let content = render_manifest(&manifest);
+
+    if stdout {
+        print!("{}", content);
+        return Ok(());
+    }
+
+    tokio::fs::write(&output, content).await?;
+    println!("✅ Wrote {} tasks to {}", manifest.tasks.len(), output.display());
+
+    Ok(())
+}
+
+fn render_manifest(manifest: &ProjectManifest) -> String {
+    let mut content = format!(
+        "# Task IDs for project: {}\n\
+        # Generated by `task-queue projects manifest` at {}\n\
+        # Re-run the command to refresh; manual edits will be overwritten.\n\
+        # Format: task_id: name [status]\n\n\
+        # Project Information:\n\
+        # ID: {}\n\
+        # Name: {}\n\
+        # Description: {}\n\
+        # Created: {}\n\n\
+        # Task IDs:\n",
+        manifest.name,
+        manifest.generated_at,
+        manifest.project_id,
+        manifest.name,
+        manifest.description.as_deref().unwrap_or("No description"),
+        manifest.created_at,
+    );
+
+    for task in &manifest.tasks {
+        content.push_str(&format!("{}: {} [{}]\n", task.id, task.name, task.status));
+    }
+
+    content
+}
@@ -1,6 +1,6 @@
 //! Task management commands implementation
 
-use crate::cli::args::{TasksAction, TaskPriority};
+use crate::cli::args::{ScriptInterpreter, TasksAction, TaskPriority};
 use crate::client::ApiClient;
 use crate::output::OutputFormatter;
 use crate::OutputFormat;
@@ -14,21 +14,29 @@ pub async fn handle_tasks_command(
     format: OutputFormat,
 ) -> Result<()> {
     match command.action {
-        TasksAction::List { status, project, priority } => {
-            list_tasks(api_client, format, status, project, priority).await
+        TasksAction::List { status, project, priority, view } => {
+            list_tasks(api_client, format, status, project, priority, view).await
         }
         TasksAction::Create {
             name,
             command: cmd,
+            script,
+            interpreter,
             project,
             description,
             priority,
             working_directory,
+            run_at,
         } => {
-            create_task(api_client, name, cmd, project, description, priority, working_directory).await
+            let cmd = match script {
+                Some(path) => std::fs::read_to_string(&path)
+                    .map_err(|e| anyhow::anyhow!("Failed to read script {}: {}", path.display(), e))?,
+                None => cmd.unwrap_or_default(),
+            };
+            create_task(api_client, name, cmd, interpreter, project, description, priority, working_directory, run_at).await
         }
-        TasksAction::Get { task_id } => {
-            get_task(api_client, format, task_id).await
+        TasksAction::Get { task_id, as_of } => {
+            get_task(api_client, format, task_id, as_of).await
         }
         TasksAction::Update {
             task_id,
@@ -56,8 +64,12 @@ async fn list_tasks(
     status: Option<String>,
     project: Option<String>,
     priority: Option<String>,
+    view: Option<String>,
 ) -> Result<()> {
-    let tasks = api_client.list_tasks(status, project, priority).await?;
+    let tasks = match view {
+        Some(view) => api_client.list_view_tasks(&view).await?,
+        None => api_client.list_tasks(status, project, priority).await?,
+    };
     
     let formatter = OutputFormatter::new(format, true);
     let output = formatter.format_tasks(&tasks);
@@ -70,12 +82,26 @@ async fn create_task(
     api_client: ApiClient,
     name: String,
     command: String,
+    interpreter: Option<ScriptInterpreter>,
     project: String,
     description: Option<String>,
     priority: Option<TaskPriority>,
     working_directory: Option<String>,
+    run_at: Option<String>,
 ) -> Result<()> {
     let project_id = Uuid::parse_str(&project)?;
+    let run_at = run_at
+        .map(|run_at| {
+            chrono::DateTime::parse_from_rfc3339(&run_at)
+                .map_err(|e| anyhow::anyhow!("Invalid --run-at timestamp {:?}: {}", run_at, e))
+        })
+        .transpose()?;
+    let task_type = match interpreter {
+        Some(interpreter) => serde_json::json!({
+            "Script": {"interpreter": format!("{:?}", interpreter).to_lowercase()}
+        }),
+        None => serde_json::json!("Simple"),
+    };
     
     let task_data = serde_json::json!({
         "name": name,
@@ -84,7 +110,8 @@ async fn create_task(
         "description": description.unwrap_or_default(),
         "priority": priority.map(|p| format!("{:?}", p)).unwrap_or_else(|| "Normal".to_string()),
         "working_directory": working_directory,
-        "task_type": "Simple"
+        "task_type": task_type,
+        "run_at": run_at
     });
     
     let task = api_client.create_task(task_data).await?;
@@ -97,8 +124,15 @@ async fn create_task(
     Ok(())
 }
 
-async fn get_task(api_client: ApiClient, format: OutputFormat, task_id: String) -> Result<()> {
-    let task = api_client.get_task(&task_id).await?;
+async fn get_task(api_client: ApiClient, format: OutputFormat, task_id: String, as_of: Option<String>) -> Result<()> {
+    let task = match as_of {
+        Some(as_of) => {
+            let at = chrono::DateTime::parse_from_rfc3339(&as_of)
+                .map_err(|e| anyhow::anyhow!("Invalid --as-of timestamp {:?}: {}", as_of, e))?;
+            api_client.get_task_as_of(&task_id, at.with_timezone(&chrono::Utc)).await?
+        }
+        None => api_client.get_task(&task_id).await?,
+    };
     
     let formatter = OutputFormatter::new(format, true);
     let output = formatter.format_task_details(&task);
@@ -6,6 +6,9 @@ use std::time::Duration;
 use anyhow::Result;
 use uuid::Uuid;
 
+/// Root of the versioned REST API on the server
+pub const API_PREFIX: &str = "/api/v1";
+
 #[derive(Debug, Clone)]
 pub struct ApiClient {
     client: Client,
@@ -36,6 +39,7 @@ pub enum TaskStatus {
     Completed,
     Failed,
     Cancelled,
+    Scheduled,
     Pending,
     Running,
 }
@@ -49,6 +53,23 @@ pub struct Project {
     pub updated_at: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjectManifest {
+    pub project_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: String,
+    pub generated_at: String,
+    pub tasks: Vec<ManifestTask>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ManifestTask {
+    pub id: Uuid,
+    pub name: String,
+    pub status: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Workflow {
     pub id: Uuid,
@@ -88,7 +109,7 @@ impl ApiClient {
         T: serde::de::DeserializeOwned,
     {
         let mut request = self.client
-            .request(method, &format!("{}{}", self.base_url, path));
+            .request(method, &format!("{}{}{}", self.base_url, API_PREFIX, path));
         
         if let Some(api_key) = &self.api_key {
             request = request.header("Authorization", format!("Bearer {}", api_key));
@@ -109,6 +130,11 @@ impl ApiClient {
         Ok(result)
     }
     
+    /// Raw JSON request, for commands that need fields the typed models don't carry
+    pub async fn request_json(&self, method: reqwest::Method, path: &str, body: Option<serde_json::Value>) -> Result<serde_json::Value> {
+        self.make_request(method, path, body).await
+    }
+    
     // Task operations
     pub async fn list_tasks(&self, status: Option<String>, project: Option<String>, priority: Option<String>) -> Result<Vec<Task>> {
         let mut params = Vec::new();
@@ -123,13 +149,33 @@ impl ApiClient {
             params.push(format!("priority={}", priority));
         }
         
-        let path = if params.is_empty() {
-            "/tasks".to_string()
-        } else {
-            format!("/tasks?{}", params.join("&"))
-        };
-        
-        self.make_request(reqwest::Method::GET, &path, None).await
+        // The server pages task listings; follow the cursors to the end
+        let mut tasks = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let mut query = params.clone();
+            if let Some(cursor) = cursor.take() {
+                query.push(format!("cursor={}", cursor));
+            }
+            let path = if query.is_empty() {
+                "/tasks".to_string()
+            } else {
+                format!("/tasks?{}", query.join("&"))
+            };
+            
+            let mut page: serde_json::Value = self.make_request(reqwest::Method::GET, &path, None).await?;
+            let page_tasks: Vec<Task> = serde_json::from_value(page["tasks"].take())?;
+            tasks.extend(page_tasks);
+            match page["next_cursor"].as_str() {
+                Some(next) => cursor = Some(next.to_string()),
+                None => return Ok(tasks),
+            }
+        }
+    }
+    
+    /// Tasks shown by a saved view
+    pub async fn list_view_tasks(&self, view: &str) -> Result<Vec<Task>> {
+        self.make_request(reqwest::Method::GET, &format!("/views/{}/tasks", view), None).await
     }
     
     pub async fn create_task(&self, task_data: serde_json::Value) -> Result<Task> {
@@ -140,6 +186,12 @@ impl ApiClient {
         self.make_request(reqwest::Method::GET, &format!("/tasks/{}", task_id), None).await
     }
     
+    /// The task as it was at the given moment
+    pub async fn get_task_as_of(&self, task_id: &str, at: chrono::DateTime<chrono::Utc>) -> Result<Task> {
+        let at = at.to_rfc3339_opts(chrono::SecondsFormat::Nanos, true);
+        self.make_request(reqwest::Method::GET, &format!("/tasks/{}?as_of={}", task_id, at), None).await
+    }
+    
     pub async fn update_task(&self, task_id: &str, update_data: serde_json::Value) -> Result<Task> {
         self.make_request(reqwest::Method::PUT, &format!("/tasks/{}", task_id), Some(update_data)).await
     }
@@ -177,6 +229,10 @@ impl ApiClient {
         Ok(())
     }
     
+    pub async fn get_project_manifest(&self, project_id: &str) -> Result<ProjectManifest> {
+        self.make_request(reqwest::Method::GET, &format!("/projects/{}/manifest", project_id), None).await
+    }
+    
     // Workflow operations
     pub async fn list_workflows(&self) -> Result<Vec<Workflow>> {
         self.make_request(reqwest::Method::GET, "/workflows", None).await
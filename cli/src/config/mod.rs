@@ -86,6 +86,10 @@ impl ConfigManager {
         Ok(Self { config_path })
     }
     
+    pub fn config_path(&self) -> &std::path::Path {
+        &self.config_path
+    }
+
     fn get_default_config_path() -> Result<PathBuf> {
         let config_dir = dirs::config_dir()
             .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
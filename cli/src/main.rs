@@ -41,6 +41,11 @@ async fn main() -> Result<()> {
         return Ok(());
     }
 
+    // Diagnose before loading the configuration, which may be what is broken
+    if matches!(args.command, Commands::Doctor) {
+        return cli::commands::doctor::handle_doctor_command(&args.global).await;
+    }
+
     // Load configuration
     let config_manager = ConfigManager::new(args.global.config.clone())?;
     let config = config_manager.load_config()?;
@@ -76,6 +81,8 @@ async fn execute_command(
         Commands::Workflows(cmd) => cli::commands::workflows::handle_workflows_command(cmd, api_client, format).await,
         Commands::Server(cmd) => cli::commands::server::handle_server_command(cmd, api_client, format).await,
         Commands::Config(cmd) => cli::commands::config::handle_config_command(cmd, config).await,
+        Commands::Apply { file, dry_run, yes } => cli::commands::apply::handle_apply_command(file, dry_run, yes, api_client).await,
+        Commands::Doctor => unreachable!(), // Handled in main()
         Commands::Interactive => unreachable!(), // Handled in main()
         Commands::Completions { .. } => unreachable!(), // Handled in main()
     }
@@ -237,6 +244,63 @@ mod integration_tests {
         }
     }
 
+    #[test]
+    fn test_tasks_show_as_of() {
+        let args = Cli::try_parse_from(&[
+            "task-queue", "tasks", "show",
+            "123e4567-e89b-12d3-a456-426614174000",
+            "--as-of", "2025-01-31T12:00:00Z"
+        ]).unwrap();
+
+        if let Commands::Tasks(cmd) = args.command {
+            if let TasksAction::Get { as_of, .. } = cmd.action {
+                assert_eq!(as_of, Some("2025-01-31T12:00:00Z".to_string()));
+            } else {
+                panic!("Expected Get action");
+            }
+        } else {
+            panic!("Expected Tasks command");
+        }
+    }
+
+    #[test]
+    fn test_tasks_create_run_at() {
+        let args = Cli::try_parse_from(&[
+            "task-queue", "tasks", "create",
+            "--name", "nightly vacuum",
+            "--command", "make vacuum",
+            "--project", "123e4567-e89b-12d3-a456-426614174000",
+            "--run-at", "2025-01-31T02:00:00Z"
+        ]).unwrap();
+
+        if let Commands::Tasks(cmd) = args.command {
+            if let TasksAction::Create { run_at, .. } = cmd.action {
+                assert_eq!(run_at, Some("2025-01-31T02:00:00Z".to_string()));
+            } else {
+                panic!("Expected Create action");
+            }
+        } else {
+            panic!("Expected Tasks command");
+        }
+    }
+
+    #[test]
+    fn test_tasks_list_view() {
+        let args = Cli::try_parse_from(&["task-queue", "tasks", "list", "--view", "overdue criticals"]).unwrap();
+
+        if let Commands::Tasks(cmd) = args.command {
+            if let TasksAction::List { view, .. } = cmd.action {
+                assert_eq!(view, Some("overdue criticals".to_string()));
+            } else {
+                panic!("Expected List action");
+            }
+        } else {
+            panic!("Expected Tasks command");
+        }
+
+        assert!(Cli::try_parse_from(&["task-queue", "tasks", "list", "--view", "mine", "--status", "pending"]).is_err());
+    }
+
     #[test]
     fn test_projects_command_integration() {
         // Test projects list command
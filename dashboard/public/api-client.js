@@ -1,10 +1,13 @@
+// Root of the versioned REST API on the server
+const API_PREFIX = '/api/v1';
+
 class TaskQueueAPIClient {
     constructor(baseURL = 'http://localhost:16080') {
         this.baseURL = baseURL;
     }
 
     async request(endpoint, options = {}) {
-        const url = `${this.baseURL}${endpoint}`;
+        const url = `${this.baseURL}${API_PREFIX}${endpoint}`;
         const config = {
             headers: {
                 'Content-Type': 'application/json',
@@ -1,4 +1,8 @@
 // API Client for Task Queue Dashboard
+
+// Root of the versioned REST API on the server
+const API_PREFIX = '/api/v1'
+
 class ApiClient {
   constructor(baseURL = 'http://localhost:16080') {
     this.baseURL = baseURL
@@ -8,7 +12,9 @@ class ApiClient {
   }
 
   async request(endpoint, options = {}) {
-    const url = `${this.baseURL}${endpoint}`
+    // Auth endpoints live outside the versioned API
+    const prefix = endpoint.startsWith('/api/') ? '' : API_PREFIX
+    const url = `${this.baseURL}${prefix}${endpoint}`
     const config = {
       headers: { ...this.defaultHeaders, ...options.headers },
       ...options
@@ -55,9 +61,16 @@ class ApiClient {
 
   // Tasks
   async listTasks(params = {}) {
-    const query = new URLSearchParams(params).toString()
-    const endpoint = query ? `/tasks?${query}` : '/tasks'
-    return this.request(endpoint)
+    // The server pages task listings; follow the cursors to the end
+    const tasks = []
+    let cursor = null
+    do {
+      const query = new URLSearchParams(cursor ? { ...params, cursor } : params).toString()
+      const page = await this.request(query ? `/tasks?${query}` : '/tasks')
+      tasks.push(...page.tasks)
+      cursor = page.next_cursor
+    } while (cursor)
+    return tasks
   }
 
   async getTask(id) {
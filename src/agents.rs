@@ -0,0 +1,259 @@
+//! Agent Registry Module
+//!
+//! Keeps track of the AI agents and workers using the queue: who they are,
+//! when they were last seen and what they have been doing. Requests name the
+//! acting agent with the `X-Agent-Id` header (or an `agent_id` MCP tool
+//! argument); while such a request runs, every event it causes is attributed
+//! to that agent.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::{Agent, AgentActivity};
+use crate::error::{TaskQueueError, Result};
+use crate::storage::Storage;
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Request header naming the acting agent
+pub const AGENT_HEADER: &str = "x-agent-id";
+/// Agents silent for longer than this are reported as idle
+pub const IDLE_AFTER_SECS: i64 = 120;
+/// Agents silent for longer than this are reported as offline
+pub const OFFLINE_AFTER_SECS: i64 = 900;
+
+tokio::task_local! {
+    static CURRENT_AGENT: Uuid;
+}
+
+/// Agent the current request acts for, if any
+pub fn current_agent() -> Option<Uuid> {
+    CURRENT_AGENT.try_with(|agent| *agent).ok()
+}
+
+/// Run `future` on behalf of `agent`
+pub async fn with_agent<F: Future>(agent: Option<Uuid>, future: F) -> F::Output {
+    match agent {
+        Some(agent) => CURRENT_AGENT.scope(agent, future).await,
+        None => future.await,
+    }
+}
+
+/// Registration request
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RegisterAgent {
+    /// Unique agent name
+    pub name: String,
+    /// Model the agent runs on
+    pub model: Option<String>,
+    /// What the agent can work on
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentState {
+    Active,
+    Idle,
+    Offline,
+}
+
+/// Agent as reported by `GET /agents`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentStatus {
+    #[serde(flatten)]
+    pub agent: Agent,
+    pub state: AgentState,
+    pub idle_seconds: i64,
+}
+
+impl AgentStatus {
+    pub fn at(agent: Agent, now: DateTime<Utc>) -> Self {
+        let idle_seconds = (now - agent.last_seen).num_seconds().max(0);
+        let state = match idle_seconds {
+            s if s > OFFLINE_AFTER_SECS => AgentState::Offline,
+            s if s > IDLE_AFTER_SECS => AgentState::Idle,
+            _ => AgentState::Active,
+        };
+        Self { agent, state, idle_seconds }
+    }
+}
+
+/// Registered agents and their activity
+///
+/// Activity is counted in memory and written to storage with heartbeats and
+/// mutations, so read-only traffic costs no disk writes.
+pub struct AgentRegistry {
+    storage: Arc<dyn Storage>,
+    agents: DashMap<Uuid, Agent>,
+}
+
+impl AgentRegistry {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self {
+            storage,
+            agents: DashMap::new(),
+        }
+    }
+
+    /// Load previously registered agents
+    pub async fn load(&self) -> Result<()> {
+        for agent in self.storage.list_agents().await? {
+            self.agents.insert(agent.id, agent);
+        }
+        Ok(())
+    }
+
+    /// Register an agent, or update the one already registered under that name
+    pub async fn register(&self, request: RegisterAgent) -> Result<Agent> {
+        if request.name.trim().is_empty() {
+            return Err(TaskQueueError::ValidationError {
+                reason: "Agent name cannot be empty".to_string(),
+            });
+        }
+
+        let now = Utc::now();
+        let existing = self.list().await?.into_iter().find(|a| a.name == request.name);
+        let agent = match existing {
+            Some(mut agent) => {
+                agent.model = request.model;
+                agent.capabilities = request.capabilities;
+                agent.last_seen = now;
+                agent
+            }
+            None => Agent {
+                id: Uuid::new_v4(),
+                name: request.name,
+                model: request.model,
+                capabilities: request.capabilities,
+                registered_at: now,
+                last_seen: now,
+                activity: AgentActivity::default(),
+            },
+        };
+
+        self.storage.store_agent(&agent).await?;
+        self.agents.insert(agent.id, agent.clone());
+        Ok(agent)
+    }
+
+    pub async fn get(&self, agent_id: &Uuid) -> Result<Option<Agent>> {
+        if let Some(agent) = self.agents.get(agent_id) {
+            return Ok(Some(agent.clone()));
+        }
+        // Registered through another replica sharing the storage
+        Ok(self.storage.list_agents().await?.into_iter().find(|a| a.id == *agent_id))
+    }
+
+    /// All agents, preferring this process's more recent in-memory state
+    pub async fn list(&self) -> Result<Vec<Agent>> {
+        let mut agents = self.storage.list_agents().await?;
+        for agent in agents.iter_mut() {
+            if let Some(local) = self.agents.get(&agent.id)
+                && local.last_seen >= agent.last_seen
+            {
+                *agent = local.clone();
+            }
+        }
+        agents.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(agents)
+    }
+
+    /// Mark an agent as alive
+    pub async fn heartbeat(&self, agent_id: &Uuid) -> Result<Agent> {
+        let mut agent = self.get(agent_id).await?.ok_or_else(|| TaskQueueError::ValidationError {
+            reason: format!("Agent {} is not registered", agent_id),
+        })?;
+        agent.last_seen = Utc::now();
+
+        self.storage.store_agent(&agent).await?;
+        self.agents.insert(agent.id, agent.clone());
+        Ok(agent)
+    }
+
+    /// Count a request made by an agent; unknown agents are ignored
+    pub async fn record(&self, agent_id: &Uuid, action: &str, mutation: bool, succeeded: bool) -> Result<()> {
+        let Some(mut agent) = self.get(agent_id).await? else {
+            return Ok(());
+        };
+
+        let now = Utc::now();
+        agent.last_seen = now;
+        agent.activity.requests += 1;
+        if !succeeded {
+            agent.activity.failures += 1;
+        }
+        if mutation {
+            agent.activity.mutations += 1;
+            agent.activity.last_action = Some(action.to_string());
+            agent.activity.last_action_at = Some(now);
+        }
+
+        if mutation {
+            self.storage.store_agent(&agent).await?;
+        }
+        self.agents.insert(agent.id, agent);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::StorageEngine;
+
+    #[tokio::test]
+    async fn test_registration_and_activity() {
+        let storage = Arc::new(StorageEngine::temporary().unwrap());
+        let registry = AgentRegistry::new(storage.clone());
+
+        let request = RegisterAgent {
+            name: "planner".to_string(),
+            model: Some("model-a".to_string()),
+            capabilities: vec!["rust".to_string()],
+        };
+        let agent = registry.register(request.clone()).await.unwrap();
+        let again = registry.register(RegisterAgent { model: Some("model-b".to_string()), ..request }).await.unwrap();
+        assert_eq!(again.id, agent.id);
+        assert_eq!(again.model.as_deref(), Some("model-b"));
+
+        registry.record(&agent.id, "GET /tasks", false, true).await.unwrap();
+        registry.record(&agent.id, "PUT /tasks/1/status", true, false).await.unwrap();
+        registry.record(&Uuid::new_v4(), "GET /tasks", false, true).await.unwrap();
+
+        // A fresh registry over the same storage sees the persisted mutation
+        let reloaded = AgentRegistry::new(storage);
+        let agents = reloaded.list().await.unwrap();
+        assert_eq!(agents.len(), 1);
+        assert_eq!(agents[0].activity.mutations, 1);
+        assert_eq!(agents[0].activity.failures, 1);
+        assert_eq!(agents[0].activity.last_action.as_deref(), Some("PUT /tasks/1/status"));
+    }
+
+    #[test]
+    fn test_state_follows_last_seen() {
+        let now = Utc::now();
+        let agent = |seconds_ago| Agent {
+            id: Uuid::new_v4(),
+            name: "worker".to_string(),
+            model: None,
+            capabilities: Vec::new(),
+            registered_at: now,
+            last_seen: now - Duration::seconds(seconds_ago),
+            activity: AgentActivity::default(),
+        };
+
+        assert_eq!(AgentStatus::at(agent(5), now).state, AgentState::Active);
+        assert_eq!(AgentStatus::at(agent(600), now).state, AgentState::Idle);
+        assert_eq!(AgentStatus::at(agent(3600), now).state, AgentState::Offline);
+    }
+}
@@ -0,0 +1,239 @@
+//! Task Artifacts Module
+//!
+//! After a task succeeds, files in its working directory matching the
+//! configured glob patterns (`execution.artifacts.patterns`) or the task's
+//! own `artifacts` metadata (e.g. `["dist/*.tar.gz", "reports/**/*.xml"]`)
+//! are copied into the blob store. The task result lists them by download
+//! URL, `/tasks/{id}/artifacts/{name}`, so they outlive the working
+//! directory. Each run's captured output is kept the same way, as a log file
+//! artifact.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::blobs::BlobStoreConfig;
+use crate::core::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Component, Path};
+use tracing::warn;
+use uuid::Uuid;
+
+/// Task metadata key holding extra glob patterns for the task's artifacts
+pub const ARTIFACTS_METADATA_KEY: &str = "artifacts";
+/// Name of the log file artifact of a task
+pub const LOG_FILE_NAME: &str = "output.log";
+
+/// Which files are kept as artifacts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArtifactConfig {
+    /// Glob patterns relative to the task's working directory
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    /// Larger files are skipped
+    #[serde(default = "default_max_file_bytes")]
+    pub max_file_bytes: u64,
+    /// Where artifact and log file contents are kept
+    #[serde(default)]
+    pub store: BlobStoreConfig,
+    /// How long the download URLs of artifact listings stay valid
+    #[serde(default = "default_url_ttl_secs")]
+    pub url_ttl_secs: u64,
+}
+
+fn default_max_file_bytes() -> u64 {
+    50 * 1024 * 1024
+}
+
+fn default_url_ttl_secs() -> u64 {
+    3600
+}
+
+impl Default for ArtifactConfig {
+    fn default() -> Self {
+        Self {
+            patterns: Vec::new(),
+            max_file_bytes: default_max_file_bytes(),
+            store: BlobStoreConfig::default(),
+            url_ttl_secs: default_url_ttl_secs(),
+        }
+    }
+}
+
+impl ArtifactConfig {
+    /// The configured patterns followed by the task's own
+    pub fn patterns_for(&self, task: &Task) -> Vec<String> {
+        let own = task.metadata.get(ARTIFACTS_METADATA_KEY)
+            .and_then(|patterns| patterns.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|pattern| pattern.as_str().map(str::to_string));
+        self.patterns.iter().cloned().chain(own).collect()
+    }
+
+    pub fn url_ttl(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.url_ttl_secs.max(1))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtifactKind {
+    /// A file from the working directory
+    #[default]
+    File,
+    /// The task's captured output
+    Log,
+}
+
+/// A stored artifact; its contents are kept apart
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Artifact {
+    pub task_id: Uuid,
+    /// Path relative to the working directory, `/`-separated
+    pub name: String,
+    pub size: u64,
+    pub sha256: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub kind: ArtifactKind,
+}
+
+impl Artifact {
+    pub fn new(task_id: Uuid, name: String, data: &[u8]) -> Self {
+        Self {
+            task_id,
+            name,
+            size: data.len() as u64,
+            sha256: format!("{:x}", Sha256::digest(data)),
+            created_at: Utc::now(),
+            kind: ArtifactKind::File,
+        }
+    }
+
+    /// The log file of a task's captured output
+    pub fn log(task_id: Uuid, data: &[u8]) -> Self {
+        Self { kind: ArtifactKind::Log, ..Self::new(task_id, LOG_FILE_NAME.to_string(), data) }
+    }
+
+    /// Key of the contents in the blob store
+    pub fn object_key(&self) -> String {
+        object_key(self.task_id, self.kind, &self.name)
+    }
+
+    pub fn download_url(&self) -> String {
+        format!("/tasks/{}/artifacts/{}", self.task_id, self.name)
+    }
+}
+
+/// Key of an artifact's contents in the blob store
+pub fn object_key(task_id: Uuid, kind: ArtifactKind, name: &str) -> String {
+    let folder = match kind {
+        ArtifactKind::File => "artifacts",
+        ArtifactKind::Log => "logs",
+    };
+    format!("tasks/{}/{}/{}", task_id, folder, name)
+}
+
+/// An artifact with a URL to download it from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArtifactLink {
+    #[serde(flatten)]
+    pub artifact: Artifact,
+    /// Signed, so it works without credentials until `expires_at`
+    pub url: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Patterns may not reach outside the working directory
+fn is_contained(pattern: &str) -> bool {
+    let path = Path::new(pattern);
+    !path.is_absolute() && path.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+/// Regular files under `dir` matching any of the patterns, as relative
+/// names and contents, sorted by name
+pub fn collect(dir: &Path, patterns: &[String], max_file_bytes: u64) -> Vec<(String, Vec<u8>)> {
+    let Ok(root) = dir.canonicalize() else {
+        return Vec::new();
+    };
+    let mut files = std::collections::BTreeMap::new();
+    for pattern in patterns {
+        if !is_contained(pattern) {
+            warn!("Ignoring artifact pattern outside the working directory: {}", pattern);
+            continue;
+        }
+        let full = root.join(pattern);
+        let paths = match glob::glob(&full.to_string_lossy()) {
+            Ok(paths) => paths,
+            Err(e) => {
+                warn!("Ignoring invalid artifact pattern {}: {}", pattern, e);
+                continue;
+            }
+        };
+        for path in paths.flatten() {
+            // Symlinks must not smuggle in files from elsewhere
+            let Ok(real) = path.canonicalize() else {
+                continue;
+            };
+            let Ok(name) = real.strip_prefix(&root) else {
+                continue;
+            };
+            let Ok(meta) = std::fs::metadata(&real) else {
+                continue;
+            };
+            if !meta.is_file() {
+                continue;
+            }
+            if meta.len() > max_file_bytes {
+                warn!("Skipping artifact {} of {} bytes, over the {} byte limit", real.display(), meta.len(), max_file_bytes);
+                continue;
+            }
+            let name = name.components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            if files.contains_key(&name) {
+                continue;
+            }
+            match std::fs::read(&real) {
+                Ok(data) => {
+                    files.insert(name, data);
+                }
+                Err(e) => warn!("Failed to read artifact {}: {}", real.display(), e),
+            }
+        }
+    }
+    files.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_matching_files_inside_the_directory() {
+        let dir = std::env::temp_dir().join(format!("task-queue-artifacts-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("reports/unit")).unwrap();
+        std::fs::write(dir.join("reports/unit/junit.xml"), "<testsuite/>").unwrap();
+        std::fs::write(dir.join("app.tar.gz"), "archive").unwrap();
+        std::fs::write(dir.join("huge.tar.gz"), vec![0u8; 64]).unwrap();
+        std::fs::write(dir.join("notes.txt"), "skip me").unwrap();
+
+        let patterns = ["reports/**/*.xml", "*.tar.gz", "../*", "/etc/passwd"].map(str::to_string);
+        let files = collect(&dir, &patterns, 32);
+        let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["app.tar.gz", "reports/unit/junit.xml"]);
+        assert_eq!(files[0].1, b"archive");
+
+        let task = Task::new("build").with_metadata(ARTIFACTS_METADATA_KEY, serde_json::json!(["*.log"])).build();
+        let config = ArtifactConfig { patterns: vec!["dist/*".to_string()], ..Default::default() };
+        assert_eq!(config.patterns_for(&task), ["dist/*", "*.log"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,239 @@
+//! Audit Trail Module
+//!
+//! Every create, update, cancel and delete of a task, project or workflow is
+//! recorded by storage as it writes the record: which agent made it (from
+//! `X-Agent-Id` or the MCP `agent_id` argument), whether it came in over REST,
+//! MCP or the CLI, and the fields that changed with their values before and
+//! after. Entries are keyed by time and never rewritten; `GET /audit` lists
+//! them, optionally for one kind of entity or one entity ID and only those
+//! made since a given moment.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::future::Future;
+use uuid::Uuid;
+
+/// Request header a client sets to say it is the CLI rather than plain REST
+pub const SOURCE_HEADER: &str = "x-request-source";
+/// Entries `GET /audit` returns when no limit is given
+pub const DEFAULT_AUDIT_LIMIT: usize = 100;
+/// Most entries one `GET /audit` returns
+pub const MAX_AUDIT_LIMIT: usize = 1000;
+
+/// Where a change came in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditSource {
+    Rest,
+    Mcp,
+    Cli,
+    Grpc,
+    /// The server itself, e.g. the execution engine or the retention job
+    #[default]
+    Internal,
+}
+
+impl AuditSource {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "rest" => Some(Self::Rest),
+            "mcp" => Some(Self::Mcp),
+            "cli" => Some(Self::Cli),
+            "grpc" => Some(Self::Grpc),
+            "internal" => Some(Self::Internal),
+            _ => None,
+        }
+    }
+}
+
+tokio::task_local! {
+    static CURRENT_SOURCE: AuditSource;
+}
+
+/// Where the change being made now came in
+pub fn current_source() -> AuditSource {
+    CURRENT_SOURCE.try_with(|source| *source).unwrap_or_default()
+}
+
+/// Run `future` as a request that came in over `source`
+pub async fn with_source<F: Future>(source: AuditSource, future: F) -> F::Output {
+    CURRENT_SOURCE.scope(source, future).await
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditEntity {
+    Task,
+    Project,
+    Workflow,
+}
+
+impl AuditEntity {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "task" | "tasks" => Some(Self::Task),
+            "project" | "projects" => Some(Self::Project),
+            "workflow" | "workflows" => Some(Self::Workflow),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditAction {
+    Create,
+    Update,
+    /// An update that moved the entity to `Cancelled`
+    Cancel,
+    Delete,
+}
+
+/// One field that differs between the stored record and the one replacing it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    /// Unset when the field did not exist before
+    pub before: Option<Value>,
+    /// Unset when the field no longer exists
+    pub after: Option<Value>,
+}
+
+/// One recorded mutation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: Uuid,
+    pub entity: AuditEntity,
+    pub entity_id: Uuid,
+    pub action: AuditAction,
+    /// Agent whose request made the change
+    pub actor: Option<Uuid>,
+    pub source: AuditSource,
+    pub changes: Vec<FieldChange>,
+    pub at: DateTime<Utc>,
+}
+
+impl AuditEntry {
+    /// The entry for replacing `before` with `after`, attributed to the
+    /// current agent and source; none if nothing changed
+    pub fn record(entity: AuditEntity, entity_id: Uuid, before: Option<Value>, after: Option<Value>) -> Option<Self> {
+        let action = match (&before, &after) {
+            (None, None) => return None,
+            (None, Some(_)) => AuditAction::Create,
+            (Some(_), None) => AuditAction::Delete,
+            (Some(before), Some(after)) => {
+                let cancelled = |record: &Value| record.get("status") == Some(&Value::from("Cancelled"));
+                if cancelled(after) && !cancelled(before) { AuditAction::Cancel } else { AuditAction::Update }
+            }
+        };
+        let changes = diff(before.as_ref(), after.as_ref());
+        if changes.is_empty() {
+            return None;
+        }
+        Some(Self {
+            id: Uuid::new_v4(),
+            entity,
+            entity_id,
+            action,
+            actor: crate::agents::current_agent(),
+            source: current_source(),
+            changes,
+            at: Utc::now(),
+        })
+    }
+}
+
+/// Top-level fields that differ between two records
+pub fn diff(before: Option<&Value>, after: Option<&Value>) -> Vec<FieldChange> {
+    let fields = |record: Option<&Value>| record.and_then(Value::as_object).cloned().unwrap_or_default();
+    let (before, mut after) = (fields(before), fields(after));
+    let mut changes = Vec::new();
+
+    for (field, old) in before {
+        let new = after.remove(&field);
+        if new.as_ref() != Some(&old) {
+            changes.push(FieldChange { field, before: Some(old), after: new });
+        }
+    }
+    changes.extend(after.into_iter().map(|(field, new)| FieldChange { field, before: None, after: Some(new) }));
+    changes.sort_by(|a, b| a.field.cmp(&b.field));
+    changes
+}
+
+/// Which entity `GET /audit?entity=` asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntityFilter {
+    Kind(AuditEntity),
+    Id(Uuid),
+}
+
+impl EntityFilter {
+    /// An entity kind (`task`, `project`, `workflow`) or an entity ID
+    pub fn parse(value: &str) -> Option<Self> {
+        AuditEntity::parse(value).map(Self::Kind)
+            .or_else(|| Uuid::parse_str(value).ok().map(Self::Id))
+    }
+}
+
+/// Filters of an audit listing
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditQuery {
+    pub entity: Option<EntityFilter>,
+    pub since: Option<DateTime<Utc>>,
+    pub limit: usize,
+}
+
+impl Default for AuditQuery {
+    fn default() -> Self {
+        Self { entity: None, since: None, limit: DEFAULT_AUDIT_LIMIT }
+    }
+}
+
+impl AuditQuery {
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        let entity = match self.entity {
+            Some(EntityFilter::Kind(kind)) => entry.entity == kind,
+            Some(EntityFilter::Id(id)) => entry.entity_id == id,
+            None => true,
+        };
+        entity && self.since.is_none_or(|since| entry.at >= since)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_entries_diff_records_and_carry_their_source() {
+        let id = Uuid::new_v4();
+        let before = json!({"name": "Build", "status": "Pending", "priority": "Normal"});
+        let after = json!({"name": "Build", "status": "Cancelled", "tags": ["ci"]});
+
+        let entry = with_source(AuditSource::Mcp, async {
+            AuditEntry::record(AuditEntity::Task, id, Some(before.clone()), Some(after.clone()))
+        }).await.unwrap();
+        assert_eq!(entry.action, AuditAction::Cancel);
+        assert_eq!(entry.source, AuditSource::Mcp);
+        let fields: Vec<&str> = entry.changes.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(fields, ["priority", "status", "tags"]);
+        assert_eq!(entry.changes[0].after, None);
+        assert_eq!(entry.changes[2].before, None);
+
+        assert!(AuditEntry::record(AuditEntity::Task, id, Some(after.clone()), Some(after.clone())).is_none());
+        let deleted = AuditEntry::record(AuditEntity::Task, id, Some(after), None).unwrap();
+        assert_eq!((deleted.action, deleted.source), (AuditAction::Delete, AuditSource::Internal));
+
+        let query = AuditQuery { entity: EntityFilter::parse("projects"), ..Default::default() };
+        assert!(!query.matches(&deleted));
+        assert!(AuditQuery { entity: EntityFilter::parse(&id.to_string()), ..Default::default() }.matches(&deleted));
+    }
+}
@@ -4,7 +4,8 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use sha2::{Sha256, Digest};
-use rand::Rng;
+use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
+use subtle::ConstantTimeEq;
 use std::collections::HashMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,6 +48,7 @@ pub struct ApiKeyInfo {
     pub permissions: Vec<String>,
 }
 
+#[derive(Default)]
 pub struct ApiKeyManager {
     api_keys: HashMap<Uuid, ApiKey>,
 }
@@ -59,16 +61,19 @@ impl ApiKeyManager {
     }
 
     pub fn generate_api_key() -> String {
-        let mut rng = rand::thread_rng();
         let key_length = 32;
         let chars: Vec<char> = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789".chars().collect();
-        
-        (0..key_length)
-            .map(|_| chars[rng.gen_range(0..chars.len())])
+        // Bytes past the last whole multiple of the alphabet would skew the pick
+        let limit = (256 / chars.len() * chars.len()) as u32;
+
+        std::iter::repeat_with(|| OsRng.next_u32() & 0xff)
+            .filter(|byte| *byte < limit)
+            .take(key_length)
+            .map(|byte| chars[byte as usize % chars.len()])
             .collect()
     }
 
-    pub fn hash_api_key(&self, key: &str) -> String {
+    pub fn hash_api_key(key: &str) -> String {
         let mut hasher = Sha256::new();
         hasher.update(key.as_bytes());
         format!("{:x}", hasher.finalize())
@@ -76,7 +81,7 @@ impl ApiKeyManager {
 
     pub fn create_api_key(&mut self, user_id: Uuid, request: CreateApiKeyRequest) -> Result<CreateApiKeyResponse, String> {
         let key = Self::generate_api_key();
-        let key_hash = self.hash_api_key(&key);
+        let key_hash = Self::hash_api_key(&key);
         
         let api_key = ApiKey {
             id: Uuid::new_v4(),
@@ -103,8 +108,8 @@ impl ApiKeyManager {
     }
 
     pub fn verify_api_key(&self, key: &str, stored_hash: &str) -> bool {
-        let computed_hash = self.hash_api_key(key);
-        computed_hash == stored_hash
+        let computed_hash = Self::hash_api_key(key);
+        computed_hash.as_bytes().ct_eq(stored_hash.as_bytes()).into()
     }
 
     pub fn is_api_key_valid(&self, api_key: &ApiKey) -> bool {
@@ -112,10 +117,10 @@ impl ApiKeyManager {
             return false;
         }
 
-        if let Some(expires_at) = api_key.expires_at {
-            if Utc::now() > expires_at {
-                return false;
-            }
+        if let Some(expires_at) = api_key.expires_at
+            && Utc::now() > expires_at
+        {
+            return false;
         }
 
         true
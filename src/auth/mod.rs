@@ -2,23 +2,18 @@
 //!
 //! This module provides comprehensive authentication and authorization functionality
 //! including JWT tokens, RBAC, MFA, OAuth 2.0, and API key management.
+//!
+//! Only the API key and permission parts are built for now; the tenant keys
+//! of [`crate::tenants`] are hashed by [`ApiKeyManager`] and their scopes
+//! checked as [`Permission`](crate::models::Permission)s. The JWT, OAuth, MFA,
+//! session, role and user account parts need crates the build doesn't carry
+//! yet (`jsonwebtoken`, `bcrypt`, `totp-rs`, `qrcode`).
+
+#![allow(unused_imports)]
+#![allow(dead_code)]
 
-pub mod jwt;
-pub mod oauth;
-pub mod mfa;
 pub mod api_keys;
-pub mod sessions;
 pub mod permissions;
-pub mod roles;
-pub mod middleware;
-pub mod handlers;
 
-pub use jwt::JwtManager;
-pub use oauth::OAuthManager;
-pub use mfa::MfaManager;
 pub use api_keys::ApiKeyManager;
-pub use sessions::SessionManager;
 pub use permissions::*;
-pub use roles::RoleManager;
-pub use middleware::{auth_middleware, api_key_middleware, AuthContext, ApiKeyContext};
-pub use handlers::{AuthState, create_auth_router};
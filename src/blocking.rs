@@ -0,0 +1,250 @@
+//! Blocking Client Module
+//!
+//! Synchronous wrapper around `TaskQueueClient` for scripts and build tools
+//! that don't run an async runtime. Enabled with the `blocking` feature.
+//!
+//! Each client owns a single-threaded Tokio runtime, so it must not be used
+//! from inside an async context.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::client::{self, TaskListFilter, TaskQueueClientBuilder};
+use crate::core::*;
+use crate::error::{TaskQueueError, Result};
+use crate::events::{EventFilter, TaskEvent};
+use futures_util::{Stream, StreamExt};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+/// Blocking task queue client
+///
+/// Mirrors the async `TaskQueueClient` API. Clones share the runtime and
+/// connection pool.
+#[derive(Clone)]
+pub struct TaskQueueClient {
+    inner: client::TaskQueueClient,
+    runtime: Arc<Runtime>,
+}
+
+impl TaskQueueClient {
+    /// Create a new client and verify the server is reachable
+    pub fn new(base_url: &str) -> Result<Self> {
+        Self::from_builder(client::TaskQueueClient::builder(base_url))
+    }
+
+    /// Build a blocking client from an async client builder
+    pub fn from_builder(builder: TaskQueueClientBuilder) -> Result<Self> {
+        let runtime = Self::build_runtime()?;
+        let inner = runtime.block_on(builder.build())?;
+        Ok(Self {
+            inner,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// Wrap an existing async client
+    pub fn from_async(inner: client::TaskQueueClient) -> Result<Self> {
+        Ok(Self {
+            inner,
+            runtime: Arc::new(Self::build_runtime()?),
+        })
+    }
+
+    fn build_runtime() -> Result<Runtime> {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| TaskQueueError::InternalError(format!("Failed to start runtime: {}", e)))
+    }
+
+    /// Get the underlying async client
+    pub fn inner(&self) -> &client::TaskQueueClient {
+        &self.inner
+    }
+
+    /// Check that the server is reachable
+    pub fn health_check(&self) -> Result<()> {
+        self.runtime.block_on(self.inner.health_check())
+    }
+
+    /// Submit a task
+    pub fn submit_task(&self, task: Task) -> Result<uuid::Uuid> {
+        self.runtime.block_on(self.inner.submit_task(task))
+    }
+
+    /// Get task by ID
+    pub fn get_task(&self, task_id: &uuid::Uuid) -> Result<Task> {
+        self.runtime.block_on(self.inner.get_task(task_id))
+    }
+
+    /// Get task status
+    pub fn get_task_status(&self, task_id: &uuid::Uuid) -> Result<TaskStatus> {
+        self.runtime.block_on(self.inner.get_task_status(task_id))
+    }
+
+    /// Get task result
+    pub fn get_task_result(&self, task_id: &uuid::Uuid) -> Result<Option<TaskResult>> {
+        self.runtime.block_on(self.inner.get_task_result(task_id))
+    }
+
+    /// List tasks
+    pub fn list_tasks(&self, project: Option<String>, status: Option<String>) -> Result<Vec<Task>> {
+        self.runtime.block_on(self.inner.list_tasks(project, status))
+    }
+
+    /// List lightweight task summaries
+    pub fn list_task_summaries(&self, project: Option<String>, status: Option<String>) -> Result<Vec<TaskSummary>> {
+        self.runtime.block_on(self.inner.list_task_summaries(project, status))
+    }
+
+    /// Fetch a single page of tasks
+    pub fn list_tasks_page(
+        &self,
+        filter: &TaskListFilter,
+        cursor: Option<&str>,
+    ) -> Result<(Vec<Task>, Option<String>)> {
+        self.runtime.block_on(self.inner.list_tasks_page(filter, cursor))
+    }
+
+    /// Iterate over every task matching the filter, fetching pages lazily
+    pub fn iter_tasks(&self, filter: TaskListFilter) -> BlockingIter<Task> {
+        let _guard = self.runtime.enter();
+        BlockingIter {
+            stream: Box::pin(self.inner.tasks().list(filter)),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    /// Submit a workflow
+    pub fn submit_workflow(&self, workflow: Workflow) -> Result<uuid::Uuid> {
+        self.runtime.block_on(self.inner.submit_workflow(workflow))
+    }
+
+    /// Get workflow by ID
+    pub fn get_workflow(&self, workflow_id: &uuid::Uuid) -> Result<Workflow> {
+        self.runtime.block_on(self.inner.get_workflow(workflow_id))
+    }
+
+    /// Get workflow status
+    pub fn get_workflow_status(&self, workflow_id: &uuid::Uuid) -> Result<WorkflowStatus> {
+        self.runtime.block_on(self.inner.get_workflow_status(workflow_id))
+    }
+
+    /// Get system metrics
+    pub fn get_metrics(&self) -> Result<serde_json::Value> {
+        self.runtime.block_on(self.inner.get_metrics())
+    }
+
+    /// Wait for task completion
+    pub fn wait_for_task_completion(
+        &self,
+        task_id: &uuid::Uuid,
+        timeout: Option<Duration>,
+    ) -> Result<TaskResult> {
+        self.runtime.block_on(self.inner.wait_for_task_completion(task_id, timeout))
+    }
+
+    /// Wait for workflow completion
+    pub fn wait_for_workflow_completion(
+        &self,
+        workflow_id: &uuid::Uuid,
+        timeout: Option<Duration>,
+    ) -> Result<WorkflowStatus> {
+        self.runtime.block_on(self.inner.wait_for_workflow_completion(workflow_id, timeout))
+    }
+
+    /// Subscribe to lifecycle events
+    ///
+    /// Events are only received while the iterator is being polled.
+    pub fn subscribe_events(&self, filter: EventFilter) -> BlockingIter<TaskEvent> {
+        self.subscribe_events_from(filter, None)
+    }
+
+    /// Subscribe to events, resuming after the given sequence cursor
+    pub fn subscribe_events_from(&self, filter: EventFilter, cursor: Option<u64>) -> BlockingIter<TaskEvent> {
+        let _guard = self.runtime.enter();
+        BlockingIter {
+            stream: Box::pin(self.inner.subscribe_events_from(filter, cursor)),
+            runtime: self.runtime.clone(),
+        }
+    }
+}
+
+/// Iterator driving one of the async client's streams to completion
+pub struct BlockingIter<T> {
+    stream: Pin<Box<dyn Stream<Item = Result<T>> + Send>>,
+    runtime: Arc<Runtime>,
+}
+
+impl<T> Iterator for BlockingIter<T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.runtime.block_on(self.stream.next())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::{Path, Query}, routing::get, Json, Router};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    /// Serve `app` from a background thread with its own runtime
+    fn serve(app: Router) -> String {
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            runtime.block_on(async move {
+                let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+                tx.send(listener.local_addr().unwrap()).unwrap();
+                axum::serve(listener, app).await.unwrap();
+            });
+        });
+        format!("http://{}", rx.recv().unwrap())
+    }
+
+    #[test]
+    fn test_blocking_client_calls_and_iterates() {
+        let tasks: Vec<Task> = (0..3).map(|i| Task::new(&format!("task-{}", i)).build()).collect();
+        let pages = tasks.clone();
+        let app = Router::new()
+            .route("/api/v1/health", get(|| async { "ok" }))
+            .route("/api/v1/tasks/{id}/status", get(|Path(_id): Path<String>| async { Json(json!({"status": "Running"})) }))
+            .route("/api/v1/tasks", get(move |Query(query): Query<HashMap<String, String>>| {
+                let pages = pages.clone();
+                async move {
+                    let start: usize = query.get("cursor").map_or(0, |c| c.parse().unwrap());
+                    let next_cursor = (start + 1 < pages.len()).then(|| (start + 1).to_string());
+                    Json(json!({ "tasks": [pages[start].clone()], "next_cursor": next_cursor }))
+                }
+            }));
+        let client = TaskQueueClient::new(&serve(app)).unwrap();
+
+        client.health_check().unwrap();
+        assert_eq!(client.get_task_status(&uuid::Uuid::new_v4()).unwrap(), TaskStatus::Running);
+
+        let names: Vec<String> = client
+            .iter_tasks(TaskListFilter::new().with_page_size(1))
+            .map(|task| task.unwrap().name)
+            .collect();
+        assert_eq!(names, ["task-0", "task-1", "task-2"]);
+    }
+
+    #[test]
+    fn test_blocking_client_reports_unreachable_servers() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let builder = client::TaskQueueClient::builder(&format!("http://{}", addr)).with_connect_timeout(Duration::from_millis(200));
+        assert!(matches!(TaskQueueClient::from_builder(builder), Err(TaskQueueError::NetworkError(_))));
+    }
+}
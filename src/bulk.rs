@@ -0,0 +1,120 @@
+//! Bulk Operations Module
+//!
+//! `POST /tasks/bulk` applies one operation — cancel, delete, set the
+//! priority or set the status — to many tasks in a single request, picked
+//! either by ID or by the filter `GET /tasks` takes. Each task is changed on
+//! its own, as by the single-task endpoint, so one failure doesn't hold back
+//! the rest; the report has the outcome of every task.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::*;
+use crate::error::{TaskQueueError, Result};
+use crate::views::TaskFilter;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Most tasks one request may change
+pub const MAX_BULK_TASKS: usize = 10_000;
+
+/// What to do to each task
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+pub enum BulkOperation {
+    Cancel {
+        #[serde(default)]
+        reason: Option<String>,
+    },
+    Delete,
+    SetPriority { priority: TaskPriority },
+    SetStatus { status: TaskStatus },
+}
+
+/// Body of `POST /tasks/bulk`; either `task_ids` or `filter` picks the tasks
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BulkRequest {
+    #[serde(flatten)]
+    pub operation: BulkOperation,
+    #[serde(default)]
+    pub task_ids: Vec<Uuid>,
+    pub filter: Option<TaskFilter>,
+}
+
+impl BulkRequest {
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |reason: &str| Err(TaskQueueError::ValidationError { reason: reason.to_string() });
+        match (&self.filter, self.task_ids.is_empty()) {
+            (Some(_), false) => return invalid("Give either task_ids or filter, not both"),
+            (None, true) => return invalid("Give task_ids or a filter"),
+            (Some(filter), true) => filter.validate()?,
+            (None, false) => {}
+        }
+        if self.task_ids.len() > MAX_BULK_TASKS {
+            return invalid(&format!("At most {} tasks can be changed at once", MAX_BULK_TASKS));
+        }
+        Ok(())
+    }
+}
+
+/// Outcome for one task
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct BulkItem {
+    pub task_id: Uuid,
+    /// Why the task was left as it was
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct BulkReport {
+    /// Tasks picked by the request
+    pub matched: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub items: Vec<BulkItem>,
+}
+
+impl BulkReport {
+    pub fn record(&mut self, task_id: Uuid, outcome: Result<()>) {
+        let error = outcome.err().map(|e| e.to_string());
+        match error {
+            Some(_) => self.failed += 1,
+            None => self.succeeded += 1,
+        }
+        self.items.push(BulkItem { task_id, error });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_request_picks_tasks_one_way() {
+        let task_id = Uuid::new_v4();
+        let request: BulkRequest = serde_json::from_value(json!({
+            "operation": "set_priority",
+            "priority": "High",
+            "task_ids": [task_id]
+        }))
+        .unwrap();
+        assert_eq!(request.operation, BulkOperation::SetPriority { priority: TaskPriority::High });
+        assert!(request.validate().is_ok());
+
+        let request: BulkRequest = serde_json::from_value(json!({
+            "operation": "cancel",
+            "filter": { "statuses": ["pending"] }
+        }))
+        .unwrap();
+        assert!(request.validate().is_ok());
+        let both = BulkRequest { task_ids: vec![task_id], ..request.clone() };
+        assert!(both.validate().is_err());
+        let neither = BulkRequest { filter: None, ..request };
+        assert!(neither.validate().is_err());
+    }
+}
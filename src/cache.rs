@@ -184,7 +184,7 @@ where
 
         // Check if we need to evict
         if data.len() >= self.config.max_size && !data.contains_key(&key) {
-            self.evict_entry(&mut data, &mut metrics).await;
+            self.evict_entry(&mut data, &mut metrics);
         }
 
         let entry = CacheEntry::new(value, ttl);
@@ -241,7 +241,7 @@ where
     }
 
     /// Evict an entry based on the configured strategy
-    async fn evict_entry(&self, data: &mut HashMap<K, CacheEntry<V>>, metrics: &mut CacheMetrics) {
+    fn evict_entry(&self, data: &mut HashMap<K, CacheEntry<V>>, metrics: &mut CacheMetrics) {
         if data.is_empty() {
             return;
         }
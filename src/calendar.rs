@@ -0,0 +1,200 @@
+//! Calendar Feed Module
+//!
+//! Renders a project's deadlines and upcoming scheduled runs as an iCalendar
+//! (RFC 5545) feed that team calendars can subscribe to.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::*;
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::BTreeMap;
+
+/// Task metadata key holding a due date
+pub const DUE_DATE_KEY: &str = "due_date";
+/// Task metadata key holding the due date of the task's milestone
+pub const MILESTONE_DUE_KEY: &str = "milestone_due";
+/// Task metadata key holding the next scheduled run
+pub const RUN_AT_KEY: &str = "run_at";
+
+/// When a calendar event happens
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventTime {
+    /// All-day event
+    Date(NaiveDate),
+    At(DateTime<Utc>),
+}
+
+impl EventTime {
+    /// Parse an RFC 3339 timestamp or a plain `YYYY-MM-DD` date
+    pub fn parse(value: &str) -> Option<Self> {
+        if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+            return Some(Self::At(at.with_timezone(&Utc)));
+        }
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").ok().map(Self::Date)
+    }
+
+    fn from_metadata(task: &Task, key: &str) -> Option<Self> {
+        task.metadata.get(key)?.as_str().and_then(Self::parse)
+    }
+}
+
+/// One entry of the feed
+#[derive(Debug, Clone)]
+pub struct CalendarEvent {
+    pub uid: String,
+    pub summary: String,
+    pub description: Option<String>,
+    pub time: EventTime,
+}
+
+fn is_closed(task: &Task) -> bool {
+    matches!(task.status, TaskStatus::Completed | TaskStatus::Finalized | TaskStatus::Cancelled)
+}
+
+/// Collect the events of a project and its tasks
+pub fn project_events(project: &Project, tasks: &[Task]) -> Vec<CalendarEvent> {
+    let mut events = Vec::new();
+
+    if let Some(due) = project.due_date {
+        events.push(CalendarEvent {
+            uid: format!("project-{}-due", project.id),
+            summary: format!("{} due", project.name),
+            description: project.description.clone(),
+            time: EventTime::At(due),
+        });
+    }
+
+    // Milestones are shared by several tasks; emit each one once
+    let mut milestones: BTreeMap<String, EventTime> = BTreeMap::new();
+
+    for task in tasks {
+        if let Some(time) = EventTime::from_metadata(task, DUE_DATE_KEY) {
+            events.push(CalendarEvent {
+                uid: format!("task-{}-due", task.id),
+                summary: format!("Due: {}", task.name),
+                description: Some(task.description.clone()),
+                time,
+            });
+        }
+
+        if let Some(milestone) = task.metadata.get("milestone").and_then(|m| m.as_str())
+            && let Some(time) = EventTime::from_metadata(task, MILESTONE_DUE_KEY)
+        {
+            milestones.entry(milestone.to_string()).or_insert(time);
+        }
+
+        if !is_closed(task)
+            && let Some(time @ EventTime::At(at)) = EventTime::from_metadata(task, RUN_AT_KEY)
+            && at > Utc::now()
+        {
+            events.push(CalendarEvent {
+                uid: format!("task-{}-run", task.id),
+                summary: format!("Run: {}", task.name),
+                description: Some(task.command.clone()),
+                time,
+            });
+        }
+    }
+
+    for (milestone, time) in milestones {
+        events.push(CalendarEvent {
+            uid: format!("project-{}-milestone-{}", project.id, milestone),
+            summary: format!("Milestone: {}", milestone),
+            description: None,
+            time,
+        });
+    }
+
+    events
+}
+
+/// Escape a TEXT value
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Write a content line, folding it at 75 octets
+fn push_line(out: &mut String, line: &str) {
+    let mut width = 0;
+    for ch in line.chars() {
+        if width + ch.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(ch);
+        width += ch.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+/// Render events as an iCalendar document
+pub fn render(name: &str, events: &[CalendarEvent]) -> String {
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut out = String::new();
+
+    push_line(&mut out, "BEGIN:VCALENDAR");
+    push_line(&mut out, "VERSION:2.0");
+    push_line(&mut out, "PRODID:-//hivellm//task-queue//EN");
+    push_line(&mut out, "CALSCALE:GREGORIAN");
+    push_line(&mut out, &format!("X-WR-CALNAME:{}", escape(name)));
+
+    for event in events {
+        push_line(&mut out, "BEGIN:VEVENT");
+        push_line(&mut out, &format!("UID:{}@task-queue", event.uid));
+        push_line(&mut out, &format!("DTSTAMP:{}", stamp));
+        match &event.time {
+            EventTime::Date(date) => {
+                push_line(&mut out, &format!("DTSTART;VALUE=DATE:{}", date.format("%Y%m%d")));
+            }
+            EventTime::At(at) => {
+                push_line(&mut out, &format!("DTSTART:{}", at.format("%Y%m%dT%H%M%SZ")));
+            }
+        }
+        push_line(&mut out, &format!("SUMMARY:{}", escape(&event.summary)));
+        if let Some(description) = &event.description {
+            push_line(&mut out, &format!("DESCRIPTION:{}", escape(description)));
+        }
+        push_line(&mut out, "END:VEVENT");
+    }
+
+    push_line(&mut out, "END:VCALENDAR");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_feed_contains_due_dates_milestones_and_runs() {
+        let project = Project::new("Launch");
+        let mut due = Task::new("Write docs, final").build();
+        due.metadata.insert(DUE_DATE_KEY.to_string(), json!("2030-05-01"));
+        due.metadata.insert("milestone".to_string(), json!("v1"));
+        due.metadata.insert(MILESTONE_DUE_KEY.to_string(), json!("2030-06-01T00:00:00Z"));
+        let mut run = Task::new("Nightly build").with_command("make").build();
+        let next_run = Utc::now() + chrono::Duration::days(1);
+        run.metadata.insert(RUN_AT_KEY.to_string(), json!(next_run.to_rfc3339()));
+        run.metadata.insert("milestone".to_string(), json!("v1"));
+
+        let events = project_events(&project, &[due, run]);
+        assert_eq!(events.len(), 3);
+
+        let ics = render("Launch", &events);
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20300501\r\n"));
+        assert!(ics.contains("SUMMARY:Due: Write docs\\, final\r\n"));
+        assert!(ics.contains("SUMMARY:Milestone: v1\r\n"));
+        assert!(ics.contains(&format!("DTSTART:{}\r\n", next_run.format("%Y%m%dT%H%M%SZ"))));
+        assert!(ics.lines().all(|line| line.len() <= 75));
+    }
+}
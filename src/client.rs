@@ -7,51 +7,364 @@
 
 use crate::core::*;
 use crate::error::{TaskQueueError, Result};
-use reqwest::Client;
+use crate::events::{EventFilter, SseParser, TaskEvent};
+use crate::server::API_PREFIX;
+use crate::tenants::API_KEY_HEADER;
+use futures_util::{Stream, TryStreamExt};
+use reqwest::header::HeaderValue;
+use reqwest::{Client, Method, RequestBuilder, Response};
 use serde_json::json;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{debug, warn, Instrument};
+
+/// Connection settings for the underlying HTTP client
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// Maximum idle connections kept per host
+    pub pool_max_idle_per_host: usize,
+    /// How long an idle pooled connection is kept before closing
+    pub pool_idle_timeout: Option<Duration>,
+    /// TCP keep-alive interval for pooled sockets
+    pub tcp_keepalive: Option<Duration>,
+    /// Speak HTTP/2 without ALPN negotiation (h2c)
+    pub http2_prior_knowledge: bool,
+    /// HTTP/2 PING interval used to keep connections alive
+    pub http2_keep_alive_interval: Option<Duration>,
+    /// Timeout for establishing a connection
+    pub connect_timeout: Option<Duration>,
+    /// Timeout for a whole request
+    pub request_timeout: Option<Duration>,
+    /// Proxy URL applied to all requests
+    pub proxy: Option<String>,
+    /// Retries for GET requests failing with a connection error, timeout or 5xx
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled on each further attempt
+    pub retry_backoff: Duration,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            pool_max_idle_per_host: 32,
+            pool_idle_timeout: Some(Duration::from_secs(90)),
+            tcp_keepalive: Some(Duration::from_secs(60)),
+            http2_prior_knowledge: false,
+            http2_keep_alive_interval: None,
+            connect_timeout: Some(Duration::from_secs(10)),
+            request_timeout: Some(Duration::from_secs(60)),
+            proxy: None,
+            max_retries: 0,
+            retry_backoff: Duration::from_millis(200),
+        }
+    }
+}
+
+impl ClientConfig {
+    /// Build a `reqwest::Client` from these settings
+    pub fn build_http_client(&self) -> Result<Client> {
+        let mut builder = Client::builder()
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .pool_idle_timeout(self.pool_idle_timeout)
+            .tcp_keepalive(self.tcp_keepalive);
+
+        if self.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        if let Some(interval) = self.http2_keep_alive_interval {
+            builder = builder
+                .http2_keep_alive_interval(interval)
+                .http2_keep_alive_while_idle(true);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.request_timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+        }
+
+        Ok(builder.build()?)
+    }
+}
+
+/// Outcome of a single client call, passed to the metrics hook
+#[derive(Debug, Clone)]
+pub struct RequestMetrics {
+    /// Client method that issued the request, e.g. `submit_task`
+    pub operation: &'static str,
+    pub method: Method,
+    /// HTTP status, if a response was received
+    pub status: Option<u16>,
+    /// Total time including retries
+    pub latency: Duration,
+    pub retries: u32,
+}
+
+/// Callback invoked after every client request
+pub type MetricsHook = Arc<dyn Fn(&RequestMetrics) + Send + Sync>;
+
+/// Builder for [`TaskQueueClient`]
+pub struct TaskQueueClientBuilder {
+    base_url: String,
+    config: ClientConfig,
+    http_client: Option<Client>,
+    metrics_hook: Option<MetricsHook>,
+    api_key: Option<String>,
+}
+
+impl TaskQueueClientBuilder {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            config: ClientConfig::default(),
+            http_client: None,
+            metrics_hook: None,
+            api_key: None,
+        }
+    }
+
+    /// Send this API key, a tenant's or the operator's admin key, with
+    /// every request
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    pub fn with_config(mut self, config: ClientConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn with_pool_max_idle_per_host(mut self, max_idle: usize) -> Self {
+        self.config.pool_max_idle_per_host = max_idle;
+        self
+    }
+
+    pub fn with_pool_idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.config.pool_idle_timeout = timeout;
+        self
+    }
+
+    pub fn with_tcp_keepalive(mut self, interval: Option<Duration>) -> Self {
+        self.config.tcp_keepalive = interval;
+        self
+    }
+
+    pub fn with_http2_prior_knowledge(mut self) -> Self {
+        self.config.http2_prior_knowledge = true;
+        self
+    }
+
+    pub fn with_http2_keep_alive_interval(mut self, interval: Duration) -> Self {
+        self.config.http2_keep_alive_interval = Some(interval);
+        self
+    }
+
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.config.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.config.request_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_proxy(mut self, proxy_url: &str) -> Self {
+        self.config.proxy = Some(proxy_url.to_string());
+        self
+    }
+
+    /// Retry idempotent requests up to `max_retries` times
+    pub fn with_retries(mut self, max_retries: u32, backoff: Duration) -> Self {
+        self.config.max_retries = max_retries;
+        self.config.retry_backoff = backoff;
+        self
+    }
+
+    /// Report latency, status code and retries of every request
+    pub fn with_metrics_hook(mut self, hook: impl Fn(&RequestMetrics) + Send + Sync + 'static) -> Self {
+        self.metrics_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Reuse an existing HTTP client (and its connection pool) instead of building one
+    pub fn with_http_client(mut self, client: Client) -> Self {
+        self.http_client = Some(client);
+        self
+    }
+
+    /// Build the client without contacting the server
+    pub fn build_unchecked(self) -> Result<TaskQueueClient> {
+        let client = match self.http_client {
+            Some(client) => client,
+            None => self.config.build_http_client()?,
+        };
+        let api_key = self.api_key
+            .map(|key| {
+                let mut value = HeaderValue::from_str(&key).map_err(|_| TaskQueueError::ValidationError {
+                    reason: "API key must be printable ASCII".to_string(),
+                })?;
+                value.set_sensitive(true);
+                Ok::<_, TaskQueueError>(value)
+            })
+            .transpose()?;
+
+        Ok(TaskQueueClient {
+            client,
+            api_url: format!("{}{}", self.base_url, API_PREFIX),
+            max_retries: self.config.max_retries,
+            retry_backoff: self.config.retry_backoff,
+            metrics_hook: self.metrics_hook,
+            api_key,
+        })
+    }
+
+    /// Build the client and verify the server is reachable
+    pub async fn build(self) -> Result<TaskQueueClient> {
+        let client = self.build_unchecked()?;
+        client.health_check().await?;
+        Ok(client)
+    }
+}
+
+/// Default number of tasks fetched per page when streaming
+const DEFAULT_LIST_PAGE_SIZE: usize = 100;
+
+/// Initial delay before reconnecting an event subscription
+const EVENT_RECONNECT_MIN: Duration = Duration::from_secs(1);
+/// Upper bound for the reconnect backoff
+const EVENT_RECONNECT_MAX: Duration = Duration::from_secs(30);
 
 /// Task queue client for interacting with the server
+///
+/// Cloning is cheap: clones share the same connection pool.
+#[derive(Clone)]
 pub struct TaskQueueClient {
     client: Client,
-    base_url: String,
+    /// Server URL followed by the versioned API prefix
+    api_url: String,
+    max_retries: u32,
+    retry_backoff: Duration,
+    metrics_hook: Option<MetricsHook>,
+    api_key: Option<HeaderValue>,
 }
 
 impl TaskQueueClient {
     /// Create a new task queue client
     pub async fn new(base_url: &str) -> Result<Self> {
-        let client = Client::new();
-        
-        // Test connection
-        let response = client
-            .get(&format!("{}/health", base_url))
-            .send()
+        Self::builder(base_url).build().await
+    }
+
+    /// Create a client builder
+    pub fn builder(base_url: &str) -> TaskQueueClientBuilder {
+        TaskQueueClientBuilder::new(base_url)
+    }
+
+    /// Send a request with tracing, retries and the metrics hook applied
+    async fn execute(&self, operation: &'static str, request: RequestBuilder) -> Result<Response> {
+        let mut request = request.build()?;
+        if let Some(api_key) = &self.api_key {
+            request.headers_mut().insert(API_KEY_HEADER, api_key.clone());
+        }
+        let method = request.method().clone();
+        let span = tracing::debug_span!(
+            "task_queue_request",
+            operation,
+            method = %method,
+            url = %request.url(),
+            status = tracing::field::Empty,
+            retries = tracing::field::Empty,
+        );
+
+        async {
+            let start = Instant::now();
+            let retryable = method == Method::GET;
+            let mut retries = 0;
+
+            let result = loop {
+                let Some(attempt) = request.try_clone() else {
+                    break self.client.execute(request).await;
+                };
+                let result = self.client.execute(attempt).await;
+                let failed = match &result {
+                    Ok(response) => response.status().is_server_error(),
+                    Err(e) => e.is_connect() || e.is_timeout(),
+                };
+                if !(failed && retryable && retries < self.max_retries) {
+                    break result;
+                }
+
+                let delay = self.retry_backoff * 2u32.saturating_pow(retries);
+                retries += 1;
+                debug!("Retrying {} in {:?} (attempt {})", operation, delay, retries);
+                tokio::time::sleep(delay).await;
+            };
+
+            let status = result.as_ref().ok().map(|r| r.status().as_u16());
+            let span = tracing::Span::current();
+            span.record("retries", retries);
+            if let Some(status) = status {
+                span.record("status", status);
+            }
+
+            if let Some(hook) = &self.metrics_hook {
+                hook(&RequestMetrics {
+                    operation,
+                    method: method.clone(),
+                    status,
+                    latency: start.elapsed(),
+                    retries,
+                });
+            }
+
+            Ok(result?)
+        }
+        .instrument(span)
+        .await
+    }
+
+    /// Task collection operations
+    pub fn tasks(&self) -> TasksApi<'_> {
+        TasksApi { client: self }
+    }
+
+    /// Check that the server is reachable
+    pub async fn health_check(&self) -> Result<()> {
+        let response = self
+            .execute("health_check", self.client.get(format!("{}/health", self.api_url)))
             .await?;
-            
+
         if !response.status().is_success() {
             return Err(TaskQueueError::NetworkError(
-                reqwest::Error::from(response.error_for_status().unwrap_err())
+                response.error_for_status().unwrap_err()
             ));
         }
-        
-        Ok(Self {
-            client,
-            base_url: base_url.to_string(),
-        })
+
+        Ok(())
     }
 
     /// Submit a new task
     pub async fn submit_task(&self, task: Task) -> Result<uuid::Uuid> {
         let response = self
-            .client
-            .post(&format!("{}/tasks", self.base_url))
-            .json(&task)
-            .send()
+            .execute(
+                "submit_task",
+                self.client
+                    .post(format!("{}/tasks", self.api_url))
+                    .json(&task),
+            )
             .await?;
 
         if !response.status().is_success() {
             return Err(TaskQueueError::NetworkError(
-                reqwest::Error::from(response.error_for_status().unwrap_err())
+                response.error_for_status().unwrap_err()
             ));
         }
 
@@ -67,9 +380,7 @@ impl TaskQueueClient {
     /// Get task by ID
     pub async fn get_task(&self, task_id: &uuid::Uuid) -> Result<Task> {
         let response = self
-            .client
-            .get(&format!("{}/tasks/{}", self.base_url, task_id))
-            .send()
+            .execute("get_task", self.client.get(format!("{}/tasks/{}", self.api_url, task_id)))
             .await?;
 
         if response.status() == 404 {
@@ -80,7 +391,7 @@ impl TaskQueueClient {
 
         if !response.status().is_success() {
             return Err(TaskQueueError::NetworkError(
-                reqwest::Error::from(response.error_for_status().unwrap_err())
+                response.error_for_status().unwrap_err()
             ));
         }
 
@@ -91,9 +402,7 @@ impl TaskQueueClient {
     /// Get task status
     pub async fn get_task_status(&self, task_id: &uuid::Uuid) -> Result<TaskStatus> {
         let response = self
-            .client
-            .get(&format!("{}/tasks/{}/status", self.base_url, task_id))
-            .send()
+            .execute("get_task_status", self.client.get(format!("{}/tasks/{}/status", self.api_url, task_id)))
             .await?;
 
         if response.status() == 404 {
@@ -104,7 +413,7 @@ impl TaskQueueClient {
 
         if !response.status().is_success() {
             return Err(TaskQueueError::NetworkError(
-                reqwest::Error::from(response.error_for_status().unwrap_err())
+                response.error_for_status().unwrap_err()
             ));
         }
 
@@ -114,8 +423,10 @@ impl TaskQueueClient {
             .ok_or_else(|| TaskQueueError::InternalError("Invalid response format".to_string()))?;
         
         match status_str {
+            "Scheduled" => Ok(TaskStatus::Scheduled),
             "Pending" => Ok(TaskStatus::Pending),
             "Running" => Ok(TaskStatus::Running),
+            "Paused" => Ok(TaskStatus::Paused),
             "Completed" => Ok(TaskStatus::Completed),
             "Failed" => Ok(TaskStatus::Failed),
             "Cancelled" => Ok(TaskStatus::Cancelled),
@@ -127,9 +438,7 @@ impl TaskQueueClient {
     /// Get task result
     pub async fn get_task_result(&self, task_id: &uuid::Uuid) -> Result<Option<TaskResult>> {
         let response = self
-            .client
-            .get(&format!("{}/tasks/{}/result", self.base_url, task_id))
-            .send()
+            .execute("get_task_result", self.client.get(format!("{}/tasks/{}/result", self.api_url, task_id)))
             .await?;
 
         if response.status() == 404 {
@@ -140,7 +449,7 @@ impl TaskQueueClient {
 
         if !response.status().is_success() {
             return Err(TaskQueueError::NetworkError(
-                reqwest::Error::from(response.error_for_status().unwrap_err())
+                response.error_for_status().unwrap_err()
             ));
         }
 
@@ -155,56 +464,119 @@ impl TaskQueueClient {
         }
     }
 
-    /// List tasks with optional filters
+    /// List tasks with optional filters, following every page
     pub async fn list_tasks(
         &self,
         project: Option<String>,
         status: Option<String>,
     ) -> Result<Vec<Task>> {
-        let mut url = format!("{}/tasks", self.base_url);
-        let mut query_params = Vec::new();
-        
+        let filter = TaskListFilter { project, status, ..TaskListFilter::default() };
+        self.tasks().list(filter).try_collect().await
+    }
+
+    /// List lightweight task summaries with optional filters
+    pub async fn list_task_summaries(
+        &self,
+        project: Option<String>,
+        status: Option<String>,
+    ) -> Result<Vec<TaskSummary>> {
+        let mut query = vec![
+            ("view", "summary".to_string()),
+            ("limit", DEFAULT_LIST_PAGE_SIZE.to_string()),
+        ];
         if let Some(project) = project {
-            query_params.push(format!("project={}", project));
+            query.push(("project", project));
         }
-        
         if let Some(status) = status {
-            query_params.push(format!("status={}", status));
+            query.push(("status", status));
         }
-        
-        if !query_params.is_empty() {
-            url.push('?');
-            url.push_str(&query_params.join("&"));
+
+        let mut summaries = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let mut page_query = query.clone();
+            if let Some(cursor) = cursor.take() {
+                page_query.push(("cursor", cursor));
+            }
+            let (page, next_cursor) = self.fetch_task_page("list_task_summaries", &page_query).await?;
+            summaries.extend(page);
+            match next_cursor {
+                Some(next) => cursor = Some(next),
+                None => return Ok(summaries),
+            }
+        }
+    }
+
+    /// Fetch a single page of tasks
+    pub async fn list_tasks_page(
+        &self,
+        filter: &TaskListFilter,
+        cursor: Option<&str>,
+    ) -> Result<(Vec<Task>, Option<String>)> {
+        let mut query = vec![("limit", filter.page_size.to_string())];
+        if let Some(project) = &filter.project {
+            query.push(("project", project.clone()));
+        }
+        if let Some(status) = &filter.status {
+            query.push(("status", status.clone()));
+        }
+        if let Some(cursor) = cursor {
+            query.push(("cursor", cursor.to_string()));
         }
 
+        self.fetch_task_page("list_tasks_page", &query).await
+    }
+
+    /// Fetch one page of `GET /tasks`, returning its items and next cursor
+    async fn fetch_task_page<T: serde::de::DeserializeOwned>(
+        &self,
+        operation: &'static str,
+        query: &[(&str, String)],
+    ) -> Result<(Vec<T>, Option<String>)> {
         let response = self
-            .client
-            .get(&url)
-            .send()
+            .execute(
+                operation,
+                self.client
+                    .get(format!("{}/tasks", self.api_url))
+                    .query(query),
+            )
             .await?;
 
         if !response.status().is_success() {
             return Err(TaskQueueError::NetworkError(
-                reqwest::Error::from(response.error_for_status().unwrap_err())
+                response.error_for_status().unwrap_err()
             ));
         }
 
-        let tasks: Vec<Task> = response.json().await?;
-        Ok(tasks)
+        let page: serde_json::Value = response.json().await?;
+        match page {
+            // Servers without pagination return the whole list at once
+            serde_json::Value::Array(_) => {
+                let tasks = serde_json::from_value(page)?;
+                Ok((tasks, None))
+            }
+            mut page => {
+                let tasks = serde_json::from_value(page["tasks"].take())?;
+                let next_cursor = page["next_cursor"].as_str().map(str::to_string);
+                Ok((tasks, next_cursor))
+            }
+        }
     }
 
     /// Submit a workflow
     pub async fn submit_workflow(&self, workflow: Workflow) -> Result<uuid::Uuid> {
         let response = self
-            .client
-            .post(&format!("{}/workflows", self.base_url))
-            .json(&workflow)
-            .send()
+            .execute(
+                "submit_workflow",
+                self.client
+                    .post(format!("{}/workflows", self.api_url))
+                    .json(&workflow),
+            )
             .await?;
 
         if !response.status().is_success() {
             return Err(TaskQueueError::NetworkError(
-                reqwest::Error::from(response.error_for_status().unwrap_err())
+                response.error_for_status().unwrap_err()
             ));
         }
 
@@ -220,9 +592,7 @@ impl TaskQueueClient {
     /// Get workflow by ID
     pub async fn get_workflow(&self, workflow_id: &uuid::Uuid) -> Result<Workflow> {
         let response = self
-            .client
-            .get(&format!("{}/workflows/{}", self.base_url, workflow_id))
-            .send()
+            .execute("get_workflow", self.client.get(format!("{}/workflows/{}", self.api_url, workflow_id)))
             .await?;
 
         if response.status() == 404 {
@@ -233,7 +603,7 @@ impl TaskQueueClient {
 
         if !response.status().is_success() {
             return Err(TaskQueueError::NetworkError(
-                reqwest::Error::from(response.error_for_status().unwrap_err())
+                response.error_for_status().unwrap_err()
             ));
         }
 
@@ -244,9 +614,7 @@ impl TaskQueueClient {
     /// Get workflow status
     pub async fn get_workflow_status(&self, workflow_id: &uuid::Uuid) -> Result<WorkflowStatus> {
         let response = self
-            .client
-            .get(&format!("{}/workflows/{}/status", self.base_url, workflow_id))
-            .send()
+            .execute("get_workflow_status", self.client.get(format!("{}/workflows/{}/status", self.api_url, workflow_id)))
             .await?;
 
         if response.status() == 404 {
@@ -257,7 +625,7 @@ impl TaskQueueClient {
 
         if !response.status().is_success() {
             return Err(TaskQueueError::NetworkError(
-                reqwest::Error::from(response.error_for_status().unwrap_err())
+                response.error_for_status().unwrap_err()
             ));
         }
 
@@ -279,14 +647,12 @@ impl TaskQueueClient {
     /// Get system metrics
     pub async fn get_metrics(&self) -> Result<serde_json::Value> {
         let response = self
-            .client
-            .get(&format!("{}/metrics", self.base_url))
-            .send()
+            .execute("get_metrics", self.client.get(format!("{}/metrics", self.api_url)))
             .await?;
 
         if !response.status().is_success() {
             return Err(TaskQueueError::NetworkError(
-                reqwest::Error::from(response.error_for_status().unwrap_err())
+                response.error_for_status().unwrap_err()
             ));
         }
 
@@ -368,4 +734,430 @@ impl TaskQueueClient {
             }
         }
     }
+
+    /// Subscribe to task/workflow lifecycle events
+    ///
+    /// The returned stream reconnects automatically and resumes after the
+    /// last event it delivered. It ends when dropped.
+    pub fn subscribe_events(&self, filter: EventFilter) -> impl Stream<Item = Result<TaskEvent>> + Send + 'static {
+        self.subscribe_events_from(filter, None)
+    }
+
+    /// Subscribe to events, resuming after the given sequence cursor
+    pub fn subscribe_events_from(
+        &self,
+        filter: EventFilter,
+        cursor: Option<u64>,
+    ) -> impl Stream<Item = Result<TaskEvent>> + Send + 'static {
+        let (tx, rx) = mpsc::channel(256);
+        let client = self.clone();
+        tokio::spawn(async move {
+            client.run_event_subscription(filter, cursor, tx).await;
+        });
+        ReceiverStream::new(rx)
+    }
+
+    async fn run_event_subscription(
+        self,
+        filter: EventFilter,
+        mut cursor: Option<u64>,
+        tx: mpsc::Sender<Result<TaskEvent>>,
+    ) {
+        let mut backoff = EVENT_RECONNECT_MIN;
+
+        loop {
+            match self.stream_events(&filter, &mut cursor, &tx).await {
+                Ok(()) => backoff = EVENT_RECONNECT_MIN,
+                // Long-lived streams hit the request timeout; just reconnect
+                Err(TaskQueueError::NetworkError(e)) if e.is_timeout() => {
+                    backoff = EVENT_RECONNECT_MIN;
+                }
+                Err(e) => {
+                    warn!("Event subscription error: {}", e);
+                    if tx.send(Err(e)).await.is_err() {
+                        return;
+                    }
+                }
+            }
+
+            if tx.is_closed() {
+                return;
+            }
+            debug!("Reconnecting event subscription in {:?} (cursor {:?})", backoff, cursor);
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(EVENT_RECONNECT_MAX);
+        }
+    }
+
+    /// Read one SSE connection until the server closes it
+    async fn stream_events(
+        &self,
+        filter: &EventFilter,
+        cursor: &mut Option<u64>,
+        tx: &mpsc::Sender<Result<TaskEvent>>,
+    ) -> Result<()> {
+        let mut request = self
+            .client
+            .get(format!("{}/events", self.api_url))
+            .query(&filter.to_query())
+            .header(reqwest::header::ACCEPT, "text/event-stream");
+
+        if let Some(cursor) = cursor {
+            request = request
+                .query(&[("cursor", cursor.to_string())])
+                .header("Last-Event-ID", cursor.to_string());
+        }
+
+        let mut response = self.execute("subscribe_events", request).await?;
+        if !response.status().is_success() {
+            return Err(TaskQueueError::NetworkError(
+                response.error_for_status().unwrap_err()
+            ));
+        }
+
+        let mut parser = SseParser::new();
+        while let Some(chunk) = response.chunk().await? {
+            for frame in parser.feed(&chunk) {
+                let event: TaskEvent = match serde_json::from_str(&frame.data) {
+                    Ok(event) => event,
+                    Err(e) => {
+                        warn!("Skipping malformed event frame: {}", e);
+                        continue;
+                    }
+                };
+
+                *cursor = Some(event.sequence);
+                if tx.send(Ok(event)).await.is_err() {
+                    return Ok(());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Core task queue operations
+///
+/// Implemented by `TaskQueueClient` and by `InMemoryTaskQueue`, so code that
+/// submits tasks can be tested without a running server.
+pub trait TaskQueueApi: Send + Sync {
+    fn submit_task(&self, task: Task) -> impl Future<Output = Result<uuid::Uuid>> + Send;
+
+    fn get_task(&self, task_id: &uuid::Uuid) -> impl Future<Output = Result<Task>> + Send;
+
+    fn get_task_status(&self, task_id: &uuid::Uuid) -> impl Future<Output = Result<TaskStatus>> + Send;
+
+    fn get_task_result(&self, task_id: &uuid::Uuid) -> impl Future<Output = Result<Option<TaskResult>>> + Send;
+
+    fn list_tasks(
+        &self,
+        project: Option<String>,
+        status: Option<String>,
+    ) -> impl Future<Output = Result<Vec<Task>>> + Send;
+
+    fn submit_workflow(&self, workflow: Workflow) -> impl Future<Output = Result<uuid::Uuid>> + Send;
+
+    fn get_workflow(&self, workflow_id: &uuid::Uuid) -> impl Future<Output = Result<Workflow>> + Send;
+
+    fn get_workflow_status(&self, workflow_id: &uuid::Uuid) -> impl Future<Output = Result<WorkflowStatus>> + Send;
+}
+
+impl TaskQueueApi for TaskQueueClient {
+    async fn submit_task(&self, task: Task) -> Result<uuid::Uuid> {
+        TaskQueueClient::submit_task(self, task).await
+    }
+
+    async fn get_task(&self, task_id: &uuid::Uuid) -> Result<Task> {
+        TaskQueueClient::get_task(self, task_id).await
+    }
+
+    async fn get_task_status(&self, task_id: &uuid::Uuid) -> Result<TaskStatus> {
+        TaskQueueClient::get_task_status(self, task_id).await
+    }
+
+    async fn get_task_result(&self, task_id: &uuid::Uuid) -> Result<Option<TaskResult>> {
+        TaskQueueClient::get_task_result(self, task_id).await
+    }
+
+    async fn list_tasks(&self, project: Option<String>, status: Option<String>) -> Result<Vec<Task>> {
+        TaskQueueClient::list_tasks(self, project, status).await
+    }
+
+    async fn submit_workflow(&self, workflow: Workflow) -> Result<uuid::Uuid> {
+        TaskQueueClient::submit_workflow(self, workflow).await
+    }
+
+    async fn get_workflow(&self, workflow_id: &uuid::Uuid) -> Result<Workflow> {
+        TaskQueueClient::get_workflow(self, workflow_id).await
+    }
+
+    async fn get_workflow_status(&self, workflow_id: &uuid::Uuid) -> Result<WorkflowStatus> {
+        TaskQueueClient::get_workflow_status(self, workflow_id).await
+    }
+}
+
+/// Filter for streaming task listings
+#[derive(Debug, Clone)]
+pub struct TaskListFilter {
+    pub project: Option<String>,
+    pub status: Option<String>,
+    /// Number of tasks requested per page
+    pub page_size: usize,
+}
+
+impl Default for TaskListFilter {
+    fn default() -> Self {
+        Self {
+            project: None,
+            status: None,
+            page_size: DEFAULT_LIST_PAGE_SIZE,
+        }
+    }
+}
+
+impl TaskListFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_project(mut self, project: impl Into<String>) -> Self {
+        self.project = Some(project.into());
+        self
+    }
+
+    pub fn with_status(mut self, status: impl Into<String>) -> Self {
+        self.status = Some(status.into());
+        self
+    }
+
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+}
+
+/// Task collection operations, obtained from `TaskQueueClient::tasks`
+pub struct TasksApi<'a> {
+    client: &'a TaskQueueClient,
+}
+
+/// Pagination state carried between pages
+struct TaskPager {
+    client: TaskQueueClient,
+    filter: TaskListFilter,
+    buffer: VecDeque<Task>,
+    cursor: Option<String>,
+    exhausted: bool,
+}
+
+impl TasksApi<'_> {
+    /// Stream every task matching the filter, fetching pages lazily
+    pub fn list(&self, filter: TaskListFilter) -> impl Stream<Item = Result<Task>> + Send + 'static {
+        let pager = TaskPager {
+            client: self.client.clone(),
+            filter,
+            buffer: VecDeque::new(),
+            cursor: None,
+            exhausted: false,
+        };
+
+        futures_util::stream::try_unfold(pager, |mut pager| async move {
+            loop {
+                if let Some(task) = pager.buffer.pop_front() {
+                    return Ok(Some((task, pager)));
+                }
+                if pager.exhausted {
+                    return Ok(None);
+                }
+
+                let (tasks, next_cursor) = pager
+                    .client
+                    .list_tasks_page(&pager.filter, pager.cursor.as_deref())
+                    .await?;
+                pager.exhausted = next_cursor.is_none();
+                pager.cursor = next_cursor;
+                pager.buffer.extend(tasks);
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::Query, routing::get, Json, Router};
+    use futures_util::TryStreamExt;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Mutex;
+
+    /// Serve `app` on a local port and return its base URL
+    async fn serve(app: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}", addr)
+    }
+
+    fn named_tasks(count: usize) -> Vec<Task> {
+        (0..count).map(|i| Task::new(&format!("task-{}", i)).build()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_requests_are_retried_and_reported() {
+        let hits = Arc::new(AtomicU32::new(0));
+        let status_hits = hits.clone();
+        let app = Router::new()
+            .route("/api/v1/tasks/{id}/status", get(move || {
+                let attempt = status_hits.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if attempt < 2 {
+                        Err(axum::http::StatusCode::SERVICE_UNAVAILABLE)
+                    } else {
+                        Ok(Json(json!({"status": "Completed"})))
+                    }
+                }
+            }))
+            .route("/api/v1/tasks", axum::routing::post(|| async { axum::http::StatusCode::SERVICE_UNAVAILABLE }));
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let sink = reported.clone();
+        let client = TaskQueueClient::builder(&serve(app).await)
+            .with_retries(3, Duration::from_millis(1))
+            .with_metrics_hook(move |metrics| sink.lock().unwrap().push(metrics.clone()))
+            .build_unchecked()
+            .unwrap();
+
+        assert_eq!(client.get_task_status(&uuid::Uuid::new_v4()).await.unwrap(), TaskStatus::Completed);
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+
+        // POST is not idempotent, so it is sent exactly once
+        assert!(client.submit_task(Task::new("once").build()).await.is_err());
+
+        let reported = reported.lock().unwrap();
+        assert_eq!(reported.len(), 2);
+        assert_eq!((reported[0].operation, reported[0].status, reported[0].retries), ("get_task_status", Some(200), 2));
+        assert_eq!(reported[0].method, Method::GET);
+        assert_eq!((reported[1].operation, reported[1].status, reported[1].retries), ("submit_task", Some(503), 0));
+    }
+
+    #[tokio::test]
+    async fn test_api_key_is_sent_with_every_request() {
+        use crate::config::ServerConfig;
+        use crate::server::TaskQueueServer;
+        use crate::storage::StorageEngine;
+        use crate::tenants::TenancyConfig;
+        use crate::vectorizer::VectorizerIntegration;
+
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap()
+        .with_server_config(ServerConfig {
+            tenancy: TenancyConfig { required: true, admin_key: Some("operator".to_string()), ..Default::default() },
+            ..ServerConfig::default()
+        });
+        let url = serve(server.rest_router()).await;
+
+        let anonymous = TaskQueueClient::builder(&url).build_unchecked().unwrap();
+        assert!(anonymous.get_metrics().await.is_err());
+        let operator = TaskQueueClient::builder(&url).with_api_key("operator").build_unchecked().unwrap();
+        operator.get_metrics().await.unwrap();
+        assert!(TaskQueueClient::builder(&url).with_api_key("bad\nkey").build_unchecked().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_retries_stop_at_the_configured_limit() {
+        let app = Router::new().route("/api/v1/metrics", get(|| async { axum::http::StatusCode::BAD_GATEWAY }));
+        let retries = Arc::new(Mutex::new(None));
+        let sink = retries.clone();
+        let client = TaskQueueClient::builder(&serve(app).await)
+            .with_retries(1, Duration::from_millis(1))
+            .with_metrics_hook(move |metrics| *sink.lock().unwrap() = Some(metrics.retries))
+            .build_unchecked()
+            .unwrap();
+
+        assert!(matches!(client.get_metrics().await, Err(TaskQueueError::NetworkError(_))));
+        assert_eq!(*retries.lock().unwrap(), Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_list_follows_pagination_cursors() {
+        let tasks = named_tasks(5);
+        let pages = tasks.clone();
+        let app = Router::new().route("/api/v1/tasks", get(move |Query(query): Query<HashMap<String, String>>| {
+            let pages = pages.clone();
+            async move {
+                assert_eq!(query.get("project").map(String::as_str), Some("alpha"));
+                let limit: usize = query["limit"].parse().unwrap();
+                let start: usize = query.get("cursor").map_or(0, |c| c.parse().unwrap());
+                let end = (start + limit).min(pages.len());
+                let next_cursor = (end < pages.len()).then(|| end.to_string());
+                Json(json!({ "tasks": pages[start..end], "next_cursor": next_cursor }))
+            }
+        }));
+        let client = TaskQueueClient::builder(&serve(app).await).build_unchecked().unwrap();
+        let filter = TaskListFilter::new().with_project("alpha").with_page_size(2);
+
+        let (first, cursor) = client.list_tasks_page(&filter, None).await.unwrap();
+        assert_eq!(first.len(), 2);
+        assert_eq!(cursor.as_deref(), Some("2"));
+
+        let streamed: Vec<Task> = client.tasks().list(filter).try_collect().await.unwrap();
+        let names: Vec<&str> = streamed.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, tasks.iter().map(|t| t.name.as_str()).collect::<Vec<_>>());
+
+        let listed = client.list_tasks(Some("alpha".to_string()), None).await.unwrap();
+        assert_eq!(listed.len(), tasks.len());
+    }
+
+    #[tokio::test]
+    async fn test_list_accepts_unpaginated_servers() {
+        let tasks = named_tasks(3);
+        let all = tasks.clone();
+        let app = Router::new().route("/api/v1/tasks", get(move || async move { Json(all) }));
+        let client = TaskQueueClient::builder(&serve(app).await).build_unchecked().unwrap();
+
+        let (page, cursor) = client.list_tasks_page(&TaskListFilter::new(), None).await.unwrap();
+        assert_eq!((page.len(), cursor), (3, None));
+
+        let streamed: Vec<Task> = client.tasks().list(TaskListFilter::new().with_page_size(1)).try_collect().await.unwrap();
+        assert_eq!(streamed.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_list_stream_surfaces_errors() {
+        let app = Router::new().route("/api/v1/tasks", get(|| async { axum::http::StatusCode::BAD_REQUEST }));
+        let client = TaskQueueClient::builder(&serve(app).await).build_unchecked().unwrap();
+
+        let result: Result<Vec<Task>> = client.tasks().list(TaskListFilter::new()).try_collect().await;
+        assert!(matches!(result, Err(TaskQueueError::NetworkError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_event_subscription_resumes_after_the_last_event() {
+        use futures_util::StreamExt;
+
+        // Each connection sends the event after the cursor, then closes
+        let cursors = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = cursors.clone();
+        let app = Router::new().route("/api/v1/events", get(move |axum::extract::Query(query): axum::extract::Query<HashMap<String, String>>| {
+            let cursor = query.get("cursor").cloned();
+            seen.lock().unwrap().push(cursor.clone());
+            let sequence = cursor.map_or(1, |cursor| cursor.parse::<u64>().unwrap() + 1);
+            async move {
+                format!(
+                    "id: {0}\ndata: {{\"sequence\":{0},\"kind\":\"TaskCreated\",\"task_id\":null,\"workflow_id\":null,\"project_id\":null,\"status\":null,\"timestamp\":\"2026-01-01T00:00:00Z\"}}\n\n",
+                    sequence
+                )
+            }
+        }));
+        let client = TaskQueueClient::builder(&serve(app).await).build_unchecked().unwrap();
+
+        let filter = EventFilter::all().with_kind(crate::events::TaskEventKind::TaskCreated);
+        let sequences: Vec<u64> = client.subscribe_events(filter).take(2).map(|event| event.unwrap().sequence).collect().await;
+        assert_eq!(sequences, vec![1, 2]);
+        assert_eq!(*cursors.lock().unwrap(), vec![None, Some("1".to_string())]);
+    }
 }
@@ -0,0 +1,116 @@
+//! Storage Codec Module
+//!
+//! Encodes records for the storage layer. Records are either plain JSON (the
+//! original format) or a versioned binary envelope around MessagePack, which
+//! is markedly smaller for tasks carrying phases and reviews and faster to
+//! decode on startup. Decoding detects the format, so both can coexist in one
+//! database while it is being migrated. JSON stays the default: storage only
+//! writes binary once `migrate-codec` converted it, or when `storage.codec`
+//! asks for it.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::error::{TaskQueueError, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// First byte of a binary record; never the first byte of JSON or MessagePack
+pub const BINARY_MAGIC: u8 = 0xC1;
+/// Version of the binary envelope written by this build
+pub const BINARY_VERSION: u8 = 1;
+
+/// Format used for newly written records
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Codec {
+    #[default]
+    Json,
+    /// MessagePack with named fields, so records survive added or reordered
+    /// struct fields. Bincode and postcard are not self-describing and cannot
+    /// hold the free-form `serde_json::Value` metadata tasks carry.
+    Binary,
+}
+
+impl Codec {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "binary" | "msgpack" => Some(Self::Binary),
+            _ => None,
+        }
+    }
+
+    /// Format a stored record was written in
+    pub fn detect(bytes: &[u8]) -> Self {
+        match bytes.first() {
+            Some(&BINARY_MAGIC) => Self::Binary,
+            _ => Self::Json,
+        }
+    }
+
+    pub fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>> {
+        match self {
+            Self::Json => Ok(serde_json::to_vec(value)?),
+            Self::Binary => {
+                let mut bytes = vec![BINARY_MAGIC, BINARY_VERSION];
+                rmp_serde::encode::write_named(&mut bytes, value)
+                    .map_err(|e| TaskQueueError::CodecError(e.to_string()))?;
+                Ok(bytes)
+            }
+        }
+    }
+
+    /// Decode a record in any supported format
+    pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+        match Self::detect(bytes) {
+            Self::Json => Ok(serde_json::from_slice(bytes)?),
+            Self::Binary => match bytes.get(1) {
+                Some(&BINARY_VERSION) => rmp_serde::from_slice(&bytes[2..])
+                    .map_err(|e| TaskQueueError::CodecError(e.to_string())),
+                Some(version) => Err(TaskQueueError::CodecError(format!(
+                    "Unsupported binary record version {}",
+                    version
+                ))),
+                None => Err(TaskQueueError::CodecError("Truncated binary record".to_string())),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_binary_round_trip_is_smaller_and_reads_json() {
+        let mut task = Task::new("Build").with_command("cargo build").build();
+        task.metadata.insert("tags".to_string(), json!(["ci", "rust"]));
+        task.metadata.insert("extra".to_string(), json!({ "nested": [1, 2.5, null, true] }));
+
+        let binary = Codec::Binary.encode(&task).unwrap();
+        let text = Codec::Json.encode(&task).unwrap();
+        assert_eq!(Codec::detect(&binary), Codec::Binary);
+        assert_eq!(Codec::detect(&text), Codec::Json);
+        assert!(binary.len() < text.len());
+
+        for bytes in [&binary, &text] {
+            let decoded: Task = Codec::decode(bytes).unwrap();
+            assert_eq!(decoded.id, task.id);
+            assert_eq!(decoded.metadata, task.metadata);
+            assert_eq!(serde_json::to_value(&decoded).unwrap(), serde_json::to_value(&task).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_unknown_binary_version_is_rejected() {
+        let mut bytes = Codec::Binary.encode(&json!({ "a": 1 })).unwrap();
+        bytes[1] = BINARY_VERSION + 1;
+        assert!(Codec::decode::<serde_json::Value>(&bytes).is_err());
+    }
+}
@@ -0,0 +1,169 @@
+//! Cold Storage Module
+//!
+//! Completed tasks are rarely read again, yet every listing, index lookup and
+//! in-memory map keeps paying for them. When `storage.cold_storage.enabled`
+//! is set, a background job moves `Completed` tasks last updated more than
+//! `after_days` ago into a cold tier of their own, stored compressed. They no
+//! longer show up in listings or filters, but `GET /tasks/{id}` still finds
+//! them, and their revisions, logs and artifacts are kept as they were. Tasks
+//! that an unfinished task still depends on stay where they are.
+//! `POST /admin/cold-storage?dry_run=true` reports what would be moved, and
+//! without `dry_run` moves it on demand. Retention never reaches the cold
+//! tier, so `retention_days` should be the longer of the two thresholds.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::*;
+use crate::error::Result;
+use crate::retention;
+use crate::server::TaskQueueServer;
+use chrono::{DateTime, Utc};
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tracing::{error, info};
+use uuid::Uuid;
+
+/// The background cold storage job
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColdStorageConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Days since a completed task last changed before it moves
+    #[serde(default = "default_after_days")]
+    pub after_days: u32,
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+}
+
+fn default_after_days() -> u32 {
+    90
+}
+
+fn default_interval_secs() -> u64 {
+    3600
+}
+
+impl Default for ColdStorageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            after_days: default_after_days(),
+            interval_secs: default_interval_secs(),
+        }
+    }
+}
+
+impl ColdStorageConfig {
+    pub fn threshold(&self) -> Duration {
+        Duration::from_secs(u64::from(self.after_days) * 24 * 3600)
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs.max(1))
+    }
+}
+
+/// A task moved, or due to move, to cold storage
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColdTask {
+    pub task_id: Uuid,
+    pub name: String,
+    pub project_id: Option<Uuid>,
+    /// When the task last changed
+    pub completed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ColdStorageReport {
+    pub dry_run: bool,
+    pub tasks: Vec<ColdTask>,
+}
+
+/// Completed tasks that changed at least `threshold` before `now`
+pub fn candidates(tasks: &[Task], threshold: Duration, now: SystemTime) -> Vec<ColdTask> {
+    let needed = retention::needed(tasks);
+    tasks.iter()
+        .filter(|task| task.status == TaskStatus::Completed && !needed.contains(&task.id))
+        .filter(|task| now.duration_since(task.updated_at).unwrap_or_default() >= threshold)
+        .map(|task| ColdTask {
+            task_id: task.id,
+            name: task.name.clone(),
+            project_id: task.project_id,
+            completed_at: task.updated_at.into(),
+        })
+        .collect()
+}
+
+/// Deflate an encoded record for the cold tier
+pub fn compress(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+    let mut decoded = Vec::new();
+    DeflateDecoder::new(data).read_to_end(&mut decoded)?;
+    Ok(decoded)
+}
+
+/// Move old completed tasks to cold storage every `interval_secs` in the background
+pub fn spawn(server: Arc<TaskQueueServer>, config: ColdStorageConfig) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(config.interval());
+        loop {
+            ticker.tick().await;
+            match server.move_to_cold_storage(SystemTime::now(), false).await {
+                Ok(report) if !report.tasks.is_empty() => {
+                    info!("Moved {} completed tasks to cold storage", report.tasks.len())
+                }
+                Ok(_) => {}
+                Err(e) => error!("Failed to move tasks to cold storage: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_old_completed_tasks_go_cold() {
+        let now = SystemTime::now();
+        let task = |name: &str, status: TaskStatus, age_days: u64| {
+            let mut task = Task::new(name).with_command("true").build();
+            task.status = status;
+            task.updated_at = now - Duration::from_secs(age_days * 24 * 3600);
+            task
+        };
+        let old = task("old", TaskStatus::Completed, 100);
+        let needed = task("needed", TaskStatus::Completed, 100);
+        let mut waiting = task("waiting", TaskStatus::Pending, 100);
+        waiting.add_dependency(needed.id, None, DependencyCondition::Success, true);
+        let tasks = vec![
+            old.clone(),
+            needed,
+            waiting,
+            task("recent", TaskStatus::Completed, 10),
+            task("cancelled", TaskStatus::Cancelled, 100),
+        ];
+
+        let cold = candidates(&tasks, ColdStorageConfig::default().threshold(), now);
+        assert_eq!(cold.iter().map(|t| t.task_id).collect::<Vec<_>>(), [old.id]);
+
+        let record = serde_json::to_vec(&old).unwrap().repeat(4);
+        let compressed = compress(&record).unwrap();
+        assert!(compressed.len() < record.len());
+        assert_eq!(decompress(&compressed).unwrap(), record);
+    }
+}
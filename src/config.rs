@@ -5,34 +5,294 @@
 #![allow(dead_code)]
 #![allow(unused_mut)]
 
+use crate::artifacts::ArtifactConfig;
+use crate::blobs::BlobStoreConfig;
+use crate::codec::Codec;
+use crate::cold_storage::ColdStorageConfig;
+use crate::resources::Resources;
+use crate::retention::RetentionConfig;
+use crate::vacuum::VacuumConfig;
+use crate::write_behind::WriteBehindConfig;
+use crate::idempotency::IdempotencyConfig;
+use crate::rate_limiting::HttpRateLimitConfig;
+use crate::tenants::TenancyConfig;
+use crate::workspaces::WorkspaceConfig;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
+use std::sync::OnceLock;
+use tower_http::compression::predicate::{And, DefaultPredicate, Predicate, SizeAbove};
+use tower_http::compression::CompressionLayer;
+use tower_http::cors::{AllowOrigin, CorsLayer};
 
-/// Task queue server configuration
+/// Read from the working directory when no `--config` is given
+pub const DEFAULT_CONFIG_FILE: &str = "task-queue.toml";
+
+/// The configuration `main` loaded, for code that can't be handed it
+static INSTALLED: OnceLock<Config> = OnceLock::new();
+
+/// Task queue server configuration; sections and fields left out of a
+/// file take their defaults
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     pub server: ServerConfig,
     pub storage: StorageConfig,
     pub vectorizer: VectorizerConfig,
     pub execution: ExecutionConfig,
     pub monitoring: MonitoringConfig,
+    #[serde(default)]
+    pub events: EventsConfig,
+    /// Consume task submissions from a message queue
+    #[serde(default)]
+    pub ingest: Option<IngestConfig>,
 }
 
 /// Server configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
     pub grpc_port: u16,
     pub mcp_port: u16,
+    /// MCP transports served next to the REST API
+    #[serde(default)]
+    pub mcp_transport: McpTransport,
+    /// Keep no task state in memory; read and write through storage
+    #[serde(default)]
+    pub stateless: bool,
+    /// How long stateless replicas may serve a cached read
+    #[serde(default = "default_cache_ttl_ms")]
+    pub cache_ttl_ms: u64,
+    /// Replaying responses to requests retried with an `Idempotency-Key`
+    #[serde(default)]
+    pub idempotency: IdempotencyConfig,
+    /// Requests each client may make per route group
+    #[serde(default)]
+    pub rate_limits: HttpRateLimitConfig,
+    /// Tenants sharing the server and the operator's key
+    #[serde(default)]
+    pub tenancy: TenancyConfig,
+    #[serde(default)]
+    pub cors: CorsConfig,
+    /// Largest request bodies accepted
+    #[serde(default)]
+    pub limits: RequestLimits,
+    #[serde(default)]
+    pub compression: CompressionConfig,
+    /// Static files served under `/dashboard`
+    #[serde(default = "default_dashboard_dir")]
+    pub dashboard_dir: String,
+    /// Where logs are written besides the terminal; `None` for the terminal only
+    #[serde(default = "default_log_file")]
+    pub log_file: Option<String>,
+}
+
+fn default_cache_ttl_ms() -> u64 {
+    1000
+}
+
+fn default_dashboard_dir() -> String {
+    "dashboard/public".to_string()
+}
+
+fn default_log_file() -> Option<String> {
+    Some("logs/task-queue.log".to_string())
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: "0.0.0.0".to_string(),
+            port: 16080,
+            grpc_port: 16081,
+            mcp_port: 16082,
+            mcp_transport: McpTransport::default(),
+            stateless: false,
+            cache_ttl_ms: default_cache_ttl_ms(),
+            idempotency: IdempotencyConfig::default(),
+            rate_limits: HttpRateLimitConfig::default(),
+            tenancy: TenancyConfig::default(),
+            cors: CorsConfig::default(),
+            limits: RequestLimits::default(),
+            compression: CompressionConfig::default(),
+            dashboard_dir: default_dashboard_dir(),
+            log_file: default_log_file(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Address the REST API and MCP endpoints listen on
+    pub fn bind_address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    /// Address the gRPC API listens on, with the `grpc` feature
+    pub fn grpc_address(&self) -> String {
+        format!("{}:{}", self.host, self.grpc_port)
+    }
+}
+
+/// Browser origins allowed to call the API
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CorsConfig {
+    /// Origins such as `https://tasks.example.com`; any origin when empty
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+}
+
+impl CorsConfig {
+    pub fn layer(&self) -> CorsLayer {
+        if self.allowed_origins.is_empty() {
+            return CorsLayer::permissive();
+        }
+        let origins: Vec<_> = self.allowed_origins.iter().filter_map(|origin| origin.parse().ok()).collect();
+        CorsLayer::permissive().allow_origin(AllowOrigin::list(origins))
+    }
+}
+
+/// Largest request bodies accepted, in bytes; larger ones are answered
+/// `413 Payload Too Large`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RequestLimits {
+    pub max_body_bytes: usize,
+    /// Workflows, task batches, bulk updates and imports
+    pub max_bulk_bytes: usize,
+    /// Backup archives sent to `POST /admin/restore`
+    pub max_restore_bytes: usize,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: 1024 * 1024,
+            max_bulk_bytes: 16 * 1024 * 1024,
+            max_restore_bytes: 1024 * 1024 * 1024,
+        }
+    }
+}
+
+/// How MCP clients connect: the older SSE transport at `/mcp/sse` and
+/// `/mcp/message`, the streamable HTTP one at `/mcp`, or both
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum McpTransport {
+    Sse,
+    StreamableHttp,
+    #[default]
+    Both,
+}
+
+impl McpTransport {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().replace('-', "_").as_str() {
+            "sse" => Some(Self::Sse),
+            "streamable_http" => Some(Self::StreamableHttp),
+            "both" => Some(Self::Both),
+            _ => None,
+        }
+    }
+
+    pub fn serves_sse(self) -> bool {
+        self != Self::StreamableHttp
+    }
+
+    pub fn serves_streamable_http(self) -> bool {
+        self != Self::Sse
+    }
+}
+
+/// gzip and Brotli compression of responses, for clients that accept it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompressionConfig {
+    pub enabled: bool,
+    /// Smaller responses are sent as they are
+    pub min_size_bytes: u16,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_size_bytes: 1024,
+        }
+    }
+}
+
+impl CompressionConfig {
+    pub fn layer(&self) -> CompressionLayer<And<DefaultPredicate, SizeAbove>> {
+        CompressionLayer::new().compress_when(DefaultPredicate::new().and(SizeAbove::new(self.min_size_bytes)))
+    }
 }
 
 /// Storage configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct StorageConfig {
+    /// Embedded database file; relative paths start at the working directory
     pub database_path: String,
     pub backup_interval: String,
     pub retention_days: u32,
+    /// Format for newly written records; unset keeps the format the storage
+    /// was last migrated to, or JSON
+    #[serde(default)]
+    pub codec: Option<Codec>,
+    #[serde(default)]
+    pub backend: StorageBackend,
+    /// Background job removing finished tasks older than `retention_days`
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    /// Background job moving old completed tasks to compressed cold storage
+    #[serde(default)]
+    pub cold_storage: ColdStorageConfig,
+    /// Background job removing data left behind by deleted tasks
+    #[serde(default)]
+    pub vacuum: VacuumConfig,
+    /// Batch lease renewals instead of writing each one
+    #[serde(default)]
+    pub write_behind: WriteBehindConfig,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            database_path: "task-queue-data/task-queue.db".to_string(),
+            backup_interval: "1h".to_string(),
+            retention_days: 30,
+            codec: None,
+            backend: StorageBackend::default(),
+            retention: RetentionConfig::default(),
+            cold_storage: ColdStorageConfig::default(),
+            vacuum: VacuumConfig::default(),
+            write_behind: WriteBehindConfig::default(),
+        }
+    }
+}
+
+/// Where records are stored
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// Embedded database at `database_path`
+    #[default]
+    Sled,
+    /// Needs the `redis` feature; durability is up to the Redis server
+    Redis {
+        url: String,
+        /// Prepended to every key, so servers can share a Redis
+        #[serde(default = "default_redis_prefix")]
+        prefix: String,
+    },
+    /// Needs the `sqlite` feature; the schema is migrated on startup
+    Sqlite { path: String },
+}
+
+pub fn default_redis_prefix() -> String {
+    "task-queue".to_string()
 }
 
 /// Vectorizer configuration
@@ -50,6 +310,230 @@ pub struct ExecutionConfig {
     pub default_timeout: String,
     pub retry_attempts: u32,
     pub retry_delay: String,
+    /// Execution backends besides the built-in shell executor
+    #[serde(default)]
+    pub executors: Vec<ExecutorConfig>,
+    /// Pick up `Pending` tasks and run their commands on this machine; off
+    /// unless asked for, and only allowed when callers must authenticate
+    #[serde(default)]
+    pub enabled: bool,
+    /// How often to look for runnable tasks
+    #[serde(default = "default_execution_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    /// Running tasks without a heartbeat for this long are reaped
+    #[serde(default = "default_stale_after_secs")]
+    pub stale_after_secs: u64,
+    /// CPU and memory shared by the resource requests of running tasks;
+    /// the machine's own if unset
+    #[serde(default)]
+    pub capacity: Option<Resources>,
+    /// Files kept from the working directory of tasks that succeed
+    #[serde(default)]
+    pub artifacts: ArtifactConfig,
+    /// Per-run directories of tasks without a working directory
+    #[serde(default)]
+    pub workspaces: WorkspaceConfig,
+    /// Hosts HTTP tasks may send secrets to in their request headers; none
+    /// unless listed
+    #[serde(default)]
+    pub secret_hosts: Vec<String>,
+}
+
+fn default_execution_poll_interval_ms() -> u64 {
+    500
+}
+
+fn default_stale_after_secs() -> u64 {
+    60
+}
+
+/// Execution backend declared in configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ExecutorConfig {
+    /// POST tasks to a remote worker
+    Http { name: String, url: String },
+    /// Pipe tasks through an external program
+    Plugin {
+        name: String,
+        program: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+    /// Docker containers through the `docker` CLI
+    Docker {
+        /// Docker-compatible CLI to run
+        #[serde(default = "default_docker_program")]
+        program: String,
+        /// Image and limits for tasks that don't set their own
+        #[serde(flatten)]
+        defaults: crate::docker::ContainerSpec,
+    },
+    /// Shell commands under resource limits and without network unless allowed
+    /// (Linux and Windows)
+    Sandbox {
+        #[serde(flatten)]
+        limits: crate::sandbox::ResourceLimits,
+        /// Delegated cgroup v2 directory for per-task cgroups (Linux)
+        #[serde(default)]
+        cgroup_root: Option<String>,
+    },
+    /// WASI sandbox; needs the `wasm` feature
+    Wasm {
+        /// Host directories tasks may mount, read-only unless marked writable
+        #[serde(default)]
+        allowed_dirs: Vec<WasmDirConfig>,
+        #[serde(default)]
+        allow_network: bool,
+    },
+}
+
+fn default_docker_program() -> String {
+    "docker".to_string()
+}
+
+/// Host directory a WASM sandbox may expose
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WasmDirConfig {
+    pub host: String,
+    #[serde(default)]
+    pub writable: bool,
+}
+
+/// Event delivery configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventsConfig {
+    /// Mirror events to a message broker
+    pub publisher: Option<PublisherConfig>,
+}
+
+/// Broker events are published to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum BrokerConfig {
+    /// Needs the `kafka` feature
+    Kafka { brokers: Vec<String> },
+    /// Needs the `nats` feature
+    Nats {
+        url: String,
+        /// Wait for JetStream acknowledgements instead of fire-and-forget publishes
+        #[serde(default)]
+        jetstream: bool,
+    },
+}
+
+/// Event publisher configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublisherConfig {
+    pub broker: BrokerConfig,
+    /// Topic for kinds not listed in `topics`; `{kind}` is replaced by the event kind
+    #[serde(default = "default_event_topic")]
+    pub default_topic: String,
+    /// Topic per event kind, e.g. `task_completed: analytics.completions`
+    #[serde(default)]
+    pub topics: HashMap<String, String>,
+    #[serde(default = "default_publish_batch_size")]
+    pub batch_size: usize,
+    /// How often the outbox is checked when no new events arrive
+    #[serde(default = "default_publish_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+}
+
+fn default_event_topic() -> String {
+    "task-queue.{kind}".to_string()
+}
+
+fn default_publish_batch_size() -> usize {
+    100
+}
+
+fn default_publish_poll_interval_ms() -> u64 {
+    500
+}
+
+impl PublisherConfig {
+    pub fn new(broker: BrokerConfig) -> Self {
+        Self {
+            broker,
+            default_topic: default_event_topic(),
+            topics: HashMap::new(),
+            batch_size: default_publish_batch_size(),
+            poll_interval_ms: default_publish_poll_interval_ms(),
+        }
+    }
+
+    /// Topic an event kind is published to
+    pub fn topic_for(&self, kind: &str) -> String {
+        match self.topics.get(kind) {
+            Some(topic) => topic.clone(),
+            None => self.default_topic.replace("{kind}", kind),
+        }
+    }
+}
+
+/// Message queue tasks are submitted through; messages carry the same JSON
+/// body as `POST /tasks`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum IngestConfig {
+    /// Needs the `amqp` feature
+    Amqp {
+        url: String,
+        queue: String,
+        /// Unacknowledged messages held at once
+        #[serde(default = "default_ingest_prefetch")]
+        prefetch: u16,
+    },
+    /// JetStream pull consumer; needs the `nats` feature
+    Nats {
+        url: String,
+        stream: String,
+        #[serde(default = "default_ingest_consumer")]
+        consumer: String,
+    },
+    /// Needs the `sqs` feature; credentials come from the AWS environment
+    Sqs { queue_url: String },
+    /// Redis stream read through a consumer group; needs the `redis` feature
+    Redis {
+        url: String,
+        stream: String,
+        #[serde(default = "default_ingest_consumer")]
+        group: String,
+    },
+}
+
+fn default_ingest_prefetch() -> u16 {
+    16
+}
+
+fn default_ingest_consumer() -> String {
+    "task-queue".to_string()
+}
+
+impl IngestConfig {
+    /// Pick the broker from the URL scheme; `queue` names the AMQP queue, JetStream stream or Redis stream
+    pub fn from_url(url: &str, queue: Option<String>) -> Option<Self> {
+        let scheme = url.split("://").next()?;
+        match scheme {
+            "amqp" | "amqps" => Some(Self::Amqp {
+                url: url.to_string(),
+                queue: queue?,
+                prefetch: default_ingest_prefetch(),
+            }),
+            "nats" | "tls" => Some(Self::Nats {
+                url: url.to_string(),
+                stream: queue?,
+                consumer: default_ingest_consumer(),
+            }),
+            "https" | "http" => Some(Self::Sqs { queue_url: url.to_string() }),
+            "redis" | "rediss" => Some(Self::Redis {
+                url: url.to_string(),
+                stream: queue?,
+                group: default_ingest_consumer(),
+            }),
+            _ => None,
+        }
+    }
 }
 
 /// Monitoring configuration
@@ -63,17 +547,8 @@ pub struct MonitoringConfig {
 impl Default for Config {
     fn default() -> Self {
         Self {
-            server: ServerConfig {
-                host: "0.0.0.0".to_string(),
-                port: 16080,
-                grpc_port: 16081,
-                mcp_port: 16082,
-            },
-            storage: StorageConfig {
-                database_path: "./data/task-queue.db".to_string(),
-                backup_interval: "1h".to_string(),
-                retention_days: 30,
-            },
+            server: ServerConfig::default(),
+            storage: StorageConfig::default(),
             vectorizer: VectorizerConfig {
                 endpoint: "http://localhost:15002".to_string(),
                 collection: "task-interactions".to_string(),
@@ -84,24 +559,73 @@ impl Default for Config {
                 default_timeout: "5m".to_string(),
                 retry_attempts: 3,
                 retry_delay: "1s".to_string(),
+                executors: Vec::new(),
+                enabled: false,
+                poll_interval_ms: default_execution_poll_interval_ms(),
+                stale_after_secs: default_stale_after_secs(),
+                capacity: None,
+                artifacts: ArtifactConfig::default(),
+                workspaces: WorkspaceConfig::default(),
+                secret_hosts: Vec::new(),
             },
             monitoring: MonitoringConfig {
                 metrics_enabled: true,
                 metrics_port: 9090,
                 health_check_interval: "30s".to_string(),
             },
+            events: EventsConfig::default(),
+            ingest: None,
         }
     }
 }
 
 impl Config {
-    /// Load configuration from file
+    /// Load configuration from file, TOML or, for any other extension, YAML
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
-        let content = std::fs::read_to_string(path)?;
-        let config: Config = serde_yaml::from_str(&content)?;
+        let content = std::fs::read_to_string(&path)?;
+        let config: Config = match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some("toml") => toml::from_str(&content)?,
+            _ => serde_yaml::from_str(&content)?,
+        };
+        Ok(config)
+    }
+
+    /// Load `path`, or `task-queue.toml` if there is one, then apply the
+    /// environment variables over it
+    pub fn load(path: Option<&Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut config = match path {
+            Some(path) => Self::from_file(path).map_err(|e| format!("Failed to load {}: {}", path.display(), e))?,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => Self::from_file(DEFAULT_CONFIG_FILE)
+                .map_err(|e| format!("Failed to load {}: {}", DEFAULT_CONFIG_FILE, e))?,
+            None => Self::default(),
+        };
+        config.apply_env();
         Ok(config)
     }
 
+    /// Refuse to run task commands while anyone who can reach the API could
+    /// submit them: unless keys are required, requests without a key act as
+    /// the operator, even with an admin key set
+    pub fn check_execution(&self) -> Result<(), String> {
+        if self.execution.enabled && !self.server.tenancy.required {
+            return Err(
+                "execution.enabled runs task commands on this machine; set server.tenancy.required first".to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Make this the configuration returned by [`Config::installed`]; only
+    /// the first call has any effect
+    pub fn install(&self) {
+        let _ = INSTALLED.set(self.clone());
+    }
+
+    /// The configuration `main` installed, or the environment's
+    pub fn installed() -> Self {
+        INSTALLED.get().cloned().unwrap_or_else(Self::from_env)
+    }
+
     /// Save configuration to file
     pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn std::error::Error>> {
         let content = serde_yaml::to_string(self)?;
@@ -112,33 +636,168 @@ impl Config {
     /// Load configuration from environment variables
     pub fn from_env() -> Self {
         let mut config = Self::default();
+        config.apply_env();
+        config
+    }
+
+    /// Override settings with the environment variables that are set
+    pub fn apply_env(&mut self) {
+        let config = self;
 
         if let Ok(host) = std::env::var("TASK_QUEUE_HOST") {
             config.server.host = host;
         }
 
-        if let Ok(port) = std::env::var("TASK_QUEUE_PORT") {
-            if let Ok(port) = port.parse() {
-                config.server.port = port;
-            }
+        if let Ok(port) = std::env::var("TASK_QUEUE_PORT")
+            && let Ok(port) = port.parse()
+        {
+            config.server.port = port;
+        }
+
+        if let Ok(grpc_port) = std::env::var("TASK_QUEUE_GRPC_PORT")
+            && let Ok(port) = grpc_port.parse()
+        {
+            config.server.grpc_port = port;
+        }
+
+        if let Ok(mcp_port) = std::env::var("TASK_QUEUE_MCP_PORT")
+            && let Ok(port) = mcp_port.parse()
+        {
+            config.server.mcp_port = port;
+        }
+
+        if let Ok(transport) = std::env::var("TASK_QUEUE_MCP_TRANSPORT")
+            && let Some(transport) = McpTransport::parse(&transport)
+        {
+            config.server.mcp_transport = transport;
+        }
+
+        if let Ok(stateless) = std::env::var("TASK_QUEUE_STATELESS") {
+            config.server.stateless = stateless.parse().unwrap_or(false);
+        }
+
+        if let Ok(ttl) = std::env::var("TASK_QUEUE_CACHE_TTL_MS")
+            && let Ok(ttl) = ttl.parse()
+        {
+            config.server.cache_ttl_ms = ttl;
+        }
+
+        if let Ok(origins) = std::env::var("TASK_QUEUE_CORS_ORIGINS") {
+            config.server.cors.allowed_origins = origins.split(',')
+                .map(|origin| origin.trim().to_string())
+                .filter(|origin| !origin.is_empty())
+                .collect();
+        }
+
+        if let Ok(bytes) = std::env::var("TASK_QUEUE_MAX_BODY_BYTES")
+            && let Ok(bytes) = bytes.parse()
+        {
+            config.server.limits.max_body_bytes = bytes;
         }
 
-        if let Ok(grpc_port) = std::env::var("TASK_QUEUE_GRPC_PORT") {
-            if let Ok(port) = grpc_port.parse() {
-                config.server.grpc_port = port;
-            }
+        if let Ok(dir) = std::env::var("TASK_QUEUE_DASHBOARD_DIR") {
+            config.server.dashboard_dir = dir;
         }
 
-        if let Ok(mcp_port) = std::env::var("TASK_QUEUE_MCP_PORT") {
-            if let Ok(port) = mcp_port.parse() {
-                config.server.mcp_port = port;
-            }
+        if let Ok(path) = std::env::var("TASK_QUEUE_LOG_FILE") {
+            config.server.log_file = (!path.is_empty()).then_some(path);
+        }
+
+        if let Ok(key) = std::env::var("TASK_QUEUE_ADMIN_KEY") {
+            config.server.tenancy.admin_key = (!key.is_empty()).then_some(key);
+        }
+
+        if let Ok(required) = std::env::var("TASK_QUEUE_TENANCY_REQUIRED")
+            && let Ok(required) = required.parse()
+        {
+            config.server.tenancy.required = required;
+        }
+
+        if let Ok(anonymous) = std::env::var("TASK_QUEUE_MCP_ANONYMOUS")
+            && let Ok(anonymous) = anonymous.parse()
+        {
+            config.server.tenancy.mcp_anonymous = anonymous;
+        }
+
+        if let Ok(enabled) = std::env::var("TASK_QUEUE_RATE_LIMITS_ENABLED")
+            && let Ok(enabled) = enabled.parse()
+        {
+            config.server.rate_limits.enabled = enabled;
+        }
+
+        if let Ok(secs) = std::env::var("TASK_QUEUE_IDEMPOTENCY_WINDOW_SECS")
+            && let Ok(secs) = secs.parse()
+        {
+            config.server.idempotency.window_secs = secs;
         }
 
         if let Ok(db_path) = std::env::var("TASK_QUEUE_DB_PATH") {
             config.storage.database_path = db_path;
         }
 
+        if let Ok(path) = std::env::var("TASK_QUEUE_SQLITE_PATH") {
+            config.storage.backend = StorageBackend::Sqlite { path };
+        }
+
+        if let Ok(url) = std::env::var("TASK_QUEUE_REDIS_URL") {
+            let prefix = std::env::var("TASK_QUEUE_REDIS_PREFIX").unwrap_or_else(|_| default_redis_prefix());
+            config.storage.backend = StorageBackend::Redis { url, prefix };
+        }
+
+        if let Ok(days) = std::env::var("TASK_QUEUE_RETENTION_DAYS")
+            && let Ok(days) = days.parse()
+        {
+            config.storage.retention_days = days;
+            config.storage.retention.enabled = true;
+        }
+
+        if let Ok(days) = std::env::var("TASK_QUEUE_COLD_STORAGE_DAYS")
+            && let Ok(days) = days.parse()
+        {
+            config.storage.cold_storage.after_days = days;
+            config.storage.cold_storage.enabled = true;
+        }
+
+        if let Ok(secs) = std::env::var("TASK_QUEUE_VACUUM_INTERVAL_SECS")
+            && let Ok(secs) = secs.parse()
+        {
+            config.storage.vacuum.interval_secs = secs;
+            config.storage.vacuum.enabled = true;
+        }
+
+        if let Ok(ms) = std::env::var("TASK_QUEUE_WRITE_BEHIND_MS")
+            && let Ok(ms) = ms.parse()
+        {
+            config.storage.write_behind.window_ms = ms;
+            config.storage.write_behind.enabled = true;
+        }
+
+        if let Ok(codec) = std::env::var("TASK_QUEUE_STORAGE_CODEC")
+            && let Some(codec) = Codec::parse(&codec)
+        {
+            config.storage.codec = Some(codec);
+        }
+
+        if let Ok(brokers) = std::env::var("TASK_QUEUE_KAFKA_BROKERS") {
+            let brokers = brokers.split(',').map(|b| b.trim().to_string()).collect();
+            config.events.publisher = Some(PublisherConfig::new(BrokerConfig::Kafka { brokers }));
+        } else if let Ok(url) = std::env::var("TASK_QUEUE_NATS_URL") {
+            let jetstream = std::env::var("TASK_QUEUE_NATS_JETSTREAM")
+                .map(|v| v.parse().unwrap_or(false))
+                .unwrap_or(false);
+            config.events.publisher = Some(PublisherConfig::new(BrokerConfig::Nats { url, jetstream }));
+        }
+
+        if let Ok(topic) = std::env::var("TASK_QUEUE_EVENTS_TOPIC")
+            && let Some(publisher) = config.events.publisher.as_mut()
+        {
+            publisher.default_topic = topic;
+        }
+
+        if let Ok(url) = std::env::var("TASK_QUEUE_INGEST_URL") {
+            config.ingest = IngestConfig::from_url(&url, std::env::var("TASK_QUEUE_INGEST_QUEUE").ok());
+        }
+
         if let Ok(vectorizer_endpoint) = std::env::var("VECTORIZER_ENDPOINT") {
             config.vectorizer.endpoint = vectorizer_endpoint;
         }
@@ -147,20 +806,37 @@ impl Config {
             config.vectorizer.collection = collection;
         }
 
-        if let Ok(max_tasks) = std::env::var("TASK_QUEUE_MAX_CONCURRENT") {
-            if let Ok(max) = max_tasks.parse() {
-                config.execution.max_concurrent_tasks = max;
-            }
+        if let Ok(max_tasks) = std::env::var("TASK_QUEUE_MAX_CONCURRENT")
+            && let Ok(max) = max_tasks.parse()
+        {
+            config.execution.max_concurrent_tasks = max;
+        }
+
+        if let Ok(root) = std::env::var("TASK_QUEUE_ARTIFACT_DIR") {
+            config.execution.artifacts.store = BlobStoreConfig::Local { root: root.into() };
+        }
+
+        if let Ok(bucket) = std::env::var("TASK_QUEUE_S3_BUCKET") {
+            config.execution.artifacts.store = BlobStoreConfig::S3 {
+                bucket,
+                region: std::env::var("TASK_QUEUE_S3_REGION").ok(),
+                endpoint: std::env::var("TASK_QUEUE_S3_ENDPOINT").ok(),
+                prefix: std::env::var("TASK_QUEUE_S3_PREFIX").unwrap_or_default(),
+            };
+        }
+
+        if let Ok(enabled) = std::env::var("TASK_QUEUE_EXECUTION_ENABLED") {
+            config.execution.enabled = enabled.parse().unwrap_or(false);
         }
 
         if let Ok(timeout) = std::env::var("TASK_QUEUE_DEFAULT_TIMEOUT") {
             config.execution.default_timeout = timeout;
         }
 
-        if let Ok(attempts) = std::env::var("TASK_QUEUE_RETRY_ATTEMPTS") {
-            if let Ok(attempts) = attempts.parse() {
-                config.execution.retry_attempts = attempts;
-            }
+        if let Ok(attempts) = std::env::var("TASK_QUEUE_RETRY_ATTEMPTS")
+            && let Ok(attempts) = attempts.parse()
+        {
+            config.execution.retry_attempts = attempts;
         }
 
         if let Ok(delay) = std::env::var("TASK_QUEUE_RETRY_DELAY") {
@@ -171,12 +847,65 @@ impl Config {
             config.monitoring.metrics_enabled = enabled.parse().unwrap_or(true);
         }
 
-        if let Ok(metrics_port) = std::env::var("TASK_QUEUE_METRICS_PORT") {
-            if let Ok(port) = metrics_port.parse() {
-                config.monitoring.metrics_port = port;
-            }
+        if let Ok(metrics_port) = std::env::var("TASK_QUEUE_METRICS_PORT")
+            && let Ok(port) = metrics_port.parse()
+        {
+            config.monitoring.metrics_port = port;
         }
+    }
+}
 
-        config
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_toml_file_keeps_defaults() {
+        let path = std::env::temp_dir().join(format!("task-queue-{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(&path, r#"
+[server]
+host = "127.0.0.1"
+port = 18080
+mcp_transport = "streamable_http"
+cors = { allowed_origins = ["https://tasks.example.com"] }
+
+[storage]
+database_path = "/var/lib/task-queue/queue.db"
+
+[execution]
+max_concurrent_tasks = 4
+default_timeout = "10m"
+retry_attempts = 1
+retry_delay = "5s"
+enabled = false
+"#).unwrap();
+        let config = Config::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(config.server.bind_address(), "127.0.0.1:18080");
+        assert_eq!(config.server.mcp_transport, McpTransport::StreamableHttp);
+        assert!(!config.server.mcp_transport.serves_sse());
+        assert_eq!(McpTransport::parse("Streamable-HTTP"), Some(McpTransport::StreamableHttp));
+        assert_eq!(config.server.cors.allowed_origins, ["https://tasks.example.com"]);
+        assert_eq!(config.server.dashboard_dir, "dashboard/public");
+        assert_eq!(config.storage.database_path, "/var/lib/task-queue/queue.db");
+        assert_eq!(config.storage.retention_days, 30);
+        assert!(!config.execution.enabled);
+        assert!(config.monitoring.metrics_enabled);
+    }
+
+    #[test]
+    fn test_execution_needs_authenticated_callers() {
+        let mut config = Config::default();
+        assert!(!config.execution.enabled);
+        assert!(config.check_execution().is_ok());
+
+        config.execution.enabled = true;
+        assert!(config.check_execution().is_err());
+        // An admin key alone still lets keyless requests in as the operator
+        config.server.tenancy.admin_key = Some("operator".to_string());
+        assert!(config.check_execution().is_err());
+        config.server.tenancy = TenancyConfig { required: true, ..Default::default() };
+        assert!(config.check_execution().is_ok());
     }
 }
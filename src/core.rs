@@ -6,14 +6,17 @@
 #![allow(unused_mut)]
 #![allow(non_snake_case)]
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::time::{Duration, SystemTime};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use crate::groups::GroupProgress;
+use crate::validation::Validate;
 
 /// Task status enumeration
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Hash)]
 pub enum TaskStatus {
     // Development lifecycle statuses
     Planning,                  // Planejamento - criar documentação técnica da implementação
@@ -31,8 +34,10 @@ pub enum TaskStatus {
     InTesting,                 // Em testes
     
     // Execution statuses
+    Scheduled,                 // Waiting for its `run_at` time before becoming Pending
     Pending,
     Running,
+    Paused,                    // Running task whose command is stopped until resumed
     Completed,
     Failed,
     Cancelled,
@@ -40,7 +45,7 @@ pub enum TaskStatus {
 }
 
 /// Task result enumeration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub enum TaskResult {
     Success {
         output: String,
@@ -51,6 +56,9 @@ pub enum TaskResult {
         error: String,
         exit_code: Option<i32>,
         logs: Vec<String>,
+        /// Resource usage up to the failure, when the executor measured it
+        #[serde(default)]
+        metrics: Option<TaskMetrics>,
     },
     Cancelled {
         reason: String,
@@ -58,7 +66,7 @@ pub enum TaskResult {
 }
 
 /// Task metrics
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct TaskMetrics {
     pub execution_time: Duration,
     pub memory_usage: u64,
@@ -68,16 +76,19 @@ pub struct TaskMetrics {
 }
 
 /// Dependency condition types
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 pub enum DependencyCondition {
     Success,
     Failure,
     Completion,
     Custom(String), // JSON serialized custom condition
+    /// The dependency's `task_id` is a task group; met once every member
+    /// succeeded, or `quorum` of them
+    Group { quorum: Option<u32> },
 }
 
 /// Task priority levels
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq, PartialOrd, Ord)]
 pub enum TaskPriority {
     Low = 1,
     Normal = 2,
@@ -86,7 +97,7 @@ pub enum TaskPriority {
 }
 
 /// Project status enumeration
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 pub enum ProjectStatus {
     Planning,
     Active,
@@ -96,7 +107,7 @@ pub enum ProjectStatus {
 }
 
 /// Project structure
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Project {
     pub id: Uuid,
     pub name: String,
@@ -107,19 +118,63 @@ pub struct Project {
     pub due_date: Option<DateTime<Utc>>,
     pub tags: Vec<String>,
     pub metadata: HashMap<String, serde_json::Value>,
+    /// Bumped on every write and served as the `ETag`
+    #[serde(default)]
+    pub version: u64,
+    /// Tenant it belongs to; `None` for data from before tenants, which
+    /// only unscoped requests see
+    #[serde(default)]
+    pub tenant_id: Option<Uuid>,
+}
+
+/// Body of `POST /projects`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CreateProjectRequest {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
 }
 
 /// Project update structure
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ProjectUpdate {
     pub name: Option<String>,
     pub description: Option<String>,
     pub status: Option<ProjectStatus>,
     pub tags: Option<Vec<String>>,
+    /// Entries to set; a `null` value removes the key
+    #[serde(default)]
+    pub metadata: Option<HashMap<String, serde_json::Value>>,
+}
+
+/// AI agent or worker that acts on the queue
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Agent {
+    pub id: Uuid,
+    pub name: String,
+    pub model: Option<String>,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    pub registered_at: DateTime<Utc>,
+    /// Last heartbeat or request from the agent
+    pub last_seen: DateTime<Utc>,
+    #[serde(default)]
+    pub activity: AgentActivity,
+}
+
+/// What an agent has done since it registered
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct AgentActivity {
+    pub requests: u64,
+    pub mutations: u64,
+    /// Requests that were rejected or failed
+    pub failures: u64,
+    pub last_action: Option<String>,
+    pub last_action_at: Option<DateTime<Utc>>,
 }
 
 /// AI Review structure
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct AIReview {
     pub model_name: String,
     pub review_result: String,
@@ -130,27 +185,119 @@ pub struct AIReview {
 }
 
 /// Task phase structure
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TaskPhase {
     pub phase: TaskStatus,
     pub started_at: Option<DateTime<Utc>>,
     pub completed_at: Option<DateTime<Utc>>,
     pub documentation: Option<String>,
-    pub artifacts: Vec<String>,
+    pub artifacts: Vec<PhaseArtifact>,
     pub ai_reviews: Vec<AIReview>,
 }
 
+/// A file or URL produced during a phase, e.g. a design doc, a diff or a
+/// test report
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(from = "StoredPhaseArtifact")]
+pub struct PhaseArtifact {
+    /// File path or URL
+    pub location: String,
+    pub description: Option<String>,
+    pub added_at: Option<DateTime<Utc>>,
+    /// Agent that recorded it
+    pub agent: Option<Uuid>,
+}
+
+impl PhaseArtifact {
+    pub fn new(location: impl Into<String>, description: Option<String>) -> Self {
+        Self { location: location.into(), description, added_at: Some(Utc::now()), agent: None }
+    }
+}
+
+/// Phase artifacts as stored, which used to be bare locations
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum StoredPhaseArtifact {
+    Location(String),
+    Artifact {
+        location: String,
+        #[serde(default)]
+        description: Option<String>,
+        #[serde(default)]
+        added_at: Option<DateTime<Utc>>,
+        #[serde(default)]
+        agent: Option<Uuid>,
+    },
+}
+
+impl From<StoredPhaseArtifact> for PhaseArtifact {
+    fn from(stored: StoredPhaseArtifact) -> Self {
+        match stored {
+            StoredPhaseArtifact::Location(location) => Self { location, description: None, added_at: None, agent: None },
+            StoredPhaseArtifact::Artifact { location, description, added_at, agent } => Self { location, description, added_at, agent },
+        }
+    }
+}
+
 /// Task type enumeration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub enum TaskType {
     Simple,
     Dependent,
     Workflow,
     Scheduled,
+    /// `command` is a whole script, run by the interpreter
+    Script { interpreter: ScriptInterpreter },
+    /// Makes the HTTP call in the task's `http` metadata instead of running
+    /// a command
+    Http,
+}
+
+/// Interpreters for `TaskType::Script` tasks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ScriptInterpreter {
+    Bash,
+    Python,
+    Pwsh,
+}
+
+impl ScriptInterpreter {
+    /// Program and leading arguments the script file is appended to
+    pub fn invocation(&self) -> &'static [&'static str] {
+        match self {
+            ScriptInterpreter::Bash => &["bash"],
+            ScriptInterpreter::Python if cfg!(windows) => &["python"],
+            ScriptInterpreter::Python => &["python3"],
+            ScriptInterpreter::Pwsh => &["pwsh", "-NoProfile", "-NonInteractive", "-File"],
+        }
+    }
+
+    /// Extension of the script file; `pwsh -File` insists on `.ps1`
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ScriptInterpreter::Bash => "sh",
+            ScriptInterpreter::Python => "py",
+            ScriptInterpreter::Pwsh => "ps1",
+        }
+    }
+}
+
+impl std::str::FromStr for ScriptInterpreter {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "bash" => Ok(ScriptInterpreter::Bash),
+            "python" => Ok(ScriptInterpreter::Python),
+            "pwsh" => Ok(ScriptInterpreter::Pwsh),
+            other => Err(format!("Unknown interpreter '{}', expected bash, python or pwsh", other)),
+        }
+    }
 }
 
 /// Development workflow information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DevelopmentWorkflow {
     /// Caminho para documentação técnica gerada na fase de Planning
     pub technical_documentation_path: Option<String>,
@@ -167,7 +314,7 @@ pub struct DevelopmentWorkflow {
 }
 
 /// Status do workflow de desenvolvimento
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub enum DevelopmentWorkflowStatus {
     NotStarted,
@@ -181,7 +328,7 @@ pub enum DevelopmentWorkflowStatus {
 }
 
 /// Relatório de revisão de desenvolvimento por IA
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct AIDevelopmentReview {
     pub model_name: String,
     pub review_type: AIReviewType,
@@ -193,7 +340,7 @@ pub struct AIDevelopmentReview {
 }
 
 /// Tipo de revisão de IA
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub enum AIReviewType {
     CodeQuality,
     Security,
@@ -204,7 +351,7 @@ pub enum AIReviewType {
 }
 
 /// Main Task structure
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Task {
     pub id: Uuid,
     pub name: String,
@@ -246,6 +393,13 @@ pub struct Task {
     pub development_workflow: Option<DevelopmentWorkflow>, // Workflow de desenvolvimento
     #[serde(default)]
     pub metadata: HashMap<String, serde_json::Value>,
+    /// Bumped on every write and served as the `ETag`
+    #[serde(default)]
+    pub version: u64,
+    /// Tenant it belongs to; `None` for data from before tenants, which
+    /// only unscoped requests see
+    #[serde(default)]
+    pub tenant_id: Option<Uuid>,
 }
 
 /// Default description for backward compatibility
@@ -297,8 +451,90 @@ fn default_development_workflow() -> Option<DevelopmentWorkflow> {
     })
 }
 
+/// Lightweight view of a task used by list endpoints
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TaskSummary {
+    pub id: Uuid,
+    pub name: String,
+    pub project: Option<String>,
+    pub project_id: Option<Uuid>,
+    pub task_type: TaskType,
+    pub priority: TaskPriority,
+    pub status: TaskStatus,
+    pub current_phase: TaskStatus,
+    pub workflow_status: Option<DevelopmentWorkflowStatus>,
+    pub dependency_count: usize,
+    pub created_at: SystemTime,
+    pub updated_at: SystemTime,
+}
+
+impl From<&Task> for TaskSummary {
+    fn from(task: &Task) -> Self {
+        Self {
+            id: task.id,
+            name: task.name.clone(),
+            project: task.project.clone(),
+            project_id: task.project_id,
+            task_type: task.task_type.clone(),
+            priority: task.priority.clone(),
+            status: task.status.clone(),
+            current_phase: task.current_phase.clone(),
+            workflow_status: task.development_workflow.as_ref().map(|w| w.workflow_status.clone()),
+            dependency_count: task.dependencies.len(),
+            created_at: task.created_at,
+            updated_at: task.updated_at,
+        }
+    }
+}
+
+/// Which page of a task listing to return
+#[derive(Debug, Clone)]
+pub struct PageRequest {
+    /// `next_cursor` of the previous page, listed with the same `sort`
+    pub cursor: Option<String>,
+    /// Tasks skipped after the cursor
+    pub offset: usize,
+    pub limit: usize,
+    /// `CreatedAt`, `UpdatedAt` or `Priority`, which pages highest first
+    pub sort: crate::views::SortField,
+}
+
+impl Default for PageRequest {
+    fn default() -> Self {
+        Self {
+            cursor: None,
+            offset: 0,
+            limit: 0,
+            sort: crate::views::SortField::CreatedAt,
+        }
+    }
+}
+
+/// One page of a task listing
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TaskPage<T> {
+    pub tasks: Vec<T>,
+    pub next_cursor: Option<String>,
+    /// Tasks matching the filters across all pages
+    pub total: usize,
+}
+
+/// Machine-readable tracking manifest for a project
+///
+/// Served by `GET /projects/{id}/manifest`; clients that want a `.tasks` file
+/// in their repository render it from this.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ProjectManifest {
+    pub project_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub generated_at: DateTime<Utc>,
+    pub tasks: Vec<TaskSummary>,
+}
+
 /// Enhanced dependency structure with correlation support
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Dependency {
     pub task_id: Uuid,
     pub task_name: Option<String>, // For easier reference
@@ -309,7 +545,7 @@ pub struct Dependency {
 }
 
 /// Dependency correlation group
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DependencyCorrelation {
     pub correlation_id: String,
     pub name: String,
@@ -320,7 +556,7 @@ pub struct DependencyCorrelation {
 }
 
 /// Workflow structure
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Workflow {
     pub id: Uuid,
     pub name: String,
@@ -330,10 +566,17 @@ pub struct Workflow {
     pub created_at: SystemTime,
     pub updated_at: SystemTime,
     pub status: WorkflowStatus,
+    /// Bumped on every write and served as the `ETag`
+    #[serde(default)]
+    pub version: u64,
+    /// Tenant it belongs to; `None` for data from before tenants, which
+    /// only unscoped requests see
+    #[serde(default)]
+    pub tenant_id: Option<Uuid>,
 }
 
 /// Workflow dependency
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct WorkflowDependency {
     pub from_task: Uuid,
     pub to_task: Uuid,
@@ -341,7 +584,7 @@ pub struct WorkflowDependency {
 }
 
 /// Workflow status
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 pub enum WorkflowStatus {
     Pending,
     Running,
@@ -351,7 +594,7 @@ pub enum WorkflowStatus {
 }
 
 /// Task execution context
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TaskContext {
     pub task_id: Uuid,
     pub project: Option<String>,
@@ -364,7 +607,7 @@ pub struct TaskContext {
 }
 
 /// Request structure for creating a new task
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CreateTaskRequest {
     pub name: String,
     pub command: String,
@@ -378,6 +621,166 @@ pub struct CreateTaskRequest {
     pub estimated_hours: Option<u32>,
     pub tags: Option<Vec<String>>,
     pub ai_reviews_required: Option<u32>, // Número de revisões IA (padrão: 3)
+    #[serde(default)]
+    pub run_at: Option<DateTime<Utc>>, // Não executar antes deste momento
+    #[serde(default)]
+    pub timeout_secs: Option<u64>, // Tempo máximo de execução
+    #[serde(default)]
+    pub cpu: Option<f64>, // Núcleos reservados durante a execução
+    #[serde(default)]
+    pub memory_mb: Option<u64>, // Memória reservada durante a execução
+    #[serde(default)]
+    pub queue: Option<String>, // Fila nomeada que recebe a tarefa
+}
+
+/// Body of `PATCH /tasks/{id}`: only the fields present are changed. On the
+/// nullable fields an explicit `null` clears the value, while leaving the field
+/// out keeps it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct UpdateTaskRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, deserialize_with = "nullable", skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<String>")]
+    pub technical_specs: Option<Option<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acceptance_criteria: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<TaskPriority>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<TaskStatus>,
+    #[serde(default, deserialize_with = "nullable", skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<Uuid>")]
+    pub project_id: Option<Option<Uuid>>,
+    #[serde(default, deserialize_with = "nullable", skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<String>")]
+    pub working_directory: Option<Option<String>>,
+}
+
+/// A present field, `null` included, becomes `Some`
+fn nullable<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// One rejected field of a request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl UpdateTaskRequest {
+    /// Parse a request body field by field, so that every bad field is
+    /// reported rather than only the first
+    pub fn from_value(value: serde_json::Value) -> Result<Self, Vec<FieldError>> {
+        let serde_json::Value::Object(fields) = value else {
+            return Err(vec![FieldError::new("", "expected a JSON object")]);
+        };
+        let mut request = Self::default();
+        let mut errors = Vec::new();
+        for (field, value) in fields {
+            let parsed = match field.as_str() {
+                "name" => required(value).map(|v| request.name = v),
+                "command" => required(value).map(|v| request.command = v),
+                "description" => required(value).map(|v| request.description = v),
+                "technical_specs" => optional(value).map(|v| request.technical_specs = v),
+                "acceptance_criteria" => required(value).map(|v| request.acceptance_criteria = v),
+                "priority" => required(value).map(|v| request.priority = v),
+                "status" => required(value).map(|v| request.status = v),
+                "project_id" => optional(value).map(|v| request.project_id = v),
+                "working_directory" => optional(value).map(|v| request.working_directory = v),
+                _ => Err("unknown field".to_string()),
+            };
+            if let Err(message) = parsed {
+                errors.push(FieldError::new(field, message));
+            }
+        }
+        errors.extend(request.validate());
+        if errors.is_empty() { Ok(request) } else { Err(errors) }
+    }
+
+    /// Whether the request changes nothing
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Apply the request to a task, stopping at a status change the task
+    /// can't make
+    pub fn apply(self, task: &mut Task) -> Result<(), FieldError> {
+        if let Some(status) = self.status {
+            task.set_status(status).map_err(|e| FieldError::new("status", e))?;
+        }
+        if let Some(name) = self.name {
+            task.name = name;
+        }
+        if let Some(command) = self.command {
+            task.command = command;
+        }
+        if let Some(description) = self.description {
+            task.description = description;
+        }
+        if let Some(specs) = self.technical_specs {
+            task.technical_specs = specs;
+        }
+        if let Some(criteria) = self.acceptance_criteria {
+            task.acceptance_criteria = criteria;
+        }
+        if let Some(priority) = self.priority {
+            task.priority = priority;
+        }
+        if let Some(project_id) = self.project_id {
+            task.project_id = project_id;
+        }
+        if let Some(working_directory) = self.working_directory {
+            task.working_directory = working_directory;
+        }
+        Ok(())
+    }
+}
+
+fn required<T: serde::de::DeserializeOwned>(value: serde_json::Value) -> Result<Option<T>, String> {
+    if value.is_null() {
+        return Err("must not be null".to_string());
+    }
+    serde_json::from_value(value).map(Some).map_err(|e| e.to_string())
+}
+
+fn optional<T: serde::de::DeserializeOwned>(value: serde_json::Value) -> Result<Option<Option<T>>, String> {
+    serde_json::from_value(value).map(Some).map_err(|e| e.to_string())
+}
+
+/// Outcome of one task in a `POST /tasks/batch` submission
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BatchItem {
+    /// Position of the task in the submitted array
+    pub index: usize,
+    pub task_id: Option<Uuid>,
+    pub error: Option<String>,
+}
+
+/// Result of a batch submission; when not `accepted` no task was stored
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BatchSubmission {
+    pub accepted: bool,
+    pub items: Vec<BatchItem>,
 }
 
 /// Task builder for fluent API
@@ -387,10 +790,13 @@ pub struct TaskBuilder {
 
 impl CreateTaskRequest {
     /// Convert CreateTaskRequest to Task
+    #[allow(clippy::wrong_self_convention)]
     pub fn to_task(self) -> Task {
         let now = SystemTime::now();
         Task {
             id: Uuid::new_v4(),
+            version: 0,
+            tenant_id: None,
             name: self.name,
             command: self.command,
             description: self.description,
@@ -401,7 +807,7 @@ impl CreateTaskRequest {
             priority: self.priority,
             project_id: self.project_id,
             dependencies: Vec::new(),
-            timeout: None,
+            timeout: self.timeout_secs.map(Duration::from_secs),
             retry_attempts: 3,
             retry_delay: Duration::from_secs(30),
             environment: HashMap::new(),
@@ -431,6 +837,18 @@ impl CreateTaskRequest {
                         tags.iter().map(|tag| serde_json::Value::String(tag.clone())).collect()
                     ));
                 }
+                if let Some(queue) = self.queue {
+                    metadata.insert(crate::queues::QUEUE_KEY.to_string(), serde_json::Value::String(queue));
+                }
+                if let Some(run_at) = self.run_at {
+                    metadata.insert(crate::calendar::RUN_AT_KEY.to_string(), serde_json::Value::String(run_at.to_rfc3339()));
+                }
+                if self.cpu.is_some() || self.memory_mb.is_some() {
+                    metadata.insert(crate::resources::RESOURCES_METADATA_KEY.to_string(), serde_json::json!({
+                        "cpu": self.cpu.unwrap_or_default(),
+                        "memory_mb": self.memory_mb.unwrap_or_default(),
+                    }));
+                }
                 metadata
             },
             development_workflow: default_development_workflow(),
@@ -444,6 +862,8 @@ impl TaskBuilder {
         Self {
             task: Task {
                 id: Uuid::new_v4(),
+                version: 0,
+                tenant_id: None,
                 name: name.to_string(),
                 command: String::new(),
                 description: String::new(),
@@ -554,6 +974,37 @@ impl TaskBuilder {
 }
 
 
+impl TaskStatus {
+    /// Whether a task with this status is done for good
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, TaskStatus::Completed | TaskStatus::Finalized | TaskStatus::Failed | TaskStatus::Cancelled)
+    }
+
+    /// Check whether this status matches a `status` list filter
+    pub fn matches_filter(&self, filter: &str) -> bool {
+        Self::from_filter(filter).as_ref() == Some(self)
+    }
+
+    /// The status a `status` list filter selects
+    pub fn from_filter(filter: &str) -> Option<Self> {
+        match filter {
+            "planning" => Some(TaskStatus::Planning),
+            "scheduled" => Some(TaskStatus::Scheduled),
+            "pending" => Some(TaskStatus::Pending),
+            "running" => Some(TaskStatus::Running),
+            "paused" => Some(TaskStatus::Paused),
+            "completed" => Some(TaskStatus::Completed),
+            "failed" => Some(TaskStatus::Failed),
+            "cancelled" => Some(TaskStatus::Cancelled),
+            "implementation" => Some(TaskStatus::InImplementation),
+            "testcreation" => Some(TaskStatus::TestCreation),
+            "testing" => Some(TaskStatus::Testing),
+            "aireview" => Some(TaskStatus::AIReview),
+            _ => None,
+        }
+    }
+}
+
 /// Convenience function to create a new task
 pub fn Task(name: &str) -> TaskBuilder {
     TaskBuilder::new(name)
@@ -561,13 +1012,26 @@ pub fn Task(name: &str) -> TaskBuilder {
 
 impl Task {
     /// Create a new task builder
+    #[allow(clippy::new_ret_no_self)]
     pub fn new(name: &str) -> TaskBuilder {
         TaskBuilder::new(name)
     }
 
     /// Check if task is ready to execute (all dependencies satisfied)
     pub fn is_ready(&self, completed_tasks: &HashMap<Uuid, TaskResult>) -> bool {
+        self.is_ready_in(completed_tasks, &HashMap::new())
+    }
+
+    /// Check if task is ready to execute, with the progress of the task
+    /// groups it may depend on
+    pub fn is_ready_in(&self, completed_tasks: &HashMap<Uuid, TaskResult>, groups: &HashMap<Uuid, GroupProgress>) -> bool {
         for dependency in &self.dependencies {
+            if let DependencyCondition::Group { quorum } = &dependency.condition {
+                if groups.get(&dependency.task_id).is_some_and(|group| group.satisfies(*quorum)) {
+                    continue;
+                }
+                return false;
+            }
             if let Some(result) = completed_tasks.get(&dependency.task_id) {
                 match (&dependency.condition, result) {
                     (DependencyCondition::Success, TaskResult::Success { .. }) => continue,
@@ -631,7 +1095,7 @@ impl Task {
     /// Get dependencies by correlation ID
     pub fn get_dependencies_by_correlation(&self, correlation_id: &str) -> Vec<&Dependency> {
         self.dependencies.iter()
-            .filter(|dep| dep.correlation_id.as_ref().map_or(false, |id| id == correlation_id))
+            .filter(|dep| dep.correlation_id.as_ref().is_some_and(|id| id == correlation_id))
             .collect()
     }
 
@@ -656,6 +1120,15 @@ impl Task {
         )
     }
 
+    /// Earliest time the task may run, from its `run_at` metadata; plain dates start at midnight UTC
+    pub fn run_at(&self) -> Option<DateTime<Utc>> {
+        let value = self.metadata.get(crate::calendar::RUN_AT_KEY)?.as_str()?;
+        match crate::calendar::EventTime::parse(value)? {
+            crate::calendar::EventTime::At(at) => Some(at),
+            crate::calendar::EventTime::Date(date) => date.and_hms_opt(0, 0, 0).map(|at| at.and_utc()),
+        }
+    }
+
     /// Check if task is ready for execution
     pub fn is_ready_for_execution(&self) -> bool {
         matches!(self.status, TaskStatus::Pending | TaskStatus::WaitingForDependencies)
@@ -700,6 +1173,8 @@ impl Project {
         let now = Utc::now();
         Self {
             id: Uuid::new_v4(),
+            version: 0,
+            tenant_id: None,
             name: name.to_string(),
             description: None,
             status: ProjectStatus::Planning,
@@ -733,8 +1208,46 @@ impl Project {
 }
 
 impl Task {
+    /// Status shown in listings: the development workflow's while one is
+    /// under way, the current phase otherwise
+    pub fn effective_status(&self) -> TaskStatus {
+        let Some(workflow) = &self.development_workflow else {
+            return self.current_phase.clone();
+        };
+        match workflow.workflow_status {
+            // Not started, but the task may have advanced phases on its own
+            DevelopmentWorkflowStatus::NotStarted => match self.current_phase {
+                TaskStatus::Planning => TaskStatus::Planning,
+                TaskStatus::Implementation => TaskStatus::Implementation,
+                TaskStatus::TestCreation => TaskStatus::TestCreation,
+                TaskStatus::Testing => TaskStatus::Testing,
+                TaskStatus::AIReview => TaskStatus::AIReview,
+                TaskStatus::Finalized => TaskStatus::Finalized,
+                TaskStatus::Completed => TaskStatus::Completed,
+                TaskStatus::Failed => TaskStatus::Failed,
+                TaskStatus::Cancelled => TaskStatus::Cancelled,
+                _ => TaskStatus::Planning,
+            },
+            DevelopmentWorkflowStatus::Planning => TaskStatus::Planning,
+            DevelopmentWorkflowStatus::InImplementation => TaskStatus::Implementation,
+            DevelopmentWorkflowStatus::TestCreation => TaskStatus::TestCreation,
+            DevelopmentWorkflowStatus::Testing => TaskStatus::Testing,
+            DevelopmentWorkflowStatus::AIReview => TaskStatus::AIReview,
+            DevelopmentWorkflowStatus::Completed => TaskStatus::Completed,
+            DevelopmentWorkflowStatus::Failed => TaskStatus::Failed,
+        }
+    }
+
     /// Validate if a status transition is allowed
     pub fn can_transition_to(&self, new_status: &TaskStatus) -> bool {
+        // Pausing concerns the execution, not the lifecycle phase
+        match (&self.status, new_status) {
+            (TaskStatus::Running, TaskStatus::Paused) | (TaskStatus::Paused, TaskStatus::Running) => return true,
+            (_, TaskStatus::Paused) => return false,
+            // A paused task has to be resumed before anything but cancelling it
+            (TaskStatus::Paused, new) if *new != TaskStatus::Cancelled => return false,
+            _ => {}
+        }
         match (&self.current_phase, new_status) {
             // Valid forward transitions
             (TaskStatus::Planning, TaskStatus::Implementation) => true,
@@ -869,6 +1382,25 @@ impl Task {
         self.updated_at = std::time::SystemTime::now();
     }
 
+    /// Record an artifact on the current phase, opening one for
+    /// `current_phase` if the task has none yet
+    pub fn add_phase_artifact(&mut self, artifact: PhaseArtifact) {
+        if self.phases.is_empty() {
+            self.phases.push(TaskPhase {
+                phase: self.current_phase.clone(),
+                started_at: Some(Utc::now()),
+                completed_at: None,
+                documentation: None,
+                artifacts: vec![],
+                ai_reviews: vec![],
+            });
+        }
+        if let Some(current_phase) = self.phases.last_mut() {
+            current_phase.artifacts.push(artifact);
+        }
+        self.updated_at = std::time::SystemTime::now();
+    }
+
     /// Get current phase progress
     pub fn get_phase_progress(&self) -> f64 {
         match self.current_phase {
@@ -892,6 +1424,8 @@ impl Workflow {
         let now = SystemTime::now();
         Self {
             id: Uuid::new_v4(),
+            version: 0,
+            tenant_id: None,
             name: name.to_string(),
             description: None,
             tasks: Vec::new(),
@@ -931,6 +1465,7 @@ impl Workflow {
 }
 
 #[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
 mod tests {
     use super::*;
     use std::time::Duration;
@@ -1048,6 +1583,7 @@ mod tests {
                 error: "Task failed".to_string(),
                 exit_code: Some(1),
                 logs: vec!["Error log".to_string()],
+                metrics: None,
             },
         );
 
@@ -1073,6 +1609,7 @@ mod tests {
                 error: "Task failed".to_string(),
                 exit_code: Some(1),
                 logs: vec![],
+                metrics: None,
             },
         );
 
@@ -1216,10 +1753,11 @@ mod tests {
             error: "Task execution failed".to_string(),
             exit_code: Some(1),
             logs: vec!["Error: Permission denied".to_string()],
+            metrics: None,
         };
 
         match result {
-            TaskResult::Failure { error, exit_code, logs } => {
+            TaskResult::Failure { error, exit_code, logs, .. } => {
                 assert_eq!(error, "Task execution failed");
                 assert_eq!(exit_code, Some(1));
                 assert_eq!(logs.len(), 1);
@@ -1372,4 +1910,45 @@ mod tests {
             _ => panic!("Expected Performance type"),
         }
     }
+
+    #[test]
+    fn test_update_request_tells_null_from_absent() {
+        let request = UpdateTaskRequest::from_value(serde_json::json!({
+            "description": "Reworded",
+            "project_id": null
+        }))
+        .unwrap();
+        assert_eq!(request.description.as_deref(), Some("Reworded"));
+        assert_eq!(request.project_id, Some(None));
+        assert_eq!(request.technical_specs, None);
+
+        let errors = UpdateTaskRequest::from_value(serde_json::json!({
+            "name": "",
+            "priority": "Urgent",
+            "command": null,
+            "owner": "me"
+        }))
+        .unwrap_err();
+        let mut fields: Vec<_> = errors.iter().map(|e| e.field.as_str()).collect();
+        fields.sort();
+        assert_eq!(fields, ["command", "name", "owner", "priority"]);
+    }
+
+    #[test]
+    fn test_phase_artifacts_go_on_the_current_phase() {
+        let mut task = Task::new("documented").with_command("true").build();
+        task.add_phase_artifact(PhaseArtifact::new("docs/design.md", Some("Design".to_string())));
+        task.add_phase_artifact(PhaseArtifact::new("https://ci.example/report/1", None));
+        assert_eq!(task.phases.len(), 1);
+        assert_eq!(task.phases[0].phase, task.current_phase);
+        let locations: Vec<_> = task.phases[0].artifacts.iter().map(|a| a.location.as_str()).collect();
+        assert_eq!(locations, ["docs/design.md", "https://ci.example/report/1"]);
+
+        // Phases stored before artifacts had descriptions still load
+        let legacy: Vec<PhaseArtifact> = serde_json::from_value(serde_json::json!(["out.log", {"location": "diff.patch"}])).unwrap();
+        assert_eq!(legacy[0], PhaseArtifact { location: "out.log".to_string(), description: None, added_at: None, agent: None });
+        assert_eq!(legacy[1].location, "diff.patch");
+        let round_trip: PhaseArtifact = serde_json::from_value(serde_json::to_value(&task.phases[0].artifacts[0]).unwrap()).unwrap();
+        assert_eq!(round_trip, task.phases[0].artifacts[0]);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,222 @@
+//! Daemon Module
+//!
+//! Process management for running the server under an init system: detaching
+//! from the terminal, owning a PID file and turning signals into actions.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Default PID file used by `--daemon`
+pub const DEFAULT_PID_FILE: &str = "task-queue.pid";
+
+/// PID file removed again when dropped
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    /// Fail if the file names a process that is still running
+    pub fn check(path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let pid = match std::fs::read_to_string(path) {
+            Ok(content) => content.trim().parse::<u32>().ok(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        match pid {
+            Some(pid) if pid != std::process::id() && is_running(pid) => Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("Server already running with PID {} ({})", pid, path.display()),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Write the current process ID, replacing a stale file
+    pub fn create(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        Self::check(&path)?;
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, format!("{}\n", std::process::id()))?;
+        Ok(Self { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            warn!("Failed to remove PID file {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // Signal 0 only checks that the process exists and may be signalled
+    // SAFETY: kill with signal 0 has no side effects
+    let result = unsafe { libc::kill(pid, 0) };
+    result == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn is_running(_pid: u32) -> bool {
+    false
+}
+
+/// Detach from the controlling terminal
+///
+/// Must run before any threads are started, so before the tokio runtime is built.
+/// The working directory is kept because data, logs and the dashboard are resolved
+/// relative to it.
+#[cfg(unix)]
+pub fn daemonize() -> io::Result<()> {
+    // SAFETY: the process is still single-threaded, so fork is sound; the
+    // parents exit immediately without running destructors
+    unsafe {
+        match libc::fork() {
+            -1 => return Err(io::Error::last_os_error()),
+            0 => {}
+            _ => libc::_exit(0),
+        }
+
+        if libc::setsid() == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // Fork again so the daemon can never reacquire a terminal
+        match libc::fork() {
+            -1 => return Err(io::Error::last_os_error()),
+            0 => {}
+            _ => libc::_exit(0),
+        }
+
+        libc::umask(0o027);
+
+        let null = libc::open(c"/dev/null".as_ptr(), libc::O_RDWR);
+        if null == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        for fd in [libc::STDIN_FILENO, libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+            if libc::dup2(null, fd) == -1 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        if null > libc::STDERR_FILENO {
+            libc::close(null);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn daemonize() -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Daemon mode is only supported on Unix",
+    ))
+}
+
+/// Resolves on SIGTERM or Ctrl+C, to drive the server's graceful shutdown
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut stream) => {
+                stream.recv().await;
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("Received Ctrl+C, shutting down"),
+        _ = terminate => info!("Received SIGTERM, shutting down"),
+    }
+}
+
+/// Call `on_hangup` every time SIGHUP is received
+#[cfg(unix)]
+pub fn on_hangup(on_hangup: impl Fn() + Send + 'static) -> io::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut stream = signal(SignalKind::hangup())?;
+    tokio::spawn(async move {
+        while stream.recv().await.is_some() {
+            info!("Received SIGHUP");
+            on_hangup();
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn on_hangup(_on_hangup: impl Fn() + Send + 'static) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pid_file_lifecycle() {
+        let path = std::env::temp_dir().join(format!("task-queue-{}.pid", uuid::Uuid::new_v4()));
+
+        // A file naming a dead process is stale and gets replaced
+        std::fs::write(&path, "999999999\n").unwrap();
+        let pid_file = PidFile::create(&path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap().trim(),
+            std::process::id().to_string()
+        );
+
+        drop(pid_file);
+        assert!(!path.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_pid_file_refuses_running_process() {
+        let path = std::env::temp_dir().join(format!("task-queue-{}.pid", uuid::Uuid::new_v4()));
+        let mut child = std::process::Command::new("sleep").arg("5").spawn().unwrap();
+        std::fs::write(&path, format!("{}\n", child.id())).unwrap();
+
+        let err = PidFile::create(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
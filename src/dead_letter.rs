@@ -0,0 +1,51 @@
+//! Dead-Letter Module
+//!
+//! Tasks that still fail after their last retry are recorded in a dead-letter
+//! collection, so operators can inspect permanent failures apart from the
+//! regular tasks and requeue them once the cause is fixed. The task itself
+//! stays `Failed` in place, so dependents waiting on its failure still run.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A permanently failed task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub task_id: Uuid,
+    pub task_name: String,
+    pub project_id: Option<Uuid>,
+    /// Error of the last attempt
+    pub error: String,
+    pub exit_code: Option<i32>,
+    pub logs: Vec<String>,
+    /// Executions before giving up
+    pub attempts: u32,
+    pub dead_lettered_at: DateTime<Utc>,
+}
+
+impl DeadLetter {
+    /// Entry for a task whose final result is a failure; `None` otherwise
+    pub fn from_task(task: &Task, now: DateTime<Utc>) -> Option<Self> {
+        let Some(TaskResult::Failure { error, exit_code, logs, .. }) = &task.result else {
+            return None;
+        };
+        Some(Self {
+            task_id: task.id,
+            task_name: task.name.clone(),
+            project_id: task.project_id,
+            error: error.clone(),
+            exit_code: *exit_code,
+            logs: logs.clone(),
+            attempts: crate::retry::attempts(task),
+            dead_lettered_at: now,
+        })
+    }
+}
@@ -0,0 +1,309 @@
+//! Docker Executor Module
+//!
+//! Executor running task commands inside a throwaway Docker container through
+//! the `docker` CLI. The image and resource limits come from the task's
+//! `docker` metadata, falling back to the executor's defaults; the task's
+//! working directory is mounted at `/workspace`. Tasks pick it with
+//! `"executor": "docker"`, either on the task or on its project.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::*;
+use crate::executor::{failure, Executor, ScriptFile, SuspendRegistration, Suspension};
+use crate::task_logs::{capture, LogSink, LogStream};
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::process::Stdio;
+use std::time::Instant;
+use tokio::process::Command;
+use tracing::{info, warn};
+
+/// Executor name
+pub const DOCKER_EXECUTOR: &str = "docker";
+/// Task (or project) metadata key holding the container settings
+pub const DOCKER_METADATA_KEY: &str = "docker";
+/// Where the task's working directory is mounted in the container
+pub const WORKSPACE: &str = "/workspace";
+/// Where the script of a script task is mounted in the container
+const SCRIPT_DIR: &str = "/task-queue";
+
+/// Exit code `docker run` uses for its own errors, e.g. an unknown image
+const DOCKER_ERROR_EXIT: i32 = 125;
+
+/// Container settings; on a task they override the executor's defaults
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContainerSpec {
+    pub image: Option<String>,
+    /// Memory limit, e.g. `512m`
+    pub memory: Option<String>,
+    /// CPU limit, e.g. `1.5`
+    pub cpus: Option<f64>,
+    /// Docker network; `none` cuts the container off
+    pub network: Option<String>,
+    /// Extra `host:container[:ro]` volume mounts
+    #[serde(default)]
+    pub volumes: Vec<String>,
+}
+
+impl ContainerSpec {
+    /// Fill the settings this spec leaves unset from `defaults`
+    fn or(mut self, defaults: &ContainerSpec) -> Self {
+        self.image = self.image.or_else(|| defaults.image.clone());
+        self.memory = self.memory.or_else(|| defaults.memory.clone());
+        self.cpus = self.cpus.or(defaults.cpus);
+        self.network = self.network.or_else(|| defaults.network.clone());
+        self.volumes.splice(0..0, defaults.volumes.iter().cloned());
+        self
+    }
+}
+
+/// Removes the container when dropped, so a task stopped early (e.g. on
+/// timeout) doesn't leave it running
+struct ContainerGuard<'a> {
+    program: &'a str,
+    name: Option<String>,
+}
+
+impl Drop for ContainerGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(name) = self.name.take() {
+            let _ = std::process::Command::new(self.program)
+                .args(["rm", "--force", &name])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn();
+        }
+    }
+}
+
+/// Runs each task's `command` with `sh -c` in a new container
+#[derive(Debug, Clone)]
+pub struct DockerExecutor {
+    program: String,
+    defaults: ContainerSpec,
+}
+
+impl Default for DockerExecutor {
+    fn default() -> Self {
+        Self {
+            program: "docker".to_string(),
+            defaults: ContainerSpec::default(),
+        }
+    }
+}
+
+impl DockerExecutor {
+    pub fn new(defaults: ContainerSpec) -> Self {
+        Self {
+            defaults,
+            ..Self::default()
+        }
+    }
+
+    /// Use another Docker-compatible CLI, e.g. `podman`
+    pub fn with_program(mut self, program: &str) -> Self {
+        self.program = program.to_string();
+        self
+    }
+
+    fn spec(&self, task: &Task) -> std::result::Result<ContainerSpec, String> {
+        let spec: ContainerSpec = match task.metadata.get(DOCKER_METADATA_KEY) {
+            Some(value) => serde_json::from_value(value.clone())
+                .map_err(|e| format!("Invalid docker settings: {}", e))?,
+            None => ContainerSpec::default(),
+        };
+        Ok(spec.or(&self.defaults))
+    }
+
+    /// Arguments of the `docker run` invocation for a task
+    pub fn run_args(&self, task: &Task, container: &str) -> std::result::Result<Vec<String>, String> {
+        let spec = self.spec(task)?;
+        let image = spec.image.ok_or("No docker image set for the task")?;
+
+        let mut args = vec!["run".to_string(), "--rm".to_string(), "--name".to_string(), container.to_string()];
+        if let Some(memory) = spec.memory {
+            args.extend(["--memory".to_string(), memory]);
+        }
+        if let Some(cpus) = spec.cpus {
+            args.extend(["--cpus".to_string(), cpus.to_string()]);
+        }
+        if let Some(network) = spec.network {
+            args.extend(["--network".to_string(), network]);
+        }
+        if let Some(dir) = &task.working_directory {
+            args.extend(["--volume".to_string(), format!("{}:{}", dir, WORKSPACE)]);
+            args.extend(["--workdir".to_string(), WORKSPACE.to_string()]);
+        }
+        for volume in spec.volumes {
+            args.extend(["--volume".to_string(), volume]);
+        }
+        // Values are passed through the CLI's environment, not its arguments
+        let mut keys: Vec<&String> = task.environment.keys().collect();
+        keys.sort();
+        for key in keys {
+            args.extend(["--env".to_string(), key.clone()]);
+        }
+        match task.task_type {
+            // The script file written on the host is mounted into the container
+            TaskType::Script { interpreter } => {
+                let script = format!("{}/script.{}", SCRIPT_DIR, interpreter.extension());
+                let host = ScriptFile::path_for(task, interpreter);
+                args.extend(["--volume".to_string(), format!("{}:{}:ro", host.display(), script)]);
+                args.push(image);
+                args.extend(interpreter.invocation().iter().map(|arg| arg.to_string()));
+                args.push(script);
+            }
+            _ => args.extend([image, "sh".to_string(), "-c".to_string(), task.command.clone()]),
+        }
+        Ok(args)
+    }
+
+    async fn run(&self, task: &Task, logs: &LogSink) -> TaskResult {
+        if task.command.trim().is_empty() {
+            return failure("Task has no command to execute", None, Vec::new());
+        }
+        let container = format!("task-queue-{}", task.id);
+        let args = match self.run_args(task, &container) {
+            Ok(args) => args,
+            Err(e) => return failure(e, None, Vec::new()),
+        };
+
+        let script = match ScriptFile::write(task) {
+            Ok(script) => script,
+            Err(e) => return failure(format!("Failed to write script: {}", e), None, Vec::new()),
+        };
+
+        info!("Executing task {} ({}) in container {}", task.name, task.id, container);
+        let start = Instant::now();
+        let child = Command::new(&self.program)
+            .args(&args)
+            .envs(&task.environment)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) => return failure(format!("Failed to start {}: {}", self.program, e), None, Vec::new()),
+        };
+
+        let registration = SuspendRegistration::new(task.id, Suspension::Container {
+            program: self.program.clone(),
+            name: container.clone(),
+        });
+        let mut guard = ContainerGuard { program: &self.program, name: Some(container) };
+        let stdout = capture(child.stdout.take(), LogStream::Stdout, logs);
+        let stderr = capture(child.stderr.take(), LogStream::Stderr, logs);
+        let (status, stdout, stderr) = tokio::join!(child.wait(), stdout, stderr);
+        drop(registration);
+        // `--rm` cleans up after a normal exit
+        guard.name = None;
+        let status = match status {
+            Ok(status) => status,
+            Err(e) => return failure(format!("Failed to wait for {}: {}", self.program, e), None, Vec::new()),
+        };
+
+        let execution_time = start.elapsed();
+        let stdout = String::from_utf8_lossy(&stdout).to_string();
+        let stderr = String::from_utf8_lossy(&stderr).to_string();
+        let metrics = TaskMetrics {
+            execution_time,
+            ..Default::default()
+        };
+        if status.success() {
+            return TaskResult::Success {
+                output: stdout,
+                artifacts: Vec::new(),
+                metrics,
+            };
+        }
+
+        let exit_code = status.code();
+        TaskResult::Failure {
+            error: match exit_code {
+                Some(DOCKER_ERROR_EXIT) => format!("Docker could not run the container: {}", stderr.trim()),
+                Some(code) => format!("Command exited with code {}", code),
+                None => "Command terminated by signal".to_string(),
+            },
+            exit_code,
+            logs: stdout.lines().chain(stderr.lines()).map(str::to_string).collect(),
+            metrics: Some(metrics),
+        }
+    }
+}
+
+impl Executor for DockerExecutor {
+    fn name(&self) -> &str {
+        DOCKER_EXECUTOR
+    }
+
+    fn execute<'a>(&'a self, task: &'a Task) -> BoxFuture<'a, TaskResult> {
+        Box::pin(async move { self.run(task, &LogSink::discard()).await })
+    }
+
+    fn execute_logged<'a>(&'a self, task: &'a Task, logs: &'a LogSink) -> BoxFuture<'a, TaskResult> {
+        Box::pin(self.run(task, logs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_run_args_merge_task_settings_with_defaults() {
+        let executor = DockerExecutor::new(ContainerSpec {
+            image: Some("alpine:3".to_string()),
+            memory: Some("256m".to_string()),
+            volumes: vec!["/cache:/cache:ro".to_string()],
+            ..Default::default()
+        });
+        let task = Task::new("build")
+            .with_command("make test")
+            .with_working_directory("/src/app")
+            .with_environment("TOKEN", "secret")
+            .with_metadata(DOCKER_METADATA_KEY, json!({"image": "rust:1", "cpus": 2.0, "network": "none"}))
+            .build();
+
+        let args = executor.run_args(&task, "c1").unwrap();
+        assert_eq!(args.join(" "), [
+            "run --rm --name c1 --memory 256m --cpus 2 --network none",
+            "--volume /src/app:/workspace --workdir /workspace --volume /cache:/cache:ro",
+            "--env TOKEN rust:1 sh -c",
+        ].join(" ") + " make test");
+        assert!(!args.iter().any(|arg| arg.contains("secret")));
+    }
+
+    #[test]
+    fn test_run_args_mount_scripts() {
+        let executor = DockerExecutor::new(ContainerSpec { image: Some("python:3".to_string()), ..Default::default() });
+        let task = Task::new("report")
+            .with_command("print('hi')\n")
+            .with_type(TaskType::Script { interpreter: ScriptInterpreter::Python })
+            .build();
+
+        let args = executor.run_args(&task, "c1").unwrap();
+        let host = ScriptFile::path_for(&task, ScriptInterpreter::Python);
+        assert_eq!(args[args.len() - 4..], [
+            format!("{}:/task-queue/script.py:ro", host.display()),
+            "python:3".to_string(),
+            "python3".to_string(),
+            "/task-queue/script.py".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn test_run_args_need_an_image() {
+        let task = Task::new("build").with_command("make").build();
+        assert!(DockerExecutor::default().run_args(&task, "c1").is_err());
+
+        let task = Task::new("build").with_command("make").with_metadata(DOCKER_METADATA_KEY, json!({"cpus": "lots"})).build();
+        assert!(DockerExecutor::default().run_args(&task, "c1").unwrap_err().contains("Invalid docker settings"));
+    }
+}
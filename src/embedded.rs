@@ -0,0 +1,229 @@
+//! Embedded Task Queue Module
+//!
+//! In-process task queue built on the same core, storage and executor as the
+//! server, without the HTTP layer. Useful for tests and small tools.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::client::TaskQueueApi;
+use crate::core::*;
+use crate::error::{TaskQueueError, Result};
+use crate::executor::{Executor, ExecutorRegistry};
+use crate::server::TaskQueueServer;
+use crate::storage::{Storage, StorageEngine};
+use crate::vectorizer::VectorizerIntegration;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Builder for [`TaskQueue`]
+#[derive(Default)]
+pub struct TaskQueueBuilder {
+    storage: Option<Arc<dyn Storage>>,
+    executors: ExecutorRegistry,
+}
+
+impl TaskQueueBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Storage backend; defaults to a temporary in-memory database
+    pub fn storage(mut self, storage: impl Storage + 'static) -> Self {
+        self.storage = Some(Arc::new(storage));
+        self
+    }
+
+    /// Executor used by `run_task` for tasks that don't select one; defaults to the system shell
+    pub fn executor(mut self, executor: impl Executor + 'static) -> Self {
+        let name = executor.name().to_string();
+        self.executors.register(executor).set_default(&name);
+        self
+    }
+
+    /// Make an additional executor available to tasks that select it by name
+    pub fn register_executor(mut self, executor: impl Executor + 'static) -> Self {
+        self.executors.register(executor);
+        self
+    }
+
+    pub async fn build(self) -> Result<TaskQueue> {
+        let storage = match self.storage {
+            Some(storage) => storage,
+            None => Arc::new(StorageEngine::temporary()?),
+        };
+        let server = TaskQueueServer::with_components(
+            storage,
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await?;
+
+        Ok(TaskQueue {
+            server,
+            executors: self.executors,
+        })
+    }
+}
+
+/// In-process task queue
+///
+/// Cloning is cheap: clones share the same task state.
+#[derive(Clone)]
+pub struct TaskQueue {
+    server: TaskQueueServer,
+    executors: ExecutorRegistry,
+}
+
+impl TaskQueue {
+    /// Create a task queue builder
+    pub fn builder() -> TaskQueueBuilder {
+        TaskQueueBuilder::new()
+    }
+
+    /// Access the underlying server core for operations not wrapped here
+    pub fn server(&self) -> &TaskQueueServer {
+        &self.server
+    }
+
+    /// Submit a task and execute it immediately
+    pub async fn submit_and_run(&self, task: Task) -> Result<TaskResult> {
+        let task_id = self.server.submit_task(task).await?;
+        self.run_task(task_id).await
+    }
+
+    /// Execute a task now, regardless of its status, and record the result;
+    /// a failure with retries left leaves the task `Scheduled` for a retry
+    pub async fn run_task(&self, task_id: Uuid) -> Result<TaskResult> {
+        let task = self.server.start_task_execution(task_id).await?;
+        let result = self.server.execute_task(&self.executors, &task).await;
+        self.server.complete_task_execution(task_id, result.clone()).await?;
+        Ok(result)
+    }
+
+    /// Execute every `Pending` task whose dependencies are met, including
+    /// scheduled tasks and retries that are due, returning how many were run
+    pub async fn run_pending(&self) -> Result<usize> {
+        self.server.promote_scheduled_tasks(chrono::Utc::now()).await?;
+        let mut run = 0;
+        for task in self.server.ready_tasks().await? {
+            self.run_task(task.id).await?;
+            run += 1;
+        }
+        Ok(run)
+    }
+}
+
+impl TaskQueueApi for TaskQueue {
+    async fn submit_task(&self, task: Task) -> Result<Uuid> {
+        self.server.submit_task(task).await
+    }
+
+    async fn get_task(&self, task_id: &Uuid) -> Result<Task> {
+        self.server.get_task(*task_id).await
+    }
+
+    async fn get_task_status(&self, task_id: &Uuid) -> Result<TaskStatus> {
+        self.server.get_task_status(*task_id).await
+    }
+
+    async fn get_task_result(&self, task_id: &Uuid) -> Result<Option<TaskResult>> {
+        self.server.get_task_result(*task_id).await
+    }
+
+    async fn list_tasks(&self, project: Option<String>, status: Option<String>) -> Result<Vec<Task>> {
+        self.server.list_tasks(project, status).await
+    }
+
+    async fn submit_workflow(&self, workflow: Workflow) -> Result<Uuid> {
+        self.server.submit_workflow(workflow).await
+    }
+
+    async fn get_workflow(&self, workflow_id: &Uuid) -> Result<Workflow> {
+        self.server.get_workflow(*workflow_id).await
+    }
+
+    async fn get_workflow_status(&self, workflow_id: &Uuid) -> Result<WorkflowStatus> {
+        self.server.get_workflow_status(*workflow_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn queue_with_project() -> (TaskQueue, Uuid) {
+        let storage = StorageEngine::temporary().unwrap();
+        let project = Project::new("embedded");
+        storage.store_project(&project).await.unwrap();
+
+        let queue = TaskQueue::builder().storage(storage).build().await.unwrap();
+        (queue, project.id)
+    }
+
+    fn task(project_id: Uuid, name: &str, command: &str) -> Task {
+        let mut task = Task::new(name).with_command(command).build();
+        task.project_id = Some(project_id);
+        task
+    }
+
+    #[tokio::test]
+    async fn test_embedded_runs_task_in_process() {
+        let (queue, project_id) = queue_with_project().await;
+        let task = task(project_id, "echo", "echo hello");
+
+        let result = queue.submit_and_run(task.clone()).await.unwrap();
+        match result {
+            TaskResult::Success { output, .. } => assert_eq!(output.trim(), "hello"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(queue.get_task_status(&task.id).await.unwrap(), TaskStatus::Completed);
+    }
+
+    #[tokio::test]
+    async fn test_embedded_records_failures() {
+        let (queue, project_id) = queue_with_project().await;
+        let mut task = task(project_id, "fail", "exit 3");
+        task.retry_attempts = 1;
+        task.retry_delay = std::time::Duration::ZERO;
+
+        let result = queue.submit_and_run(task.clone()).await.unwrap();
+        assert!(matches!(result, TaskResult::Failure { exit_code: Some(3), .. }));
+        assert_eq!(queue.get_task_status(&task.id).await.unwrap(), TaskStatus::Scheduled);
+
+        assert_eq!(queue.run_pending().await.unwrap(), 1);
+        assert_eq!(queue.get_task_status(&task.id).await.unwrap(), TaskStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_embedded_captures_task_output() {
+        let (queue, project_id) = queue_with_project().await;
+        let task = task(project_id, "chatty", "echo out; echo err >&2; exit 1");
+
+        queue.submit_and_run(task.clone()).await.unwrap();
+        let logs = queue.server().get_task_logs(task.id, None).await.unwrap();
+        let mut output: Vec<(crate::task_logs::LogStream, &str)> = logs.iter().map(|c| (c.stream, c.data.as_str())).collect();
+        output.sort_by_key(|(stream, _)| *stream == crate::task_logs::LogStream::Stderr);
+        assert_eq!(output, vec![
+            (crate::task_logs::LogStream::Stdout, "out\n"),
+            (crate::task_logs::LogStream::Stderr, "err\n"),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_project_manifest_lists_project_tasks() {
+        let (queue, project_id) = queue_with_project().await;
+        let task = task(project_id, "build", "true");
+        queue.submit_task(task.clone()).await.unwrap();
+
+        let manifest = queue.server().project_manifest(&project_id).await.unwrap();
+        assert_eq!(manifest.name, "embedded");
+        assert_eq!(manifest.tasks.len(), 1);
+        assert_eq!(manifest.tasks[0].id, task.id);
+
+        let missing = queue.server().project_manifest(&Uuid::new_v4()).await;
+        assert!(matches!(missing, Err(TaskQueueError::ProjectNotFound { .. })));
+    }
+}
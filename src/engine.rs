@@ -0,0 +1,364 @@
+//! Execution Engine Module
+//!
+//! Background dispatcher that runs `Pending` tasks. Every poll it first
+//! creates the instances of due recurring tasks, releases `Scheduled` tasks
+//! whose start time has arrived and takes back tasks whose external worker
+//! let its lease expire, then looks for tasks whose dependencies are met and
+//! that don't select external workers, claims as many as there are free
+//! slots, node capacity for their resource requests and room in their
+//! queue, highest priority first, and runs each with the executor it selects
+//! after resolving its secret references, capturing its output into the
+//! task's log. The result, with the measured metrics and any secret values
+//! redacted, is recorded on the task; failed tasks with retries left are
+//! scheduled again after a backoff. While the server drains, nothing is
+//! started.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::config::ExecutionConfig;
+use crate::core::*;
+use crate::error::Result;
+use crate::executor::{failure, ExecutorRegistry};
+use crate::resources::{self, ResourceTracker, Resources};
+use crate::server::TaskQueueServer;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tracing::{error, info};
+
+/// Runs pending tasks with bounded concurrency
+#[derive(Clone)]
+pub struct ExecutionEngine {
+    server: Arc<TaskQueueServer>,
+    executors: ExecutorRegistry,
+    slots: Arc<Semaphore>,
+    max_concurrent: usize,
+    resources: ResourceTracker,
+    poll_interval: Duration,
+}
+
+impl ExecutionEngine {
+    pub fn new(server: Arc<TaskQueueServer>, executors: ExecutorRegistry, max_concurrent: usize) -> Self {
+        let max_concurrent = max_concurrent.max(1);
+        Self {
+            server,
+            executors,
+            slots: Arc::new(Semaphore::new(max_concurrent)),
+            max_concurrent,
+            resources: ResourceTracker::new(Resources::host()),
+            poll_interval: Duration::from_millis(500),
+        }
+    }
+
+    /// Node capacity shared by the resource requests of running tasks
+    pub fn with_capacity(mut self, capacity: Resources) -> Self {
+        self.resources = ResourceTracker::new(capacity);
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Tasks currently executing
+    pub fn running(&self) -> usize {
+        self.max_concurrent - self.slots.available_permits()
+    }
+
+    /// Start every ready task there is a free slot for; returns how many started
+    pub async fn dispatch(&self) -> Result<usize> {
+        let mut started = 0;
+        let now = chrono::Utc::now();
+        self.server.materialize_recurrences(now).await?;
+        self.server.promote_scheduled_tasks(now).await?;
+        self.server.expire_leases(now).await?;
+        // Draining lets what runs finish but starts nothing new
+        if self.server.maintenance().is_draining() {
+            return Ok(0);
+        }
+
+        let mut gate = self.server.queue_gate().await?;
+        for task in self.server.ready_tasks().await? {
+            // Left for the external workers it selects
+            if crate::workers::selector(&task).is_some() {
+                continue;
+            }
+            let Ok(slot) = self.slots.clone().try_acquire_owned() else {
+                break;
+            };
+            let request = Resources::requested_by(&task).unwrap_or_default();
+            let capacity = self.resources.capacity();
+            if !request.fits_within(&capacity) {
+                // Would never start here; fail it rather than leave it waiting
+                if let Some(task) = self.server.claim_task(task.id).await? {
+                    let error = resources::oversized_error(request, capacity);
+                    self.server.complete_task_execution(task.id, failure(error, None, vec![])).await?;
+                }
+                continue;
+            }
+            // Smaller tasks further down may still fit
+            let Some(reservation) = self.resources.try_reserve(request) else {
+                continue;
+            };
+            // Held back by its queue's concurrency or rate limit
+            if !gate.admit(&task) {
+                continue;
+            }
+            // Another dispatcher or a user may have changed the task meanwhile
+            let Some(task) = self.server.claim_task(task.id).await? else {
+                continue;
+            };
+
+            let server = self.server.clone();
+            let executors = self.executors.clone();
+            tokio::spawn(async move {
+                let result = server.execute_task(&executors, &task).await;
+                if let Err(e) = server.complete_task_execution(task.id, result).await {
+                    error!("Failed to record result of task {}: {}", task.id, e);
+                }
+                drop(reservation);
+                drop(slot);
+            });
+            started += 1;
+        }
+
+        Ok(started)
+    }
+
+    /// Wait until no task is executing
+    pub async fn idle(&self) {
+        if let Ok(all) = self.slots.acquire_many(self.max_concurrent as u32).await {
+            drop(all);
+        }
+    }
+
+    /// Dispatch forever
+    pub async fn run(self) {
+        info!(
+            "Execution engine started: up to {} concurrent tasks within {}, executors {:?}",
+            self.max_concurrent,
+            self.resources.capacity(),
+            self.executors.names()
+        );
+        let mut ticker = tokio::time::interval(self.poll_interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = self.dispatch().await {
+                error!("Failed to dispatch tasks: {}", e);
+            }
+        }
+    }
+}
+
+/// Start executing pending tasks in the background
+pub fn spawn(server: Arc<TaskQueueServer>, config: &ExecutionConfig) -> tokio::task::JoinHandle<()> {
+    let engine = ExecutionEngine::new(
+        server,
+        ExecutorRegistry::from_config(config),
+        config.max_concurrent_tasks as usize,
+    )
+    .with_capacity(config.capacity.unwrap_or_else(Resources::host))
+    .with_poll_interval(Duration::from_millis(config.poll_interval_ms));
+    tokio::spawn(engine.run())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::TaskQueueError;
+    use crate::storage::StorageEngine;
+    use crate::vectorizer::VectorizerIntegration;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_dispatch_runs_ready_tasks_in_dependency_order() {
+        let storage = Arc::new(StorageEngine::temporary().unwrap());
+        let server = Arc::new(
+            TaskQueueServer::with_components(storage, Arc::new(VectorizerIntegration::new_dummy()))
+                .await
+                .unwrap(),
+        );
+        let project_id = server.create_project("engine".to_string(), None).await.unwrap();
+
+        let mut build = Task::new("build").with_command("echo built").build();
+        build.project_id = Some(project_id);
+        build.status = TaskStatus::Pending;
+        let mut deploy = Task::new("deploy").with_command("exit 4").with_retry(0, Duration::ZERO).build();
+        deploy.project_id = Some(project_id);
+        deploy.status = TaskStatus::Pending;
+        deploy.add_dependency(build.id, None, DependencyCondition::Success, true);
+        server.submit_task(build.clone()).await.unwrap();
+        server.submit_task(deploy.clone()).await.unwrap();
+
+        let engine = ExecutionEngine::new(server.clone(), ExecutorRegistry::new(), 4);
+        assert_eq!(engine.dispatch().await.unwrap(), 1);
+        engine.idle().await;
+        assert_eq!(engine.dispatch().await.unwrap(), 1);
+        engine.idle().await;
+        assert_eq!(engine.dispatch().await.unwrap(), 0);
+
+        let build = server.get_task(build.id).await.unwrap();
+        assert_eq!(build.status, TaskStatus::Completed);
+        match build.result {
+            Some(TaskResult::Success { output, metrics, .. }) => {
+                assert_eq!(output.trim(), "built");
+                assert!(metrics.execution_time > Duration::ZERO);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let deploy = server.get_task(deploy.id).await.unwrap();
+        assert!(matches!(deploy.result, Some(TaskResult::Failure { exit_code: Some(4), .. })));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_failed_tasks_are_retried() {
+        let storage = Arc::new(StorageEngine::temporary().unwrap());
+        let server = Arc::new(
+            TaskQueueServer::with_components(storage, Arc::new(VectorizerIntegration::new_dummy()))
+                .await
+                .unwrap(),
+        );
+        let project_id = server.create_project("engine".to_string(), None).await.unwrap();
+
+        let mut task = Task::new("flaky").with_command("exit 1").with_retry(1, Duration::ZERO).build();
+        task.project_id = Some(project_id);
+        task.status = TaskStatus::Pending;
+        let task_id = server.submit_task(task).await.unwrap();
+
+        let engine = ExecutionEngine::new(server.clone(), ExecutorRegistry::new(), 4);
+        assert_eq!(engine.dispatch().await.unwrap(), 1);
+        engine.idle().await;
+        let task = server.get_task(task_id).await.unwrap();
+        assert_eq!(task.status, TaskStatus::Scheduled);
+        assert!(task.result.is_none());
+
+        assert_eq!(engine.dispatch().await.unwrap(), 1);
+        engine.idle().await;
+        let task = server.get_task(task_id).await.unwrap();
+        assert_eq!(task.status, TaskStatus::Failed);
+        assert_eq!(crate::retry::attempts(&task), 2);
+        assert_eq!(task.phases.iter().filter(|p| p.phase == TaskStatus::Running).count(), 2);
+
+        let dead = server.list_dead_letters().await.unwrap();
+        assert_eq!(dead.len(), 1);
+        assert_eq!((dead[0].task_id, dead[0].attempts, dead[0].exit_code), (task_id, 2, Some(1)));
+
+        let task = server.requeue_dead_letter(task_id).await.unwrap().unwrap();
+        assert_eq!(task.status, TaskStatus::Pending);
+        assert_eq!(crate::retry::attempts(&task), 0);
+        assert!(server.list_dead_letters().await.unwrap().is_empty());
+        assert!(server.requeue_dead_letter(task_id).await.unwrap().is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_running_tasks_can_be_paused_and_resumed() {
+        let storage = Arc::new(StorageEngine::temporary().unwrap());
+        let server = Arc::new(
+            TaskQueueServer::with_components(storage, Arc::new(VectorizerIntegration::new_dummy()))
+                .await
+                .unwrap(),
+        );
+        let project_id = server.create_project("engine".to_string(), None).await.unwrap();
+
+        let mut task = Task::new("slow").with_command("sleep 0.3; echo done").build();
+        task.project_id = Some(project_id);
+        task.status = TaskStatus::Pending;
+        let task_id = server.submit_task(task).await.unwrap();
+        assert!(matches!(server.pause_task(task_id).await, Err(TaskQueueError::InvalidStatusTransition(_))));
+
+        let engine = ExecutionEngine::new(server.clone(), ExecutorRegistry::new(), 4);
+        assert_eq!(engine.dispatch().await.unwrap(), 1);
+        // The command may not have been spawned yet
+        let mut paused = server.pause_task(task_id).await;
+        for _ in 0..100 {
+            if paused.is_ok() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            paused = server.pause_task(task_id).await;
+        }
+        assert_eq!(paused.unwrap().status, TaskStatus::Paused);
+
+        tokio::time::sleep(Duration::from_millis(600)).await;
+        let task = server.get_task(task_id).await.unwrap();
+        assert_eq!(task.status, TaskStatus::Paused);
+        assert!(task.result.is_none());
+        assert!(!task.can_transition_to(&TaskStatus::Completed));
+
+        assert_eq!(server.resume_task(task_id).await.unwrap().status, TaskStatus::Running);
+        engine.idle().await;
+        assert_eq!(server.get_task(task_id).await.unwrap().status, TaskStatus::Completed);
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_tasks_wait_for_run_at() {
+        let storage = Arc::new(StorageEngine::temporary().unwrap());
+        let server = Arc::new(
+            TaskQueueServer::with_components(storage, Arc::new(VectorizerIntegration::new_dummy()))
+                .await
+                .unwrap(),
+        );
+        let project_id = server.create_project("engine".to_string(), None).await.unwrap();
+
+        let run_at = chrono::Utc::now() + chrono::Duration::hours(1);
+        let mut task = Task::new("maintenance")
+            .with_command("true")
+            .with_metadata(crate::calendar::RUN_AT_KEY, serde_json::json!(run_at.to_rfc3339()))
+            .build();
+        task.project_id = Some(project_id);
+        let task_id = server.submit_task(task).await.unwrap();
+        assert_eq!(server.get_task(task_id).await.unwrap().status, TaskStatus::Scheduled);
+
+        let engine = ExecutionEngine::new(server.clone(), ExecutorRegistry::new(), 4);
+        assert_eq!(engine.dispatch().await.unwrap(), 0);
+        assert!(server.promote_scheduled_tasks(run_at - chrono::Duration::seconds(1)).await.unwrap().is_empty());
+        assert_eq!(server.promote_scheduled_tasks(run_at).await.unwrap(), vec![task_id]);
+        assert_eq!(server.get_task(task_id).await.unwrap().status, TaskStatus::Pending);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_dispatch_respects_node_capacity() {
+        let storage = Arc::new(StorageEngine::temporary().unwrap());
+        let server = Arc::new(
+            TaskQueueServer::with_components(storage, Arc::new(VectorizerIntegration::new_dummy()))
+                .await
+                .unwrap(),
+        );
+        let project_id = server.create_project("engine".to_string(), None).await.unwrap();
+        let submit = |name: &str, cpu: u32| {
+            let mut task = Task::new(name)
+                .with_command("sleep 0.1")
+                .with_retry(0, Duration::ZERO)
+                .with_metadata(resources::RESOURCES_METADATA_KEY, serde_json::json!({"cpu": cpu, "memory_mb": 512}))
+                .build();
+            task.project_id = Some(project_id);
+            task.status = TaskStatus::Pending;
+            let server = server.clone();
+            async move { server.submit_task(task).await.unwrap() }
+        };
+        let heavy = submit("heavy", 8).await;
+        for name in ["test-1", "test-2", "test-3"] {
+            submit(name, 1).await;
+        }
+
+        let engine = ExecutionEngine::new(server.clone(), ExecutorRegistry::new(), 4)
+            .with_capacity(Resources { cpu: 2.0, memory_mb: 2048 });
+        assert_eq!(engine.dispatch().await.unwrap(), 2);
+        assert_eq!(engine.running(), 2);
+        engine.idle().await;
+        assert_eq!(engine.dispatch().await.unwrap(), 1);
+        engine.idle().await;
+
+        let heavy = server.get_task(heavy).await.unwrap();
+        assert_eq!(heavy.status, TaskStatus::Failed);
+        assert!(matches!(heavy.result, Some(TaskResult::Failure { error, .. }) if error.contains("node only has")));
+    }
+}
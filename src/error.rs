@@ -19,6 +19,9 @@ pub enum TaskQueueError {
     #[error("Project not found: {project_id}")]
     ProjectNotFound { project_id: String },
 
+    #[error("Tenant not found: {tenant_id}")]
+    TenantNotFound { tenant_id: String },
+
     #[error("Circular dependency detected: {cycle}")]
     CircularDependency { cycle: String },
 
@@ -31,12 +34,30 @@ pub enum TaskQueueError {
     #[error("Storage error: {0}")]
     StorageError(#[from] sled::Error),
 
+    #[cfg(feature = "redis")]
+    #[error("Redis error: {0}")]
+    RedisError(#[from] redis::RedisError),
+
+    #[cfg(feature = "sqlite")]
+    #[error("SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+
+    #[cfg(feature = "s3")]
+    #[error("Object store error: {0}")]
+    ObjectStoreError(#[from] object_store::Error),
+
     #[error("Serialization error: {0}")]
     SerializationError(#[from] serde_json::Error),
 
+    #[error("Codec error: {0}")]
+    CodecError(String),
+
     #[error("Network error: {0}")]
     NetworkError(#[from] reqwest::Error),
 
+    #[error("Broker error: {0}")]
+    BrokerError(String),
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
@@ -58,6 +79,9 @@ pub enum TaskQueueError {
     #[error("Permission denied: {operation}")]
     PermissionDenied { operation: String },
 
+    #[error("Task {task_id} is not leased to worker {worker_id}")]
+    LeaseNotHeld { task_id: String, worker_id: String },
+
     #[error("Invalid task definition: {reason}")]
     InvalidTaskDefinition { reason: String },
 
@@ -67,6 +91,15 @@ pub enum TaskQueueError {
     #[error("Validation error: {reason}")]
     ValidationError { reason: String },
 
+    #[error("Precondition failed: the current version is {version}")]
+    PreconditionFailed { version: u64 },
+
+    #[error("Write conflict: {key} changed while it was being written")]
+    WriteConflict { key: String },
+
+    #[error("Invalid fields: {}", .errors.iter().map(|e| e.field.as_str()).collect::<Vec<_>>().join(", "))]
+    InvalidFields { errors: Vec<crate::core::FieldError> },
+
     #[error("Internal error: {0}")]
     InternalError(String),
 }
@@ -0,0 +1,231 @@
+//! Completion Estimates Module
+//!
+//! Predicts when in-flight tasks, workflows and projects will finish from how
+//! long each development phase has taken on past tasks. A task's estimate is
+//! the time left in its current phase plus the typical duration of every phase
+//! after it, and is flagged when it lands after the task's due date.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::calendar::{EventTime, DUE_DATE_KEY};
+use crate::core::*;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Development phases in the order a task goes through them
+pub const PIPELINE: [TaskStatus; 5] = [
+    TaskStatus::Planning,
+    TaskStatus::Implementation,
+    TaskStatus::TestCreation,
+    TaskStatus::Testing,
+    TaskStatus::AIReview,
+];
+
+/// Typical duration of each development phase
+#[derive(Debug, Clone, Default)]
+pub struct PhaseDurations {
+    medians: HashMap<String, Duration>,
+    samples: usize,
+}
+
+fn phase_key(phase: &TaskStatus) -> String {
+    format!("{:?}", phase)
+}
+
+fn is_open(task: &Task) -> bool {
+    !matches!(
+        task.status,
+        TaskStatus::Completed | TaskStatus::Finalized | TaskStatus::Failed | TaskStatus::Cancelled
+    )
+}
+
+impl PhaseDurations {
+    /// Learn from every completed phase of the given tasks
+    pub fn learn(tasks: &[Task]) -> Self {
+        let mut durations: HashMap<String, Vec<Duration>> = HashMap::new();
+        let mut samples = 0;
+
+        for phase in tasks.iter().flat_map(|t| &t.phases) {
+            if let (Some(started), Some(completed)) = (phase.started_at, phase.completed_at)
+                && completed >= started
+            {
+                durations.entry(phase_key(&phase.phase)).or_default().push(completed - started);
+                samples += 1;
+            }
+        }
+
+        let medians = durations
+            .into_iter()
+            .map(|(phase, mut values)| {
+                values.sort();
+                (phase, values[values.len() / 2])
+            })
+            .collect();
+        Self { medians, samples }
+    }
+
+    /// Number of completed phases the estimates are based on
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    pub fn median(&self, phase: &TaskStatus) -> Option<Duration> {
+        self.medians.get(&phase_key(phase)).copied()
+    }
+
+    /// When the task is expected to finish; `None` for closed tasks, tasks
+    /// outside the development pipeline, and phases never seen completed
+    pub fn estimate_task(&self, task: &Task, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if !is_open(task) {
+            return None;
+        }
+        let position = PIPELINE.iter().position(|phase| *phase == task.current_phase)?;
+
+        let started = task.phases.iter().rev()
+            .find(|p| p.phase == task.current_phase)
+            .and_then(|p| p.started_at)
+            .unwrap_or(now);
+        // A phase running longer than usual is assumed to be about to finish
+        let mut remaining = (self.median(&task.current_phase)? - (now - started)).max(Duration::zero());
+        for phase in &PIPELINE[position + 1..] {
+            remaining += self.median(phase)?;
+        }
+        Some(now + remaining)
+    }
+
+    /// Estimate for a task together with its due date
+    pub fn task_estimate(&self, task: &Task, now: DateTime<Utc>) -> TaskEstimate {
+        let estimated_completion_at = self.estimate_task(task, now);
+        let due_at = due_date(task);
+        TaskEstimate {
+            task_id: task.id,
+            estimated_completion_at,
+            due_at,
+            past_due: matches!((estimated_completion_at, due_at), (Some(eta), Some(due)) if eta > due),
+        }
+    }
+
+    /// A group of tasks finishes when its last open task does
+    pub fn estimate_all(&self, tasks: &[Task], now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut latest = None;
+        for task in tasks.iter().filter(|t| is_open(t)) {
+            let eta = self.estimate_task(task, now)?;
+            latest = latest.max(Some(eta));
+        }
+        latest
+    }
+
+    /// Progress and estimate of a project from its tasks
+    pub fn project_stats(&self, project: &Project, tasks: &[Task], now: DateTime<Utc>) -> ProjectStats {
+        let mut tasks_by_status = BTreeMap::new();
+        for task in tasks {
+            *tasks_by_status.entry(format!("{:?}", task.status)).or_insert(0) += 1;
+        }
+
+        let estimated_completion_at = self.estimate_all(tasks, now);
+        ProjectStats {
+            project_id: project.id,
+            total_tasks: tasks.len(),
+            open_tasks: tasks.iter().filter(|t| is_open(t)).count(),
+            tasks_by_status,
+            due_date: project.due_date,
+            estimated_completion_at,
+            past_due: matches!((estimated_completion_at, project.due_date), (Some(eta), Some(due)) if eta > due),
+            tasks_past_due: tasks.iter()
+                .map(|task| self.task_estimate(task, now))
+                .filter(|estimate| estimate.past_due)
+                .collect(),
+            estimate_samples: self.samples,
+        }
+    }
+}
+
+/// Due date from the task's `due_date` metadata; plain dates end at midnight UTC
+pub fn due_date(task: &Task) -> Option<DateTime<Utc>> {
+    let value = task.metadata.get(DUE_DATE_KEY)?.as_str()?;
+    match EventTime::parse(value)? {
+        EventTime::At(at) => Some(at),
+        EventTime::Date(date) => date.succ_opt()?.and_hms_opt(0, 0, 0).map(|at| at.and_utc()),
+    }
+}
+
+/// Completion estimate for one task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskEstimate {
+    pub task_id: uuid::Uuid,
+    pub estimated_completion_at: Option<DateTime<Utc>>,
+    pub due_at: Option<DateTime<Utc>>,
+    /// The estimate lands after the due date
+    pub past_due: bool,
+}
+
+/// Served by `GET /projects/{id}/stats`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectStats {
+    pub project_id: uuid::Uuid,
+    pub total_tasks: usize,
+    pub open_tasks: usize,
+    pub tasks_by_status: BTreeMap<String, usize>,
+    pub due_date: Option<DateTime<Utc>>,
+    /// When the last open task is expected to finish
+    pub estimated_completion_at: Option<DateTime<Utc>>,
+    /// The estimate lands after the project's due date
+    pub past_due: bool,
+    /// Open tasks expected to finish after their own due date
+    pub tasks_past_due: Vec<TaskEstimate>,
+    /// Completed phases the estimates are based on
+    pub estimate_samples: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phase(phase: TaskStatus, started: DateTime<Utc>, hours: Option<i64>) -> TaskPhase {
+        TaskPhase {
+            phase,
+            started_at: Some(started),
+            completed_at: hours.map(|h| started + Duration::hours(h)),
+            documentation: None,
+            artifacts: Vec::new(),
+            ai_reviews: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_estimates_from_past_phase_durations() {
+        let now = Utc::now();
+        let start = now - Duration::days(30);
+
+        // Two finished tasks: phases took 2h/10h/1h/1h/4h and 4h/20h/3h/1h/2h
+        let mut history = Vec::new();
+        for hours in [[2, 10, 1, 1, 4], [4, 20, 3, 1, 2]] {
+            let mut task = Task::new("done").build();
+            task.phases = PIPELINE.iter().zip(hours).map(|(p, h)| phase(p.clone(), start, Some(h))).collect();
+            task.status = TaskStatus::Finalized;
+            history.push(task);
+        }
+
+        // In implementation for 5 of the median 20 hours, then 3h + 1h + 4h to go
+        let mut task = Task::new("in flight").build();
+        task.phases = vec![phase(TaskStatus::Implementation, now - Duration::hours(5), None)];
+        task.current_phase = TaskStatus::Implementation;
+        task.status = TaskStatus::Implementation;
+        task.metadata.insert(DUE_DATE_KEY.to_string(), serde_json::json!((now + Duration::hours(12)).to_rfc3339()));
+        history.push(task.clone());
+
+        let durations = PhaseDurations::learn(&history);
+        assert_eq!(durations.samples(), 10);
+        let estimate = durations.task_estimate(&task, now);
+        assert_eq!(estimate.estimated_completion_at, Some(now + Duration::hours(23)));
+        assert!(estimate.past_due);
+
+        assert_eq!(durations.estimate_task(&history[0], now), None);
+        assert_eq!(PhaseDurations::default().estimate_task(&task, now), None);
+    }
+}
@@ -0,0 +1,109 @@
+//! ETag Module
+//!
+//! Tasks, projects and workflows carry a `version` bumped on every write,
+//! served as a strong `ETag` by their GETs. A client holding a copy sends it
+//! back in `If-None-Match` and gets `304 Not Modified` while it's current.
+//! Sent in `If-Match` on an update, it makes the update apply only to the
+//! version the client last read; if someone changed the record since, the
+//! update fails with `412 Precondition Failed` and nothing is written.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::error::{TaskQueueError, Result};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+
+/// The ETag of a version
+pub fn etag(version: u64) -> String {
+    format!("\"{}\"", version)
+}
+
+/// Versions listed in an ETag header; `W/` weak tags count as their version
+fn versions(value: &str) -> Vec<u64> {
+    value.split(',')
+        .filter_map(|tag| {
+            let tag = tag.trim();
+            let tag = tag.strip_prefix("W/").unwrap_or(tag);
+            tag.strip_prefix('"')?.strip_suffix('"')?.parse().ok()
+        })
+        .collect()
+}
+
+/// Versions an update may apply to, from its `If-Match` header
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Precondition {
+    /// No header, or `*`
+    #[default]
+    Any,
+    Versions(Vec<u64>),
+}
+
+impl Precondition {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        match headers.get(header::IF_MATCH).and_then(|value| value.to_str().ok()) {
+            None => Self::Any,
+            Some(value) if value.trim() == "*" => Self::Any,
+            // Weak tags never match under the strong comparison If-Match uses
+            Some(value) => Self::Versions(
+                value.split(',')
+                    .filter(|tag| !tag.trim().starts_with("W/"))
+                    .flat_map(versions)
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Fail unless the record is at a version the client expects
+    pub fn check(&self, version: u64) -> Result<()> {
+        match self {
+            Self::Versions(expected) if !expected.contains(&version) => {
+                Err(TaskQueueError::PreconditionFailed { version })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Whether the client's `If-None-Match` already lists the current version
+pub fn is_fresh(headers: &HeaderMap, version: u64) -> bool {
+    match headers.get(header::IF_NONE_MATCH).and_then(|value| value.to_str().ok()) {
+        Some(value) => value.trim() == "*" || versions(value).contains(&version),
+        None => false,
+    }
+}
+
+/// Respond with `value` and its ETag, or with `304 Not Modified` when the
+/// client's copy is current
+pub fn respond<T: Serialize>(headers: &HeaderMap, version: u64, value: T) -> Response {
+    let tag = HeaderValue::from_str(&etag(version)).expect("an ETag is a valid header value");
+    if is_fresh(headers, version) {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, tag)]).into_response();
+    }
+    ([(header::ETAG, tag)], Json(value)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matching_etags() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("W/\"3\", \"4\""));
+        headers.insert(header::IF_MATCH, HeaderValue::from_static("W/\"3\", \"4\""));
+        assert!(is_fresh(&headers, 3));
+        assert!(!is_fresh(&headers, 5));
+
+        let precondition = Precondition::from_headers(&headers);
+        assert_eq!(precondition, Precondition::Versions(vec![4]));
+        assert!(precondition.check(4).is_ok());
+        assert!(matches!(precondition.check(3), Err(TaskQueueError::PreconditionFailed { version: 3 })));
+        assert!(Precondition::from_headers(&HeaderMap::new()).check(7).is_ok());
+    }
+}
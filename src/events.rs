@@ -0,0 +1,585 @@
+//! Task Lifecycle Events Module
+//!
+//! Typed task/workflow/project lifecycle events, subscription filters, the
+//! server's in-process event bus and the Server-Sent Events framing shared by
+//! the server stream and the client. `GET /events` streams the bus to
+//! dashboards and integrations, filtered by the same query parameters the
+//! client sends; the bus keeps its latest events so a reconnecting stream
+//! resumes after the `Last-Event-ID` (or `cursor`) it last saw.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::*;
+use crate::error::Result;
+use crate::history::{status_name, StateChange};
+use crate::progress::TaskProgress;
+use crate::storage::Storage;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::broadcast;
+use tracing::error;
+use uuid::Uuid;
+
+/// Kind of lifecycle event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TaskEventKind {
+    TaskCreated,
+    TaskUpdated,
+    TaskStatusChanged,
+    TaskCompleted,
+    TaskFailed,
+    TaskCancelled,
+    TaskDeleted,
+    /// Progress reported while the task runs
+    TaskProgress,
+    WorkflowCreated,
+    WorkflowStatusChanged,
+    ProjectCreated,
+    ProjectUpdated,
+    ProjectDeleted,
+}
+
+impl TaskEventKind {
+    /// Name used for the SSE `event:` field and query filters
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskEventKind::TaskCreated => "task_created",
+            TaskEventKind::TaskUpdated => "task_updated",
+            TaskEventKind::TaskStatusChanged => "task_status_changed",
+            TaskEventKind::TaskCompleted => "task_completed",
+            TaskEventKind::TaskFailed => "task_failed",
+            TaskEventKind::TaskCancelled => "task_cancelled",
+            TaskEventKind::TaskDeleted => "task_deleted",
+            TaskEventKind::TaskProgress => "task_progress",
+            TaskEventKind::WorkflowCreated => "workflow_created",
+            TaskEventKind::WorkflowStatusChanged => "workflow_status_changed",
+            TaskEventKind::ProjectCreated => "project_created",
+            TaskEventKind::ProjectUpdated => "project_updated",
+            TaskEventKind::ProjectDeleted => "project_deleted",
+        }
+    }
+
+    /// Parse an event kind from its wire name
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "task_created" => Some(TaskEventKind::TaskCreated),
+            "task_updated" => Some(TaskEventKind::TaskUpdated),
+            "task_status_changed" => Some(TaskEventKind::TaskStatusChanged),
+            "task_completed" => Some(TaskEventKind::TaskCompleted),
+            "task_failed" => Some(TaskEventKind::TaskFailed),
+            "task_cancelled" => Some(TaskEventKind::TaskCancelled),
+            "task_deleted" => Some(TaskEventKind::TaskDeleted),
+            "task_progress" => Some(TaskEventKind::TaskProgress),
+            "workflow_created" => Some(TaskEventKind::WorkflowCreated),
+            "workflow_status_changed" => Some(TaskEventKind::WorkflowStatusChanged),
+            "project_created" => Some(TaskEventKind::ProjectCreated),
+            "project_updated" => Some(TaskEventKind::ProjectUpdated),
+            "project_deleted" => Some(TaskEventKind::ProjectDeleted),
+            _ => None,
+        }
+    }
+}
+
+/// A task, workflow or project lifecycle event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskEvent {
+    /// Monotonic sequence number, used as the resume cursor
+    pub sequence: u64,
+    pub kind: TaskEventKind,
+    pub task_id: Option<Uuid>,
+    pub workflow_id: Option<Uuid>,
+    pub project_id: Option<Uuid>,
+    pub status: Option<TaskStatus>,
+    pub timestamp: DateTime<Utc>,
+    /// Agent whose request caused the event
+    #[serde(default)]
+    pub agent: Option<Uuid>,
+    /// Tenant owning what changed
+    #[serde(default)]
+    pub tenant_id: Option<Uuid>,
+    #[serde(default)]
+    pub data: serde_json::Value,
+}
+
+/// Subscription filter; empty fields match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventFilter {
+    #[serde(default)]
+    pub kinds: Vec<TaskEventKind>,
+    pub task_id: Option<Uuid>,
+    pub workflow_id: Option<Uuid>,
+    pub project_id: Option<Uuid>,
+}
+
+impl EventFilter {
+    /// Filter matching every event
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    pub fn with_kind(mut self, kind: TaskEventKind) -> Self {
+        self.kinds.push(kind);
+        self
+    }
+
+    pub fn with_task(mut self, task_id: Uuid) -> Self {
+        self.task_id = Some(task_id);
+        self
+    }
+
+    pub fn with_workflow(mut self, workflow_id: Uuid) -> Self {
+        self.workflow_id = Some(workflow_id);
+        self
+    }
+
+    pub fn with_project(mut self, project_id: Uuid) -> Self {
+        self.project_id = Some(project_id);
+        self
+    }
+
+    /// Check whether an event passes this filter
+    pub fn matches(&self, event: &TaskEvent) -> bool {
+        if !self.kinds.is_empty() && !self.kinds.contains(&event.kind) {
+            return false;
+        }
+        if self.task_id.is_some() && self.task_id != event.task_id {
+            return false;
+        }
+        if self.workflow_id.is_some() && self.workflow_id != event.workflow_id {
+            return false;
+        }
+        if self.project_id.is_some() && self.project_id != event.project_id {
+            return false;
+        }
+        true
+    }
+
+    /// Parse the query parameters written by `to_query`; `kinds` is
+    /// comma-separated
+    pub fn from_query(params: &std::collections::HashMap<String, String>) -> std::result::Result<Self, String> {
+        let id = |name: &str| {
+            params.get(name)
+                .map(|id| Uuid::parse_str(id).map_err(|_| format!("{} is not a UUID", name)))
+                .transpose()
+        };
+        let kinds = match params.get("kinds") {
+            Some(kinds) => kinds.split(',')
+                .filter(|kind| !kind.is_empty())
+                .map(|kind| TaskEventKind::parse(kind.trim()).ok_or_else(|| format!("unknown event kind {}", kind)))
+                .collect::<std::result::Result<_, _>>()?,
+            None => Vec::new(),
+        };
+        Ok(Self {
+            kinds,
+            task_id: id("task_id")?,
+            workflow_id: id("workflow_id")?,
+            project_id: id("project_id")?,
+        })
+    }
+
+    /// Encode the filter as query parameters
+    pub fn to_query(&self) -> Vec<(String, String)> {
+        let mut params = Vec::new();
+        if !self.kinds.is_empty() {
+            let kinds: Vec<&str> = self.kinds.iter().map(|k| k.as_str()).collect();
+            params.push(("kinds".to_string(), kinds.join(",")));
+        }
+        if let Some(task_id) = self.task_id {
+            params.push(("task_id".to_string(), task_id.to_string()));
+        }
+        if let Some(workflow_id) = self.workflow_id {
+            params.push(("workflow_id".to_string(), workflow_id.to_string()));
+        }
+        if let Some(project_id) = self.project_id {
+            params.push(("project_id".to_string(), project_id.to_string()));
+        }
+        params
+    }
+}
+
+/// In-process bus the server publishes lifecycle events on
+///
+/// Subscribers receive events through a broadcast channel and may miss some
+/// if they lag. When the outbox is enabled every event is also written to
+/// storage before it is broadcast, for consumers that need at-least-once
+/// delivery.
+pub struct EventBus {
+    sequence: AtomicU64,
+    sender: broadcast::Sender<TaskEvent>,
+    task_statuses: DashMap<Uuid, TaskStatus>,
+    workflow_statuses: DashMap<Uuid, WorkflowStatus>,
+    outbox: OnceLock<Arc<dyn Storage>>,
+    history: OnceLock<Arc<dyn Storage>>,
+    /// Latest events, oldest first, for streams resuming after a reconnect
+    recent: Mutex<VecDeque<TaskEvent>>,
+}
+
+/// Events kept for resuming streams
+const RECENT_EVENTS: usize = 1024;
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(1024);
+        Self {
+            sequence: AtomicU64::new(0),
+            sender,
+            task_statuses: DashMap::new(),
+            workflow_statuses: DashMap::new(),
+            outbox: OnceLock::new(),
+            history: OnceLock::new(),
+            recent: Mutex::new(VecDeque::with_capacity(RECENT_EVENTS)),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<TaskEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Kept events after `sequence`; `None` if some of them are no longer
+    /// kept
+    pub fn since(&self, sequence: u64) -> Option<Vec<TaskEvent>> {
+        let recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        let missed = recent.front().is_some_and(|oldest| oldest.sequence > sequence + 1)
+            || (recent.is_empty() && self.last_sequence() > sequence);
+        if missed {
+            return None;
+        }
+        Some(recent.iter().filter(|event| event.sequence > sequence).cloned().collect())
+    }
+
+    /// Sequence number of the latest event
+    pub fn last_sequence(&self) -> u64 {
+        self.sequence.load(Ordering::SeqCst)
+    }
+
+    /// Record every event in the storage outbox from now on
+    pub fn enable_outbox(&self, storage: Arc<dyn Storage>) -> Result<()> {
+        // Continue after undelivered events left by a previous run
+        self.sequence.fetch_max(storage.last_outbox_sequence()?, Ordering::SeqCst);
+        let _ = self.outbox.set(storage);
+        Ok(())
+    }
+
+    /// Log every status change in storage's state history from now on
+    pub fn enable_history(&self, storage: Arc<dyn Storage>) {
+        let _ = self.history.set(storage);
+    }
+
+    pub fn outbox_enabled(&self) -> bool {
+        self.outbox.get().is_some()
+    }
+
+    /// Remember a task's status without emitting anything, e.g. on startup
+    pub fn track_task(&self, task: &Task) {
+        self.task_statuses.insert(task.id, task.status.clone());
+    }
+
+    pub fn track_workflow(&self, workflow: &Workflow) {
+        self.workflow_statuses.insert(workflow.id, workflow.status.clone());
+    }
+
+    pub async fn task_created(&self, task: &Task) -> TaskEvent {
+        self.track_task(task);
+        let event = self.publish_task(TaskEventKind::TaskCreated, task).await;
+        self.record_change(task.id, &event, None, status_name(&task.status)).await;
+        event
+    }
+
+    /// Emit the event for a stored task change, derived from its status transition
+    pub async fn task_changed(&self, task: &Task) -> TaskEvent {
+        let previous = self.task_statuses.insert(task.id, task.status.clone());
+        let previous = previous.filter(|previous| *previous != task.status);
+        let kind = match previous {
+            Some(_) => match task.status {
+                TaskStatus::Completed | TaskStatus::Finalized => TaskEventKind::TaskCompleted,
+                TaskStatus::Failed => TaskEventKind::TaskFailed,
+                TaskStatus::Cancelled => TaskEventKind::TaskCancelled,
+                _ => TaskEventKind::TaskStatusChanged,
+            },
+            None => TaskEventKind::TaskUpdated,
+        };
+        let event = self.publish_task(kind, task).await;
+        if let Some(previous) = previous {
+            self.record_change(task.id, &event, status_name(&previous), status_name(&task.status)).await;
+        }
+        event
+    }
+
+    pub async fn task_deleted(&self, task: &Task) -> TaskEvent {
+        self.task_statuses.remove(&task.id);
+        let event = self.publish_task(TaskEventKind::TaskDeleted, task).await;
+        self.record_change(task.id, &event, status_name(&task.status), None).await;
+        event
+    }
+
+    /// Emit a progress report on a task
+    pub async fn task_progress(&self, task: &Task, progress: &TaskProgress) -> TaskEvent {
+        self.publish(TaskEvent {
+            sequence: 0,
+            kind: TaskEventKind::TaskProgress,
+            task_id: Some(task.id),
+            workflow_id: None,
+            project_id: task.project_id,
+            status: Some(task.status.clone()),
+            timestamp: progress.reported_at,
+            agent: progress.agent,
+            tenant_id: task.tenant_id,
+            data: serde_json::json!({ "name": task.name, "percent": progress.percent, "note": progress.note }),
+        })
+        .await
+    }
+
+    pub async fn project_created(&self, project: &Project) -> TaskEvent {
+        self.publish_project(TaskEventKind::ProjectCreated, project).await
+    }
+
+    pub async fn project_changed(&self, project: &Project) -> TaskEvent {
+        self.publish_project(TaskEventKind::ProjectUpdated, project).await
+    }
+
+    pub async fn project_deleted(&self, project: &Project) -> TaskEvent {
+        self.publish_project(TaskEventKind::ProjectDeleted, project).await
+    }
+
+    pub async fn workflow_created(&self, workflow: &Workflow) -> TaskEvent {
+        self.track_workflow(workflow);
+        let event = self.publish_workflow(TaskEventKind::WorkflowCreated, workflow).await;
+        self.record_change(workflow.id, &event, None, status_name(&workflow.status)).await;
+        event
+    }
+
+    /// Emit a status change for a stored workflow, if its status changed
+    pub async fn workflow_changed(&self, workflow: &Workflow) -> Option<TaskEvent> {
+        let previous = self.workflow_statuses.insert(workflow.id, workflow.status.clone());
+        if previous.as_ref() == Some(&workflow.status) {
+            return None;
+        }
+        let event = self.publish_workflow(TaskEventKind::WorkflowStatusChanged, workflow).await;
+        let from = previous.and_then(|previous| status_name(&previous));
+        self.record_change(workflow.id, &event, from, status_name(&workflow.status)).await;
+        Some(event)
+    }
+
+    async fn record_change(&self, entity_id: Uuid, event: &TaskEvent, from: Option<String>, to: Option<String>) {
+        if let Some(storage) = self.history.get()
+            && let Err(e) = storage.append_state_change(&StateChange::new(entity_id, event, from, to)).await
+        {
+            error!("Failed to record state change of {}: {}", entity_id, e);
+        }
+    }
+
+    async fn publish_task(&self, kind: TaskEventKind, task: &Task) -> TaskEvent {
+        self.publish(TaskEvent {
+            sequence: 0,
+            kind,
+            task_id: Some(task.id),
+            workflow_id: None,
+            project_id: task.project_id,
+            status: Some(task.status.clone()),
+            timestamp: Utc::now(),
+            agent: None,
+            tenant_id: task.tenant_id,
+            data: serde_json::json!({ "name": task.name }),
+        })
+        .await
+    }
+
+    async fn publish_workflow(&self, kind: TaskEventKind, workflow: &Workflow) -> TaskEvent {
+        self.publish(TaskEvent {
+            sequence: 0,
+            kind,
+            task_id: None,
+            workflow_id: Some(workflow.id),
+            project_id: None,
+            status: None,
+            timestamp: Utc::now(),
+            agent: None,
+            tenant_id: workflow.tenant_id,
+            data: serde_json::json!({ "name": workflow.name, "status": workflow.status }),
+        })
+        .await
+    }
+
+    async fn publish_project(&self, kind: TaskEventKind, project: &Project) -> TaskEvent {
+        self.publish(TaskEvent {
+            sequence: 0,
+            kind,
+            task_id: None,
+            workflow_id: None,
+            project_id: Some(project.id),
+            status: None,
+            timestamp: Utc::now(),
+            agent: None,
+            tenant_id: project.tenant_id,
+            data: serde_json::json!({ "name": project.name, "status": project.status }),
+        })
+        .await
+    }
+
+    /// Assign the next sequence number and deliver an event
+    pub async fn publish(&self, mut event: TaskEvent) -> TaskEvent {
+        event.sequence = self.sequence.fetch_add(1, Ordering::SeqCst) + 1;
+        event.agent = event.agent.or_else(crate::agents::current_agent);
+
+        if let Some(storage) = self.outbox.get()
+            && let Err(e) = storage.append_outbox(&event).await
+        {
+            error!("Failed to record event {} in the outbox: {}", event.sequence, e);
+        }
+
+        {
+            let mut recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+            if recent.len() == RECENT_EVENTS {
+                recent.pop_front();
+            }
+            recent.push_back(event.clone());
+        }
+        // No receivers is not an error
+        let _ = self.sender.send(event.clone());
+        event
+    }
+}
+
+/// A single Server-Sent Events frame
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SseFrame {
+    pub id: Option<String>,
+    pub event: Option<String>,
+    pub data: String,
+}
+
+/// Incremental parser for a `text/event-stream` body
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a chunk of the response body and return every completed frame
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseFrame> {
+        self.buffer.extend(chunk.iter().filter(|b| **b != b'\r'));
+
+        let mut frames = Vec::new();
+        while let Some(end) = self.buffer.windows(2).position(|w| w == b"\n\n") {
+            let block: Vec<u8> = self.buffer.drain(..end + 2).collect();
+            let block = String::from_utf8_lossy(&block);
+            let mut frame = SseFrame::default();
+            let mut data_lines = Vec::new();
+
+            for line in block.lines() {
+                if line.is_empty() || line.starts_with(':') {
+                    continue; // Comments are used as keep-alives
+                }
+                let (field, value) = match line.split_once(':') {
+                    Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                    None => (line, ""),
+                };
+                match field {
+                    "id" => frame.id = Some(value.to_string()),
+                    "event" => frame.event = Some(value.to_string()),
+                    "data" => data_lines.push(value.to_string()),
+                    _ => {}
+                }
+            }
+
+            if !data_lines.is_empty() {
+                frame.data = data_lines.join("\n");
+                frames.push(frame);
+            }
+        }
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_event(kind: TaskEventKind, task_id: Uuid) -> TaskEvent {
+        TaskEvent {
+            sequence: 7,
+            kind,
+            task_id: Some(task_id),
+            workflow_id: None,
+            project_id: None,
+            status: Some(TaskStatus::Completed),
+            timestamp: Utc::now(),
+            agent: None,
+            tenant_id: None,
+            data: serde_json::Value::Null,
+        }
+    }
+
+    #[test]
+    fn test_event_filter_matching() {
+        let task_id = Uuid::new_v4();
+        let event = sample_event(TaskEventKind::TaskCompleted, task_id);
+
+        assert!(EventFilter::all().matches(&event));
+        assert!(EventFilter::all().with_task(task_id).matches(&event));
+        assert!(!EventFilter::all().with_task(Uuid::new_v4()).matches(&event));
+        assert!(!EventFilter::all().with_kind(TaskEventKind::TaskFailed).matches(&event));
+    }
+
+    #[tokio::test]
+    async fn test_streams_resume_from_kept_events() {
+        let bus = EventBus::new();
+        let task = Task::new("Build").with_command("make").build();
+        let first = bus.task_created(&task).await;
+        let second = bus.task_deleted(&task).await;
+        let sequences = |events: Vec<TaskEvent>| events.iter().map(|event| event.sequence).collect::<Vec<_>>();
+        assert_eq!(sequences(bus.since(0).unwrap()), [first.sequence, second.sequence]);
+        assert_eq!(sequences(bus.since(first.sequence).unwrap()), [second.sequence]);
+
+        for _ in 0..RECENT_EVENTS {
+            bus.task_created(&task).await;
+        }
+        assert!(bus.since(first.sequence).is_none());
+        assert!(bus.since(bus.last_sequence()).unwrap().is_empty());
+
+        let params = [("kinds", "task_created,project_deleted"), ("project_id", "not-a-uuid")];
+        let params = params.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        assert!(EventFilter::from_query(&params).is_err());
+        let filter = EventFilter::all().with_kind(TaskEventKind::ProjectDeleted).with_project(Uuid::new_v4());
+        let parsed = EventFilter::from_query(&filter.to_query().into_iter().collect()).unwrap();
+        assert_eq!((parsed.kinds, parsed.project_id), (filter.kinds, filter.project_id));
+    }
+
+    #[test]
+    fn test_sse_parser_handles_split_frames() {
+        let mut parser = SseParser::new();
+        assert!(parser.feed(b"id: 1\nevent: task_created\nda").is_empty());
+
+        let frames = parser.feed(b"ta: {\"a\":1}\r\n\r\n: keep-alive\n\nid: 2\ndata: x\n\n");
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].id.as_deref(), Some("1"));
+        assert_eq!(frames[0].event.as_deref(), Some("task_created"));
+        assert_eq!(frames[0].data, "{\"a\":1}");
+        assert_eq!(frames[1].data, "x");
+    }
+
+    #[test]
+    fn test_event_kind_round_trip() {
+        for kind in [TaskEventKind::TaskCreated, TaskEventKind::TaskProgress, TaskEventKind::WorkflowStatusChanged] {
+            assert_eq!(TaskEventKind::parse(kind.as_str()), Some(kind));
+        }
+    }
+}
@@ -0,0 +1,850 @@
+//! Task Executor Module
+//!
+//! Execution backends behind the [`Executor`] trait and the registry that
+//! picks one per task. Built in are the system shell, an HTTP callback to a
+//! remote worker and external plugin programs, plus Docker containers in
+//! [`crate::docker`], resource-limited shells in [`crate::sandbox`] and the
+//! calls of HTTP tasks in [`crate::http_task`];
+//! deployments can register their own implementations.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::config::{ExecutionConfig, ExecutorConfig};
+use crate::core::*;
+use crate::task_logs::{capture, LogSink, LogStream};
+use dashmap::DashMap;
+use futures_util::future::BoxFuture;
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::{Arc, LazyLock};
+use std::time::Instant;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, Command};
+use tracing::{info, warn};
+
+/// Task metadata key naming the executor that should run the task
+pub const EXECUTOR_METADATA_KEY: &str = "executor";
+/// Name of the shell executor, used when a task doesn't pick one
+pub const DEFAULT_EXECUTOR: &str = "shell";
+
+/// An execution backend
+pub trait Executor: Send + Sync {
+    /// Name tasks use to select this executor
+    fn name(&self) -> &str;
+
+    /// Run a task to completion
+    fn execute<'a>(&'a self, task: &'a Task) -> BoxFuture<'a, TaskResult>;
+
+    /// Run a task to completion, sending its output to `logs` as it is
+    /// produced; by default the output is logged once the task is done
+    fn execute_logged<'a>(&'a self, task: &'a Task, logs: &'a LogSink) -> BoxFuture<'a, TaskResult> {
+        Box::pin(async move {
+            let result = self.execute(task).await;
+            logs.record_result(&result);
+            result
+        })
+    }
+}
+
+pub(crate) fn failure(error: impl Into<String>, exit_code: Option<i32>, logs: Vec<String>) -> TaskResult {
+    TaskResult::Failure {
+        error: error.into(),
+        exit_code,
+        logs,
+        metrics: None,
+    }
+}
+
+/// Error of tasks stopped for running longer than their `timeout`
+pub fn timeout_error(timeout: std::time::Duration) -> String {
+    format!("Task timed out after {:?}", timeout)
+}
+
+fn success(output: String, execution_time: std::time::Duration) -> TaskResult {
+    TaskResult::Success {
+        output,
+        artifacts: Vec::new(),
+        metrics: TaskMetrics {
+            execution_time,
+            memory_usage: 0,
+            cpu_usage: 0.0,
+            disk_usage: 0,
+            network_io: 0,
+        },
+    }
+}
+
+/// How often a running command's resource usage is sampled
+const SAMPLE_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Resource usage of a command, sampled from `/proc` while it runs
+///
+/// The command runs in its own process group and every process in the group
+/// is counted, so work done by pipelines and subprocesses shows up too.
+#[derive(Debug, Clone, Copy, Default)]
+struct ResourceUsage {
+    peak_rss_bytes: u64,
+    cpu_ticks: u64,
+    io_bytes: u64,
+}
+
+impl ResourceUsage {
+    #[cfg(target_os = "linux")]
+    fn sample(&mut self, group: u32) {
+        let Ok(entries) = std::fs::read_dir("/proc") else {
+            return;
+        };
+        let page_size = match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+            size if size > 0 => size as u64,
+            _ => 4096,
+        };
+
+        let (mut rss_bytes, mut cpu_ticks, mut io_bytes) = (0, 0, 0);
+        for entry in entries.flatten() {
+            let Some(pid) = entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else {
+                continue;
+            };
+            // The command name is parenthesized and may contain spaces, so
+            // fields are counted from the closing parenthesis (state is 0)
+            let Ok(stat) = std::fs::read_to_string(format!("/proc/{}/stat", pid)) else {
+                continue;
+            };
+            let Some((_, rest)) = stat.rsplit_once(')') else {
+                continue;
+            };
+            let fields: Vec<&str> = rest.split_whitespace().collect();
+            let field = |i: usize| fields.get(i).and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
+            if field(2) != group as u64 {
+                continue;
+            }
+
+            rss_bytes += field(21) * page_size;
+            // Own time plus that of children already waited for
+            cpu_ticks += field(11) + field(12) + field(13) + field(14);
+            if let Ok(io) = std::fs::read_to_string(format!("/proc/{}/io", pid)) {
+                io_bytes += io.lines()
+                    .filter(|line| line.starts_with("read_bytes:") || line.starts_with("write_bytes:"))
+                    .filter_map(|line| line.split_whitespace().nth(1)?.parse::<u64>().ok())
+                    .sum::<u64>();
+            }
+        }
+
+        self.peak_rss_bytes = self.peak_rss_bytes.max(rss_bytes);
+        self.cpu_ticks = self.cpu_ticks.max(cpu_ticks);
+        self.io_bytes = self.io_bytes.max(io_bytes);
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn sample(&mut self, pid: u32) {}
+
+    fn metrics(&self, execution_time: std::time::Duration) -> TaskMetrics {
+        #[cfg(target_os = "linux")]
+        let ticks_per_second = match unsafe { libc::sysconf(libc::_SC_CLK_TCK) } {
+            ticks if ticks > 0 => ticks as f64,
+            _ => 100.0,
+        };
+        #[cfg(not(target_os = "linux"))]
+        let ticks_per_second = 100.0;
+
+        let cpu_seconds = self.cpu_ticks as f64 / ticks_per_second;
+        let wall_seconds = execution_time.as_secs_f64();
+        TaskMetrics {
+            execution_time,
+            memory_usage: self.peak_rss_bytes,
+            cpu_usage: if wall_seconds > 0.0 { cpu_seconds / wall_seconds * 100.0 } else { 0.0 },
+            disk_usage: self.io_bytes,
+            network_io: 0,
+        }
+    }
+}
+
+/// Kills a command's whole process group unless disarmed, so a task stopped
+/// early (e.g. on timeout) leaves no subprocesses behind
+struct ProcessGroupGuard(Option<u32>);
+
+impl ProcessGroupGuard {
+    fn disarm(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for ProcessGroupGuard {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(group) = self.0 {
+            unsafe {
+                libc::killpg(group as libc::pid_t, libc::SIGKILL);
+            }
+        }
+    }
+}
+
+/// The script of a `TaskType::Script` task written to a temporary file for
+/// its interpreter; removed again on drop
+pub(crate) struct ScriptFile {
+    pub(crate) path: std::path::PathBuf,
+    pub(crate) interpreter: ScriptInterpreter,
+}
+
+impl ScriptFile {
+    /// Where the script of a task goes
+    pub(crate) fn path_for(task: &Task, interpreter: ScriptInterpreter) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("task-queue-{}.{}", task.id, interpreter.extension()))
+    }
+
+    /// Write out the script of a task, if it is a script task
+    pub(crate) fn write(task: &Task) -> std::io::Result<Option<Self>> {
+        let TaskType::Script { interpreter } = task.task_type else {
+            return Ok(None);
+        };
+        let path = Self::path_for(task, interpreter);
+        // Never write through whatever a previous run or someone else left there
+        let _ = std::fs::remove_file(&path);
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        std::io::Write::write_all(&mut options.open(&path)?, task.command.as_bytes())?;
+        Ok(Some(Self { path, interpreter }))
+    }
+
+    /// The interpreter invocation running the script
+    fn command(&self) -> Command {
+        let (program, args) = self.interpreter.invocation().split_first().expect("interpreter invocation");
+        let mut cmd = Command::new(program);
+        cmd.args(args).arg(&self.path);
+        cmd
+    }
+}
+
+impl Drop for ScriptFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// How a task's running execution is stopped and continued
+#[derive(Debug, Clone)]
+pub(crate) enum Suspension {
+    /// Signal the command's process group
+    ProcessGroup(u32),
+    /// `pause`/`unpause` the container through the Docker CLI
+    Container { program: String, name: String },
+}
+
+/// Executions running in this process that can be paused, by task
+static SUSPENDABLE: LazyLock<DashMap<uuid::Uuid, Suspension>> = LazyLock::new(DashMap::new);
+
+/// Makes a task's execution pausable until dropped
+pub(crate) struct SuspendRegistration(uuid::Uuid);
+
+impl SuspendRegistration {
+    pub(crate) fn new(task_id: uuid::Uuid, suspension: Suspension) -> Self {
+        SUSPENDABLE.insert(task_id, suspension);
+        Self(task_id)
+    }
+}
+
+impl Drop for SuspendRegistration {
+    fn drop(&mut self) {
+        SUSPENDABLE.remove(&self.0);
+    }
+}
+
+async fn signal_suspension(task_id: uuid::Uuid, pause: bool) -> std::io::Result<bool> {
+    let Some(suspension) = SUSPENDABLE.get(&task_id).map(|entry| entry.clone()) else {
+        return Ok(false);
+    };
+    match suspension {
+        #[cfg(unix)]
+        Suspension::ProcessGroup(group) => {
+            let signal = if pause { libc::SIGSTOP } else { libc::SIGCONT };
+            if unsafe { libc::killpg(group as libc::pid_t, signal) } != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        #[cfg(not(unix))]
+        Suspension::ProcessGroup(_) => {
+            return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "Pausing commands needs a Unix system"));
+        }
+        Suspension::Container { program, name } => {
+            let status = Command::new(&program)
+                .args([if pause { "pause" } else { "unpause" }, &name])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .await?;
+            if !status.success() {
+                return Err(std::io::Error::other(format!("{} exited with {}", program, status)));
+            }
+        }
+    }
+    Ok(true)
+}
+
+/// Stop a task executing in this process where it is; `false` if it isn't
+/// executing here. Its `timeout` keeps running while it is paused.
+pub async fn pause_execution(task_id: uuid::Uuid) -> std::io::Result<bool> {
+    signal_suspension(task_id, true).await
+}
+
+/// Continue a task paused with [`pause_execution`]
+pub async fn resume_execution(task_id: uuid::Uuid) -> std::io::Result<bool> {
+    signal_suspension(task_id, false).await
+}
+
+#[cfg(unix)]
+fn termination_reason(status: &std::process::ExitStatus) -> String {
+    use std::os::unix::process::ExitStatusExt;
+    match status.signal() {
+        Some(libc::SIGXCPU) => "Command exceeded its CPU time limit".to_string(),
+        Some(libc::SIGXFSZ) => "Command exceeded its file size limit".to_string(),
+        Some(signal) => format!("Command terminated by signal {}", signal),
+        None => "Command terminated by signal".to_string(),
+    }
+}
+
+#[cfg(not(unix))]
+fn termination_reason(status: &std::process::ExitStatus) -> String {
+    "Command terminated by signal".to_string()
+}
+
+/// Executes task commands through the system shell
+#[derive(Debug, Clone, Default)]
+pub struct ShellExecutor {
+    /// Shell used to run commands; defaults to `sh -c` (`cmd /C` on Windows)
+    pub shell: Option<(String, String)>,
+}
+
+impl ShellExecutor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use a custom shell program and its "run this command" flag
+    pub fn with_shell(mut self, program: &str, flag: &str) -> Self {
+        self.shell = Some((program.to_string(), flag.to_string()));
+        self
+    }
+
+    fn shell_command(&self, command: &str) -> Command {
+        let (program, flag) = match &self.shell {
+            Some((program, flag)) => (program.as_str(), flag.as_str()),
+            None if cfg!(windows) => ("cmd", "/C"),
+            None => ("sh", "-c"),
+        };
+
+        let mut cmd = Command::new(program);
+        cmd.arg(flag).arg(command);
+        cmd
+    }
+
+    /// Run a task to completion
+    pub async fn execute(&self, task: &Task) -> TaskResult {
+        self.execute_with(task, &LogSink::discard(), |_| Ok(())).await
+    }
+
+    /// Run a task to completion, sending its output to `logs` and letting
+    /// `prepare` adjust the command (e.g. to confine it) before it is spawned
+    pub async fn execute_with(
+        &self,
+        task: &Task,
+        logs: &LogSink,
+        prepare: impl FnOnce(&mut Command) -> std::result::Result<(), String>,
+    ) -> TaskResult {
+        self.execute_confined(task, logs, prepare, |_| Ok(())).await
+    }
+
+    /// Like [`Self::execute_with`], also handing the child to `spawned`
+    /// before waiting on it (e.g. to put it in a job object); the child is
+    /// killed if that fails
+    pub async fn execute_confined(
+        &self,
+        task: &Task,
+        logs: &LogSink,
+        prepare: impl FnOnce(&mut Command) -> std::result::Result<(), String>,
+        spawned: impl FnOnce(&Child) -> std::result::Result<(), String>,
+    ) -> TaskResult {
+        if task.command.trim().is_empty() {
+            return failure("Task has no command to execute", None, Vec::new());
+        }
+
+        let script = match ScriptFile::write(task) {
+            Ok(script) => script,
+            Err(e) => return failure(format!("Failed to write script: {}", e), None, Vec::new()),
+        };
+        let mut cmd = match &script {
+            Some(script) => script.command(),
+            None => self.shell_command(&task.command),
+        };
+        #[cfg(unix)]
+        cmd.process_group(0);
+        cmd.envs(&task.environment)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(dir) = &task.working_directory {
+            cmd.current_dir(dir);
+        }
+        if let Err(e) = prepare(&mut cmd) {
+            return failure(e, None, Vec::new());
+        }
+
+        info!("Executing task {} ({}): {}", task.name, task.id, task.command);
+        let start = Instant::now();
+
+        let mut child = match cmd.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                warn!("Failed to spawn task {}: {}", task.id, e);
+                return failure(format!("Failed to spawn command: {}", e), None, Vec::new());
+            }
+        };
+        // Dropping the child kills it
+        if let Err(e) = spawned(&child) {
+            return failure(e, None, Vec::new());
+        }
+
+        let pid = child.id();
+        // The command leads its own process group, so its pid is the group id
+        let group = ProcessGroupGuard(pid.filter(|_| cfg!(unix)));
+        let registration = pid.map(|pid| SuspendRegistration::new(task.id, Suspension::ProcessGroup(pid)));
+        let stdout = capture(child.stdout.take(), LogStream::Stdout, logs);
+        let stderr = capture(child.stderr.take(), LogStream::Stderr, logs);
+        let wait = async {
+            let mut usage = ResourceUsage::default();
+            let mut ticker = tokio::time::interval(SAMPLE_INTERVAL);
+            loop {
+                tokio::select! {
+                    status = child.wait() => break status.map(|status| (status, usage)),
+                    _ = ticker.tick() => {
+                        if let Some(pid) = pid {
+                            usage.sample(pid);
+                        }
+                    }
+                }
+            }
+        };
+        let (status, stdout, stderr) = tokio::join!(wait, stdout, stderr);
+        drop(registration);
+        group.disarm();
+
+        let execution_time = start.elapsed();
+        let (status, usage) = match status {
+            Ok(result) => result,
+            Err(e) => {
+                warn!("Failed to wait for task {}: {}", task.id, e);
+                return failure(format!("Failed to wait for command: {}", e), None, Vec::new());
+            }
+        };
+        let stdout = String::from_utf8_lossy(&stdout).to_string();
+        let stderr = String::from_utf8_lossy(&stderr).to_string();
+
+        if status.success() {
+            TaskResult::Success {
+                output: stdout,
+                artifacts: Vec::new(),
+                metrics: usage.metrics(execution_time),
+            }
+        } else {
+            let exit_code = status.code();
+            TaskResult::Failure {
+                error: match exit_code {
+                    Some(code) => format!("Command exited with code {}", code),
+                    None => termination_reason(&status),
+                },
+                exit_code,
+                logs: stdout.lines().chain(stderr.lines()).map(str::to_string).collect(),
+                metrics: Some(usage.metrics(execution_time)),
+            }
+        }
+    }
+}
+
+impl Executor for ShellExecutor {
+    fn name(&self) -> &str {
+        DEFAULT_EXECUTOR
+    }
+
+    fn execute<'a>(&'a self, task: &'a Task) -> BoxFuture<'a, TaskResult> {
+        Box::pin(ShellExecutor::execute(self, task))
+    }
+
+    fn execute_logged<'a>(&'a self, task: &'a Task, logs: &'a LogSink) -> BoxFuture<'a, TaskResult> {
+        Box::pin(self.execute_with(task, logs, |_| Ok(())))
+    }
+}
+
+/// Hands tasks to a remote worker over HTTP
+///
+/// The task is POSTed as JSON to `url`. A 2xx response whose body is a
+/// `TaskResult` is used as is; any other 2xx body becomes the task output.
+#[derive(Debug, Clone)]
+pub struct HttpCallbackExecutor {
+    name: String,
+    url: String,
+    client: reqwest::Client,
+}
+
+impl HttpCallbackExecutor {
+    pub fn new(name: &str, url: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            url: url.to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    async fn run(&self, task: &Task) -> TaskResult {
+        let start = Instant::now();
+        let response = match self.client.post(&self.url).json(task).send().await {
+            Ok(response) => response,
+            Err(e) => return failure(format!("Callback to {} failed: {}", self.url, e), None, Vec::new()),
+        };
+
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        if !status.is_success() {
+            return failure(
+                format!("Callback to {} returned {}", self.url, status),
+                None,
+                body.lines().map(str::to_string).collect(),
+            );
+        }
+
+        serde_json::from_str(&body).unwrap_or_else(|_| success(body, start.elapsed()))
+    }
+}
+
+impl Executor for HttpCallbackExecutor {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn execute<'a>(&'a self, task: &'a Task) -> BoxFuture<'a, TaskResult> {
+        Box::pin(self.run(task))
+    }
+}
+
+/// Runs tasks through an external plugin program
+///
+/// The program receives the task as JSON on stdin and prints a `TaskResult`
+/// as JSON on stdout, so backends can be written in any language and shipped
+/// without rebuilding the server.
+#[derive(Debug, Clone)]
+pub struct PluginExecutor {
+    name: String,
+    program: String,
+    args: Vec<String>,
+}
+
+impl PluginExecutor {
+    pub fn new(name: &str, program: &str, args: Vec<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            program: program.to_string(),
+            args,
+        }
+    }
+
+    async fn run(&self, task: &Task) -> TaskResult {
+        let input = match serde_json::to_vec(task) {
+            Ok(input) => input,
+            Err(e) => return failure(format!("Failed to encode task: {}", e), None, Vec::new()),
+        };
+
+        let child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) => return failure(format!("Failed to start plugin {}: {}", self.program, e), None, Vec::new()),
+        };
+
+        if let Some(mut stdin) = child.stdin.take()
+            && let Err(e) = stdin.write_all(&input).await
+        {
+            warn!("Plugin {} did not read its input: {}", self.name, e);
+        }
+
+        let output = match child.wait_with_output().await {
+            Ok(output) => output,
+            Err(e) => return failure(format!("Plugin {} failed: {}", self.name, e), None, Vec::new()),
+        };
+
+        let stderr: Vec<String> = String::from_utf8_lossy(&output.stderr).lines().map(str::to_string).collect();
+        if !output.status.success() {
+            return failure(
+                format!("Plugin {} exited with {}", self.name, output.status),
+                output.status.code(),
+                stderr,
+            );
+        }
+
+        serde_json::from_slice(&output.stdout).unwrap_or_else(|e| {
+            failure(format!("Plugin {} returned an invalid result: {}", self.name, e), None, stderr)
+        })
+    }
+}
+
+impl Executor for PluginExecutor {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn execute<'a>(&'a self, task: &'a Task) -> BoxFuture<'a, TaskResult> {
+        Box::pin(self.run(task))
+    }
+}
+
+/// Executors available to run tasks, by name
+///
+/// A task selects one through its `executor` metadata key and falls back to
+/// the default executor. Cloning is cheap.
+#[derive(Clone)]
+pub struct ExecutorRegistry {
+    executors: HashMap<String, Arc<dyn Executor>>,
+    default: String,
+}
+
+impl Default for ExecutorRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            executors: HashMap::new(),
+            default: DEFAULT_EXECUTOR.to_string(),
+        };
+        registry.register(ShellExecutor::new());
+        registry.register(crate::http_task::HttpRequestExecutor::new());
+        registry
+    }
+}
+
+impl std::fmt::Debug for ExecutorRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExecutorRegistry")
+            .field("executors", &self.names())
+            .field("default", &self.default)
+            .finish()
+    }
+}
+
+impl ExecutorRegistry {
+    /// Registry with only the built-in shell and HTTP request executors
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry with the built-in executors plus the ones declared in configuration
+    pub fn from_config(config: &ExecutionConfig) -> Self {
+        let mut registry = Self::new();
+        for executor in &config.executors {
+            match executor {
+                ExecutorConfig::Http { name, url } => {
+                    registry.register(HttpCallbackExecutor::new(name, url));
+                }
+                ExecutorConfig::Plugin { name, program, args } => {
+                    registry.register(PluginExecutor::new(name, program, args.clone()));
+                }
+                #[cfg(feature = "wasm")]
+                ExecutorConfig::Wasm { allowed_dirs, allow_network } => {
+                    let policy = crate::wasm::WasmPolicy {
+                        allowed_dirs: allowed_dirs.iter()
+                            .map(|dir| crate::wasm::AllowedDir { host: dir.host.clone().into(), writable: dir.writable })
+                            .collect(),
+                        allow_network: *allow_network,
+                        ..Default::default()
+                    };
+                    match crate::wasm::WasmExecutor::new(policy) {
+                        Ok(executor) => {
+                            registry.register(executor);
+                        }
+                        Err(e) => warn!("Failed to set up the WASM executor: {}", e),
+                    }
+                }
+                ExecutorConfig::Docker { program, defaults } => {
+                    registry.register(crate::docker::DockerExecutor::new(defaults.clone()).with_program(program));
+                }
+                ExecutorConfig::Sandbox { limits, cgroup_root } => {
+                    registry.register(crate::sandbox::SandboxExecutor::new(crate::sandbox::SandboxPolicy {
+                        limits: limits.clone(),
+                        cgroup_root: cgroup_root.clone().map(Into::into),
+                    }));
+                }
+                #[cfg(not(feature = "wasm"))]
+                ExecutorConfig::Wasm { .. } => {
+                    warn!("WASM executor configured but the server was built without the `wasm` feature");
+                }
+            }
+        }
+        registry
+    }
+
+    /// Add an executor, replacing any registered under the same name
+    pub fn register(&mut self, executor: impl Executor + 'static) -> &mut Self {
+        self.executors.insert(executor.name().to_string(), Arc::new(executor));
+        self
+    }
+
+    /// Executor used by tasks that don't select one
+    pub fn set_default(&mut self, name: &str) -> &mut Self {
+        self.default = name.to_string();
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Executor>> {
+        self.executors.get(name).cloned()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.executors.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Name of the executor a task asks for
+    pub fn executor_name<'a>(&'a self, task: &'a Task) -> &'a str {
+        let default = match task.task_type {
+            TaskType::Http => crate::http_task::HTTP_EXECUTOR,
+            _ => &self.default,
+        };
+        task.metadata
+            .get(EXECUTOR_METADATA_KEY)
+            .and_then(|name| name.as_str())
+            .unwrap_or(default)
+    }
+
+    /// Run a task with the executor it selects, stopping it once it has run
+    /// for longer than its `timeout`
+    pub async fn execute(&self, task: &Task) -> TaskResult {
+        self.execute_logged(task, &LogSink::discard()).await
+    }
+
+    /// Like [`Self::execute`], sending the task's output to `logs`
+    pub async fn execute_logged(&self, task: &Task, logs: &LogSink) -> TaskResult {
+        let name = self.executor_name(task);
+        let Some(executor) = self.get(name) else {
+            return failure(format!("Unknown executor '{}'", name), None, Vec::new());
+        };
+        let Some(timeout) = task.timeout else {
+            return executor.execute_logged(task, logs).await;
+        };
+
+        let start = Instant::now();
+        match tokio::time::timeout(timeout, executor.execute_logged(task, logs)).await {
+            Ok(result) => result,
+            // Dropping the execution kills the command and its subprocesses
+            Err(_) => {
+                warn!("Task {} ({}) timed out after {:?}", task.name, task.id, timeout);
+                TaskResult::Failure {
+                    error: timeout_error(timeout),
+                    exit_code: None,
+                    logs: Vec::new(),
+                    metrics: Some(TaskMetrics {
+                        execution_time: start.elapsed(),
+                        ..Default::default()
+                    }),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    struct EchoExecutor;
+
+    impl Executor for EchoExecutor {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn execute<'a>(&'a self, task: &'a Task) -> BoxFuture<'a, TaskResult> {
+            Box::pin(async move { success(task.name.clone(), std::time::Duration::ZERO) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_registry_selects_executor_from_metadata() {
+        let mut registry = ExecutorRegistry::new();
+        registry.register(EchoExecutor);
+
+        let mut task = Task::new("hello").with_command("exit 1").build();
+        task.metadata.insert(EXECUTOR_METADATA_KEY.to_string(), json!("echo"));
+        assert!(matches!(registry.execute(&task).await, TaskResult::Success { output, .. } if output == "hello"));
+
+        task.metadata.insert(EXECUTOR_METADATA_KEY.to_string(), json!("missing"));
+        assert!(matches!(registry.execute(&task).await, TaskResult::Failure { error, .. } if error.contains("missing")));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_timeout_kills_the_whole_command() {
+        let marker = std::env::temp_dir().join(format!("task-queue-timeout-{}", uuid::Uuid::new_v4()));
+        let task = Task::new("slow")
+            .with_command(&format!("(sleep 1; touch {}) & sleep 30", marker.display()))
+            .with_timeout(std::time::Duration::from_millis(300))
+            .build();
+
+        match ExecutorRegistry::new().execute(&task).await {
+            TaskResult::Failure { error, metrics: Some(metrics), .. } => {
+                assert_eq!(error, timeout_error(std::time::Duration::from_millis(300)));
+                assert!(metrics.execution_time >= std::time::Duration::from_millis(300));
+                assert!(metrics.execution_time < std::time::Duration::from_secs(5));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // The backgrounded subshell was killed with the rest of the group
+        tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+        assert!(!marker.exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_script_tasks_run_through_their_interpreter() {
+        let mut task = Task::new("script")
+            .with_command("set -e\nfor word in multi line; do\n  echo \"$word\"\ndone\necho \"$0\" >&2\n")
+            .with_type(TaskType::Script { interpreter: ScriptInterpreter::Bash })
+            .build();
+        let path = ScriptFile::path_for(&task, ScriptInterpreter::Bash);
+
+        match ExecutorRegistry::new().execute(&task).await {
+            TaskResult::Success { output, .. } => assert_eq!(output, "multi\nline\n"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(!path.exists());
+
+        task.command = "exit 3".to_string();
+        assert!(matches!(ExecutorRegistry::new().execute(&task).await, TaskResult::Failure { exit_code: Some(3), .. }));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_plugin_executor_reads_result_from_stdout() {
+        let plugin = PluginExecutor::new("plugin", "sh", vec![
+            "-c".to_string(),
+            r#"cat > /dev/null; echo '{"Failure":{"error":"boom","exit_code":3,"logs":[]}}'"#.to_string(),
+        ]);
+        let task = Task::new("plugin task").with_command("noop").build();
+
+        match Executor::execute(&plugin, &task).await {
+            TaskResult::Failure { error, exit_code, .. } => {
+                assert_eq!(error, "boom");
+                assert_eq!(exit_code, Some(3));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! In-Memory Fake Module
+//!
+//! A `TaskQueueApi` implementation backed by in-process maps, for unit tests
+//! of code that talks to the task queue.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::client::TaskQueueApi;
+use crate::core::*;
+use crate::error::{TaskQueueError, Result};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// In-memory stand-in for `TaskQueueClient`
+///
+/// Clones share state, so a test can keep a handle to inspect what the code
+/// under test submitted.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryTaskQueue {
+    tasks: Arc<RwLock<HashMap<Uuid, Task>>>,
+    workflows: Arc<RwLock<HashMap<Uuid, Workflow>>>,
+    /// Submission order, so tests can assert on it
+    submitted: Arc<RwLock<Vec<Uuid>>>,
+}
+
+impl InMemoryTaskQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tasks in the order they were submitted
+    pub async fn submitted_tasks(&self) -> Vec<Task> {
+        let tasks = self.tasks.read().await;
+        self.submitted
+            .read()
+            .await
+            .iter()
+            .filter_map(|id| tasks.get(id).cloned())
+            .collect()
+    }
+
+    /// Set a task's status, as a worker would
+    pub async fn set_task_status(&self, task_id: &Uuid, status: TaskStatus) -> Result<()> {
+        let mut tasks = self.tasks.write().await;
+        let task = tasks.get_mut(task_id).ok_or_else(|| TaskQueueError::TaskNotFound {
+            task_id: task_id.to_string(),
+        })?;
+        task.status = status;
+        task.updated_at = std::time::SystemTime::now();
+        Ok(())
+    }
+
+    /// Record a task result and the matching terminal status
+    pub async fn complete_task(&self, task_id: &Uuid, result: TaskResult) -> Result<()> {
+        let mut tasks = self.tasks.write().await;
+        let task = tasks.get_mut(task_id).ok_or_else(|| TaskQueueError::TaskNotFound {
+            task_id: task_id.to_string(),
+        })?;
+        task.set_result(result);
+        Ok(())
+    }
+
+    /// Set a workflow's status
+    pub async fn set_workflow_status(&self, workflow_id: &Uuid, status: WorkflowStatus) -> Result<()> {
+        let mut workflows = self.workflows.write().await;
+        let workflow = workflows.get_mut(workflow_id).ok_or_else(|| TaskQueueError::WorkflowNotFound {
+            workflow_id: workflow_id.to_string(),
+        })?;
+        workflow.status = status;
+        workflow.updated_at = std::time::SystemTime::now();
+        Ok(())
+    }
+}
+
+impl TaskQueueApi for InMemoryTaskQueue {
+    async fn submit_task(&self, task: Task) -> Result<Uuid> {
+        let task_id = task.id;
+        self.tasks.write().await.insert(task_id, task);
+        self.submitted.write().await.push(task_id);
+        Ok(task_id)
+    }
+
+    async fn get_task(&self, task_id: &Uuid) -> Result<Task> {
+        self.tasks
+            .read()
+            .await
+            .get(task_id)
+            .cloned()
+            .ok_or_else(|| TaskQueueError::TaskNotFound {
+                task_id: task_id.to_string(),
+            })
+    }
+
+    async fn get_task_status(&self, task_id: &Uuid) -> Result<TaskStatus> {
+        Ok(self.get_task(task_id).await?.status)
+    }
+
+    async fn get_task_result(&self, task_id: &Uuid) -> Result<Option<TaskResult>> {
+        Ok(self.get_task(task_id).await?.result)
+    }
+
+    async fn list_tasks(&self, project: Option<String>, status: Option<String>) -> Result<Vec<Task>> {
+        let tasks = self.tasks.read().await;
+        let submitted = self.submitted.read().await;
+        Ok(submitted
+            .iter()
+            .filter_map(|id| tasks.get(id))
+            .filter(|task| project.is_none() || task.project == project)
+            .filter(|task| status.as_ref().is_none_or(|s| task.status.matches_filter(s)))
+            .cloned()
+            .collect())
+    }
+
+    async fn submit_workflow(&self, workflow: Workflow) -> Result<Uuid> {
+        let workflow_id = workflow.id;
+        self.workflows.write().await.insert(workflow_id, workflow);
+        Ok(workflow_id)
+    }
+
+    async fn get_workflow(&self, workflow_id: &Uuid) -> Result<Workflow> {
+        self.workflows
+            .read()
+            .await
+            .get(workflow_id)
+            .cloned()
+            .ok_or_else(|| TaskQueueError::WorkflowNotFound {
+                workflow_id: workflow_id.to_string(),
+            })
+    }
+
+    async fn get_workflow_status(&self, workflow_id: &Uuid) -> Result<WorkflowStatus> {
+        Ok(self.get_workflow(workflow_id).await?.status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn submit_build<Q: TaskQueueApi>(queue: &Q) -> Result<Uuid> {
+        let task = Task::new("build")
+            .with_command("cargo build")
+            .with_project("demo")
+            .build();
+        queue.submit_task(task).await
+    }
+
+    #[tokio::test]
+    async fn test_fake_records_submissions() {
+        let queue = InMemoryTaskQueue::new();
+        let task_id = submit_build(&queue).await.unwrap();
+
+        let submitted = queue.submitted_tasks().await;
+        assert_eq!(submitted.len(), 1);
+        assert_eq!(submitted[0].id, task_id);
+        assert_eq!(queue.list_tasks(Some("demo".to_string()), None).await.unwrap().len(), 1);
+        assert!(queue.list_tasks(Some("other".to_string()), None).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fake_status_transitions() {
+        let queue = InMemoryTaskQueue::new();
+        let task_id = submit_build(&queue).await.unwrap();
+
+        queue.set_task_status(&task_id, TaskStatus::Running).await.unwrap();
+        assert_eq!(queue.get_task_status(&task_id).await.unwrap(), TaskStatus::Running);
+        assert_eq!(queue.list_tasks(None, Some("running".to_string())).await.unwrap().len(), 1);
+
+        assert!(queue.get_task(&Uuid::new_v4()).await.is_err());
+    }
+}
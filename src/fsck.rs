@@ -0,0 +1,321 @@
+//! Storage Integrity Module
+//!
+//! `task-queue server fsck` and `POST /admin/fsck` check that the stored
+//! records hang together: every task, project and workflow still decodes,
+//! tasks belong to projects that exist and depend on tasks that exist (or
+//! that were archived or moved to cold storage), and workflow dependencies
+//! join tasks of the workflow. By default problems are only reported.
+//! `repair` drops the broken references and keeps the records; `quarantine`
+//! moves the records holding them out of the queue instead. Records that no longer decode can't be
+//! repaired, so both move them to quarantine, where their stored bytes are
+//! kept under `{collection}/{id}` for inspection.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::*;
+use crate::error::Result;
+use crate::storage::{Storage, Transaction};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Main collection a record is stored in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Collection {
+    Tasks,
+    Projects,
+    Workflows,
+}
+
+impl Collection {
+    pub const ALL: [Self; 3] = [Self::Tasks, Self::Projects, Self::Workflows];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Tasks => "tasks",
+            Self::Projects => "projects",
+            Self::Workflows => "workflows",
+        }
+    }
+}
+
+/// A stored record that no longer decodes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UndecodableRecord {
+    pub collection: Collection,
+    pub key: String,
+    pub error: String,
+}
+
+/// What a check does about the problems it finds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FsckAction {
+    /// Only report them
+    #[default]
+    Check,
+    /// Drop broken references, keeping the records
+    Repair,
+    /// Move records with broken references out of the queue
+    Quarantine,
+}
+
+impl FsckAction {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "check" => Some(Self::Check),
+            "repair" => Some(Self::Repair),
+            "quarantine" => Some(Self::Quarantine),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Problem {
+    Undecodable { collection: Collection, key: String, error: String },
+    /// A task in a project that doesn't exist
+    MissingProject { task_id: Uuid, project_id: Uuid },
+    /// A task depending on a task that doesn't exist
+    MissingDependency { task_id: Uuid, dependency_id: Uuid },
+    /// A workflow dependency naming a task the workflow doesn't have
+    MissingWorkflowTask { workflow_id: Uuid, task_id: Uuid },
+}
+
+impl Problem {
+    /// The record holding the problem
+    pub fn record(&self) -> (Collection, String) {
+        match self {
+            Self::Undecodable { collection, key, .. } => (*collection, key.clone()),
+            Self::MissingProject { task_id, .. } | Self::MissingDependency { task_id, .. } => (Collection::Tasks, task_id.to_string()),
+            Self::MissingWorkflowTask { workflow_id, .. } => (Collection::Workflows, workflow_id.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Resolution {
+    #[default]
+    Reported,
+    Repaired,
+    Quarantined,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Inconsistency {
+    #[serde(flatten)]
+    pub problem: Problem,
+    pub resolution: Resolution,
+}
+
+/// Outcome of a check
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FsckReport {
+    pub action: FsckAction,
+    pub tasks: usize,
+    pub projects: usize,
+    pub workflows: usize,
+    pub inconsistencies: Vec<Inconsistency>,
+}
+
+impl FsckReport {
+    pub fn is_clean(&self) -> bool {
+        self.inconsistencies.is_empty()
+    }
+
+    pub fn count(&self, resolution: Resolution) -> usize {
+        self.inconsistencies.iter().filter(|i| i.resolution == resolution).count()
+    }
+
+    /// Records the check rewrote or moved to quarantine
+    pub fn changed_records(&self) -> BTreeSet<(Collection, String)> {
+        self.inconsistencies.iter()
+            .filter(|i| i.resolution != Resolution::Reported)
+            .map(|i| i.problem.record())
+            .collect()
+    }
+}
+
+/// Broken references between decoded records; dependencies on `archived`
+/// or cold tasks still count as resolved
+pub fn find_problems(tasks: &[Task], projects: &[Project], workflows: &[Workflow], archived: &HashSet<Uuid>) -> Vec<Problem> {
+    let task_ids: HashSet<Uuid> = tasks.iter().map(|task| task.id).collect();
+    let project_ids: HashSet<Uuid> = projects.iter().map(|project| project.id).collect();
+    let mut problems = Vec::new();
+
+    for task in tasks {
+        if let Some(project_id) = task.project_id
+            && !project_ids.contains(&project_id)
+        {
+            problems.push(Problem::MissingProject { task_id: task.id, project_id });
+        }
+        for dependency in &task.dependencies {
+            if !task_ids.contains(&dependency.task_id) && !archived.contains(&dependency.task_id) {
+                problems.push(Problem::MissingDependency { task_id: task.id, dependency_id: dependency.task_id });
+            }
+        }
+    }
+    for workflow in workflows {
+        let members: HashSet<Uuid> = workflow.tasks.iter().map(|task| task.id).collect();
+        let named: BTreeSet<Uuid> = workflow.dependencies.iter().flat_map(|d| [d.from_task, d.to_task]).collect();
+        for task_id in named {
+            if !members.contains(&task_id) {
+                problems.push(Problem::MissingWorkflowTask { workflow_id: workflow.id, task_id });
+            }
+        }
+    }
+    problems
+}
+
+/// Drop the broken reference a problem describes
+fn repair(problem: &Problem, tasks: &mut HashMap<Uuid, Task>, workflows: &mut HashMap<Uuid, Workflow>) {
+    match problem {
+        Problem::MissingProject { task_id, .. } => {
+            if let Some(task) = tasks.get_mut(task_id) {
+                task.project_id = None;
+            }
+        }
+        Problem::MissingDependency { task_id, dependency_id } => {
+            if let Some(task) = tasks.get_mut(task_id) {
+                task.dependencies.retain(|dependency| dependency.task_id != *dependency_id);
+            }
+        }
+        Problem::MissingWorkflowTask { workflow_id, task_id } => {
+            if let Some(workflow) = workflows.get_mut(workflow_id) {
+                workflow.dependencies.retain(|d| d.from_task != *task_id && d.to_task != *task_id);
+            }
+        }
+        Problem::Undecodable { .. } => {}
+    }
+}
+
+/// Check the stored records, then repair or quarantine what is broken as
+/// `action` says
+pub async fn run(storage: &dyn Storage, action: FsckAction) -> Result<FsckReport> {
+    let mut report = FsckReport { action, ..Default::default() };
+
+    // Collections can't be listed while they hold these
+    for record in storage.find_undecodable_records().await? {
+        let resolution = if action == FsckAction::Check {
+            Resolution::Reported
+        } else {
+            storage.quarantine_record(record.collection, &record.key).await?;
+            Resolution::Quarantined
+        };
+        let problem = Problem::Undecodable { collection: record.collection, key: record.key, error: record.error };
+        report.inconsistencies.push(Inconsistency { problem, resolution });
+    }
+    if action == FsckAction::Check && !report.is_clean() {
+        warn!("Storage check found {} records that no longer decode; references are checked once they are quarantined", report.inconsistencies.len());
+        return Ok(report);
+    }
+
+    let (tasks, projects, workflows) = storage.consistent_read().await?;
+    (report.tasks, report.projects, report.workflows) = (tasks.len(), projects.len(), workflows.len());
+
+    let known: HashSet<Uuid> = tasks.iter().map(|task| task.id).collect();
+    let mut archived = HashSet::new();
+    for dependency in tasks.iter().flat_map(|task| &task.dependencies) {
+        if known.contains(&dependency.task_id) || archived.contains(&dependency.task_id) {
+            continue;
+        }
+        if storage.load_archived_task(&dependency.task_id).await?.is_some()
+            || storage.load_cold_task(&dependency.task_id).await?.is_some() {
+            archived.insert(dependency.task_id);
+        }
+    }
+    let problems = find_problems(&tasks, &projects, &workflows, &archived);
+
+    let resolution = match action {
+        FsckAction::Check => Resolution::Reported,
+        FsckAction::Repair => {
+            let mut tasks: HashMap<Uuid, Task> = tasks.into_iter().map(|task| (task.id, task)).collect();
+            let mut workflows: HashMap<Uuid, Workflow> = workflows.into_iter().map(|workflow| (workflow.id, workflow)).collect();
+            for problem in &problems {
+                repair(problem, &mut tasks, &mut workflows);
+            }
+            // Every repair lands in one commit
+            let mut transaction = Transaction::default();
+            for (collection, key) in problems.iter().map(Problem::record).collect::<BTreeSet<_>>() {
+                let Ok(id) = Uuid::parse_str(&key) else { continue };
+                match collection {
+                    Collection::Tasks => tasks.get(&id).map(|task| transaction.put_task(task)),
+                    Collection::Workflows => workflows.get(&id).map(|workflow| transaction.put_workflow(workflow)),
+                    Collection::Projects => None,
+                };
+            }
+            storage.commit(&transaction).await?;
+            Resolution::Repaired
+        }
+        FsckAction::Quarantine => {
+            for (collection, key) in problems.iter().map(Problem::record).collect::<BTreeSet<_>>() {
+                storage.quarantine_record(collection, &key).await?;
+            }
+            Resolution::Quarantined
+        }
+    };
+    report.inconsistencies.extend(problems.into_iter().map(|problem| Inconsistency { problem, resolution }));
+
+    if report.is_clean() {
+        info!("Storage check found no problems in {} tasks, {} projects and {} workflows", report.tasks, report.projects, report.workflows);
+    } else {
+        warn!(
+            "Storage check found {} problems: {} repaired, {} quarantined",
+            report.inconsistencies.len(), report.count(Resolution::Repaired), report.count(Resolution::Quarantined)
+        );
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::StorageEngine;
+
+    async fn broken_storage() -> (StorageEngine, Task, Task, Project) {
+        let storage = StorageEngine::temporary().unwrap();
+        let project = Project::new("Release");
+        storage.store_project(&project).await.unwrap();
+
+        let mut orphan = Task::new("Deploy").with_command("make deploy").build();
+        orphan.project_id = Some(Uuid::new_v4());
+        orphan.add_dependency(Uuid::new_v4(), None, DependencyCondition::Success, true);
+        let mut linked = Task::new("Build").with_command("make").build();
+        linked.project_id = Some(project.id);
+        orphan.add_dependency(linked.id, None, DependencyCondition::Success, true);
+        storage.store_task(&orphan).await.unwrap();
+        storage.store_task(&linked).await.unwrap();
+        (storage, orphan, linked, project)
+    }
+
+    #[tokio::test]
+    async fn test_fsck_reports_repairs_and_quarantines_broken_records() {
+        let (storage, orphan, linked, _) = broken_storage().await;
+        let report = run(&storage, FsckAction::Check).await.unwrap();
+        assert_eq!((report.tasks, report.projects), (2, 1));
+        assert_eq!(report.count(Resolution::Reported), 2);
+        assert!(matches!(report.inconsistencies[0].problem, Problem::MissingProject { task_id, .. } if task_id == orphan.id));
+
+        let report = run(&storage, FsckAction::Repair).await.unwrap();
+        assert_eq!(report.count(Resolution::Repaired), 2);
+        let repaired = storage.load_task(&orphan.id).await.unwrap().unwrap();
+        assert_eq!(repaired.project_id, None);
+        assert_eq!(repaired.dependencies.len(), 1);
+        assert!(run(&storage, FsckAction::Check).await.unwrap().is_clean());
+
+        let (storage, orphan, linked, _) = broken_storage().await;
+        let report = run(&storage, FsckAction::Quarantine).await.unwrap();
+        assert_eq!(report.changed_records().len(), 1);
+        assert!(storage.load_task(&orphan.id).await.unwrap().is_none());
+        assert!(storage.load_task(&linked.id).await.unwrap().is_some());
+        assert!(storage.load_quarantined(Collection::Tasks, &orphan.id.to_string()).await.unwrap().is_some());
+    }
+}
@@ -0,0 +1,411 @@
+//! GitHub Integration Module
+//!
+//! Imports issues from a GitHub repository as tasks of a project and, when
+//! enabled, closes the issue again once its task is finalized. Imported tasks
+//! track their issue and have no command; the issue's URL is in their
+//! metadata. The token is stored sealed with the secrets key, unless it is
+//! given as a `{{secret:NAME}}` reference.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::calendar::MILESTONE_DUE_KEY;
+use crate::core::*;
+use crate::error::{TaskQueueError, Result};
+use crate::secrets::{self, SecretCipher, StoredSecret};
+use crate::server::TaskQueueServer;
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION, USER_AGENT};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Integration kind under which the configuration is stored
+pub const INTEGRATION_KIND: &str = "github";
+/// Task metadata key linking a task to its issue
+pub const ISSUE_METADATA_KEY: &str = "github_issue";
+
+const DEFAULT_API_URL: &str = "https://api.github.com";
+const PAGE_SIZE: usize = 100;
+
+fn default_api_url() -> String {
+    DEFAULT_API_URL.to_string()
+}
+
+/// Per-project GitHub configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubConfig {
+    /// Repository as `owner/name`
+    pub repository: String,
+    /// Personal access token or GitHub App installation token, or a
+    /// `{{secret:NAME}}` reference to one
+    pub token: String,
+    /// API base URL, for GitHub Enterprise
+    #[serde(default = "default_api_url")]
+    pub api_url: String,
+    /// Close the issue when its task is finalized
+    #[serde(default)]
+    pub sync_status: bool,
+    /// Only import issues carrying all of these labels
+    #[serde(default)]
+    pub labels: Vec<String>,
+}
+
+impl GitHubConfig {
+    /// Copy safe to return from the API
+    pub fn redacted(&self) -> Self {
+        Self {
+            token: "********".to_string(),
+            ..self.clone()
+        }
+    }
+
+    pub(crate) fn validate(&self) -> Result<()> {
+        let mut parts = self.repository.split('/');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(owner), Some(name), None) if !owner.is_empty() && !name.is_empty() => {}
+            _ => {
+                return Err(TaskQueueError::ValidationError {
+                    reason: format!("Repository must be in the form owner/name, got '{}'", self.repository),
+                })
+            }
+        }
+
+        if self.token.trim().is_empty() {
+            return Err(TaskQueueError::ValidationError {
+                reason: "GitHub token cannot be empty".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// The configuration as stored: the token sealed with the secrets key,
+    /// unless it only refers to secrets
+    pub(crate) fn seal(&self, project_id: &Uuid, cipher: Option<&SecretCipher>) -> Result<serde_json::Value> {
+        let mut stored = serde_json::to_value(self)?;
+        if secrets::references(&self.token).is_empty() {
+            let cipher = cipher.ok_or_else(secrets::unavailable)?;
+            let sealed = cipher.seal(None, &token_name(project_id), &self.token, chrono::Utc::now())?;
+            stored["token"] = serde_json::to_value(sealed)?;
+        }
+        Ok(stored)
+    }
+
+    /// A stored configuration with its token opened again; configurations
+    /// stored before tokens were sealed keep theirs as is
+    pub(crate) fn unseal(mut stored: serde_json::Value, cipher: Option<&SecretCipher>) -> Result<Self> {
+        if stored["token"].is_object() {
+            let sealed: StoredSecret = serde_json::from_value(stored["token"].take())?;
+            let cipher = cipher.ok_or_else(secrets::unavailable)?;
+            stored["token"] = json!(cipher.open(&sealed)?);
+        }
+        Ok(serde_json::from_value(stored)?)
+    }
+}
+
+/// Name a project's token is sealed under, so it only opens for that project
+fn token_name(project_id: &Uuid) -> String {
+    format!("github:{}", project_id)
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitHubLabel {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitHubMilestone {
+    pub title: String,
+    pub due_on: Option<String>,
+}
+
+/// Issue as returned by the GitHub REST API
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitHubIssue {
+    pub number: u64,
+    pub title: String,
+    pub body: Option<String>,
+    pub html_url: String,
+    pub state: String,
+    #[serde(default)]
+    pub labels: Vec<GitHubLabel>,
+    pub milestone: Option<GitHubMilestone>,
+    /// Present when the issue is actually a pull request
+    pub pull_request: Option<serde_json::Value>,
+}
+
+/// Outcome of a sync run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GitHubSyncReport {
+    pub imported: usize,
+    pub updated: usize,
+    pub closed: usize,
+}
+
+/// Minimal GitHub REST client
+pub struct GitHubClient {
+    client: reqwest::Client,
+    config: GitHubConfig,
+}
+
+impl GitHubClient {
+    pub fn new(config: GitHubConfig) -> Result<Self> {
+        config.validate()?;
+
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_static("application/vnd.github+json"));
+        headers.insert(USER_AGENT, HeaderValue::from_static("task-queue"));
+        headers.insert("X-GitHub-Api-Version", HeaderValue::from_static("2022-11-28"));
+        let auth = HeaderValue::from_str(&format!("Bearer {}", config.token))
+            .map_err(|_| TaskQueueError::ConfigurationError("Invalid GitHub token".to_string()))?;
+        headers.insert(AUTHORIZATION, auth);
+
+        let client = reqwest::Client::builder().default_headers(headers).build()?;
+        Ok(Self { client, config })
+    }
+
+    fn repo_url(&self) -> String {
+        format!("{}/repos/{}", self.config.api_url.trim_end_matches('/'), self.config.repository)
+    }
+
+    /// Fetch every open issue matching the configured labels
+    pub async fn list_open_issues(&self) -> Result<Vec<GitHubIssue>> {
+        let mut issues = Vec::new();
+        let mut page = 1;
+
+        loop {
+            let mut query = vec![
+                ("state", "open".to_string()),
+                ("per_page", PAGE_SIZE.to_string()),
+                ("page", page.to_string()),
+            ];
+            if !self.config.labels.is_empty() {
+                query.push(("labels", self.config.labels.join(",")));
+            }
+
+            let batch: Vec<GitHubIssue> = self.client
+                .get(format!("{}/issues", self.repo_url()))
+                .query(&query)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+
+            let done = batch.len() < PAGE_SIZE;
+            issues.extend(batch.into_iter().filter(|issue| issue.pull_request.is_none()));
+            if done {
+                break;
+            }
+            page += 1;
+        }
+
+        Ok(issues)
+    }
+
+    /// Close an issue
+    pub async fn close_issue(&self, number: u64) -> Result<()> {
+        self.client
+            .patch(format!("{}/issues/{}", self.repo_url(), number))
+            .json(&json!({ "state": "closed", "state_reason": "completed" }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Issue number a task was imported from, if any
+pub fn linked_issue(task: &Task, repository: &str) -> Option<u64> {
+    let link = task.metadata.get(ISSUE_METADATA_KEY)?;
+    if link.get("repository")?.as_str()? != repository {
+        return None;
+    }
+    link.get("number")?.as_u64()
+}
+
+fn issue_description(issue: &GitHubIssue) -> String {
+    match issue.body.as_deref().map(str::trim) {
+        Some(body) if !body.is_empty() => body.to_string(),
+        _ => format!("Imported from {}", issue.html_url),
+    }
+}
+
+/// Copy the issue's title, body, labels and milestone onto a task
+fn apply_issue(task: &mut Task, issue: &GitHubIssue, repository: &str) {
+    task.name = issue.title.clone();
+    task.description = issue_description(issue);
+
+    let tags = issue.labels.iter().map(|label| json!(label.name)).collect();
+    task.metadata.insert("tags".to_string(), serde_json::Value::Array(tags));
+    match &issue.milestone {
+        Some(milestone) => {
+            task.metadata.insert("milestone".to_string(), json!(milestone.title));
+            match &milestone.due_on {
+                Some(due_on) => {
+                    task.metadata.insert(MILESTONE_DUE_KEY.to_string(), json!(due_on));
+                }
+                None => {
+                    task.metadata.remove(MILESTONE_DUE_KEY);
+                }
+            }
+        }
+        None => {
+            task.metadata.remove("milestone");
+            task.metadata.remove(MILESTONE_DUE_KEY);
+        }
+    }
+
+    let closed = task.metadata.get(ISSUE_METADATA_KEY)
+        .and_then(|link| link.get("closed"))
+        .cloned()
+        .unwrap_or(json!(false));
+    task.metadata.insert(ISSUE_METADATA_KEY.to_string(), json!({
+        "repository": repository,
+        "number": issue.number,
+        "url": issue.html_url,
+        "closed": closed,
+    }));
+}
+
+/// Build a new task for an issue
+pub fn issue_to_task(issue: &GitHubIssue, repository: &str, project_id: Uuid) -> Task {
+    let mut task = Task::new(&issue.title).build();
+    task.project_id = Some(project_id);
+    apply_issue(&mut task, issue, repository);
+    task
+}
+
+/// Close issues of finalized tasks, then import or refresh open issues
+pub async fn sync_project(
+    server: &TaskQueueServer,
+    project_id: Uuid,
+    config: GitHubConfig,
+) -> Result<GitHubSyncReport> {
+    let repository = config.repository.clone();
+    let sync_status = config.sync_status;
+    let github = GitHubClient::new(config)?;
+    let mut report = GitHubSyncReport::default();
+
+    let mut linked: HashMap<u64, Task> = HashMap::new();
+    for task in server.get_tasks_by_project(&project_id).await? {
+        if let Some(number) = linked_issue(&task, &repository) {
+            linked.insert(number, task);
+        }
+    }
+
+    if sync_status {
+        for (number, task) in &linked {
+            let finalized = TaskQueueServer::get_effective_task_status(task) == TaskStatus::Finalized;
+            let already_closed = task.metadata.get(ISSUE_METADATA_KEY)
+                .and_then(|link| link.get("closed"))
+                .and_then(|closed| closed.as_bool())
+                .unwrap_or(false);
+            if !finalized || already_closed {
+                continue;
+            }
+
+            github.close_issue(*number).await?;
+            server.modify_task(task.id, |task| {
+                if let Some(link) = task.metadata.get_mut(ISSUE_METADATA_KEY) {
+                    link["closed"] = json!(true);
+                }
+            }).await?;
+            info!("Closed GitHub issue {}#{} for task {}", repository, number, task.id);
+            report.closed += 1;
+        }
+    }
+
+    for issue in github.list_open_issues().await? {
+        match linked.get(&issue.number) {
+            Some(task) => {
+                server.modify_task(task.id, |task| apply_issue(task, &issue, &repository)).await?;
+                report.updated += 1;
+            }
+            None => {
+                server.submit_task(issue_to_task(&issue, &repository, project_id)).await?;
+                report.imported += 1;
+            }
+        }
+    }
+
+    info!(
+        "GitHub sync for project {}: {} imported, {} updated, {} closed",
+        project_id, report.imported, report.updated, report.closed
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issue() -> GitHubIssue {
+        serde_json::from_value(json!({
+            "number": 42,
+            "title": "Crash on startup",
+            "body": "Steps to reproduce...",
+            "html_url": "https://github.com/acme/app/issues/42",
+            "state": "open",
+            "labels": [{ "name": "bug" }, { "name": "p1" }],
+            "milestone": { "title": "v1.2" }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_issue_maps_labels_and_milestone() {
+        let project_id = Uuid::new_v4();
+        let task = issue_to_task(&issue(), "acme/app", project_id);
+
+        assert_eq!(task.name, "Crash on startup");
+        assert_eq!(task.project_id, Some(project_id));
+        assert!(task.command.is_empty());
+        assert_eq!(task.metadata[ISSUE_METADATA_KEY]["url"], json!("https://github.com/acme/app/issues/42"));
+        assert_eq!(task.metadata["tags"], json!(["bug", "p1"]));
+        assert_eq!(task.metadata["milestone"], json!("v1.2"));
+        assert_eq!(linked_issue(&task, "acme/app"), Some(42));
+        assert_eq!(linked_issue(&task, "acme/other"), None);
+    }
+
+    #[test]
+    fn test_config_requires_owner_and_name() {
+        let config = GitHubConfig {
+            repository: "acme".to_string(),
+            token: "token".to_string(),
+            api_url: default_api_url(),
+            sync_status: false,
+            labels: Vec::new(),
+        };
+        assert!(GitHubClient::new(config).is_err());
+    }
+
+    #[test]
+    fn test_tokens_are_stored_sealed() {
+        let cipher = SecretCipher::from_key("passphrase");
+        let project_id = Uuid::new_v4();
+        let config = GitHubConfig {
+            repository: "acme/app".to_string(),
+            token: "ghp_plaintext".to_string(),
+            api_url: default_api_url(),
+            sync_status: false,
+            labels: Vec::new(),
+        };
+
+        let stored = config.seal(&project_id, Some(&cipher)).unwrap();
+        assert!(!stored.to_string().contains("ghp_plaintext"));
+        assert_eq!(GitHubConfig::unseal(stored.clone(), Some(&cipher)).unwrap().token, "ghp_plaintext");
+        assert!(GitHubConfig::unseal(stored, None).is_err());
+        assert!(config.seal(&project_id, None).is_err());
+
+        // References are resolved when syncing, so they are kept as given
+        let reference = GitHubConfig { token: "{{secret:GITHUB_TOKEN}}".to_string(), ..config };
+        let stored = reference.seal(&project_id, None).unwrap();
+        assert_eq!(stored["token"], json!("{{secret:GITHUB_TOKEN}}"));
+    }
+}
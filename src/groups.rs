@@ -0,0 +1,167 @@
+//! Task Groups Module
+//!
+//! Fan-out/join: sibling tasks submitted together share a group ID in their
+//! `group_id` metadata, and a join task depends on the whole group through a
+//! [`DependencyCondition::Group`] dependency whose `task_id` is the group ID.
+//! The join becomes runnable once every member succeeded, or at least
+//! `quorum` of them.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::*;
+use crate::error::{TaskQueueError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Task metadata key holding the ID of the group the task belongs to
+pub const GROUP_METADATA_KEY: &str = "group_id";
+
+/// The group a task belongs to, if any
+pub fn group_id(task: &Task) -> Option<Uuid> {
+    task.metadata.get(GROUP_METADATA_KEY)?.as_str()?.parse().ok()
+}
+
+/// How far the members of a group have got
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupProgress {
+    pub group_id: Uuid,
+    pub members: Vec<Uuid>,
+    pub succeeded: usize,
+    /// Members that ended `Failed` or `Cancelled`
+    pub failed: usize,
+}
+
+impl GroupProgress {
+    fn new(group_id: Uuid) -> Self {
+        Self { group_id, members: Vec::new(), succeeded: 0, failed: 0 }
+    }
+
+    /// Members that must succeed; every member unless a quorum is given
+    pub fn required(&self, quorum: Option<u32>) -> usize {
+        quorum.map_or(self.members.len(), |quorum| quorum as usize)
+    }
+
+    /// Whether enough members succeeded for a join with this quorum to run
+    pub fn satisfies(&self, quorum: Option<u32>) -> bool {
+        !self.members.is_empty() && self.succeeded >= self.required(quorum)
+    }
+
+    /// Whether too many members failed for the quorum to still be met
+    pub fn unreachable(&self, quorum: Option<u32>) -> bool {
+        self.members.len() - self.failed < self.required(quorum)
+    }
+}
+
+/// Progress of every group the tasks belong to
+pub fn progress<'a>(tasks: impl IntoIterator<Item = &'a Task>) -> HashMap<Uuid, GroupProgress> {
+    let mut groups = HashMap::new();
+    for task in tasks {
+        let Some(group_id) = group_id(task) else {
+            continue;
+        };
+        let group = groups.entry(group_id).or_insert_with(|| GroupProgress::new(group_id));
+        group.members.push(task.id);
+        if matches!(task.result, Some(TaskResult::Success { .. })) {
+            group.succeeded += 1;
+        } else if matches!(task.status, TaskStatus::Failed | TaskStatus::Cancelled) {
+            group.failed += 1;
+        }
+    }
+    groups
+}
+
+/// Body of `POST /groups`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateTaskGroup {
+    /// The sibling tasks fanned out
+    pub tasks: Vec<CreateTaskRequest>,
+    /// Task run once the group is done
+    pub join: Option<CreateTaskRequest>,
+    /// Members that must succeed for the join to run; all if unset
+    pub quorum: Option<u32>,
+}
+
+impl CreateTaskGroup {
+    pub fn validate(&self) -> Result<()> {
+        if self.tasks.is_empty() {
+            return Err(TaskQueueError::ValidationError {
+                reason: "A task group needs at least one task".to_string(),
+            });
+        }
+        if let Some(quorum) = self.quorum
+            && (quorum == 0 || quorum as usize > self.tasks.len())
+        {
+            return Err(TaskQueueError::ValidationError {
+                reason: format!("quorum must be between 1 and the {} tasks in the group", self.tasks.len()),
+            });
+        }
+        Ok(())
+    }
+
+    /// The member tasks tagged with the group, followed by the join task
+    /// depending on it
+    pub fn into_tasks(self, group_id: Uuid) -> Vec<Task> {
+        let mut tasks: Vec<Task> = self.tasks.into_iter()
+            .map(|request| {
+                let mut task = request.to_task();
+                task.metadata.insert(GROUP_METADATA_KEY.to_string(), serde_json::json!(group_id.to_string()));
+                task
+            })
+            .collect();
+        if let Some(join) = self.join {
+            let mut join = join.to_task();
+            join.add_dependency(group_id, None, DependencyCondition::Group { quorum: self.quorum }, true);
+            tasks.push(join);
+        }
+        tasks
+    }
+}
+
+/// Result of `POST /groups`; when not `accepted` no task was stored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupSubmission {
+    pub group_id: Uuid,
+    pub join_task_id: Option<Uuid>,
+    pub accepted: bool,
+    /// One per member, then one for the join task
+    pub items: Vec<BatchItem>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_waits_for_quorum() {
+        let group_id = Uuid::new_v4();
+        let mut members: Vec<Task> = (0..3)
+            .map(|i| {
+                let mut task = Task::new(&format!("shard-{}", i)).build();
+                task.metadata.insert(GROUP_METADATA_KEY.to_string(), serde_json::json!(group_id.to_string()));
+                task
+            })
+            .collect();
+        let mut all = Task::new("merge").build();
+        all.add_dependency(group_id, None, DependencyCondition::Group { quorum: None }, true);
+        let mut two = Task::new("merge-any-two").build();
+        two.add_dependency(group_id, None, DependencyCondition::Group { quorum: Some(2) }, true);
+
+        let success = TaskResult::Success { output: String::new(), artifacts: vec![], metrics: TaskMetrics::default() };
+        let failure = TaskResult::Failure { error: "boom".to_string(), exit_code: Some(1), logs: vec![], metrics: None };
+        members[0].set_result(success.clone());
+        members[1].set_result(success);
+        assert!(two.is_ready_in(&HashMap::new(), &progress(&members)));
+        assert!(!all.is_ready_in(&HashMap::new(), &progress(&members)));
+        assert!(!all.is_ready(&HashMap::new()));
+
+        members[2].set_result(failure);
+        let groups = progress(&members);
+        assert!(groups[&group_id].unreachable(None));
+        assert!(!groups[&group_id].unreachable(Some(2)));
+    }
+}
@@ -0,0 +1,416 @@
+//! gRPC Module
+//!
+//! A tonic service on `server.grpc_port` mirroring the core task operations
+//! of the REST API: submit (one task or a batch), get, list, claim and
+//! complete, defined in `proto/task_queue.proto`. It is meant for workers
+//! that move thousands of tasks, for whom encoding JSON over HTTP/1 costs
+//! more than the work. Calls carry the REST API keys in the `x-api-key` or
+//! `authorization` metadata and are scoped to their tenant the same way;
+//! getting and listing tasks need the key's `read` scope, the rest `write`.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::audit::{self, AuditSource};
+use crate::core::{CreateTaskRequest, PageRequest, TaskMetrics, TaskPriority, TaskResult, TaskType};
+use crate::error::TaskQueueError;
+use crate::history::status_name;
+use crate::server::{TaskQueueServer, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+use crate::tenants::{self, KeyScope, Principal};
+use crate::validation::Validate;
+use crate::views::{SortField, TaskFilter};
+use axum::http::StatusCode;
+use chrono::DateTime;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::{Request, Response, Status};
+use tracing::{error, info};
+use uuid::Uuid;
+
+/// Types and service generated from `proto/task_queue.proto`
+pub mod proto {
+    tonic::include_proto!("task_queue.v1");
+}
+
+use proto::task_queue_server::{TaskQueue, TaskQueueServer as TaskQueueService};
+
+/// The gRPC service over a running server
+#[derive(Clone)]
+pub struct GrpcService {
+    server: Arc<TaskQueueServer>,
+}
+
+impl GrpcService {
+    pub fn new(server: Arc<TaskQueueServer>) -> Self {
+        Self { server }
+    }
+
+    /// Who the API key a call carries belongs to, turning away calls the
+    /// tenancy settings don't let in
+    async fn principal<T>(&self, request: &Request<T>) -> Result<Principal, Status> {
+        let headers = request.metadata().clone().into_headers();
+        self.server.authenticate(tenants::credential(&headers)).await.map_err(|(code, error)| match code {
+            StatusCode::UNAUTHORIZED => Status::unauthenticated(error),
+            _ => Status::internal(error),
+        })
+    }
+
+    /// Run a call needing `scope` scoped to its tenant, audited as coming
+    /// over gRPC
+    async fn scoped<T, R, F>(&self, scope: KeyScope, request: Request<T>, call: impl FnOnce(Arc<TaskQueueServer>, T) -> F) -> Result<Response<R>, Status>
+    where
+        F: Future<Output = Result<R, Status>>,
+    {
+        let principal = self.principal(&request).await?;
+        if !principal.allows(scope) {
+            return Err(Status::permission_denied("The API key lacks the scope this call needs"));
+        }
+        let future = call(self.server.clone(), request.into_inner());
+        tenants::with_tenant(principal.tenant(), audit::with_source(AuditSource::Grpc, future)).await.map(Response::new)
+    }
+}
+
+#[tonic::async_trait]
+impl TaskQueue for GrpcService {
+    async fn submit_task(&self, request: Request<proto::SubmitTaskRequest>) -> Result<Response<proto::SubmitTaskResponse>, Status> {
+        self.scoped(KeyScope::Write, request, |server, request| async move {
+            accepting(&server)?;
+            let task = new_task(request.task.unwrap_or_default()).map_err(Status::invalid_argument)?;
+            let task_id = server.submit_task(task.to_task()).await.map_err(status)?;
+            Ok(proto::SubmitTaskResponse { task_id: task_id.to_string() })
+        }).await
+    }
+
+    async fn submit_tasks(&self, request: Request<proto::SubmitTasksRequest>) -> Result<Response<proto::SubmitTasksResponse>, Status> {
+        self.scoped(KeyScope::Write, request, |server, request| async move {
+            accepting(&server)?;
+            let mut tasks = Vec::with_capacity(request.tasks.len());
+            for (index, task) in request.tasks.into_iter().enumerate() {
+                let task = new_task(task).map_err(|e| Status::invalid_argument(format!("tasks[{}]: {}", index, e)))?;
+                tasks.push(task.to_task());
+            }
+            let batch = server.submit_tasks(tasks).await.map_err(status)?;
+            Ok(proto::SubmitTasksResponse {
+                accepted: batch.accepted,
+                items: batch.items.into_iter().map(|item| proto::BatchItem {
+                    index: item.index as u32,
+                    task_id: item.task_id.map(|id| id.to_string()),
+                    error: item.error,
+                }).collect(),
+            })
+        }).await
+    }
+
+    async fn get_task(&self, request: Request<proto::GetTaskRequest>) -> Result<Response<proto::Task>, Status> {
+        self.scoped(KeyScope::Read, request, |server, request| async move {
+            let task = server.get_task(parse_id("task_id", &request.task_id)?).await.map_err(status)?;
+            Ok(task_message(&task))
+        }).await
+    }
+
+    async fn list_tasks(&self, request: Request<proto::ListTasksRequest>) -> Result<Response<proto::ListTasksResponse>, Status> {
+        self.scoped(KeyScope::Read, request, |server, request| async move {
+            let filter = TaskFilter {
+                project: request.project,
+                project_id: request.project_id.as_deref().map(|id| parse_id("project_id", id)).transpose()?,
+                statuses: request.statuses,
+                tags: request.tags,
+                ..TaskFilter::default()
+            };
+            let limit = match request.limit {
+                0 => DEFAULT_PAGE_SIZE,
+                limit => (limit as usize).min(MAX_PAGE_SIZE),
+            };
+            let page = PageRequest { cursor: request.cursor, offset: 0, limit, sort: SortField::CreatedAt };
+            let page = server.list_tasks_page(&filter, page).await.map_err(status)?;
+            Ok(proto::ListTasksResponse {
+                tasks: page.tasks.iter().map(task_message).collect(),
+                next_cursor: page.next_cursor,
+                total: page.total as u64,
+            })
+        }).await
+    }
+
+    async fn claim_task(&self, request: Request<proto::ClaimTaskRequest>) -> Result<Response<proto::ClaimTaskResponse>, Status> {
+        self.scoped(KeyScope::Write, request, |server, request| async move {
+            if request.worker_id.trim().is_empty() {
+                return Err(Status::invalid_argument("worker_id must not be empty"));
+            }
+            let task = server.claim_next_task(&request.worker_id, request.lease_secs).await.map_err(status)?;
+            Ok(proto::ClaimTaskResponse { task: task.as_ref().map(task_message) })
+        }).await
+    }
+
+    async fn complete_task(&self, request: Request<proto::CompleteTaskRequest>) -> Result<Response<proto::Task>, Status> {
+        self.scoped(KeyScope::Write, request, |server, request| async move {
+            let task_id = parse_id("task_id", &request.task_id)?;
+            let result = request.result.and_then(task_result).ok_or_else(|| Status::invalid_argument("result must be set"))?;
+            let task = server.complete_leased_task(task_id, &request.worker_id, result).await.map_err(status)?;
+            Ok(task_message(&task))
+        }).await
+    }
+}
+
+/// The gRPC answer to an error, as REST would answer it
+fn status(e: TaskQueueError) -> Status {
+    match e {
+        TaskQueueError::TaskNotFound { .. } | TaskQueueError::ProjectNotFound { .. } => Status::not_found(e.to_string()),
+        TaskQueueError::LeaseNotHeld { .. } => Status::failed_precondition(e.to_string()),
+        TaskQueueError::ValidationError { .. }
+        | TaskQueueError::InvalidTaskDefinition { .. }
+        | TaskQueueError::InvalidFields { .. } => Status::invalid_argument(e.to_string()),
+        TaskQueueError::PermissionDenied { .. } => Status::permission_denied(e.to_string()),
+        e => {
+            error!("gRPC call failed: {}", e);
+            Status::internal(e.to_string())
+        }
+    }
+}
+
+/// Turn away submissions while the server is in maintenance, like REST's `503`
+fn accepting(server: &TaskQueueServer) -> Result<(), Status> {
+    match server.maintenance().is_enabled() {
+        true => Err(Status::unavailable("The server is in maintenance and accepts no new tasks")),
+        false => Ok(()),
+    }
+}
+
+fn parse_id(field: &str, id: &str) -> Result<Uuid, Status> {
+    Uuid::parse_str(id).map_err(|_| Status::invalid_argument(format!("{} is not a UUID", field)))
+}
+
+fn millis(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_millis() as i64)
+}
+
+fn priority(priority: proto::TaskPriority) -> TaskPriority {
+    match priority {
+        proto::TaskPriority::Unspecified | proto::TaskPriority::Normal => TaskPriority::Normal,
+        proto::TaskPriority::Low => TaskPriority::Low,
+        proto::TaskPriority::High => TaskPriority::High,
+        proto::TaskPriority::Critical => TaskPriority::Critical,
+    }
+}
+
+fn priority_message(priority: &TaskPriority) -> proto::TaskPriority {
+    match priority {
+        TaskPriority::Low => proto::TaskPriority::Low,
+        TaskPriority::Normal => proto::TaskPriority::Normal,
+        TaskPriority::High => proto::TaskPriority::High,
+        TaskPriority::Critical => proto::TaskPriority::Critical,
+    }
+}
+
+/// The REST body of a new task, checked as REST checks it
+fn new_task(task: proto::NewTask) -> Result<CreateTaskRequest, String> {
+    let task_type = match task.task_type.as_str() {
+        "" => TaskType::Simple,
+        name => serde_json::from_value(serde_json::json!(name)).map_err(|_| format!("task_type {} is not a task type", name))?,
+    };
+    let run_at = task.run_at_ms
+        .map(|at| DateTime::from_timestamp_millis(at).ok_or_else(|| "run_at_ms is out of range".to_string()))
+        .transpose()?;
+    let request = CreateTaskRequest {
+        priority: priority(task.priority()),
+        name: task.name,
+        command: task.command,
+        description: task.description,
+        technical_specs: task.technical_specs,
+        acceptance_criteria: Some(task.acceptance_criteria),
+        project: task.project,
+        task_type,
+        project_id: task.project_id.as_deref().map(|id| Uuid::parse_str(id).map_err(|_| "project_id is not a UUID".to_string())).transpose()?,
+        estimated_hours: task.estimated_hours,
+        tags: (!task.tags.is_empty()).then_some(task.tags),
+        ai_reviews_required: task.ai_reviews_required,
+        run_at,
+        timeout_secs: task.timeout_secs,
+        cpu: task.cpu,
+        memory_mb: task.memory_mb,
+        queue: task.queue,
+    };
+    match request.validate() {
+        errors if errors.is_empty() => Ok(request),
+        errors => Err(errors.iter().map(|e| format!("{}: {}", e.field, e.message)).collect::<Vec<_>>().join("; ")),
+    }
+}
+
+fn metrics_message(metrics: &TaskMetrics) -> proto::TaskMetrics {
+    proto::TaskMetrics {
+        execution_time_ms: metrics.execution_time.as_millis() as u64,
+        memory_usage: metrics.memory_usage,
+        cpu_usage: metrics.cpu_usage,
+        disk_usage: metrics.disk_usage,
+        network_io: metrics.network_io,
+    }
+}
+
+fn task_metrics(metrics: proto::TaskMetrics) -> TaskMetrics {
+    TaskMetrics {
+        execution_time: Duration::from_millis(metrics.execution_time_ms),
+        memory_usage: metrics.memory_usage,
+        cpu_usage: metrics.cpu_usage,
+        disk_usage: metrics.disk_usage,
+        network_io: metrics.network_io,
+    }
+}
+
+fn result_message(result: &TaskResult) -> proto::TaskResult {
+    use proto::task_result::{Cancelled, Failure, Outcome, Success};
+    let outcome = match result {
+        TaskResult::Success { output, artifacts, metrics } => Outcome::Success(Success {
+            output: output.clone(),
+            artifacts: artifacts.clone(),
+            metrics: Some(metrics_message(metrics)),
+        }),
+        TaskResult::Failure { error, exit_code, logs, metrics } => Outcome::Failure(Failure {
+            error: error.clone(),
+            exit_code: *exit_code,
+            logs: logs.clone(),
+            metrics: metrics.as_ref().map(metrics_message),
+        }),
+        TaskResult::Cancelled { reason } => Outcome::Cancelled(Cancelled { reason: reason.clone() }),
+    };
+    proto::TaskResult { outcome: Some(outcome) }
+}
+
+/// The result a worker reports; `None` without an outcome
+fn task_result(result: proto::TaskResult) -> Option<TaskResult> {
+    use proto::task_result::Outcome;
+    Some(match result.outcome? {
+        Outcome::Success(success) => TaskResult::Success {
+            output: success.output,
+            artifacts: success.artifacts,
+            metrics: success.metrics.map(task_metrics).unwrap_or_default(),
+        },
+        Outcome::Failure(failure) => TaskResult::Failure {
+            error: failure.error,
+            exit_code: failure.exit_code,
+            logs: failure.logs,
+            metrics: failure.metrics.map(task_metrics),
+        },
+        Outcome::Cancelled(cancelled) => TaskResult::Cancelled { reason: cancelled.reason },
+    })
+}
+
+fn task_message(task: &crate::core::Task) -> proto::Task {
+    let mut message = proto::Task {
+        id: task.id.to_string(),
+        name: task.name.clone(),
+        command: task.command.clone(),
+        description: task.description.clone(),
+        project: task.project.clone(),
+        project_id: task.project_id.map(|id| id.to_string()),
+        task_type: status_name(&task.task_type).unwrap_or_default(),
+        priority: 0,
+        status: status_name(&task.status).unwrap_or_default(),
+        created_at_ms: millis(task.created_at),
+        updated_at_ms: millis(task.updated_at),
+        version: task.version,
+        result: task.result.as_ref().map(result_message),
+        metadata_json: serde_json::to_string(&task.metadata).unwrap_or_default(),
+    };
+    message.set_priority(priority_message(&task.priority));
+    message
+}
+
+/// Serve the gRPC API on `listener` until the process exits
+pub async fn serve(server: Arc<TaskQueueServer>, listener: TcpListener) -> Result<(), tonic::transport::Error> {
+    tonic::transport::Server::builder()
+        .add_service(TaskQueueService::new(GrpcService::new(server)))
+        .serve_with_incoming(TcpListenerStream::new(listener))
+        .await
+}
+
+/// Start serving the gRPC API on `address`
+pub fn spawn(server: Arc<TaskQueueServer>, address: String) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let listener = match TcpListener::bind(&address).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("Failed to bind the gRPC API to {}: {}", address, e);
+                return;
+            }
+        };
+        info!("gRPC API listening on {}", address);
+        if let Err(e) = serve(server, listener).await {
+            error!("gRPC API stopped: {}", e);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::StorageEngine;
+    use crate::vectorizer::VectorizerIntegration;
+    use proto::task_queue_client::TaskQueueClient;
+
+    #[tokio::test]
+    async fn test_workers_submit_claim_and_complete_over_grpc() {
+        let server = Arc::new(TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        ).await.unwrap());
+        let project_id = server.create_project("grpc".to_string(), None).await.unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(serve(server.clone(), listener));
+        let mut client = TaskQueueClient::connect(format!("http://{}", address)).await.unwrap();
+
+        let new_task = |name: &str| proto::NewTask {
+            name: name.to_string(),
+            command: "make".to_string(),
+            description: "Build it".to_string(),
+            project_id: Some(project_id.to_string()),
+            priority: proto::TaskPriority::High as i32,
+            ..Default::default()
+        };
+        let submitted = client.submit_task(proto::SubmitTaskRequest { task: Some(new_task("build")) }).await.unwrap().into_inner();
+        let invalid = client.submit_task(proto::SubmitTaskRequest { task: Some(new_task(" ")) }).await.unwrap_err();
+        assert_eq!(invalid.code(), tonic::Code::InvalidArgument);
+        let batch = client.submit_tasks(proto::SubmitTasksRequest { tasks: vec![new_task("test"), new_task("lint")] })
+            .await.unwrap().into_inner();
+        assert!(batch.accepted);
+        assert_eq!(batch.items.len(), 2);
+
+        let task = client.get_task(proto::GetTaskRequest { task_id: submitted.task_id.clone() }).await.unwrap().into_inner();
+        assert_eq!((task.name.as_str(), task.priority()), ("build", proto::TaskPriority::High));
+        let missing = client.get_task(proto::GetTaskRequest { task_id: Uuid::new_v4().to_string() }).await.unwrap_err();
+        assert_eq!(missing.code(), tonic::Code::NotFound);
+
+        let page = client.list_tasks(proto::ListTasksRequest { limit: 2, ..Default::default() }).await.unwrap().into_inner();
+        assert_eq!((page.tasks.len(), page.total), (2, 3));
+        assert!(page.next_cursor.is_some());
+
+        // Tasks submitted over the API start out planned; run one that is ready
+        let mut ready = crate::core::Task::new("ready").with_command("make").build();
+        ready.project_id = Some(project_id);
+        ready.status = crate::core::TaskStatus::Pending;
+        let ready = server.submit_task(ready).await.unwrap();
+        let claimed = client.claim_task(proto::ClaimTaskRequest { worker_id: "worker-1".to_string(), lease_secs: Some(60) })
+            .await.unwrap().into_inner().task.unwrap();
+        assert_eq!(claimed.id, ready.to_string());
+        let result = proto::TaskResult {
+            outcome: Some(proto::task_result::Outcome::Success(proto::task_result::Success { output: "ok".to_string(), ..Default::default() })),
+        };
+        let stranger = client.complete_task(proto::CompleteTaskRequest {
+            task_id: claimed.id.clone(),
+            worker_id: "worker-2".to_string(),
+            result: Some(result.clone()),
+        }).await.unwrap_err();
+        assert_eq!(stranger.code(), tonic::Code::FailedPrecondition);
+        let completed = client.complete_task(proto::CompleteTaskRequest {
+            task_id: claimed.id,
+            worker_id: "worker-1".to_string(),
+            result: Some(result),
+        }).await.unwrap().into_inner();
+        assert_eq!(completed.status, "Completed");
+        assert!(matches!(completed.result.and_then(|result| result.outcome), Some(proto::task_result::Outcome::Success(_))));
+    }
+}
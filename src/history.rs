@@ -0,0 +1,150 @@
+//! State History Module
+//!
+//! Append-only log of every task and workflow state change: who made it,
+//! when, and the status before and after. It is written next to the current
+//! records, never rewritten, and kept when a task is deleted.
+//! `GET /tasks/{id}/history` and `GET /workflows/{id}/history` replay it into
+//! a timeline, which is what you want when an agent keeps flipping a task's
+//! status back and forth.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::events::{TaskEvent, TaskEventKind};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// One recorded change of a task's or workflow's state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateChange {
+    /// Task or workflow ID
+    pub entity_id: Uuid,
+    /// Sequence of the lifecycle event it was recorded with
+    pub sequence: u64,
+    pub kind: TaskEventKind,
+    /// Status before the change; none on creation
+    pub from: Option<String>,
+    /// Status after the change; none on deletion
+    pub to: Option<String>,
+    /// Agent whose request made the change
+    pub agent: Option<Uuid>,
+    pub at: DateTime<Utc>,
+}
+
+impl StateChange {
+    /// The change an event records for an entity, given the statuses around it
+    pub fn new(entity_id: Uuid, event: &TaskEvent, from: Option<String>, to: Option<String>) -> Self {
+        Self {
+            entity_id,
+            sequence: event.sequence,
+            kind: event.kind,
+            from,
+            to,
+            agent: event.agent,
+            at: event.timestamp,
+        }
+    }
+}
+
+/// Name of a task or workflow status as it appears on the wire
+pub fn status_name(status: &impl Serialize) -> Option<String> {
+    match serde_json::to_value(status).ok()? {
+        serde_json::Value::String(name) => Some(name),
+        other => Some(other.to_string()),
+    }
+}
+
+/// Time spent in one status
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusPeriod {
+    pub status: String,
+    pub since: DateTime<Utc>,
+    /// Still in this status if unset
+    pub until: Option<DateTime<Utc>>,
+    /// Agent that moved the entity into this status
+    pub set_by: Option<Uuid>,
+}
+
+/// Timeline of an entity rebuilt from its recorded changes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateHistory {
+    pub entity_id: Uuid,
+    /// Status after the last change; none once deleted
+    pub status: Option<String>,
+    pub deleted: bool,
+    pub periods: Vec<StatusPeriod>,
+    pub changes: Vec<StateChange>,
+}
+
+impl StateHistory {
+    /// Replay changes, oldest first
+    pub fn replay(entity_id: Uuid, changes: Vec<StateChange>) -> Self {
+        let mut periods: Vec<StatusPeriod> = Vec::new();
+        let mut status = None;
+        let mut deleted = false;
+
+        for change in &changes {
+            deleted = change.kind == TaskEventKind::TaskDeleted;
+            if change.to == status && !deleted {
+                continue;
+            }
+            if let Some(open) = periods.last_mut().filter(|period| period.until.is_none()) {
+                open.until = Some(change.at);
+            }
+            status = if deleted { None } else { change.to.clone() };
+            if let Some(to) = &status {
+                periods.push(StatusPeriod {
+                    status: to.clone(),
+                    since: change.at,
+                    until: None,
+                    set_by: change.agent,
+                });
+            }
+        }
+
+        Self { entity_id, status, deleted, periods, changes }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_builds_status_periods() {
+        let task_id = Uuid::new_v4();
+        let agent = Uuid::new_v4();
+        let start = Utc::now();
+        let change = |sequence: u64, kind, from: Option<&str>, to: Option<&str>, agent| StateChange {
+            entity_id: task_id,
+            sequence,
+            kind,
+            from: from.map(str::to_string),
+            to: to.map(str::to_string),
+            agent,
+            at: start + chrono::Duration::seconds(sequence as i64),
+        };
+
+        let history = StateHistory::replay(task_id, vec![
+            change(1, TaskEventKind::TaskCreated, None, Some("Planning"), None),
+            change(2, TaskEventKind::TaskStatusChanged, Some("Planning"), Some("Implementation"), Some(agent)),
+            change(3, TaskEventKind::TaskStatusChanged, Some("Implementation"), Some("Planning"), Some(agent)),
+        ]);
+        assert_eq!(history.status.as_deref(), Some("Planning"));
+        let statuses: Vec<&str> = history.periods.iter().map(|p| p.status.as_str()).collect();
+        assert_eq!(statuses, ["Planning", "Implementation", "Planning"]);
+        assert_eq!(history.periods[1].set_by, Some(agent));
+        assert_eq!(history.periods[1].until, Some(start + chrono::Duration::seconds(3)));
+        assert!(history.periods[2].until.is_none());
+
+        let mut changes = history.changes;
+        changes.push(change(4, TaskEventKind::TaskDeleted, Some("Planning"), None, None));
+        let history = StateHistory::replay(task_id, changes);
+        assert!(history.deleted && history.status.is_none());
+        assert!(history.periods.iter().all(|p| p.until.is_some()));
+    }
+}
@@ -0,0 +1,211 @@
+//! HTTP Request Task Module
+//!
+//! Built-in executor for `TaskType::Http` tasks, which make one HTTP call
+//! instead of running a command: webhook-style workflow steps without
+//! shelling out to curl. The request is described by the task's `http`
+//! metadata, e.g.
+//!
+//! ```json
+//! {"method": "POST", "url": "https://ci.example.com/hooks/deploy",
+//!  "headers": {"Authorization": "Bearer {{secret:CI_TOKEN}}"},
+//!  "body": {"ref": "main"}}
+//! ```
+//!
+//! Header values may refer to secrets. The response status, headers and body
+//! are recorded as the task output; a status outside `expected_status` (any
+//! 2xx by default) fails the task.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::*;
+use crate::executor::{failure, Executor};
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Instant;
+use tracing::info;
+
+/// Executor name
+pub const HTTP_EXECUTOR: &str = "http_request";
+/// Task metadata key holding the request
+pub const HTTP_METADATA_KEY: &str = "http";
+
+/// The HTTP call an HTTP task makes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HttpRequestSpec {
+    #[serde(default = "default_method")]
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Strings are sent as they are, anything else as JSON
+    pub body: Option<serde_json::Value>,
+    /// Statuses that count as success; any 2xx if empty
+    #[serde(default)]
+    pub expected_status: Vec<u16>,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+impl HttpRequestSpec {
+    /// The request described by a task's metadata
+    pub fn of(task: &Task) -> std::result::Result<Self, String> {
+        let value = task.metadata.get(HTTP_METADATA_KEY).ok_or("HTTP task has no `http` request metadata")?;
+        let spec: Self = serde_json::from_value(value.clone()).map_err(|e| format!("Invalid HTTP request: {}", e))?;
+        spec.method()?;
+        reqwest::Url::parse(&spec.url).map_err(|e| format!("Invalid HTTP request URL '{}': {}", spec.url, e))?;
+        Ok(spec)
+    }
+
+    fn method(&self) -> std::result::Result<reqwest::Method, String> {
+        reqwest::Method::from_bytes(self.method.to_uppercase().as_bytes())
+            .map_err(|_| format!("Invalid HTTP method '{}'", self.method))
+    }
+
+    fn accepts(&self, status: reqwest::StatusCode) -> bool {
+        match self.expected_status.is_empty() {
+            true => status.is_success(),
+            false => self.expected_status.contains(&status.as_u16()),
+        }
+    }
+}
+
+/// What an HTTP task records as its output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: BTreeMap<String, String>,
+    pub body: String,
+}
+
+/// Makes the HTTP call of `TaskType::Http` tasks
+#[derive(Debug, Clone, Default)]
+pub struct HttpRequestExecutor {
+    client: reqwest::Client,
+}
+
+impl HttpRequestExecutor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    async fn run(&self, task: &Task) -> TaskResult {
+        let spec = match HttpRequestSpec::of(task) {
+            Ok(spec) => spec,
+            Err(e) => return failure(e, None, Vec::new()),
+        };
+        let method = spec.method().unwrap_or(reqwest::Method::GET);
+        let mut request = self.client.request(method.clone(), &spec.url);
+        for (name, value) in &spec.headers {
+            request = request.header(name, value);
+        }
+        request = match &spec.body {
+            Some(serde_json::Value::String(body)) => request.body(body.clone()),
+            Some(body) => request.json(body),
+            None => request,
+        };
+
+        info!("Executing task {} ({}): {} {}", task.name, task.id, method, spec.url);
+        let start = Instant::now();
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(e) => return failure(format!("{} {} failed: {}", method, spec.url, e), None, Vec::new()),
+        };
+        let status = response.status();
+        let headers = response.headers().iter()
+            .map(|(name, value)| (name.to_string(), String::from_utf8_lossy(value.as_bytes()).to_string()))
+            .collect();
+        let body = match response.text().await {
+            Ok(body) => body,
+            Err(e) => return failure(format!("Failed to read response of {} {}: {}", method, spec.url, e), None, Vec::new()),
+        };
+        let metrics = TaskMetrics {
+            execution_time: start.elapsed(),
+            network_io: body.len() as u64,
+            ..Default::default()
+        };
+
+        if !spec.accepts(status) {
+            return TaskResult::Failure {
+                error: format!("{} {} returned {}", method, spec.url, status),
+                exit_code: None,
+                logs: body.lines().map(str::to_string).collect(),
+                metrics: Some(metrics),
+            };
+        }
+        let response = HttpResponse { status: status.as_u16(), headers, body };
+        TaskResult::Success {
+            output: serde_json::to_string(&response).unwrap_or(response.body),
+            artifacts: Vec::new(),
+            metrics,
+        }
+    }
+}
+
+impl Executor for HttpRequestExecutor {
+    fn name(&self) -> &str {
+        HTTP_EXECUTOR
+    }
+
+    fn execute<'a>(&'a self, task: &'a Task) -> BoxFuture<'a, TaskResult> {
+        Box::pin(self.run(task))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::ExecutorRegistry;
+    use axum::{routing::post, Json, Router};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_http_tasks_record_the_response() {
+        let app = Router::new()
+            .route("/hook", post(|headers: axum::http::HeaderMap, Json(body): Json<serde_json::Value>| async move {
+                let token = headers.get("x-token").and_then(|v| v.to_str().ok()).unwrap_or_default().to_string();
+                Json(json!({"token": token, "ref": body["ref"]}))
+            }))
+            .route("/broken", post(|| async { (axum::http::StatusCode::BAD_GATEWAY, "upstream down") }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let task = |path: &str| {
+            Task::new("notify")
+                .with_type(TaskType::Http)
+                .with_metadata(HTTP_METADATA_KEY, json!({
+                    "method": "post",
+                    "url": format!("http://{}{}", addr, path),
+                    "headers": {"X-Token": "abc"},
+                    "body": {"ref": "main"}
+                }))
+                .build()
+        };
+
+        match ExecutorRegistry::new().execute(&task("/hook")).await {
+            TaskResult::Success { output, .. } => {
+                let response: HttpResponse = serde_json::from_str(&output).unwrap();
+                assert_eq!(response.status, 200);
+                assert_eq!(serde_json::from_str::<serde_json::Value>(&response.body).unwrap(), json!({"token": "abc", "ref": "main"}));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match ExecutorRegistry::new().execute(&task("/broken")).await {
+            TaskResult::Failure { error, logs, .. } => {
+                assert!(error.contains("502"));
+                assert_eq!(logs, ["upstream down"]);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let invalid = Task::new("notify").with_type(TaskType::Http).with_metadata(HTTP_METADATA_KEY, json!({"url": "not a url"})).build();
+        assert!(HttpRequestSpec::of(&invalid).is_err());
+    }
+}
@@ -0,0 +1,150 @@
+//! Idempotency Module
+//!
+//! Agents on flaky connections retry calls whose response they never got,
+//! which used to create the task, workflow or project twice. A `POST` to
+//! `/tasks`, `/workflows` or `/projects` may carry an `Idempotency-Key`
+//! header; its successful response is stored under the key for
+//! `server.idempotency.window_secs`, and a repeat of the request with the same
+//! key gets that response back, marked `Idempotent-Replayed: true`, without
+//! running it again. Reusing a key for a different body is rejected with
+//! `422`, and a repeat arriving while the first request still runs with
+//! `409`. Failed requests aren't stored, so they can be retried as they are.
+//! Responses live in storage, so every replica of a stateless deployment
+//! replays them. Each tenant's keys are its own, so two tenants using the
+//! same key never get each other's responses.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+pub const REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// Longest key accepted
+pub const MAX_KEY_LENGTH: usize = 255;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdempotencyConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// How long a response is replayed for
+    #[serde(default = "default_window_secs")]
+    pub window_secs: u64,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_window_secs() -> u64 {
+    24 * 60 * 60
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            window_secs: default_window_secs(),
+        }
+    }
+}
+
+/// A stored response, keyed by tenant, method, path and key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdempotencyRecord {
+    pub scope: String,
+    /// SHA-256 of the request body
+    pub fingerprint: String,
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl IdempotencyRecord {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at <= now
+    }
+}
+
+/// The scope a key is stored under; the same key may be used on each route
+/// and by each tenant, which never get each other's responses back
+pub fn scope(tenant: Option<Uuid>, method: &str, path: &str, key: &str) -> String {
+    let tenant = tenant.map_or_else(|| "-".to_string(), |tenant| tenant.to_string());
+    format!("{} {} {} {}", tenant, method, path, key)
+}
+
+pub fn fingerprint(body: &[u8]) -> String {
+    Sha256::digest(body).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Keys of the requests running right now
+#[derive(Debug, Default)]
+pub struct Idempotency {
+    config: IdempotencyConfig,
+    in_flight: Mutex<HashSet<String>>,
+}
+
+impl Idempotency {
+    pub fn new(config: &IdempotencyConfig) -> Self {
+        Self {
+            config: config.clone(),
+            in_flight: Mutex::default(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    pub fn expires_at(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now + chrono::Duration::seconds(self.config.window_secs.min(i64::MAX as u64) as i64)
+    }
+
+    /// Mark a scope as running, or `None` if it already is; the mark goes
+    /// away when the guard is dropped
+    pub fn begin(&self, scope: &str) -> Option<InFlight<'_>> {
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        in_flight.insert(scope.to_string()).then(|| InFlight { idempotency: self, scope: scope.to_string() })
+    }
+}
+
+pub struct InFlight<'a> {
+    idempotency: &'a Idempotency,
+    scope: String,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.idempotency.in_flight.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.scope);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_one_request_per_key_at_a_time() {
+        let idempotency = Idempotency::new(&IdempotencyConfig::default());
+        let scope = scope(None, "POST", "/tasks", "retry-1");
+        let running = idempotency.begin(&scope).unwrap();
+        assert!(idempotency.begin(&scope).is_none());
+        assert!(idempotency.begin(&super::scope(None, "POST", "/projects", "retry-1")).is_some());
+        assert!(idempotency.begin(&super::scope(Some(Uuid::new_v4()), "POST", "/tasks", "retry-1")).is_some());
+        drop(running);
+        assert!(idempotency.begin(&scope).is_some());
+
+        assert_eq!(fingerprint(b"{}"), fingerprint(b"{}"));
+        assert_ne!(fingerprint(b"{}"), fingerprint(b"[]"));
+    }
+}
@@ -0,0 +1,48 @@
+//! Task Import Module
+//!
+//! Bulk import for moving a backlog over from another queue system.
+//! `POST /admin/import` and `task-queue tasks import <file>` take JSON Lines,
+//! one task per line in the shape `POST /tasks` accepts. The input is read a
+//! line at a time, so files of any size stream through. Each line is parsed
+//! and validated on its own; lines that fail are reported with their line
+//! number and skipped while the rest are imported. Blank lines are ignored.
+//! With `dry_run` every line is checked but nothing is stored.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::CreateTaskRequest;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A line that could not be imported
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LineError {
+    /// 1-based line number in the input
+    pub line: usize,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    /// Non-blank lines read
+    pub lines: usize,
+    /// Tasks stored, or that would be with `dry_run`
+    pub imported: usize,
+    /// IDs of the stored tasks, in input order
+    pub task_ids: Vec<Uuid>,
+    pub errors: Vec<LineError>,
+}
+
+/// Parse one input line; `None` for a blank one
+pub fn parse_line(line: &str) -> Option<Result<CreateTaskRequest, String>> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+    Some(serde_json::from_str(line).map_err(|e| e.to_string()))
+}
@@ -0,0 +1,498 @@
+//! Queue Ingest Module
+//!
+//! Consumes task submissions from AMQP, NATS JetStream, SQS or Redis Streams. Each message
+//! body is the JSON accepted by `POST /tasks` and goes through the same
+//! validation as the REST API. Messages are acknowledged only after the task
+//! is durably stored; invalid ones are rejected and transient failures are
+//! handed back to the broker for redelivery.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::config::IngestConfig;
+use crate::core::CreateTaskRequest;
+use crate::error::{TaskQueueError, Result};
+use crate::server::TaskQueueServer;
+use futures_util::StreamExt;
+use futures_util::future::BoxFuture;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// What to tell the broker about a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Task stored; remove the message
+    Ack,
+    /// Message can never be accepted; drop or dead-letter it
+    Reject,
+    /// Temporary failure; deliver the message again
+    Requeue,
+}
+
+/// Broker-specific acknowledgement of one message
+pub trait Settle: Send {
+    fn settle(self: Box<Self>, outcome: Outcome) -> BoxFuture<'static, Result<()>>;
+}
+
+/// A message received from a queue
+pub struct IngestMessage {
+    pub body: Vec<u8>,
+    settle: Box<dyn Settle>,
+}
+
+impl IngestMessage {
+    pub fn new(body: Vec<u8>, settle: impl Settle + 'static) -> Self {
+        Self {
+            body,
+            settle: Box::new(settle),
+        }
+    }
+
+    pub async fn settle(self, outcome: Outcome) -> Result<()> {
+        self.settle.settle(outcome).await
+    }
+}
+
+/// Queue task submissions are read from
+pub trait MessageSource: Send {
+    /// Wait for the next message; `None` once the source is closed
+    fn next<'a>(&'a mut self) -> BoxFuture<'a, Result<Option<IngestMessage>>>;
+}
+
+fn broker_error(e: impl std::fmt::Display) -> TaskQueueError {
+    TaskQueueError::BrokerError(e.to_string())
+}
+
+#[cfg(feature = "amqp")]
+pub struct AmqpSource {
+    // Kept so the connection is not closed while consuming
+    connection: lapin::Connection,
+    consumer: lapin::Consumer,
+}
+
+#[cfg(feature = "amqp")]
+impl AmqpSource {
+    pub async fn connect(url: &str, queue: &str, prefetch: u16) -> Result<Self> {
+        use lapin::options::{BasicConsumeOptions, BasicQosOptions};
+
+        let connection = lapin::Connection::connect(url, lapin::ConnectionProperties::default())
+            .await
+            .map_err(broker_error)?;
+        let channel = connection.create_channel().await.map_err(broker_error)?;
+        channel.basic_qos(prefetch, BasicQosOptions::default()).await.map_err(broker_error)?;
+        let consumer = channel
+            .basic_consume(
+                queue.into(),
+                "task-queue".into(),
+                BasicConsumeOptions::default(),
+                lapin::types::FieldTable::default(),
+            )
+            .await
+            .map_err(broker_error)?;
+        Ok(Self { connection, consumer })
+    }
+}
+
+#[cfg(feature = "amqp")]
+impl Settle for lapin::Acker {
+    fn settle(self: Box<Self>, outcome: Outcome) -> BoxFuture<'static, Result<()>> {
+        use lapin::options::{BasicAckOptions, BasicNackOptions};
+
+        Box::pin(async move {
+            match outcome {
+                Outcome::Ack => self.ack(BasicAckOptions::default()).await,
+                Outcome::Reject => self.nack(BasicNackOptions { requeue: false, multiple: false }).await,
+                Outcome::Requeue => self.nack(BasicNackOptions { requeue: true, multiple: false }).await,
+            }
+            .map(|_| ())
+            .map_err(broker_error)
+        })
+    }
+}
+
+#[cfg(feature = "amqp")]
+impl MessageSource for AmqpSource {
+    fn next<'a>(&'a mut self) -> BoxFuture<'a, Result<Option<IngestMessage>>> {
+        Box::pin(async move {
+            match self.consumer.next().await {
+                Some(delivery) => {
+                    let delivery = delivery.map_err(broker_error)?;
+                    Ok(Some(IngestMessage::new(delivery.data, delivery.acker)))
+                }
+                None => Ok(None),
+            }
+        })
+    }
+}
+
+/// Durable JetStream pull consumer on an existing stream
+#[cfg(feature = "nats")]
+pub struct NatsSource {
+    messages: async_nats::jetstream::consumer::pull::Stream,
+}
+
+#[cfg(feature = "nats")]
+impl NatsSource {
+    pub async fn connect(url: &str, stream: &str, consumer: &str) -> Result<Self> {
+        let client = async_nats::connect(url).await.map_err(broker_error)?;
+        let jetstream = async_nats::jetstream::new(client);
+        let stream = jetstream.get_stream(stream).await.map_err(broker_error)?;
+        let consumer = stream
+            .get_or_create_consumer(
+                consumer,
+                async_nats::jetstream::consumer::pull::Config {
+                    durable_name: Some(consumer.to_string()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .map_err(broker_error)?;
+        let messages = consumer.messages().await.map_err(broker_error)?;
+        Ok(Self { messages })
+    }
+}
+
+#[cfg(feature = "nats")]
+impl Settle for async_nats::jetstream::Message {
+    fn settle(self: Box<Self>, outcome: Outcome) -> BoxFuture<'static, Result<()>> {
+        use async_nats::jetstream::AckKind;
+
+        Box::pin(async move {
+            let kind = match outcome {
+                Outcome::Ack => AckKind::Ack,
+                Outcome::Reject => AckKind::Term,
+                Outcome::Requeue => AckKind::Nak(None),
+            };
+            self.ack_with(kind).await.map_err(broker_error)
+        })
+    }
+}
+
+#[cfg(feature = "nats")]
+impl MessageSource for NatsSource {
+    fn next<'a>(&'a mut self) -> BoxFuture<'a, Result<Option<IngestMessage>>> {
+        Box::pin(async move {
+            match self.messages.next().await {
+                Some(message) => {
+                    let message = message.map_err(broker_error)?;
+                    Ok(Some(IngestMessage::new(message.payload.to_vec(), message)))
+                }
+                None => Ok(None),
+            }
+        })
+    }
+}
+
+/// Long-polls an SQS queue
+#[cfg(feature = "sqs")]
+pub struct SqsSource {
+    client: aws_sdk_sqs::Client,
+    queue_url: String,
+    buffered: VecDeque<aws_sdk_sqs::types::Message>,
+}
+
+#[cfg(feature = "sqs")]
+impl SqsSource {
+    pub async fn connect(queue_url: &str) -> Result<Self> {
+        let config = aws_config::load_from_env().await;
+        Ok(Self {
+            client: aws_sdk_sqs::Client::new(&config),
+            queue_url: queue_url.to_string(),
+            buffered: VecDeque::new(),
+        })
+    }
+}
+
+#[cfg(feature = "sqs")]
+struct SqsReceipt {
+    client: aws_sdk_sqs::Client,
+    queue_url: String,
+    receipt_handle: String,
+}
+
+#[cfg(feature = "sqs")]
+impl Settle for SqsReceipt {
+    fn settle(self: Box<Self>, outcome: Outcome) -> BoxFuture<'static, Result<()>> {
+        Box::pin(async move {
+            match outcome {
+                // SQS has no reject, and an invalid message would only fail
+                // again on every redelivery, so it is deleted as well
+                Outcome::Ack | Outcome::Reject => {
+                    self.client
+                        .delete_message()
+                        .queue_url(self.queue_url)
+                        .receipt_handle(self.receipt_handle)
+                        .send()
+                        .await
+                        .map_err(broker_error)?;
+                }
+                Outcome::Requeue => {
+                    self.client
+                        .change_message_visibility()
+                        .queue_url(self.queue_url)
+                        .receipt_handle(self.receipt_handle)
+                        .visibility_timeout(0)
+                        .send()
+                        .await
+                        .map_err(broker_error)?;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(feature = "sqs")]
+impl MessageSource for SqsSource {
+    fn next<'a>(&'a mut self) -> BoxFuture<'a, Result<Option<IngestMessage>>> {
+        Box::pin(async move {
+            loop {
+                if let Some(message) = self.buffered.pop_front() {
+                    let Some(receipt_handle) = message.receipt_handle else {
+                        continue;
+                    };
+                    let receipt = SqsReceipt {
+                        client: self.client.clone(),
+                        queue_url: self.queue_url.clone(),
+                        receipt_handle,
+                    };
+                    let body = message.body.unwrap_or_default().into_bytes();
+                    return Ok(Some(IngestMessage::new(body, receipt)));
+                }
+
+                let output = self.client
+                    .receive_message()
+                    .queue_url(&self.queue_url)
+                    .max_number_of_messages(10)
+                    .wait_time_seconds(20)
+                    .send()
+                    .await
+                    .map_err(broker_error)?;
+                self.buffered.extend(output.messages.unwrap_or_default());
+            }
+        })
+    }
+}
+
+/// Redis stream read through a consumer group, named after the group
+#[cfg(feature = "redis")]
+pub struct RedisSource {
+    stream: crate::redis_store::RedisStream,
+    buffered: VecDeque<crate::redis_store::StreamEntry>,
+    /// Entries left unacknowledged by a previous run are read first
+    recovered: bool,
+}
+
+#[cfg(feature = "redis")]
+impl RedisSource {
+    pub async fn connect(url: &str, stream: &str, group: &str) -> Result<Self> {
+        Ok(Self {
+            stream: crate::redis_store::RedisStream::connect(url, stream, group, group).await?,
+            buffered: VecDeque::new(),
+            recovered: false,
+        })
+    }
+}
+
+#[cfg(feature = "redis")]
+struct RedisReceipt {
+    stream: crate::redis_store::RedisStream,
+    entry: crate::redis_store::StreamEntry,
+}
+
+#[cfg(feature = "redis")]
+impl Settle for RedisReceipt {
+    fn settle(self: Box<Self>, outcome: Outcome) -> BoxFuture<'static, Result<()>> {
+        Box::pin(async move {
+            match outcome {
+                // As with SQS an invalid entry is dropped rather than redelivered
+                Outcome::Ack | Outcome::Reject => self.stream.ack(&self.entry.id).await,
+                Outcome::Requeue => self.stream.requeue(&self.entry).await,
+            }
+        })
+    }
+}
+
+#[cfg(feature = "redis")]
+impl MessageSource for RedisSource {
+    fn next<'a>(&'a mut self) -> BoxFuture<'a, Result<Option<IngestMessage>>> {
+        Box::pin(async move {
+            loop {
+                if let Some(entry) = self.buffered.pop_front() {
+                    let body = entry.body.clone();
+                    let receipt = RedisReceipt { stream: self.stream.clone(), entry };
+                    return Ok(Some(IngestMessage::new(body, receipt)));
+                }
+
+                let pending = !self.recovered;
+                let entries = self.stream.read(10, Duration::from_secs(20), pending).await?;
+                if pending && entries.is_empty() {
+                    self.recovered = true;
+                }
+                self.buffered.extend(entries);
+            }
+        })
+    }
+}
+
+/// Connect to the configured queue
+pub async fn connect_source(config: &IngestConfig) -> Result<Box<dyn MessageSource>> {
+    match config {
+        #[cfg(feature = "amqp")]
+        IngestConfig::Amqp { url, queue, prefetch } => Ok(Box::new(AmqpSource::connect(url, queue, *prefetch).await?)),
+        #[cfg(feature = "nats")]
+        IngestConfig::Nats { url, stream, consumer } => Ok(Box::new(NatsSource::connect(url, stream, consumer).await?)),
+        #[cfg(feature = "sqs")]
+        IngestConfig::Sqs { queue_url } => Ok(Box::new(SqsSource::connect(queue_url).await?)),
+        #[cfg(feature = "redis")]
+        IngestConfig::Redis { url, stream, group } => Ok(Box::new(RedisSource::connect(url, stream, group).await?)),
+        #[allow(unreachable_patterns)]
+        other => Err(TaskQueueError::ConfigurationError(format!(
+            "Ingesting from {:?} needs a build with the matching feature",
+            other
+        ))),
+    }
+}
+
+/// Submits queued messages as tasks
+#[derive(Clone)]
+pub struct TaskIngestor {
+    server: Arc<TaskQueueServer>,
+}
+
+impl TaskIngestor {
+    pub fn new(server: Arc<TaskQueueServer>) -> Self {
+        Self { server }
+    }
+
+    /// Validate and store the task in a message body
+    pub async fn ingest(&self, body: &[u8]) -> Outcome {
+        let request: CreateTaskRequest = match serde_json::from_slice(body) {
+            Ok(request) => request,
+            Err(e) => {
+                warn!("Rejecting queued task with invalid body: {}", e);
+                return Outcome::Reject;
+            }
+        };
+
+        match self.server.submit_task(request.to_task()).await {
+            Ok(task_id) => {
+                info!("Ingested task {} from queue", task_id);
+                Outcome::Ack
+            }
+            Err(e @ (TaskQueueError::InvalidTaskDefinition { .. } | TaskQueueError::ValidationError { .. })) => {
+                warn!("Rejecting invalid queued task: {}", e);
+                Outcome::Reject
+            }
+            Err(e) => {
+                error!("Failed to store queued task, requeueing: {}", e);
+                Outcome::Requeue
+            }
+        }
+    }
+
+    /// Process messages until the source closes or fails
+    pub async fn run(&self, mut source: Box<dyn MessageSource>) -> Result<()> {
+        while let Some(message) = source.next().await? {
+            let outcome = self.ingest(&message.body).await;
+            message.settle(outcome).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Start ingesting in the background, reconnecting whenever the broker goes away
+pub fn spawn(server: Arc<TaskQueueServer>, config: IngestConfig) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let ingestor = TaskIngestor::new(server);
+        let retry_delay = Duration::from_secs(5);
+        loop {
+            let result = match connect_source(&config).await {
+                Ok(source) => ingestor.run(source).await,
+                Err(TaskQueueError::ConfigurationError(e)) => {
+                    warn!("Queue ingestion disabled: {}", e);
+                    return;
+                }
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(()) => warn!("Ingest queue closed, reconnecting in {:?}", retry_delay),
+                Err(e) => warn!("Queue ingestion failed, reconnecting in {:?}: {}", retry_delay, e),
+            }
+            tokio::time::sleep(retry_delay).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::*;
+    use crate::storage::StorageEngine;
+    use crate::vectorizer::VectorizerIntegration;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    struct Recorder(Arc<Mutex<Vec<Outcome>>>);
+
+    impl Settle for Recorder {
+        fn settle(self: Box<Self>, outcome: Outcome) -> BoxFuture<'static, Result<()>> {
+            self.0.lock().unwrap().push(outcome);
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    struct MemorySource {
+        bodies: VecDeque<Vec<u8>>,
+        outcomes: Arc<Mutex<Vec<Outcome>>>,
+    }
+
+    impl MessageSource for MemorySource {
+        fn next<'a>(&'a mut self) -> BoxFuture<'a, Result<Option<IngestMessage>>> {
+            let message = self.bodies.pop_front()
+                .map(|body| IngestMessage::new(body, Recorder(self.outcomes.clone())));
+            Box::pin(async move { Ok(message) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_messages_are_validated_and_settled() {
+        let storage = Arc::new(StorageEngine::temporary().unwrap());
+        let server = Arc::new(
+            TaskQueueServer::with_components(storage.clone(), Arc::new(VectorizerIntegration::new_dummy()))
+                .await
+                .unwrap(),
+        );
+        let project_id = server.create_project("ingest".to_string(), None).await.unwrap();
+
+        let request = |name: &str, project_id: Option<uuid::Uuid>| json!({
+            "name": name,
+            "command": "make",
+            "description": "Queued build",
+            "task_type": "Simple",
+            "priority": "Normal",
+            "project_id": project_id,
+        }).to_string().into_bytes();
+
+        let outcomes = Arc::new(Mutex::new(Vec::new()));
+        let source = MemorySource {
+            bodies: VecDeque::from([
+                request("build", Some(project_id)),
+                request("orphan", None),
+                b"not json".to_vec(),
+            ]),
+            outcomes: outcomes.clone(),
+        };
+        TaskIngestor::new(server.clone()).run(Box::new(source)).await.unwrap();
+
+        assert_eq!(*outcomes.lock().unwrap(), vec![Outcome::Ack, Outcome::Reject, Outcome::Reject]);
+        let stored = storage.list_tasks().await.unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].name, "build");
+    }
+}
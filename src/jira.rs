@@ -0,0 +1,472 @@
+//! Jira Import Module
+//!
+//! One-shot importer for teams moving their backlog over from Jira. Epics
+//! become projects and the remaining issues become tasks, with no command
+//! and the issue's URL in their metadata; running the import again updates
+//! what was imported before instead of duplicating it. Only the operator may
+//! run it, since it fetches from whatever URL it is given.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::*;
+use crate::error::{TaskQueueError, Result};
+use crate::server::TaskQueueServer;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tracing::info;
+use uuid::Uuid;
+
+/// Task metadata key linking a task to its Jira issue
+pub const ISSUE_METADATA_KEY: &str = "jira_issue";
+/// Project metadata key linking a project to its Jira epic
+pub const EPIC_METADATA_KEY: &str = "jira_epic";
+/// Project metadata key marking the catch-all project of a Jira project
+pub const PROJECT_METADATA_KEY: &str = "jira_project";
+
+const PAGE_SIZE: usize = 100;
+
+fn default_search_path() -> String {
+    "/rest/api/2/search".to_string()
+}
+
+/// How Jira fields map onto tasks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JiraFieldMapping {
+    /// Jira priority name to task priority; unmapped priorities become `Normal`
+    #[serde(default = "default_priority_mapping")]
+    pub priority: HashMap<String, TaskPriority>,
+    /// Field holding the epic key on classic projects, e.g. `customfield_10014`
+    #[serde(default)]
+    pub epic_link_field: Option<String>,
+    /// Field holding acceptance criteria, one per line
+    #[serde(default)]
+    pub acceptance_criteria_field: Option<String>,
+    /// Extra Jira fields copied into task metadata, by field ID → metadata key
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+fn default_priority_mapping() -> HashMap<String, TaskPriority> {
+    HashMap::from([
+        ("Highest".to_string(), TaskPriority::Critical),
+        ("High".to_string(), TaskPriority::High),
+        ("Medium".to_string(), TaskPriority::Normal),
+        ("Low".to_string(), TaskPriority::Low),
+        ("Lowest".to_string(), TaskPriority::Low),
+    ])
+}
+
+impl Default for JiraFieldMapping {
+    fn default() -> Self {
+        Self {
+            priority: default_priority_mapping(),
+            epic_link_field: None,
+            acceptance_criteria_field: None,
+            metadata: HashMap::new(),
+        }
+    }
+}
+
+/// Parameters of a Jira import
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JiraImportConfig {
+    /// Site URL, e.g. `https://acme.atlassian.net`
+    pub base_url: String,
+    /// Account email; with it the token is sent as a Cloud API token,
+    /// without it as a Data Center personal access token
+    #[serde(default)]
+    pub email: Option<String>,
+    pub token: String,
+    /// Jira project key, e.g. `OPS`
+    pub project_key: String,
+    /// Extra JQL, combined with the project key
+    #[serde(default)]
+    pub jql: Option<String>,
+    /// Project receiving issues that belong to no epic; defaults to one named
+    /// after the Jira project key
+    #[serde(default)]
+    pub target_project_id: Option<Uuid>,
+    /// Search endpoint, for sites that only serve `/rest/api/2/search/jql`
+    #[serde(default = "default_search_path")]
+    pub search_path: String,
+    #[serde(default)]
+    pub field_mapping: JiraFieldMapping,
+}
+
+impl JiraImportConfig {
+    fn validate(&self) -> Result<()> {
+        if self.base_url.trim().is_empty() || self.project_key.trim().is_empty() {
+            return Err(TaskQueueError::ValidationError {
+                reason: "Jira base_url and project_key are required".to_string(),
+            });
+        }
+        if self.token.trim().is_empty() {
+            return Err(TaskQueueError::ValidationError {
+                reason: "Jira token cannot be empty".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn full_jql(&self) -> String {
+        let mut jql = format!("project = \"{}\"", self.project_key);
+        if let Some(extra) = self.jql.as_deref().map(str::trim).filter(|jql| !jql.is_empty()) {
+            jql.push_str(&format!(" AND ({})", extra));
+        }
+        jql.push_str(" ORDER BY created ASC");
+        jql
+    }
+
+    fn browse_url(&self, key: &str) -> String {
+        format!("{}/browse/{}", self.base_url.trim_end_matches('/'), key)
+    }
+}
+
+/// Issue as returned by the Jira search API
+#[derive(Debug, Clone, Deserialize)]
+pub struct JiraIssue {
+    pub key: String,
+    pub fields: HashMap<String, Value>,
+}
+
+impl JiraIssue {
+    fn text(&self, field: &str) -> Option<&str> {
+        self.fields.get(field)?.as_str()
+    }
+
+    fn named(&self, field: &str) -> Option<&str> {
+        self.fields.get(field)?.get("name")?.as_str()
+    }
+
+    pub fn summary(&self) -> &str {
+        self.text("summary").unwrap_or(&self.key)
+    }
+
+    pub fn is_epic(&self) -> bool {
+        self.named("issuetype").is_some_and(|name| name.eq_ignore_ascii_case("epic"))
+    }
+
+    pub fn labels(&self) -> Vec<String> {
+        self.fields.get("labels")
+            .and_then(Value::as_array)
+            .map(|labels| labels.iter().filter_map(|l| l.as_str().map(str::to_string)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Key of the epic this issue belongs to
+    pub fn epic_key(&self, mapping: &JiraFieldMapping) -> Option<String> {
+        if let Some(field) = &mapping.epic_link_field
+            && let Some(key) = self.text(field)
+        {
+            return Some(key.to_string());
+        }
+
+        let parent = self.fields.get("parent")?;
+        let is_epic = parent.pointer("/fields/issuetype/name")
+            .and_then(Value::as_str)
+            .is_some_and(|name| name.eq_ignore_ascii_case("epic"));
+        if is_epic {
+            parent.get("key")?.as_str().map(str::to_string)
+        } else {
+            None
+        }
+    }
+
+    fn description(&self, config: &JiraImportConfig) -> String {
+        // API v3 returns rich-text documents; only plain text is copied over
+        match self.text("description").map(str::trim) {
+            Some(text) if !text.is_empty() => text.to_string(),
+            _ => format!("Imported from {}", config.browse_url(&self.key)),
+        }
+    }
+}
+
+/// Outcome of an import run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JiraImportReport {
+    pub projects_created: usize,
+    pub tasks_imported: usize,
+    pub tasks_updated: usize,
+}
+
+/// Minimal Jira REST client
+pub struct JiraClient {
+    client: reqwest::Client,
+    config: JiraImportConfig,
+}
+
+impl JiraClient {
+    pub fn new(config: JiraImportConfig) -> Result<Self> {
+        config.validate()?;
+        Ok(Self {
+            client: reqwest::Client::new(),
+            config,
+        })
+    }
+
+    fn authorized(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.config.email {
+            Some(email) => request.basic_auth(email, Some(&self.config.token)),
+            None => request.bearer_auth(&self.config.token),
+        }
+    }
+
+    /// Fetch every issue matched by the import's JQL
+    ///
+    /// Follows `nextPageToken` when the endpoint returns one and falls back to
+    /// `startAt` offsets otherwise.
+    pub async fn search(&self) -> Result<Vec<JiraIssue>> {
+        let url = format!("{}{}", self.config.base_url.trim_end_matches('/'), self.config.search_path);
+        let jql = self.config.full_jql();
+        let mut issues: Vec<JiraIssue> = Vec::new();
+        let mut next_page_token: Option<String> = None;
+
+        loop {
+            let mut query = vec![
+                ("jql", jql.clone()),
+                ("maxResults", PAGE_SIZE.to_string()),
+                ("fields", "*all".to_string()),
+            ];
+            match &next_page_token {
+                Some(token) => query.push(("nextPageToken", token.clone())),
+                None => query.push(("startAt", issues.len().to_string())),
+            }
+
+            let page: Value = self.authorized(self.client.get(&url).query(&query))
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+
+            let batch: Vec<JiraIssue> = serde_json::from_value(page.get("issues").cloned().unwrap_or(json!([])))?;
+            let batch_len = batch.len();
+            issues.extend(batch);
+
+            next_page_token = page.get("nextPageToken").and_then(Value::as_str).map(str::to_string);
+            let total = page.get("total").and_then(Value::as_u64).map(|total| total as usize);
+            let more = match (&next_page_token, total) {
+                (Some(_), _) => true,
+                (None, Some(total)) => issues.len() < total,
+                (None, None) => false,
+            };
+            if !more || batch_len == 0 {
+                break;
+            }
+        }
+
+        Ok(issues)
+    }
+}
+
+/// Copy the mapped fields of an issue onto a task
+fn apply_issue(task: &mut Task, issue: &JiraIssue, config: &JiraImportConfig, project_id: Uuid) {
+    let mapping = &config.field_mapping;
+
+    task.name = issue.summary().to_string();
+    task.description = issue.description(config);
+    task.project_id = Some(project_id);
+    task.priority = issue.named("priority")
+        .and_then(|name| mapping.priority.get(name))
+        .cloned()
+        .unwrap_or(TaskPriority::Normal);
+
+    if let Some(field) = &mapping.acceptance_criteria_field
+        && let Some(text) = issue.text(field)
+    {
+        task.acceptance_criteria = text.lines()
+            .map(|line| line.trim().trim_start_matches(['-', '*']).trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect();
+    }
+
+    let tags = issue.labels().into_iter().map(Value::String).collect();
+    task.metadata.insert("tags".to_string(), Value::Array(tags));
+    for (field, key) in &mapping.metadata {
+        if let Some(value) = issue.fields.get(field).filter(|value| !value.is_null()) {
+            task.metadata.insert(key.clone(), value.clone());
+        }
+    }
+    task.metadata.insert(ISSUE_METADATA_KEY.to_string(), json!({
+        "key": issue.key,
+        "url": config.browse_url(&issue.key),
+    }));
+}
+
+/// Build a new task for an issue
+pub fn issue_to_task(issue: &JiraIssue, config: &JiraImportConfig, project_id: Uuid) -> Task {
+    let mut task = Task::new(issue.summary()).build();
+    apply_issue(&mut task, issue, config, project_id);
+    task
+}
+
+fn metadata_key<'a>(metadata: &'a HashMap<String, Value>, name: &str) -> Option<&'a str> {
+    metadata.get(name)?.get("key")?.as_str()
+}
+
+/// State of one import run
+struct Importer<'a> {
+    server: &'a TaskQueueServer,
+    config: &'a JiraImportConfig,
+    /// Projects created by earlier runs, by (metadata kind, Jira key)
+    projects: HashMap<(String, String), Uuid>,
+    /// Tasks created by earlier runs, by issue key
+    tasks: HashMap<String, Uuid>,
+    report: JiraImportReport,
+}
+
+impl<'a> Importer<'a> {
+    async fn new(server: &'a TaskQueueServer, config: &'a JiraImportConfig) -> Result<Self> {
+        let mut projects = HashMap::new();
+        for project in server.list_projects().await? {
+            for kind in [EPIC_METADATA_KEY, PROJECT_METADATA_KEY] {
+                if let Some(key) = metadata_key(&project.metadata, kind) {
+                    projects.insert((kind.to_string(), key.to_string()), project.id);
+                }
+            }
+        }
+
+        let mut tasks = HashMap::new();
+        for task in server.list_tasks(None, None).await? {
+            if let Some(key) = metadata_key(&task.metadata, ISSUE_METADATA_KEY) {
+                tasks.insert(key.to_string(), task.id);
+            }
+        }
+
+        Ok(Self {
+            server,
+            config,
+            projects,
+            tasks,
+            report: JiraImportReport::default(),
+        })
+    }
+
+    /// Find or create the project tagged with the given Jira metadata
+    async fn ensure_project(&mut self, kind: &str, key: &str, name: &str, description: Option<String>) -> Result<Uuid> {
+        if let Some(id) = self.projects.get(&(kind.to_string(), key.to_string())) {
+            return Ok(*id);
+        }
+
+        let url = self.config.browse_url(key);
+        let id = self.server.create_project(name.to_string(), description).await?;
+        self.server.modify_project(&id, |project| {
+            project.metadata.insert(kind.to_string(), json!({ "key": key, "url": url }));
+        }).await?;
+        self.projects.insert((kind.to_string(), key.to_string()), id);
+        self.report.projects_created += 1;
+        Ok(id)
+    }
+
+    async fn import_issue(&mut self, issue: &JiraIssue) -> Result<()> {
+        let epic_project = issue.epic_key(&self.config.field_mapping)
+            .and_then(|key| self.projects.get(&(EPIC_METADATA_KEY.to_string(), key)).copied());
+        let project_id = match (epic_project, self.config.target_project_id) {
+            (Some(id), _) | (None, Some(id)) => id,
+            (None, None) => {
+                let key = self.config.project_key.clone();
+                self.ensure_project(PROJECT_METADATA_KEY, &key, &key, None).await?
+            }
+        };
+
+        let config = self.config;
+        match self.tasks.get(&issue.key) {
+            Some(task_id) => {
+                self.server.modify_task(*task_id, |task| apply_issue(task, issue, config, project_id)).await?;
+                self.report.tasks_updated += 1;
+            }
+            None => {
+                let task_id = self.server.submit_task(issue_to_task(issue, config, project_id)).await?;
+                self.tasks.insert(issue.key.clone(), task_id);
+                self.report.tasks_imported += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Import the issues selected by `config` into the server
+pub async fn import(server: &TaskQueueServer, config: JiraImportConfig) -> Result<JiraImportReport> {
+    if let Some(id) = &config.target_project_id
+        && server.get_project(id).await?.is_none()
+    {
+        return Err(TaskQueueError::ProjectNotFound { project_id: id.to_string() });
+    }
+
+    let issues = JiraClient::new(config.clone())?.search().await?;
+    let mut importer = Importer::new(server, &config).await?;
+
+    // Epics first, so their issues can be placed in the matching project
+    for epic in issues.iter().filter(|issue| issue.is_epic()) {
+        let description = epic.text("description").map(str::to_string);
+        importer.ensure_project(EPIC_METADATA_KEY, &epic.key, epic.summary(), description).await?;
+    }
+    for issue in issues.iter().filter(|issue| !issue.is_epic()) {
+        importer.import_issue(issue).await?;
+    }
+
+    let report = importer.report;
+    info!(
+        "Jira import of {}: {} projects created, {} tasks imported, {} updated",
+        config.project_key, report.projects_created, report.tasks_imported, report.tasks_updated
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> JiraImportConfig {
+        serde_json::from_value(json!({
+            "base_url": "https://acme.atlassian.net",
+            "token": "token",
+            "project_key": "OPS",
+            "jql": "status != Done",
+            "field_mapping": {
+                "epic_link_field": "customfield_10014",
+                "acceptance_criteria_field": "customfield_10100",
+                "metadata": { "customfield_10016": "story_points" }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_issue_fields_are_mapped() {
+        let config = config();
+        let issue: JiraIssue = serde_json::from_value(json!({
+            "key": "OPS-7",
+            "fields": {
+                "summary": "Rotate certificates",
+                "description": "Before they expire",
+                "issuetype": { "name": "Story" },
+                "priority": { "name": "Highest" },
+                "labels": ["security"],
+                "customfield_10014": "OPS-1",
+                "customfield_10100": "- New certs deployed\n- Old certs revoked",
+                "customfield_10016": 3
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(config.full_jql(), "project = \"OPS\" AND (status != Done) ORDER BY created ASC");
+        assert!(!issue.is_epic());
+        assert_eq!(issue.epic_key(&config.field_mapping).as_deref(), Some("OPS-1"));
+
+        let task = issue_to_task(&issue, &config, Uuid::new_v4());
+        assert_eq!(task.name, "Rotate certificates");
+        assert_eq!(task.priority, TaskPriority::Critical);
+        assert_eq!(task.acceptance_criteria, vec!["New certs deployed", "Old certs revoked"]);
+        assert_eq!(task.metadata["tags"], json!(["security"]));
+        assert_eq!(task.metadata["story_points"], json!(3));
+        assert_eq!(task.metadata[ISSUE_METADATA_KEY]["key"], json!("OPS-7"));
+        assert_eq!(task.metadata[ISSUE_METADATA_KEY]["url"], json!(config.browse_url("OPS-7")));
+        assert!(task.command.is_empty());
+    }
+}
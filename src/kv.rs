@@ -0,0 +1,582 @@
+//! Key-Value Backend Module
+//!
+//! The ordered key-value trees the storage engine keeps its collections in.
+//! By default they are sled trees in an embedded database file; with the
+//! `redis` feature they can live in Redis instead (see
+//! [`crate::redis_store`]), for ephemeral high-throughput deployments where
+//! file-based storage is too slow, and with the `sqlite` feature in a SQLite
+//! database (see [`crate::sqlite_store`]) for single-node deployments that
+//! want to query it. All of them iterate keys in byte order. Trees kept only
+//! in memory back the temporary storage used by tests.
+//!
+//! Writes to several trees can be gathered in a [`Batch`] and applied with
+//! [`Backend::apply`], which lands all of them or none: a sled transaction, a
+//! Redis `MULTI`/`EXEC`, a SQLite transaction, or every tree held locked. A
+//! batch can also expect keys to still hold the values they were read with,
+//! failing with [`TaskQueueError::WriteConflict`] instead of applying when
+//! another writer, possibly another process, changed one of them.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::error::{Result, TaskQueueError};
+use sled::IVec;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::{Bound, RangeBounds};
+use sled::Transactional;
+use std::sync::{Arc, Mutex, RwLock};
+
+#[cfg(feature = "redis")]
+use crate::redis_store::{RedisStore, RedisTree};
+#[cfg(feature = "sqlite")]
+use crate::sqlite_store::{SqliteStore, SqliteTree};
+
+/// A key and its value
+pub type Entry = Result<(IVec, IVec)>;
+
+/// Entries of a tree in key order
+pub type Entries = Box<dyn DoubleEndedIterator<Item = Entry> + Send>;
+
+/// A key of a tree set to a value, or removed when there is none
+pub type TreeWrite<'a, T> = (&'a T, &'a [u8], Option<&'a [u8]>);
+
+/// Where the trees are kept
+#[derive(Clone)]
+pub enum Backend {
+    Sled(Arc<sled::Db>),
+    Memory(MemoryStore),
+    #[cfg(feature = "redis")]
+    Redis(RedisStore),
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqliteStore),
+}
+
+impl Backend {
+    pub fn open_tree(&self, name: &str) -> Result<Tree> {
+        match self {
+            Self::Sled(db) => Ok(Tree::Sled(db.open_tree(name)?)),
+            Self::Memory(store) => Ok(Tree::Memory(store.tree(name))),
+            #[cfg(feature = "redis")]
+            Self::Redis(store) => Ok(Tree::Redis(store.tree(name))),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(store) => Ok(Tree::Sqlite(store.tree(name))),
+        }
+    }
+
+    /// Tree for records that belong to no collection
+    pub fn default_tree(&self) -> Tree {
+        match self {
+            Self::Sled(db) => Tree::Sled(sled::Tree::clone(db)),
+            Self::Memory(store) => Tree::Memory(store.tree("default")),
+            #[cfg(feature = "redis")]
+            Self::Redis(store) => Tree::Redis(store.tree("default")),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(store) => Tree::Sqlite(store.tree("default")),
+        }
+    }
+
+    /// Bytes used on disk, or in memory for Redis and in-memory trees
+    pub fn size_on_disk(&self) -> Result<u64> {
+        match self {
+            Self::Sled(db) => Ok(db.size_on_disk()?),
+            Self::Memory(store) => Ok(store.size()),
+            #[cfg(feature = "redis")]
+            Self::Redis(store) => store.used_memory(),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(store) => store.size(),
+        }
+    }
+
+    /// Whether several processes can open the trees at once; sled locks its
+    /// database to one process and memory trees live in this one
+    pub fn is_shared(&self) -> bool {
+        match self {
+            Self::Sled(_) | Self::Memory(_) => false,
+            #[cfg(feature = "redis")]
+            Self::Redis(_) => true,
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(_) => true,
+        }
+    }
+
+    /// Apply every write of the batch, or none of them
+    pub fn apply(&self, batch: Batch) -> Result<()> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let foreign = || TaskQueueError::InternalError("Batch writes to a tree of another backend".to_string());
+        match self {
+            Self::Sled(_) => {
+                let mut trees: Vec<sled::Tree> = Vec::new();
+                let mut position = |tree: Tree| {
+                    let Tree::Sled(tree) = tree else { return Err(foreign()) };
+                    Ok(match trees.iter().position(|known| known.name() == tree.name()) {
+                        Some(index) => index,
+                        None => {
+                            trees.push(tree);
+                            trees.len() - 1
+                        }
+                    })
+                };
+                let mut writes = Vec::with_capacity(batch.len());
+                for (tree, key, value) in batch.writes {
+                    writes.push((position(tree)?, key, value));
+                }
+                let mut expected = Vec::with_capacity(batch.expected.len());
+                for (tree, key, value) in batch.expected {
+                    expected.push((position(tree)?, key, value));
+                }
+                trees[..].transaction(|trees| {
+                    for (index, key, value) in &expected {
+                        if trees[*index].get(key.as_slice())? != *value {
+                            return Err(sled::transaction::ConflictableTransactionError::Abort(key.clone()));
+                        }
+                    }
+                    for (index, key, value) in &writes {
+                        match value {
+                            Some(value) => trees[*index].insert(key.as_slice(), value.clone())?,
+                            None => trees[*index].remove(key.as_slice())?,
+                        };
+                    }
+                    Ok::<_, sled::transaction::ConflictableTransactionError<Vec<u8>>>(())
+                }).map_err(|e| match e {
+                    sled::transaction::TransactionError::Storage(e) => e.into(),
+                    sled::transaction::TransactionError::Abort(key) => conflict(&key),
+                })
+            }
+            Self::Memory(_) => {
+                let mut trees: Vec<&MemoryTree> = Vec::new();
+                for (tree, _, _) in batch.writes.iter().chain(&batch.expected) {
+                    let Tree::Memory(tree) = tree else { return Err(foreign()) };
+                    if !trees.iter().any(|known| Arc::ptr_eq(&known.records, &tree.records)) {
+                        trees.push(tree);
+                    }
+                }
+                // Locked in a fixed order so two batches can't wait on each other
+                trees.sort_by_key(|tree| Arc::as_ptr(&tree.records));
+                let mut guards: Vec<_> = trees.iter().map(|tree| tree.write()).collect();
+                for (tree, key, value) in &batch.expected {
+                    let Tree::Memory(tree) = tree else { continue };
+                    let Some(index) = trees.iter().position(|known| Arc::ptr_eq(&known.records, &tree.records)) else { continue };
+                    if guards[index].get(key) != value.as_ref() {
+                        return Err(conflict(key));
+                    }
+                }
+                for (tree, key, value) in &batch.writes {
+                    let Tree::Memory(tree) = tree else { continue };
+                    let Some(index) = trees.iter().position(|known| Arc::ptr_eq(&known.records, &tree.records)) else { continue };
+                    match value {
+                        Some(value) => guards[index].insert(key.clone(), value.clone()),
+                        None => guards[index].remove(key),
+                    };
+                }
+                Ok(())
+            }
+            #[cfg(feature = "redis")]
+            Self::Redis(store) => {
+                let mut writes = Vec::with_capacity(batch.len());
+                for (tree, key, value) in &batch.writes {
+                    let Tree::Redis(tree) = tree else { return Err(foreign()) };
+                    writes.push((tree, key.as_slice(), value.as_deref()));
+                }
+                let mut expected = Vec::with_capacity(batch.expected.len());
+                for (tree, key, value) in &batch.expected {
+                    let Tree::Redis(tree) = tree else { return Err(foreign()) };
+                    expected.push((tree, key.as_slice(), value.as_deref()));
+                }
+                store.apply(&writes, &expected)
+            }
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(store) => {
+                let mut writes = Vec::with_capacity(batch.len());
+                for (tree, key, value) in &batch.writes {
+                    let Tree::Sqlite(tree) = tree else { return Err(foreign()) };
+                    writes.push((tree, key.as_slice(), value.as_deref()));
+                }
+                let mut expected = Vec::with_capacity(batch.expected.len());
+                for (tree, key, value) in &batch.expected {
+                    let Tree::Sqlite(tree) = tree else { return Err(foreign()) };
+                    expected.push((tree, key.as_slice(), value.as_deref()));
+                }
+                store.apply(&writes, &expected)
+            }
+        }
+    }
+
+    /// Give space held by removed records back; sled and Redis reclaim it
+    /// on their own, so for them this only flushes
+    pub async fn compact(&self) -> Result<()> {
+        match self {
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(store) => store.vacuum(),
+            _ => self.flush_async().await,
+        }
+    }
+
+    pub async fn flush_async(&self) -> Result<()> {
+        match self {
+            Self::Sled(db) => {
+                db.flush_async().await?;
+            }
+            Self::Memory(_) => {}
+            #[cfg(feature = "redis")]
+            Self::Redis(_) => {}
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(_) => {}
+        }
+        Ok(())
+    }
+}
+
+/// One ordered collection of records
+#[derive(Clone)]
+pub enum Tree {
+    Sled(sled::Tree),
+    Memory(MemoryTree),
+    #[cfg(feature = "redis")]
+    Redis(RedisTree),
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqliteTree),
+}
+
+impl Tree {
+    /// Set a key, returning its previous value
+    pub fn insert<K: AsRef<[u8]>, V: Into<IVec>>(&self, key: K, value: V) -> Result<Option<IVec>> {
+        match self {
+            Self::Sled(tree) => Ok(tree.insert(key, value)?),
+            Self::Memory(tree) => Ok(tree.write().insert(key.as_ref().to_vec(), value.into())),
+            #[cfg(feature = "redis")]
+            Self::Redis(tree) => Ok(tree.insert(key.as_ref(), &value.into())?.map(IVec::from)),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(tree) => Ok(tree.insert(key.as_ref(), &value.into())?.map(IVec::from)),
+        }
+    }
+
+    pub fn get<K: AsRef<[u8]>>(&self, key: K) -> Result<Option<IVec>> {
+        match self {
+            Self::Sled(tree) => Ok(tree.get(key)?),
+            Self::Memory(tree) => Ok(tree.read().get(key.as_ref()).cloned()),
+            #[cfg(feature = "redis")]
+            Self::Redis(tree) => Ok(tree.get(key.as_ref())?.map(IVec::from)),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(tree) => Ok(tree.get(key.as_ref())?.map(IVec::from)),
+        }
+    }
+
+    /// Remove a key, returning its value
+    pub fn remove<K: AsRef<[u8]>>(&self, key: K) -> Result<Option<IVec>> {
+        match self {
+            Self::Sled(tree) => Ok(tree.remove(key)?),
+            Self::Memory(tree) => Ok(tree.write().remove(key.as_ref())),
+            #[cfg(feature = "redis")]
+            Self::Redis(tree) => Ok(tree.remove(key.as_ref())?.map(IVec::from)),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(tree) => Ok(tree.remove(key.as_ref())?.map(IVec::from)),
+        }
+    }
+
+    /// Number of records; 0 if the backend can't be read
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Sled(tree) => tree.len(),
+            Self::Memory(tree) => tree.read().len(),
+            #[cfg(feature = "redis")]
+            Self::Redis(tree) => tree.len().unwrap_or(0),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(tree) => tree.len().unwrap_or(0),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The record with the highest key
+    pub fn last(&self) -> Result<Option<(IVec, IVec)>> {
+        match self {
+            Self::Sled(tree) => Ok(tree.last()?),
+            Self::Memory(tree) => Ok(tree.read().iter().next_back().map(|(key, value)| (key.as_slice().into(), value.clone()))),
+            #[cfg(feature = "redis")]
+            Self::Redis(tree) => Ok(tree.last()?.map(|(key, value)| (key.into(), value.into()))),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(tree) => Ok(tree.last()?.map(|(key, value)| (key.into(), value.into()))),
+        }
+    }
+
+    pub fn iter(&self) -> Entries {
+        self.range::<&[u8], _>(..)
+    }
+
+    /// Records with keys in the range
+    pub fn range<K: AsRef<[u8]>, R: RangeBounds<K>>(&self, range: R) -> Entries {
+        match self {
+            Self::Sled(tree) => Box::new(tree.range(range).map(|entry| Ok(entry?))),
+            Self::Memory(tree) => {
+                let start = range.start_bound().map(AsRef::as_ref);
+                let end = range.end_bound().map(AsRef::as_ref);
+                tree.entries(start, end, None)
+            }
+            #[cfg(feature = "redis")]
+            Self::Redis(tree) => {
+                let start = range.start_bound().map(AsRef::as_ref);
+                let end = range.end_bound().map(AsRef::as_ref);
+                fetched_entries(tree.entries(start, end, None))
+            }
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(tree) => {
+                let start = range.start_bound().map(AsRef::as_ref);
+                let end = range.end_bound().map(AsRef::as_ref);
+                fetched_entries(tree.entries(start, end, None))
+            }
+        }
+    }
+
+    /// Up to `limit` records with keys after `after`, or from the first key;
+    /// only the page is read, whatever the backend
+    pub fn page(&self, after: Option<&[u8]>, limit: usize) -> Entries {
+        let start = after.map_or(Bound::Unbounded, Bound::Excluded);
+        match self {
+            Self::Sled(tree) => {
+                let entries: Vec<Entry> = tree.range::<&[u8], _>((start, Bound::Unbounded))
+                    .take(limit)
+                    .map(|entry| Ok(entry?))
+                    .collect();
+                Box::new(entries.into_iter())
+            }
+            Self::Memory(tree) => tree.entries(start, Bound::Unbounded, Some(limit)),
+            #[cfg(feature = "redis")]
+            Self::Redis(tree) => fetched_entries(tree.entries(start, Bound::Unbounded, Some(limit))),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(tree) => fetched_entries(tree.entries(start, Bound::Unbounded, Some(limit))),
+        }
+    }
+
+    /// Records whose keys start with the prefix
+    pub fn scan_prefix<P: AsRef<[u8]>>(&self, prefix: P) -> Entries {
+        match self {
+            Self::Sled(tree) => Box::new(tree.scan_prefix(prefix).map(|entry| Ok(entry?))),
+            Self::Memory(tree) => {
+                let prefix = prefix.as_ref();
+                let end = prefix_end(prefix);
+                tree.entries(Bound::Included(prefix), end.as_deref().map_or(Bound::Unbounded, Bound::Excluded), None)
+            }
+            #[cfg(feature = "redis")]
+            Self::Redis(tree) => fetched_entries(tree.scan_prefix(prefix.as_ref())),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(tree) => fetched_entries(tree.scan_prefix(prefix.as_ref())),
+        }
+    }
+
+    pub async fn flush_async(&self) -> Result<usize> {
+        match self {
+            Self::Sled(tree) => Ok(tree.flush_async().await?),
+            Self::Memory(_) => Ok(0),
+            // Durability is left to the Redis server's own persistence settings
+            #[cfg(feature = "redis")]
+            Self::Redis(_) => Ok(0),
+            // SQLite commits each write durably as it happens
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(_) => Ok(0),
+        }
+    }
+}
+
+/// Writes to one or more trees of a backend, applied together
+#[derive(Clone, Default)]
+pub struct Batch {
+    /// Tree, key and the new value, or `None` to remove the key
+    writes: Vec<(Tree, Vec<u8>, Option<IVec>)>,
+    /// Tree, key and the value it must still hold, or `None` for no value
+    expected: Vec<(Tree, Vec<u8>, Option<IVec>)>,
+}
+
+impl Batch {
+    pub fn insert<K: AsRef<[u8]>, V: Into<IVec>>(&mut self, tree: &Tree, key: K, value: V) {
+        self.writes.push((tree.clone(), key.as_ref().to_vec(), Some(value.into())));
+    }
+
+    pub fn remove<K: AsRef<[u8]>>(&mut self, tree: &Tree, key: K) {
+        self.writes.push((tree.clone(), key.as_ref().to_vec(), None));
+    }
+
+    /// Apply only if the key still holds `value`, or no value when `None`
+    pub fn expect<K: AsRef<[u8]>>(&mut self, tree: &Tree, key: K, value: Option<IVec>) {
+        self.expected.push((tree.clone(), key.as_ref().to_vec(), value));
+    }
+
+    pub fn len(&self) -> usize {
+        self.writes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.writes.is_empty()
+    }
+}
+
+/// Trees that live only in memory, by name
+#[derive(Clone, Default)]
+pub struct MemoryStore {
+    trees: Arc<Mutex<HashMap<String, MemoryTree>>>,
+}
+
+impl MemoryStore {
+    pub fn tree(&self, name: &str) -> MemoryTree {
+        let mut trees = self.trees.lock().unwrap_or_else(|e| e.into_inner());
+        trees.entry(name.to_string()).or_default().clone()
+    }
+
+    /// Bytes held by the keys and values of every tree
+    pub fn size(&self) -> u64 {
+        let trees = self.trees.lock().unwrap_or_else(|e| e.into_inner());
+        trees.values()
+            .flat_map(|tree| tree.read().iter().map(|(key, value)| (key.len() + value.len()) as u64).collect::<Vec<_>>())
+            .sum()
+    }
+}
+
+/// One tree kept in memory
+#[derive(Clone, Default)]
+pub struct MemoryTree {
+    records: Arc<RwLock<BTreeMap<Vec<u8>, IVec>>>,
+}
+
+impl MemoryTree {
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, BTreeMap<Vec<u8>, IVec>> {
+        self.records.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, BTreeMap<Vec<u8>, IVec>> {
+        self.records.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Copies of the records between the bounds, so the tree isn't held locked
+    fn entries(&self, start: Bound<&[u8]>, end: Bound<&[u8]>, limit: Option<usize>) -> Entries {
+        // BTreeMap panics on ranges that end before they start
+        let empty = match (start, end) {
+            (Bound::Included(start), Bound::Included(end)) => start > end,
+            (Bound::Included(start) | Bound::Excluded(start), Bound::Included(end) | Bound::Excluded(end)) => start >= end,
+            _ => false,
+        };
+        if empty {
+            return Box::new(std::iter::empty());
+        }
+        let entries: Vec<Entry> = self.read()
+            .range::<[u8], _>((start, end))
+            .take(limit.unwrap_or(usize::MAX))
+            .map(|(key, value)| Ok((key.as_slice().into(), value.clone())))
+            .collect();
+        Box::new(entries.into_iter())
+    }
+}
+
+/// The error for a batch whose expected value of `key` no longer holds
+pub(crate) fn conflict(key: &[u8]) -> TaskQueueError {
+    TaskQueueError::WriteConflict { key: String::from_utf8_lossy(key).into_owned() }
+}
+
+/// The first key after every key starting with `prefix`, if there is one
+pub(crate) fn prefix_end(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last < u8::MAX {
+            end.push(last + 1);
+            return Some(end);
+        }
+    }
+    None
+}
+
+/// Entries read in one go, or the error reading them
+#[cfg(any(feature = "redis", feature = "sqlite"))]
+fn fetched_entries(entries: Result<Vec<(Vec<u8>, Vec<u8>)>>) -> Entries {
+    match entries {
+        Ok(entries) => Box::new(entries.into_iter().map(|(key, value)| Ok((key.into(), value.into())))),
+        Err(e) => Box::new(std::iter::once(Err(e))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_trees_iterate_like_sled() {
+        let sled = Backend::Sled(Arc::new(sled::Config::new().temporary(true).open().unwrap()));
+        let memory = Backend::Memory(MemoryStore::default());
+
+        for backend in [&sled, &memory] {
+            let tree = backend.open_tree("records").unwrap();
+            for key in [&b"b2"[..], b"a", b"b1", &[b'b', 0xff], b"c"] {
+                tree.insert(key, key).unwrap();
+            }
+            assert_eq!(tree.insert("a", "A").unwrap().as_deref(), Some(&b"a"[..]));
+            assert_eq!(tree.remove("c").unwrap().as_deref(), Some(&b"c"[..]));
+
+            let keys = |entries: Entries| entries.map(|entry| entry.unwrap().0.to_vec()).collect::<Vec<_>>();
+            assert_eq!(keys(tree.iter()), [&b"a"[..], b"b1", b"b2", &[b'b', 0xff]]);
+            assert_eq!(keys(tree.scan_prefix("b")), [&b"b1"[..], b"b2", &[b'b', 0xff]]);
+            assert_eq!(keys(tree.range(&b"b1"[..]..&b"b2"[..])), [b"b1"]);
+            assert!(keys(tree.range(&b"b2"[..]..&b"b1"[..])).is_empty());
+            assert_eq!(tree.last().unwrap().unwrap().0.to_vec(), [b'b', 0xff]);
+            assert_eq!(tree.len(), 4);
+            assert_eq!(backend.open_tree("records").unwrap().get("a").unwrap().as_deref(), Some(&b"A"[..]));
+        }
+    }
+
+    #[test]
+    fn test_batches_apply_to_every_tree_at_once() {
+        let sled = Backend::Sled(Arc::new(sled::Config::new().temporary(true).open().unwrap()));
+        let memory = Backend::Memory(MemoryStore::default());
+
+        for backend in [&sled, &memory] {
+            let (tasks, index) = (backend.open_tree("tasks").unwrap(), backend.open_tree("index").unwrap());
+            tasks.insert("old", "1").unwrap();
+
+            let mut batch = Batch::default();
+            batch.insert(&tasks, "new", "2");
+            batch.remove(&tasks, "old");
+            batch.insert(&index, "new", "");
+            batch.insert(&backend.default_tree(), "marker", "3");
+            assert_eq!(batch.len(), 4);
+            backend.apply(batch).unwrap();
+
+            assert_eq!(tasks.get("old").unwrap(), None);
+            assert_eq!(tasks.get("new").unwrap().as_deref(), Some(&b"2"[..]));
+            assert_eq!(index.len(), 1);
+            assert_eq!(backend.default_tree().get("marker").unwrap().as_deref(), Some(&b"3"[..]));
+        }
+
+        let mut foreign = Batch::default();
+        foreign.insert(&memory.open_tree("tasks").unwrap(), "key", "value");
+        assert!(sled.apply(foreign).is_err());
+        assert_eq!(sled.open_tree("tasks").unwrap().get("key").unwrap(), None);
+    }
+
+    #[test]
+    fn test_batches_apply_only_while_expected_values_hold() {
+        let sled = Backend::Sled(Arc::new(sled::Config::new().temporary(true).open().unwrap()));
+        let memory = Backend::Memory(MemoryStore::default());
+
+        for backend in [&sled, &memory] {
+            let (tasks, index) = (backend.open_tree("tasks").unwrap(), backend.open_tree("index").unwrap());
+            tasks.insert("task", "1").unwrap();
+
+            let mut batch = Batch::default();
+            batch.insert(&tasks, "task", "2");
+            batch.insert(&index, "task", "");
+            batch.expect(&tasks, "task", Some(IVec::from("1")));
+            batch.expect(&tasks, "other", None);
+            let retry = batch.clone();
+            backend.apply(batch).unwrap();
+            assert_eq!(tasks.get("task").unwrap().as_deref(), Some(&b"2"[..]));
+
+            // The same batch again expects a value that was since replaced
+            index.remove("task").unwrap();
+            assert!(matches!(backend.apply(retry), Err(TaskQueueError::WriteConflict { key }) if key == "task"));
+            assert_eq!(index.get("task").unwrap(), None);
+        }
+    }
+}
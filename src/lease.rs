@@ -0,0 +1,111 @@
+//! Task Leasing Module
+//!
+//! Lets external workers pull work from the queue. A claim moves the next
+//! runnable task to `Running` under a lease held by the worker; the worker
+//! renews it with heartbeats and reports the result before it expires. Tasks
+//! whose lease runs out go back to `Pending` for someone else to claim.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::*;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Task metadata key holding the task's current [`Lease`]
+pub const LEASE_KEY: &str = "lease";
+
+/// Lease length when the worker doesn't ask for one
+pub const DEFAULT_LEASE: Duration = Duration::from_secs(5 * 60);
+
+/// Longest lease a worker can hold without renewing
+pub const MAX_LEASE: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// A worker's claim on a running task
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Lease {
+    pub worker_id: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Lease {
+    /// Lease for `worker_id` running `secs` seconds from `now`, within [`MAX_LEASE`]
+    pub fn new(worker_id: &str, secs: Option<u64>, now: DateTime<Utc>) -> Self {
+        let length = secs.map_or(DEFAULT_LEASE, Duration::from_secs).min(MAX_LEASE);
+        Self {
+            worker_id: worker_id.to_string(),
+            expires_at: now + chrono::Duration::from_std(length).unwrap_or(chrono::Duration::zero()),
+        }
+    }
+
+    /// The task's lease, if it is leased to an external worker
+    pub fn of(task: &Task) -> Option<Self> {
+        serde_json::from_value(task.metadata.get(LEASE_KEY)?.clone()).ok()
+    }
+
+    pub fn apply(&self, task: &mut Task) {
+        task.metadata.insert(LEASE_KEY.to_string(), serde_json::json!(self));
+        task.updated_at = std::time::SystemTime::now();
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at <= now
+    }
+
+    /// Whether `task` is executing under a lease held by `worker_id`
+    pub fn is_held_by(task: &Task, worker_id: &str) -> bool {
+        matches!(task.status, TaskStatus::Running | TaskStatus::Paused)
+            && Self::of(task).is_some_and(|lease| lease.worker_id == worker_id)
+    }
+}
+
+/// Body of `POST /tasks/claim`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ClaimRequest {
+    pub worker_id: String,
+    /// Visibility timeout; defaults to [`DEFAULT_LEASE`]
+    #[serde(default)]
+    pub lease_secs: Option<u64>,
+}
+
+/// Body of `POST /tasks/{id}/lease/heartbeat`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HeartbeatRequest {
+    pub worker_id: String,
+    /// New visibility timeout from now; defaults to [`DEFAULT_LEASE`]
+    #[serde(default)]
+    pub lease_secs: Option<u64>,
+}
+
+/// Body of `POST /tasks/{id}/lease/complete`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CompleteRequest {
+    pub worker_id: String,
+    pub result: TaskResult,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lease_round_trips_through_metadata() {
+        let now = Utc::now();
+        let mut task = Task::new("leased").build();
+        assert_eq!(Lease::of(&task), None);
+
+        let lease = Lease::new("worker-1", Some(30), now);
+        lease.apply(&mut task);
+        assert_eq!(Lease::of(&task), Some(lease.clone()));
+        assert!(!lease.is_expired(now + chrono::Duration::seconds(29)));
+        assert!(lease.is_expired(now + chrono::Duration::seconds(30)));
+
+        let capped = Lease::new("worker-1", Some(u64::MAX / 2), now);
+        assert_eq!(capped.expires_at, now + chrono::Duration::from_std(MAX_LEASE).unwrap());
+    }
+}
@@ -3,21 +3,91 @@
 //! A comprehensive task queue system with workflow management, dependency tracking,
 //! and MCP (Model Context Protocol) integration.
 
+pub mod agents;
+pub mod artifacts;
+pub mod audit;
+pub mod auth;
+pub mod backup;
+pub mod blobs;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod bulk;
 pub mod cache;
+pub mod calendar;
 pub mod client;
+pub mod codec;
+pub mod cold_storage;
 pub mod config;
 pub mod core;
+pub mod daemon;
+pub mod dead_letter;
+pub mod docker;
+pub mod embedded;
+pub mod engine;
 pub mod error;
+pub mod estimates;
+pub mod etag;
+pub mod events;
+pub mod executor;
+pub mod fake;
+pub mod fsck;
+pub mod github;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod groups;
+pub mod history;
+pub mod http_task;
+pub mod idempotency;
+pub mod import;
+pub mod ingest;
+pub mod jira;
+pub mod kv;
+pub mod lease;
 pub mod logging;
+pub mod maintenance;
 pub mod mcp;
 pub mod metrics;
+pub mod migration;
+pub mod models;
+pub mod openapi;
+pub mod progress;
+pub mod publisher;
+pub mod queues;
 pub mod rate_limiting;
+pub mod reaper;
+pub mod recurrence;
+#[cfg(feature = "redis")]
+pub mod redis_store;
+pub mod resources;
+pub mod retention;
+pub mod retry;
+pub mod sandbox;
+pub mod scheduler;
+pub mod search;
+pub mod secrets;
 pub mod server;
+pub mod snapshot;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_store;
+pub mod status_index;
 pub mod storage;
+pub mod task_index;
+pub mod task_logs;
+pub mod tenants;
+pub mod timeseries;
+pub mod vacuum;
+pub mod validation;
 pub mod vectorizer;
+pub mod views;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 pub mod websocket;
+pub mod workers;
+pub mod workspaces;
+pub mod write_behind;
 
 // Re-export main types for convenience
 pub use core::*;
 pub use error::{TaskQueueError, Result};
 pub use server::TaskQueueServer;
+pub use embedded::TaskQueue;
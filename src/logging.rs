@@ -14,6 +14,8 @@ use tracing::{info, warn, error, debug, trace, Level};
 use tracing_subscriber::{fmt, EnvFilter};
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
+use std::io::Write;
+use std::sync::{Arc, Mutex, OnceLock};
 
 /// Log levels supported by the system
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -107,15 +109,59 @@ impl Default for LoggingConfig {
     }
 }
 
+/// Append-only log file that can be reopened after rotation
+#[derive(Debug)]
+pub struct LogFile {
+    path: String,
+    file: Mutex<std::fs::File>,
+}
+
+impl LogFile {
+    pub fn open(path: &str) -> std::io::Result<Self> {
+        Ok(Self {
+            path: path.to_string(),
+            file: Mutex::new(Self::open_file(path)?),
+        })
+    }
+
+    fn open_file(path: &str) -> std::io::Result<std::fs::File> {
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+    }
+
+    /// Switch to a fresh handle on the same path, e.g. after logrotate moved the file
+    pub fn reopen(&self) -> std::io::Result<()> {
+        let file = Self::open_file(&self.path)?;
+        *self.file.lock().unwrap_or_else(|e| e.into_inner()) = file;
+        Ok(())
+    }
+}
+
+impl Write for &LogFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.file.lock().unwrap_or_else(|e| e.into_inner()).write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.lock().unwrap_or_else(|e| e.into_inner()).flush()
+    }
+}
+
 /// Structured logger implementation
 pub struct StructuredLogger {
     config: LoggingConfig,
+    log_file: OnceLock<Arc<LogFile>>,
 }
 
 impl StructuredLogger {
     /// Create a new structured logger with the given configuration
     pub fn new(config: LoggingConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            log_file: OnceLock::new(),
+        }
     }
 
     /// Initialize the logging system
@@ -192,10 +238,8 @@ impl StructuredLogger {
     }
 
     fn init_file(&self, path: &str, filter: &EnvFilter) -> Result<(), Box<dyn std::error::Error>> {
-        let file = std::fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(path)?;
+        let file = Arc::new(LogFile::open(path)?);
+        let _ = self.log_file.set(file.clone());
         
         match self.config.format {
             LogFormat::Json => {
@@ -223,10 +267,8 @@ impl StructuredLogger {
     }
 
     fn init_both(&self, path: &str, filter: &EnvFilter) -> Result<(), Box<dyn std::error::Error>> {
-        let file = std::fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(path)?;
+        let file = Arc::new(LogFile::open(path)?);
+        let _ = self.log_file.set(file.clone());
         
         match self.config.format {
             LogFormat::Json => {
@@ -253,6 +295,14 @@ impl StructuredLogger {
         Ok(())
     }
 
+    /// Reopen the log file, if logging to one
+    pub fn reopen(&self) -> std::io::Result<()> {
+        match self.log_file.get() {
+            Some(file) => file.reopen(),
+            None => Ok(()),
+        }
+    }
+
     /// Log a structured message
     pub fn log(&self, level: LogLevel, message: &str, fields: HashMap<String, serde_json::Value>) {
         let log_entry = LogEntry {
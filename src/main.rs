@@ -2,55 +2,640 @@
 //!
 //! This binary starts the Task Queue HTTP server with all necessary components.
 
+use clap::{Parser, Subcommand};
 use tracing::{info, error};
-use crate::server::TaskQueueServer;
+use crate::audit::AuditSource;
+use crate::backup::{Backup, BackupArchive, RestoreMode, RestoreReport};
+use crate::codec::Codec;
+use crate::config::Config;
+use crate::daemon::PidFile;
+use crate::fsck::{FsckAction, FsckReport, Problem, Resolution};
+use crate::import::ImportReport;
+use crate::server::{TaskQueueServer, API_PREFIX};
+use crate::storage::StorageEngine;
+use crate::vectorizer::VectorizerIntegration;
+use crate::migration::{BackendSpec, Progress};
 use crate::logging::{StructuredLogger, LoggingConfig, LogLevel, LogFormat, LogOutput};
+use std::path::PathBuf;
 use std::sync::Arc;
 
+mod agents;
+mod artifacts;
+mod audit;
+mod auth;
+mod backup;
+mod blobs;
+#[cfg(feature = "blocking")]
+mod blocking;
+mod bulk;
 mod cache;
+mod calendar;
 mod client;
+mod codec;
+mod cold_storage;
 mod config;
 mod core;
+mod daemon;
+mod dead_letter;
+mod docker;
+mod embedded;
+mod engine;
 mod error;
+mod estimates;
+mod etag;
+mod events;
+mod executor;
+mod fake;
+mod fsck;
+mod github;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod groups;
+mod history;
+mod http_task;
+mod idempotency;
+mod import;
+mod ingest;
+mod jira;
+mod kv;
+mod lease;
 mod logging;
+mod maintenance;
 mod metrics;
+mod migration;
+mod models;
+mod openapi;
+mod progress;
+mod publisher;
+mod queues;
 mod rate_limiting;
+mod reaper;
+mod recurrence;
+#[cfg(feature = "redis")]
+mod redis_store;
+mod resources;
+mod retention;
+mod retry;
+mod sandbox;
+mod scheduler;
+mod search;
+mod secrets;
 mod server;
+mod snapshot;
+#[cfg(feature = "sqlite")]
+mod sqlite_store;
+mod status_index;
 mod storage;
+mod task_index;
+mod task_logs;
+mod tenants;
+mod timeseries;
+mod vacuum;
+mod validation;
 mod vectorizer;
+mod views;
+#[cfg(feature = "wasm")]
+mod wasm;
 mod websocket;
+mod workers;
+mod workspaces;
+mod write_behind;
 mod mcp;
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// Task Queue server
+#[derive(Parser, Debug)]
+#[command(name = "task-queue", version)]
+struct Args {
+    /// Configuration file, TOML or YAML (defaults to task-queue.toml if
+    /// present); environment variables override it
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Address to listen on, overriding the configuration
+    #[arg(long)]
+    host: Option<String>,
+
+    /// Port to listen on, overriding the configuration
+    #[arg(long)]
+    port: Option<u16>,
+
+    /// Detach from the terminal and run in the background
+    #[arg(long)]
+    daemon: bool,
+
+    /// Write the process ID to this file (defaults to task-queue.pid with --daemon)
+    #[arg(long)]
+    pid_file: Option<PathBuf>,
+
+    /// Re-encode every stored record as `json` or `binary`, then exit
+    #[arg(long, value_name = "FORMAT", value_parser = parse_codec)]
+    migrate_storage: Option<Codec>,
+
+    /// API key for commands run against a server with `--server`; these
+    /// need the operator's `server.tenancy.admin_key`
+    #[arg(long, global = true, env = "TASK_QUEUE_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Maintain the server's storage
+    Server {
+        #[command(subcommand)]
+        command: ServerCommand,
+    },
+    /// Work with the queue's tasks
+    Tasks {
+        #[command(subcommand)]
+        command: TasksCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum TasksCommand {
+    /// Import tasks from a JSON Lines file, one `POST /tasks` body per line,
+    /// then exit; fails if any line was rejected
+    Import {
+        /// File to import
+        file: PathBuf,
+
+        /// Only check every line, storing nothing
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Import into this running server instead of the local database
+        #[arg(long, value_name = "URL")]
+        server: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ServerCommand {
+    /// Write a tar archive of all tasks, projects and workflows, then exit
+    Backup {
+        /// Archive to write
+        output: PathBuf,
+
+        /// Download the backup from this running server (e.g.
+        /// http://localhost:16080) instead of opening the database, which a
+        /// running server keeps locked
+        #[arg(long, value_name = "URL")]
+        server: Option<String>,
+    },
+    /// Restore tasks, projects and workflows from a backup archive, then exit
+    Restore {
+        /// Archive written by `server backup`
+        archive: PathBuf,
+
+        /// `merge` to add what is missing and keep existing records, or
+        /// `replace` to make the queue match the backup exactly
+        #[arg(long, default_value = "merge", value_parser = parse_restore_mode)]
+        mode: RestoreMode,
+
+        /// Restore into this running server instead of the local database
+        #[arg(long, value_name = "URL")]
+        server: Option<String>,
+    },
+    /// Check that stored records decode and reference records that exist,
+    /// then exit; fails if problems are left
+    Fsck {
+        /// Drop broken references, keeping the records
+        #[arg(long, conflicts_with = "quarantine")]
+        repair: bool,
+
+        /// Move records with broken references out of the queue
+        #[arg(long)]
+        quarantine: bool,
+
+        /// Check this running server instead of the local database
+        #[arg(long, value_name = "URL")]
+        server: Option<String>,
+    },
+    /// Copy all stored data into another backend and verify the copy, then
+    /// exit; stop the server first
+    Migrate {
+        /// Backend to copy into: `sled:<path>`, `sqlite:<path>` or a
+        /// `redis://` URL
+        #[arg(long, value_parser = parse_backend)]
+        to: BackendSpec,
+
+        /// Backend to copy from, instead of the configured storage
+        #[arg(long, value_parser = parse_backend)]
+        from: Option<BackendSpec>,
+
+        /// Replace whatever the target already holds
+        #[arg(long)]
+        overwrite: bool,
+    },
+}
+
+fn parse_backend(value: &str) -> Result<BackendSpec, String> {
+    BackendSpec::parse(value).ok_or_else(|| format!("unknown backend '{}', expected sled:<path>, sqlite:<path> or a redis:// URL", value))
+}
+
+fn parse_restore_mode(value: &str) -> Result<RestoreMode, String> {
+    RestoreMode::parse(value).ok_or_else(|| format!("unknown restore mode '{}', expected merge or replace", value))
+}
+
+fn parse_codec(value: &str) -> Result<Codec, String> {
+    Codec::parse(value).ok_or_else(|| format!("unknown storage format '{}', expected json or binary", value))
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let mut config = Config::load(args.config.as_deref())?;
+    if let Some(host) = args.host {
+        config.server.host = host;
+    }
+    if let Some(port) = args.port {
+        config.server.port = port;
+    }
+    // Storage opened by any command below reads it from here
+    config.install();
+
+    if let Some(codec) = args.migrate_storage {
+        return tokio::runtime::Runtime::new()?.block_on(migrate_storage(codec));
+    }
+    let api_key = args.api_key.as_deref();
+    match args.command {
+        Some(Command::Server { command: ServerCommand::Backup { output, server } }) => {
+            return tokio::runtime::Runtime::new()?.block_on(backup(output, server, api_key));
+        }
+        Some(Command::Server { command: ServerCommand::Restore { archive, mode, server } }) => {
+            return tokio::runtime::Runtime::new()?.block_on(restore(archive, mode, server, api_key));
+        }
+        Some(Command::Server { command: ServerCommand::Fsck { repair, quarantine, server } }) => {
+            let action = match (repair, quarantine) {
+                (true, _) => FsckAction::Repair,
+                (_, true) => FsckAction::Quarantine,
+                _ => FsckAction::Check,
+            };
+            return tokio::runtime::Runtime::new()?.block_on(fsck(action, server, api_key));
+        }
+        Some(Command::Server { command: ServerCommand::Migrate { to, from, overwrite } }) => {
+            return tokio::runtime::Runtime::new()?.block_on(migrate(to, from, overwrite));
+        }
+        Some(Command::Tasks { command: TasksCommand::Import { file, dry_run, server } }) => {
+            return tokio::runtime::Runtime::new()?.block_on(import_tasks(file, dry_run, server, api_key));
+        }
+        None => {}
+    }
+
+    let pid_path = args.pid_file.or_else(|| args.daemon.then(|| PathBuf::from(daemon::DEFAULT_PID_FILE)));
+
+    // Refuse to start over a running instance while errors still reach the terminal
+    if let Some(path) = &pid_path {
+        PidFile::check(path)?;
+    }
+    if args.daemon {
+        daemon::daemonize()?;
+    }
+    let _pid_file = pid_path.map(PidFile::create).transpose()?;
+
+    tokio::runtime::Runtime::new()?.block_on(run(config))
+}
+
+/// Convert the local database to another record format
+async fn migrate_storage(codec: Codec) -> Result<(), Box<dyn std::error::Error>> {
+    let mut storage = StorageEngine::new().await?;
+    let report = storage.migrate_codec(codec).await?;
+    println!(
+        "Migrated storage to {:?}: {} records converted, {} already in that format",
+        codec, report.converted, report.unchanged
+    );
+    Ok(())
+}
+
+/// URL of `path` under the versioned API of the server at `server`
+fn remote_url(server: &str, path: &str) -> String {
+    format!("{}{}{}", server.trim_end_matches('/'), API_PREFIX, path)
+}
+
+/// HTTP client for commands run against a server, sending `api_key` with
+/// every request
+fn remote_client(api_key: Option<&str>) -> Result<reqwest::Client, Box<dyn std::error::Error>> {
+    let mut headers = reqwest::header::HeaderMap::new();
+    if let Some(api_key) = api_key {
+        let mut value = reqwest::header::HeaderValue::from_str(api_key)?;
+        value.set_sensitive(true);
+        headers.insert(tenants::API_KEY_HEADER, value);
+    }
+    Ok(reqwest::Client::builder().default_headers(headers).build()?)
+}
+
+/// Write a backup archive of the local database or of a running server
+async fn backup(output: PathBuf, server: Option<String>, api_key: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(server) = server {
+        let url = remote_url(&server, "/admin/backup");
+        let mut response = remote_client(api_key)?.post(url).send().await?.error_for_status()?;
+        let mut file = std::fs::File::create(&output)?;
+        while let Some(chunk) = response.chunk().await? {
+            std::io::Write::write_all(&mut file, &chunk)?;
+        }
+        println!("Downloaded backup from {} to {}", server, output.display());
+        return Ok(());
+    }
+
+    let file = std::fs::File::create(&output)?;
+    let storage = StorageEngine::new().await?;
+    let manifest = Backup::capture(&storage).await?.write_to(std::io::BufWriter::new(file))?;
+    let counts: Vec<String> = manifest.sections.iter()
+        .map(|section| format!("{} {}", section.records, section.name))
+        .collect();
+    println!("Wrote backup to {}: {}", output.display(), counts.join(", "));
+    Ok(())
+}
+
+/// Restore a backup archive into the local database or a running server
+async fn restore(archive: PathBuf, mode: RestoreMode, server: Option<String>, api_key: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let bytes = std::fs::read(&archive)?;
+    let report: RestoreReport = match server {
+        Some(server) => {
+            let url = remote_url(&server, "/admin/restore");
+            remote_client(api_key)?
+                .post(url)
+                .header(audit::SOURCE_HEADER, "cli")
+                .query(&[("mode", mode)])
+                .body(bytes)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?
+        }
+        None => {
+            let archive = BackupArchive::read(bytes.as_slice())?;
+            let storage = Arc::new(StorageEngine::new().await?);
+            let server = TaskQueueServer::with_components(storage, Arc::new(VectorizerIntegration::new_dummy())).await?;
+            audit::with_source(AuditSource::Cli, server.restore_backup(archive, mode)).await?
+        }
+    };
+
+    println!(
+        "Restored {} ({:?}): {} written, {} unchanged, {} removed, {} conflicts",
+        archive.display(), report.mode, report.restored, report.unchanged, report.removed, report.conflicts.len()
+    );
+    for conflict in &report.conflicts {
+        println!("  kept existing {} {} ({})", conflict.section, conflict.name, conflict.id);
+    }
+    Ok(())
+}
+
+/// Import a JSON Lines file of tasks into the local database or a running server
+async fn import_tasks(file: PathBuf, dry_run: bool, server: Option<String>, api_key: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let report: ImportReport = match server {
+        Some(server) => {
+            let url = remote_url(&server, "/admin/import");
+            remote_client(api_key)?
+                .post(url)
+                .header(audit::SOURCE_HEADER, "cli")
+                .query(&[("dry_run", dry_run)])
+                .body(std::fs::read(&file)?)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?
+        }
+        None => {
+            let reader = tokio::io::BufReader::new(tokio::fs::File::open(&file).await?);
+            let storage = Arc::new(StorageEngine::new().await?);
+            let server = TaskQueueServer::with_components(storage, Arc::new(VectorizerIntegration::new_dummy())).await?;
+            audit::with_source(AuditSource::Cli, server.import_tasks(reader, dry_run)).await?
+        }
+    };
+
+    let verb = if report.dry_run { "Checked" } else { "Imported" };
+    println!("{} {} of {} tasks from {}", verb, report.imported, report.lines, file.display());
+    for error in &report.errors {
+        println!("  line {}: {}", error.line, error.error);
+    }
+    if !report.errors.is_empty() {
+        return Err(format!("{} lines were rejected", report.errors.len()).into());
+    }
+    Ok(())
+}
+
+/// Copy the configured storage, or `from`, into another backend
+async fn migrate(to: BackendSpec, from: Option<BackendSpec>, overwrite: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let source = match from {
+        Some(from) => from.open()?,
+        None => StorageEngine::new().await?,
+    };
+    let target = to.open()?;
+
+    let report = migration::migrate(&source, &target, overwrite, |progress: Progress<'_>| {
+        println!("  {}: {}/{}", progress.tree, progress.copied, progress.total);
+    }).await?;
+
+    for tree in report.trees.iter().filter(|tree| !tree.verified) {
+        println!("  {}: target differs from the {} source records", tree.tree, tree.records);
+    }
+    if !report.verified() {
+        return Err("the copy does not match the source".into());
+    }
+    println!("Copied and verified {} records in {} trees", report.records(), report.trees.len());
+    Ok(())
+}
+
+/// Check the local database or a running server's storage
+async fn fsck(action: FsckAction, server: Option<String>, api_key: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let report: FsckReport = match server {
+        Some(server) => {
+            let url = remote_url(&server, "/admin/fsck");
+            remote_client(api_key)?
+                .post(url)
+                .header(audit::SOURCE_HEADER, "cli")
+                .query(&[("action", action)])
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?
+        }
+        // Records that no longer decode would stop a server from loading
+        None => {
+            let storage = StorageEngine::new().await?;
+            audit::with_source(AuditSource::Cli, fsck::run(&storage, action)).await?
+        }
+    };
+
+    println!(
+        "Checked {} tasks, {} projects and {} workflows: {} problems",
+        report.tasks, report.projects, report.workflows, report.inconsistencies.len()
+    );
+    for inconsistency in &report.inconsistencies {
+        let problem = match &inconsistency.problem {
+            Problem::Undecodable { collection, key, error } => format!("{} record {} does not decode: {}", collection.name(), key, error),
+            Problem::MissingProject { task_id, project_id } => format!("task {} is in missing project {}", task_id, project_id),
+            Problem::MissingDependency { task_id, dependency_id } => format!("task {} depends on missing task {}", task_id, dependency_id),
+            Problem::MissingWorkflowTask { workflow_id, task_id } => format!("workflow {} has a dependency on task {} it does not contain", workflow_id, task_id),
+        };
+        println!("  {:?}: {}", inconsistency.resolution, problem);
+    }
+
+    let left = report.count(Resolution::Reported);
+    if left > 0 {
+        return Err(format!("{} problems left; run again with --repair or --quarantine", left).into());
+    }
+    Ok(())
+}
+
+async fn run(config: Config) -> Result<(), Box<dyn std::error::Error>> {
+    config.check_execution()?;
+
     // Initialize structured logging
+    let log_file = config.server.log_file.clone();
     let logging_config = LoggingConfig {
         level: LogLevel::Info,
         format: LogFormat::Pretty,
-        output: LogOutput::Both("logs/task-queue.log".to_string()),
+        output: log_file.clone().map_or(LogOutput::Stdout, LogOutput::Both),
         enable_span_events: true,
-        enable_file_logging: true,
-        log_file_path: Some("logs/task-queue.log".to_string()),
+        enable_file_logging: log_file.is_some(),
+        log_file_path: log_file,
         max_file_size: Some(10 * 1024 * 1024), // 10MB
         max_files: Some(5),
     };
 
-    let logger = StructuredLogger::new(logging_config);
+    let logger = Arc::new(StructuredLogger::new(logging_config));
     logger.init()?;
 
+    // Reopen the log file on SIGHUP so logrotate can move it away
+    let hangup_logger = logger.clone();
+    daemon::on_hangup(move || match hangup_logger.reopen() {
+        Ok(()) => info!("Log file reopened"),
+        Err(e) => error!("Failed to reopen log file: {}", e),
+    })?;
+
     info!("🚀 Starting Task Queue Server with MCP integration");
 
     // Create the task queue server
     info!("🔧 Creating TaskQueueServer...");
-    let server = Arc::new(TaskQueueServer::new().await?);
+    let server = if config.server.stateless {
+        info!("Running stateless: all reads and writes go through storage");
+        TaskQueueServer::new_stateless(std::time::Duration::from_millis(config.server.cache_ttl_ms)).await?
+    } else {
+        TaskQueueServer::new().await?
+    };
+    let blobs = blobs::open(&config.execution.artifacts.store, server.storage().clone())?;
+    let server = server
+        .with_artifacts(config.execution.artifacts.clone())
+        .with_blob_store(blobs)
+        .with_workspaces(config.execution.workspaces.clone())
+        .with_secret_hosts(config.execution.secret_hosts.clone())
+        .with_retention(config.storage.retention.policy(config.storage.retention_days))
+        .with_cold_storage(config.storage.cold_storage.clone())
+        .with_vacuum(config.storage.vacuum.clone())
+        .with_write_behind(&config.storage.write_behind)
+        .with_idempotency(&config.server.idempotency)
+        .with_rate_limits(&config.server.rate_limits)
+        .with_server_config(config.server.clone());
+    let server = Arc::new(server);
     info!("✅ TaskQueueServer created successfully");
 
+    // Mirror events to Kafka/NATS through the storage outbox
+    if let Some(publisher_config) = config.events.publisher.clone() {
+        server.events().enable_outbox(server.storage().clone())?;
+        publisher::spawn(server.storage().clone(), server.events().clone(), publisher_config);
+    }
+
+    // Run pending tasks
+    if config.execution.enabled {
+        engine::spawn(server.clone(), &config.execution);
+        workspaces::spawn(config.execution.workspaces.clone());
+    }
+
+    // Take back tasks left running by a server that died
+    reaper::spawn(server.clone(), std::time::Duration::from_secs(config.execution.stale_after_secs));
+
+    // Archive or delete old finished tasks
+    if config.storage.retention.enabled {
+        retention::spawn(server.clone(), config.storage.retention.clone());
+    }
+
+    // Move old completed tasks to compressed cold storage
+    if config.storage.cold_storage.enabled {
+        cold_storage::spawn(server.clone(), config.storage.cold_storage.clone());
+    }
+
+    // Remove what deleted tasks left behind
+    if config.storage.vacuum.enabled {
+        vacuum::spawn(server.clone(), config.storage.vacuum.clone());
+    }
+
+    // Write batched lease renewals
+    if config.storage.write_behind.enabled {
+        write_behind::spawn(server.clone(), config.storage.write_behind.clone());
+    }
+
+    // Accept task submissions from a message queue
+    if let Some(ingest_config) = config.ingest.clone() {
+        ingest::spawn(server.clone(), ingest_config);
+    }
+
+    // Serve the core operations over gRPC as well
+    #[cfg(feature = "grpc")]
+    grpc::spawn(server.clone(), config.server.grpc_address());
+
     // Start the MCP server with REST API routes
     info!("🚀 Starting MCP server with REST API integration...");
-    if let Err(e) = server.start().await {
+    if let Err(e) = server.start_with_shutdown(daemon::shutdown_signal()).await {
         error!("❌ Failed to start server: {}", e);
         return Err(e.into());
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ServerConfig;
+    use crate::tenants::TenancyConfig;
+
+    #[tokio::test]
+    async fn test_remote_commands_send_the_api_key() {
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap()
+        .with_server_config(ServerConfig {
+            tenancy: TenancyConfig { required: true, admin_key: Some("operator".to_string()), ..Default::default() },
+            ..ServerConfig::default()
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let router = server.rest_router();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        assert!(fsck(FsckAction::Check, Some(url.clone()), None).await.is_err());
+        fsck(FsckAction::Check, Some(url.clone()), Some("operator")).await.unwrap();
+
+        let archive = std::env::temp_dir().join(format!("task-queue-backup-{}.tar", uuid::Uuid::new_v4()));
+        assert!(backup(archive.clone(), Some(url.clone()), None).await.is_err());
+        assert!(!archive.exists());
+        backup(archive.clone(), Some(url.clone()), Some("operator")).await.unwrap();
+        restore(archive.clone(), RestoreMode::Merge, Some(url), Some("operator")).await.unwrap();
+        std::fs::remove_file(archive).unwrap();
+    }
+
+    #[test]
+    fn test_backup_and_restore_are_server_commands_on_the_versioned_api() {
+        let args = Args::try_parse_from(["task-queue", "server", "backup", "queue.tar"]).unwrap();
+        assert!(matches!(args.command, Some(Command::Server { command: ServerCommand::Backup { .. } })));
+        assert!(Args::try_parse_from(["task-queue", "backup", "queue.tar"]).is_err());
+        let args = Args::try_parse_from(["task-queue", "server", "restore", "queue.tar", "--mode", "replace"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Some(Command::Server { command: ServerCommand::Restore { mode: RestoreMode::Replace, .. } })
+        ));
+        assert!(Args::try_parse_from(["task-queue", "restore", "queue.tar"]).is_err());
+        assert_eq!(remote_url("http://localhost:16080/", "/admin/backup"), "http://localhost:16080/api/v1/admin/backup");
+    }
+}
@@ -1,23 +1,382 @@
 //! MCP (Model Context Protocol) integration using rmcp crate
 //!
-//! This module provides MCP functionality using the rmcp crate with SSE transport,
-//! compatible with Cursor's MCP client.
+//! This module provides MCP functionality using the rmcp crate, over the SSE
+//! transport at `/mcp/sse` and the streamable HTTP transport at `/mcp` for
+//! clients that have dropped SSE; `server.mcp_transport` picks which are served.
+//! Each project is a resource, and connected clients are notified as tasks and
+//! projects change so they can refresh instead of polling `list_tasks`.
+//!
+//! Both transports take the REST API keys, in `X-API-Key` or
+//! `Authorization: Bearer`, and turn away requests with an unknown key or
+//! none at all; `server.tenancy.mcp_anonymous` lets keyless clients in as
+//! the operator unless `server.tenancy.required` is set. A tenant key sees
+//! only its tenant's tasks and projects, and may only call the tools its
+//! scopes allow: read-only tools need `read`, destructive ones such as
+//! `delete_task` need `delete`, and the rest `write`.
 //!
 
 use std::sync::Arc;
 use std::borrow::Cow;
 
-use axum::Router as AxumRouter;
+use axum::extract::{Request, State};
+use axum::http::request::Parts;
+use axum::http::StatusCode;
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::{Json, Router as AxumRouter};
 use rmcp::{
     handler::server::ServerHandler,
     model::{CallToolResult, Content, ErrorData, ListToolsResult, ListResourcesResult, ProtocolVersion, ServerCapabilities, Tool, ServerInfo, Implementation, ToolAnnotations},
+    model::{AnnotateAble, LoggingLevel, LoggingMessageNotificationParam, RawResource, ReadResourceRequestParam, ReadResourceResult, ResourceContents, ResourceUpdatedNotificationParam},
     transport::sse_server::{SseServer, SseServerConfig},
-    service::{RequestContext, RoleServer},
+    transport::streamable_http_server::{session::local::LocalSessionManager, StreamableHttpServerConfig, StreamableHttpService},
+    service::{NotificationContext, Peer, RequestContext, RoleServer, ServiceError},
 };
 use std::net::SocketAddr;
+use rmcp::handler::server::common::cached_schema_for_type;
+use rmcp::model::CallToolRequestParam;
+use schemars::{json_schema, JsonSchema};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
 use serde_json::json;
 
+use crate::config::McpTransport;
+use crate::error::TaskQueueError;
+use crate::events::{TaskEvent, TaskEventKind};
 use crate::server::TaskQueueServer;
+use crate::tenants::{self, KeyScope, Principal};
+
+/// Path of the streamable HTTP transport
+pub const MCP_STREAMABLE_PATH: &str = "/mcp";
+/// Prefix of the URIs of project resources, followed by the project ID
+const PROJECT_URI_PREFIX: &str = "task-queue://projects/";
+
+fn project_uri(project_id: uuid::Uuid) -> String {
+    format!("{}{}", PROJECT_URI_PREFIX, project_id)
+}
+
+/// Turn away MCP requests without a known key, unless the tenancy settings
+/// let anonymous clients in, handing the principal of the others on to the
+/// handler with the request's parts
+async fn authenticate(
+    State(task_queue): State<Arc<TaskQueueServer>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let credential = tenants::credential(request.headers());
+    if credential.is_none() && !task_queue.tenancy().mcp_anonymous {
+        return (StatusCode::UNAUTHORIZED, Json(json!({ "error": "An API key is required" }))).into_response();
+    }
+    match task_queue.authenticate(credential).await {
+        Ok(principal) => {
+            request.extensions_mut().insert(principal);
+            next.run(request).await
+        }
+        Err((status, error)) => (status, Json(json!({ "error": error }))).into_response(),
+    }
+}
+
+/// Principal of the HTTP request a message came in; the operator for
+/// messages that didn't come over HTTP
+fn principal(extensions: &rmcp::model::Extensions) -> Principal {
+    extensions.get::<Parts>()
+        .and_then(|parts| parts.extensions.get::<Principal>())
+        .cloned()
+        .unwrap_or(Principal::Operator)
+}
+
+/// Forward changes to tasks and projects visible to `tenant` to a connected
+/// client until it goes away
+async fn forward_changes(task_queue: Arc<TaskQueueServer>, peer: Peer<RoleServer>, tenant: Option<uuid::Uuid>) {
+    use tokio::sync::broadcast::error::RecvError;
+
+    let mut receiver = task_queue.events().subscribe();
+    loop {
+        let notified = match receiver.recv().await {
+            Ok(event) if tenant.is_some() && event.tenant_id != tenant => continue,
+            Ok(event) => notify_change(&peer, &event).await,
+            // Changes were missed, so the client refreshes everything
+            Err(RecvError::Lagged(_)) => peer.notify_resource_list_changed().await,
+            Err(RecvError::Closed) => break,
+        };
+        if notified.is_err() {
+            break;
+        }
+    }
+}
+
+/// Tell a client a task or project changed: projects coming or going change
+/// the resource list, other changes update their project's resource, and
+/// every change is also sent as a log message carrying the event
+async fn notify_change(peer: &Peer<RoleServer>, event: &TaskEvent) -> Result<(), ServiceError> {
+    if matches!(event.kind, TaskEventKind::ProjectCreated | TaskEventKind::ProjectDeleted) {
+        peer.notify_resource_list_changed().await?;
+    } else if let Some(project_id) = event.project_id {
+        peer.notify_resource_updated(ResourceUpdatedNotificationParam { uri: project_uri(project_id) }).await?;
+    }
+    peer.notify_logging_message(LoggingMessageNotificationParam {
+        level: LoggingLevel::Info,
+        logger: Some("task-queue".to_string()),
+        data: json!(event),
+    }).await
+}
+
+/// Arguments of a tool call, or why they don't fit the tool's input schema
+fn parse_args<T: DeserializeOwned>(request: &CallToolRequestParam) -> Result<T, ErrorData> {
+    let args = request.arguments.clone().unwrap_or_default();
+    serde_json::from_value(serde_json::Value::Object(args))
+        .map_err(|e| ErrorData::invalid_params(format!("Invalid arguments: {}", e), None))
+}
+
+/// A value, or a list of values any of which matches
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T> OneOrMany<T> {
+    fn into_vec(self) -> Vec<T> {
+        match self {
+            OneOrMany::One(value) => vec![value],
+            OneOrMany::Many(values) => values,
+        }
+    }
+}
+
+/// `list_tasks` status filters, from the values the filter understands
+fn status_filters_schema(_generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+    json_schema!({
+        "description": "Only tasks with this status, or any of these",
+        "anyOf": [
+            {"type": "string", "enum": crate::views::STATUS_FILTERS},
+            {"type": "array", "items": {"type": "string", "enum": crate::views::STATUS_FILTERS}}
+        ]
+    })
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct NoArgs {}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct TaskIdArgs {
+    /// Task ID
+    task_id: uuid::Uuid,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ProjectIdArgs {
+    /// Project ID
+    project_id: uuid::Uuid,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct SubmitTaskArgs {
+    /// Task name
+    name: String,
+    /// Command to execute, or the whole script when interpreter is set
+    command: String,
+    /// Project ID to associate the task with
+    project_id: uuid::Uuid,
+    /// Task priority (default Normal)
+    priority: Option<crate::core::TaskPriority>,
+    /// Keep the task Scheduled until this RFC 3339 time
+    run_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Run command as a multi-line script with this interpreter
+    interpreter: Option<crate::core::ScriptInterpreter>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ListTasksArgs {
+    /// Maximum number of tasks to return (default 50)
+    #[schemars(range(min = 1, max = 1000))]
+    limit: Option<u32>,
+    /// Cursor returned by a previous call, to list the next page
+    cursor: Option<String>,
+    #[serde(default)]
+    #[schemars(schema_with = "status_filters_schema")]
+    status: Option<OneOrMany<String>>,
+    /// Only tasks with this priority, or any of these
+    priority: Option<OneOrMany<crate::core::TaskPriority>>,
+    /// Only tasks of this project; unlike status and priority, a single ID
+    project_id: Option<uuid::Uuid>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct SearchTasksArgs {
+    /// What the task is about, in plain words
+    query: String,
+    /// Most tasks to return (default 5)
+    #[schemars(range(min = 1, max = 50))]
+    limit: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct UpdateTaskArgs {
+    /// Task ID to update
+    task_id: uuid::Uuid,
+    /// New task name
+    name: Option<String>,
+    /// New command
+    command: Option<String>,
+    /// New description
+    description: Option<String>,
+    /// New priority
+    priority: Option<crate::core::TaskPriority>,
+    /// New status
+    status: Option<crate::core::TaskStatus>,
+    /// Project ID to associate with task (empty string to remove association)
+    project_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct UpsertTaskArgs {
+    /// Task name (used for lookup)
+    name: String,
+    /// Command to execute
+    command: String,
+    /// Task description
+    description: String,
+    /// Project ID to associate the task with
+    project_id: uuid::Uuid,
+    /// Task priority
+    priority: Option<crate::core::TaskPriority>,
+    /// Technical specifications
+    technical_specs: Option<String>,
+    /// Acceptance criteria
+    acceptance_criteria: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct GetNextTaskArgs {
+    /// ID of the calling agent, from register_agent
+    agent_id: uuid::Uuid,
+    /// Only tasks of this project
+    project_id: Option<uuid::Uuid>,
+    /// Only tasks tagged with all of these
+    #[serde(default)]
+    labels: Vec<String>,
+    /// How long the task is leased (default 300, at most 43200)
+    #[schemars(range(min = 1))]
+    lease_secs: Option<u64>,
+}
+
+/// When a dependency added through MCP is met
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+enum DependencyConditionArg {
+    #[default]
+    Success,
+    Failure,
+    Completion,
+    Group,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct AddTaskDependencyArgs {
+    /// Task that should wait
+    task_id: uuid::Uuid,
+    /// Task, or task group with the Group condition, to wait for
+    dependency_task_id: uuid::Uuid,
+    /// When the dependency is met (default Success)
+    #[serde(default)]
+    condition: DependencyConditionArg,
+    /// Group members that must succeed, with the Group condition; all by default
+    #[schemars(range(min = 1))]
+    quorum: Option<u32>,
+    /// Whether the task waits for this dependency (default true)
+    required: Option<bool>,
+    /// Groups related dependencies
+    correlation_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct AddArtifactArgs {
+    /// Task ID
+    task_id: uuid::Uuid,
+    /// File path or URL
+    location: String,
+    /// What the artifact is
+    description: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ReportProgressArgs {
+    /// Task ID
+    task_id: uuid::Uuid,
+    /// Share of the work done
+    #[schemars(range(min = 0.0, max = 100.0))]
+    percent: f64,
+    /// What is being done
+    #[schemars(length(max = crate::progress::MAX_NOTE_LENGTH))]
+    note: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct CreateProjectArgs {
+    /// Project name
+    name: String,
+    /// Project description
+    description: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct SetTechnicalDocumentationArgs {
+    /// Task ID
+    task_id: uuid::Uuid,
+    /// Path to technical documentation
+    doc_path: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct SetTestCoverageArgs {
+    /// Task ID
+    task_id: uuid::Uuid,
+    /// Test coverage percentage (0.0-1.0)
+    #[schemars(range(min = 0.0, max = 1.0))]
+    coverage: f64,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct AddAiReviewReportArgs {
+    /// Task ID
+    task_id: uuid::Uuid,
+    /// AI model name
+    model_name: String,
+    /// Type of review
+    review_type: crate::core::AIReviewType,
+    /// Review content
+    content: String,
+    /// Review score (0.0-1.0)
+    #[schemars(range(min = 0.0, max = 1.0))]
+    score: f64,
+    /// Whether the code is approved
+    approved: bool,
+    /// List of suggestions
+    #[serde(default)]
+    suggestions: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ViewNameArgs {
+    /// View name
+    name: String,
+}
+
+/// A view to create or replace, under its name
+#[derive(Debug, Deserialize, JsonSchema)]
+struct SaveViewArgs {
+    /// View name
+    name: String,
+    #[serde(flatten)]
+    view: crate::views::SaveViewRequest,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct AgentIdArgs {
+    /// Agent ID returned by register_agent
+    agent_id: uuid::Uuid,
+}
 
 #[derive(Clone)]
 pub struct TaskQueueMcpServer {
@@ -36,7 +395,7 @@ impl TaskQueueMcpServer {
         
         match current_phase {
             crate::core::TaskStatus::Planning => {
-                format!(r#"
+                r#"
 📋 **WORKFLOW INSTRUCTIONS - PHASE: PLANNING**
 
 🚨 **CRITICAL**: You MUST follow this exact workflow process. Do NOT skip phases!
@@ -56,10 +415,10 @@ impl TaskQueueMcpServer {
 - **AIReview**: Get reviews from 3 AI models before completion
 
 ⚠️ **FAILURE TO FOLLOW WORKFLOW WILL RESULT IN TASK REJECTION**
-                "#)
+                "#.to_string()
             },
             crate::core::TaskStatus::Implementation => {
-                format!(r#"
+                r#"
 📋 **WORKFLOW INSTRUCTIONS - PHASE: IMPLEMENTATION** (IN PROGRESS)
 
 🔄 **CONTINUE IMPLEMENTATION PHASE**:
@@ -69,10 +428,10 @@ impl TaskQueueMcpServer {
 - **When implementation is complete, change status to "TestCreation"**
 
 📝 **Remember**: Test creation comes AFTER implementation is finished
-                "#)
+                "#.to_string()
             },
             crate::core::TaskStatus::TestCreation => {
-                format!(r#"
+                r#"
 📋 **WORKFLOW INSTRUCTIONS - PHASE: TEST CREATION** (IN PROGRESS)
 
 🔄 **CONTINUE TEST CREATION PHASE**:
@@ -83,10 +442,10 @@ impl TaskQueueMcpServer {
 - **When all tests are created, change status to "Testing"**
 
 🧪 **Focus**: Test creation, not test execution yet
-                "#)
+                "#.to_string()
             },
             crate::core::TaskStatus::Testing => {
-                format!(r#"
+                r#"
 📋 **WORKFLOW INSTRUCTIONS - PHASE: TESTING** (IN PROGRESS)
 
 🚨 **CRITICAL TESTING REQUIREMENTS**:
@@ -100,10 +459,10 @@ impl TaskQueueMcpServer {
 ⚠️ **NO ADVANCEMENT WITHOUT PASSING TESTS** - All tests must be executed and pass successfully!
 
 **When ALL tests pass, change status to "AIReview"**
-                "#)
+                "#.to_string()
             },
             crate::core::TaskStatus::AIReview => {
-                format!(r#"
+                r#"
 📋 **WORKFLOW INSTRUCTIONS - PHASE: AI REVIEW** (IN PROGRESS)
 
 🔄 **CONTINUE AI REVIEW PHASE**:
@@ -114,7 +473,7 @@ impl TaskQueueMcpServer {
 - **When all 3 AI models approve the code, change status to "Completed"**
 
 🤖 **REQUIREMENT**: All 3 AI models must approve before completion
-                "#)
+                "#.to_string()
             },
             crate::core::TaskStatus::Completed => {
                 "✅ **WORKFLOW COMPLETED**: Task has successfully passed all phases.".to_string()
@@ -234,32 +593,32 @@ impl TaskQueueMcpServer {
         &self,
         name: String,
         command: String,
-        project_id: String,
-        priority: Option<String>,
+        project_id: uuid::Uuid,
+        priority: Option<crate::core::TaskPriority>,
+        run_at: Option<chrono::DateTime<chrono::Utc>>,
+        interpreter: Option<crate::core::ScriptInterpreter>,
     ) -> Result<CallToolResult, String> {
-        let project_id_uuid = match uuid::Uuid::parse_str(&project_id) {
-            Ok(id) => id,
-            Err(_) => return Err("Invalid project ID format".to_string()),
-        };
-
-        let priority = match priority.as_deref() {
-            Some("Low") => crate::core::TaskPriority::Low,
-            Some("High") => crate::core::TaskPriority::High,
-            Some("Critical") => crate::core::TaskPriority::Critical,
-            _ => crate::core::TaskPriority::Normal,
-        };
+        if self.task_queue.maintenance().is_enabled() {
+            return Err("The server is in maintenance and accepts no new tasks".to_string());
+        }
+        let priority = priority.unwrap_or(crate::core::TaskPriority::Normal);
 
         let task = crate::core::Task {
             id: uuid::Uuid::new_v4(),
+            version: 0,
+            tenant_id: None,
             name: name.clone(),
             command: command.clone(),
             description: format!("Task: {}", name),
             technical_specs: None,
             acceptance_criteria: vec![],
             project: None,
-            task_type: crate::core::TaskType::Simple,
+            task_type: match interpreter {
+                Some(interpreter) => crate::core::TaskType::Script { interpreter },
+                None => crate::core::TaskType::Simple,
+            },
             priority,
-            project_id: Some(project_id_uuid),
+            project_id: Some(project_id),
             dependencies: vec![],
             timeout: None,
             retry_attempts: 3,
@@ -289,7 +648,10 @@ impl TaskQueueMcpServer {
                 started_at: Some(chrono::Utc::now()),
                 completed_at: None,
             }),
-            metadata: std::collections::HashMap::new(),
+            metadata: run_at
+                .map(|at| (crate::calendar::RUN_AT_KEY.to_string(), json!(at.to_rfc3339())))
+                .into_iter()
+                .collect(),
         };
 
         match self.task_queue.submit_task(task.clone()).await {
@@ -307,52 +669,46 @@ impl TaskQueueMcpServer {
         }
     }
 
-    async fn get_task(&self, task_id: String) -> Result<CallToolResult, String> {
-        match uuid::Uuid::parse_str(&task_id) {
-            Ok(id) => match self.task_queue.get_task(id).await {
-                Ok(task) => {
-                    let workflow_instructions = self.generate_workflow_instructions(&task);
-                    let effective_status = crate::server::TaskQueueServer::get_effective_task_status(&task);
-                    let task_info = format!(
-                        "Task: {}\nStatus: {:?}\nPriority: {:?}\nType: {:?}\n\n{}",
-                        task.name, effective_status, task.priority, task.task_type, workflow_instructions
-                    );
-                    Ok(CallToolResult::success(vec![
-                        Content::text(task_info),
-                    ]))
-                },
-                Err(e) => Err(format!("Failed to get task: {}", e)),
+    async fn get_task(&self, task_id: uuid::Uuid) -> Result<CallToolResult, String> {
+        match self.task_queue.get_task(task_id).await {
+            Ok(task) => {
+                let workflow_instructions = self.generate_workflow_instructions(&task);
+                let effective_status = crate::server::TaskQueueServer::get_effective_task_status(&task);
+                let task_info = format!(
+                    "Task: {}\nStatus: {:?}\nPriority: {:?}\nType: {:?}\n\n{}",
+                    task.name, effective_status, task.priority, task.task_type, workflow_instructions
+                );
+                Ok(CallToolResult::success(vec![
+                    Content::text(task_info),
+                ]))
             },
-            Err(_) => Err("Invalid task ID format".to_string()),
+            Err(e) => Err(format!("Failed to get task: {}", e)),
         }
     }
 
-    async fn list_tasks(&self, limit: Option<u32>) -> Result<CallToolResult, String> {
-        let _limit = limit.unwrap_or(50) as usize;
-        match self.task_queue.list_tasks(None, None).await {
-            Ok(tasks) => {
+    async fn list_tasks(&self, filter: crate::views::TaskFilter, limit: Option<u32>, cursor: Option<String>) -> Result<CallToolResult, String> {
+        let limit = limit.unwrap_or(50).clamp(1, 1000) as usize;
+        let page = crate::core::PageRequest { cursor, limit, ..Default::default() };
+        match self.task_queue.list_task_summaries_page(&filter, page).await {
+            Ok(crate::core::TaskPage { tasks, next_cursor, total }) => {
                 let content = if tasks.is_empty() {
                     "No tasks found".to_string()
                 } else {
-                    let mut result = format!("Found {} tasks:\n", tasks.len());
-                    for task in tasks.iter().take(10) {
-                        let effective_status = crate::server::TaskQueueServer::get_effective_task_status(task);
-                        let workflow_status = task.development_workflow
+                    let mut result = format!("Showing {} of {} matching tasks:\n", tasks.len(), total);
+                    for task in &tasks {
+                        let workflow_status = task.workflow_status
                             .as_ref()
-                            .map(|w| format!("{:?}", w.workflow_status))
+                            .map(|w| format!("{:?}", w))
                             .unwrap_or_else(|| "NotStarted".to_string());
-                        result.push_str(&format!("- {} ({}): Status={:?}, Workflow={}\n",
-                            task.name, task.id, effective_status, workflow_status));
-                    }
-                    if tasks.len() > 10 {
-                        result.push_str(&format!("... and {} more", tasks.len() - 10));
+                        result.push_str(&format!("- {} ({}): Status={:?}, Priority={:?}, Workflow={}\n",
+                            task.name, task.id, task.status, task.priority, workflow_status));
                     }
 
                     // Add workflow reminder for tasks that need attention
                     let incomplete_tasks = tasks.iter()
                         .filter(|t| {
-                            t.development_workflow.as_ref()
-                                .map(|w| w.workflow_status != crate::core::DevelopmentWorkflowStatus::Completed)
+                            t.workflow_status.as_ref()
+                                .map(|w| *w != crate::core::DevelopmentWorkflowStatus::Completed)
                                 .unwrap_or(true)
                         })
                         .count();
@@ -362,6 +718,11 @@ impl TaskQueueMcpServer {
                             incomplete_tasks));
                     }
 
+                    if let Some(next_cursor) = next_cursor {
+                        result.push_str(&format!("\nMore tasks available: call 'list_tasks' with cursor \"{}\" and the same filters for the next page.\n",
+                            next_cursor));
+                    }
+
                     result
                 };
                 Ok(CallToolResult::success(vec![
@@ -372,68 +733,22 @@ impl TaskQueueMcpServer {
         }
     }
 
-                async fn cancel_task(&self, task_id: String) -> Result<bool, String> {
-                    match uuid::Uuid::parse_str(&task_id) {
-                        Ok(id) => match self.task_queue.cancel_task(id, "Cancelled via MCP".to_string()).await {
-                            Ok(()) => Ok(true),
-                            Err(e) => Err(format!("Failed to cancel task: {}", e)),
-                        },
-                        Err(_) => Err("Invalid task ID format".to_string()),
+                async fn cancel_task(&self, task_id: uuid::Uuid) -> Result<bool, String> {
+                    match self.task_queue.cancel_task(task_id, "Cancelled via MCP".to_string()).await {
+                        Ok(()) => Ok(true),
+                        Err(e) => Err(format!("Failed to cancel task: {}", e)),
                     }
                 }
 
-                async fn delete_task(&self, task_id: String) -> Result<bool, String> {
-                    match uuid::Uuid::parse_str(&task_id) {
-                        Ok(id) => match self.task_queue.delete_task(id).await {
-                            Ok(()) => Ok(true),
-                            Err(e) => Err(format!("Failed to delete task: {}", e)),
-                        },
-                        Err(_) => Err("Invalid task ID format".to_string()),
+                async fn delete_task(&self, task_id: uuid::Uuid) -> Result<bool, String> {
+                    match self.task_queue.delete_task(task_id).await {
+                        Ok(()) => Ok(true),
+                        Err(e) => Err(format!("Failed to delete task: {}", e)),
                     }
                 }
 
-                async fn update_task(
-                    &self,
-                    task_id: String,
-                    name: Option<String>,
-                    command: Option<String>,
-                    description: Option<String>,
-                    priority: Option<String>,
-                    status: Option<String>,
-                    project_id: Option<String>,
-                ) -> Result<serde_json::Value, String> {
-                    let task_id_uuid = uuid::Uuid::parse_str(&task_id).map_err(|e| e.to_string())?;
-                    
-                    let priority_enum = if let Some(p) = priority {
-                        match p.as_str() {
-                            "Low" => Some(crate::core::TaskPriority::Low),
-                            "Normal" => Some(crate::core::TaskPriority::Normal),
-                            "High" => Some(crate::core::TaskPriority::High),
-                            "Critical" => Some(crate::core::TaskPriority::Critical),
-                            _ => None,
-                        }
-                    } else {
-                        None
-                    };
-
-                    let status_enum = if let Some(s) = status {
-                        match s.as_str() {
-                            "Planning" => Some(crate::core::TaskStatus::Planning),
-                            "Implementation" => Some(crate::core::TaskStatus::Implementation),
-                            "TestCreation" => Some(crate::core::TaskStatus::TestCreation),
-                            "Testing" => Some(crate::core::TaskStatus::Testing),
-                            "AIReview" => Some(crate::core::TaskStatus::AIReview),
-                            "Finalized" => Some(crate::core::TaskStatus::Finalized),
-                            "Pending" => Some(crate::core::TaskStatus::Pending),
-                            "Running" => Some(crate::core::TaskStatus::Running),
-                            "Completed" => Some(crate::core::TaskStatus::Completed),
-                            "Failed" => Some(crate::core::TaskStatus::Failed),
-                            "Cancelled" => Some(crate::core::TaskStatus::Cancelled),
-                            _ => None,
-                        }
-                    } else {
-                        None
-                    };
+                async fn update_task(&self, args: UpdateTaskArgs) -> Result<serde_json::Value, String> {
+                    let UpdateTaskArgs { task_id, name, command, description, priority, status, project_id } = args;
 
                     let project_id_uuid = if let Some(p) = project_id {
                         if p.is_empty() {
@@ -449,12 +764,12 @@ impl TaskQueueMcpServer {
                     };
 
                     match self.task_queue.update_task(
-                        task_id_uuid,
+                        task_id,
                         name,
                         command,
                         description,
-                        priority_enum,
-                        status_enum,
+                        priority,
+                        status,
                         project_id_uuid,
                     ).await {
                         Ok(task) => Ok(json!({
@@ -471,37 +786,15 @@ impl TaskQueueMcpServer {
                     }
                 }
 
-                async fn upsert_task(
-                    &self,
-                    name: String,
-                    command: String,
-                    description: String,
-                    project_id: String,
-                    priority: Option<String>,
-                    technical_specs: Option<String>,
-                    acceptance_criteria: Option<Vec<String>>,
-                ) -> Result<serde_json::Value, String> {
-                    let project_id_uuid = match uuid::Uuid::parse_str(&project_id) {
-                        Ok(id) => id,
-                        Err(_) => return Err("Invalid project ID format".to_string()),
-                    };
-
-                    let priority_enum = match priority.as_deref() {
-                        Some("Low") => crate::core::TaskPriority::Low,
-                        Some("Normal") => crate::core::TaskPriority::Normal,
-                        Some("High") => crate::core::TaskPriority::High,
-                        Some("Critical") => crate::core::TaskPriority::Critical,
-                        _ => crate::core::TaskPriority::Normal,
-                    };
-
+                async fn upsert_task(&self, args: UpsertTaskArgs) -> Result<serde_json::Value, String> {
                     match self.task_queue.upsert_task(
-                        name,
-                        command,
-                        description,
-                        project_id_uuid,
-                        priority_enum,
-                        technical_specs,
-                        acceptance_criteria,
+                        args.name,
+                        args.command,
+                        args.description,
+                        args.project_id,
+                        args.priority.unwrap_or(crate::core::TaskPriority::Normal),
+                        args.technical_specs,
+                        args.acceptance_criteria,
                     ).await {
                         Ok(task) => Ok(json!({
                             "id": task.id,
@@ -529,11 +822,10 @@ impl TaskQueueMcpServer {
                         .map_err(|e| e.to_string())
                 }
 
-                async fn get_project(&self, project_id: String) -> Result<serde_json::Value, String> {
-                    let project_id_uuid = uuid::Uuid::parse_str(&project_id).map_err(|e| e.to_string())?;
+                async fn get_project(&self, project_id: uuid::Uuid) -> Result<serde_json::Value, String> {
                     let project = self
                         .task_queue
-                        .get_project(&project_id_uuid)
+                        .get_project(&project_id)
                         .await
                         .map_err(|e| e.to_string())?;
                     match project {
@@ -574,11 +866,10 @@ impl TaskQueueMcpServer {
                     Ok(json!(project_list_json))
                 }
 
-                async fn get_project_tasks(&self, project_id: String) -> Result<serde_json::Value, String> {
-                    let project_id_uuid = uuid::Uuid::parse_str(&project_id).map_err(|e| e.to_string())?;
+                async fn get_project_tasks(&self, project_id: uuid::Uuid) -> Result<serde_json::Value, String> {
                     let tasks = self
                         .task_queue
-                        .get_tasks_by_project(&project_id_uuid)
+                        .get_tasks_by_project(&project_id)
                         .await
                         .map_err(|e| e.to_string())?;
 
@@ -597,300 +888,65 @@ impl TaskQueueMcpServer {
 
                     Ok(json!(task_list_json))
                 }
-}
 
-impl ServerHandler for TaskQueueMcpServer {
-    fn get_info(&self) -> ServerInfo {
-        ServerInfo {
-            protocol_version: ProtocolVersion::default(),
-            capabilities: ServerCapabilities::builder()
-                .enable_tools()
-                .build(),
-            server_info: Implementation {
-                name: "task-queue-mcp-server".to_string(),
-                title: Some("HiveLLM Task Queue MCP Server".to_string()),
-                version: env!("CARGO_PKG_VERSION").to_string(),
-                website_url: Some("https://github.com/hivellm/hivellm".to_string()),
-                icons: None,
-            },
-            instructions: Some("This is the HiveLLM Task Queue MCP Server - a high-performance task queue management system with comprehensive development workflow support. It provides capabilities for:\n\n📋 TASK MANAGEMENT: Submit, track, update, and manage tasks with priorities and dependencies. Each task follows a rigorous development workflow to ensure quality.\n\n🔄 DEVELOPMENT WORKFLOW: Automatic workflow enforcement through phases: Planning → Implementation → TestCreation → Testing → AIReview → Completed. Each phase has specific requirements and validations.\n\n🎯 PROJECT ORGANIZATION: Create and manage projects to organize related tasks. Track project status, tasks, and progress.\n\n🤖 AI REVIEW INTEGRATION: Built-in support for AI code reviews with multiple review types (CodeQuality, Security, Performance, Documentation, Testing, Architecture). Requires 3 AI model approvals before task completion.\n\n📊 QUALITY ASSURANCE: Enforced test coverage tracking, technical documentation requirements, and comprehensive acceptance criteria validation.\n\n⚡ PRIORITY MANAGEMENT: Support for task priorities (Low, Normal, High, Critical) with intelligent scheduling.\n\nAll operations are designed to enforce best practices and ensure high-quality deliverables.".to_string()),
+    /// Scope an API key needs to call a tool, as its annotations tell
+    fn tool_scope(name: &str) -> KeyScope {
+        let annotations = Self::tools().into_iter()
+            .find(|tool| tool.name == name)
+            .and_then(|tool| tool.annotations);
+        match annotations {
+            Some(annotations) if annotations.read_only_hint == Some(true) => KeyScope::Read,
+            Some(annotations) if annotations.destructive_hint == Some(true) => KeyScope::Delete,
+            _ => KeyScope::Write,
         }
     }
 
-    fn list_tools(
-        &self,
-        _request: Option<rmcp::model::PaginatedRequestParam>,
-        _context: RequestContext<RoleServer>,
-    ) -> impl std::future::Future<Output = Result<ListToolsResult, ErrorData>> + Send + '_ {
-        async move {
-            let tools = vec![
-                Tool {
-                    name: Cow::Borrowed("submit_task"),
-                    title: Some("Submit Task".to_string()),
-                    description: Some(Cow::Borrowed("Submit a new task to the queue with automatic workflow initialization. Creates a task that enters the Planning phase immediately. The task will be associated with a project and assigned a priority level. Returns the task ID and detailed workflow instructions for the Planning phase. IMPORTANT: Check the .tasks file in the project root before creating new tasks to avoid duplication. The .tasks file tracks all existing task IDs and should be consulted to prevent creating redundant tasks. Use this to create new development tasks that need to follow the complete development workflow with documentation, implementation, testing, and AI review phases.")),
-                    input_schema: json!({
-                        "type": "object",
-                        "properties": {
-                            "name": {"type": "string", "description": "Task name"},
-                            "command": {"type": "string", "description": "Command to execute"},
-                            "project_id": {"type": "string", "description": "Project ID to associate the task with"},
-                            "priority": {"type": "string", "enum": ["Low", "Normal", "High", "Critical"], "description": "Task priority", "default": "Normal"}
-                        },
-                        "required": ["name", "command", "project_id"]
-                    }).as_object().unwrap().clone().into(),
-                    output_schema: None,
-                    icons: None,
-                    annotations: Some(ToolAnnotations::new()
-                        .read_only(false)
-                        .destructive(false)
-                        .idempotent(false)
-                        .open_world(false)),
-                },
-                Tool {
-                    name: Cow::Borrowed("get_task"),
-                    title: Some("Get Task".to_string()),
-                    description: Some(Cow::Borrowed("Get detailed information about a specific task by its ID. Returns comprehensive task details including name, status, current workflow phase, priority, type, and dynamic workflow instructions based on the current phase. The workflow instructions provide specific guidance on what needs to be done next and what phase comes after. Essential for understanding task progress and next steps in the development workflow.")),
-                    input_schema: json!({
-                        "type": "object",
-                        "properties": {
-                            "task_id": {"type": "string", "description": "Task ID"}
-                        },
-                        "required": ["task_id"]
-                    }).as_object().unwrap().clone().into(),
-                    output_schema: None,
-                    icons: None,
-                    annotations: Some(ToolAnnotations::new()
-                        .read_only(true)
-                        .idempotent(true)
-                        .open_world(false)),
-                },
-                Tool {
-                    name: Cow::Borrowed("list_tasks"),
-                    title: Some("List Tasks".to_string()),
-                    description: Some(Cow::Borrowed("List all tasks in the queue with their current status and workflow state. Returns a summary of tasks including name, ID, current status, workflow status, and a count of incomplete tasks that require attention. Provides quick overview of all tasks and highlights those needing workflow completion. Use this to get an overall view of task queue state and identify tasks that need action.")),
-                    input_schema: json!({
-                        "type": "object",
-                        "properties": {
-                            "limit": {"type": "number", "description": "Maximum number of tasks to return", "default": 50}
-                        }
-                    }).as_object().unwrap().clone().into(),
-                    output_schema: None,
-                    icons: None,
-                    annotations: Some(ToolAnnotations::new()
-                        .read_only(true)
-                        .idempotent(true)
-                        .open_world(false)),
-                },
-                Tool {
-                        name: Cow::Borrowed("cancel_task"),
-                        title: Some("Cancel Task".to_string()),
-                        description: Some(Cow::Borrowed("Cancel a task by its ID, preventing further execution. This operation marks the task as cancelled and stops any ongoing or scheduled execution. Useful when a task is no longer needed, was submitted in error, or requirements have changed. Returns success status indicating whether the task was successfully cancelled. Cannot cancel already completed tasks.")),
-                        input_schema: json!({
-                            "type": "object",
-                            "properties": {
-                                "task_id": {"type": "string", "description": "Task ID to cancel"}
-                            },
-                            "required": ["task_id"]
-                        }).as_object().unwrap().clone().into(),
-                        output_schema: None,
-                        icons: None,
-                        annotations: Some(ToolAnnotations::new()
-                            .read_only(false)
-                            .destructive(false)
-                            .idempotent(true)
-                            .open_world(false)),
-                },
-                Tool {
-                    name: Cow::Borrowed("delete_task"),
-                    title: Some("Delete Task".to_string()),
-                    description: Some(Cow::Borrowed("Permanently delete a task from the queue by its ID. This operation is irreversible and removes all task data, including history, workflow status, and review reports. Use with caution as this cannot be undone. Only use this for cleanup of obsolete tasks or tasks that were created in error. Returns success status confirming deletion. For tasks that should be preserved for audit purposes, use cancel_task instead.")),
-                    input_schema: json!({
-                        "type": "object",
-                        "properties": {
-                            "task_id": {"type": "string", "description": "Task ID to delete"}
-                        },
-                        "required": ["task_id"]
-                    }).as_object().unwrap().clone().into(),
-                    output_schema: None,
-                    icons: None,
-                    annotations: Some(ToolAnnotations::new()
-                        .read_only(false)
-                        .destructive(true)
-                        .idempotent(true)
-                        .open_world(false)),
-                },
-                Tool {
-                    name: Cow::Borrowed("update_task"),
-                    title: Some("Update Task".to_string()),
-                    description: Some(Cow::Borrowed("Update an existing task's properties including name, command, description, priority, status, or project association. Allows partial updates - only specified fields will be changed. Can be used to change task priority, update descriptions, modify commands, or reassign to different projects. When updating status, ensure it follows the proper workflow sequence. Returns updated task information including all current properties and timestamps.")),
-                    input_schema: json!({
-                        "type": "object",
-                        "properties": {
-                            "task_id": {"type": "string", "description": "Task ID to update"},
-                            "name": {"type": "string", "description": "New task name"},
-                            "command": {"type": "string", "description": "New command"},
-                            "description": {"type": "string", "description": "New description"},
-                            "priority": {"type": "string", "enum": ["Low", "Normal", "High", "Critical"], "description": "New priority"},
-                            "status": {"type": "string", "enum": ["Planning", "Implementation", "TestCreation", "Testing", "AIReview", "Finalized", "Pending", "Running", "Completed", "Failed", "Cancelled"], "description": "New status"},
-                            "project_id": {"type": "string", "description": "Project ID to associate with task (empty string to remove association)"}
-                        },
-                        "required": ["task_id"]
-                    }).as_object().unwrap().clone().into(),
-                    output_schema: None,
-                    icons: None,
-                    annotations: Some(ToolAnnotations::new()
-                        .read_only(false)
-                        .destructive(false)
-                        .idempotent(false)
-                        .open_world(false)),
-                },
-                Tool {
-                    name: Cow::Borrowed("upsert_task"),
-                    title: Some("Upsert Task".to_string()),
-                    description: Some(Cow::Borrowed("Create a new task or update an existing one by name (insert or update). If a task with the given name exists, it will be updated; otherwise, a new task is created. This is useful for maintaining tasks that should be unique by name. Supports setting technical specifications and acceptance criteria upfront. Returns the task information indicating whether it was created or updated. Ideal for idempotent task submission where you want to ensure a task exists with specific properties.")),
-                    input_schema: json!({
-                        "type": "object",
-                        "properties": {
-                            "name": {"type": "string", "description": "Task name (used for lookup)"},
-                            "command": {"type": "string", "description": "Command to execute"},
-                            "description": {"type": "string", "description": "Task description"},
-                            "project_id": {"type": "string", "description": "Project ID to associate the task with"},
-                            "priority": {"type": "string", "enum": ["Low", "Normal", "High", "Critical"], "description": "Task priority"},
-                            "technical_specs": {"type": "string", "description": "Technical specifications"},
-                            "acceptance_criteria": {"type": "array", "items": {"type": "string"}, "description": "Acceptance criteria"}
-                        },
-                        "required": ["name", "command", "description", "project_id"]
-                    }).as_object().unwrap().clone().into(),
-                    output_schema: None,
-                    icons: None,
-                    annotations: Some(ToolAnnotations::new()
-                        .read_only(false)
-                        .destructive(false)
-                        .idempotent(true)
-                        .open_world(false)),
-                },
-                Tool {
-                    name: Cow::Borrowed("create_project"),
-                    title: Some("Create Project".to_string()),
-                    description: Some(Cow::Borrowed("Create a new project to organize and group related tasks. Projects serve as containers for tasks that belong to the same initiative, feature, or module. Returns the project ID which can be used when creating tasks. Projects help with task organization, progress tracking, and reporting. IMPORTANT: This will create a .tasks file in the project root to track all task IDs. AI models should check existing .tasks files before creating new projects to avoid duplication.")),
-                    input_schema: json!({
-                        "type": "object",
-                        "properties": {
-                            "name": {"type": "string", "description": "Project name"},
-                            "description": {"type": "string", "description": "Project description"}
-                        },
-                        "required": ["name"]
-                    }).as_object().unwrap().clone().into(),
-                    output_schema: None,
-                    icons: None,
-                    annotations: Some(ToolAnnotations::new()
-                        .read_only(false)
-                        .destructive(false)
-                        .idempotent(false)
-                        .open_world(false)),
-                },
-                Tool {
-                    name: Cow::Borrowed("get_project"),
-                    title: Some("Get Project".to_string()),
-                    description: Some(Cow::Borrowed("Get detailed information about a specific project by its ID. Returns comprehensive project details including name, description, status, creation and update timestamps, due dates, tags, and custom metadata. Use this to retrieve project information, check project status, or get project metadata before creating or querying tasks.")),
-                    input_schema: json!({
-                        "type": "object",
-                        "properties": {
-                            "project_id": {"type": "string", "description": "Project ID"}
-                        },
-                        "required": ["project_id"]
-                    }).as_object().unwrap().clone().into(),
-                    output_schema: None,
-                    icons: None,
-                    annotations: Some(ToolAnnotations::new()
-                        .read_only(true)
-                        .idempotent(true)
-                        .open_world(false)),
-                },
-                Tool {
-                    name: Cow::Borrowed("list_projects"),
-                    title: Some("List Projects".to_string()),
-                    description: Some(Cow::Borrowed("List all projects in the system. Returns a summary of all projects including their ID, name, status, and creation timestamp. Use this to discover available projects, find project IDs for task creation, or get an overview of all active projects in the system. Essential for project discovery and selection.")),
-                    input_schema: json!({
-                        "type": "object",
-                        "properties": {}
-                    }).as_object().unwrap().clone().into(),
-                    output_schema: None,
-                    icons: None,
-                    annotations: Some(ToolAnnotations::new()
-                        .read_only(true)
-                        .idempotent(true)
-                        .open_world(false)),
-                },
-                Tool {
-                    name: Cow::Borrowed("get_project_tasks"),
-                    title: Some("Get Project Tasks".to_string()),
-                    description: Some(Cow::Borrowed("Get all tasks associated with a specific project. Returns a list of tasks belonging to the specified project, including task ID, name, status, current workflow phase, and priority. Useful for viewing all tasks within a project, tracking project progress, or identifying tasks that need attention. Provides quick overview of project workload and status distribution.")),
-                    input_schema: json!({
-                        "type": "object",
-                        "properties": {
-                            "project_id": {"type": "string", "description": "Project ID"}
-                        },
-                        "required": ["project_id"]
-                    }).as_object().unwrap().clone().into(),
-                    output_schema: None,
-                    icons: None,
-                    annotations: Some(ToolAnnotations::new()
-                        .read_only(true)
-                        .idempotent(true)
-                        .open_world(false)),
-                },
-                Tool {
-                    name: Cow::Borrowed("advance_workflow_phase"),
-                    title: Some("Advance Workflow Phase".to_string()),
-                    description: Some(Cow::Borrowed("Advance a task to the next development workflow phase. The workflow follows this sequence: NotStarted → Planning → Implementation → TestCreation → Testing → AIReview → Completed. Each phase transition is validated to ensure requirements are met. Returns the new workflow status and detailed instructions for the next phase. CRITICAL: Use this tool only when current phase requirements are fully satisfied (e.g., documentation complete for Planning, all tests passing for Testing).")),
-                    input_schema: json!({
-                        "type": "object",
-                        "properties": {
-                            "task_id": {"type": "string", "description": "Task ID to advance"}
-                        },
-                        "required": ["task_id"]
-                    }).as_object().unwrap().clone().into(),
-                    output_schema: None,
-                    icons: None,
-                    annotations: Some(ToolAnnotations::new()
-                        .read_only(false)
-                        .destructive(false)
-                        .idempotent(false)
-                        .open_world(false)),
-                },
-                Tool {
-                    name: Cow::Borrowed("set_technical_documentation"),
-                    title: Some("Set Technical Documentation".to_string()),
-                    description: Some(Cow::Borrowed("Set the technical documentation path for a task in the Planning phase. This documents where the technical specifications, architecture decisions, and implementation details are stored. Required before advancing from Planning to Implementation phase. The documentation should include all implementation details, API contracts, data structures, and architectural decisions. Use this when documentation is complete and ready for implementation.")),
-                    input_schema: json!({
-                        "type": "object",
-                        "properties": {
-                            "task_id": {"type": "string", "description": "Task ID"},
-                            "doc_path": {"type": "string", "description": "Path to technical documentation"}
-                        },
-                        "required": ["task_id", "doc_path"]
-                    }).as_object().unwrap().clone().into(),
-                    output_schema: None,
-                    icons: None,
-                    annotations: Some(ToolAnnotations::new()
-                        .read_only(false)
-                        .destructive(false)
-                        .idempotent(true)
-                        .open_world(false)),
-                },
-                Tool {
-                    name: Cow::Borrowed("set_test_coverage"),
-                    title: Some("Set Test Coverage".to_string()),
-                    description: Some(Cow::Borrowed("Set the test coverage percentage for a task in the Testing phase. Coverage value should be between 0.0 and 1.0 (0% to 100%). This documents the actual test coverage achieved after running tests. Minimum 85% coverage is typically required before advancing to AIReview phase. Use this after executing tests and calculating coverage to record the quality metrics. Essential for tracking testing completeness and quality standards.")),
-                    input_schema: json!({
-                        "type": "object",
-                        "properties": {
-                            "task_id": {"type": "string", "description": "Task ID"},
-                            "coverage": {"type": "number", "description": "Test coverage percentage (0.0-1.0)", "minimum": 0.0, "maximum": 1.0}
-                        },
-                        "required": ["task_id", "coverage"]
-                    }).as_object().unwrap().clone().into(),
+    /// The tools offered, with input schemas derived from the types their
+    /// arguments are parsed into
+    pub fn tools() -> Vec<Tool> {
+        let mut tools = vec![
+            Tool {
+                name: Cow::Borrowed("submit_task"),
+                title: Some("Submit Task".to_string()),
+                description: Some(Cow::Borrowed("Submit a new task to the queue with automatic workflow initialization. Creates a task that enters the Planning phase immediately. The task will be associated with a project and assigned a priority level. Returns the task ID and detailed workflow instructions for the Planning phase. IMPORTANT: List the project's existing tasks, or look for similar ones with search_tasks, before creating new ones to avoid duplication. Use this to create new development tasks that need to follow the complete development workflow with documentation, implementation, testing, and AI review phases.")),
+                input_schema: cached_schema_for_type::<SubmitTaskArgs>(),
+                output_schema: None,
+                icons: None,
+                annotations: Some(ToolAnnotations::new()
+                    .read_only(false)
+                    .destructive(false)
+                    .idempotent(false)
+                    .open_world(false)),
+            },
+            Tool {
+                name: Cow::Borrowed("get_task"),
+                title: Some("Get Task".to_string()),
+                description: Some(Cow::Borrowed("Get detailed information about a specific task by its ID. Returns comprehensive task details including name, status, current workflow phase, priority, type, and dynamic workflow instructions based on the current phase. The workflow instructions provide specific guidance on what needs to be done next and what phase comes after. Essential for understanding task progress and next steps in the development workflow.")),
+                input_schema: cached_schema_for_type::<TaskIdArgs>(),
+                output_schema: None,
+                icons: None,
+                annotations: Some(ToolAnnotations::new()
+                    .read_only(true)
+                    .idempotent(true)
+                    .open_world(false)),
+            },
+            Tool {
+                name: Cow::Borrowed("list_tasks"),
+                title: Some("List Tasks".to_string()),
+                description: Some(Cow::Borrowed("List the tasks in the queue, one page at a time, with their current status and workflow state, optionally only those with a status, priority or project. Returns a summary of every task on the page including name, ID, current status, priority and workflow status, the number of tasks matching, a count of incomplete tasks that require attention, and a cursor for the next page while there are more. Pass the cursor back with the same filters to enumerate all matching tasks. Use this to get an overall view of task queue state and identify tasks that need action.")),
+                input_schema: cached_schema_for_type::<ListTasksArgs>(),
+                output_schema: None,
+                icons: None,
+                annotations: Some(ToolAnnotations::new()
+                    .read_only(true)
+                    .idempotent(true)
+                    .open_world(false)),
+            },
+            Tool {
+                    name: Cow::Borrowed("cancel_task"),
+                    title: Some("Cancel Task".to_string()),
+                    description: Some(Cow::Borrowed("Cancel a task by its ID, preventing further execution. This operation marks the task as cancelled and stops any ongoing or scheduled execution. Useful when a task is no longer needed, was submitted in error, or requirements have changed. Returns success status indicating whether the task was successfully cancelled. Cannot cancel already completed tasks.")),
+                    input_schema: cached_schema_for_type::<TaskIdArgs>(),
                     output_schema: None,
                     icons: None,
                     annotations: Some(ToolAnnotations::new()
@@ -898,72 +954,309 @@ impl ServerHandler for TaskQueueMcpServer {
                         .destructive(false)
                         .idempotent(true)
                         .open_world(false)),
-                },
-                Tool {
-                    name: Cow::Borrowed("add_ai_review_report"),
-                    title: Some("Add AI Review Report".to_string()),
-                    description: Some(Cow::Borrowed("Add an AI code review report for a task in the AIReview phase. Supports multiple review types: CodeQuality (code structure and best practices), Security (security vulnerabilities and risks), Performance (performance bottlenecks and optimizations), Documentation (documentation completeness), Testing (test coverage and quality), and Architecture (architectural decisions and patterns). Each review requires a score (0.0-1.0), approval status, detailed content, and optional suggestions. Tasks require 3 AI model approvals before completion. Use this to record AI model reviews and track quality assurance progress.")),
-                    input_schema: json!({
-                        "type": "object",
-                        "properties": {
-                            "task_id": {"type": "string", "description": "Task ID"},
-                            "model_name": {"type": "string", "description": "AI model name"},
-                            "review_type": {"type": "string", "enum": ["CodeQuality", "Security", "Performance", "Documentation", "Testing", "Architecture"], "description": "Type of review"},
-                            "content": {"type": "string", "description": "Review content"},
-                            "score": {"type": "number", "description": "Review score (0.0-1.0)", "minimum": 0.0, "maximum": 1.0},
-                            "approved": {"type": "boolean", "description": "Whether the code is approved"},
-                            "suggestions": {"type": "array", "items": {"type": "string"}, "description": "List of suggestions"}
-                        },
-                        "required": ["task_id", "model_name", "review_type", "content", "score", "approved"]
-                    }).as_object().unwrap().clone().into(),
-                    output_schema: None,
-                    icons: None,
-                    annotations: Some(ToolAnnotations::new()
-                        .read_only(false)
-                        .destructive(false)
-                        .idempotent(false)
-                        .open_world(false)),
-                },
-            ];
-
-            Ok(ListToolsResult { 
-                tools,
-                next_cursor: None,
-            })
+            },
+            Tool {
+                name: Cow::Borrowed("delete_task"),
+                title: Some("Delete Task".to_string()),
+                description: Some(Cow::Borrowed("Permanently delete a task from the queue by its ID. This operation is irreversible and removes all task data, including history, workflow status, and review reports. Use with caution as this cannot be undone. Only use this for cleanup of obsolete tasks or tasks that were created in error. Returns success status confirming deletion. For tasks that should be preserved for audit purposes, use cancel_task instead.")),
+                input_schema: cached_schema_for_type::<TaskIdArgs>(),
+                output_schema: None,
+                icons: None,
+                annotations: Some(ToolAnnotations::new()
+                    .read_only(false)
+                    .destructive(true)
+                    .idempotent(true)
+                    .open_world(false)),
+            },
+            Tool {
+                name: Cow::Borrowed("update_task"),
+                title: Some("Update Task".to_string()),
+                description: Some(Cow::Borrowed("Update an existing task's properties including name, command, description, priority, status, or project association. Allows partial updates - only specified fields will be changed. Can be used to change task priority, update descriptions, modify commands, or reassign to different projects. When updating status, ensure it follows the proper workflow sequence. Returns updated task information including all current properties and timestamps.")),
+                input_schema: cached_schema_for_type::<UpdateTaskArgs>(),
+                output_schema: None,
+                icons: None,
+                annotations: Some(ToolAnnotations::new()
+                    .read_only(false)
+                    .destructive(false)
+                    .idempotent(false)
+                    .open_world(false)),
+            },
+            Tool {
+                name: Cow::Borrowed("upsert_task"),
+                title: Some("Upsert Task".to_string()),
+                description: Some(Cow::Borrowed("Create a new task or update an existing one by name (insert or update). If a task with the given name exists, it will be updated; otherwise, a new task is created. This is useful for maintaining tasks that should be unique by name. Supports setting technical specifications and acceptance criteria upfront. Returns the task information indicating whether it was created or updated. Ideal for idempotent task submission where you want to ensure a task exists with specific properties.")),
+                input_schema: cached_schema_for_type::<UpsertTaskArgs>(),
+                output_schema: None,
+                icons: None,
+                annotations: Some(ToolAnnotations::new()
+                    .read_only(false)
+                    .destructive(false)
+                    .idempotent(true)
+                    .open_world(false)),
+            },
+            Tool {
+                name: Cow::Borrowed("get_next_task"),
+                title: Some("Get Next Task".to_string()),
+                description: Some(Cow::Borrowed("Claim the highest-priority runnable task for this agent, optionally only from one project or among tasks carrying all the given labels. The task is marked Running and leased to the agent, so no other agent or worker gets it. Send heartbeats to /tasks/{id}/lease/heartbeat and report the outcome to /tasks/{id}/lease/complete with the agent_id as worker_id before the lease expires, or the task goes back to the queue. Returns no task when nothing is runnable.")),
+                input_schema: cached_schema_for_type::<GetNextTaskArgs>(),
+                output_schema: None,
+                icons: None,
+                annotations: Some(ToolAnnotations::new()
+                    .read_only(false)
+                    .destructive(false)
+                    .idempotent(false)
+                    .open_world(false)),
+            },
+            Tool {
+                name: Cow::Borrowed("add_task_dependency"),
+                title: Some("Add Task Dependency".to_string()),
+                description: Some(Cow::Borrowed("Make a task wait for another one. With the Success condition (default) the task runs only after the dependency succeeds, with Failure only after it fails, and with Completion once it finishes either way; with Group, dependency_task_id names a task group and the task runs once every member succeeded, or quorum of them. Optional dependencies (required: false) don't hold the task back. Dependencies sharing a correlation_id are tracked together. Returns the task's dependencies.")),
+                input_schema: cached_schema_for_type::<AddTaskDependencyArgs>(),
+                output_schema: None,
+                icons: None,
+                annotations: Some(ToolAnnotations::new()
+                    .read_only(false)
+                    .destructive(false)
+                    .idempotent(false)
+                    .open_world(false)),
+            },
+            Tool {
+                name: Cow::Borrowed("add_artifact"),
+                title: Some("Add Artifact".to_string()),
+                description: Some(Cow::Borrowed("Record a file path or URL produced while working on a task, such as a design doc, a diff or a test report, on the task's current phase. Returns the phase and its artifacts.")),
+                input_schema: cached_schema_for_type::<AddArtifactArgs>(),
+                output_schema: None,
+                icons: None,
+                annotations: Some(ToolAnnotations::new()
+                    .read_only(false)
+                    .destructive(false)
+                    .idempotent(false)
+                    .open_world(false)),
+            },
+            Tool {
+                name: Cow::Borrowed("report_progress"),
+                title: Some("Report Progress".to_string()),
+                description: Some(Cow::Borrowed("Report how far along a task is, with a short note on what is being done, so long phases show progress instead of looking stuck. The task keeps the latest report and each one is broadcast as a task_progress event.")),
+                input_schema: cached_schema_for_type::<ReportProgressArgs>(),
+                output_schema: None,
+                icons: None,
+                annotations: Some(ToolAnnotations::new()
+                    .read_only(false)
+                    .destructive(false)
+                    .idempotent(false)
+                    .open_world(false)),
+            },
+            Tool {
+                name: Cow::Borrowed("get_task_dependencies"),
+                title: Some("Get Task Dependencies".to_string()),
+                description: Some(Cow::Borrowed("List the tasks a task waits for, with each dependency's condition, whether it is required and its correlation ID.")),
+                input_schema: cached_schema_for_type::<TaskIdArgs>(),
+                output_schema: None,
+                icons: None,
+                annotations: Some(ToolAnnotations::new()
+                    .read_only(true)
+                    .idempotent(true)
+                    .open_world(false)),
+            },
+            Tool {
+                name: Cow::Borrowed("create_project"),
+                title: Some("Create Project".to_string()),
+                description: Some(Cow::Borrowed("Create a new project to organize and group related tasks. Projects serve as containers for tasks that belong to the same initiative, feature, or module. Returns the project ID which can be used when creating tasks. Projects help with task organization, progress tracking, and reporting. IMPORTANT: AI models should list existing projects before creating new ones to avoid duplication.")),
+                input_schema: cached_schema_for_type::<CreateProjectArgs>(),
+                output_schema: None,
+                icons: None,
+                annotations: Some(ToolAnnotations::new()
+                    .read_only(false)
+                    .destructive(false)
+                    .idempotent(false)
+                    .open_world(false)),
+            },
+            Tool {
+                name: Cow::Borrowed("get_project"),
+                title: Some("Get Project".to_string()),
+                description: Some(Cow::Borrowed("Get detailed information about a specific project by its ID. Returns comprehensive project details including name, description, status, creation and update timestamps, due dates, tags, and custom metadata. Use this to retrieve project information, check project status, or get project metadata before creating or querying tasks.")),
+                input_schema: cached_schema_for_type::<ProjectIdArgs>(),
+                output_schema: None,
+                icons: None,
+                annotations: Some(ToolAnnotations::new()
+                    .read_only(true)
+                    .idempotent(true)
+                    .open_world(false)),
+            },
+            Tool {
+                name: Cow::Borrowed("list_projects"),
+                title: Some("List Projects".to_string()),
+                description: Some(Cow::Borrowed("List all projects in the system. Returns a summary of all projects including their ID, name, status, and creation timestamp. Use this to discover available projects, find project IDs for task creation, or get an overview of all active projects in the system. Essential for project discovery and selection.")),
+                input_schema: cached_schema_for_type::<NoArgs>(),
+                output_schema: None,
+                icons: None,
+                annotations: Some(ToolAnnotations::new()
+                    .read_only(true)
+                    .idempotent(true)
+                    .open_world(false)),
+            },
+            Tool {
+                name: Cow::Borrowed("get_project_tasks"),
+                title: Some("Get Project Tasks".to_string()),
+                description: Some(Cow::Borrowed("Get all tasks associated with a specific project. Returns a list of tasks belonging to the specified project, including task ID, name, status, current workflow phase, and priority. Useful for viewing all tasks within a project, tracking project progress, or identifying tasks that need attention. Provides quick overview of project workload and status distribution.")),
+                input_schema: cached_schema_for_type::<ProjectIdArgs>(),
+                output_schema: None,
+                icons: None,
+                annotations: Some(ToolAnnotations::new()
+                    .read_only(true)
+                    .idempotent(true)
+                    .open_world(false)),
+            },
+            Tool {
+                name: Cow::Borrowed("advance_workflow_phase"),
+                title: Some("Advance Workflow Phase".to_string()),
+                description: Some(Cow::Borrowed("Advance a task to the next development workflow phase. The workflow follows this sequence: NotStarted → Planning → Implementation → TestCreation → Testing → AIReview → Completed. Each phase transition is validated to ensure requirements are met. Returns the new workflow status and detailed instructions for the next phase. CRITICAL: Use this tool only when current phase requirements are fully satisfied (e.g., documentation complete for Planning, all tests passing for Testing).")),
+                input_schema: cached_schema_for_type::<TaskIdArgs>(),
+                output_schema: None,
+                icons: None,
+                annotations: Some(ToolAnnotations::new()
+                    .read_only(false)
+                    .destructive(false)
+                    .idempotent(false)
+                    .open_world(false)),
+            },
+            Tool {
+                name: Cow::Borrowed("set_technical_documentation"),
+                title: Some("Set Technical Documentation".to_string()),
+                description: Some(Cow::Borrowed("Set the technical documentation path for a task in the Planning phase. This documents where the technical specifications, architecture decisions, and implementation details are stored. Required before advancing from Planning to Implementation phase. The documentation should include all implementation details, API contracts, data structures, and architectural decisions. Use this when documentation is complete and ready for implementation.")),
+                input_schema: cached_schema_for_type::<SetTechnicalDocumentationArgs>(),
+                output_schema: None,
+                icons: None,
+                annotations: Some(ToolAnnotations::new()
+                    .read_only(false)
+                    .destructive(false)
+                    .idempotent(true)
+                    .open_world(false)),
+            },
+            Tool {
+                name: Cow::Borrowed("set_test_coverage"),
+                title: Some("Set Test Coverage".to_string()),
+                description: Some(Cow::Borrowed("Set the test coverage percentage for a task in the Testing phase. Coverage value should be between 0.0 and 1.0 (0% to 100%). This documents the actual test coverage achieved after running tests. Minimum 85% coverage is typically required before advancing to AIReview phase. Use this after executing tests and calculating coverage to record the quality metrics. Essential for tracking testing completeness and quality standards.")),
+                input_schema: cached_schema_for_type::<SetTestCoverageArgs>(),
+                output_schema: None,
+                icons: None,
+                annotations: Some(ToolAnnotations::new()
+                    .read_only(false)
+                    .destructive(false)
+                    .idempotent(true)
+                    .open_world(false)),
+            },
+            Tool {
+                name: Cow::Borrowed("add_ai_review_report"),
+                title: Some("Add AI Review Report".to_string()),
+                description: Some(Cow::Borrowed("Add an AI code review report for a task in the AIReview phase. Supports multiple review types: CodeQuality (code structure and best practices), Security (security vulnerabilities and risks), Performance (performance bottlenecks and optimizations), Documentation (documentation completeness), Testing (test coverage and quality), and Architecture (architectural decisions and patterns). Each review requires a score (0.0-1.0), approval status, detailed content, and optional suggestions. Tasks require 3 AI model approvals before completion. Use this to record AI model reviews and track quality assurance progress.")),
+                input_schema: cached_schema_for_type::<AddAiReviewReportArgs>(),
+                output_schema: None,
+                icons: None,
+                annotations: Some(ToolAnnotations::new()
+                    .read_only(false)
+                    .destructive(false)
+                    .idempotent(false)
+                    .open_world(false)),
+            },
+            Tool {
+                name: Cow::Borrowed("search_tasks"),
+                title: Some("Search Similar Tasks".to_string()),
+                description: Some(Cow::Borrowed("Find existing tasks similar to a natural-language description, most similar first, each with a similarity score between 0 and 1. Use this before submit_task to check for prior art and avoid creating duplicates.")),
+                input_schema: cached_schema_for_type::<SearchTasksArgs>(),
+                output_schema: None,
+                icons: None,
+                annotations: Some(ToolAnnotations::new()
+                    .read_only(true)
+                    .idempotent(true)
+                    .open_world(true)),
+            },
+            Tool {
+                name: Cow::Borrowed("list_views"),
+                title: Some("List Saved Views".to_string()),
+                description: Some(Cow::Borrowed("List the saved views: named task filters and sort orders shared with the dashboard and CLI, such as \"my overdue criticals\". Use list_view_tasks to see the tasks a view shows.")),
+                input_schema: cached_schema_for_type::<NoArgs>(),
+                output_schema: None,
+                icons: None,
+                annotations: Some(ToolAnnotations::new()
+                    .read_only(true)
+                    .idempotent(true)
+                    .open_world(false)),
+            },
+            Tool {
+                name: Cow::Borrowed("list_view_tasks"),
+                title: Some("List View Tasks".to_string()),
+                description: Some(Cow::Borrowed("List the tasks shown by a saved view, filtered and sorted as the view defines.")),
+                input_schema: cached_schema_for_type::<ViewNameArgs>(),
+                output_schema: None,
+                icons: None,
+                annotations: Some(ToolAnnotations::new()
+                    .read_only(true)
+                    .idempotent(true)
+                    .open_world(false)),
+            },
+            Tool {
+                name: Cow::Borrowed("save_view"),
+                title: Some("Save View".to_string()),
+                description: Some(Cow::Borrowed("Create or replace a saved view. The filter matches tasks on project, statuses (planning, pending, running, completed, failed, cancelled, implementation, testcreation, testing, aireview), priorities, overdue, name_contains and metadata entries; every field given must match.")),
+                input_schema: cached_schema_for_type::<SaveViewArgs>(),
+                output_schema: None,
+                icons: None,
+                annotations: Some(ToolAnnotations::new()
+                    .read_only(false)
+                    .destructive(false)
+                    .idempotent(true)
+                    .open_world(false)),
+            },
+            Tool {
+                name: Cow::Borrowed("register_agent"),
+                title: Some("Register Agent".to_string()),
+                description: Some(Cow::Borrowed("Register this agent with the task queue, or update its registration if an agent with the same name exists. Returns an agent_id; pass it as the agent_id argument of any other tool so changes are attributed to you and show up in the agent's activity stats.")),
+                input_schema: cached_schema_for_type::<crate::agents::RegisterAgent>(),
+                output_schema: None,
+                icons: None,
+                annotations: Some(ToolAnnotations::new()
+                    .read_only(false)
+                    .destructive(false)
+                    .idempotent(true)
+                    .open_world(false)),
+            },
+            Tool {
+                name: Cow::Borrowed("agent_heartbeat"),
+                title: Some("Agent Heartbeat".to_string()),
+                description: Some(Cow::Borrowed("Tell the task queue this agent is still alive. Agents silent for more than 2 minutes are reported as idle, and after 15 minutes as offline.")),
+                input_schema: cached_schema_for_type::<AgentIdArgs>(),
+                output_schema: None,
+                icons: None,
+                annotations: Some(ToolAnnotations::new()
+                    .read_only(false)
+                    .destructive(false)
+                    .idempotent(true)
+                    .open_world(false)),
+            },
+        ];
+
+        // Every tool accepts the calling agent's ID for attribution
+        for tool in tools.iter_mut() {
+            let schema = Arc::make_mut(&mut tool.input_schema);
+            if let Some(properties) = schema.entry("properties").or_insert_with(|| json!({})).as_object_mut() {
+                properties.entry("agent_id").or_insert_with(|| json!({
+                    "type": "string",
+                    "format": "uuid",
+                    "description": "ID of the calling agent, from register_agent"
+                }));
+            }
         }
+        tools
     }
 
-    fn call_tool(
-        &self,
-        request: rmcp::model::CallToolRequestParam,
-        _context: RequestContext<RoleServer>,
-    ) -> impl std::future::Future<Output = Result<CallToolResult, ErrorData>> + Send + '_ {
-        async move {
-            match request.name.as_ref() {
+    /// Run a tool call; `call_tool` wraps this to attribute it to the calling agent
+    async fn dispatch_tool(&self, request: rmcp::model::CallToolRequestParam) -> Result<CallToolResult, ErrorData> {
+        match request.name.as_ref() {
                 "submit_task" => {
-                    let args = request
-                        .arguments
-                        .as_ref()
-                        .ok_or_else(|| ErrorData::invalid_params("Missing arguments", None))?;
-
-                    let name = args
-                        .get("name")
-                        .and_then(|n| n.as_str())
-                        .ok_or_else(|| ErrorData::invalid_params("Missing name parameter", None))?;
-
-                    let command = args
-                        .get("command")
-                        .and_then(|c| c.as_str())
-                        .ok_or_else(|| ErrorData::invalid_params("Missing command parameter", None))?;
-
-                    let project_id = args
-                        .get("project_id")
-                        .and_then(|p| p.as_str())
-                        .ok_or_else(|| ErrorData::invalid_params("Missing project_id parameter", None))?;
+                    let args: SubmitTaskArgs = parse_args(&request)?;
 
-                    let priority = args.get("priority").and_then(|p| p.as_str()).map(|s| s.to_string());
-
-                    match self.submit_task(name.to_string(), command.to_string(), project_id.to_string(), priority).await {
+                    match self.submit_task(args.name, args.command, args.project_id, args.priority, args.run_at, args.interpreter).await {
                         Ok(result) => {
                             let result_text = json!({
                                 "task_id": result,
@@ -982,17 +1275,9 @@ impl ServerHandler for TaskQueueMcpServer {
                     }
                 },
                 "get_task" => {
-                    let args = request
-                        .arguments
-                        .as_ref()
-                        .ok_or_else(|| ErrorData::invalid_params("Missing arguments", None))?;
-
-                    let task_id = args
-                        .get("task_id")
-                        .and_then(|t| t.as_str())
-                        .ok_or_else(|| ErrorData::invalid_params("Missing task_id parameter", None))?;
+                    let TaskIdArgs { task_id } = parse_args(&request)?;
 
-                    match self.get_task(task_id.to_string()).await {
+                    match self.get_task(task_id).await {
                         Ok(result) => {
                             let result_text = json!({
                                 "task": result,
@@ -1010,13 +1295,21 @@ impl ServerHandler for TaskQueueMcpServer {
                     }
                 },
                 "list_tasks" => {
-                    let args = request.arguments.as_ref();
-                    let limit = args
-                        .and_then(|a| a.get("limit"))
-                        .and_then(|l| l.as_u64())
-                        .map(|l| l as u32);
+                    let args: ListTasksArgs = parse_args(&request)?;
+
+                    // Filters read like the REST list query: a value or a list of any to match
+                    let filter = crate::views::TaskFilter {
+                        project_id: args.project_id,
+                        statuses: args.status.map(OneOrMany::into_vec).unwrap_or_default()
+                            .iter()
+                            .map(|status| status.to_lowercase())
+                            .collect(),
+                        priorities: args.priority.map(OneOrMany::into_vec).unwrap_or_default(),
+                        ..Default::default()
+                    };
+                    filter.validate().map_err(|e| ErrorData::invalid_params(e.to_string(), None))?;
 
-                    match self.list_tasks(limit).await {
+                    match self.list_tasks(filter, args.limit, args.cursor).await {
                         Ok(result) => {
                             let result_text = json!({
                                 "tasks": result,
@@ -1033,18 +1326,40 @@ impl ServerHandler for TaskQueueMcpServer {
                         Err(e) => Err(ErrorData::internal_error(format!("Failed to list tasks: {}", e), None))
                     }
                 },
-                "cancel_task" => {
-                    let args = request
-                        .arguments
-                        .as_ref()
-                        .ok_or_else(|| ErrorData::invalid_params("Missing arguments", None))?;
+                "search_tasks" => {
+                    let args: SearchTasksArgs = parse_args(&request)?;
+                    let query = args.query.as_str();
+                    if query.trim().is_empty() {
+                        return Err(ErrorData::invalid_params("Missing query parameter", None));
+                    }
+
+                    let limit = args.limit.unwrap_or(5).clamp(1, 50) as usize;
+
+                    match self.task_queue.search_similar_tasks(query, limit).await {
+                        Ok(similar) => {
+                            let tasks: Vec<serde_json::Value> = similar.iter().map(|(task, score)| json!({
+                                "id": task.id,
+                                "name": task.name,
+                                "description": task.description,
+                                "status": task.status,
+                                "project": task.project,
+                                "score": score,
+                            })).collect();
 
-                    let task_id = args
-                        .get("task_id")
-                        .and_then(|t| t.as_str())
-                        .ok_or_else(|| ErrorData::invalid_params("Missing task_id parameter", None))?;
+                            Ok(CallToolResult {
+                                content: vec![Content::text(json!({ "query": query, "tasks": tasks }).to_string())],
+                                structured_content: None,
+                                is_error: Some(false),
+                                meta: None,
+                            })
+                        }
+                        Err(e) => Err(ErrorData::internal_error(format!("Failed to search tasks: {}", e), None))
+                    }
+                },
+                "cancel_task" => {
+                    let TaskIdArgs { task_id } = parse_args(&request)?;
 
-                    match self.cancel_task(task_id.to_string()).await {
+                    match self.cancel_task(task_id).await {
                         Ok(cancelled) => {
                             let result_text = json!({
                                 "task_id": task_id,
@@ -1063,17 +1378,9 @@ impl ServerHandler for TaskQueueMcpServer {
                     }
                 },
                 "delete_task" => {
-                    let args = request
-                        .arguments
-                        .as_ref()
-                        .ok_or_else(|| ErrorData::invalid_params("Missing arguments", None))?;
-
-                    let task_id = args
-                        .get("task_id")
-                        .and_then(|t| t.as_str())
-                        .ok_or_else(|| ErrorData::invalid_params("Missing task_id parameter", None))?;
+                    let TaskIdArgs { task_id } = parse_args(&request)?;
 
-                    match self.delete_task(task_id.to_string()).await {
+                    match self.delete_task(task_id).await {
                         Ok(result) => {
                             let result_text = json!({
                                 "deleted": result,
@@ -1092,24 +1399,9 @@ impl ServerHandler for TaskQueueMcpServer {
                     }
                 },
                 "update_task" => {
-                    let args = request
-                        .arguments
-                        .as_ref()
-                        .ok_or_else(|| ErrorData::invalid_params("Missing arguments", None))?;
-
-                    let task_id = args
-                        .get("task_id")
-                        .and_then(|t| t.as_str())
-                        .ok_or_else(|| ErrorData::invalid_params("Missing task_id parameter", None))?;
-
-                    let name = args.get("name").and_then(|n| n.as_str()).map(|s| s.to_string());
-                    let command = args.get("command").and_then(|c| c.as_str()).map(|s| s.to_string());
-                    let description = args.get("description").and_then(|d| d.as_str()).map(|s| s.to_string());
-                    let priority = args.get("priority").and_then(|p| p.as_str()).map(|s| s.to_string());
-                    let status = args.get("status").and_then(|s| s.as_str()).map(|s| s.to_string());
-                    let project_id = args.get("project_id").and_then(|p| p.as_str()).map(|s| s.to_string());
-
-                    match self.update_task(task_id.to_string(), name, command, description, priority, status, project_id).await {
+                    let args: UpdateTaskArgs = parse_args(&request)?;
+
+                    match self.update_task(args).await {
                         Ok(result) => {
                             let result_text = json!({
                                 "task": result,
@@ -1127,46 +1419,9 @@ impl ServerHandler for TaskQueueMcpServer {
                     }
                 },
                 "upsert_task" => {
-                    let args = request
-                        .arguments
-                        .as_ref()
-                        .ok_or_else(|| ErrorData::invalid_params("Missing arguments", None))?;
-
-                    let name = args
-                        .get("name")
-                        .and_then(|n| n.as_str())
-                        .ok_or_else(|| ErrorData::invalid_params("Missing name parameter", None))?;
-
-                    let command = args
-                        .get("command")
-                        .and_then(|c| c.as_str())
-                        .ok_or_else(|| ErrorData::invalid_params("Missing command parameter", None))?;
-
-                    let description = args
-                        .get("description")
-                        .and_then(|d| d.as_str())
-                        .ok_or_else(|| ErrorData::invalid_params("Missing description parameter", None))?;
-
-                    let project_id = args
-                        .get("project_id")
-                        .and_then(|p| p.as_str())
-                        .ok_or_else(|| ErrorData::invalid_params("Missing project_id parameter", None))?;
-
-                    let priority = args.get("priority").and_then(|p| p.as_str()).map(|s| s.to_string());
-                    let technical_specs = args.get("technical_specs").and_then(|t| t.as_str()).map(|s| s.to_string());
-                    let acceptance_criteria = args.get("acceptance_criteria").and_then(|a| a.as_array()).map(|arr| {
-                        arr.iter().filter_map(|v| v.as_str()).map(|s| s.to_string()).collect()
-                    });
-
-                    match self.upsert_task(
-                        name.to_string(),
-                        command.to_string(),
-                        description.to_string(),
-                        project_id.to_string(),
-                        priority,
-                        technical_specs,
-                        acceptance_criteria,
-                    ).await {
+                    let args: UpsertTaskArgs = parse_args(&request)?;
+
+                    match self.upsert_task(args).await {
                         Ok(result) => {
                             let result_text = json!({
                                 "task": result,
@@ -1183,20 +1438,176 @@ impl ServerHandler for TaskQueueMcpServer {
                         Err(e) => Err(ErrorData::internal_error(format!("Failed to upsert task: {}", e), None))
                     }
                 },
-                            "create_project" => {
-                                let args = request
-                                    .arguments
-                                    .as_ref()
-                                    .ok_or_else(|| ErrorData::invalid_params("Missing arguments", None))?;
+                "get_next_task" => {
+                    let GetNextTaskArgs { agent_id, project_id, labels, lease_secs } = parse_args(&request)?;
+
+                    match self.task_queue.agents().get(&agent_id).await {
+                        Ok(Some(_)) => {}
+                        Ok(None) => return Err(ErrorData::invalid_params(format!("No agent with ID {}; register with register_agent first", agent_id), None)),
+                        Err(e) => return Err(ErrorData::internal_error(format!("Failed to look up agent: {}", e), None)),
+                    }
+
+                    let filter = crate::views::TaskFilter { project_id, tags: labels, ..Default::default() };
 
-                                let name = args
-                                    .get("name")
-                                    .and_then(|n| n.as_str())
-                                    .ok_or_else(|| ErrorData::invalid_params("Missing name parameter", None))?;
+                    // The agent's ID is its worker ID, so its leases show up under /workers
+                    match self.task_queue.claim_next_matching_task(&agent_id.to_string(), lease_secs, &filter).await {
+                        Ok(Some(task)) => {
+                            let lease = crate::lease::Lease::of(&task);
+                            Ok(CallToolResult {
+                                content: vec![Content::text(json!({
+                                    "task": {
+                                        "id": task.id,
+                                        "name": task.name,
+                                        "description": task.description,
+                                        "command": task.command,
+                                        "priority": task.priority,
+                                        "project_id": task.project_id,
+                                        "technical_specs": task.technical_specs,
+                                        "acceptance_criteria": task.acceptance_criteria,
+                                    },
+                                    "lease_expires_at": lease.map(|lease| lease.expires_at),
+                                    "status": "claimed"
+                                }).to_string())],
+                                structured_content: None,
+                                is_error: Some(false),
+                                meta: None,
+                            })
+                        }
+                        Ok(None) => Ok(CallToolResult {
+                            content: vec![Content::text(json!({
+                                "task": null,
+                                "status": "empty",
+                                "message": "No runnable task matches; try again later"
+                            }).to_string())],
+                            structured_content: None,
+                            is_error: Some(false),
+                            meta: None,
+                        }),
+                        Err(e) => Err(ErrorData::internal_error(format!("Failed to claim task: {}", e), None))
+                    }
+                },
+                "add_task_dependency" => {
+                    let args: AddTaskDependencyArgs = parse_args(&request)?;
+                    let (task_id, dependency_task_id) = (args.task_id, args.dependency_task_id);
 
-                                let description = args.get("description").and_then(|d| d.as_str()).map(|s| s.to_string());
+                    if dependency_task_id == task_id {
+                        return Err(ErrorData::invalid_params("A task can't depend on itself", None));
+                    }
 
-                                match self.create_project(name.to_string(), description).await {
+                    let condition = match args.condition {
+                        DependencyConditionArg::Success => crate::core::DependencyCondition::Success,
+                        DependencyConditionArg::Failure => crate::core::DependencyCondition::Failure,
+                        DependencyConditionArg::Completion => crate::core::DependencyCondition::Completion,
+                        // dependency_task_id names a task group
+                        DependencyConditionArg::Group => crate::core::DependencyCondition::Group { quorum: args.quorum },
+                    };
+
+                    // Groups aren't tasks; any other dependency must name one
+                    let task_name = match condition {
+                        crate::core::DependencyCondition::Group { .. } => None,
+                        _ => match self.task_queue.get_task(dependency_task_id).await {
+                            Ok(dependency) => Some(dependency.name),
+                            Err(e) => return Err(ErrorData::invalid_params(format!("Unknown dependency task: {}", e), None)),
+                        },
+                    };
+                    let required = args.required.unwrap_or(true);
+
+                    match self.task_queue.add_task_dependency(task_id, dependency_task_id, task_name, condition, required, args.correlation_id).await {
+                        Ok(()) => match self.task_queue.get_task_dependencies(task_id).await {
+                            Ok(dependencies) => Ok(CallToolResult {
+                                content: vec![Content::text(json!({
+                                    "task_id": task_id,
+                                    "dependencies": dependencies,
+                                    "status": "added"
+                                }).to_string())],
+                                structured_content: None,
+                                is_error: Some(false),
+                                meta: None,
+                            }),
+                            Err(e) => Err(ErrorData::internal_error(format!("Failed to get task dependencies: {}", e), None))
+                        },
+                        Err(crate::error::TaskQueueError::TaskNotFound { .. }) => {
+                            Err(ErrorData::invalid_params(format!("No task with ID {}", task_id), None))
+                        }
+                        Err(e @ crate::error::TaskQueueError::CircularDependency { .. }) => {
+                            Err(ErrorData::invalid_params(e.to_string(), None))
+                        }
+                        Err(e) => Err(ErrorData::internal_error(format!("Failed to add dependency: {}", e), None))
+                    }
+                },
+                "add_artifact" => {
+                    let AddArtifactArgs { task_id, location, description } = parse_args(&request)?;
+
+                    match self.task_queue.add_phase_artifact(task_id, &location, description).await {
+                        Ok(task) => {
+                            let phase = task.phases.last();
+                            Ok(CallToolResult {
+                                content: vec![Content::text(json!({
+                                    "task_id": task_id,
+                                    "phase": phase.map(|p| &p.phase),
+                                    "artifacts": phase.map(|p| &p.artifacts),
+                                    "status": "added"
+                                }).to_string())],
+                                structured_content: None,
+                                is_error: Some(false),
+                                meta: None,
+                            })
+                        }
+                        Err(crate::error::TaskQueueError::TaskNotFound { .. }) => {
+                            Err(ErrorData::invalid_params(format!("No task with ID {}", task_id), None))
+                        }
+                        Err(crate::error::TaskQueueError::ValidationError { reason }) => {
+                            Err(ErrorData::invalid_params(reason, None))
+                        }
+                        Err(e) => Err(ErrorData::internal_error(format!("Failed to add artifact: {}", e), None))
+                    }
+                },
+                "report_progress" => {
+                    let ReportProgressArgs { task_id, percent, note } = parse_args(&request)?;
+
+                    match self.task_queue.report_progress(task_id, percent, note).await {
+                        Ok(task) => Ok(CallToolResult {
+                            content: vec![Content::text(json!({
+                                "task_id": task_id,
+                                "progress": crate::progress::progress(&task),
+                                "status": "reported"
+                            }).to_string())],
+                            structured_content: None,
+                            is_error: Some(false),
+                            meta: None,
+                        }),
+                        Err(crate::error::TaskQueueError::TaskNotFound { .. }) => {
+                            Err(ErrorData::invalid_params(format!("No task with ID {}", task_id), None))
+                        }
+                        Err(crate::error::TaskQueueError::ValidationError { reason }) => {
+                            Err(ErrorData::invalid_params(reason, None))
+                        }
+                        Err(e) => Err(ErrorData::internal_error(format!("Failed to report progress: {}", e), None))
+                    }
+                },
+                "get_task_dependencies" => {
+                    let TaskIdArgs { task_id } = parse_args(&request)?;
+
+                    match self.task_queue.get_task_dependencies(task_id).await {
+                        Ok(dependencies) => Ok(CallToolResult {
+                            content: vec![Content::text(json!({
+                                "task_id": task_id,
+                                "dependencies": dependencies
+                            }).to_string())],
+                            structured_content: None,
+                            is_error: Some(false),
+                            meta: None,
+                        }),
+                        Err(crate::error::TaskQueueError::TaskNotFound { .. }) => {
+                            Err(ErrorData::invalid_params(format!("No task with ID {}", task_id), None))
+                        }
+                        Err(e) => Err(ErrorData::internal_error(format!("Failed to get task dependencies: {}", e), None))
+                    }
+                },
+                            "create_project" => {
+                                let CreateProjectArgs { name, description } = parse_args(&request)?;
+
+                                match self.create_project(name, description).await {
                                     Ok(result) => {
                                         let result_text = json!({
                                             "project_id": result,
@@ -1215,17 +1626,9 @@ impl ServerHandler for TaskQueueMcpServer {
                                 }
                             },
                             "get_project" => {
-                                let args = request
-                                    .arguments
-                                    .as_ref()
-                                    .ok_or_else(|| ErrorData::invalid_params("Missing arguments", None))?;
+                                let ProjectIdArgs { project_id } = parse_args(&request)?;
 
-                                let project_id = args
-                                    .get("project_id")
-                                    .and_then(|p| p.as_str())
-                                    .ok_or_else(|| ErrorData::invalid_params("Missing project_id parameter", None))?;
-
-                                match self.get_project(project_id.to_string()).await {
+                                match self.get_project(project_id).await {
                                     Ok(result) => {
                                         let result_text = json!({
                                             "project": result,
@@ -1261,17 +1664,9 @@ impl ServerHandler for TaskQueueMcpServer {
                                 }
                             },
                             "get_project_tasks" => {
-                                let args = request
-                                    .arguments
-                                    .as_ref()
-                                    .ok_or_else(|| ErrorData::invalid_params("Missing arguments", None))?;
-
-                                let project_id = args
-                                    .get("project_id")
-                                    .and_then(|p| p.as_str())
-                                    .ok_or_else(|| ErrorData::invalid_params("Missing project_id parameter", None))?;
+                                let ProjectIdArgs { project_id } = parse_args(&request)?;
 
-                                match self.get_project_tasks(project_id.to_string()).await {
+                                match self.get_project_tasks(project_id).await {
                                     Ok(result) => {
                                         let result_text = json!({
                                             "tasks": result,
@@ -1289,18 +1684,7 @@ impl ServerHandler for TaskQueueMcpServer {
                                 }
                             },
                             "advance_workflow_phase" => {
-                                let args = request
-                                    .arguments
-                                    .as_ref()
-                                    .ok_or_else(|| ErrorData::invalid_params("Missing arguments", None))?;
-
-                                let task_id_str = args
-                                    .get("task_id")
-                                    .and_then(|t| t.as_str())
-                                    .ok_or_else(|| ErrorData::invalid_params("Missing task_id parameter", None))?;
-
-                                let task_id = uuid::Uuid::parse_str(task_id_str)
-                                    .map_err(|_| ErrorData::invalid_params("Invalid task ID format", None))?;
+                                let TaskIdArgs { task_id } = parse_args(&request)?;
 
                                 match self.task_queue.advance_development_workflow(task_id).await {
                                     Ok(new_status) => {
@@ -1321,25 +1705,9 @@ impl ServerHandler for TaskQueueMcpServer {
                                 }
                             },
                             "set_technical_documentation" => {
-                                let args = request
-                                    .arguments
-                                    .as_ref()
-                                    .ok_or_else(|| ErrorData::invalid_params("Missing arguments", None))?;
+                                let SetTechnicalDocumentationArgs { task_id, doc_path } = parse_args(&request)?;
 
-                                let task_id_str = args
-                                    .get("task_id")
-                                    .and_then(|t| t.as_str())
-                                    .ok_or_else(|| ErrorData::invalid_params("Missing task_id parameter", None))?;
-
-                                let doc_path = args
-                                    .get("doc_path")
-                                    .and_then(|d| d.as_str())
-                                    .ok_or_else(|| ErrorData::invalid_params("Missing doc_path parameter", None))?;
-
-                                let task_id = uuid::Uuid::parse_str(task_id_str)
-                                    .map_err(|_| ErrorData::invalid_params("Invalid task ID format", None))?;
-
-                                match self.task_queue.set_technical_documentation(task_id, doc_path.to_string()).await {
+                                match self.task_queue.set_technical_documentation(task_id, doc_path.clone()).await {
                                     Ok(()) => {
                                         let result_text = format!(
                                             "✅ Technical documentation path set successfully!\n\nPath: {}\n\n📋 **Next Step**: When documentation is complete, advance to Implementation phase.",
@@ -1357,23 +1725,7 @@ impl ServerHandler for TaskQueueMcpServer {
                                 }
                             },
                             "set_test_coverage" => {
-                                let args = request
-                                    .arguments
-                                    .as_ref()
-                                    .ok_or_else(|| ErrorData::invalid_params("Missing arguments", None))?;
-
-                                let task_id_str = args
-                                    .get("task_id")
-                                    .and_then(|t| t.as_str())
-                                    .ok_or_else(|| ErrorData::invalid_params("Missing task_id parameter", None))?;
-
-                                let coverage = args
-                                    .get("coverage")
-                                    .and_then(|c| c.as_f64())
-                                    .ok_or_else(|| ErrorData::invalid_params("Missing or invalid coverage parameter", None))?;
-
-                                let task_id = uuid::Uuid::parse_str(task_id_str)
-                                    .map_err(|_| ErrorData::invalid_params("Invalid task ID format", None))?;
+                                let SetTestCoverageArgs { task_id, coverage } = parse_args(&request)?;
 
                                 match self.task_queue.set_test_coverage(task_id, coverage).await {
                                     Ok(()) => {
@@ -1393,64 +1745,12 @@ impl ServerHandler for TaskQueueMcpServer {
                                 }
                             },
                             "add_ai_review_report" => {
-                                let args = request
-                                    .arguments
-                                    .as_ref()
-                                    .ok_or_else(|| ErrorData::invalid_params("Missing arguments", None))?;
-
-                                let task_id_str = args
-                                    .get("task_id")
-                                    .and_then(|t| t.as_str())
-                                    .ok_or_else(|| ErrorData::invalid_params("Missing task_id parameter", None))?;
-
-                                let model_name = args
-                                    .get("model_name")
-                                    .and_then(|m| m.as_str())
-                                    .ok_or_else(|| ErrorData::invalid_params("Missing model_name parameter", None))?;
-
-                                let review_type_str = args
-                                    .get("review_type")
-                                    .and_then(|r| r.as_str())
-                                    .ok_or_else(|| ErrorData::invalid_params("Missing review_type parameter", None))?;
-
-                                let content = args
-                                    .get("content")
-                                    .and_then(|c| c.as_str())
-                                    .ok_or_else(|| ErrorData::invalid_params("Missing content parameter", None))?;
-
-                                let score = args
-                                    .get("score")
-                                    .and_then(|s| s.as_f64())
-                                    .ok_or_else(|| ErrorData::invalid_params("Missing or invalid score parameter", None))?;
-
-                                let approved = args
-                                    .get("approved")
-                                    .and_then(|a| a.as_bool())
-                                    .ok_or_else(|| ErrorData::invalid_params("Missing or invalid approved parameter", None))?;
-
-                                let suggestions = args
-                                    .get("suggestions")
-                                    .and_then(|s| s.as_array())
-                                    .map(|arr| arr.iter().filter_map(|v| v.as_str()).map(|s| s.to_string()).collect())
-                                    .unwrap_or_default();
-
-                                let review_type = match review_type_str {
-                                    "CodeQuality" => crate::core::AIReviewType::CodeQuality,
-                                    "Security" => crate::core::AIReviewType::Security,
-                                    "Performance" => crate::core::AIReviewType::Performance,
-                                    "Documentation" => crate::core::AIReviewType::Documentation,
-                                    "Testing" => crate::core::AIReviewType::Testing,
-                                    "Architecture" => crate::core::AIReviewType::Architecture,
-                                    _ => return Err(ErrorData::invalid_params("Invalid review_type", None))
-                                };
-
-                                let task_id = uuid::Uuid::parse_str(task_id_str)
-                                    .map_err(|_| ErrorData::invalid_params("Invalid task ID format", None))?;
+                                let AddAiReviewReportArgs { task_id, model_name, review_type, content, score, approved, suggestions } = parse_args(&request)?;
 
                                 let review = crate::core::AIDevelopmentReview {
-                                    model_name: model_name.to_string(),
+                                    model_name: model_name.clone(),
                                     review_type: review_type.clone(),
-                                    content: content.to_string(),
+                                    content,
                                     score,
                                     approved,
                                     suggestions,
@@ -1475,27 +1775,268 @@ impl ServerHandler for TaskQueueMcpServer {
                                     Err(e) => Err(ErrorData::internal_error(format!("Failed to add AI review: {}", e), None))
                                 }
                             },
+                            "list_views" => {
+                                match self.task_queue.list_views().await {
+                                    Ok(views) => Ok(CallToolResult {
+                                        content: vec![Content::text(json!({ "views": views }).to_string())],
+                                        structured_content: None,
+                                        is_error: Some(false),
+                                        meta: None,
+                                    }),
+                                    Err(e) => Err(ErrorData::internal_error(format!("Failed to list views: {}", e), None))
+                                }
+                            },
+                            "list_view_tasks" => {
+                                let ViewNameArgs { name } = parse_args(&request)?;
+
+                                match self.task_queue.view_tasks(&name).await {
+                                    Ok(Some(tasks)) => {
+                                        let tasks: Vec<serde_json::Value> = tasks.iter().map(|task| json!({
+                                            "id": task.id,
+                                            "name": task.name,
+                                            "status": task.status,
+                                            "current_phase": task.current_phase,
+                                            "priority": task.priority,
+                                            "project": task.project,
+                                        })).collect();
+
+                                        Ok(CallToolResult {
+                                            content: vec![Content::text(json!({ "view": name, "tasks": tasks }).to_string())],
+                                            structured_content: None,
+                                            is_error: Some(false),
+                                            meta: None,
+                                        })
+                                    }
+                                    Ok(None) => Err(ErrorData::invalid_params(format!("No view named '{}'", name), None)),
+                                    Err(e) => Err(ErrorData::internal_error(format!("Failed to list view tasks: {}", e), None))
+                                }
+                            },
+                            "save_view" => {
+                                let SaveViewArgs { name, view } = parse_args(&request)?;
+
+                                match self.task_queue.save_view(&name, view).await {
+                                    Ok(view) => Ok(CallToolResult {
+                                        content: vec![Content::text(json!({ "view": view, "status": "saved" }).to_string())],
+                                        structured_content: None,
+                                        is_error: Some(false),
+                                        meta: None,
+                                    }),
+                                    Err(e) => Err(ErrorData::invalid_params(format!("Failed to save view: {}", e), None))
+                                }
+                            },
+                            "register_agent" => {
+                                let registration: crate::agents::RegisterAgent = parse_args(&request)?;
+
+                                match self.task_queue.agents().register(registration).await {
+                                    Ok(agent) => Ok(CallToolResult {
+                                        content: vec![Content::text(json!({
+                                            "agent_id": agent.id,
+                                            "name": agent.name,
+                                            "message": "Agent registered. Pass agent_id to other tools to attribute your changes."
+                                        }).to_string())],
+                                        structured_content: None,
+                                        is_error: Some(false),
+                                        meta: None,
+                                    }),
+                                    Err(e) => Err(ErrorData::invalid_params(format!("Failed to register agent: {}", e), None))
+                                }
+                            },
+                            "agent_heartbeat" => {
+                                let AgentIdArgs { agent_id } = parse_args(&request)?;
+
+                                match self.task_queue.agents().heartbeat(&agent_id).await {
+                                    Ok(agent) => Ok(CallToolResult {
+                                        content: vec![Content::text(json!({
+                                            "agent_id": agent.id,
+                                            "last_seen": agent.last_seen
+                                        }).to_string())],
+                                        structured_content: None,
+                                        is_error: Some(false),
+                                        meta: None,
+                                    }),
+                                    Err(e) => Err(ErrorData::invalid_params(format!("Heartbeat failed: {}", e), None))
+                                }
+                            },
                             _ => Err(ErrorData::invalid_params("Unknown tool", None)),
                         }
-                    }
-                }
+    }
+}
 
-    fn list_resources(
+impl ServerHandler for TaskQueueMcpServer {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            protocol_version: ProtocolVersion::default(),
+            capabilities: ServerCapabilities::builder()
+                .enable_logging()
+                .enable_tools()
+                .enable_resources()
+                .enable_resources_list_changed()
+                .build(),
+            server_info: Implementation {
+                name: "task-queue-mcp-server".to_string(),
+                title: Some("HiveLLM Task Queue MCP Server".to_string()),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                website_url: Some("https://github.com/hivellm/hivellm".to_string()),
+                icons: None,
+            },
+            instructions: Some("This is the HiveLLM Task Queue MCP Server - a high-performance task queue management system with comprehensive development workflow support. It provides capabilities for:\n\n📋 TASK MANAGEMENT: Submit, track, update, and manage tasks with priorities and dependencies. Each task follows a rigorous development workflow to ensure quality.\n\n🔄 DEVELOPMENT WORKFLOW: Automatic workflow enforcement through phases: Planning → Implementation → TestCreation → Testing → AIReview → Completed. Each phase has specific requirements and validations.\n\n🎯 PROJECT ORGANIZATION: Create and manage projects to organize related tasks. Track project status, tasks, and progress.\n\n🤖 AI REVIEW INTEGRATION: Built-in support for AI code reviews with multiple review types (CodeQuality, Security, Performance, Documentation, Testing, Architecture). Requires 3 AI model approvals before task completion.\n\n📊 QUALITY ASSURANCE: Enforced test coverage tracking, technical documentation requirements, and comprehensive acceptance criteria validation.\n\n⚡ PRIORITY MANAGEMENT: Support for task priorities (Low, Normal, High, Critical) with intelligent scheduling.\n\nAll operations are designed to enforce best practices and ensure high-quality deliverables.".to_string()),
+        }
+    }
+
+    #[allow(clippy::manual_async_fn)]
+    fn list_tools(
         &self,
         _request: Option<rmcp::model::PaginatedRequestParam>,
         _context: RequestContext<RoleServer>,
+    ) -> impl std::future::Future<Output = Result<ListToolsResult, ErrorData>> + Send + '_ {
+        async move {
+            Ok(ListToolsResult { 
+                tools: Self::tools(),
+                next_cursor: None,
+            })
+        }
+    }
+
+    #[allow(clippy::manual_async_fn)]
+    fn call_tool(
+        &self,
+        request: rmcp::model::CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> impl std::future::Future<Output = Result<CallToolResult, ErrorData>> + Send + '_ {
+        async move {
+            let principal = principal(&context.extensions);
+            let scope = Self::tool_scope(&request.name);
+            if !principal.allows(scope) {
+                let operation = format!("{} needs an API key with the {} scope", request.name, scope.as_str());
+                return Err(ErrorData::invalid_request(TaskQueueError::PermissionDenied { operation }.to_string(), None));
+            }
+            let agent = request
+                .arguments
+                .as_ref()
+                .and_then(|args| args.get("agent_id"))
+                .and_then(|a| a.as_str())
+                .and_then(|a| uuid::Uuid::parse_str(a).ok());
+            let dispatch = |request| tenants::with_tenant(
+                principal.tenant(),
+                crate::audit::with_source(crate::audit::AuditSource::Mcp, self.dispatch_tool(request)),
+            );
+            let Some(agent) = agent else {
+                return dispatch(request).await;
+            };
+
+            // get_next_task claims a task, unlike the other getters
+            let mutation = request.name == "get_next_task" || !(request.name.starts_with("get_") || request.name.starts_with("list_"));
+            let action = format!("mcp {}", request.name);
+            let result = crate::agents::with_agent(Some(agent), dispatch(request)).await;
+            if let Err(e) = self.task_queue.agents().record(&agent, &action, mutation, result.is_ok()).await {
+                tracing::warn!("Failed to record activity of agent {}: {}", agent, e);
+            }
+            result
+        }
+    }
+
+    #[allow(clippy::manual_async_fn)]
+    fn list_resources(
+        &self,
+        _request: Option<rmcp::model::PaginatedRequestParam>,
+        context: RequestContext<RoleServer>,
     ) -> impl std::future::Future<Output = Result<ListResourcesResult, ErrorData>> + Send + '_ {
         async move {
+            let principal = principal(&context.extensions);
+            if !principal.allows(KeyScope::Read) {
+                return Err(ErrorData::invalid_request("Permission denied: listing resources needs an API key with the read scope", None));
+            }
+            let projects = tenants::with_tenant(principal.tenant(), self.task_queue.list_projects()).await
+                .map_err(|e| ErrorData::internal_error(format!("Failed to list projects: {}", e), None))?;
+            let resources = projects.into_iter().map(|project| RawResource {
+                uri: project_uri(project.id),
+                name: project.name,
+                title: None,
+                description: project.description,
+                mime_type: Some("application/json".to_string()),
+                size: None,
+                icons: None,
+            }.no_annotation()).collect();
+
             Ok(ListResourcesResult { 
-                resources: vec![],
+                resources,
                 next_cursor: None,
             })
         }
     }
+
+    #[allow(clippy::manual_async_fn)]
+    fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> impl std::future::Future<Output = Result<ReadResourceResult, ErrorData>> + Send + '_ {
+        async move {
+            let principal = principal(&context.extensions);
+            if !principal.allows(KeyScope::Read) {
+                return Err(ErrorData::invalid_request("Permission denied: reading resources needs an API key with the read scope", None));
+            }
+            tenants::with_tenant(principal.tenant(), async move {
+                let project_id = request.uri
+                    .strip_prefix(PROJECT_URI_PREFIX)
+                    .and_then(|id| uuid::Uuid::parse_str(id).ok())
+                    .ok_or_else(|| ErrorData::resource_not_found(format!("Unknown resource {}", request.uri), None))?;
+                let project = self.task_queue.get_project(&project_id).await
+                    .map_err(|e| ErrorData::internal_error(format!("Failed to get project: {}", e), None))?
+                    .ok_or_else(|| ErrorData::resource_not_found(format!("No project with ID {}", project_id), None))?;
+                let tasks: Vec<crate::core::TaskSummary> = self.task_queue.get_tasks_by_project(&project_id).await
+                    .map_err(|e| ErrorData::internal_error(format!("Failed to get project tasks: {}", e), None))?
+                    .iter()
+                    .map(crate::core::TaskSummary::from)
+                    .collect();
+
+                let text = json!({ "project": project, "tasks": tasks }).to_string();
+                Ok(ReadResourceResult {
+                    contents: vec![ResourceContents::TextResourceContents {
+                        uri: request.uri,
+                        mime_type: Some("application/json".to_string()),
+                        text,
+                        meta: None,
+                    }],
+                })
+            }).await
+        }
+    }
+
+    #[allow(clippy::manual_async_fn)]
+    fn on_initialized(
+        &self,
+        context: NotificationContext<RoleServer>,
+    ) -> impl std::future::Future<Output = ()> + Send + '_ {
+        async move {
+            let tenant = principal(&context.extensions).tenant();
+            tokio::spawn(forward_changes(self.task_queue.clone(), context.peer, tenant));
+        }
+    }
+}
+
+/// Create MCP router using rmcp crate, serving the given transports
+pub async fn create_mcp_router(task_queue: Arc<TaskQueueServer>, transport: McpTransport) -> AxumRouter {
+    let mut router = AxumRouter::new();
+    if transport.serves_sse() {
+        router = router.merge(create_sse_router(task_queue.clone()));
+    }
+    if transport.serves_streamable_http() {
+        let server = task_queue.clone();
+        let service = StreamableHttpService::new(
+            move || Ok(TaskQueueMcpServer::new(server.clone())),
+            Arc::new(LocalSessionManager::default()),
+            StreamableHttpServerConfig {
+                sse_keep_alive: Some(std::time::Duration::from_secs(30)),
+                stateful_mode: true,
+            },
+        );
+        router = router.route_service(MCP_STREAMABLE_PATH, service);
+    }
+    router.layer(middleware::from_fn_with_state(task_queue, authenticate))
 }
 
-/// Create MCP router using rmcp crate
-pub async fn create_mcp_router(task_queue: Arc<TaskQueueServer>) -> AxumRouter {
+fn create_sse_router(task_queue: Arc<TaskQueueServer>) -> AxumRouter {
     let config = SseServerConfig {
         bind: "0.0.0.0:0".parse::<SocketAddr>().expect("Invalid bind address"), // Port 0 means don't bind, just create router
         sse_path: "/mcp/sse".into(),
@@ -1507,10 +2048,229 @@ pub async fn create_mcp_router(task_queue: Arc<TaskQueueServer>) -> AxumRouter {
     let (sse, axum_router) = SseServer::new(config);
 
     // Create the MCP server and register it with the SSE server
-    let _cancel = sse.with_service(move || {
-        let mcp_server = TaskQueueMcpServer::new(task_queue.clone());
-        mcp_server
-    });
+    let _cancel = sse.with_service(move || TaskQueueMcpServer::new(task_queue.clone()));
 
     axum_router
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Task, TaskStatus};
+    use crate::storage::StorageEngine;
+    use crate::vectorizer::VectorizerIntegration;
+    use axum::routing::post;
+    use serde_json::Value;
+    use std::sync::Mutex;
+
+    async fn call(mcp: &TaskQueueMcpServer, name: &'static str, arguments: Value) -> Result<Value, ErrorData> {
+        let request = CallToolRequestParam { name: Cow::Borrowed(name), arguments: arguments.as_object().cloned() };
+        let result = mcp.dispatch_tool(request).await?;
+        let text = result.content[0].as_text().unwrap().text.clone();
+        Ok(serde_json::from_str(&text).unwrap())
+    }
+
+    /// Vectorizer answering every search with `hits`, recording the limits asked for
+    async fn fake_vectorizer(hits: Vec<(String, f64)>) -> (VectorizerIntegration, Arc<Mutex<Vec<u64>>>) {
+        let limits = Arc::new(Mutex::new(Vec::new()));
+        let seen = limits.clone();
+        let app = AxumRouter::new().route("/collections/task-interactions/search", post(move |Json(body): Json<Value>| {
+            let hits = hits.clone();
+            seen.lock().unwrap().push(body["limit"].as_u64().unwrap());
+            async move {
+                let results: Vec<Value> = hits.iter().map(|(id, score)| json!({ "id": id, "score": score })).collect();
+                Json(json!({ "results": results }))
+            }
+        }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (VectorizerIntegration::with_base_url(url), limits)
+    }
+
+    #[tokio::test]
+    async fn test_search_tasks_ranks_visible_tasks_and_clamps_the_limit() {
+        let storage = Arc::new(StorageEngine::temporary().unwrap());
+        let setup = TaskQueueServer::with_components(storage.clone(), Arc::new(VectorizerIntegration::new_dummy()))
+            .await
+            .unwrap();
+        let alpha = setup.create_tenant("Alpha".to_string(), None).await.unwrap().id;
+        let beta = setup.create_tenant("Beta".to_string(), None).await.unwrap().id;
+        let submit = |tenant, name: &'static str| {
+            let server = &setup;
+            tenants::with_tenant(Some(tenant), async move {
+                let project_id = server.create_project(format!("{} project", name), None).await.unwrap();
+                let mut task = Task::new(name).with_command("make").build();
+                task.project_id = Some(project_id);
+                task.status = TaskStatus::Pending;
+                server.submit_task(task).await.unwrap()
+            })
+        };
+        let close = submit(alpha, "close").await;
+        let closer = submit(alpha, "closer").await;
+        let deleted = submit(alpha, "deleted").await;
+        let hidden = submit(beta, "hidden").await;
+        setup.delete_task(deleted).await.unwrap();
+
+        let hits = vec![
+            (close.to_string(), 0.6),
+            (hidden.to_string(), 0.95),
+            ("not-a-task".to_string(), 0.9),
+            (deleted.to_string(), 0.85),
+            (closer.to_string(), 0.8),
+        ];
+        let (vectorizer, limits) = fake_vectorizer(hits).await;
+        let server = TaskQueueServer::with_components(storage, Arc::new(vectorizer)).await.unwrap();
+        let mcp = TaskQueueMcpServer::new(Arc::new(server));
+        let search = |limit: Value| {
+            let mcp = &mcp;
+            tenants::with_tenant(Some(alpha), async move {
+                let found = call(mcp, "search_tasks", json!({ "query": "build it", "limit": limit })).await.unwrap();
+                found["tasks"].as_array().unwrap().iter().map(|task| task["name"].as_str().unwrap().to_string()).collect::<Vec<_>>()
+            })
+        };
+
+        // Most similar first, leaving out deleted tasks and other tenants'
+        assert_eq!(search(Value::Null).await, ["closer", "close"]);
+        assert_eq!(search(json!(1)).await, ["closer"]);
+        assert_eq!(search(json!(500)).await, ["closer", "close"]);
+        assert_eq!(search(json!(0)).await, ["closer"]);
+        assert_eq!(*limits.lock().unwrap(), [5, 1, 50, 1]);
+
+        let blank = call(&mcp, "search_tasks", json!({ "query": "  " })).await.unwrap_err();
+        assert!(blank.message.contains("Missing query"));
+    }
+
+    /// Task names on a `list_tasks` page, and the cursor of the next one
+    fn listed(found: &Value) -> (Vec<String>, Option<String>) {
+        let text = found["tasks"]["content"][0]["text"].as_str().unwrap();
+        let names = text.lines()
+            .filter_map(|line| line.strip_prefix("- "))
+            .map(|line| line.split(" (").next().unwrap().to_string())
+            .collect();
+        let cursor = text.split("cursor \"").nth(1).map(|rest| rest.split('"').next().unwrap().to_string());
+        (names, cursor)
+    }
+
+    #[tokio::test]
+    async fn test_list_tasks_filters_and_pages() {
+        use crate::core::TaskPriority;
+
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let backend = server.create_project("backend".to_string(), None).await.unwrap();
+        let frontend = server.create_project("frontend".to_string(), None).await.unwrap();
+        // The status filter goes by the development phase
+        for (name, project_id, priority, phase) in [
+            ("api", backend, TaskPriority::High, TaskStatus::Planning),
+            ("db", backend, TaskPriority::Low, TaskStatus::Planning),
+            ("css", frontend, TaskPriority::High, TaskStatus::Planning),
+            ("docs", frontend, TaskPriority::Normal, TaskStatus::Testing),
+        ] {
+            let mut task = Task::new(name).with_command("make").build();
+            task.project_id = Some(project_id);
+            task.priority = priority;
+            task.current_phase = phase;
+            server.submit_task(task).await.unwrap();
+        }
+        let mcp = TaskQueueMcpServer::new(Arc::new(server));
+        let list = |arguments: Value| {
+            let mcp = &mcp;
+            async move { listed(&call(mcp, "list_tasks", arguments).await.unwrap()) }
+        };
+        let names = |arguments: Value| {
+            let list = &list;
+            async move { list(arguments).await.0 }
+        };
+
+        assert_eq!(names(json!({})).await, ["api", "db", "css", "docs"]);
+        assert_eq!(names(json!({ "status": "planning" })).await, ["api", "db", "css"]);
+        assert_eq!(names(json!({ "status": ["Testing", "planning"] })).await, ["api", "db", "css", "docs"]);
+        assert_eq!(names(json!({ "priority": "High" })).await, ["api", "css"]);
+        assert_eq!(names(json!({ "priority": ["High", "Low"] })).await, ["api", "db", "css"]);
+        assert_eq!(names(json!({ "project_id": backend })).await, ["api", "db"]);
+        assert_eq!(names(json!({ "project_id": frontend, "status": "planning" })).await, ["css"]);
+
+        // Following the cursor with the same filters visits every match once
+        let (first, cursor) = list(json!({ "limit": 2 })).await;
+        assert_eq!(first, ["api", "db"]);
+        let (second, cursor) = list(json!({ "limit": 2, "cursor": cursor.unwrap() })).await;
+        assert_eq!((second, cursor), (vec!["css".to_string(), "docs".to_string()], None));
+        let (first, cursor) = list(json!({ "status": "planning", "limit": 2 })).await;
+        assert_eq!(first, ["api", "db"]);
+        let (second, cursor) = list(json!({ "status": "planning", "limit": 2, "cursor": cursor.unwrap() })).await;
+        assert_eq!((second, cursor), (vec!["css".to_string()], None));
+
+        // One project at a time, as the schema says
+        let schema = Value::Object((*cached_schema_for_type::<ListTasksArgs>()).clone());
+        assert!(!schema["properties"]["project_id"].to_string().contains("array"), "{}", schema);
+        let many = call(&mcp, "list_tasks", json!({ "project_id": [backend, frontend] })).await.unwrap_err();
+        assert_eq!(many.code, rmcp::model::ErrorCode::INVALID_PARAMS);
+        let unknown = call(&mcp, "list_tasks", json!({ "status": "lost" })).await.unwrap_err();
+        assert_eq!(unknown.code, rmcp::model::ErrorCode::INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn test_task_dependencies_are_validated_and_stored() {
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let project_id = server.create_project("ci".to_string(), None).await.unwrap();
+        let mut ids = Vec::new();
+        for name in ["build", "test", "deploy"] {
+            let mut task = Task::new(name).with_command("make").build();
+            task.project_id = Some(project_id);
+            ids.push(server.submit_task(task).await.unwrap());
+        }
+        let (build, test, deploy) = (ids[0], ids[1], ids[2]);
+        let mcp = TaskQueueMcpServer::new(Arc::new(server));
+        let add = |arguments: Value| call(&mcp, "add_task_dependency", arguments);
+        let dependencies = |task_id: uuid::Uuid| {
+            let mcp = &mcp;
+            async move { call(mcp, "get_task_dependencies", json!({ "task_id": task_id })).await.map(|found| found["dependencies"].clone()) }
+        };
+
+        add(json!({ "task_id": deploy, "dependency_task_id": test })).await.unwrap();
+        let added = add(json!({
+            "task_id": test, "dependency_task_id": build,
+            "condition": "Completion", "required": false, "correlation_id": "pipeline"
+        })).await.unwrap();
+        assert_eq!(added["status"], "added");
+        let group = uuid::Uuid::new_v4();
+        add(json!({ "task_id": deploy, "dependency_task_id": group, "condition": "Group", "quorum": 2 })).await.unwrap();
+
+        let stored = dependencies(test).await.unwrap();
+        assert_eq!(stored.as_array().unwrap().len(), 1);
+        assert_eq!(stored[0]["task_id"], json!(build));
+        assert_eq!(stored[0]["task_name"], "build");
+        assert_eq!(stored[0]["condition"], "Completion");
+        assert_eq!(stored[0]["required"], false);
+        assert_eq!(stored[0]["correlation_id"], "pipeline");
+        let stored = dependencies(deploy).await.unwrap();
+        assert_eq!((stored[0]["task_name"].clone(), stored[0]["condition"].clone(), stored[0]["required"].clone()), (json!("test"), json!("Success"), json!(true)));
+        assert_eq!((stored[1]["task_id"].clone(), stored[1]["task_name"].clone()), (json!(group), Value::Null));
+        assert_eq!(stored[1]["condition"], json!({ "Group": { "quorum": 2 } }));
+
+        // Self-dependencies and cycles, direct or through other tasks, are refused
+        for (task_id, dependency_task_id) in [(build, build), (test, deploy), (build, deploy)] {
+            let refused = add(json!({ "task_id": task_id, "dependency_task_id": dependency_task_id })).await.unwrap_err();
+            assert_eq!(refused.code, rmcp::model::ErrorCode::INVALID_PARAMS, "{}", refused.message);
+        }
+        assert_eq!(dependencies(build).await.unwrap(), json!([]));
+
+        // Unknown tasks on either side
+        let unknown = uuid::Uuid::new_v4();
+        let missing = add(json!({ "task_id": build, "dependency_task_id": unknown })).await.unwrap_err();
+        assert!(missing.message.contains("Unknown dependency task"), "{}", missing.message);
+        let missing = add(json!({ "task_id": unknown, "dependency_task_id": build })).await.unwrap_err();
+        assert!(missing.message.contains("No task with ID"), "{}", missing.message);
+        let missing = dependencies(unknown).await.unwrap_err();
+        assert_eq!(missing.code, rmcp::model::ErrorCode::INVALID_PARAMS);
+    }
+}
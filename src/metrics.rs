@@ -6,7 +6,8 @@
 #![allow(unused_mut)]
 
 use crate::core::*;
-use prometheus::{Counter, Histogram, Gauge, Registry, TextEncoder, Encoder};
+use crate::storage::StorageStats;
+use prometheus::{Counter, Histogram, Gauge, GaugeVec, Opts, Registry, TextEncoder, Encoder};
 use std::sync::Arc;
 use std::time::Instant;
 
@@ -34,6 +35,13 @@ pub struct MetricsCollector {
     memory_usage: Gauge,
     cpu_usage: Gauge,
     storage_size: Gauge,
+
+    // Storage metrics
+    storage_records: GaugeVec,
+    storage_writes: Gauge,
+    storage_write_latency: Gauge,
+    storage_last_write_latency: Gauge,
+    storage_last_compaction: Gauge,
 }
 
 impl MetricsCollector {
@@ -108,6 +116,31 @@ impl MetricsCollector {
             "Current storage size in bytes"
         ).unwrap();
         
+        let storage_records = GaugeVec::new(
+            Opts::new("storage_records", "Records stored, by tree"),
+            &["tree"]
+        ).unwrap();
+        
+        let storage_writes = Gauge::new(
+            "storage_writes",
+            "Batches written to storage since it was opened"
+        ).unwrap();
+        
+        let storage_write_latency = Gauge::new(
+            "storage_write_latency_seconds",
+            "Mean time to write and flush a batch to storage"
+        ).unwrap();
+        
+        let storage_last_write_latency = Gauge::new(
+            "storage_last_write_latency_seconds",
+            "Time the latest storage write took"
+        ).unwrap();
+        
+        let storage_last_compaction = Gauge::new(
+            "storage_last_compaction_timestamp_seconds",
+            "Unix time storage was last compacted, 0 if never"
+        ).unwrap();
+        
         // Register metrics
         registry.register(Box::new(tasks_submitted.clone())).unwrap();
         registry.register(Box::new(tasks_completed.clone())).unwrap();
@@ -122,6 +155,11 @@ impl MetricsCollector {
         registry.register(Box::new(memory_usage.clone())).unwrap();
         registry.register(Box::new(cpu_usage.clone())).unwrap();
         registry.register(Box::new(storage_size.clone())).unwrap();
+        registry.register(Box::new(storage_records.clone())).unwrap();
+        registry.register(Box::new(storage_writes.clone())).unwrap();
+        registry.register(Box::new(storage_write_latency.clone())).unwrap();
+        registry.register(Box::new(storage_last_write_latency.clone())).unwrap();
+        registry.register(Box::new(storage_last_compaction.clone())).unwrap();
         
         Self {
             registry,
@@ -138,6 +176,11 @@ impl MetricsCollector {
             memory_usage,
             cpu_usage,
             storage_size,
+            storage_records,
+            storage_writes,
+            storage_write_latency,
+            storage_last_write_latency,
+            storage_last_compaction,
         }
     }
 
@@ -220,6 +263,18 @@ impl MetricsCollector {
         self.storage_size.set(bytes);
     }
 
+    /// Update the storage metrics from current statistics
+    pub fn update_storage(&self, stats: &StorageStats) {
+        self.storage_size.set(stats.db_size_bytes as f64);
+        for (tree, records) in &stats.records {
+            self.storage_records.with_label_values(&[tree.as_str()]).set(*records as f64);
+        }
+        self.storage_writes.set(stats.writes as f64);
+        self.storage_write_latency.set(stats.mean_write_seconds);
+        self.storage_last_write_latency.set(stats.last_write_seconds);
+        self.storage_last_compaction.set(stats.last_compaction.map_or(0.0, |at| at.timestamp() as f64));
+    }
+
     /// Get all metrics as JSON
     pub fn get_metrics(&self) -> serde_json::Value {
         let mut buffer = Vec::new();
@@ -274,6 +329,12 @@ impl MetricsCollector {
     }
 }
 
+impl Default for MetricsCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Metrics summary
 #[derive(Debug, Clone)]
 pub struct MetricsSummary {
@@ -1,9 +1,11 @@
 //! Data models for authentication and authorization
+//!
+//! Only the permission models are built for now; users and sessions wait on
+//! the rest of [`crate::auth`].
+
+#![allow(unused_imports)]
+#![allow(dead_code)]
 
-pub mod user;
-pub mod session;
 pub mod permission;
 
-pub use user::{User, CreateUserRequest, UpdateUserRequest, LoginRequest, LoginResponse, UserPublic};
-pub use session::{Session, SessionData};
 pub use permission::{Permission, Role, UserRole};
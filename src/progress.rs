@@ -0,0 +1,77 @@
+//! Progress Module
+//!
+//! Progress reported while a task runs, so long phases don't look stuck.
+//! An agent posts a percentage and a note, e.g. through the MCP
+//! `report_progress` tool; the latest report is kept in the task's
+//! `progress` metadata and each one goes out on the event stream as a
+//! `task_progress` event.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::Task;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Task metadata key holding the latest progress report
+pub const PROGRESS_KEY: &str = "progress";
+/// Longest progress note
+pub const MAX_NOTE_LENGTH: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TaskProgress {
+    /// Share of the work done, from 0 to 100
+    pub percent: u8,
+    pub note: Option<String>,
+    pub reported_at: DateTime<Utc>,
+    /// Agent that reported it
+    pub agent: Option<Uuid>,
+}
+
+impl TaskProgress {
+    /// A report made now, or why it can't be made
+    pub fn new(percent: f64, note: Option<String>) -> Result<Self, String> {
+        if !(0.0..=100.0).contains(&percent) {
+            return Err(format!("percent must be between 0 and 100, got {}", percent));
+        }
+        let note = note.map(|note| note.trim().to_string()).filter(|note| !note.is_empty());
+        if note.as_ref().is_some_and(|note| note.chars().count() > MAX_NOTE_LENGTH) {
+            return Err(format!("note can't be longer than {} characters", MAX_NOTE_LENGTH));
+        }
+        Ok(Self { percent: percent.round() as u8, note, reported_at: Utc::now(), agent: None })
+    }
+
+    pub fn apply(&self, task: &mut Task) {
+        task.metadata.insert(PROGRESS_KEY.to_string(), serde_json::to_value(self).unwrap_or_default());
+    }
+}
+
+/// The latest progress reported on a task, if any
+pub fn progress(task: &Task) -> Option<TaskProgress> {
+    serde_json::from_value(task.metadata.get(PROGRESS_KEY)?.clone()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latest_report_is_kept_on_the_task() {
+        assert!(TaskProgress::new(101.0, None).is_err() && TaskProgress::new(-1.0, None).is_err());
+        assert!(TaskProgress::new(50.0, Some("x".repeat(MAX_NOTE_LENGTH + 1))).is_err());
+
+        let mut task = Task::new("long").with_command("true").build();
+        assert!(progress(&task).is_none());
+        TaskProgress::new(10.0, Some("Parsing".to_string())).unwrap().apply(&mut task);
+        let report = TaskProgress::new(62.4, Some("  ".to_string())).unwrap();
+        report.apply(&mut task);
+        assert_eq!(progress(&task), Some(report));
+        assert_eq!(progress(&task).unwrap().percent, 62);
+        assert_eq!(progress(&task).unwrap().note, None);
+    }
+}
@@ -0,0 +1,334 @@
+//! Event Publisher Module
+//!
+//! Mirrors the server's event bus to Kafka topics or NATS subjects so data
+//! pipelines can consume queue activity. Events are read from the storage
+//! outbox and only removed once the broker accepted them, which gives
+//! at-least-once delivery across broker outages and restarts. Consumers
+//! should deduplicate on the event's `sequence`.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::config::{BrokerConfig, PublisherConfig};
+use crate::error::{TaskQueueError, Result};
+use crate::events::{EventBus, TaskEvent};
+use crate::storage::Storage;
+use futures_util::future::BoxFuture;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{info, warn};
+
+/// Destination the publisher hands serialized events to
+pub trait EventSink: Send + Sync {
+    /// Deliver one message; returns once the broker accepted it
+    fn send<'a>(&'a self, topic: &'a str, key: Option<&'a str>, payload: Vec<u8>) -> BoxFuture<'a, Result<()>>;
+}
+
+/// Publishes to Kafka, keyed by task or workflow so each entity's events stay ordered
+#[cfg(feature = "kafka")]
+pub struct KafkaSink {
+    client: rskafka::client::Client,
+    partitions: tokio::sync::Mutex<std::collections::HashMap<String, Vec<Arc<rskafka::client::partition::PartitionClient>>>>,
+}
+
+#[cfg(feature = "kafka")]
+impl KafkaSink {
+    pub async fn connect(brokers: Vec<String>) -> Result<Self> {
+        let client = rskafka::client::ClientBuilder::new(brokers)
+            .build()
+            .await
+            .map_err(|e| TaskQueueError::BrokerError(e.to_string()))?;
+        Ok(Self {
+            client,
+            partitions: tokio::sync::Mutex::new(std::collections::HashMap::new()),
+        })
+    }
+
+    /// Partition clients of a topic, looked up once
+    async fn partitions(&self, topic: &str) -> Result<Vec<Arc<rskafka::client::partition::PartitionClient>>> {
+        let mut cache = self.partitions.lock().await;
+        if let Some(partitions) = cache.get(topic) {
+            return Ok(partitions.clone());
+        }
+
+        let broker_error = |e: rskafka::client::error::Error| TaskQueueError::BrokerError(e.to_string());
+        let ids = self.client.list_topics().await.map_err(broker_error)?
+            .into_iter()
+            .find(|t| t.name == topic)
+            .map(|t| t.partitions)
+            .ok_or_else(|| TaskQueueError::BrokerError(format!("Kafka topic {} does not exist", topic)))?;
+
+        let mut partitions = Vec::new();
+        for id in ids {
+            let client = self.client
+                .partition_client(topic, id, rskafka::client::partition::UnknownTopicHandling::Retry)
+                .await
+                .map_err(broker_error)?;
+            partitions.push(Arc::new(client));
+        }
+        cache.insert(topic.to_string(), partitions.clone());
+        Ok(partitions)
+    }
+}
+
+#[cfg(feature = "kafka")]
+impl EventSink for KafkaSink {
+    fn send<'a>(&'a self, topic: &'a str, key: Option<&'a str>, payload: Vec<u8>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let partitions = self.partitions(topic).await?;
+            if partitions.is_empty() {
+                return Err(TaskQueueError::BrokerError(format!("Kafka topic {} has no partitions", topic)));
+            }
+            let index = key.map_or(0, |key| stable_hash(key) as usize % partitions.len());
+
+            let record = rskafka::record::Record {
+                key: key.map(|key| key.as_bytes().to_vec()),
+                value: Some(payload),
+                headers: Default::default(),
+                timestamp: chrono::Utc::now(),
+            };
+            partitions[index]
+                .produce(vec![record], rskafka::client::partition::Compression::NoCompression)
+                .await
+                .map_err(|e| TaskQueueError::BrokerError(e.to_string()))?;
+            Ok(())
+        })
+    }
+}
+
+/// FNV-1a, so a key maps to the same partition in every process
+fn stable_hash(key: &str) -> u64 {
+    key.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Publishes to NATS subjects, optionally through JetStream
+#[cfg(feature = "nats")]
+pub struct NatsSink {
+    client: async_nats::Client,
+    jetstream: Option<async_nats::jetstream::Context>,
+}
+
+#[cfg(feature = "nats")]
+impl NatsSink {
+    pub async fn connect(url: &str, jetstream: bool) -> Result<Self> {
+        let client = async_nats::connect(url)
+            .await
+            .map_err(|e| TaskQueueError::BrokerError(e.to_string()))?;
+        let jetstream = jetstream.then(|| async_nats::jetstream::new(client.clone()));
+        Ok(Self { client, jetstream })
+    }
+}
+
+#[cfg(feature = "nats")]
+impl EventSink for NatsSink {
+    fn send<'a>(&'a self, topic: &'a str, key: Option<&'a str>, payload: Vec<u8>) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let broker_error = |e: &dyn std::fmt::Display| TaskQueueError::BrokerError(e.to_string());
+            match &self.jetstream {
+                Some(jetstream) => {
+                    jetstream
+                        .publish(topic.to_string(), payload.into())
+                        .await
+                        .map_err(|e| broker_error(&e))?
+                        .await
+                        .map_err(|e| broker_error(&e))?;
+                }
+                None => {
+                    // Core NATS has no acknowledgement; flushing at least
+                    // confirms the server received the message
+                    self.client
+                        .publish(topic.to_string(), payload.into())
+                        .await
+                        .map_err(|e| broker_error(&e))?;
+                    self.client.flush().await.map_err(|e| broker_error(&e))?;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Connect to the configured broker
+pub async fn connect_sink(broker: &BrokerConfig) -> Result<Arc<dyn EventSink>> {
+    match broker {
+        #[cfg(feature = "kafka")]
+        BrokerConfig::Kafka { brokers } => Ok(Arc::new(KafkaSink::connect(brokers.clone()).await?)),
+        #[cfg(feature = "nats")]
+        BrokerConfig::Nats { url, jetstream } => Ok(Arc::new(NatsSink::connect(url, *jetstream).await?)),
+        #[allow(unreachable_patterns)]
+        other => Err(TaskQueueError::ConfigurationError(format!(
+            "Event publishing to {:?} needs a build with the matching feature",
+            other
+        ))),
+    }
+}
+
+/// Drains the storage outbox into an event sink
+pub struct EventPublisher {
+    storage: Arc<dyn Storage>,
+    sink: Arc<dyn EventSink>,
+    config: PublisherConfig,
+}
+
+impl EventPublisher {
+    pub fn new(storage: Arc<dyn Storage>, sink: Arc<dyn EventSink>, config: PublisherConfig) -> Self {
+        Self { storage, sink, config }
+    }
+
+    /// Connect to the broker named in the configuration
+    pub async fn connect(storage: Arc<dyn Storage>, config: PublisherConfig) -> Result<Self> {
+        let sink = connect_sink(&config.broker).await?;
+        Ok(Self::new(storage, sink, config))
+    }
+
+    /// Publish one batch of pending events in order, returning how many were delivered
+    ///
+    /// Stops at the first failure so later events never overtake an earlier
+    /// one; the failed event is retried on the next call.
+    pub async fn drain(&self) -> Result<usize> {
+        let events = self.storage.outbox_batch(self.config.batch_size).await?;
+        let mut delivered = 0;
+
+        for event in events {
+            let topic = self.config.topic_for(event.kind.as_str());
+            let key = event.task_id.or(event.workflow_id).map(|id| id.to_string());
+            let payload = serde_json::to_vec(&event)?;
+
+            self.sink.send(&topic, key.as_deref(), payload).await?;
+            self.storage.ack_outbox(event.sequence).await?;
+            delivered += 1;
+        }
+
+        Ok(delivered)
+    }
+
+    /// Publish until the bus closes, waking up on new events or the poll interval
+    pub async fn run(self, events: Arc<EventBus>) {
+        let mut receiver = events.subscribe();
+        let poll_interval = Duration::from_millis(self.config.poll_interval_ms);
+        info!("Publishing events to {:?}", self.config.broker);
+
+        loop {
+            match self.drain().await {
+                Ok(delivered) if delivered == self.config.batch_size => continue,
+                Ok(_) => {}
+                Err(e) => warn!("Event publishing failed, will retry: {}", e),
+            }
+
+            tokio::select! {
+                received = receiver.recv() => {
+                    if let Err(RecvError::Closed) = received {
+                        break;
+                    }
+                }
+                _ = tokio::time::sleep(poll_interval) => {}
+            }
+        }
+    }
+}
+
+/// Start publishing in the background
+///
+/// Connecting is retried while the broker is unreachable; events accumulate
+/// in the outbox meanwhile.
+pub fn spawn(storage: Arc<dyn Storage>, events: Arc<EventBus>, config: PublisherConfig) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let retry_delay = Duration::from_secs(5);
+        loop {
+            match EventPublisher::connect(storage.clone(), config.clone()).await {
+                Ok(publisher) => return publisher.run(events).await,
+                Err(TaskQueueError::ConfigurationError(e)) => {
+                    warn!("Event publishing disabled: {}", e);
+                    return;
+                }
+                Err(e) => {
+                    warn!("Cannot connect to event broker, retrying in {:?}: {}", retry_delay, e);
+                    tokio::time::sleep(retry_delay).await;
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::StorageEngine;
+    use crate::core::*;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// Records messages and fails while `down` is set
+    #[derive(Default)]
+    struct MemorySink {
+        sent: Mutex<Vec<(String, Option<String>, TaskEvent)>>,
+        down: AtomicBool,
+    }
+
+    impl EventSink for MemorySink {
+        fn send<'a>(&'a self, topic: &'a str, key: Option<&'a str>, payload: Vec<u8>) -> BoxFuture<'a, Result<()>> {
+            Box::pin(async move {
+                if self.down.load(Ordering::SeqCst) {
+                    return Err(TaskQueueError::BrokerError("broker unavailable".to_string()));
+                }
+                let event = serde_json::from_slice(&payload)?;
+                self.sent.lock().unwrap().push((topic.to_string(), key.map(str::to_string), event));
+                Ok(())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_outbox_is_kept_until_broker_accepts() {
+        let storage = Arc::new(StorageEngine::temporary().unwrap());
+        let bus = EventBus::new();
+        bus.enable_outbox(storage.clone()).unwrap();
+
+        let sink = Arc::new(MemorySink::default());
+        let mut config = PublisherConfig::new(BrokerConfig::Nats { url: String::new(), jetstream: false });
+        config.topics.insert("task_completed".to_string(), "analytics.completions".to_string());
+        let publisher = EventPublisher::new(storage.clone(), sink.clone(), config);
+
+        let mut task = Task::new("Build").with_command("cargo build").build();
+        bus.task_created(&task).await;
+        task.status = TaskStatus::Completed;
+        bus.task_changed(&task).await;
+
+        sink.down.store(true, Ordering::SeqCst);
+        assert!(publisher.drain().await.is_err());
+        assert_eq!(storage.outbox_batch(10).await.unwrap().len(), 2);
+
+        sink.down.store(false, Ordering::SeqCst);
+        assert_eq!(publisher.drain().await.unwrap(), 2);
+        assert!(storage.outbox_batch(10).await.unwrap().is_empty());
+
+        let sent = sink.sent.lock().unwrap();
+        assert_eq!(sent[0].0, "task-queue.task_created");
+        assert_eq!(sent[1].0, "analytics.completions");
+        assert_eq!(sent[1].1, Some(task.id.to_string()));
+        assert_eq!(sent[1].2.sequence, 2);
+        assert_eq!(sent[1].2.status, Some(TaskStatus::Completed));
+    }
+
+    #[tokio::test]
+    async fn test_sequence_resumes_after_undelivered_events() {
+        let storage = Arc::new(StorageEngine::temporary().unwrap());
+        let task = Task::new("Build").build();
+
+        let bus = EventBus::new();
+        bus.enable_outbox(storage.clone()).unwrap();
+        bus.task_created(&task).await;
+        bus.task_deleted(&task).await;
+
+        // A restarted server must not reuse sequences still in the outbox
+        let bus = EventBus::new();
+        bus.enable_outbox(storage.clone()).unwrap();
+        assert_eq!(bus.task_created(&task).await.sequence, 3);
+    }
+}
@@ -0,0 +1,240 @@
+//! Queues Module
+//!
+//! Named queues keep kinds of traffic apart, e.g. `ci` from `ai-review`.
+//! A queue is created with `POST /queues` and a task joins it by naming it
+//! in the `queue` field when submitted, which puts the name in the task's
+//! `queue` metadata. A queue may cap how many of its tasks run at once and
+//! how fast they start, counting both the execution engine and external
+//! workers, and give its tasks a retry policy of their own. Tasks without a
+//! queue, or in one since deleted, run without these limits.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::Task;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Task metadata key naming the queue the task was submitted to
+pub const QUEUE_KEY: &str = "queue";
+/// Longest queue name
+pub const MAX_QUEUE_NAME_LENGTH: usize = 64;
+
+/// How fast a queue's tasks may start
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct QueueRateLimit {
+    /// Rate the allowance refills at
+    pub tasks_per_minute: u32,
+    /// Tasks that may start at once from a full allowance
+    pub burst: u32,
+}
+
+/// Retries of the tasks submitted to a queue, in place of the usual ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct RetryPolicy {
+    /// Retries after the first failed execution
+    pub attempts: u32,
+    /// Delay before the first retry, doubling for each one after
+    pub delay_secs: u64,
+}
+
+impl RetryPolicy {
+    pub fn apply(&self, task: &mut Task) {
+        task.retry_attempts = self.attempts;
+        task.retry_delay = Duration::from_secs(self.delay_secs);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Queue {
+    pub name: String,
+    pub description: Option<String>,
+    /// Most of the queue's tasks running at once; unlimited if unset
+    pub max_concurrency: Option<u32>,
+    pub rate_limit: Option<QueueRateLimit>,
+    pub retry: Option<RetryPolicy>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Body of `POST /queues` and `PUT /queues/{name}`; the name in the path
+/// wins over the one in the body
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CreateQueueRequest {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub max_concurrency: Option<u32>,
+    #[serde(default)]
+    pub rate_limit: Option<QueueRateLimit>,
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+}
+
+impl CreateQueueRequest {
+    pub fn into_queue(self, created_at: DateTime<Utc>, now: DateTime<Utc>) -> Queue {
+        Queue {
+            name: self.name,
+            description: self.description,
+            max_concurrency: self.max_concurrency,
+            rate_limit: self.rate_limit,
+            retry: self.retry,
+            created_at,
+            updated_at: now,
+        }
+    }
+}
+
+/// A queue with how many of its tasks wait and run, for `GET /queues`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct QueueStatus {
+    #[serde(flatten)]
+    pub queue: Queue,
+    pub pending: usize,
+    pub running: usize,
+}
+
+/// Whether a queue name is short and only letters, digits, `-`, `_` and `.`
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.chars().count() <= MAX_QUEUE_NAME_LENGTH
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// The queue a task was submitted to, if any
+pub fn queue_name(task: &Task) -> Option<&str> {
+    task.metadata.get(QUEUE_KEY)?.as_str()
+}
+
+/// A queue's start allowance
+#[derive(Debug, Clone, Copy)]
+struct Allowance {
+    tokens: u32,
+    refilled_at: Instant,
+}
+
+/// Start allowances of the rate-limited queues, kept across dispatches
+#[derive(Debug, Default)]
+pub struct QueueLimiter {
+    allowances: Mutex<HashMap<String, Allowance>>,
+}
+
+impl QueueLimiter {
+    /// Take one start from the queue's allowance, if it has one left
+    pub fn try_start(&self, queue: &str, limit: &QueueRateLimit, now: Instant) -> bool {
+        let mut allowances = self.allowances.lock().unwrap();
+        let burst = limit.burst.max(1);
+        let allowance = allowances.entry(queue.to_string()).or_insert(Allowance { tokens: burst, refilled_at: now });
+
+        // Whole starts earned since the last refill, keeping the rest of the interval
+        let interval = Duration::from_secs(60) / limit.tasks_per_minute.max(1);
+        let earned = (now.saturating_duration_since(allowance.refilled_at).as_nanos() / interval.as_nanos().max(1)).min(u32::MAX as u128) as u32;
+        allowance.tokens = allowance.tokens.saturating_add(earned).min(burst);
+        allowance.refilled_at = if allowance.tokens == burst { now } else { allowance.refilled_at + interval.saturating_mul(earned) };
+
+        if allowance.tokens == 0 {
+            return false;
+        }
+        allowance.tokens -= 1;
+        true
+    }
+
+    /// Start over with a full allowance, e.g. after the limit changed
+    pub fn reset(&self, queue: &str) {
+        self.allowances.lock().unwrap().remove(queue);
+    }
+}
+
+/// Decides which ready tasks may start in one dispatch, given the queues'
+/// limits and the tasks of each already running
+pub struct QueueGate<'a> {
+    queues: HashMap<String, Queue>,
+    running: HashMap<String, usize>,
+    limiter: &'a QueueLimiter,
+}
+
+impl<'a> QueueGate<'a> {
+    pub fn new(queues: Vec<Queue>, running: &[Task], limiter: &'a QueueLimiter) -> Self {
+        let mut counts = HashMap::new();
+        for name in running.iter().filter_map(queue_name) {
+            *counts.entry(name.to_string()).or_insert(0) += 1;
+        }
+        Self {
+            queues: queues.into_iter().map(|queue| (queue.name.clone(), queue)).collect(),
+            running: counts,
+            limiter,
+        }
+    }
+
+    /// Whether `task` may start now, counting it as running if so
+    pub fn admit(&mut self, task: &Task) -> bool {
+        let Some(queue) = queue_name(task).and_then(|name| self.queues.get(name)) else {
+            return true;
+        };
+        let running = self.running.get(&queue.name).copied().unwrap_or(0);
+        if queue.max_concurrency.is_some_and(|max| running >= max as usize) {
+            return false;
+        }
+        if let Some(limit) = &queue.rate_limit
+            && !self.limiter.try_start(&queue.name, limit, Instant::now())
+        {
+            return false;
+        }
+        self.running.insert(queue.name.clone(), running + 1);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_gate_applies_concurrency_and_rate_limits() {
+        let now = Utc::now();
+        let queue = |name: &str, max_concurrency, rate_limit| CreateQueueRequest {
+            name: name.to_string(),
+            description: None,
+            max_concurrency,
+            rate_limit,
+            retry: None,
+        }.into_queue(now, now);
+        let task = |queue: Option<&str>| {
+            let mut task = Task::new("job").with_command("true").build();
+            if let Some(queue) = queue {
+                task.metadata.insert(QUEUE_KEY.to_string(), json!(queue));
+            }
+            task
+        };
+        assert!(is_valid_name("ai-review") && !is_valid_name("a b") && !is_valid_name(""));
+
+        let limiter = QueueLimiter::default();
+        let queues = vec![
+            queue("ci", Some(2), None),
+            queue("review", None, Some(QueueRateLimit { tasks_per_minute: 60, burst: 1 })),
+        ];
+        let mut gate = QueueGate::new(queues, &[task(Some("ci"))], &limiter);
+        assert!(gate.admit(&task(Some("ci"))));
+        assert!(!gate.admit(&task(Some("ci"))));
+        assert!(gate.admit(&task(Some("review"))));
+        assert!(!gate.admit(&task(Some("review"))));
+        // No queue, or one that doesn't exist, isn't limited
+        assert!(gate.admit(&task(None)) && gate.admit(&task(Some("gone"))));
+
+        let limit = QueueRateLimit { tasks_per_minute: 60, burst: 2 };
+        let start = Instant::now();
+        assert!(limiter.try_start("slow", &limit, start) && limiter.try_start("slow", &limit, start));
+        assert!(!limiter.try_start("slow", &limit, start + Duration::from_millis(500)));
+        assert!(limiter.try_start("slow", &limit, start + Duration::from_secs(1)));
+    }
+}
@@ -3,13 +3,24 @@
 //! Provides comprehensive rate limiting and throttling capabilities to protect
 //! the Task Queue API from abuse and ensure fair resource usage.
 //!
+//! The REST API gives every client a token bucket per route group: reads,
+//! writes and `/admin` endpoints, each sized by `server.rate_limits`. A client
+//! is the tenant key or operator its `X-API-Key` or bearer token
+//! authenticates as, or else its address, so unknown keys can't be rotated
+//! for fresh buckets.
+//! A request over the limit is answered `429 Too Many Requests` with a
+//! `Retry-After` header saying when the next one will be let through.
+//!
 
 #![allow(unused_imports)]
 #![allow(unused_variables)]
 #![allow(dead_code)]
 #![allow(unused_mut)]
 
+use axum::http::Method;
+use crate::tenants::Principal;
 use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use serde::{Deserialize, Serialize};
@@ -123,6 +134,11 @@ impl RateLimiter {
 
     /// Check if a request should be allowed
     pub async fn is_allowed(&self, client_id: &str) -> bool {
+        self.check(client_id).await.is_ok()
+    }
+
+    /// Let a request through, or say how long the client has to wait
+    pub async fn check(&self, client_id: &str) -> Result<(), Duration> {
         let start_time = Instant::now();
         let mut entries = self.entries.write().await;
         let mut metrics = self.metrics.write().unwrap();
@@ -133,14 +149,17 @@ impl RateLimiter {
             RateLimitEntry::new(self.config.burst_size.unwrap_or(self.config.requests_per_minute))
         });
 
-        let allowed = match self.config.algorithm {
-            RateLimitAlgorithm::TokenBucket => self.check_token_bucket(entry),
-            RateLimitAlgorithm::SlidingWindow => self.check_sliding_window(entry),
-            RateLimitAlgorithm::FixedWindow => self.check_fixed_window(entry),
-            RateLimitAlgorithm::LeakyBucket => self.check_leaky_bucket(entry),
+        let allowed = match entry.blocked_until {
+            Some(blocked_until) if start_time < blocked_until => Err(blocked_until - start_time),
+            _ => match self.config.algorithm {
+                RateLimitAlgorithm::TokenBucket => self.check_token_bucket(entry),
+                RateLimitAlgorithm::SlidingWindow => self.check_sliding_window(entry),
+                RateLimitAlgorithm::FixedWindow => self.check_fixed_window(entry),
+                RateLimitAlgorithm::LeakyBucket => self.check_leaky_bucket(entry),
+            },
         };
 
-        if allowed {
+        if allowed.is_ok() {
             metrics.allowed_requests += 1;
             debug!("Request allowed for client: {}", client_id);
         } else {
@@ -157,28 +176,39 @@ impl RateLimiter {
         allowed
     }
 
+    /// Time it takes to earn or leak one token
+    fn token_interval(&self) -> Duration {
+        Duration::from_secs(60) / self.config.requests_per_minute.max(1)
+    }
+
+    /// Whole tokens earned since `since`, and the time they took
+    fn tokens_since(&self, since: Instant, now: Instant) -> (u32, Duration) {
+        let interval = self.token_interval();
+        let tokens = (now.duration_since(since).as_nanos() / interval.as_nanos().max(1)).min(u32::MAX as u128) as u32;
+        (tokens, interval.saturating_mul(tokens))
+    }
+
     /// Check rate limit using token bucket algorithm
-    fn check_token_bucket(&self, entry: &mut RateLimitEntry) -> bool {
+    fn check_token_bucket(&self, entry: &mut RateLimitEntry) -> Result<(), Duration> {
         let now = Instant::now();
-        let time_passed = now.duration_since(entry.last_refill);
-        
-        // Refill tokens based on time passed
-        let tokens_to_add = (time_passed.as_secs() * self.config.requests_per_minute as u64) / 60;
-        entry.tokens = (entry.tokens + tokens_to_add as u32).min(
-            self.config.burst_size.unwrap_or(self.config.requests_per_minute)
-        );
-        entry.last_refill = now;
+        let max_tokens = self.config.burst_size.unwrap_or(self.config.requests_per_minute);
+
+        // Refill tokens based on time passed, keeping the part of an
+        // interval that hasn't earned a whole token yet
+        let (tokens_to_add, earned_in) = self.tokens_since(entry.last_refill, now);
+        entry.tokens = entry.tokens.saturating_add(tokens_to_add).min(max_tokens);
+        entry.last_refill = if entry.tokens == max_tokens { now } else { entry.last_refill + earned_in };
 
         if entry.tokens > 0 {
             entry.tokens -= 1;
-            true
+            Ok(())
         } else {
-            false
+            Err(self.token_interval().saturating_sub(now.duration_since(entry.last_refill)))
         }
     }
 
     /// Check rate limit using sliding window algorithm
-    fn check_sliding_window(&self, entry: &mut RateLimitEntry) -> bool {
+    fn check_sliding_window(&self, entry: &mut RateLimitEntry) -> Result<(), Duration> {
         let now = Instant::now();
         
         // Reset window if it has expired
@@ -189,14 +219,14 @@ impl RateLimiter {
 
         if entry.request_count < self.config.requests_per_minute {
             entry.request_count += 1;
-            true
+            Ok(())
         } else {
-            false
+            Err(self.config.window_size.saturating_sub(now.duration_since(entry.window_start)))
         }
     }
 
     /// Check rate limit using fixed window algorithm
-    fn check_fixed_window(&self, entry: &mut RateLimitEntry) -> bool {
+    fn check_fixed_window(&self, entry: &mut RateLimitEntry) -> Result<(), Duration> {
         let now = Instant::now();
         let window_duration = self.config.window_size;
         
@@ -208,29 +238,28 @@ impl RateLimiter {
 
         if entry.request_count < self.config.requests_per_minute {
             entry.request_count += 1;
-            true
+            Ok(())
         } else {
-            false
+            Err(self.config.window_size.saturating_sub(now.duration_since(entry.window_start)))
         }
     }
 
     /// Check rate limit using leaky bucket algorithm
-    fn check_leaky_bucket(&self, entry: &mut RateLimitEntry) -> bool {
+    fn check_leaky_bucket(&self, entry: &mut RateLimitEntry) -> Result<(), Duration> {
         let now = Instant::now();
-        let time_passed = now.duration_since(entry.last_refill);
-        
+
         // Leak tokens based on time passed
-        let tokens_to_leak = (time_passed.as_secs() * self.config.requests_per_minute as u64) / 60;
-        entry.tokens = entry.tokens.saturating_sub(tokens_to_leak as u32);
-        entry.last_refill = now;
+        let (tokens_to_leak, leaked_in) = self.tokens_since(entry.last_refill, now);
+        entry.tokens = entry.tokens.saturating_sub(tokens_to_leak);
+        entry.last_refill = if entry.tokens == 0 { now } else { entry.last_refill + leaked_in };
 
         let max_tokens = self.config.burst_size.unwrap_or(self.config.requests_per_minute);
         
         if entry.tokens < max_tokens {
             entry.tokens += 1;
-            true
+            Ok(())
         } else {
-            false
+            Err(self.token_interval().saturating_sub(now.duration_since(entry.last_refill)))
         }
     }
 
@@ -246,12 +275,11 @@ impl RateLimiter {
     /// Check if a client is currently blocked
     pub async fn is_blocked(&self, client_id: &str) -> bool {
         let entries = self.entries.read().await;
-        if let Some(entry) = entries.get(client_id) {
-            if let Some(blocked_until) = entry.blocked_until {
-                if Instant::now() < blocked_until {
-                    return true;
-                }
-            }
+        if let Some(entry) = entries.get(client_id)
+            && let Some(blocked_until) = entry.blocked_until
+            && Instant::now() < blocked_until
+        {
+            return true;
         }
         false
     }
@@ -406,11 +434,11 @@ impl Throttler {
 
     /// Complete a request
     pub async fn complete_request(&self, request_id: &str) -> bool {
-        let mut active_requests = self.active_requests.write().await;
-        
-        if active_requests.remove(request_id).is_some() {
+        let removed = self.active_requests.write().await.remove(request_id).is_some();
+
+        if removed {
             debug!("Request {} completed", request_id);
-            
+
             // Process next request from queue
             self.process_next_request().await;
             return true;
@@ -424,12 +452,12 @@ impl Throttler {
         let mut request_queue = self.request_queue.write().await;
         let mut active_requests = self.active_requests.write().await;
 
-        if active_requests.len() < self.config.max_concurrent_requests as usize {
-            if let Some(request) = request_queue.pop() {
-                let request_id = request.id.clone();
-                active_requests.insert(request_id.clone(), request);
-                debug!("Processed queued request {}", request_id);
-            }
+        if active_requests.len() < self.config.max_concurrent_requests as usize
+            && let Some(request) = request_queue.pop()
+        {
+            let request_id = request.id.clone();
+            active_requests.insert(request_id.clone(), request);
+            debug!("Processed queued request {}", request_id);
         }
     }
 
@@ -462,6 +490,138 @@ impl Throttler {
     }
 }
 
+/// Requests a client may make to one route group
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimitBucket {
+    /// Rate the bucket refills at
+    pub requests_per_minute: u32,
+    /// Requests that may be made at once from a full bucket
+    pub burst: u32,
+}
+
+impl RateLimitBucket {
+    pub fn limiter(&self) -> RateLimiter {
+        RateLimiter::new(RateLimitConfig {
+            algorithm: RateLimitAlgorithm::TokenBucket,
+            requests_per_minute: self.requests_per_minute,
+            burst_size: Some(self.burst),
+            ..RateLimitConfig::default()
+        })
+    }
+}
+
+/// Per-client limits of the REST API
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpRateLimitConfig {
+    pub enabled: bool,
+    /// `GET` and `HEAD` requests
+    pub read: RateLimitBucket,
+    /// Requests that change something
+    pub write: RateLimitBucket,
+    /// `/admin` endpoints, whatever the method
+    pub admin: RateLimitBucket,
+}
+
+impl Default for HttpRateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            read: RateLimitBucket { requests_per_minute: 600, burst: 120 },
+            write: RateLimitBucket { requests_per_minute: 120, burst: 60 },
+            admin: RateLimitBucket { requests_per_minute: 10, burst: 10 },
+        }
+    }
+}
+
+/// The bucket a route draws from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RouteGroup {
+    Read,
+    Write,
+    Admin,
+}
+
+impl RouteGroup {
+    /// Group of a request to an API path relative to the API root; `None`
+    /// for health checks, which are never limited
+    pub fn of(method: &Method, path: &str) -> Option<Self> {
+        if path == "/health" {
+            None
+        } else if path == "/admin" || path.starts_with("/admin/") {
+            Some(Self::Admin)
+        } else if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) {
+            Some(Self::Read)
+        } else {
+            Some(Self::Write)
+        }
+    }
+}
+
+/// The API's limiters, one per route group
+pub struct HttpRateLimiter {
+    enabled: bool,
+    read: RateLimiter,
+    write: RateLimiter,
+    admin: RateLimiter,
+}
+
+impl HttpRateLimiter {
+    pub fn new(config: &HttpRateLimitConfig) -> Self {
+        Self {
+            enabled: config.enabled,
+            read: config.read.limiter(),
+            write: config.write.limiter(),
+            admin: config.admin.limiter(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn limiter(&self, group: RouteGroup) -> &RateLimiter {
+        match group {
+            RouteGroup::Read => &self.read,
+            RouteGroup::Write => &self.write,
+            RouteGroup::Admin => &self.admin,
+        }
+    }
+
+    /// Let a client's request through, or say how long it has to wait
+    pub async fn check(&self, group: RouteGroup, client_id: &str) -> Result<(), Duration> {
+        if !self.enabled {
+            return Ok(());
+        }
+        self.limiter(group).check(client_id).await
+    }
+
+    /// Forget clients idle for an hour, in the background
+    pub async fn start_cleanup_task(&self) {
+        for group in [RouteGroup::Read, RouteGroup::Write, RouteGroup::Admin] {
+            self.limiter(group).start_cleanup_task().await;
+        }
+    }
+}
+
+impl Default for HttpRateLimiter {
+    fn default() -> Self {
+        Self::new(&HttpRateLimitConfig::default())
+    }
+}
+
+/// Who a request is counted against: the principal its credential
+/// authenticated as, or else the address it came from
+pub fn client_id(principal: Option<&Principal>, peer: Option<SocketAddr>) -> String {
+    match (principal, peer) {
+        (Some(Principal::Tenant { key_id, .. }), _) => format!("key:{}", key_id),
+        (Some(Principal::Operator), _) => "operator".to_string(),
+        (None, Some(peer)) => format!("ip:{}", peer.ip()),
+        (None, None) => "ip:unknown".to_string(),
+    }
+}
+
 /// Rate limiter factory
 pub struct RateLimiterFactory;
 
@@ -586,4 +746,28 @@ mod tests {
         assert_eq!(metrics.allowed_requests, 3);
         assert_eq!(metrics.blocked_requests, 0);
     }
+
+    #[tokio::test]
+    async fn test_route_groups_have_their_own_buckets() {
+        let bucket = RateLimitBucket { requests_per_minute: 60, burst: 2 };
+        let limiter = HttpRateLimiter::new(&HttpRateLimitConfig { enabled: true, read: bucket, write: bucket, admin: bucket });
+        assert!(limiter.check(RouteGroup::Write, "ip:10.0.0.1").await.is_ok());
+        assert!(limiter.check(RouteGroup::Write, "ip:10.0.0.1").await.is_ok());
+        let retry_after = limiter.check(RouteGroup::Write, "ip:10.0.0.1").await.unwrap_err();
+        assert!(retry_after > Duration::ZERO && retry_after <= Duration::from_secs(1));
+        assert!(limiter.check(RouteGroup::Read, "ip:10.0.0.1").await.is_ok());
+        assert!(limiter.check(RouteGroup::Write, "ip:10.0.0.2").await.is_ok());
+
+        assert_eq!(RouteGroup::of(&Method::POST, "/admin/backup"), Some(RouteGroup::Admin));
+        assert_eq!(RouteGroup::of(&Method::GET, "/tasks"), Some(RouteGroup::Read));
+        assert_eq!(RouteGroup::of(&Method::GET, "/health"), None);
+
+        let peer: SocketAddr = "10.0.0.1:4000".parse().unwrap();
+        assert_eq!(client_id(None, Some(peer)), "ip:10.0.0.1");
+        assert_eq!(client_id(None, None), "ip:unknown");
+        let key_id = uuid::Uuid::new_v4();
+        let tenant = Principal::Tenant { tenant_id: uuid::Uuid::new_v4(), key_id, scopes: Vec::new() };
+        assert_eq!(client_id(Some(&tenant), Some(peer)), format!("key:{}", key_id));
+        assert_eq!(client_id(Some(&Principal::Operator), Some(peer)), "operator");
+    }
 }
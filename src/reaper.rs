@@ -0,0 +1,72 @@
+//! Stale Task Reaper Module
+//!
+//! While the server executes a task it records a heartbeat for it every
+//! [`HEARTBEAT_INTERVAL`]. If the process dies mid-execution the task would
+//! otherwise stay `Running` forever; the reaper finds running tasks whose
+//! heartbeat has gone stale and records them as failed, so the task's retry
+//! policy re-queues them or they end `Failed`. Tasks leased to external
+//! workers have no heartbeat here: their lease expiring takes them back.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::*;
+use crate::server::TaskQueueServer;
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, warn};
+
+/// How often executing tasks record a heartbeat and the reaper looks for
+/// stale ones
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Least time without a heartbeat before a task counts as stale, so a slow
+/// storage write or two don't get live tasks reaped
+pub const MIN_STALE_AFTER: Duration = Duration::from_secs(3 * HEARTBEAT_INTERVAL.as_secs());
+
+/// Error recorded on reaped tasks
+pub fn stale_error(last_heartbeat: DateTime<Utc>) -> String {
+    format!(
+        "Task stopped reporting heartbeats (last at {}); the server executing it probably died",
+        last_heartbeat.to_rfc3339()
+    )
+}
+
+/// Whether a heartbeat recorded at `last` is too old at `now`
+pub fn is_stale(last: DateTime<Utc>, now: DateTime<Utc>, stale_after: Duration) -> bool {
+    let stale_after = stale_after.max(MIN_STALE_AFTER);
+    now - last >= chrono::Duration::from_std(stale_after).unwrap_or(chrono::Duration::MAX)
+}
+
+/// Reap stale tasks every [`HEARTBEAT_INTERVAL`] in the background
+pub fn spawn(server: Arc<TaskQueueServer>, stale_after: Duration) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(HEARTBEAT_INTERVAL);
+        loop {
+            ticker.tick().await;
+            match server.reap_stale_tasks(Utc::now(), stale_after).await {
+                Ok(reaped) if !reaped.is_empty() => warn!("Reaped {} stale running tasks", reaped.len()),
+                Ok(_) => {}
+                Err(e) => error!("Failed to reap stale tasks: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_staleness_has_a_floor() {
+        let now = Utc::now();
+        let last = now - chrono::Duration::seconds(20);
+        assert!(!is_stale(last, now, Duration::from_secs(1)));
+        assert!(is_stale(now - chrono::Duration::seconds(30), now, Duration::from_secs(1)));
+        assert!(!is_stale(now - chrono::Duration::seconds(59), now, Duration::from_secs(60)));
+    }
+}
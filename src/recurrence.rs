@@ -0,0 +1,441 @@
+//! Recurring Tasks Module
+//!
+//! Recurring task definitions: a task template plus a schedule, either a fixed
+//! interval or an RRULE-style rule (RFC 5545 subset). Whenever a definition's
+//! next run comes due a new task instance is created from the template, with
+//! the definition's ID in its `recurrence_id` metadata. Runs missed while the
+//! server was down or the recurrence was paused are collapsed into one.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::{CreateTaskRequest, Task};
+use crate::error::{TaskQueueError, Result};
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, TimeZone, Timelike, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Task metadata key holding the ID of the recurrence that created the task
+pub const RECURRENCE_ID_KEY: &str = "recurrence_id";
+
+/// Periods searched for the next occurrence before a rule is considered exhausted
+const MAX_PERIODS: i64 = 100_000;
+
+fn invalid(reason: impl Into<String>) -> TaskQueueError {
+    TaskQueueError::ValidationError { reason: reason.into() }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Minutely,
+    Hourly,
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+/// Parsed `FREQ=...;INTERVAL=...` rule
+///
+/// Supports `FREQ` (MINUTELY to MONTHLY), `INTERVAL`, `BYDAY`, `BYHOUR`,
+/// `BYMINUTE`, `COUNT` and `UNTIL`. Weeks start on Monday.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecurrenceRule {
+    pub frequency: Frequency,
+    pub interval: u32,
+    pub by_day: Vec<Weekday>,
+    pub by_hour: Vec<u32>,
+    pub by_minute: Vec<u32>,
+    pub count: Option<u64>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+fn parse_list(name: &str, value: &str, max: u32) -> Result<Vec<u32>> {
+    value
+        .split(',')
+        .map(|item| match item.trim().parse::<u32>() {
+            Ok(n) if n <= max => Ok(n),
+            _ => Err(invalid(format!("Invalid {} value '{}'", name, item))),
+        })
+        .collect()
+}
+
+fn parse_weekday(value: &str) -> Result<Weekday> {
+    Ok(match value.trim() {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        other => return Err(invalid(format!("Invalid BYDAY value '{}'", other))),
+    })
+}
+
+fn parse_until(value: &str) -> Result<DateTime<Utc>> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    if let Ok(at) = chrono::NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ") {
+        return Ok(at.and_utc());
+    }
+    NaiveDate::parse_from_str(value, "%Y%m%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(23, 59, 59))
+        .map(|at| at.and_utc())
+        .ok_or_else(|| invalid(format!("Invalid UNTIL value '{}'", value)))
+}
+
+impl RecurrenceRule {
+    /// Parse a rule such as `FREQ=WEEKLY;BYDAY=MO,WE;BYHOUR=9;BYMINUTE=30`
+    pub fn parse(rule: &str) -> Result<Self> {
+        let rule = rule.trim();
+        let rule = rule.strip_prefix("RRULE:").unwrap_or(rule);
+
+        let mut frequency = None;
+        let mut parsed = Self {
+            frequency: Frequency::Daily,
+            interval: 1,
+            by_day: Vec::new(),
+            by_hour: Vec::new(),
+            by_minute: Vec::new(),
+            count: None,
+            until: None,
+        };
+
+        for part in rule.split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part.split_once('=').ok_or_else(|| invalid(format!("Invalid rule part '{}'", part)))?;
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.to_ascii_uppercase().as_str() {
+                        "MINUTELY" => Frequency::Minutely,
+                        "HOURLY" => Frequency::Hourly,
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        other => return Err(invalid(format!("Unsupported FREQ '{}'", other))),
+                    })
+                }
+                "INTERVAL" => match value.parse::<u32>() {
+                    Ok(n) if n > 0 => parsed.interval = n,
+                    _ => return Err(invalid(format!("Invalid INTERVAL '{}'", value))),
+                },
+                "BYDAY" => parsed.by_day = value.split(',').map(parse_weekday).collect::<Result<_>>()?,
+                "BYHOUR" => parsed.by_hour = parse_list("BYHOUR", value, 23)?,
+                "BYMINUTE" => parsed.by_minute = parse_list("BYMINUTE", value, 59)?,
+                "COUNT" => match value.parse::<u64>() {
+                    Ok(n) if n > 0 => parsed.count = Some(n),
+                    _ => return Err(invalid(format!("Invalid COUNT '{}'", value))),
+                },
+                "UNTIL" => parsed.until = Some(parse_until(value)?),
+                other => return Err(invalid(format!("Unsupported rule part '{}'", other))),
+            }
+        }
+
+        parsed.frequency = frequency.ok_or_else(|| invalid("Rule has no FREQ"))?;
+        parsed.by_hour.sort_unstable();
+        parsed.by_hour.dedup();
+        parsed.by_minute.sort_unstable();
+        parsed.by_minute.dedup();
+        Ok(parsed)
+    }
+
+    /// Start of the `k`-th period after `start`
+    fn period(&self, start: DateTime<Utc>, k: i64) -> Option<DateTime<Utc>> {
+        let steps = k * self.interval as i64;
+        match self.frequency {
+            Frequency::Minutely => start.checked_add_signed(Duration::minutes(steps)),
+            Frequency::Hourly => start.checked_add_signed(Duration::hours(steps)),
+            Frequency::Daily => start.checked_add_signed(Duration::days(steps)),
+            Frequency::Weekly => start.checked_add_signed(Duration::weeks(steps)),
+            Frequency::Monthly => start.checked_add_months(Months::new(u32::try_from(steps).ok()?)),
+        }
+    }
+
+    /// First period that can hold an occurrence after `after`
+    fn first_period(&self, start: DateTime<Utc>, after: DateTime<Utc>) -> i64 {
+        if after <= start {
+            return 0;
+        }
+        let elapsed = match self.frequency {
+            Frequency::Minutely => (after - start).num_minutes(),
+            Frequency::Hourly => (after - start).num_hours(),
+            Frequency::Daily => (after - start).num_days(),
+            Frequency::Weekly => (after - start).num_weeks(),
+            Frequency::Monthly => {
+                (after.year() - start.year()) as i64 * 12 + after.month() as i64 - start.month() as i64
+            }
+        };
+        (elapsed / self.interval as i64 - 1).max(0)
+    }
+
+    /// Occurrences within the period starting at `period`, in order
+    fn expand(&self, start: DateTime<Utc>, period: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+        let hours = if self.by_hour.is_empty() { vec![start.hour()] } else { self.by_hour.clone() };
+        let minutes = if self.by_minute.is_empty() { vec![start.minute()] } else { self.by_minute.clone() };
+        let times: Vec<(u32, u32)> = hours.iter().flat_map(|h| minutes.iter().map(move |m| (*h, *m))).collect();
+        let on = |date: NaiveDate| -> Vec<DateTime<Utc>> {
+            times.iter()
+                .filter_map(|(h, m)| date.and_hms_opt(*h, *m, start.second()))
+                .map(|at| at.and_utc())
+                .collect()
+        };
+
+        let mut occurrences: Vec<DateTime<Utc>> = match self.frequency {
+            Frequency::Minutely => vec![period],
+            Frequency::Hourly => minutes.iter()
+                .filter_map(|m| period.date_naive().and_hms_opt(period.hour(), *m, start.second()))
+                .map(|at| at.and_utc())
+                .collect(),
+            Frequency::Daily => on(period.date_naive()),
+            Frequency::Weekly => {
+                let days = if self.by_day.is_empty() { vec![start.weekday()] } else { self.by_day.clone() };
+                let monday = period.date_naive() - Duration::days(period.weekday().num_days_from_monday() as i64);
+                (0..7)
+                    .map(|offset| monday + Duration::days(offset))
+                    .filter(|date| days.contains(&date.weekday()))
+                    .flat_map(on)
+                    .collect()
+            }
+            // Months too short for the start day are skipped
+            Frequency::Monthly => NaiveDate::from_ymd_opt(period.year(), period.month(), start.day())
+                .map(on)
+                .unwrap_or_default(),
+        };
+
+        // Finer BYxxx parts than the frequency expands act as filters
+        occurrences.retain(|occurrence| {
+            (self.by_day.is_empty() || self.by_day.contains(&occurrence.weekday()))
+                && (self.by_hour.is_empty() || self.by_hour.contains(&occurrence.hour()))
+                && (self.by_minute.is_empty() || self.by_minute.contains(&occurrence.minute()))
+        });
+        occurrences.sort();
+        occurrences
+    }
+
+    /// First occurrence strictly after `after`, counting from `start`
+    pub fn next_after(&self, start: DateTime<Utc>, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let first = self.first_period(start, after);
+        for k in first..first + MAX_PERIODS {
+            let period = self.period(start, k)?;
+            if let Some(occurrence) = self.expand(start, period).into_iter().find(|o| *o >= start && *o > after) {
+                return match self.until {
+                    Some(until) if occurrence > until => None,
+                    _ => Some(occurrence),
+                };
+            }
+            if self.until.is_some_and(|until| period > until) {
+                return None;
+            }
+        }
+        None
+    }
+}
+
+/// When a recurring task runs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Schedule {
+    /// Every `every_secs` seconds from the start time
+    Interval { every_secs: u64 },
+    /// RRULE-style rule, e.g. `FREQ=DAILY;BYHOUR=2;BYMINUTE=0`
+    Rule { rrule: String },
+}
+
+impl Schedule {
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Schedule::Interval { every_secs: 0 } => Err(invalid("every_secs must be at least 1")),
+            Schedule::Interval { .. } => Ok(()),
+            Schedule::Rule { rrule } => RecurrenceRule::parse(rrule).map(|_| ()),
+        }
+    }
+
+    /// Maximum number of runs, if the schedule has one
+    pub fn count(&self) -> Option<u64> {
+        match self {
+            Schedule::Interval { .. } => None,
+            Schedule::Rule { rrule } => RecurrenceRule::parse(rrule).ok()?.count,
+        }
+    }
+
+    /// First run strictly after `after`, counting from `start`
+    pub fn next_after(&self, start: DateTime<Utc>, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Schedule::Interval { every_secs } => {
+                if after < start {
+                    return Some(start);
+                }
+                let every = *every_secs as i64;
+                let runs = (after - start).num_seconds() / every + 1;
+                start.checked_add_signed(Duration::seconds(runs.checked_mul(every)?))
+            }
+            Schedule::Rule { rrule } => RecurrenceRule::parse(rrule).ok()?.next_after(start, after),
+        }
+    }
+}
+
+/// Body of `POST /recurrences`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateRecurrenceRequest {
+    /// Defaults to the template's task name
+    pub name: Option<String>,
+    pub schedule: Schedule,
+    /// Defaults to now
+    pub starts_at: Option<DateTime<Utc>>,
+    /// Task created on every run
+    pub task: CreateTaskRequest,
+}
+
+/// A recurring task definition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecurringTask {
+    pub id: Uuid,
+    pub name: String,
+    pub schedule: Schedule,
+    pub starts_at: DateTime<Utc>,
+    pub template: CreateTaskRequest,
+    pub paused: bool,
+    /// `None` once the schedule has no runs left
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub last_task_id: Option<Uuid>,
+    /// Task instances created so far
+    pub occurrences: u64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Tenant it belongs to, which also owns the tasks it creates
+    #[serde(default)]
+    pub tenant_id: Option<Uuid>,
+}
+
+impl RecurringTask {
+    pub fn new(request: CreateRecurrenceRequest, now: DateTime<Utc>) -> Result<Self> {
+        request.schedule.validate()?;
+        let name = request.name.unwrap_or_else(|| request.task.name.clone());
+        if name.trim().is_empty() {
+            return Err(invalid("Recurrence name cannot be empty"));
+        }
+
+        let starts_at = request.starts_at.unwrap_or(now);
+        let after = if starts_at >= now { starts_at - Duration::seconds(1) } else { now };
+        Ok(Self {
+            id: Uuid::new_v4(),
+            name,
+            next_run_at: request.schedule.next_after(starts_at, after),
+            schedule: request.schedule,
+            starts_at,
+            template: request.task,
+            paused: false,
+            last_run_at: None,
+            last_task_id: None,
+            occurrences: 0,
+            created_at: now,
+            updated_at: now,
+            tenant_id: None,
+        })
+    }
+
+    /// Whether a new instance should be created now
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        !self.paused && self.next_run_at.is_some_and(|at| at <= now)
+    }
+
+    /// Task instance for the run at `run_at`
+    pub fn instance(&self, run_at: DateTime<Utc>) -> Task {
+        let mut request = self.template.clone();
+        request.run_at = Some(run_at);
+        let mut task = request.to_task();
+        task.metadata.insert(RECURRENCE_ID_KEY.to_string(), serde_json::json!(self.id));
+        task
+    }
+
+    /// Record that the run at `run_at` created `task_id` and move on to the
+    /// first run after `now`
+    pub fn advance(&mut self, run_at: DateTime<Utc>, task_id: Uuid, now: DateTime<Utc>) {
+        self.occurrences += 1;
+        self.last_run_at = Some(run_at);
+        self.last_task_id = Some(task_id);
+        self.next_run_at = match self.schedule.count() {
+            Some(count) if self.occurrences >= count => None,
+            _ => self.schedule.next_after(self.starts_at, now.max(run_at)),
+        };
+        self.updated_at = now;
+    }
+
+    /// Pause or resume; resuming skips the runs missed while paused
+    pub fn set_paused(&mut self, paused: bool, now: DateTime<Utc>) {
+        if self.paused && !paused && self.next_run_at.is_some_and(|at| at < now) {
+            self.next_run_at = self.schedule.next_after(self.starts_at, now);
+        }
+        self.paused = paused;
+        self.updated_at = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_rules_and_intervals() {
+        // Monday 2025-01-06 10:00
+        let start = at("2025-01-06T10:00:00Z");
+
+        let weekly = RecurrenceRule::parse("RRULE:FREQ=WEEKLY;BYDAY=MO,WE;BYHOUR=9;BYMINUTE=30").unwrap();
+        assert_eq!(weekly.next_after(start, start), Some(at("2025-01-08T09:30:00Z")));
+        assert_eq!(weekly.next_after(start, at("2025-01-08T09:30:00Z")), Some(at("2025-01-13T09:30:00Z")));
+
+        let monthly = RecurrenceRule::parse("FREQ=MONTHLY;UNTIL=20250401").unwrap();
+        let month_end = at("2025-01-31T00:00:00Z");
+        assert_eq!(monthly.next_after(month_end, month_end), Some(at("2025-03-31T00:00:00Z")));
+        assert_eq!(monthly.next_after(month_end, at("2025-03-31T00:00:00Z")), None);
+
+        assert!(RecurrenceRule::parse("FREQ=YEARLY").is_err());
+        assert!(RecurrenceRule::parse("BYHOUR=25;FREQ=DAILY").is_err());
+
+        let every_hour = Schedule::Interval { every_secs: 3600 };
+        assert_eq!(every_hour.next_after(start, start + Duration::minutes(90)), Some(start + Duration::hours(2)));
+    }
+
+    #[test]
+    fn test_missed_runs_collapse_and_count_ends_the_recurrence() {
+        let start = at("2025-01-06T00:00:00Z");
+        let request = CreateRecurrenceRequest {
+            name: None,
+            schedule: Schedule::Rule { rrule: "FREQ=DAILY;COUNT=2".to_string() },
+            starts_at: Some(start),
+            task: serde_json::from_value(serde_json::json!({
+                "name": "nightly backup",
+                "command": "backup.sh",
+                "description": "Back up the database",
+                "project": null,
+                "task_type": "Simple",
+                "priority": "Normal"
+            }))
+            .unwrap(),
+        };
+        let mut recurrence = RecurringTask::new(request, start - Duration::hours(1)).unwrap();
+        assert_eq!(recurrence.next_run_at, Some(start));
+
+        let task = recurrence.instance(start);
+        assert_eq!(task.metadata[RECURRENCE_ID_KEY], serde_json::json!(recurrence.id));
+        assert_eq!(task.run_at(), Some(start));
+
+        // Three days late: one instance, and the next run is the following day
+        let now = start + Duration::days(3) + Duration::hours(1);
+        assert!(recurrence.is_due(now));
+        recurrence.advance(start, task.id, now);
+        assert_eq!(recurrence.next_run_at, Some(start + Duration::days(4)));
+        recurrence.advance(start + Duration::days(4), Uuid::new_v4(), now);
+        assert_eq!(recurrence.next_run_at, None);
+        assert!(!recurrence.is_due(now + Duration::days(30)));
+    }
+}
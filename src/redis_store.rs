@@ -0,0 +1,325 @@
+//! Redis Store Module
+//!
+//! Redis-backed storage trees and queue primitives, for ephemeral
+//! high-throughput deployments where file-based storage is too slow. Each
+//! tree is a hash of its records plus a sorted set of its keys, all with the
+//! same score so Redis orders them by their bytes as sled does; both live
+//! under `{prefix}:{tree}`. Redis Streams with a consumer group serve as a
+//! queue tasks can be ingested from (`kind: redis` in the ingest config).
+//!
+//! Needs the `redis` feature.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::error::{TaskQueueError, Result};
+use crate::kv::{prefix_end, TreeWrite};
+use redis::streams::{StreamReadOptions, StreamReadReply};
+use redis::{AsyncCommands, Commands};
+use std::ops::Bound;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Field of a stream entry holding the message body
+pub const STREAM_BODY_FIELD: &str = "body";
+
+/// Times a watched batch is tried before giving up on a busy tree
+const WATCH_ATTEMPTS: usize = 8;
+
+/// Connection shared by the trees of a store
+#[derive(Clone)]
+struct SharedConnection {
+    client: redis::Client,
+    connection: Arc<Mutex<redis::Connection>>,
+}
+
+impl SharedConnection {
+    /// Run commands on the connection, replacing it for the next call if it broke
+    fn run<T>(&self, run: impl FnOnce(&mut redis::Connection) -> redis::RedisResult<T>) -> Result<T> {
+        let mut connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        run(&mut connection).map_err(|e| {
+            if (e.is_connection_dropped() || e.is_io_error())
+                && let Ok(fresh) = self.client.get_connection()
+            {
+                *connection = fresh;
+            }
+            e.into()
+        })
+    }
+}
+
+/// A Redis server holding the storage trees
+#[derive(Clone)]
+pub struct RedisStore {
+    connection: SharedConnection,
+    prefix: String,
+}
+
+impl RedisStore {
+    /// Connect to the server at `url`, keeping every key under `prefix`
+    pub fn connect(url: &str, prefix: &str) -> Result<Self> {
+        let client = redis::Client::open(url)?;
+        let connection = client.get_connection()?;
+        Ok(Self {
+            connection: SharedConnection { client, connection: Arc::new(Mutex::new(connection)) },
+            prefix: prefix.to_string(),
+        })
+    }
+
+    pub fn tree(&self, name: &str) -> RedisTree {
+        RedisTree {
+            connection: self.connection.clone(),
+            keys: format!("{}:{}:keys", self.prefix, name),
+            values: format!("{}:{}:values", self.prefix, name),
+        }
+    }
+
+    /// Set or remove keys of several trees in one `MULTI`/`EXEC`, provided
+    /// the `expected` keys still hold their values
+    pub fn apply(&self, writes: &[TreeWrite<'_, RedisTree>], expected: &[TreeWrite<'_, RedisTree>]) -> Result<()> {
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for (tree, key, value) in writes {
+            match value {
+                Some(value) => pipe.hset(&tree.values, key, value).ignore().zadd(&tree.keys, key, 0).ignore(),
+                None => pipe.hdel(&tree.values, key).ignore().zrem(&tree.keys, key).ignore(),
+            };
+        }
+        if expected.is_empty() {
+            return self.connection.run(|c| pipe.query::<()>(c));
+        }
+
+        // WATCH covers a whole hash, so writes to other keys of the tree
+        // abort the EXEC too; it is retried while the expected values hold
+        for _ in 0..WATCH_ATTEMPTS {
+            let applied = self.connection.run(|c| {
+                let mut watch = redis::cmd("WATCH");
+                for (tree, _, _) in expected {
+                    watch.arg(&tree.values);
+                }
+                watch.query::<()>(c)?;
+                for (tree, key, value) in expected {
+                    let stored: Option<Vec<u8>> = c.hget(&tree.values, *key)?;
+                    if stored.as_deref() != *value {
+                        redis::cmd("UNWATCH").query::<()>(c)?;
+                        return Ok(Err(key.to_vec()));
+                    }
+                }
+                Ok(Ok(pipe.query::<Option<()>>(c)?.is_some()))
+            })?;
+            match applied {
+                Ok(true) => return Ok(()),
+                Ok(false) => continue,
+                Err(key) => return Err(crate::kv::conflict(&key)),
+            }
+        }
+        Err(crate::kv::conflict(expected[0].1))
+    }
+
+    /// Memory used by the whole Redis server
+    pub fn used_memory(&self) -> Result<u64> {
+        let info: String = self.connection.run(|c| redis::cmd("INFO").arg("memory").query(c))?;
+        Ok(info.lines()
+            .find_map(|line| line.strip_prefix("used_memory:"))
+            .and_then(|bytes| bytes.trim().parse().ok())
+            .unwrap_or(0))
+    }
+}
+
+/// One storage tree in Redis
+#[derive(Clone)]
+pub struct RedisTree {
+    connection: SharedConnection,
+    /// Sorted set of the keys
+    keys: String,
+    /// Hash of the records
+    values: String,
+}
+
+impl RedisTree {
+    /// Set a key, returning its previous value
+    pub fn insert(&self, key: &[u8], value: &[u8]) -> Result<Option<Vec<u8>>> {
+        let (previous,): (Option<Vec<u8>>,) = self.connection.run(|c| {
+            redis::pipe()
+                .atomic()
+                .hget(&self.values, key)
+                .hset(&self.values, key, value).ignore()
+                .zadd(&self.keys, key, 0).ignore()
+                .query(c)
+        })?;
+        Ok(previous)
+    }
+
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.connection.run(|c| c.hget(&self.values, key))
+    }
+
+    /// Remove a key, returning its value
+    pub fn remove(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let (previous,): (Option<Vec<u8>>,) = self.connection.run(|c| {
+            redis::pipe()
+                .atomic()
+                .hget(&self.values, key)
+                .hdel(&self.values, key).ignore()
+                .zrem(&self.keys, key).ignore()
+                .query(c)
+        })?;
+        Ok(previous)
+    }
+
+    pub fn len(&self) -> Result<usize> {
+        self.connection.run(|c| c.zcard(&self.keys))
+    }
+
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// The record with the highest key
+    pub fn last(&self) -> Result<Option<(Vec<u8>, Vec<u8>)>> {
+        let keys: Vec<Vec<u8>> = self.connection.run(|c| c.zrange(&self.keys, -1, -1))?;
+        let Some(key) = keys.into_iter().next() else {
+            return Ok(None);
+        };
+        Ok(self.get(&key)?.map(|value| (key, value)))
+    }
+
+    /// Records with keys between the bounds, in key order, up to `limit` of them
+    pub fn entries(&self, start: Bound<&[u8]>, end: Bound<&[u8]>, limit: Option<usize>) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let keys: Vec<Vec<u8>> = self.connection.run(|c| {
+            let mut command = redis::cmd("ZRANGEBYLEX");
+            command.arg(&self.keys).arg(lex_bound(start, b'-')).arg(lex_bound(end, b'+'));
+            if let Some(limit) = limit {
+                command.arg("LIMIT").arg(0).arg(limit);
+            }
+            command.query(c)
+        })?;
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+        let values: Vec<Option<Vec<u8>>> = self.connection.run(|c| redis::cmd("HMGET").arg(&self.values).arg(&keys).query(c))?;
+        // Records removed between the two reads are skipped
+        Ok(keys.into_iter()
+            .zip(values)
+            .filter_map(|(key, value)| Some((key, value?)))
+            .collect())
+    }
+
+    /// Records whose keys start with the prefix, in key order
+    pub fn scan_prefix(&self, prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let end = prefix_end(prefix);
+        let end = end.as_deref().map_or(Bound::Unbounded, Bound::Excluded);
+        self.entries(Bound::Included(prefix), end, None)
+    }
+}
+
+/// A `ZRANGEBYLEX` bound; `unbounded` is `-` or `+`
+fn lex_bound(bound: Bound<&[u8]>, unbounded: u8) -> Vec<u8> {
+    match bound {
+        Bound::Included(key) => [b"[", key].concat(),
+        Bound::Excluded(key) => [b"(", key].concat(),
+        Bound::Unbounded => vec![unbounded],
+    }
+}
+
+/// A message read from a stream
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamEntry {
+    pub id: String,
+    pub body: Vec<u8>,
+}
+
+/// A Redis stream read through a consumer group: each entry goes to one
+/// consumer and stays pending until acknowledged
+#[derive(Clone)]
+pub struct RedisStream {
+    connection: redis::aio::MultiplexedConnection,
+    key: String,
+    group: String,
+    consumer: String,
+}
+
+impl RedisStream {
+    /// Connect to the stream, creating it and the group if missing
+    pub async fn connect(url: &str, key: &str, group: &str, consumer: &str) -> Result<Self> {
+        let client = redis::Client::open(url)?;
+        let mut connection = client.get_multiplexed_async_connection().await?;
+        let created: redis::RedisResult<()> = connection.xgroup_create_mkstream(key, group, "0").await;
+        match created {
+            Err(e) if e.code() != Some("BUSYGROUP") => return Err(e.into()),
+            _ => {}
+        }
+        Ok(Self {
+            connection,
+            key: key.to_string(),
+            group: group.to_string(),
+            consumer: consumer.to_string(),
+        })
+    }
+
+    /// Append a message, returning its entry ID
+    pub async fn push(&self, body: &[u8]) -> Result<String> {
+        let mut connection = self.connection.clone();
+        Ok(connection.xadd(&self.key, "*", &[(STREAM_BODY_FIELD, body)]).await?)
+    }
+
+    /// Up to `count` entries, waiting at most `block` for the first one.
+    /// With `pending` set, entries delivered to this consumer before but never
+    /// acknowledged, e.g. because the server stopped, are read instead of new
+    /// ones.
+    pub async fn read(&self, count: usize, block: Duration, pending: bool) -> Result<Vec<StreamEntry>> {
+        let mut connection = self.connection.clone();
+        let mut options = StreamReadOptions::default().group(&self.group, &self.consumer).count(count);
+        if !pending {
+            options = options.block(block.as_millis() as usize);
+        }
+        let id = if pending { "0" } else { ">" };
+        let reply: Option<StreamReadReply> = connection.xread_options(&[&self.key], &[id], &options).await?;
+        Ok(reply.into_iter()
+            .flat_map(|reply| reply.keys)
+            .flat_map(|key| key.ids)
+            .map(|entry| StreamEntry {
+                body: entry.get(STREAM_BODY_FIELD).unwrap_or_default(),
+                id: entry.id,
+            })
+            .collect())
+    }
+
+    /// Acknowledge an entry and drop it from the stream
+    pub async fn ack(&self, id: &str) -> Result<()> {
+        let mut connection = self.connection.clone();
+        redis::pipe()
+            .atomic()
+            .xack(&self.key, &self.group, &[id]).ignore()
+            .xdel(&self.key, &[id]).ignore()
+            .query_async::<()>(&mut connection)
+            .await?;
+        Ok(())
+    }
+
+    /// Put an entry back at the end of the stream to be delivered again
+    pub async fn requeue(&self, entry: &StreamEntry) -> Result<()> {
+        self.push(&entry.body).await?;
+        self.ack(&entry.id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_ranges_map_to_lex_bounds() {
+        assert_eq!(lex_bound(Bound::Included(b"task"), b'-'), b"[task");
+        assert_eq!(lex_bound(Bound::Excluded(b"task"), b'-'), b"(task");
+        assert_eq!(lex_bound(Bound::Unbounded, b'+'), b"+");
+
+        assert_eq!(prefix_end(b"ab"), Some(b"ac".to_vec()));
+        assert_eq!(prefix_end(&[1, 0xff, 0xff]), Some(vec![2]));
+        assert_eq!(prefix_end(&[0xff]), None);
+        assert_eq!(prefix_end(b""), None);
+    }
+}
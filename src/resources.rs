@@ -0,0 +1,161 @@
+//! Resource Requests Module
+//!
+//! Tasks declare the CPU and memory they need in their `resources` metadata
+//! (e.g. `{"cpu": 2, "memory_mb": 4096}`). The execution engine keeps track
+//! of what the tasks it runs have requested and only starts a task once its
+//! request fits in what is left of the node's capacity, so heavy build and
+//! test commands don't oversubscribe the machine. Tasks without a request
+//! are only limited by `max_concurrent_tasks`.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::*;
+use crate::error::{TaskQueueError, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+/// Task metadata key holding the task's resource request
+pub const RESOURCES_METADATA_KEY: &str = "resources";
+
+/// CPU and memory, requested by a task or available on the node
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Resources {
+    /// Cores; fractions allowed
+    #[serde(default)]
+    pub cpu: f64,
+    #[serde(default)]
+    pub memory_mb: u64,
+}
+
+impl Resources {
+    /// What a task requests; nothing if it declares no request
+    pub fn requested_by(task: &Task) -> Result<Self> {
+        let Some(value) = task.metadata.get(RESOURCES_METADATA_KEY) else {
+            return Ok(Self::default());
+        };
+        let request: Self = serde_json::from_value(value.clone()).map_err(|e| TaskQueueError::InvalidTaskDefinition {
+            reason: format!("Invalid resources metadata: {}", e),
+        })?;
+        if !request.cpu.is_finite() || request.cpu < 0.0 {
+            return Err(TaskQueueError::InvalidTaskDefinition {
+                reason: "Requested cpu must be a non-negative number".to_string(),
+            });
+        }
+        Ok(request)
+    }
+
+    /// The capacity of this machine: its cores and, on Linux, its memory
+    pub fn host() -> Self {
+        let cpu = std::thread::available_parallelism().map_or(1, |n| n.get()) as f64;
+        let memory_mb = std::fs::read_to_string("/proc/meminfo")
+            .ok()
+            .and_then(|meminfo| {
+                let line = meminfo.lines().find(|line| line.starts_with("MemTotal:"))?;
+                line.split_whitespace().nth(1)?.parse::<u64>().ok()
+            })
+            .map_or(u64::MAX, |kb| kb / 1024);
+        Self { cpu, memory_mb }
+    }
+
+    fn millicores(&self) -> u64 {
+        (self.cpu * 1000.0).round() as u64
+    }
+
+    /// Whether this request fits in `capacity`
+    pub fn fits_within(&self, capacity: &Resources) -> bool {
+        self.millicores() <= capacity.millicores() && self.memory_mb <= capacity.memory_mb
+    }
+}
+
+impl std::fmt::Display for Resources {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} CPU, {} MB", self.cpu, self.memory_mb)
+    }
+}
+
+/// Requests reserved so far, in millicores and megabytes
+#[derive(Debug, Default)]
+struct Usage {
+    millicores: u64,
+    memory_mb: u64,
+}
+
+/// Node capacity and the part of it reserved by running tasks
+#[derive(Debug, Clone)]
+pub struct ResourceTracker {
+    capacity: Resources,
+    used: Arc<Mutex<Usage>>,
+}
+
+impl ResourceTracker {
+    pub fn new(capacity: Resources) -> Self {
+        Self { capacity, used: Arc::default() }
+    }
+
+    pub fn capacity(&self) -> Resources {
+        self.capacity
+    }
+
+    /// Reserve a task's request if it fits in the capacity left; released
+    /// when the returned [`Reservation`] is dropped
+    pub fn try_reserve(&self, request: Resources) -> Option<Reservation> {
+        let mut used = self.used.lock().unwrap_or_else(|e| e.into_inner());
+        let millicores = used.millicores.checked_add(request.millicores())?;
+        let memory_mb = used.memory_mb.checked_add(request.memory_mb)?;
+        if millicores > self.capacity.millicores() || memory_mb > self.capacity.memory_mb {
+            return None;
+        }
+        used.millicores = millicores;
+        used.memory_mb = memory_mb;
+        Some(Reservation { used: self.used.clone(), request })
+    }
+}
+
+/// Capacity held by a running task
+#[derive(Debug)]
+pub struct Reservation {
+    used: Arc<Mutex<Usage>>,
+    request: Resources,
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        let mut used = self.used.lock().unwrap_or_else(|e| e.into_inner());
+        used.millicores -= self.request.millicores();
+        used.memory_mb -= self.request.memory_mb;
+    }
+}
+
+/// Error recorded on tasks requesting more than the whole node has
+pub fn oversized_error(request: Resources, capacity: Resources) -> String {
+    format!("Task requests {} but the node only has {}", request, capacity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_reservations_never_exceed_capacity() {
+        let tracker = ResourceTracker::new(Resources { cpu: 2.0, memory_mb: 4096 });
+        let request = |cpu: f64, memory_mb: u64| Resources { cpu, memory_mb };
+
+        let first = tracker.try_reserve(request(1.5, 1024)).unwrap();
+        assert!(tracker.try_reserve(request(1.0, 1024)).is_none());
+        let second = tracker.try_reserve(request(0.5, 3072)).unwrap();
+        assert!(tracker.try_reserve(request(0.0, 1)).is_none());
+        drop(first);
+        assert!(tracker.try_reserve(request(1.5, 0)).is_some());
+
+        let task = Task::new("build").with_metadata(RESOURCES_METADATA_KEY, json!({"cpu": 4})).build();
+        assert_eq!(Resources::requested_by(&task).unwrap(), request(4.0, 0));
+        assert!(!Resources::requested_by(&task).unwrap().fits_within(&tracker.capacity()));
+        let task = Task::new("bad").with_metadata(RESOURCES_METADATA_KEY, json!({"cpu": -1})).build();
+        assert!(Resources::requested_by(&task).is_err());
+    }
+}
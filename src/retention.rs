@@ -0,0 +1,247 @@
+//! Task Retention Module
+//!
+//! Finished tasks otherwise pile up forever, in storage and in the server's
+//! in-memory maps. When `storage.retention.enabled` is set, a background job
+//! archives or deletes `Completed` and `Cancelled` tasks last updated more
+//! than `storage.retention_days` ago. A project can set its own policy under
+//! the `retention` key of its metadata, e.g.
+//! `{"max_age_days": 7, "action": "delete"}`, or opt out with
+//! `{"action": "keep"}`. Archived tasks move to storage of their own, readable
+//! at `GET /archive/tasks/{id}`; either way their logs, artifacts and other
+//! run data are dropped. Tasks that an unfinished task still depends on are
+//! left alone. With `dry_run` the job only reports what it would remove, and
+//! `POST /admin/retention?dry_run=true` does the same on demand.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::*;
+use crate::server::TaskQueueServer;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Project metadata key holding a project's own policy
+pub const RETENTION_METADATA_KEY: &str = "retention";
+
+/// What happens to a finished task once it is old enough
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RetentionAction {
+    /// Move it out of the queue into the archive
+    #[default]
+    Archive,
+    Delete,
+    /// Never remove it
+    Keep,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    pub max_age_days: u32,
+    pub action: RetentionAction,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self { max_age_days: 30, action: RetentionAction::default() }
+    }
+}
+
+/// A project's override; what it leaves out comes from the global policy
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProjectRetention {
+    max_age_days: Option<u32>,
+    action: Option<RetentionAction>,
+}
+
+impl RetentionPolicy {
+    /// Policy for the tasks of `project`
+    pub fn for_project(self, project: Option<&Project>) -> Self {
+        let Some(value) = project.and_then(|project| project.metadata.get(RETENTION_METADATA_KEY)) else {
+            return self;
+        };
+        match serde_json::from_value::<ProjectRetention>(value.clone()) {
+            Ok(own) => Self {
+                max_age_days: own.max_age_days.unwrap_or(self.max_age_days),
+                action: own.action.unwrap_or(self.action),
+            },
+            Err(e) => {
+                warn!("Ignoring invalid retention policy of project {:?}: {}", project.map(|p| &p.name), e);
+                self
+            }
+        }
+    }
+
+    pub fn max_age(&self) -> Duration {
+        Duration::from_secs(u64::from(self.max_age_days) * 24 * 3600)
+    }
+}
+
+/// The background retention job
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetentionConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Applied to projects without a policy of their own
+    #[serde(default)]
+    pub action: RetentionAction,
+    /// Only log what would be removed
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+}
+
+fn default_interval_secs() -> u64 {
+    3600
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            action: RetentionAction::default(),
+            dry_run: false,
+            interval_secs: default_interval_secs(),
+        }
+    }
+}
+
+impl RetentionConfig {
+    /// The global policy, given `storage.retention_days`
+    pub fn policy(&self, max_age_days: u32) -> RetentionPolicy {
+        RetentionPolicy { max_age_days, action: self.action }
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs.max(1))
+    }
+}
+
+/// A task past its retention
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExpiredTask {
+    pub task_id: Uuid,
+    pub name: String,
+    pub project_id: Option<Uuid>,
+    pub status: TaskStatus,
+    pub action: RetentionAction,
+    /// When the task last changed
+    pub finished_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RetentionReport {
+    pub dry_run: bool,
+    pub archived: usize,
+    pub deleted: usize,
+    pub tasks: Vec<ExpiredTask>,
+}
+
+fn finished(task: &Task) -> bool {
+    matches!(task.status, TaskStatus::Completed | TaskStatus::Cancelled)
+}
+
+/// Tasks an unfinished task depends on; removing them would leave their
+/// dependents waiting forever
+pub fn needed(tasks: &[Task]) -> HashSet<Uuid> {
+    tasks.iter()
+        .filter(|task| !finished(task))
+        .flat_map(|task| task.dependencies.iter().map(|dependency| dependency.task_id))
+        .collect()
+}
+
+/// Finished tasks past their project's retention at `now`
+pub fn expired(tasks: &[Task], projects: &[Project], global: RetentionPolicy, now: SystemTime) -> Vec<ExpiredTask> {
+    let needed = needed(tasks);
+    let projects: HashMap<Uuid, &Project> = projects.iter().map(|project| (project.id, project)).collect();
+
+    tasks.iter()
+        .filter(|task| finished(task) && !needed.contains(&task.id))
+        .filter_map(|task| {
+            let project = task.project_id.and_then(|id| projects.get(&id).copied());
+            let policy = global.for_project(project);
+            let age = now.duration_since(task.updated_at).unwrap_or_default();
+            (policy.action != RetentionAction::Keep && age >= policy.max_age()).then(|| ExpiredTask {
+                task_id: task.id,
+                name: task.name.clone(),
+                project_id: task.project_id,
+                status: task.status.clone(),
+                action: policy.action,
+                finished_at: task.updated_at.into(),
+            })
+        })
+        .collect()
+}
+
+/// Apply the retention policies every `interval_secs` in the background
+pub fn spawn(server: Arc<TaskQueueServer>, config: RetentionConfig) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(config.interval());
+        loop {
+            ticker.tick().await;
+            match server.apply_retention(SystemTime::now(), config.dry_run).await {
+                Ok(report) if report.dry_run && !report.tasks.is_empty() => info!(
+                    "Retention would archive {} and delete {} finished tasks",
+                    report.archived, report.deleted
+                ),
+                Ok(report) if !report.tasks.is_empty() => info!(
+                    "Retention archived {} and deleted {} finished tasks",
+                    report.archived, report.deleted
+                ),
+                Ok(_) => {}
+                Err(e) => error!("Failed to apply task retention: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finished_tasks_expire_by_project_policy() {
+        let now = SystemTime::now();
+        let days_ago = |days: u64| now - Duration::from_secs(days * 24 * 3600);
+        let mut quick = Project::new("quick");
+        quick.metadata.insert(RETENTION_METADATA_KEY.to_string(), serde_json::json!({"max_age_days": 1, "action": "delete"}));
+        let mut pinned = Project::new("pinned");
+        pinned.metadata.insert(RETENTION_METADATA_KEY.to_string(), serde_json::json!({"action": "keep"}));
+
+        let task = |name: &str, status: TaskStatus, age_days: u64, project: Option<&Project>| {
+            let mut task = Task::new(name).with_command("true").build();
+            task.status = status;
+            task.updated_at = days_ago(age_days);
+            task.project_id = project.map(|project| project.id);
+            task
+        };
+        let old = task("old", TaskStatus::Completed, 40, None);
+        let needed = task("needed", TaskStatus::Completed, 40, None);
+        let mut waiting = task("waiting", TaskStatus::Pending, 40, None);
+        waiting.add_dependency(needed.id, None, DependencyCondition::Success, true);
+        let tasks = vec![
+            old.clone(),
+            needed,
+            waiting,
+            task("recent", TaskStatus::Cancelled, 10, None),
+            task("failed", TaskStatus::Failed, 40, None),
+            task("quick", TaskStatus::Cancelled, 2, Some(&quick)),
+            task("pinned", TaskStatus::Completed, 400, Some(&pinned)),
+        ];
+
+        let expired = expired(&tasks, &[quick, pinned], RetentionPolicy::default(), now);
+        let names: Vec<(&str, RetentionAction)> = expired.iter().map(|t| (t.name.as_str(), t.action)).collect();
+        assert_eq!(names, [("old", RetentionAction::Archive), ("quick", RetentionAction::Delete)]);
+        assert_eq!(expired[0].task_id, old.id);
+    }
+}
@@ -0,0 +1,155 @@
+//! Retry Module
+//!
+//! Automatic retries of failed executions. A task that fails with retries
+//! left goes back to `Scheduled`, its `run_at` pushed out by an exponential
+//! backoff from the task's `retry_delay` with jitter, until it has been
+//! retried `retry_attempts` times. Every execution is recorded as a `Running`
+//! phase of the task.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::calendar::RUN_AT_KEY;
+use crate::core::*;
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use std::time::Duration;
+
+/// Task metadata key counting the executions of the task so far
+pub const ATTEMPTS_KEY: &str = "attempts";
+
+/// Upper bound for the delay between two attempts
+pub const MAX_RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+
+/// Executions of the task so far
+pub fn attempts(task: &Task) -> u32 {
+    task.metadata
+        .get(ATTEMPTS_KEY)
+        .and_then(|value| value.as_u64())
+        .map_or(0, |n| n.min(u32::MAX as u64) as u32)
+}
+
+/// Delay before retry number `retry` (from 1): `base * 2^(retry - 1)`, capped
+/// at [`MAX_RETRY_DELAY`], of which the upper half is random
+pub fn backoff(base: Duration, retry: u32) -> Duration {
+    let delay = base
+        .checked_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+        .unwrap_or(MAX_RETRY_DELAY)
+        .min(MAX_RETRY_DELAY);
+    let half = delay / 2;
+    half + Duration::from_millis(fastrand::u64(..=(delay - half).as_millis() as u64))
+}
+
+/// Record a finished execution on the task; returns when it will be retried,
+/// or `None` when the result is final
+pub fn record_attempt(task: &mut Task, result: TaskResult, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let attempt = attempts(task) + 1;
+    task.metadata.insert(ATTEMPTS_KEY.to_string(), json!(attempt));
+
+    let (execution_time, error) = match &result {
+        TaskResult::Success { metrics, .. } => (Some(metrics.execution_time), None),
+        TaskResult::Failure { error, metrics, .. } => (metrics.as_ref().map(|m| m.execution_time), Some(error.clone())),
+        TaskResult::Cancelled { .. } => (None, None),
+    };
+    let retry_at = match &error {
+        Some(_) if attempt <= task.retry_attempts => {
+            let delay = backoff(task.retry_delay, attempt);
+            Some(now + chrono::Duration::from_std(delay).unwrap_or(chrono::Duration::MAX))
+        }
+        _ => None,
+    };
+
+    let outcome = match (&error, retry_at) {
+        (None, _) => "succeeded".to_string(),
+        (Some(error), Some(at)) => format!("failed: {}; retrying at {}", error, at.to_rfc3339()),
+        (Some(error), None) => format!("failed: {}", error),
+    };
+    if !matches!(result, TaskResult::Cancelled { .. }) {
+        task.phases.push(TaskPhase {
+            phase: TaskStatus::Running,
+            started_at: execution_time.and_then(|t| chrono::Duration::from_std(t).ok()).map(|t| now - t),
+            completed_at: Some(now),
+            documentation: Some(format!("Attempt {} of {} {}", attempt, task.retry_attempts + 1, outcome)),
+            artifacts: Vec::new(),
+            ai_reviews: Vec::new(),
+        });
+    }
+
+    match retry_at {
+        Some(at) => {
+            // No result while waiting, so dependents aren't released by the failure
+            task.result = None;
+            task.metadata.insert(RUN_AT_KEY.to_string(), json!(at.to_rfc3339()));
+            task.update_status(TaskStatus::Scheduled);
+        }
+        None => task.set_result(result),
+    }
+    retry_at
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failed() -> TaskResult {
+        TaskResult::Failure {
+            error: "boom".to_string(),
+            exit_code: Some(1),
+            logs: Vec::new(),
+            metrics: Some(TaskMetrics {
+                execution_time: Duration::from_secs(2),
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[test]
+    fn test_backoff_doubles_with_jitter_and_cap() {
+        for retry in 1..=4 {
+            let full = Duration::from_secs(10 * 2u64.pow(retry - 1));
+            let delay = backoff(Duration::from_secs(10), retry);
+            assert!(delay >= full / 2 && delay <= full, "retry {}: {:?}", retry, delay);
+        }
+        assert!(backoff(Duration::from_secs(10), 40) <= MAX_RETRY_DELAY);
+        assert!(backoff(Duration::from_secs(10), 40) >= MAX_RETRY_DELAY / 2);
+    }
+
+    #[test]
+    fn test_failures_are_retried_until_attempts_run_out() {
+        let mut task = Task::new("flaky").with_retry(2, Duration::from_secs(1)).build();
+        let now = Utc::now();
+
+        let retry_at = record_attempt(&mut task, failed(), now).unwrap();
+        assert!(retry_at > now && retry_at <= now + chrono::Duration::seconds(1));
+        assert_eq!(task.status, TaskStatus::Scheduled);
+        assert!(task.result.is_none());
+        assert_eq!(task.run_at(), Some(retry_at));
+
+        assert!(record_attempt(&mut task, failed(), now).is_some());
+        assert!(record_attempt(&mut task, failed(), now).is_none());
+        assert_eq!(task.status, TaskStatus::Failed);
+        assert_eq!(attempts(&task), 3);
+
+        let runs: Vec<_> = task.phases.iter().filter(|p| p.phase == TaskStatus::Running).collect();
+        assert_eq!(runs.len(), 3);
+        assert_eq!(runs[0].started_at, Some(now - chrono::Duration::seconds(2)));
+        assert!(runs[0].documentation.as_deref().unwrap().starts_with("Attempt 1 of 3 failed: boom; retrying at"));
+        assert_eq!(runs[2].documentation.as_deref(), Some("Attempt 3 of 3 failed: boom"));
+    }
+
+    #[test]
+    fn test_success_is_final() {
+        let mut task = Task::new("steady").build();
+        let result = TaskResult::Success {
+            output: String::new(),
+            artifacts: Vec::new(),
+            metrics: TaskMetrics::default(),
+        };
+        assert!(record_attempt(&mut task, result, Utc::now()).is_none());
+        assert_eq!(task.status, TaskStatus::Completed);
+        assert_eq!(attempts(&task), 1);
+    }
+}
@@ -0,0 +1,534 @@
+//! Sandbox Module
+//!
+//! Shell executor that confines each command before it starts, so untrusted
+//! (e.g. AI-generated) commands can't exhaust the host. Memory, CPU time and
+//! file size are capped with resource limits; without network access the
+//! command gets its own empty network namespace. With a delegated cgroup v2
+//! directory, each task also gets a cgroup enforcing its memory, CPU share
+//! and process count for the whole process tree.
+//!
+//! On Windows each task runs in a job object instead, started suspended so
+//! nothing runs before it is in the job, which caps memory, CPU time, CPU
+//! share and process count for the whole process tree and kills what is
+//! left of it when the task ends. Windows has no file size limit or network
+//! isolation for it to apply, so tasks needing either fail there.
+//!
+//! Tasks may tighten the executor's limits through their `sandbox` metadata,
+//! never loosen them. Linux and Windows are supported; elsewhere tasks fail
+//! rather than run unconfined.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::*;
+use crate::executor::{failure, Executor, ShellExecutor};
+use crate::task_logs::LogSink;
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tokio::process::Command;
+use tracing::warn;
+
+/// Executor name
+pub const SANDBOX_EXECUTOR: &str = "sandbox";
+/// Task metadata key holding the limits a task asks for
+pub const LIMITS_METADATA_KEY: &str = "sandbox";
+
+/// Period of the cgroup CPU bandwidth limit, in microseconds
+const CPU_PERIOD_US: u64 = 100_000;
+
+/// Resource limits of a sandboxed command; unset means unlimited
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResourceLimits {
+    /// Memory, in bytes
+    pub memory_bytes: Option<u64>,
+    /// Total CPU time, in seconds
+    pub cpu_seconds: Option<u64>,
+    /// Largest file the command may write, in bytes
+    pub max_file_bytes: Option<u64>,
+    /// Share of one CPU, in percent; needs a cgroup on Linux
+    pub cpu_percent: Option<u32>,
+    /// Processes alive at once; needs a cgroup on Linux
+    pub max_processes: Option<u64>,
+    #[serde(default)]
+    pub network: bool,
+}
+
+fn stricter(a: Option<u64>, b: Option<u64>) -> Option<u64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+impl ResourceLimits {
+    /// The stricter of these limits and the ones a task asks for
+    pub fn tighten(&self, requested: &ResourceLimits) -> ResourceLimits {
+        ResourceLimits {
+            memory_bytes: stricter(self.memory_bytes, requested.memory_bytes),
+            cpu_seconds: stricter(self.cpu_seconds, requested.cpu_seconds),
+            max_file_bytes: stricter(self.max_file_bytes, requested.max_file_bytes),
+            cpu_percent: stricter(self.cpu_percent.map(u64::from), requested.cpu_percent.map(u64::from))
+                .map(|p| p as u32),
+            max_processes: stricter(self.max_processes, requested.max_processes),
+            network: self.network && requested.network,
+        }
+    }
+
+    fn needs_cgroup(&self) -> bool {
+        self.cpu_percent.is_some() || self.max_processes.is_some()
+    }
+}
+
+/// Limits applied by a [`SandboxExecutor`]
+#[derive(Debug, Clone, Default)]
+pub struct SandboxPolicy {
+    /// Applied to every task; tasks may only ask for less
+    pub limits: ResourceLimits,
+    /// Delegated cgroup v2 directory to create per-task cgroups in
+    pub cgroup_root: Option<PathBuf>,
+}
+
+/// Runs tasks through the shell under the policy's limits
+#[derive(Debug, Clone, Default)]
+pub struct SandboxExecutor {
+    shell: ShellExecutor,
+    policy: SandboxPolicy,
+}
+
+impl SandboxExecutor {
+    pub fn new(policy: SandboxPolicy) -> Self {
+        if cfg!(target_os = "linux") && policy.cgroup_root.is_none() && policy.limits.needs_cgroup() {
+            warn!("Sandbox CPU share and process limits need a cgroup_root; tasks will fail until one is set");
+        }
+        Self {
+            shell: ShellExecutor::new(),
+            policy,
+        }
+    }
+
+    pub fn policy(&self) -> &SandboxPolicy {
+        &self.policy
+    }
+
+    /// Limits that apply to a task
+    pub fn limits(&self, task: &Task) -> std::result::Result<ResourceLimits, String> {
+        let requested = match task.metadata.get(LIMITS_METADATA_KEY) {
+            Some(value) => serde_json::from_value(value.clone())
+                .map_err(|e| format!("Invalid sandbox limits: {}", e))?,
+            // Nothing requested, so nothing is tightened
+            None => ResourceLimits {
+                network: true,
+                ..Default::default()
+            },
+        };
+        Ok(self.policy.limits.tighten(&requested))
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn run(&self, task: &Task, logs: &LogSink) -> TaskResult {
+        let limits = match self.limits(task) {
+            Ok(limits) => limits,
+            Err(e) => return failure(e, None, Vec::new()),
+        };
+        let cgroup = match (&self.policy.cgroup_root, limits.needs_cgroup()) {
+            (Some(root), _) => match linux::Cgroup::create(root, task, &limits) {
+                Ok(cgroup) => Some(cgroup),
+                Err(e) => return failure(format!("Failed to create the task's cgroup: {}", e), None, Vec::new()),
+            },
+            (None, true) => return failure("CPU share and process limits need a sandbox cgroup_root", None, Vec::new()),
+            (None, false) => None,
+        };
+
+        self.shell
+            .execute_with(task, logs, |cmd| linux::confine(cmd, &limits, cgroup.as_ref()))
+            .await
+    }
+
+    #[cfg(windows)]
+    async fn run(&self, task: &Task, logs: &LogSink) -> TaskResult {
+        let limits = match self.limits(task) {
+            Ok(limits) => limits,
+            Err(e) => return failure(e, None, Vec::new()),
+        };
+        let job = match windows::Job::create(&limits) {
+            Ok(job) => job,
+            Err(e) => return failure(format!("Failed to create the task's job object: {}", e), None, Vec::new()),
+        };
+
+        self.shell
+            .execute_confined(task, logs, |cmd| windows::confine(cmd, &limits), |child| job.assign(child))
+            .await
+    }
+
+    #[cfg(not(any(target_os = "linux", windows)))]
+    async fn run(&self, task: &Task, logs: &LogSink) -> TaskResult {
+        failure("Sandboxed execution is only supported on Linux and Windows", None, Vec::new())
+    }
+}
+
+impl Executor for SandboxExecutor {
+    fn name(&self) -> &str {
+        SANDBOX_EXECUTOR
+    }
+
+    fn execute<'a>(&'a self, task: &'a Task) -> BoxFuture<'a, TaskResult> {
+        Box::pin(async move { self.run(task, &LogSink::discard()).await })
+    }
+
+    fn execute_logged<'a>(&'a self, task: &'a Task, logs: &'a LogSink) -> BoxFuture<'a, TaskResult> {
+        Box::pin(self.run(task, logs))
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::*;
+    use std::ffi::CString;
+    use std::io;
+    use std::path::Path;
+
+    /// Per-task cgroup, killed and removed when dropped
+    pub struct Cgroup {
+        path: PathBuf,
+        procs: CString,
+    }
+
+    impl Cgroup {
+        pub fn create(root: &Path, task: &Task, limits: &ResourceLimits) -> io::Result<Self> {
+            let path = root.join(format!("task-queue-{}", task.id));
+            std::fs::create_dir_all(&path)?;
+            let cgroup = Self {
+                procs: CString::new(path.join("cgroup.procs").into_os_string().into_encoded_bytes())
+                    .map_err(io::Error::other)?,
+                path,
+            };
+
+            if let Some(bytes) = limits.memory_bytes {
+                std::fs::write(cgroup.path.join("memory.max"), bytes.to_string())?;
+                // No swapping around the limit
+                let _ = std::fs::write(cgroup.path.join("memory.swap.max"), "0");
+            }
+            if let Some(percent) = limits.cpu_percent {
+                let quota = (CPU_PERIOD_US * percent as u64 / 100).max(1000);
+                std::fs::write(cgroup.path.join("cpu.max"), format!("{} {}", quota, CPU_PERIOD_US))?;
+            }
+            if let Some(processes) = limits.max_processes {
+                std::fs::write(cgroup.path.join("pids.max"), processes.to_string())?;
+            }
+            Ok(cgroup)
+        }
+    }
+
+    impl Drop for Cgroup {
+        fn drop(&mut self) {
+            let _ = std::fs::write(self.path.join("cgroup.kill"), "1");
+            if let Err(e) = std::fs::remove_dir(&self.path) {
+                warn!("Failed to remove cgroup {}: {}", self.path.display(), e);
+            }
+        }
+    }
+
+    fn write_file(path: &std::ffi::CStr, contents: &[u8]) -> io::Result<()> {
+        // Only async-signal-safe calls: this runs between fork and exec
+        unsafe {
+            let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let written = libc::write(fd, contents.as_ptr().cast(), contents.len());
+            let error = io::Error::last_os_error();
+            libc::close(fd);
+            if written < 0 { Err(error) } else { Ok(()) }
+        }
+    }
+
+    #[cfg(target_env = "gnu")]
+    type Resource = libc::__rlimit_resource_t;
+    #[cfg(not(target_env = "gnu"))]
+    type Resource = libc::c_int;
+
+    fn set_limit(resource: Resource, soft: u64, hard: u64) -> io::Result<()> {
+        let limit = libc::rlimit { rlim_cur: soft, rlim_max: hard };
+        match unsafe { libc::setrlimit(resource, &limit) } {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
+    /// Move into an empty network namespace, through a user namespace when
+    /// not privileged enough to create one directly
+    fn isolate_network(uid_map: &[u8], gid_map: &[u8]) -> io::Result<()> {
+        if unsafe { libc::unshare(libc::CLONE_NEWNET) } == 0 {
+            return Ok(());
+        }
+        if unsafe { libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // Keep the same IDs inside the user namespace
+        write_file(c"/proc/self/setgroups", b"deny")?;
+        write_file(c"/proc/self/uid_map", uid_map)?;
+        write_file(c"/proc/self/gid_map", gid_map)
+    }
+
+    pub fn confine(cmd: &mut Command, limits: &ResourceLimits, cgroup: Option<&Cgroup>) -> std::result::Result<(), String> {
+        let limits = limits.clone();
+        let procs = cgroup.map(|cgroup| cgroup.procs.clone());
+        // Formatted here, as allocating after fork isn't safe
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        let uid_map = format!("{} {} 1", uid, uid).into_bytes();
+        let gid_map = format!("{} {} 1", gid, gid).into_bytes();
+
+        let confine = move || {
+            if let Some(procs) = &procs {
+                write_file(procs, b"0")?;
+            }
+            if let Some(bytes) = limits.memory_bytes {
+                set_limit(libc::RLIMIT_AS, bytes, bytes)?;
+            }
+            if let Some(seconds) = limits.cpu_seconds {
+                // SIGXCPU at the soft limit, SIGKILL a second later
+                set_limit(libc::RLIMIT_CPU, seconds, seconds + 1)?;
+            }
+            if let Some(bytes) = limits.max_file_bytes {
+                set_limit(libc::RLIMIT_FSIZE, bytes, bytes)?;
+            }
+            if !limits.network {
+                isolate_network(&uid_map, &gid_map)?;
+            }
+            Ok(())
+        };
+        unsafe {
+            cmd.pre_exec(confine);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use super::*;
+    use std::io;
+    use tokio::process::Child;
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD, THREADENTRY32,
+    };
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectCpuRateControlInformation,
+        JobObjectExtendedLimitInformation, SetInformationJobObject, JOBOBJECTINFOCLASS,
+        JOBOBJECT_CPU_RATE_CONTROL_INFORMATION, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+        JOB_OBJECT_CPU_RATE_CONTROL_ENABLE, JOB_OBJECT_CPU_RATE_CONTROL_HARD_CAP, JOB_OBJECT_LIMIT_ACTIVE_PROCESS,
+        JOB_OBJECT_LIMIT_JOB_MEMORY, JOB_OBJECT_LIMIT_JOB_TIME, JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    };
+    use windows_sys::Win32::System::Threading::{OpenThread, ResumeThread, CREATE_SUSPENDED, THREAD_SUSPEND_RESUME};
+
+    /// CPU time unit of job object limits, in ticks per second
+    const TICKS_PER_SECOND: u64 = 10_000_000;
+
+    /// Per-task job object; closing it kills every process still in it
+    pub struct Job(HANDLE);
+
+    // The handle is only passed to thread-safe Win32 calls
+    unsafe impl Send for Job {}
+    unsafe impl Sync for Job {}
+
+    impl Job {
+        pub fn create(limits: &ResourceLimits) -> io::Result<Self> {
+            let handle = unsafe { CreateJobObjectW(std::ptr::null(), std::ptr::null()) };
+            if handle.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            let job = Self(handle);
+
+            let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = unsafe { std::mem::zeroed() };
+            let basic = &mut info.BasicLimitInformation;
+            basic.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+            if let Some(bytes) = limits.memory_bytes {
+                basic.LimitFlags |= JOB_OBJECT_LIMIT_JOB_MEMORY;
+                info.JobMemoryLimit = usize::try_from(bytes).unwrap_or(usize::MAX);
+            }
+            if let Some(seconds) = limits.cpu_seconds {
+                basic.LimitFlags |= JOB_OBJECT_LIMIT_JOB_TIME;
+                basic.PerJobUserTimeLimit = i64::try_from(seconds.saturating_mul(TICKS_PER_SECOND)).unwrap_or(i64::MAX);
+            }
+            if let Some(processes) = limits.max_processes {
+                basic.LimitFlags |= JOB_OBJECT_LIMIT_ACTIVE_PROCESS;
+                basic.ActiveProcessLimit = u32::try_from(processes).unwrap_or(u32::MAX);
+            }
+            job.set(JobObjectExtendedLimitInformation, &info)?;
+
+            if let Some(percent) = limits.cpu_percent {
+                // The rate is in hundredths of a percent of every CPU together
+                let cpus = std::thread::available_parallelism().map_or(1, |cpus| cpus.get()) as u32;
+                let mut rate: JOBOBJECT_CPU_RATE_CONTROL_INFORMATION = unsafe { std::mem::zeroed() };
+                rate.ControlFlags = JOB_OBJECT_CPU_RATE_CONTROL_ENABLE | JOB_OBJECT_CPU_RATE_CONTROL_HARD_CAP;
+                rate.Anonymous.CpuRate = (percent.saturating_mul(100) / cpus).clamp(1, 10_000);
+                job.set(JobObjectCpuRateControlInformation, &rate)?;
+            }
+            Ok(job)
+        }
+
+        fn set<T>(&self, class: JOBOBJECTINFOCLASS, info: &T) -> io::Result<()> {
+            let size = std::mem::size_of::<T>() as u32;
+            match unsafe { SetInformationJobObject(self.0, class, (info as *const T).cast(), size) } {
+                0 => Err(io::Error::last_os_error()),
+                _ => Ok(()),
+            }
+        }
+
+        /// Put a child started suspended into the job, then let it run
+        pub fn assign(&self, child: &Child) -> std::result::Result<(), String> {
+            let (Some(process), Some(pid)) = (child.raw_handle(), child.id()) else {
+                return Err("The command exited before it could be confined".to_string());
+            };
+            if unsafe { AssignProcessToJobObject(self.0, process as HANDLE) } == 0 {
+                return Err(format!("Failed to put the command in its job object: {}", io::Error::last_os_error()));
+            }
+            resume(pid).map_err(|e| format!("Failed to resume the command: {}", e))
+        }
+    }
+
+    impl Drop for Job {
+        fn drop(&mut self) {
+            unsafe { CloseHandle(self.0) };
+        }
+    }
+
+    /// Resume the threads of a process started suspended
+    fn resume(pid: u32) -> io::Result<()> {
+        let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0) };
+        if snapshot == INVALID_HANDLE_VALUE {
+            return Err(io::Error::last_os_error());
+        }
+        let mut entry: THREADENTRY32 = unsafe { std::mem::zeroed() };
+        entry.dwSize = std::mem::size_of::<THREADENTRY32>() as u32;
+
+        let mut result = Ok(());
+        let mut found = unsafe { Thread32First(snapshot, &mut entry) } != 0;
+        while found {
+            if entry.th32OwnerProcessID == pid {
+                let thread = unsafe { OpenThread(THREAD_SUSPEND_RESUME, 0, entry.th32ThreadID) };
+                if thread.is_null() || unsafe { ResumeThread(thread) } == u32::MAX {
+                    result = Err(io::Error::last_os_error());
+                }
+                if !thread.is_null() {
+                    unsafe { CloseHandle(thread) };
+                }
+            }
+            found = unsafe { Thread32Next(snapshot, &mut entry) } != 0;
+        }
+        unsafe { CloseHandle(snapshot) };
+        result
+    }
+
+    pub fn confine(cmd: &mut Command, limits: &ResourceLimits) -> std::result::Result<(), String> {
+        if limits.max_file_bytes.is_some() {
+            return Err("File size limits aren't supported on Windows".to_string());
+        }
+        if !limits.network {
+            return Err("Network isolation isn't supported on Windows; allow network in the sandbox limits".to_string());
+        }
+        cmd.creation_flags(CREATE_SUSPENDED);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_tasks_can_only_tighten_limits() {
+        let executor = SandboxExecutor::new(SandboxPolicy {
+            limits: ResourceLimits {
+                memory_bytes: Some(1 << 30),
+                cpu_seconds: Some(60),
+                ..Default::default()
+            },
+            cgroup_root: None,
+        });
+
+        let task = Task::new("t").with_command("true").build();
+        assert_eq!(executor.limits(&task).unwrap(), executor.policy().limits);
+
+        let task = Task::new("t")
+            .with_command("true")
+            .with_metadata(LIMITS_METADATA_KEY, json!({"memory_bytes": 1u64 << 20, "cpu_seconds": 600, "network": true}))
+            .build();
+        let limits = executor.limits(&task).unwrap();
+        assert_eq!(limits.memory_bytes, Some(1 << 20));
+        assert_eq!(limits.cpu_seconds, Some(60));
+        assert!(!limits.network);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_file_size_limit_stops_the_command() {
+        let dir = std::env::temp_dir().join(format!("task-queue-sandbox-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let executor = SandboxExecutor::new(SandboxPolicy {
+            limits: ResourceLimits {
+                max_file_bytes: Some(4096),
+                network: true,
+                ..Default::default()
+            },
+            cgroup_root: None,
+        });
+        let task = Task::new("writer")
+            .with_command("head -c 100000 /dev/zero > big")
+            .with_working_directory(dir.to_str().unwrap())
+            .build();
+
+        let result = Executor::execute(&executor, &task).await;
+        assert!(matches!(result, TaskResult::Failure { .. }), "{:?}", result);
+        assert!(std::fs::metadata(dir.join("big")).unwrap().len() <= 4096);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_commands_without_network_only_see_loopback() {
+        let executor = SandboxExecutor::new(SandboxPolicy::default());
+        let task = Task::new("offline").with_command("tail -n +3 /proc/net/dev | cut -d: -f1").build();
+
+        match Executor::execute(&executor, &task).await {
+            TaskResult::Success { output, .. } => assert_eq!(output.trim(), "lo"),
+            // Namespaces are unavailable in some containers; the command must not run unconfined
+            TaskResult::Failure { error, .. } => assert!(error.contains("spawn"), "{}", error),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[cfg(windows)]
+    #[tokio::test]
+    async fn test_job_object_confines_the_process_tree() {
+        let executor = SandboxExecutor::new(SandboxPolicy {
+            limits: ResourceLimits {
+                max_processes: Some(1),
+                network: true,
+                ..Default::default()
+            },
+            cgroup_root: None,
+        });
+        let task = Task::new("alone").with_command("echo confined").build();
+        match Executor::execute(&executor, &task).await {
+            TaskResult::Success { output, .. } => assert_eq!(output.trim(), "confined"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // cmd.exe is the one process allowed, so it can't start another
+        let task = Task::new("spawner").with_command("cmd /C echo escaped").build();
+        let result = Executor::execute(&executor, &task).await;
+        assert!(matches!(result, TaskResult::Failure { .. }), "{:?}", result);
+
+        // Network isolation can't be enforced, so the task doesn't run at all
+        let isolated = SandboxExecutor::new(SandboxPolicy::default());
+        let result = Executor::execute(&isolated, &task).await;
+        assert!(matches!(&result, TaskResult::Failure { error, .. } if error.contains("Network isolation")), "{:?}", result);
+    }
+}
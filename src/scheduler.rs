@@ -0,0 +1,131 @@
+//! Scheduler Module
+//!
+//! Order in which runnable tasks are handed to the execution engine and to
+//! external workers: a binary heap keyed by [`TaskPriority`], so `Critical`
+//! work goes before `Low`, and by submission time, so tasks of the same
+//! priority run first in, first out.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::*;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// A task waiting in the [`ReadyQueue`]
+#[derive(Debug, Clone)]
+struct Entry(Task);
+
+impl Entry {
+    fn key(&self) -> (&TaskPriority, std::cmp::Reverse<(std::time::SystemTime, uuid::Uuid)>) {
+        // Earlier submissions rank higher; the ID keeps the order total
+        (&self.0.priority, std::cmp::Reverse((self.0.created_at, self.0.id)))
+    }
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+/// Runnable tasks, popped highest priority first and oldest first within
+/// a priority
+#[derive(Debug, Clone, Default)]
+pub struct ReadyQueue {
+    heap: BinaryHeap<Entry>,
+}
+
+impl ReadyQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, task: Task) {
+        self.heap.push(Entry(task));
+    }
+
+    /// Take the task that should run next
+    pub fn pop(&mut self) -> Option<Task> {
+        self.heap.pop().map(|entry| entry.0)
+    }
+
+    /// The task that would be popped next
+    pub fn peek(&self) -> Option<&Task> {
+        self.heap.peek().map(|entry| &entry.0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+}
+
+impl FromIterator<Task> for ReadyQueue {
+    fn from_iter<I: IntoIterator<Item = Task>>(iter: I) -> Self {
+        Self {
+            heap: iter.into_iter().map(Entry).collect(),
+        }
+    }
+}
+
+/// Drains the queue in scheduling order
+impl Iterator for ReadyQueue {
+    type Item = Task;
+
+    fn next(&mut self) -> Option<Task> {
+        self.pop()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len(), Some(self.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_higher_priority_first_then_fifo() {
+        let start = SystemTime::now();
+        let task = |name: &str, priority: TaskPriority, offset_secs: u64| {
+            let mut task = Task::new(name).with_priority(priority).build();
+            task.created_at = start + Duration::from_secs(offset_secs);
+            task
+        };
+
+        let queue: ReadyQueue = [
+            task("low", TaskPriority::Low, 0),
+            task("critical-late", TaskPriority::Critical, 3),
+            task("normal", TaskPriority::Normal, 1),
+            task("critical-early", TaskPriority::Critical, 2),
+            task("high", TaskPriority::High, 4),
+        ].into_iter().collect();
+
+        assert_eq!(queue.peek().unwrap().name, "critical-early");
+        let order: Vec<String> = queue.map(|task| task.name).collect();
+        assert_eq!(order, ["critical-early", "critical-late", "high", "normal", "low"]);
+    }
+}
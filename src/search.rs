@@ -0,0 +1,287 @@
+//! Search Module
+//!
+//! Full-text search over the words of a task's name, description,
+//! technical specs and acceptance criteria, apart from the semantic search
+//! of the vectorizer. Storage keeps an inverted index next to the tasks:
+//! one key `{term}\0{task id}` per word of a task, holding how often the
+//! word occurs in each field, written in the same commit as the task.
+//! `GET /tasks/search?q=` finds the tasks with every word of the query (a
+//! word ending in `*` matches any word it starts), ranks them BM25-style
+//! with matches in the name weighing most, and highlights the matches.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::{Task, TaskSummary};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+/// Words shorter than this aren't indexed
+pub const MIN_TERM_LENGTH: usize = 2;
+/// Longer words are cut to this many characters
+pub const MAX_TERM_LENGTH: usize = 64;
+/// Most words a query may have
+pub const MAX_QUERY_TERMS: usize = 16;
+pub const DEFAULT_LIMIT: usize = 20;
+pub const MAX_LIMIT: usize = 200;
+/// Characters of context around the first match in a highlight
+const SNIPPET_CONTEXT: usize = 60;
+const SNIPPET_LENGTH: usize = 200;
+
+/// A searched field of a task
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchField {
+    Name,
+    Description,
+    TechnicalSpecs,
+    AcceptanceCriteria,
+}
+
+impl SearchField {
+    pub const ALL: [Self; 4] = [Self::Name, Self::Description, Self::TechnicalSpecs, Self::AcceptanceCriteria];
+
+    /// How much a match in the field counts
+    fn weight(self) -> f64 {
+        match self {
+            Self::Name => 4.0,
+            Self::Description => 2.0,
+            Self::TechnicalSpecs | Self::AcceptanceCriteria => 1.0,
+        }
+    }
+
+    /// The field's text in a task
+    pub fn text(self, task: &Task) -> String {
+        match self {
+            Self::Name => task.name.clone(),
+            Self::Description => task.description.clone(),
+            Self::TechnicalSpecs => task.technical_specs.clone().unwrap_or_default(),
+            Self::AcceptanceCriteria => task.acceptance_criteria.join("\n"),
+        }
+    }
+}
+
+/// Occurrences of a term in each field, in `SearchField::ALL` order
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TermCounts(pub [u16; 4]);
+
+impl TermCounts {
+    pub fn encode(&self) -> Vec<u8> {
+        self.0.iter().flat_map(|count| count.to_le_bytes()).collect()
+    }
+
+    pub fn decode(bytes: &[u8]) -> Self {
+        let mut counts = [0u16; 4];
+        for (count, chunk) in counts.iter_mut().zip(bytes.chunks_exact(2)) {
+            *count = u16::from_le_bytes([chunk[0], chunk[1]]);
+        }
+        Self(counts)
+    }
+}
+
+/// The indexed words of a text, lowercased, with where each starts and ends
+fn words(text: &str) -> impl Iterator<Item = (usize, usize, String)> + '_ {
+    let mut start = None;
+    text.char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+        .filter_map(move |(at, c)| match (c.is_alphanumeric(), start) {
+            (true, None) => {
+                start = Some(at);
+                None
+            }
+            (false, Some(from)) => {
+                start = None;
+                Some((from, at))
+            }
+            _ => None,
+        })
+        .filter_map(|(from, to)| {
+            let word: String = text[from..to].to_lowercase().chars().take(MAX_TERM_LENGTH).collect();
+            (word.chars().count() >= MIN_TERM_LENGTH).then_some((from, to, word))
+        })
+}
+
+pub fn tokenize(text: &str) -> Vec<String> {
+    words(text).map(|(_, _, word)| word).collect()
+}
+
+/// Every term of a task with its counts per field
+pub fn terms(task: &Task) -> BTreeMap<String, TermCounts> {
+    let mut terms: BTreeMap<String, TermCounts> = BTreeMap::new();
+    for (slot, field) in SearchField::ALL.into_iter().enumerate() {
+        for term in tokenize(&field.text(task)) {
+            let counts = &mut terms.entry(term).or_default().0[slot];
+            *counts = counts.saturating_add(1);
+        }
+    }
+    terms
+}
+
+/// Prefix shared by the index keys of a term; without the separator, of
+/// every term it starts
+pub fn term_prefix(term: &str, whole: bool) -> Vec<u8> {
+    let mut prefix = term.as_bytes().to_vec();
+    if whole {
+        prefix.push(0);
+    }
+    prefix
+}
+
+pub fn index_key(term: &str, task_id: &Uuid) -> Vec<u8> {
+    [term_prefix(term, true).as_slice(), task_id.as_bytes()].concat()
+}
+
+/// A task having a term
+#[derive(Debug, Clone, PartialEq)]
+pub struct Posting {
+    pub task_id: Uuid,
+    pub term: String,
+    pub counts: TermCounts,
+}
+
+/// A word of a query
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryTerm {
+    pub term: String,
+    /// Matches every word starting with `term`
+    pub prefix: bool,
+}
+
+impl QueryTerm {
+    fn matches(&self, word: &str) -> bool {
+        if self.prefix { word.starts_with(&self.term) } else { word == self.term }
+    }
+}
+
+/// The words of a query, at most `MAX_QUERY_TERMS`
+pub fn parse_query(query: &str) -> Vec<QueryTerm> {
+    let mut terms: Vec<QueryTerm> = Vec::new();
+    for word in query.split_whitespace() {
+        let prefix = word.ends_with('*');
+        for term in tokenize(word) {
+            if !terms.iter().any(|existing| existing.term == term) {
+                terms.push(QueryTerm { term, prefix });
+            }
+        }
+    }
+    terms.truncate(MAX_QUERY_TERMS);
+    terms
+}
+
+/// Relevance of a task from the counts of each query term it has;
+/// `matching` is how many tasks have each term, out of `total`
+pub fn score(counts: &[TermCounts], matching: &[usize], total: usize) -> f64 {
+    counts.iter().zip(matching).map(|(counts, &matching)| {
+        let idf = (1.0 + (total as f64 - matching as f64 + 0.5) / (matching as f64 + 0.5)).ln();
+        let weighted: f64 = SearchField::ALL.iter().zip(counts.0).map(|(field, count)| {
+            let count = count as f64;
+            field.weight() * count / (count + 1.0)
+        }).sum();
+        idf * weighted
+    }).sum()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// The part of a text around its first match, HTML-escaped, with every
+/// match wrapped in `<mark>`; `None` without a match
+pub fn highlight(text: &str, query: &[QueryTerm]) -> Option<String> {
+    let matches: Vec<(usize, usize)> = words(text)
+        .filter(|(_, _, word)| query.iter().any(|term| term.matches(word)))
+        .map(|(from, to, _)| (from, to))
+        .collect();
+    let &(first, _) = matches.first()?;
+
+    // Cut on character boundaries
+    let start = text[..first].char_indices().rev().nth(SNIPPET_CONTEXT - 1).map_or(0, |(at, _)| at);
+    let end = text[start..].char_indices().nth(SNIPPET_LENGTH).map_or(text.len(), |(at, _)| start + at);
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push('…');
+    }
+    let mut at = start;
+    for &(from, to) in matches.iter().filter(|(from, to)| *from >= start && *to <= end) {
+        snippet.push_str(&escape(&text[at..from]));
+        snippet.push_str("<mark>");
+        snippet.push_str(&escape(&text[from..to]));
+        snippet.push_str("</mark>");
+        at = to;
+    }
+    snippet.push_str(&escape(&text[at..end]));
+    if end < text.len() {
+        snippet.push('…');
+    }
+    Some(snippet)
+}
+
+/// Highlights of every field of a task the query matches
+pub fn highlights(task: &Task, query: &[QueryTerm]) -> BTreeMap<SearchField, String> {
+    SearchField::ALL.into_iter()
+        .filter_map(|field| highlight(&field.text(task), query).map(|snippet| (field, snippet)))
+        .collect()
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct SearchQuery {
+    pub q: String,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SearchHit {
+    pub task: TaskSummary,
+    pub score: f64,
+    /// Matching parts of each field, matches wrapped in `<mark>`
+    pub highlights: BTreeMap<SearchField, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SearchResults {
+    pub query: String,
+    /// Tasks matching, of which the best `limit` are returned
+    pub total: usize,
+    pub results: Vec<SearchHit>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terms_rank_and_highlight() {
+        let mut task = Task::new("Build the web app").with_command("make").build();
+        task.description = "Run the build for <web> and build docs".to_string();
+        task.acceptance_criteria = vec!["Builds cleanly".to_string()];
+
+        let terms = terms(&task);
+        assert_eq!(terms["build"], TermCounts([1, 2, 0, 0]));
+        assert_eq!(terms["builds"], TermCounts([0, 0, 0, 1]));
+        assert!(!terms.contains_key("a"));
+        assert_eq!(TermCounts::decode(&terms["build"].encode()), terms["build"]);
+
+        let query = parse_query("BUILD web*");
+        assert_eq!(query, [
+            QueryTerm { term: "build".to_string(), prefix: false },
+            QueryTerm { term: "web".to_string(), prefix: true },
+        ]);
+        assert_eq!(
+            highlight(&task.description, &query).unwrap(),
+            "Run the <mark>build</mark> for &lt;<mark>web</mark>&gt; and <mark>build</mark> docs"
+        );
+        assert!(highlight("nothing here", &query).is_none());
+
+        // Name matches outrank description matches; rarer terms outrank common ones
+        let in_name = score(&[TermCounts([1, 0, 0, 0])], &[5], 100);
+        let in_description = score(&[TermCounts([0, 1, 0, 0])], &[5], 100);
+        let common = score(&[TermCounts([1, 0, 0, 0])], &[90], 100);
+        assert!(in_name > in_description && in_name > common);
+    }
+}
@@ -0,0 +1,361 @@
+//! Secrets Module
+//!
+//! Named secrets, encrypted at rest with XChaCha20-Poly1305 under a key taken
+//! from `TASK_QUEUE_SECRETS_KEY` (64 hex digits, or a passphrase that is
+//! hashed into a key). Task environment values refer to them as
+//! `{{secret:NAME}}`; references are resolved only when the task is handed
+//! to an executor, and secret values are redacted from what the execution
+//! produced before it is stored. Values are never returned by the API.
+//!
+//! Secrets belong to the tenant whose request stored them, or to the
+//! operator, and a task only resolves those of its own tenant. HTTP tasks
+//! only get secrets in their request headers when the request goes to one
+//! of the hosts in `execution.secret_hosts`.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::*;
+use crate::error::{TaskQueueError, Result};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Environment variable holding the encryption key
+pub const SECRETS_KEY_ENV: &str = "TASK_QUEUE_SECRETS_KEY";
+
+/// Shown in place of secret values
+pub const REDACTED: &str = "[REDACTED]";
+
+const PLACEHOLDER_START: &str = "{{secret:";
+const PLACEHOLDER_END: &str = "}}";
+
+/// A secret as stored: the value only in encrypted form
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredSecret {
+    pub name: String,
+    /// Tenant the secret belongs to; `None` for the operator's
+    #[serde(default)]
+    pub tenant_id: Option<Uuid>,
+    pub nonce: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// What the API shows of a secret
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecretInfo {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<&StoredSecret> for SecretInfo {
+    fn from(secret: &StoredSecret) -> Self {
+        Self {
+            name: secret.name.clone(),
+            tenant_id: secret.tenant_id,
+            created_at: secret.created_at,
+            updated_at: secret.updated_at,
+        }
+    }
+}
+
+/// Body of `PUT /secrets/{name}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetSecretRequest {
+    pub value: String,
+}
+
+pub(crate) fn unavailable() -> TaskQueueError {
+    TaskQueueError::ConfigurationError(format!("Secrets are disabled: set {} to enable them", SECRETS_KEY_ENV))
+}
+
+/// Check a secret name: letters, digits, `_`, `-` and `.`
+pub fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')) {
+        return Err(TaskQueueError::ValidationError {
+            reason: format!("Invalid secret name '{}': use letters, digits, '_', '-' and '.'", name),
+        });
+    }
+    Ok(())
+}
+
+/// Key a secret is stored under: its name, prefixed with its tenant's ID
+/// unless it is the operator's
+pub fn key(tenant: Option<Uuid>, name: &str) -> String {
+    match tenant {
+        Some(tenant) => format!("{}/{}", tenant, name),
+        None => name.to_string(),
+    }
+}
+
+/// Whether the secrets a task's HTTP request headers refer to may be sent:
+/// the request has to go to one of `hosts`
+pub fn may_send_to(task: &Task, hosts: &[String]) -> bool {
+    if header_values(task).all(|value| references(value).is_empty()) {
+        return true;
+    }
+    let url = task.metadata.get(crate::http_task::HTTP_METADATA_KEY).and_then(|http| http.get("url"));
+    let host = url.and_then(|url| url.as_str()).and_then(|url| reqwest::Url::parse(url).ok()).and_then(|url| url.host_str().map(str::to_string));
+    host.is_some_and(|host| hosts.iter().any(|allowed| allowed.eq_ignore_ascii_case(&host)))
+}
+
+/// Encrypts and decrypts secret values
+#[derive(Clone)]
+pub struct SecretCipher {
+    cipher: XChaCha20Poly1305,
+}
+
+impl std::fmt::Debug for SecretCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SecretCipher")
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+impl SecretCipher {
+    /// Cipher for a key given as 64 hex digits, or derived from a passphrase
+    pub fn from_key(key: &str) -> Self {
+        let key = match decode_hex(key.trim()) {
+            Some(bytes) if bytes.len() == 32 => bytes,
+            _ => Sha256::digest(key.as_bytes()).to_vec(),
+        };
+        Self {
+            cipher: XChaCha20Poly1305::new_from_slice(&key).expect("key is 32 bytes"),
+        }
+    }
+
+    /// Cipher for the key in [`SECRETS_KEY_ENV`], if set
+    pub fn from_env() -> Option<Self> {
+        std::env::var(SECRETS_KEY_ENV).ok().filter(|key| !key.is_empty()).map(|key| Self::from_key(&key))
+    }
+
+    pub fn seal(&self, tenant: Option<Uuid>, name: &str, value: &str, now: DateTime<Utc>) -> Result<StoredSecret> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        // The key is authenticated too, so ciphertexts can't be swapped between secrets or tenants
+        let ciphertext = self.cipher
+            .encrypt(&nonce, Payload { msg: value.as_bytes(), aad: key(tenant, name).as_bytes() })
+            .map_err(|_| TaskQueueError::InternalError("Failed to encrypt secret".to_string()))?;
+        Ok(StoredSecret {
+            name: name.to_string(),
+            tenant_id: tenant,
+            nonce: nonce.to_vec(),
+            ciphertext,
+            created_at: now,
+            updated_at: now,
+        })
+    }
+
+    pub fn open(&self, secret: &StoredSecret) -> Result<String> {
+        let failed = || TaskQueueError::ConfigurationError(format!(
+            "Secret '{}' cannot be decrypted; was {} changed?", secret.name, SECRETS_KEY_ENV
+        ));
+        if secret.nonce.len() != 24 {
+            return Err(failed());
+        }
+        let plaintext = self.cipher
+            .decrypt(XNonce::from_slice(&secret.nonce), Payload {
+                msg: &secret.ciphertext,
+                aad: key(secret.tenant_id, &secret.name).as_bytes(),
+            })
+            .map_err(|_| failed())?;
+        String::from_utf8(plaintext).map_err(|_| failed())
+    }
+}
+
+/// Names of the secrets a value refers to
+pub fn references(value: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = value;
+    while let Some(start) = rest.find(PLACEHOLDER_START) {
+        let after = &rest[start + PLACEHOLDER_START.len()..];
+        let Some(end) = after.find(PLACEHOLDER_END) else {
+            break;
+        };
+        names.push(after[..end].trim());
+        rest = &after[end + PLACEHOLDER_END.len()..];
+    }
+    names
+}
+
+/// Replace the placeholders in a value with the secrets' values
+pub fn substitute(value: &str, secrets: &HashMap<String, String>) -> String {
+    let mut resolved = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find(PLACEHOLDER_START) {
+        let after = &rest[start + PLACEHOLDER_START.len()..];
+        let Some(end) = after.find(PLACEHOLDER_END) else {
+            break;
+        };
+        resolved.push_str(&rest[..start]);
+        match secrets.get(after[..end].trim()) {
+            Some(secret) => resolved.push_str(secret),
+            None => resolved.push_str(&rest[start..start + PLACEHOLDER_START.len() + end + PLACEHOLDER_END.len()]),
+        }
+        rest = &after[end + PLACEHOLDER_END.len()..];
+    }
+    resolved.push_str(rest);
+    resolved
+}
+
+/// Removes secret values from execution output
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    values: Vec<String>,
+}
+
+impl Redactor {
+    pub fn new(values: impl IntoIterator<Item = String>) -> Self {
+        let mut values: Vec<String> = values.into_iter().filter(|v| !v.is_empty()).collect();
+        // Longest first, so a secret containing another is redacted whole
+        values.sort_by_key(|v| std::cmp::Reverse(v.len()));
+        values.dedup();
+        Self { values }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        self.values.iter().fold(text.to_string(), |text, value| text.replace(value.as_str(), REDACTED))
+    }
+
+    pub fn redact_result(&self, result: TaskResult) -> TaskResult {
+        if self.is_empty() {
+            return result;
+        }
+        match result {
+            TaskResult::Success { output, artifacts, metrics } => TaskResult::Success {
+                output: self.redact(&output),
+                artifacts,
+                metrics,
+            },
+            TaskResult::Failure { error, exit_code, logs, metrics } => TaskResult::Failure {
+                error: self.redact(&error),
+                exit_code,
+                logs: logs.iter().map(|line| self.redact(line)).collect(),
+                metrics,
+            },
+            TaskResult::Cancelled { reason } => TaskResult::Cancelled { reason: self.redact(&reason) },
+        }
+    }
+}
+
+/// Values of a task that may hold secret references: its environment and
+/// the headers of an HTTP task's request
+pub fn placeholders(task: &Task) -> impl Iterator<Item = &str> {
+    task.environment.values().map(String::as_str).chain(header_values(task))
+}
+
+fn header_values(task: &Task) -> impl Iterator<Item = &str> {
+    task.metadata.get(crate::http_task::HTTP_METADATA_KEY)
+        .and_then(|http| http.get("headers"))
+        .and_then(|headers| headers.as_object())
+        .into_iter()
+        .flat_map(|headers| headers.values().filter_map(|value| value.as_str()))
+}
+
+/// The task as handed to an executor: its environment and HTTP request
+/// headers with secrets resolved, plus the redactor for what the execution
+/// produces. Callers check [`may_send_to`] before passing secrets the
+/// headers refer to.
+pub fn resolve_task(task: &Task, secrets: &HashMap<String, String>) -> (Task, Redactor) {
+    let mut resolved = task.clone();
+    for value in resolved.environment.values_mut() {
+        *value = substitute(value, secrets);
+    }
+    let headers = resolved.metadata.get_mut(crate::http_task::HTTP_METADATA_KEY)
+        .and_then(|http| http.get_mut("headers"))
+        .and_then(|headers| headers.as_object_mut());
+    for value in headers.into_iter().flat_map(|headers| headers.values_mut()) {
+        if let serde_json::Value::String(header) = value {
+            *header = substitute(header, secrets);
+        }
+    }
+    (resolved, Redactor::new(secrets.values().cloned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let cipher = SecretCipher::from_key("correct horse battery staple");
+        let secret = cipher.seal(None, "DB_PASSWORD", "hunter2", Utc::now()).unwrap();
+        assert!(!secret.ciphertext.windows(7).any(|w| w == b"hunter2"));
+        assert_eq!(cipher.open(&secret).unwrap(), "hunter2");
+
+        let renamed = StoredSecret { name: "OTHER".to_string(), ..secret.clone() };
+        assert!(cipher.open(&renamed).is_err());
+        let moved = StoredSecret { tenant_id: Some(Uuid::new_v4()), ..secret.clone() };
+        assert!(cipher.open(&moved).is_err());
+        let tenant = Uuid::new_v4();
+        assert_eq!(key(Some(tenant), "DB_PASSWORD"), format!("{}/DB_PASSWORD", tenant));
+        assert_eq!(cipher.open(&cipher.seal(Some(tenant), "DB_PASSWORD", "x", Utc::now()).unwrap()).unwrap(), "x");
+        assert!(SecretCipher::from_key("another key").open(&secret).is_err());
+    }
+
+    #[test]
+    fn test_placeholders_are_resolved_and_outputs_redacted() {
+        assert_eq!(references("postgres://app:{{secret:DB}}@{{secret:HOST}}/x"), vec!["DB", "HOST"]);
+        assert!(references("plain {{secret:unterminated").is_empty());
+
+        let secrets = HashMap::from([("DB".to_string(), "hunter2".to_string())]);
+        let task = Task::new("migrate")
+            .with_command("migrate")
+            .with_environment("URL", "postgres://app:{{secret:DB}}@db/{{secret:MISSING}}")
+            .build();
+        let (resolved, redactor) = resolve_task(&task, &secrets);
+        assert_eq!(resolved.environment["URL"], "postgres://app:hunter2@db/{{secret:MISSING}}");
+
+        let hook = Task::new("hook")
+            .with_type(TaskType::Http)
+            .with_metadata(crate::http_task::HTTP_METADATA_KEY, serde_json::json!({
+                "url": "https://example.com",
+                "headers": {"Authorization": "Bearer {{secret:DB}}"}
+            }))
+            .build();
+        assert_eq!(placeholders(&hook).collect::<Vec<_>>(), ["Bearer {{secret:DB}}"]);
+        assert!(may_send_to(&hook, &["EXAMPLE.com".to_string()]));
+        assert!(!may_send_to(&hook, &["example.org".to_string()]) && !may_send_to(&hook, &[]));
+        assert!(may_send_to(&task, &[]));
+        let (resolved, _) = resolve_task(&hook, &secrets);
+        assert_eq!(resolved.metadata["http"]["headers"]["Authorization"], "Bearer hunter2");
+
+        let result = redactor.redact_result(TaskResult::Failure {
+            error: "login failed for hunter2".to_string(),
+            exit_code: Some(1),
+            logs: vec!["using hunter2".to_string()],
+            metrics: None,
+        });
+        match result {
+            TaskResult::Failure { error, logs, .. } => {
+                assert_eq!(error, "login failed for [REDACTED]");
+                assert_eq!(logs, vec!["using [REDACTED]"]);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
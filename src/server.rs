@@ -5,42 +5,220 @@
 #![allow(dead_code)]
 #![allow(unused_mut)]
 
+use crate::agents::{self, AgentRegistry, AgentStatus, RegisterAgent};
+use crate::audit::{self, AuditEntity, AuditEntry, AuditQuery, AuditSource, EntityFilter};
+use crate::artifacts::{self, Artifact, ArtifactConfig, ArtifactKind, ArtifactLink};
+use crate::blobs::{BlobStore, DatabaseBlobs, UrlSigner};
+use crate::bulk::{BulkOperation, BulkReport, BulkRequest};
+use crate::backup::{self, Backup, BackupArchive, RestoreMode, RestoreReport};
+use crate::cache::{Cache, CacheConfig, EvictionStrategy};
+use crate::calendar;
 use crate::core::*;
+use crate::dead_letter::DeadLetter;
 use crate::error::{TaskQueueError, Result};
-use crate::storage::StorageEngine;
+use crate::estimates::{PhaseDurations, ProjectStats, TaskEstimate};
+use crate::events::{EventBus, EventFilter, TaskEvent};
+use crate::fsck::{self, Collection, FsckAction, FsckReport};
+use crate::github::{self, GitHubConfig, GitHubSyncReport};
+use crate::groups::{self, CreateTaskGroup, GroupProgress, GroupSubmission};
+use crate::history::StateHistory;
+use crate::import::{self, ImportReport, LineError};
+use crate::reaper;
+use crate::recurrence::{CreateRecurrenceRequest, RecurringTask};
+use crate::cold_storage::{self, ColdStorageConfig, ColdStorageReport};
+use crate::retention::{self, RetentionAction, RetentionPolicy, RetentionReport};
+use crate::scheduler::ReadyQueue;
+use crate::status_index::StatusIndex;
+use crate::secrets::{self, Redactor, SecretCipher, SecretInfo, SetSecretRequest};
+use crate::executor::{failure, ExecutorRegistry};
+use crate::search::{self, SearchField, SearchHit, SearchQuery, SearchResults, TermCounts};
+use crate::task_index::TaskIndex;
+use crate::timeseries::{self, Timeseries, TimeseriesQuery};
+use crate::task_logs::{self, LogChunk, LogSink};
+use crate::tenants::{self, CreateTenantKeyRequest, CreateTenantRequest, IssuedKey, KeyScope, Owned, Principal, Tenant, TenantKey};
+use crate::workers::{self, RegisterWorker, Worker, WorkerStatus};
+use crate::workspaces::{Workspace, WorkspaceConfig};
+use crate::snapshot::{Snapshot, PROJECTS_SECTION, TASKS_SECTION, WORKFLOWS_SECTION};
+use crate::jira::{self, JiraImportConfig, JiraImportReport};
+use crate::lease::{ClaimRequest, CompleteRequest, HeartbeatRequest, Lease};
+use crate::maintenance::{self, Maintenance, MaintenanceStatus};
+use crate::storage::{RecordWrite, Storage, StorageEngine, StorageStats, Transaction};
+use crate::vacuum::{VacuumConfig, VacuumReport};
+use crate::write_behind::{WriteBehind, WriteBehindConfig};
+use crate::config::ServerConfig;
+use crate::idempotency::{self, Idempotency, IdempotencyConfig, IdempotencyRecord};
+use crate::queues::{self, CreateQueueRequest, Queue, QueueGate, QueueLimiter, QueueStatus};
+use crate::rate_limiting::{self, HttpRateLimitConfig, HttpRateLimiter, RouteGroup};
 use crate::vectorizer::VectorizerIntegration;
+use crate::views::{SaveViewRequest, SavedView, SortField, TaskFilter};
 use crate::metrics::MetricsCollector;
+use crate::openapi;
+use crate::validation::{Valid, Validate};
+use crate::etag::{self, Precondition};
 use crate::mcp::create_mcp_router;
 // MCP will be accessed via crate::
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::{Html, Json},
-    routing::{delete, get, post, put},
+    extract::{ConnectInfo, DefaultBodyLimit, Path, Query, Request, State},
+    http::{HeaderMap, Method, StatusCode},
+    middleware::{self, Next},
+    response::{sse::{Event, KeepAlive, Sse}, Html, IntoResponse, Json, Response},
+    routing::{delete, get, patch, post, put},
     Router,
 };
 use tower_http::services::ServeDir;
 use serde_json::{json, Value};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use dashmap::DashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use tower_http::cors::CorsLayer;
+use std::time::Duration;
+use tokio::sync::{Mutex, OwnedRwLockWriteGuard, RwLock};
 use tracing::{info, error, warn};
 
+/// Prefix of the current REST API version; the same routes stay available
+/// without it, marked deprecated
+pub const API_PREFIX: &str = "/api/v1";
+/// Page size used when `limit` is not given
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+/// Largest page a client may request
+pub const MAX_PAGE_SIZE: usize = 1000;
+/// Tasks read from shared storage at a time when listing
+const TASK_SCAN_BATCH: usize = 500;
+/// How long learned phase durations are reused before being recomputed
+const ESTIMATE_REFRESH: Duration = Duration::from_secs(60);
+
+/// Concurrent map sharded by key
+///
+/// Each entry carries its own lock, so updating one task never blocks
+/// requests that touch other tasks.
+pub type ShardedMap<T> = Arc<DashMap<uuid::Uuid, Arc<RwLock<T>>>>;
+
+/// Get an entry handle without holding the shard lock
+fn map_entry<T>(map: &ShardedMap<T>, id: &uuid::Uuid) -> Option<Arc<RwLock<T>>> {
+    map.get(id).map(|entry| entry.value().clone())
+}
+
+/// Clone every value in the map
+async fn map_snapshot<T: Clone>(map: &ShardedMap<T>) -> Vec<T> {
+    // Collect handles first so no shard lock is held across an await
+    let entries: Vec<_> = map.iter().map(|entry| entry.value().clone()).collect();
+    let mut values = Vec::with_capacity(entries.len());
+    for entry in entries {
+        values.push(entry.read().await.clone());
+    }
+    values
+}
+
+fn map_insert<T>(map: &ShardedMap<T>, id: uuid::Uuid, value: T) {
+    map.insert(id, Arc::new(RwLock::new(value)));
+}
+
+/// Drop an entry the request's tenant may not see
+async fn visible_entry<T: Owned>(entry: Option<Arc<RwLock<T>>>) -> Option<Arc<RwLock<T>>> {
+    let entry = entry?;
+    if tenants::current_tenant().is_some() && !tenants::visible(entry.read().await.tenant_id()) {
+        return None;
+    }
+    Some(entry)
+}
+
+/// Read caches used by a stateless server
+///
+/// Storage stays the source of truth: writes always start from the stored
+/// value and refresh the cache afterwards, so reads on other replicas are at
+/// most one TTL behind.
+#[derive(Clone)]
+struct SharedCache {
+    tasks: Cache<uuid::Uuid, Task>,
+    workflows: Cache<uuid::Uuid, Workflow>,
+    projects: Cache<uuid::Uuid, Project>,
+}
+
+impl SharedCache {
+    fn new(ttl: Duration) -> Self {
+        let config = CacheConfig {
+            max_size: 5000,
+            eviction_strategy: EvictionStrategy::Lru,
+            default_ttl: Some(ttl),
+            cleanup_interval: Duration::from_secs(30),
+            enable_metrics: true,
+        };
+
+        Self {
+            tasks: Cache::new(config.clone()),
+            workflows: Cache::new(config.clone()),
+            projects: Cache::new(config),
+        }
+    }
+}
+
+/// Phase durations learned from past tasks, and when
+type LearnedDurations = (std::time::Instant, Arc<PhaseDurations>);
+
 /// Task queue server state
 pub struct TaskQueueServer {
-    storage: Arc<StorageEngine>,
+    storage: Arc<dyn Storage>,
     vectorizer: Arc<VectorizerIntegration>,
     metrics: Arc<MetricsCollector>,
-    tasks: Arc<RwLock<HashMap<uuid::Uuid, Task>>>,
-    workflows: Arc<RwLock<HashMap<uuid::Uuid, Workflow>>>,
-    projects: Arc<RwLock<HashMap<uuid::Uuid, Project>>>,
+    tasks: ShardedMap<Task>,
+    /// IDs of the tasks above by execution status
+    statuses: Arc<StatusIndex>,
+    workflows: ShardedMap<Workflow>,
+    projects: ShardedMap<Project>,
+    /// Serializes upserts so two callers can't both create the same name
+    upsert_lock: Arc<Mutex<()>>,
+    /// Serializes changes to recurring task definitions
+    recurrence_lock: Arc<Mutex<()>>,
+    /// Set in stateless mode, where the maps above stay empty
+    shared: Option<SharedCache>,
+    events: Arc<EventBus>,
+    agents: Arc<AgentRegistry>,
+    phase_durations: Arc<std::sync::Mutex<Option<LearnedDurations>>>,
+    /// `None` when no secrets key is configured
+    secrets: Option<SecretCipher>,
+    /// Hosts HTTP tasks may send secrets to
+    secret_hosts: Arc<Vec<String>>,
+    /// Listen address, CORS and dashboard location
+    http: ServerConfig,
+    artifacts: ArtifactConfig,
+    /// Contents of artifacts and log files
+    blobs: Arc<dyn BlobStore>,
+    /// Signs download URLs the blob store can't presign
+    url_signer: UrlSigner,
+    workspaces: WorkspaceConfig,
+    /// Applied to projects without a retention policy of their own
+    retention: RetentionPolicy,
+    cold_storage: ColdStorageConfig,
+    vacuum: VacuumConfig,
+    /// Tasks whose lease renewals are not yet written
+    write_behind: Arc<WriteBehind>,
+    idempotency: Arc<Idempotency>,
+    rate_limits: Arc<HttpRateLimiter>,
+    /// Start allowances of the rate-limited queues
+    queue_limiter: Arc<QueueLimiter>,
+    maintenance: Arc<Maintenance>,
 }
 
 impl TaskQueueServer {
     /// Create a new task queue server
     pub async fn new() -> Result<Self> {
-        let storage = Arc::new(StorageEngine::new().await?);
+        let (storage, vectorizer) = Self::default_components().await?;
+        Self::with_components(storage, vectorizer).await
+    }
+
+    /// Create a stateless server over the default storage, which must be
+    /// one several servers can share
+    pub async fn new_stateless(cache_ttl: Duration) -> Result<Self> {
+        let (storage, vectorizer) = Self::default_components().await?;
+        if !storage.is_shared() {
+            return Err(TaskQueueError::ConfigurationError(
+                "Stateless mode needs storage other servers can share; set storage.backend to redis or sqlite".to_string(),
+            ));
+        }
+        Ok(Self::stateless(storage, vectorizer, cache_ttl))
+    }
+
+    async fn default_components() -> Result<(Arc<dyn Storage>, Arc<VectorizerIntegration>)> {
+        let storage: Arc<dyn Storage> = Arc::new(StorageEngine::new().await?);
         let vectorizer = match VectorizerIntegration::new().await {
             Ok(v) => Arc::new(v),
             Err(e) => {
@@ -49,16 +227,16 @@ impl TaskQueueServer {
                 Arc::new(VectorizerIntegration::new_dummy())
             }
         };
-        let metrics = Arc::new(MetricsCollector::new());
 
-        let server = Self {
-            storage,
-            vectorizer,
-            metrics,
-            tasks: Arc::new(RwLock::new(HashMap::new())),
-            workflows: Arc::new(RwLock::new(HashMap::new())),
-            projects: Arc::new(RwLock::new(HashMap::new())),
-        };
+        Ok((storage, vectorizer))
+    }
+
+    /// Create a server over existing storage and vectorizer instances
+    pub async fn with_components(
+        storage: Arc<dyn Storage>,
+        vectorizer: Arc<VectorizerIntegration>,
+    ) -> Result<Self> {
+        let server = Self::build(storage, vectorizer, None);
 
         // Load existing data from storage
         server.load_data_from_storage().await?;
@@ -66,51 +244,609 @@ impl TaskQueueServer {
         Ok(server)
     }
 
+    /// Create a server that keeps no authoritative state in memory
+    ///
+    /// Every write goes straight to storage and reads go through a cache with
+    /// the given TTL, so replicas sharing one storage backend stay consistent
+    /// without talking to each other. A write based on a copy another replica
+    /// has since changed fails with `PreconditionFailed` instead of
+    /// overwriting it.
+    pub fn stateless(
+        storage: Arc<dyn Storage>,
+        vectorizer: Arc<VectorizerIntegration>,
+        cache_ttl: Duration,
+    ) -> Self {
+        Self::build(storage, vectorizer, Some(SharedCache::new(cache_ttl)))
+    }
+
+    fn build(
+        storage: Arc<dyn Storage>,
+        vectorizer: Arc<VectorizerIntegration>,
+        shared: Option<SharedCache>,
+    ) -> Self {
+        let events = Arc::new(EventBus::new());
+        events.enable_history(storage.clone());
+        Self {
+            agents: Arc::new(AgentRegistry::new(storage.clone())),
+            blobs: Arc::new(DatabaseBlobs::new(storage.clone())),
+            storage,
+            vectorizer,
+            metrics: Arc::new(MetricsCollector::new()),
+            tasks: Arc::new(DashMap::new()),
+            statuses: Arc::new(StatusIndex::default()),
+            workflows: Arc::new(DashMap::new()),
+            projects: Arc::new(DashMap::new()),
+            upsert_lock: Arc::new(Mutex::new(())),
+            recurrence_lock: Arc::new(Mutex::new(())),
+            shared,
+            events,
+            phase_durations: Arc::new(std::sync::Mutex::new(None)),
+            secrets: SecretCipher::from_env(),
+            secret_hosts: Arc::new(Vec::new()),
+            http: ServerConfig::default(),
+            artifacts: ArtifactConfig::default(),
+            url_signer: UrlSigner::from_env(),
+            workspaces: WorkspaceConfig::default(),
+            retention: RetentionPolicy::default(),
+            cold_storage: ColdStorageConfig::default(),
+            vacuum: VacuumConfig::default(),
+            write_behind: Arc::new(WriteBehind::default()),
+            idempotency: Arc::new(Idempotency::default()),
+            rate_limits: Arc::new(HttpRateLimiter::default()),
+            queue_limiter: Arc::new(QueueLimiter::default()),
+            maintenance: Arc::new(Maintenance::default()),
+        }
+    }
+
+    /// Listen on the configured address, with its CORS and dashboard settings
+    pub fn with_server_config(mut self, http: ServerConfig) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Keep these files from the working directory of tasks that succeed
+    pub fn with_artifacts(mut self, artifacts: ArtifactConfig) -> Self {
+        self.artifacts = artifacts;
+        self
+    }
+
+    /// Keep artifact and log file contents in this blob store instead of storage
+    pub fn with_blob_store(mut self, blobs: Arc<dyn BlobStore>) -> Self {
+        self.blobs = blobs;
+        self
+    }
+
+    /// Where tasks without a working directory run
+    pub fn with_workspaces(mut self, workspaces: WorkspaceConfig) -> Self {
+        self.workspaces = workspaces;
+        self
+    }
+
+    /// Remove finished tasks by this policy unless their project sets its own
+    pub fn with_retention(mut self, retention: RetentionPolicy) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Move completed tasks to cold storage after this long
+    pub fn with_cold_storage(mut self, cold_storage: ColdStorageConfig) -> Self {
+        self.cold_storage = cold_storage;
+        self
+    }
+
+    /// Keep the revisions of deleted tasks this long before vacuuming them
+    pub fn with_vacuum(mut self, vacuum: VacuumConfig) -> Self {
+        self.vacuum = vacuum;
+        self
+    }
+
+    /// Defer writing lease renewals, to be written by `flush_writes`
+    pub fn with_write_behind(mut self, write_behind: &WriteBehindConfig) -> Self {
+        self.write_behind = Arc::new(WriteBehind::new(write_behind));
+        self
+    }
+
+    pub fn with_idempotency(mut self, idempotency: &IdempotencyConfig) -> Self {
+        self.idempotency = Arc::new(Idempotency::new(idempotency));
+        self
+    }
+
+    /// Limit the requests each client makes to the REST API
+    pub fn with_rate_limits(mut self, rate_limits: &HttpRateLimitConfig) -> Self {
+        self.rate_limits = Arc::new(HttpRateLimiter::new(rate_limits));
+        self
+    }
+
+    /// Encrypt secrets with the given key instead of the one in the environment
+    pub fn with_secrets_key(mut self, key: &str) -> Self {
+        self.secrets = Some(SecretCipher::from_key(key));
+        self
+    }
+
+    /// Let HTTP tasks send secrets in their headers to these hosts
+    pub fn with_secret_hosts(mut self, hosts: Vec<String>) -> Self {
+        self.secret_hosts = Arc::new(hosts);
+        self
+    }
+
+    /// Whether the server reads and writes through shared storage
+    pub fn is_stateless(&self) -> bool {
+        self.shared.is_some()
+    }
+
+    /// Bus carrying every task and workflow change made through this server
+    pub fn events(&self) -> &Arc<EventBus> {
+        &self.events
+    }
+
+    pub fn storage(&self) -> &Arc<dyn Storage> {
+        &self.storage
+    }
+
+    /// Registry of the agents acting on the queue
+    pub fn agents(&self) -> &Arc<AgentRegistry> {
+        &self.agents
+    }
+
     /// Load existing data from storage
     async fn load_data_from_storage(&self) -> Result<()> {
         info!("Loading data from storage...");
 
         // Load tasks
         let stored_tasks = self.storage.list_tasks().await?;
-        let mut tasks = self.tasks.write().await;
         for task in stored_tasks {
-            tasks.insert(task.id, task);
+            self.events.track_task(&task);
+            self.statuses.update(&task);
+            map_insert(&self.tasks, task.id, task);
         }
-        info!("Loaded {} tasks from storage", tasks.len());
+        info!("Loaded {} tasks from storage", self.tasks.len());
 
         // Load workflows
         let stored_workflows = self.storage.list_workflows().await?;
-        let mut workflows = self.workflows.write().await;
         for workflow in stored_workflows {
-            workflows.insert(workflow.id, workflow);
+            self.events.track_workflow(&workflow);
+            map_insert(&self.workflows, workflow.id, workflow);
         }
-        info!("Loaded {} workflows from storage", workflows.len());
+        info!("Loaded {} workflows from storage", self.workflows.len());
 
         // Load projects
         let stored_projects = self.storage.list_projects().await?;
-        let mut projects = self.projects.write().await;
         for project in stored_projects {
-            projects.insert(project.id, project);
+            map_insert(&self.projects, project.id, project);
         }
-        info!("Loaded {} projects from storage", projects.len());
+        info!("Loaded {} projects from storage", self.projects.len());
+
+        self.agents.load().await?;
 
         Ok(())
     }
 
-    /// Get reference to tasks map (for MCP access)
-    pub fn tasks(&self) -> &Arc<RwLock<HashMap<uuid::Uuid, Task>>> {
+    /// Get reference to tasks map (for MCP access; empty in stateless mode)
+    pub fn tasks(&self) -> &ShardedMap<Task> {
         &self.tasks
     }
 
-    /// Get reference to projects map
-    pub fn projects(&self) -> &Arc<RwLock<HashMap<uuid::Uuid, Project>>> {
+    /// Get reference to projects map (empty in stateless mode)
+    pub fn projects(&self) -> &ShardedMap<Project> {
         &self.projects
     }
 
+    /// Handle to a task for updating it
+    ///
+    /// In stateless mode this is a fresh copy loaded from storage, so changes
+    /// only take effect once written back with `persist_task`.
+    async fn task_entry(&self, task_id: &uuid::Uuid) -> Result<Option<Arc<RwLock<Task>>>> {
+        let entry = match self.shared {
+            None => map_entry(&self.tasks, task_id),
+            Some(_) => self.storage.load_task(task_id).await?.map(|task| Arc::new(RwLock::new(task))),
+        };
+        Ok(visible_entry(entry).await)
+    }
+
+    async fn workflow_entry(&self, workflow_id: &uuid::Uuid) -> Result<Option<Arc<RwLock<Workflow>>>> {
+        let entry = match self.shared {
+            None => map_entry(&self.workflows, workflow_id),
+            Some(_) => self.storage.load_workflow(workflow_id).await?.map(|workflow| Arc::new(RwLock::new(workflow))),
+        };
+        Ok(visible_entry(entry).await)
+    }
+
+    async fn project_entry(&self, project_id: &uuid::Uuid) -> Result<Option<Arc<RwLock<Project>>>> {
+        let entry = match self.shared {
+            None => map_entry(&self.projects, project_id),
+            Some(_) => self.storage.load_project(project_id).await?.map(|project| Arc::new(RwLock::new(project))),
+        };
+        Ok(visible_entry(entry).await)
+    }
+
+    /// Current value of a task, if the request's tenant may see it
+    async fn read_task(&self, task_id: &uuid::Uuid) -> Result<Option<Task>> {
+        Ok(self.load_task_cached(task_id).await?.filter(|task| tenants::visible(task.tenant_id)))
+    }
+
+    async fn read_workflow(&self, workflow_id: &uuid::Uuid) -> Result<Option<Workflow>> {
+        Ok(self.load_workflow_cached(workflow_id).await?.filter(|workflow| tenants::visible(workflow.tenant_id)))
+    }
+
+    async fn read_project(&self, project_id: &uuid::Uuid) -> Result<Option<Project>> {
+        Ok(self.load_project_cached(project_id).await?.filter(|project| tenants::visible(project.tenant_id)))
+    }
+
+    /// Current value of a task, served from the cache in stateless mode
+    async fn load_task_cached(&self, task_id: &uuid::Uuid) -> Result<Option<Task>> {
+        let Some(shared) = &self.shared else {
+            return Ok(match map_entry(&self.tasks, task_id) {
+                Some(entry) => Some(entry.read().await.clone()),
+                None => None,
+            });
+        };
+
+        if let Some(task) = shared.tasks.get(task_id).await {
+            return Ok(Some(task));
+        }
+        let task = self.storage.load_task(task_id).await?;
+        if let Some(task) = &task {
+            shared.tasks.insert(*task_id, task.clone()).await;
+        }
+        Ok(task)
+    }
+
+    async fn load_workflow_cached(&self, workflow_id: &uuid::Uuid) -> Result<Option<Workflow>> {
+        let Some(shared) = &self.shared else {
+            return Ok(match map_entry(&self.workflows, workflow_id) {
+                Some(entry) => Some(entry.read().await.clone()),
+                None => None,
+            });
+        };
+
+        if let Some(workflow) = shared.workflows.get(workflow_id).await {
+            return Ok(Some(workflow));
+        }
+        let workflow = self.storage.load_workflow(workflow_id).await?;
+        if let Some(workflow) = &workflow {
+            shared.workflows.insert(*workflow_id, workflow.clone()).await;
+        }
+        Ok(workflow)
+    }
+
+    async fn load_project_cached(&self, project_id: &uuid::Uuid) -> Result<Option<Project>> {
+        let Some(shared) = &self.shared else {
+            return Ok(match map_entry(&self.projects, project_id) {
+                Some(entry) => Some(entry.read().await.clone()),
+                None => None,
+            });
+        };
+
+        if let Some(project) = shared.projects.get(project_id).await {
+            return Ok(Some(project));
+        }
+        let project = self.storage.load_project(project_id).await?;
+        if let Some(project) = &project {
+            shared.projects.insert(*project_id, project.clone()).await;
+        }
+        Ok(project)
+    }
+
+    /// Handles to every task the request's tenant may see, without holding
+    /// any shard lock
+    async fn task_handles(&self) -> Result<Vec<Arc<RwLock<Task>>>> {
+        let handles: Vec<_> = match self.shared {
+            None => self.tasks.iter().map(|entry| entry.value().clone()).collect(),
+            Some(_) => self.storage.list_tasks().await?.into_iter().map(|task| Arc::new(RwLock::new(task))).collect(),
+        };
+        if tenants::current_tenant().is_none() {
+            return Ok(handles);
+        }
+        let mut visible = Vec::with_capacity(handles.len());
+        for handle in handles {
+            if tenants::visible(handle.read().await.tenant_id) {
+                visible.push(handle);
+            }
+        }
+        Ok(visible)
+    }
+
+    /// Every workflow, whatever the request's tenant
+    async fn workflow_handles(&self) -> Result<Vec<Arc<RwLock<Workflow>>>> {
+        Ok(match self.shared {
+            None => self.workflows.iter().map(|entry| entry.value().clone()).collect(),
+            Some(_) => self.storage.list_workflows().await?.into_iter().map(|workflow| Arc::new(RwLock::new(workflow))).collect(),
+        })
+    }
+
+    async fn task_snapshot(&self) -> Result<Vec<Task>> {
+        let mut tasks = match self.shared {
+            None => map_snapshot(&self.tasks).await,
+            Some(_) => self.storage.list_tasks().await?,
+        };
+        tasks.retain(|task| tenants::visible(task.tenant_id));
+        Ok(tasks)
+    }
+
+    /// Tasks in any of `statuses`; with tasks in memory only those are read,
+    /// through the status index
+    async fn tasks_with_status(&self, statuses: &[TaskStatus]) -> Result<Vec<Task>> {
+        self.tasks_with_status_where(statuses, tenants::visible).await
+    }
+
+    /// Tasks in any of `statuses` whoever they belong to, for limits that
+    /// hold across tenants
+    async fn all_tasks_with_status(&self, statuses: &[TaskStatus]) -> Result<Vec<Task>> {
+        self.tasks_with_status_where(statuses, |_| true).await
+    }
+
+    async fn tasks_with_status_where(&self, statuses: &[TaskStatus], visible: fn(Option<uuid::Uuid>) -> bool) -> Result<Vec<Task>> {
+        if self.shared.is_some() {
+            let mut tasks = self.storage.list_tasks().await?;
+            tasks.retain(|task| statuses.contains(&task.status) && visible(task.tenant_id));
+            return Ok(tasks);
+        }
+        let mut tasks = Vec::new();
+        for id in self.statuses.ids(statuses) {
+            let Some(entry) = map_entry(&self.tasks, &id) else { continue };
+            let task = entry.read().await;
+            // The index may be ahead of or behind a write in progress
+            if statuses.contains(&task.status) && visible(task.tenant_id) {
+                tasks.push(task.clone());
+            }
+        }
+        Ok(tasks)
+    }
+
+    async fn workflow_snapshot(&self) -> Result<Vec<Workflow>> {
+        let mut workflows = match self.shared {
+            None => map_snapshot(&self.workflows).await,
+            Some(_) => self.storage.list_workflows().await?,
+        };
+        workflows.retain(|workflow| tenants::visible(workflow.tenant_id));
+        Ok(workflows)
+    }
+
+    async fn project_snapshot(&self) -> Result<Vec<Project>> {
+        let mut projects = match self.shared {
+            None => map_snapshot(&self.projects).await,
+            Some(_) => self.storage.list_projects().await?,
+        };
+        projects.retain(|project| tenants::visible(project.tenant_id));
+        Ok(projects)
+    }
+
+    /// Store a new task
+    async fn insert_task(&self, task: &Task) -> Result<()> {
+        let task = &Task { version: task.version + 1, tenant_id: tenants::owner(task.tenant_id), ..task.clone() };
+        if self.shared.is_none() {
+            map_insert(&self.tasks, task.id, task.clone());
+        }
+        self.write_task(task).await?;
+        self.events.task_created(task).await;
+        Ok(())
+    }
+
+    async fn insert_workflow(&self, workflow: &Workflow) -> Result<()> {
+        let workflow = &Workflow { version: workflow.version + 1, tenant_id: tenants::owner(workflow.tenant_id), ..workflow.clone() };
+        if self.shared.is_none() {
+            map_insert(&self.workflows, workflow.id, workflow.clone());
+        }
+        self.write_workflow(workflow).await?;
+        self.events.workflow_created(workflow).await;
+        Ok(())
+    }
+
+    async fn insert_project(&self, project: &Project) -> Result<()> {
+        let project = &Project { version: project.version + 1, tenant_id: tenants::owner(project.tenant_id), ..project.clone() };
+        if self.shared.is_none() {
+            map_insert(&self.projects, project.id, project.clone());
+        }
+        self.write_project(project).await?;
+        self.events.project_created(project).await;
+        Ok(())
+    }
+
+    /// Write a task back to storage as its next version
+    async fn persist_task(&self, task: &mut Task) -> Result<()> {
+        task.version += 1;
+        self.write_task(task).await?;
+        self.events.task_changed(task).await;
+        Ok(())
+    }
+
+    async fn persist_workflow(&self, workflow: &mut Workflow) -> Result<()> {
+        workflow.version += 1;
+        self.write_workflow(workflow).await?;
+        self.events.workflow_changed(workflow).await;
+        Ok(())
+    }
+
+    /// Write a task back to storage later if write-behind is on
+    async fn defer_task(&self, task: &mut Task) -> Result<()> {
+        if self.shared.is_some() || !self.write_behind.is_enabled() {
+            return self.persist_task(task).await;
+        }
+        task.version += 1;
+        self.write_behind.mark(task.id);
+        self.statuses.update(task);
+        self.events.task_changed(task).await;
+        Ok(())
+    }
+
+    /// Write the tasks marked by `defer_task` in one commit; tasks being
+    /// updated right now are left for the next flush
+    pub async fn flush_writes(&self) -> Result<usize> {
+        let mut transaction = Transaction::default();
+        let mut guards = Vec::new();
+        for task_id in self.write_behind.pending() {
+            let Some(entry) = map_entry(&self.tasks, &task_id) else {
+                self.write_behind.clear(&task_id);
+                continue;
+            };
+            // Holding the entries keeps the tasks from changing until committed
+            let Ok(guard) = entry.try_read_owned() else { continue };
+            transaction.put_task(&guard);
+            guards.push(guard);
+        }
+        if guards.is_empty() {
+            return Ok(0);
+        }
+        self.storage.commit(&transaction).await?;
+        for task in &guards {
+            self.write_behind.clear(&task.id);
+        }
+        Ok(guards.len())
+    }
+
+    async fn write_task(&self, task: &Task) -> Result<()> {
+        let mut transaction = Transaction::default();
+        transaction.put_task(task);
+        self.commit(transaction).await
+    }
+
+    async fn write_workflow(&self, workflow: &Workflow) -> Result<()> {
+        let mut transaction = Transaction::default();
+        transaction.put_workflow(workflow);
+        self.commit(transaction).await
+    }
+
+    async fn persist_project(&self, project: &mut Project) -> Result<()> {
+        project.version += 1;
+        self.write_project(project).await?;
+        self.events.project_changed(project).await;
+        Ok(())
+    }
+
+    async fn write_project(&self, project: &Project) -> Result<()> {
+        let mut transaction = Transaction::default();
+        transaction.put_project(project);
+        self.commit(transaction).await
+    }
+
+    /// Commit writes to several records at once, then bring the shared cache
+    /// in line; in-memory entries and events are left to the caller, to be
+    /// updated only once the commit succeeded
+    ///
+    /// In stateless mode the copies written may have been read from a stale
+    /// cache or overtaken by another replica, so storage checks each record
+    /// is still older than the version written; on a conflict the cached
+    /// copies are dropped, for the caller's retry to read them afresh.
+    async fn commit(&self, mut transaction: Transaction) -> Result<()> {
+        if self.shared.is_some() {
+            transaction.check_versions();
+        }
+        if let Err(e) = self.storage.commit(&transaction).await {
+            if let Some(shared) = &self.shared
+                && matches!(e, TaskQueueError::PreconditionFailed { .. } | TaskQueueError::WriteConflict { .. })
+            {
+                for write in transaction.writes() {
+                    match write.target() {
+                        (AuditEntity::Task, id) => {
+                            shared.tasks.remove(&id).await;
+                        }
+                        (AuditEntity::Workflow, id) => {
+                            shared.workflows.remove(&id).await;
+                        }
+                        (AuditEntity::Project, id) => {
+                            shared.projects.remove(&id).await;
+                        }
+                    }
+                }
+            }
+            return Err(e);
+        }
+        let Some(shared) = &self.shared else {
+            for write in transaction.writes() {
+                match write {
+                    RecordWrite::Task(task) => {
+                        self.write_behind.clear(&task.id);
+                        self.statuses.update(task);
+                    }
+                    RecordWrite::DeleteTask(task_id) => {
+                        self.write_behind.clear(task_id);
+                        self.statuses.remove(task_id);
+                    }
+                    _ => {}
+                }
+            }
+            return Ok(());
+        };
+        for write in transaction.writes() {
+            match write {
+                RecordWrite::Task(task) => shared.tasks.insert(task.id, (**task).clone()).await,
+                RecordWrite::DeleteTask(task_id) => {
+                    shared.tasks.remove(task_id).await;
+                }
+                RecordWrite::Workflow(workflow) => shared.workflows.insert(workflow.id, workflow.clone()).await,
+                RecordWrite::DeleteWorkflow(workflow_id) => {
+                    shared.workflows.remove(workflow_id).await;
+                }
+                RecordWrite::Project(project) => shared.projects.insert(project.id, project.clone()).await,
+                RecordWrite::DeleteProject(project_id) => {
+                    shared.projects.remove(project_id).await;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove a task, returning it if it existed
+    async fn remove_task(&self, task_id: &uuid::Uuid) -> Result<Option<Task>> {
+        let task = match &self.shared {
+            None => match self.tasks.remove(task_id) {
+                Some((_, entry)) => {
+                    self.write_behind.clear(task_id);
+                    self.statuses.remove(task_id);
+                    Some(entry.read().await.clone())
+                }
+                None => None,
+            },
+            Some(shared) => {
+                shared.tasks.remove(task_id).await;
+                self.storage.load_task(task_id).await?
+            }
+        };
+
+        if let Some(task) = &task {
+            self.storage.delete_task(task_id).await?;
+            self.events.task_deleted(task).await;
+        }
+        Ok(task)
+    }
+
+    async fn remove_workflow(&self, workflow_id: &uuid::Uuid) -> Result<()> {
+        match &self.shared {
+            None => {
+                self.workflows.remove(workflow_id);
+            }
+            Some(shared) => {
+                shared.workflows.remove(workflow_id).await;
+            }
+        }
+        self.storage.delete_workflow(workflow_id).await
+    }
+
+    async fn remove_project(&self, project_id: &uuid::Uuid) -> Result<Option<Project>> {
+        let project = match &self.shared {
+            None => match self.projects.remove(project_id) {
+                Some((_, entry)) => Some(entry.read().await.clone()),
+                None => None,
+            },
+            Some(shared) => {
+                shared.projects.remove(project_id).await;
+                self.storage.load_project(project_id).await?
+            }
+        };
+
+        if let Some(project) = &project {
+            self.storage.delete_project(project_id).await?;
+            self.events.project_deleted(project).await;
+        }
+        Ok(project)
+    }
+
     /// Create a new project
     pub async fn create_project(&self, name: String, description: Option<String>) -> Result<uuid::Uuid> {
         let project = Project {
             id: uuid::Uuid::new_v4(),
+            version: 0,
+            tenant_id: None,
             name,
             description,
             status: ProjectStatus::Planning,
@@ -122,43 +858,7 @@ impl TaskQueueServer {
         };
 
         let project_id = project.id;
-        let mut projects = self.projects.write().await;
-        projects.insert(project_id, project.clone());
-        
-        // Store in persistent storage
-        self.storage.store_project(&project).await?;
-
-        // Create .tasks file for project tracking
-        // This helps AI models avoid creating duplicate projects/tasks
-        let tasks_file_path = format!(".tasks");
-        if let Ok(mut file) = tokio::fs::File::create(&tasks_file_path).await {
-            let tasks_content = format!(
-                "# Task IDs for project: {}\n\
-                # This file tracks all tasks created for this project\n\
-                # AI models should check this file before creating new projects/tasks\n\
-                # to avoid duplication. Add new task IDs as they are created.\n\
-                # Format: task_id: description\n\n\
-                # Project Information:\n\
-                # ID: {}\n\
-                # Name: {}\n\
-                # Description: {}\n\
-                # Created: {}\n\n\
-                # Task IDs (add new tasks below):\n",
-                project.name,
-                project_id,
-                project.name,
-                project.description.as_deref().unwrap_or("No description"),
-                project.created_at.to_rfc3339()
-            );
-
-            if let Err(e) = tokio::fs::write(&tasks_file_path, tasks_content).await {
-                warn!("Failed to create .tasks file for project {}: {}", project_id, e);
-            } else {
-                info!("Created .tasks tracking file for project: {}", project.name);
-            }
-        } else {
-            warn!("Failed to create .tasks file for project: {}", project.name);
-        }
+        self.insert_project(&project).await?;
 
         info!("Created project with ID: {}", project_id);
         Ok(project_id)
@@ -166,20 +866,22 @@ impl TaskQueueServer {
 
     /// Get project by ID
     pub async fn get_project(&self, project_id: &uuid::Uuid) -> Result<Option<Project>> {
-        let projects = self.projects.read().await;
-        Ok(projects.get(project_id).cloned())
+        self.read_project(project_id).await
     }
 
     /// List all projects
     pub async fn list_projects(&self) -> Result<Vec<Project>> {
-        let projects = self.projects.read().await;
-        Ok(projects.values().cloned().collect())
+        self.project_snapshot().await
     }
 
     /// Update project
-    pub async fn update_project(&self, project_id: &uuid::Uuid, updates: ProjectUpdate) -> Result<()> {
-        let mut projects = self.projects.write().await;
-        if let Some(project) = projects.get_mut(project_id) {
+    pub async fn update_project(&self, project_id: &uuid::Uuid, updates: ProjectUpdate, precondition: &Precondition) -> Result<()> {
+        if let Some(entry) = self.project_entry(project_id).await? {
+            let mut guard = entry.write().await;
+            precondition.check(guard.version)?;
+            // Changed on a copy, so a failed write leaves the entry as stored
+            let mut updated = guard.clone();
+            let project = &mut updated;
             if let Some(name) = updates.name {
                 project.name = name;
             }
@@ -192,10 +894,18 @@ impl TaskQueueServer {
             if let Some(tags) = updates.tags {
                 project.tags = tags;
             }
+            for (key, value) in updates.metadata.unwrap_or_default() {
+                if value.is_null() {
+                    project.metadata.remove(&key);
+                } else {
+                    project.metadata.insert(key, value);
+                }
+            }
             project.updated_at = chrono::Utc::now();
             
             // Store in persistent storage
-            self.storage.store_project(project).await?;
+            self.persist_project(project).await?;
+            *guard = updated;
             
             Ok(())
         } else {
@@ -205,157 +915,779 @@ impl TaskQueueServer {
 
     /// Delete project
     pub async fn delete_project(&self, project_id: &uuid::Uuid) -> Result<()> {
-        let mut projects = self.projects.write().await;
-        if projects.remove(project_id).is_some() {
-            // Also remove project_id from all tasks
-            let mut tasks = self.tasks.write().await;
-            for task in tasks.values_mut() {
-                if task.project_id == Some(*project_id) {
-                    task.project_id = None;
-                }
+        let Some(project) = self.read_project(project_id).await? else {
+            return Err(TaskQueueError::ProjectNotFound { project_id: project_id.to_string() });
+        };
+
+        // The project goes in the same commit as every task's link to it;
+        // the tasks stay locked until their entries match what was committed
+        let mut transaction = Transaction::default();
+        transaction.delete_project(*project_id);
+        let mut unlinked = Vec::new();
+        for entry in self.task_handles().await? {
+            let guard = entry.write_owned().await;
+            if guard.project_id != Some(*project_id) {
+                continue;
             }
-            
-            // Delete from persistent storage
-            self.storage.delete_project(project_id).await?;
-            
-            info!("Deleted project: {}", project_id);
-            Ok(())
-        } else {
-            Err(TaskQueueError::ProjectNotFound { project_id: project_id.to_string() })
+            let mut task = guard.clone();
+            task.project_id = None;
+            task.version += 1;
+            transaction.put_task(&task);
+            unlinked.push((guard, task));
+        }
+        self.commit(transaction).await?;
+
+        self.projects.remove(project_id);
+        for (mut guard, task) in unlinked {
+            *guard = task;
+            self.events.task_changed(&guard).await;
         }
+        self.events.project_deleted(&project).await;
+
+        info!("Deleted project: {}", project_id);
+        Ok(())
     }
 
     /// Get tasks by project
     pub async fn get_tasks_by_project(&self, project_id: &uuid::Uuid) -> Result<Vec<Task>> {
-        let tasks = self.tasks.read().await;
-        Ok(tasks.values()
+        Ok(self.task_snapshot().await?
+            .into_iter()
             .filter(|task| task.project_id == Some(*project_id))
-            .cloned()
             .collect())
     }
 
-    /// Get reference to workflows map (for MCP access)
-    pub fn workflows(&self) -> &Arc<RwLock<HashMap<uuid::Uuid, Workflow>>> {
-        &self.workflows
+    /// Build the tracking manifest for a project
+    ///
+    /// Tasks are matched by `project_id` and listed in creation order.
+    pub async fn project_manifest(&self, project_id: &uuid::Uuid) -> Result<ProjectManifest> {
+        let project = self.get_project(project_id).await?
+            .ok_or_else(|| TaskQueueError::ProjectNotFound { project_id: project_id.to_string() })?;
+
+        let entries = self.task_handles().await?;
+        let mut tasks = Vec::new();
+        for entry in entries {
+            let task = entry.read().await;
+            if task.project_id == Some(*project_id) {
+                tasks.push(Self::summarize(&task, Self::get_effective_task_status(&task)));
+            }
+        }
+        tasks.sort_by_key(|task| (task.created_at, task.id));
+
+        Ok(ProjectManifest {
+            project_id: project.id,
+            name: project.name,
+            description: project.description,
+            created_at: project.created_at,
+            generated_at: chrono::Utc::now(),
+            tasks,
+        })
     }
 
-    /// Get reference to metrics (for MCP access)
-    pub fn metrics(&self) -> &Arc<MetricsCollector> {
-        &self.metrics
+    /// Phase durations of past tasks, recomputed at most every `ESTIMATE_REFRESH`
+    pub async fn phase_durations(&self) -> Result<Arc<PhaseDurations>> {
+        if let Some((learned_at, durations)) = &*self.phase_durations.lock().unwrap()
+            && learned_at.elapsed() < ESTIMATE_REFRESH
+        {
+            return Ok(durations.clone());
+        }
+
+        let durations = Arc::new(PhaseDurations::learn(&self.task_snapshot().await?));
+        *self.phase_durations.lock().unwrap() = Some((std::time::Instant::now(), durations.clone()));
+        Ok(durations)
     }
 
+    pub async fn task_estimate(&self, task: &Task) -> Result<TaskEstimate> {
+        Ok(self.phase_durations().await?.task_estimate(task, chrono::Utc::now()))
+    }
 
-    /// Start the server
-    pub async fn start(&self) -> Result<()> {
-        // Create MCP router (main server)
-        let mcp_router = create_mcp_router(Arc::new(self.clone())).await;
-        
-        // Create REST API router to add to MCP
-        let rest_routes = Router::new()
-            // API routes
-            .route("/health", get(health_check))
-            .route("/tasks", post(submit_task))
-            .route("/tasks/{id}", get(get_task))
-            .route("/tasks/{id}/status", get(get_task_status))
-            .route("/tasks/{id}/result", get(get_task_result))
-            .route("/tasks/{id}/cancel", post(cancel_task))
-            .route("/tasks/{id}/retry", post(retry_task))
-            .route("/tasks/{id}", delete(delete_task))
-            .route("/tasks/{id}", put(update_task))
-            .route("/tasks/upsert", post(upsert_task))
-            .route("/tasks/{id}/priority", put(update_task_priority))
-            .route("/tasks/{id}/dependencies", post(add_task_dependency))
-            .route("/tasks/{id}/dependencies", get(get_task_dependencies))
+    /// When the workflow's last open task is expected to finish
+    pub async fn workflow_estimate(&self, workflow: &Workflow) -> Result<Option<chrono::DateTime<chrono::Utc>>> {
+        Ok(self.phase_durations().await?.estimate_all(&workflow.tasks, chrono::Utc::now()))
+    }
+
+    /// Task counts and completion estimate of a project
+    pub async fn project_stats(&self, project_id: &uuid::Uuid) -> Result<ProjectStats> {
+        let project = self.get_project(project_id).await?
+            .ok_or_else(|| TaskQueueError::ProjectNotFound { project_id: project_id.to_string() })?;
+        let mut tasks = self.get_tasks_by_project(project_id).await?;
+        for task in tasks.iter_mut() {
+            task.status = Self::get_effective_task_status(task);
+        }
+
+        Ok(self.phase_durations().await?.project_stats(&project, &tasks, chrono::Utc::now()))
+    }
+
+    /// Create or replace a saved view
+    pub async fn save_view(&self, name: &str, request: SaveViewRequest) -> Result<SavedView> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(TaskQueueError::ValidationError {
+                reason: "View name cannot be empty".to_string(),
+            });
+        }
+        request.filter.validate()?;
+
+        let now = chrono::Utc::now();
+        let tenant_id = tenants::current_tenant();
+        let key = SavedView::storage_key(tenant_id, name);
+        let created_at = self.storage.load_view(&key).await?.map_or(now, |view| view.created_at);
+        let view = SavedView {
+            name: name.to_string(),
+            description: request.description,
+            filter: request.filter,
+            sort: request.sort,
+            created_at,
+            updated_at: now,
+            tenant_id,
+        };
+        self.storage.store_view(&view).await?;
+        info!("Saved view '{}'", view.name);
+        Ok(view)
+    }
+
+    pub async fn get_view(&self, name: &str) -> Result<Option<SavedView>> {
+        self.storage.load_view(&SavedView::storage_key(tenants::current_tenant(), name)).await
+    }
+
+    pub async fn list_views(&self) -> Result<Vec<SavedView>> {
+        let mut views = self.storage.list_views().await?;
+        views.retain(|view| tenants::visible(view.tenant_id));
+        Ok(views)
+    }
+
+    /// Delete a saved view, returning whether it existed
+    /// Create a queue; `None` if there is one of that name already
+    pub async fn create_queue(&self, request: CreateQueueRequest) -> Result<Option<Queue>> {
+        if self.storage.load_queue(&request.name).await?.is_some() {
+            return Ok(None);
+        }
+        self.save_queue(request).await.map(Some)
+    }
+
+    /// Create a queue or replace the settings of the one of that name
+    pub async fn save_queue(&self, request: CreateQueueRequest) -> Result<Queue> {
+        let now = chrono::Utc::now();
+        let created_at = self.storage.load_queue(&request.name).await?.map_or(now, |queue| queue.created_at);
+        let queue = request.into_queue(created_at, now);
+        self.storage.store_queue(&queue).await?;
+        self.queue_limiter.reset(&queue.name);
+        info!("Saved queue '{}'", queue.name);
+        Ok(queue)
+    }
+
+    /// A queue with its waiting and running tasks
+    pub async fn get_queue(&self, name: &str) -> Result<Option<QueueStatus>> {
+        let Some(queue) = self.storage.load_queue(name).await? else {
+            return Ok(None);
+        };
+        Ok(self.queue_statuses(vec![queue]).await?.pop())
+    }
+
+    pub async fn list_queues(&self) -> Result<Vec<QueueStatus>> {
+        self.queue_statuses(self.storage.list_queues().await?).await
+    }
+
+    async fn queue_statuses(&self, queues: Vec<Queue>) -> Result<Vec<QueueStatus>> {
+        let mut counts: HashMap<String, (usize, usize)> = HashMap::new();
+        for task in self.tasks_with_status(&[TaskStatus::Pending, TaskStatus::Running]).await? {
+            if let Some(name) = queues::queue_name(&task) {
+                let (pending, running) = counts.entry(name.to_string()).or_default();
+                match task.status {
+                    TaskStatus::Pending => *pending += 1,
+                    _ => *running += 1,
+                }
+            }
+        }
+        Ok(queues.into_iter().map(|queue| {
+            let (pending, running) = counts.get(&queue.name).copied().unwrap_or_default();
+            QueueStatus { queue, pending, running }
+        }).collect())
+    }
+
+    /// Delete a queue, returning whether it existed; its tasks run on
+    /// without its limits
+    pub async fn delete_queue(&self, name: &str) -> Result<bool> {
+        let existed = self.storage.delete_queue(name).await?;
+        self.queue_limiter.reset(name);
+        Ok(existed)
+    }
+
+    /// What the queues let start now, given their tasks already running
+    pub async fn queue_gate(&self) -> Result<QueueGate<'_>> {
+        let queues = self.storage.list_queues().await?;
+        let running = match queues.is_empty() {
+            true => Vec::new(),
+            false => self.all_tasks_with_status(&[TaskStatus::Running]).await?,
+        };
+        Ok(QueueGate::new(queues, &running, &self.queue_limiter))
+    }
+
+    /// Maintenance and drain switches, flipped through `/admin`
+    pub fn maintenance(&self) -> &Maintenance {
+        &self.maintenance
+    }
+
+    pub async fn maintenance_status(&self) -> Result<MaintenanceStatus> {
+        Ok(MaintenanceStatus {
+            maintenance: self.maintenance.is_enabled(),
+            draining: self.maintenance.is_draining(),
+            running: self.all_tasks_with_status(&[TaskStatus::Running]).await?.len(),
+        })
+    }
+
+    /// Stop dispatching and wait up to `timeout` for the running tasks to
+    /// finish; they keep running past it, and the status tells whether
+    /// they did
+    pub async fn drain(&self, timeout: Duration) -> Result<MaintenanceStatus> {
+        self.maintenance.drain();
+        // Subscribing before counting misses no task finishing in between
+        let mut receiver = self.events.subscribe();
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let status = self.maintenance_status().await?;
+            if status.drained() || tokio::time::Instant::now() >= deadline {
+                return Ok(status);
+            }
+            // Workers of other replicas finish tasks without an event here
+            let wake = deadline.min(tokio::time::Instant::now() + TASK_WAIT_RECHECK);
+            if let Ok(Err(tokio::sync::broadcast::error::RecvError::Closed)) = tokio::time::timeout_at(wake, receiver.recv()).await {
+                tokio::time::sleep_until(wake).await;
+            }
+        }
+    }
+
+    pub async fn delete_view(&self, name: &str) -> Result<bool> {
+        self.storage.delete_view(&SavedView::storage_key(tenants::current_tenant(), name)).await
+    }
+
+    /// Tasks shown by a saved view, with their effective status; `None` if
+    /// there is no such view
+    pub async fn view_tasks(&self, name: &str) -> Result<Option<Vec<Task>>> {
+        let Some(view) = self.get_view(name).await? else {
+            return Ok(None);
+        };
+
+        let mut tasks = self.list_matching_tasks(&view.filter).await?;
+
+        match view.sort {
+            Some(sort) => sort.apply(&mut tasks),
+            None => tasks.sort_by_key(|task| (task.created_at, task.id)),
+        }
+        Ok(Some(tasks))
+    }
+
+    /// Create a recurring task definition
+    pub async fn create_recurrence(&self, request: CreateRecurrenceRequest) -> Result<RecurringTask> {
+        let mut recurrence = RecurringTask::new(request, chrono::Utc::now())?;
+        recurrence.tenant_id = tenants::current_tenant();
+        // Catch template problems now rather than on the first run
+        self.validate_task(&recurrence.instance(recurrence.starts_at)).await?;
+
+        self.storage.store_recurrence(&recurrence).await?;
+        info!("Recurring task created: {} ({}), next run {:?}", recurrence.name, recurrence.id, recurrence.next_run_at);
+        Ok(recurrence)
+    }
+
+    pub async fn get_recurrence(&self, recurrence_id: uuid::Uuid) -> Result<Option<RecurringTask>> {
+        Ok(self.storage.load_recurrence(&recurrence_id).await?.filter(|r| tenants::visible(r.tenant_id)))
+    }
+
+    pub async fn list_recurrences(&self) -> Result<Vec<RecurringTask>> {
+        let mut recurrences = self.storage.list_recurrences().await?;
+        recurrences.retain(|r| tenants::visible(r.tenant_id));
+        recurrences.sort_by_key(|r| (r.created_at, r.id));
+        Ok(recurrences)
+    }
+
+    /// Pause or resume a recurrence; `None` if there is no such recurrence
+    pub async fn set_recurrence_paused(&self, recurrence_id: uuid::Uuid, paused: bool) -> Result<Option<RecurringTask>> {
+        let _lock = self.recurrence_lock.lock().await;
+        let Some(mut recurrence) = self.get_recurrence(recurrence_id).await? else {
+            return Ok(None);
+        };
+
+        recurrence.set_paused(paused, chrono::Utc::now());
+        self.storage.store_recurrence(&recurrence).await?;
+        info!("Recurring task {}: {} ({})", if paused { "paused" } else { "resumed" }, recurrence.name, recurrence.id);
+        Ok(Some(recurrence))
+    }
+
+    /// Delete a recurrence, returning whether it existed; tasks it already
+    /// created are kept
+    pub async fn delete_recurrence(&self, recurrence_id: uuid::Uuid) -> Result<bool> {
+        let _lock = self.recurrence_lock.lock().await;
+        if self.get_recurrence(recurrence_id).await?.is_none() {
+            return Ok(false);
+        }
+        self.storage.delete_recurrence(&recurrence_id).await
+    }
+
+    /// Create a task for every recurrence whose next run is due; returns the new task IDs
+    pub async fn materialize_recurrences(&self, now: chrono::DateTime<chrono::Utc>) -> Result<Vec<uuid::Uuid>> {
+        let _lock = self.recurrence_lock.lock().await;
+        let mut created = Vec::new();
+
+        for mut recurrence in self.storage.list_recurrences().await? {
+            let Some(run_at) = recurrence.next_run_at.filter(|_| recurrence.is_due(now)) else {
+                continue;
+            };
+
+            let instance = self.submit_task(recurrence.instance(run_at));
+            let task_id = match tenants::with_tenant(recurrence.tenant_id, instance).await {
+                Ok(task_id) => task_id,
+                // E.g. the project was deleted; stop instead of failing every poll
+                Err(TaskQueueError::InvalidTaskDefinition { reason }) => {
+                    warn!("Pausing recurring task {} ({}): {}", recurrence.name, recurrence.id, reason);
+                    recurrence.set_paused(true, now);
+                    self.storage.store_recurrence(&recurrence).await?;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            recurrence.advance(run_at, task_id, now);
+            self.storage.store_recurrence(&recurrence).await?;
+            info!("Recurring task {} ({}) created task {}", recurrence.name, recurrence.id, task_id);
+            created.push(task_id);
+        }
+
+        Ok(created)
+    }
+
+    /// Render a project's deadlines and upcoming runs as an iCalendar feed
+    pub async fn project_calendar(&self, project_id: &uuid::Uuid) -> Result<String> {
+        let project = self.get_project(project_id).await?
+            .ok_or_else(|| TaskQueueError::ProjectNotFound { project_id: project_id.to_string() })?;
+        let tasks = self.get_tasks_by_project(project_id).await?;
+
+        let events = calendar::project_events(&project, &tasks);
+        Ok(calendar::render(&project.name, &events))
+    }
+
+    /// Apply a change to a task and persist it
+    pub async fn modify_task(&self, task_id: uuid::Uuid, f: impl FnOnce(&mut Task)) -> Result<Task> {
+        let entry = self.task_entry(&task_id).await?.ok_or_else(|| TaskQueueError::TaskNotFound {
+            task_id: task_id.to_string(),
+        })?;
+        let mut guard = entry.write().await;
+        // Changed on a copy, so a failed write leaves the entry as stored
+        let mut task = guard.clone();
+        f(&mut task);
+        task.updated_at = std::time::SystemTime::now();
+        self.persist_task(&mut task).await?;
+        *guard = task.clone();
+        Ok(task)
+    }
+
+    /// Apply a change to a project and persist it
+    pub async fn modify_project(&self, project_id: &uuid::Uuid, f: impl FnOnce(&mut Project)) -> Result<Project> {
+        let entry = self.project_entry(project_id).await?.ok_or_else(|| TaskQueueError::ProjectNotFound {
+            project_id: project_id.to_string(),
+        })?;
+        let mut guard = entry.write().await;
+        let mut project = guard.clone();
+        f(&mut project);
+        project.updated_at = chrono::Utc::now();
+        self.persist_project(&mut project).await?;
+        *guard = project.clone();
+        Ok(project)
+    }
+
+    /// Import issues from Jira
+    pub async fn import_jira(&self, config: JiraImportConfig) -> Result<JiraImportReport> {
+        jira::import(self, config).await
+    }
+
+    /// Configure GitHub issue import for a project
+    pub async fn configure_github(&self, project_id: &uuid::Uuid, config: GitHubConfig) -> Result<()> {
+        if self.get_project(project_id).await?.is_none() {
+            return Err(TaskQueueError::ProjectNotFound { project_id: project_id.to_string() });
+        }
+        config.validate()?;
+        let stored = config.seal(project_id, self.secrets.as_ref())?;
+        self.storage.store_integration(project_id, github::INTEGRATION_KIND, stored).await
+    }
+
+    /// GitHub configuration of a project, if any
+    pub async fn github_config(&self, project_id: &uuid::Uuid) -> Result<Option<GitHubConfig>> {
+        match self.storage.load_integration(project_id, github::INTEGRATION_KIND).await? {
+            Some(stored) => Ok(Some(GitHubConfig::unseal(stored, self.secrets.as_ref())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Stop syncing a project with GitHub
+    pub async fn remove_github(&self, project_id: &uuid::Uuid) -> Result<()> {
+        self.storage.delete_integration(project_id, github::INTEGRATION_KIND).await
+    }
+
+    /// Import open issues and close issues of finalized tasks
+    pub async fn sync_github(&self, project_id: &uuid::Uuid) -> Result<GitHubSyncReport> {
+        let mut config = self.github_config(project_id).await?.ok_or_else(|| TaskQueueError::ConfigurationError(
+            format!("GitHub is not configured for project {}", project_id),
+        ))?;
+        let tenant = self.get_project(project_id).await?.and_then(|project| project.tenant_id);
+        let values = self.secret_values(tenant, secrets::references(&config.token)).await?;
+        config.token = secrets::substitute(&config.token, &values);
+        github::sync_project(self, *project_id, config).await
+    }
+
+    /// Get reference to workflows map (for MCP access)
+    pub fn workflows(&self) -> &ShardedMap<Workflow> {
+        &self.workflows
+    }
+
+    /// Get reference to metrics (for MCP access)
+    pub fn metrics(&self) -> &Arc<MetricsCollector> {
+        &self.metrics
+    }
+
+
+    /// Start the server
+    pub async fn start(&self) -> Result<()> {
+        self.start_with_shutdown(std::future::pending()).await
+    }
+
+    /// Start the server and shut it down gracefully once `signal` resolves
+    pub async fn start_with_shutdown(&self, signal: impl std::future::Future<Output = ()> + Send + 'static) -> Result<()> {
+        // Create MCP router (main server)
+        let mcp_router = create_mcp_router(Arc::new(self.clone()), self.http.mcp_transport).await;
+
+        // Merge REST routes into MCP router
+        let app = mcp_router.merge(self.rest_router());
+
+        let address = self.http.bind_address();
+        let listener = tokio::net::TcpListener::bind(&address).await
+            .map_err(|e| TaskQueueError::ConfigurationError(format!("Failed to bind listener on {}: {}", address, e)))?;
+        let port = self.http.port;
+        info!("MCP server with REST API listening on {}", address);
+        if self.http.mcp_transport.serves_streamable_http() {
+            info!("MCP streamable HTTP endpoint: http://localhost:{}{}", port, crate::mcp::MCP_STREAMABLE_PATH);
+        }
+        if self.http.mcp_transport.serves_sse() {
+            info!("MCP SSE endpoint: http://localhost:{}/mcp/sse", port);
+            info!("MCP POST endpoint: http://localhost:{}/mcp/message", port);
+        }
+        info!("Dashboard available at: http://localhost:{}", port);
+        
+        self.rate_limits.start_cleanup_task().await;
+        axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>())
+            .with_graceful_shutdown(signal)
+            .await
+            .map_err(|e| TaskQueueError::ConfigurationError(format!("Server error: {}", e)))?;
+        self.flush_writes().await?;
+        info!("Server stopped");
+        Ok(())
+    }
+
+    /// REST API and dashboard routes
+    pub(crate) fn rest_router(&self) -> Router {
+        // REST API, served under `/api/v1` and, for older clients, unversioned
+        let idempotent = middleware::from_fn_with_state(Arc::new(self.clone()), replay_idempotent);
+        let limits = &self.http.limits;
+        let body_limit = |bytes: usize, setting: &'static str| {
+            tower::ServiceBuilder::new()
+                .layer(middleware::from_fn_with_state((bytes, setting), explain_too_large))
+                .layer(DefaultBodyLimit::max(bytes))
+        };
+        let bulk = body_limit(limits.max_bulk_bytes, "max_bulk_bytes");
+        let api = Router::new()
+            .route("/health", get(health_check))
+            .route("/tasks", post(submit_task).layer(idempotent.clone()))
+            .route("/tasks/batch", post(submit_tasks).layer(bulk.clone()))
+            .route("/tasks/bulk", post(bulk_update_tasks).layer(bulk.clone()))
+            .route("/groups", post(submit_group).layer(bulk.clone()))
+            .route("/groups/{id}", get(get_group))
+            .route("/tasks/{id}", get(get_task))
+            .route("/tasks/{id}/status", get(get_task_status))
+            .route("/tasks/{id}/result", get(get_task_result))
+            .route("/tasks/{id}/wait", get(wait_for_task))
+            .route("/tasks/{id}/logs", get(get_task_logs))
+            .route("/tasks/{id}/history", get(get_task_history))
+            .route("/tasks/{id}/artifacts", get(list_task_artifacts))
+            .route("/tasks/{id}/artifacts/{*name}", get(download_task_artifact))
+            .route("/blobs/{*key}", get(download_blob))
+            .route("/tasks/{id}/cancel", post(cancel_task))
+            .route("/tasks/{id}/pause", post(pause_task))
+            .route("/tasks/{id}/resume", post(resume_task))
+            .route("/tasks/{id}/retry", post(retry_task))
+            .route("/tasks/{id}", delete(delete_task))
+            .route("/tasks/{id}", put(update_task))
+            .route("/tasks/{id}", patch(update_task))
+            .route("/tasks/upsert", post(upsert_task))
+            .route("/tasks/{id}/priority", put(update_task_priority))
+            .route("/tasks/{id}/dependencies", post(add_task_dependency))
+            .route("/tasks/{id}/dependencies", get(get_task_dependencies))
             .route("/tasks/{id}/advance-phase", post(advance_task_phase))
             .route("/tasks/{id}/status", put(set_task_status))
             .route("/tasks/{id}/correlations", get(get_task_correlations))
             .route("/tasks", get(list_tasks))
+            .route("/tasks/search", get(search_tasks))
+            .route("/tasks/claim", post(claim_task))
+            .route("/tasks/{id}/lease/heartbeat", post(renew_task_lease))
+            .route("/tasks/{id}/lease/complete", post(complete_leased_task))
             .route("/workflows", get(list_workflows))
-            .route("/workflows", post(submit_workflow))
+            .route("/workflows", post(submit_workflow).layer(bulk.clone()).layer(idempotent.clone()))
             .route("/workflows/{id}", get(get_workflow))
             .route("/workflows/{id}/status", get(get_workflow_status))
-            .route("/projects", post(create_project))
+            .route("/workflows/{id}/history", get(get_workflow_history))
+            .route("/projects", post(create_project).layer(idempotent))
             .route("/projects", get(list_projects))
             .route("/projects/{id}", get(get_project))
             .route("/projects/{id}", put(update_project))
             .route("/projects/{id}", post(delete_project))
             .route("/projects/{id}/tasks", get(get_project_tasks))
+            .route("/projects/{id}/manifest", get(get_project_manifest))
+            .route("/projects/{id}/stats", get(get_project_stats))
+            .route("/projects/{id}/calendar.ics", get(get_project_calendar))
+            .route("/projects/{id}/github", get(get_github_config))
+            .route("/projects/{id}/github", put(configure_github))
+            .route("/projects/{id}/github", delete(remove_github))
+            .route("/projects/{id}/github/sync", post(sync_github))
+            .route("/import/jira", post(import_jira))
+            .route("/admin/snapshot", post(create_snapshot))
+            .route("/admin/backup", post(create_backup))
+            .route("/admin/import", post(import_tasks).layer(bulk))
+            .route("/admin/restore", post(restore_backup).layer(body_limit(limits.max_restore_bytes, "max_restore_bytes")))
+            .route("/admin/retention", post(apply_retention))
+            .route("/admin/cold-storage", post(move_to_cold_storage))
+            .route("/admin/compact", post(compact_storage))
+            .route("/admin/vacuum", post(vacuum_storage))
+            .route("/admin/fsck", post(check_storage))
+            .route("/admin/maintenance", get(get_maintenance))
+            .route("/admin/maintenance", post(enable_maintenance))
+            .route("/admin/drain", post(drain))
+            .route("/admin/resume", post(resume))
+            .route("/audit", get(list_audit))
+            .route("/archive/tasks/{id}", get(get_archived_task))
+            .route("/agents", get(list_agents))
+            .route("/agents", post(register_agent))
+            .route("/workers", get(list_workers))
+            .route("/workers/register", post(register_worker))
+            .route("/agents/{id}", get(get_agent))
+            .route("/agents/{id}/heartbeat", post(agent_heartbeat))
+            .route("/views", get(list_views))
+            .route("/views/{name}", get(get_view))
+            .route("/views/{name}", put(save_view))
+            .route("/views/{name}", delete(delete_view))
+            .route("/views/{name}/tasks", get(get_view_tasks))
+            .route("/queues", get(list_queues))
+            .route("/queues", post(create_queue))
+            .route("/queues/{name}", get(get_queue))
+            .route("/queues/{name}", put(save_queue))
+            .route("/queues/{name}", delete(delete_queue))
+            .route("/recurrences", get(list_recurrences))
+            .route("/recurrences", post(create_recurrence))
+            .route("/recurrences/{id}", get(get_recurrence))
+            .route("/recurrences/{id}", delete(delete_recurrence))
+            .route("/recurrences/{id}/pause", post(pause_recurrence))
+            .route("/recurrences/{id}/resume", post(resume_recurrence))
+            .route("/dead-letter", get(list_dead_letters))
+            .route("/dead-letter/{id}/requeue", post(requeue_dead_letter))
+            .route("/secrets", get(list_secrets))
+            .route("/secrets/{name}", put(set_secret))
+            .route("/secrets/{name}", delete(delete_secret))
             .route("/metrics", get(get_metrics))
             .route("/stats", get(get_stats))
+            .route("/stats/timeseries", get(get_stats_timeseries))
+            .route("/events", get(stream_events))
+            .route("/tenants", get(list_tenants))
+            .route("/tenants", post(create_tenant))
+            .route("/tenants/{id}", get(get_tenant))
+            .route("/tenants/{id}/keys", get(list_tenant_keys))
+            .route("/tenants/{id}/keys", post(create_tenant_key))
+            .route("/tenants/{id}/keys/{key_id}", delete(revoke_tenant_key))
+            .route("/openapi.json", get(get_openapi))
+            .layer(middleware::from_fn_with_state(Arc::new(self.clone()), refuse_in_maintenance))
+            .layer(middleware::from_fn_with_state(Arc::new(self.clone()), resolve_tenant))
+            .layer(middleware::from_fn_with_state((limits.max_body_bytes, "max_body_bytes"), explain_too_large))
+            .layer(DefaultBodyLimit::max(limits.max_body_bytes))
+            .layer(middleware::from_fn_with_state(Arc::new(self.clone()), limit_rate));
+
+        let router = Router::new()
+            .nest(API_PREFIX, api.clone())
+            .merge(api.layer(middleware::from_fn(deprecate_unversioned)))
+            .route("/docs", get(serve_docs))
             // Dashboard routes - serve static files
-            .nest_service("/dashboard", ServeDir::new("dashboard/public"))
+            .nest_service("/dashboard", ServeDir::new(&self.http.dashboard_dir))
             .route("/", get(serve_dashboard))
-            .layer(CorsLayer::permissive())
+            .layer(middleware::from_fn_with_state(Arc::new(self.clone()), track_agent))
+            .layer(middleware::from_fn(track_source))
+            .layer(self.http.cors.layer())
             .with_state(Arc::new(self.clone()));
-
-        // Merge REST routes into MCP router
-        let app = mcp_router.merge(rest_routes);
-
-        let listener = tokio::net::TcpListener::bind("0.0.0.0:16080").await
-            .map_err(|e| TaskQueueError::ConfigurationError(format!("Failed to bind listener: {}", e)))?;
-        info!("MCP server with REST API listening on 0.0.0.0:16080");
-        info!("MCP SSE endpoint: http://localhost:16080/mcp/sse");
-        info!("MCP POST endpoint: http://localhost:16080/mcp/message");
-        info!("Dashboard available at: http://localhost:16080");
-        
-        axum::serve(listener, app).await
-            .map_err(|e| TaskQueueError::ConfigurationError(format!("Server error: {}", e)))?;
-        Ok(())
+        if self.http.compression.enabled {
+            router.layer(self.http.compression.layer())
+        } else {
+            router
+        }
     }
 
     /// Submit a new task
     pub async fn submit_task(&self, task: Task) -> Result<uuid::Uuid> {
+        let task = self.prepare_task(task).await?;
+        let task_id = task.id;
+        self.insert_task(&task).await?;
+        self.task_submitted(&task).await;
+        Ok(task_id)
+    }
+
+    /// Submit several tasks at once, all or nothing: if any task is invalid
+    /// none is stored and every rejected item reports its error
+    pub async fn submit_tasks(&self, tasks: Vec<Task>) -> Result<BatchSubmission> {
+        let mut prepared = Vec::with_capacity(tasks.len());
+        let mut items = Vec::with_capacity(tasks.len());
+        for (index, task) in tasks.into_iter().enumerate() {
+            match self.prepare_task(task).await {
+                Ok(task) => {
+                    items.push(BatchItem { index, task_id: Some(task.id), error: None });
+                    prepared.push(task);
+                }
+                Err(e) => items.push(BatchItem { index, task_id: None, error: Some(e.to_string()) }),
+            }
+        }
+
+        if items.iter().any(|item| item.error.is_some()) {
+            // Nothing is stored, so no item got an ID
+            for item in &mut items {
+                item.task_id = None;
+            }
+            return Ok(BatchSubmission { accepted: false, items });
+        }
+
+        // Stored in one commit, so a failure leaves none of them behind
+        let prepared: Vec<Task> = prepared.into_iter()
+            .map(|task| Task { version: task.version + 1, tenant_id: tenants::owner(task.tenant_id), ..task })
+            .collect();
+        let mut transaction = Transaction::default();
+        for task in &prepared {
+            transaction.put_task(task);
+        }
+        self.commit(transaction).await?;
+
+        for task in &prepared {
+            if self.shared.is_none() {
+                map_insert(&self.tasks, task.id, task.clone());
+            }
+            self.events.task_created(task).await;
+            self.task_submitted(task).await;
+        }
+        Ok(BatchSubmission { accepted: true, items })
+    }
+
+    /// Import tasks from JSON Lines, one `CreateTaskRequest` per line; lines
+    /// that don't parse or validate are reported and skipped
+    pub async fn import_tasks<R>(&self, mut reader: R, dry_run: bool) -> Result<ImportReport>
+    where
+        R: tokio::io::AsyncBufRead + Unpin,
+    {
+        use tokio::io::AsyncBufReadExt;
+
+        let mut report = ImportReport { dry_run, ..Default::default() };
+        let mut buffer = Vec::new();
+        let mut number = 0;
+        loop {
+            buffer.clear();
+            if reader.read_until(b'\n', &mut buffer).await? == 0 {
+                break;
+            }
+            number += 1;
+            let parsed = match std::str::from_utf8(&buffer) {
+                Ok(line) => import::parse_line(line),
+                Err(e) => Some(Err(e.to_string())),
+            };
+            let Some(parsed) = parsed else { continue };
+            report.lines += 1;
+
+            let task = match parsed {
+                Ok(request) => self.prepare_task(request.to_task()).await.map_err(|e| e.to_string()),
+                Err(e) => Err(e),
+            };
+            match task {
+                Ok(task) => {
+                    if !dry_run {
+                        self.insert_task(&task).await?;
+                        self.task_submitted(&task).await;
+                        report.task_ids.push(task.id);
+                    }
+                    report.imported += 1;
+                }
+                Err(error) => report.errors.push(LineError { line: number, error }),
+            }
+        }
+        info!("Imported {} of {} task lines{}", report.imported, report.lines, if dry_run { " (dry run)" } else { "" });
+        Ok(report)
+    }
+
+    /// Fan out a group of sibling tasks, with an optional join task that
+    /// runs once the group is done; all or nothing like [`Self::submit_tasks`]
+    pub async fn submit_group(&self, request: CreateTaskGroup) -> Result<GroupSubmission> {
+        request.validate()?;
+        let group_id = uuid::Uuid::new_v4();
+        let has_join = request.join.is_some();
+        let batch = self.submit_tasks(request.into_tasks(group_id)).await?;
+        let join_task_id = match has_join {
+            true => batch.items.last().and_then(|item| item.task_id),
+            false => None,
+        };
+        if batch.accepted {
+            info!("Task group submitted: {} ({} tasks)", group_id, batch.items.len());
+        }
+        Ok(GroupSubmission { group_id, join_task_id, accepted: batch.accepted, items: batch.items })
+    }
+
+    /// How far the members of a task group have got
+    pub async fn group_progress(&self, group_id: uuid::Uuid) -> Result<Option<GroupProgress>> {
+        let tasks = self.task_snapshot().await?;
+        Ok(groups::progress(&tasks).remove(&group_id))
+    }
+
+    /// Validate a submitted task and fill in what it inherits from its project
+    async fn prepare_task(&self, mut task: Task) -> Result<Task> {
         // Validate task
         self.validate_task(&task).await?;
 
-        // Store in memory
-        let task_id = task.id;
+        // Tasks run with their project's executor settings unless they pick their own
+        if let Some(project_id) = task.project_id
+            && let Some(project) = self.get_project(&project_id).await?
         {
-            let mut tasks = self.tasks.write().await;
-            tasks.insert(task_id, task.clone());
+            for key in [crate::executor::EXECUTOR_METADATA_KEY, crate::docker::DOCKER_METADATA_KEY] {
+                if let Some(value) = project.metadata.get(key)
+                    && !task.metadata.contains_key(key) {
+                        task.metadata.insert(key.to_string(), value.clone());
+                    }
+            }
         }
-        
-        // Store in persistent storage
-        self.storage.store_task(&task).await?;
-
-        // Update .tasks file with new task ID
-        // This helps AI models track existing tasks and avoid duplication
-        if let Ok(tasks_content) = tokio::fs::read_to_string(".tasks").await {
-            // Convert SystemTime to DateTime<Utc> for RFC3339 formatting
-            let created_at_rfc3339 = chrono::DateTime::<chrono::Utc>::from(task.created_at)
-                .to_rfc3339();
-
-            let task_entry = format!("# {}: {}\n#   Created: {}\n#   Status: {}\n#   Command: {}\n\n",
-                task_id,
-                task.name,
-                created_at_rfc3339,
-                format!("{:?}", task.status),
-                task.command
-            );
-
-            let updated_content = if tasks_content.contains("# Task IDs (add new tasks below):") {
-                // Insert after the header
-                tasks_content.replace("# Task IDs (add new tasks below):\n", &format!("# Task IDs (add new tasks below):\n{}", task_entry))
-            } else {
-                // Append to end if header not found
-                format!("{}\n{}", tasks_content, task_entry)
-            };
 
-            if let Err(e) = tokio::fs::write(".tasks", updated_content).await {
-                warn!("Failed to update .tasks file for task {}: {}", task_id, e);
-            } else {
-                info!("Updated .tasks file with new task: {}", task.name);
+        // Tasks in a queue retry as the queue says
+        if let Some(name) = queues::queue_name(&task) {
+            let queue = self.storage.load_queue(name).await?.ok_or_else(|| TaskQueueError::ValidationError {
+                reason: format!("Queue '{}' does not exist", name),
+            })?;
+            if let Some(retry) = &queue.retry {
+                retry.apply(&mut task);
             }
-        } else {
-            warn!("Could not read .tasks file to update with new task: {}", task.name);
         }
 
+        // Tasks with a start time wait as Scheduled until it arrives
+        if let Some(run_at) = task.run_at() {
+            task.status = if run_at > chrono::Utc::now() { TaskStatus::Scheduled } else { TaskStatus::Pending };
+        }
+
+        Ok(task)
+    }
+
+    /// Bookkeeping once a submitted task is stored
+    async fn task_submitted(&self, task: &Task) {
+        let task_id = task.id;
+
         // Store in vectorizer (non-blocking - don't fail task submission if vectorizer fails)
         let context = TaskContext {
             task_id,
             project: task.project.clone(),
             dependencies: task.dependencies.iter().map(|d| d.task_id).collect(),
-            parameters: HashMap::new(),
+            parameters: HashMap::from([
+                ("name".to_string(), json!(task.name)),
+                ("description".to_string(), json!(task.description)),
+            ]),
             execution_time: std::time::Duration::from_secs(0),
             result: TaskResult::Success {
                 output: "Task submitted".to_string(),
@@ -386,17 +1718,84 @@ impl TaskQueueServer {
         self.metrics.increment_tasks_submitted();
         
         info!("Task submitted: {} ({})", task.name, task_id);
-        Ok(task_id)
     }
 
-    /// Get task by ID
+    /// Tasks most like a natural-language query, most similar first, with
+    /// the vectorizer's similarity scores; tasks since deleted or of another
+    /// tenant are left out
+    pub async fn search_similar_tasks(&self, query: &str, limit: usize) -> Result<Vec<(Task, f64)>> {
+        let mut similar = Vec::new();
+        for hit in self.vectorizer.search_task_contexts(query, Some(limit)).await? {
+            let Ok(task_id) = uuid::Uuid::parse_str(&hit.task_id) else {
+                continue;
+            };
+            match self.get_task(task_id).await {
+                Ok(task) => similar.push((task, hit.score)),
+                Err(TaskQueueError::TaskNotFound { .. }) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        similar.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        similar.truncate(limit);
+        Ok(similar)
+    }
+
+    /// Get task by ID, from cold storage if it was moved there
     pub async fn get_task(&self, task_id: uuid::Uuid) -> Result<Task> {
-        let tasks = self.tasks.read().await;
-        tasks.get(&task_id)
-            .cloned()
-            .ok_or_else(|| TaskQueueError::TaskNotFound { 
+        if let Some(task) = self.read_task(&task_id).await? {
+            return Ok(task);
+        }
+        match self.storage.load_cold_task(&task_id).await?.filter(|task| tenants::visible(task.tenant_id)) {
+            Some(task) => Ok(task),
+            None => Err(TaskQueueError::TaskNotFound { 
                 task_id: task_id.to_string() 
-            })
+            }),
+        }
+    }
+
+    /// Reconstruct a task as it was at the given moment
+    pub async fn get_task_as_of(&self, task_id: uuid::Uuid, at: chrono::DateTime<chrono::Utc>) -> Result<Task> {
+        match self.storage.load_task_as_of(&task_id, at).await?.filter(|task| tenants::visible(task.tenant_id)) {
+            Some(task) => Ok(task),
+            None => Err(TaskQueueError::TaskNotFound {
+                task_id: task_id.to_string(),
+            }),
+        }
+    }
+
+    /// Status timeline of a task, replayed from its recorded state changes;
+    /// still available after the task was deleted
+    pub async fn task_history(&self, task_id: uuid::Uuid) -> Result<StateHistory> {
+        let changes = self.storage.list_state_changes(&task_id).await?;
+        // Tasks from before the log existed have no changes yet, and tenants
+        // only see the history of their tasks
+        if changes.is_empty() || tenants::current_tenant().is_some() {
+            self.get_task(task_id).await?;
+        }
+        Ok(StateHistory::replay(task_id, changes))
+    }
+
+    /// Task activity over the query's window, bucketed from the state history
+    pub async fn task_timeseries(&self, query: &TimeseriesQuery) -> Result<Timeseries> {
+        let now = chrono::Utc::now();
+        let (_, to) = query.range(now);
+        let mut changes = self.storage.list_state_changes_before(to).await?;
+        // The history doesn't say whose a task was; tenants see their
+        // current tasks' activity
+        if tenants::current_tenant().is_some() {
+            let visible: HashSet<uuid::Uuid> = self.visit_tasks(&TaskFilter::default(), |task, _| task.id).await?.into_iter().collect();
+            changes.retain(|change| visible.contains(&change.entity_id));
+        }
+        Ok(timeseries::compute(&changes, query, now))
+    }
+
+    /// Status timeline of a workflow, replayed from its recorded state changes
+    pub async fn workflow_history(&self, workflow_id: uuid::Uuid) -> Result<StateHistory> {
+        let changes = self.storage.list_state_changes(&workflow_id).await?;
+        if changes.is_empty() || tenants::current_tenant().is_some() {
+            self.get_workflow(workflow_id).await?;
+        }
+        Ok(StateHistory::replay(workflow_id, changes))
     }
 
     /// Get task status
@@ -405,6 +1804,36 @@ impl TaskQueueServer {
         Ok(task.status)
     }
 
+    /// Wait up to `timeout` for a task to reach a terminal status; returns
+    /// the task as it then is and whether it got there
+    pub async fn wait_for_task(&self, task_id: uuid::Uuid, timeout: Duration) -> Result<(Task, bool)> {
+        // Subscribing before reading the task misses no change in between
+        let mut receiver = self.events.subscribe();
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let task = self.get_task(task_id).await?;
+            if task.status.is_terminal() {
+                return Ok((task, true));
+            }
+            // Changes made by other replicas don't reach this bus, so the
+            // task is read again now and then regardless
+            let wake = deadline.min(tokio::time::Instant::now() + TASK_WAIT_RECHECK);
+            loop {
+                match tokio::time::timeout_at(wake, receiver.recv()).await {
+                    Ok(Ok(event)) if event.task_id != Some(task_id) => continue,
+                    Ok(Err(tokio::sync::broadcast::error::RecvError::Closed)) => tokio::time::sleep_until(wake).await,
+                    _ => {}
+                }
+                break;
+            }
+            if tokio::time::Instant::now() >= deadline {
+                let task = self.get_task(task_id).await?;
+                let finished = task.status.is_terminal();
+                return Ok((task, finished));
+            }
+        }
+    }
+
     /// Get task result
     pub async fn get_task_result(&self, task_id: uuid::Uuid) -> Result<Option<TaskResult>> {
         let task = self.get_task(task_id).await?;
@@ -417,117 +1846,288 @@ impl TaskQueueServer {
         project: Option<String>,
         status: Option<String>
     ) -> Result<Vec<Task>> {
-        let tasks = self.tasks.read().await;
-        let mut filtered_tasks: Vec<Task> = tasks.values().cloned().collect();
-
-        if let Some(project) = project {
-            filtered_tasks.retain(|task| task.project.as_ref() == Some(&project));
-        }
-
-        if let Some(status) = status {
-            filtered_tasks.retain(|task| {
-                // Get the effective status considering workflow status
-                let effective_status = Self::get_effective_task_status(task);
-                match status.as_str() {
-                    "planning" => matches!(effective_status, TaskStatus::Planning),
-                    "pending" => matches!(effective_status, TaskStatus::Pending),
-                    "running" => matches!(effective_status, TaskStatus::Running),
-                    "completed" => matches!(effective_status, TaskStatus::Completed),
-                    "failed" => matches!(effective_status, TaskStatus::Failed),
-                    "cancelled" => matches!(effective_status, TaskStatus::Cancelled),
-                    "implementation" => matches!(effective_status, TaskStatus::InImplementation),
-                    "testcreation" => matches!(effective_status, TaskStatus::TestCreation),
-                    "testing" => matches!(effective_status, TaskStatus::Testing),
-                    "aireview" => matches!(effective_status, TaskStatus::AIReview),
-                    _ => false,
-                }
-            });
-        }
+        self.list_matching_tasks(&TaskFilter::of(project, status)).await
+    }
+
+    /// List lightweight task summaries with optional filters
+    pub async fn list_task_summaries(
+        &self,
+        project: Option<String>,
+        status: Option<String>
+    ) -> Result<Vec<TaskSummary>> {
+        self.list_matching_task_summaries(&TaskFilter::of(project, status)).await
+    }
 
-        // Update tasks to show effective status for display
-        let mut display_tasks = Vec::new();
-        for task in filtered_tasks {
+    /// List the tasks matching every criterion of `filter`
+    pub async fn list_matching_tasks(&self, filter: &TaskFilter) -> Result<Vec<Task>> {
+        // Only matching tasks are cloned, with the effective status for display
+        self.visit_tasks(filter, |task, effective_status| {
             let mut display_task = task.clone();
-            display_task.status = Self::get_effective_task_status(&task);
-            display_tasks.push(display_task);
-        }
+            display_task.status = effective_status;
+            display_task
+        }).await
+    }
 
-        Ok(display_tasks)
+    pub async fn list_matching_task_summaries(&self, filter: &TaskFilter) -> Result<Vec<TaskSummary>> {
+        self.visit_tasks(filter, Self::summarize).await
     }
 
-    /// Get the effective task status considering workflow status and current phase
-    pub fn get_effective_task_status(task: &Task) -> TaskStatus {
-        // If task has an active development workflow, use workflow status
-        if let Some(ref workflow) = task.development_workflow {
-            info!("Task {} has workflow status: {:?}, current phase: {:?}", task.name, workflow.workflow_status, task.current_phase);
-            
-            // If workflow is NotStarted but task has advanced phases, use current_phase
-            if workflow.workflow_status == crate::core::DevelopmentWorkflowStatus::NotStarted {
-                match task.current_phase {
-                    crate::core::TaskStatus::Planning => TaskStatus::Planning,
-                    crate::core::TaskStatus::Implementation => TaskStatus::Implementation,
-                    crate::core::TaskStatus::TestCreation => TaskStatus::TestCreation,
-                    crate::core::TaskStatus::Testing => TaskStatus::Testing,
-                    crate::core::TaskStatus::AIReview => TaskStatus::AIReview,
-                    crate::core::TaskStatus::Finalized => TaskStatus::Finalized,
-                    crate::core::TaskStatus::Completed => TaskStatus::Completed,
-                    crate::core::TaskStatus::Failed => TaskStatus::Failed,
-                    crate::core::TaskStatus::Cancelled => TaskStatus::Cancelled,
-                    _ => TaskStatus::Planning, // Default fallback
-                }
-            } else {
-                // Use workflow status for active workflows
-                match workflow.workflow_status {
-                    crate::core::DevelopmentWorkflowStatus::NotStarted => TaskStatus::Planning,
-                    crate::core::DevelopmentWorkflowStatus::Planning => TaskStatus::Planning,
-                    crate::core::DevelopmentWorkflowStatus::InImplementation => TaskStatus::Implementation,
-                    crate::core::DevelopmentWorkflowStatus::TestCreation => TaskStatus::TestCreation,
-                    crate::core::DevelopmentWorkflowStatus::Testing => TaskStatus::Testing,
-                    crate::core::DevelopmentWorkflowStatus::AIReview => TaskStatus::AIReview,
-                    crate::core::DevelopmentWorkflowStatus::Completed => {
-                        info!("Task {} workflow is completed, returning Completed status", task.name);
-                        TaskStatus::Completed
-                    },
-                    crate::core::DevelopmentWorkflowStatus::Failed => TaskStatus::Failed,
+    /// Call `f` on every task matching the filter, without cloning the rest
+    async fn visit_tasks<R>(
+        &self,
+        filter: &TaskFilter,
+        mut f: impl FnMut(&Task, TaskStatus) -> R,
+    ) -> Result<Vec<R>> {
+        let now = chrono::Utc::now();
+        let mut results = Vec::new();
+        let mut visit = |task: &Task| {
+            // Get the effective status considering workflow status
+            if !tenants::visible(task.tenant_id) {
+                return;
+            }
+            let effective_status = Self::get_effective_task_status(task);
+            if filter.matches(task, &effective_status, now) {
+                results.push(f(task, effective_status));
+            }
+        };
+
+        // Filtered listings only read the tasks the indexes point at
+        if let Some(ids) = self.indexed_task_ids(filter).await? {
+            for id in ids {
+                if let Some(entry) = self.task_entry(&id).await? {
+                    visit(&*entry.read().await);
                 }
             }
-        } else {
-            info!("Task {} has no workflow, using current phase: {:?}", task.name, task.current_phase);
-            // Fall back to the task's current phase
-            task.current_phase.clone()
+            return Ok(results);
         }
-    }
 
-    /// Submit a workflow
-    pub async fn submit_workflow(&self, workflow: Workflow) -> Result<uuid::Uuid> {
-        // Validate workflow
-        self.validate_workflow(&workflow)?;
-        
-        // Store in memory
-        let workflow_id = workflow.id;
-        {
-            let mut workflows = self.workflows.write().await;
-            workflows.insert(workflow_id, workflow.clone());
+        if self.shared.is_none() {
+            for entry in self.task_handles().await? {
+                visit(&*entry.read().await);
+            }
+            return Ok(results);
         }
-        
-        // Store in persistent storage
-        self.storage.store_workflow(&workflow).await?;
-        
-        // Update metrics
-        self.metrics.increment_workflows_submitted();
-        
-        info!("Workflow submitted: {} ({})", workflow.name, workflow_id);
-        Ok(workflow_id)
+
+        // Shared storage is read a batch at a time so only matches are kept
+        let mut after = None;
+        loop {
+            let (tasks, next) = self.storage.list_tasks_page(after, TASK_SCAN_BATCH).await?;
+            tasks.iter().for_each(&mut visit);
+            match next {
+                Some(next) => after = Some(next),
+                None => break,
+            }
+        }
+        Ok(results)
     }
 
-    /// Get workflow by ID
+    /// IDs of the tasks in the filter's project and statuses by the storage
+    /// indexes; `None` without either
+    async fn indexed_task_ids(&self, filter: &TaskFilter) -> Result<Option<Vec<uuid::Uuid>>> {
+        let mut ids = match &filter.project {
+            Some(project) => Some(self.storage.find_task_ids(&TaskIndex::Project(project.clone())).await?),
+            None => None,
+        };
+        if !filter.statuses.is_empty() {
+            // A status no task can have matches nothing
+            let mut matching = HashSet::new();
+            for status in filter.statuses.iter().filter_map(|status| TaskStatus::from_filter(status)) {
+                matching.extend(self.storage.find_task_ids(&TaskIndex::Status(status)).await?);
+            }
+            ids = Some(match ids {
+                Some(ids) => ids.into_iter().filter(|id| matching.contains(id)).collect(),
+                None => matching.into_iter().collect(),
+            });
+        }
+        Ok(ids)
+    }
+
+    fn summarize(task: &Task, effective_status: TaskStatus) -> TaskSummary {
+        let mut summary = TaskSummary::from(task);
+        summary.status = effective_status;
+        summary
+    }
+
+    /// List one page of tasks in the requested order
+    ///
+    /// Returns the page with an opaque cursor for the next page, if any.
+    pub async fn list_tasks_page(&self, filter: &TaskFilter, page: PageRequest) -> Result<TaskPage<Task>> {
+        let (ids, next_cursor, total) = self.page_task_ids(filter, page).await?;
+
+        let mut tasks = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(entry) = self.task_entry(&id).await? {
+                let task = entry.read().await;
+                let mut display_task = task.clone();
+                display_task.status = Self::get_effective_task_status(&task);
+                tasks.push(display_task);
+            }
+        }
+
+        Ok(TaskPage { tasks, next_cursor, total })
+    }
+
+    /// Tasks with every word of a full-text query, best match first
+    pub async fn search_tasks(&self, query: &str, limit: usize) -> Result<SearchResults> {
+        let terms = search::parse_query(query);
+        let total_tasks = self.storage.count_tasks().await?;
+
+        // Counts of each query term in each task having all of them so far
+        let mut matches: Option<HashMap<uuid::Uuid, Vec<TermCounts>>> = None;
+        let mut matching = Vec::with_capacity(terms.len());
+        for term in &terms {
+            let mut found: HashMap<uuid::Uuid, TermCounts> = HashMap::new();
+            // A prefix adds up the counts of every term it starts
+            for posting in self.storage.find_postings(&term.term, term.prefix).await? {
+                let counts = found.entry(posting.task_id).or_default();
+                for (count, more) in counts.0.iter_mut().zip(posting.counts.0) {
+                    *count = count.saturating_add(more);
+                }
+            }
+            matching.push(found.len());
+            matches = Some(match matches {
+                None => found.into_iter().map(|(id, counts)| (id, vec![counts])).collect(),
+                Some(mut matches) => {
+                    matches.retain(|id, _| found.contains_key(id));
+                    for (id, counts) in matches.iter_mut() {
+                        counts.push(found[id]);
+                    }
+                    matches
+                }
+            });
+        }
+
+        let mut ranked: Vec<(uuid::Uuid, f64)> = matches.unwrap_or_default().into_iter()
+            .map(|(id, counts)| (id, search::score(&counts, &matching, total_tasks)))
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        // Tasks of other tenants aren't counted
+        let mut results = Vec::new();
+        let mut total = 0;
+        for (id, score) in ranked {
+            let Some(task) = self.read_task(&id).await? else { continue };
+            total += 1;
+            if results.len() < limit {
+                results.push(SearchHit {
+                    task: Self::summarize(&task, Self::get_effective_task_status(&task)),
+                    score,
+                    highlights: search::highlights(&task, &terms),
+                });
+            }
+        }
+        Ok(SearchResults { query: query.to_string(), total, results })
+    }
+
+    /// List one page of task summaries in the requested order
+    pub async fn list_task_summaries_page(&self, filter: &TaskFilter, page: PageRequest) -> Result<TaskPage<TaskSummary>> {
+        let (ids, next_cursor, total) = self.page_task_ids(filter, page).await?;
+
+        let mut summaries = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(entry) = self.task_entry(&id).await? {
+                let task = entry.read().await;
+                summaries.push(Self::summarize(&task, Self::get_effective_task_status(&task)));
+            }
+        }
+
+        Ok(TaskPage { tasks: summaries, next_cursor, total })
+    }
+
+    /// Resolve the task IDs of one page and the number of matching tasks,
+    /// sorting only the keys
+    async fn page_task_ids(&self, filter: &TaskFilter, page: PageRequest) -> Result<(Vec<uuid::Uuid>, Option<String>, usize)> {
+        if matches!(page.sort, SortField::Name | SortField::DueDate) {
+            return Err(TaskQueueError::ValidationError {
+                reason: format!("Tasks can't be paged by {:?}", page.sort),
+            });
+        }
+        let after = match &page.cursor {
+            Some(cursor) => Some(Self::decode_task_cursor(cursor)?),
+            None => None,
+        };
+
+        let mut keys = self
+            .visit_tasks(filter, |task, _| Self::task_sort_key(task, page.sort))
+            .await?;
+        let total = keys.len();
+        keys.sort();
+        if let Some(after) = after {
+            keys.retain(|key| *key > after);
+        }
+        keys.drain(..page.offset.min(keys.len()));
+
+        let limit = page.limit.max(1);
+        let next_cursor = if keys.len() > limit {
+            keys.truncate(limit);
+            keys.last().map(Self::encode_sort_key)
+        } else {
+            None
+        };
+
+        Ok((keys.into_iter().map(|(_, id)| id).collect(), next_cursor, total))
+    }
+
+    fn task_sort_key(task: &Task, sort: SortField) -> (u128, uuid::Uuid) {
+        let micros = |time: std::time::SystemTime| {
+            time.duration_since(std::time::UNIX_EPOCH).map(|d| d.as_micros()).unwrap_or(0)
+        };
+        let key = match sort {
+            SortField::UpdatedAt => micros(task.updated_at),
+            // Priorities rank above any creation time, highest first
+            SortField::Priority => {
+                let rank = TaskPriority::Critical as u128 - task.priority.clone() as u128;
+                (rank << 64) | micros(task.created_at)
+            }
+            _ => micros(task.created_at),
+        };
+        (key, task.id)
+    }
+
+    fn encode_sort_key(key: &(u128, uuid::Uuid)) -> String {
+        format!("{}_{}", key.0, key.1)
+    }
+
+    fn decode_task_cursor(cursor: &str) -> Result<(u128, uuid::Uuid)> {
+        let invalid = || TaskQueueError::ValidationError {
+            reason: format!("Invalid cursor: {}", cursor),
+        };
+        let (micros, id) = cursor.split_once('_').ok_or_else(invalid)?;
+        Ok((
+            micros.parse().map_err(|_| invalid())?,
+            id.parse().map_err(|_| invalid())?,
+        ))
+    }
+
+    /// Get the effective task status considering workflow status and current phase
+    pub fn get_effective_task_status(task: &Task) -> TaskStatus {
+        task.effective_status()
+    }
+
+    /// Submit a workflow
+    pub async fn submit_workflow(&self, workflow: Workflow) -> Result<uuid::Uuid> {
+        // Validate workflow
+        self.validate_workflow(&workflow)?;
+        
+        // Store in memory and persistent storage
+        let workflow_id = workflow.id;
+        self.insert_workflow(&workflow).await?;
+        
+        // Update metrics
+        self.metrics.increment_workflows_submitted();
+        
+        info!("Workflow submitted: {} ({})", workflow.name, workflow_id);
+        Ok(workflow_id)
+    }
+
+    /// Get workflow by ID
     pub async fn get_workflow(&self, workflow_id: uuid::Uuid) -> Result<Workflow> {
-        let workflows = self.workflows.read().await;
-        workflows.get(&workflow_id)
-            .cloned()
-            .ok_or_else(|| TaskQueueError::WorkflowNotFound { 
+        match self.read_workflow(&workflow_id).await? {
+            Some(workflow) => Ok(workflow),
+            None => Err(TaskQueueError::WorkflowNotFound { 
                 workflow_id: workflow_id.to_string() 
-            })
+            }),
+        }
     }
 
     /// Get workflow status
@@ -544,7 +2144,11 @@ impl TaskQueueServer {
             });
         }
 
-        if task.command.is_empty() {
+        // Issues imported from GitHub or Jira are tracked, not run
+        let imported = [github::ISSUE_METADATA_KEY, jira::ISSUE_METADATA_KEY].iter().any(|key| task.metadata.contains_key(*key));
+        if let TaskType::Http = task.task_type {
+            crate::http_task::HttpRequestSpec::of(task).map_err(|reason| TaskQueueError::InvalidTaskDefinition { reason })?;
+        } else if task.command.is_empty() && !imported {
             return Err(TaskQueueError::InvalidTaskDefinition {
                 reason: "Task command cannot be empty".to_string(),
             });
@@ -557,14 +2161,16 @@ impl TaskQueueServer {
         }
 
         // Validate that the project exists
-        if let Some(project_id) = &task.project_id {
-            if self.get_project(project_id).await?.is_none() {
-                return Err(TaskQueueError::InvalidTaskDefinition {
-                    reason: format!("Project with ID {} does not exist", project_id),
-                });
-            }
+        if let Some(project_id) = &task.project_id
+            && self.get_project(project_id).await?.is_none()
+        {
+            return Err(TaskQueueError::InvalidTaskDefinition {
+                reason: format!("Project with ID {} does not exist", project_id),
+            });
         }
 
+        crate::resources::Resources::requested_by(task)?;
+
         Ok(())
     }
 
@@ -595,12 +2201,12 @@ impl TaskQueueServer {
         let mut rec_stack = std::collections::HashSet::new();
         
         for task in &workflow.tasks {
-            if !visited.contains(&task.id) {
-                if self.has_cycle(task.id, &workflow.tasks, &mut visited, &mut rec_stack) {
-                    return Err(TaskQueueError::CircularDependency {
-                        cycle: "Circular dependency detected in workflow".to_string(),
-                    });
-                }
+            if !visited.contains(&task.id)
+                && self.has_cycle(task.id, &workflow.tasks, &mut visited, &mut rec_stack)
+            {
+                return Err(TaskQueueError::CircularDependency {
+                    cycle: "Circular dependency detected in workflow".to_string(),
+                });
             }
         }
         
@@ -634,10 +2240,37 @@ impl TaskQueueServer {
         false
     }
 
-    /// Add dependency to a task
+    /// Whether `task_id` waits on `target`, directly or through its dependencies
+    async fn depends_on(&self, task_id: uuid::Uuid, target: uuid::Uuid) -> Result<bool> {
+        let mut seen = std::collections::HashSet::from([task_id]);
+        let mut pending = vec![task_id];
+        while let Some(id) = pending.pop() {
+            // Groups and deleted tasks have no dependencies to follow
+            let Some(task) = self.read_task(&id).await? else {
+                continue;
+            };
+            for dependency in task.dependencies {
+                if dependency.task_id == target {
+                    return Ok(true);
+                }
+                if seen.insert(dependency.task_id) {
+                    pending.push(dependency.task_id);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    /// Add dependency to a task; refused if the dependency already waits on the task
     pub async fn add_task_dependency(&self, task_id: uuid::Uuid, dependency_task_id: uuid::Uuid, task_name: Option<String>, condition: crate::core::DependencyCondition, required: bool, correlation_id: Option<String>) -> Result<()> {
-        let mut tasks = self.tasks.write().await;
-        if let Some(task) = tasks.get_mut(&task_id) {
+        if dependency_task_id == task_id || self.depends_on(dependency_task_id, task_id).await? {
+            return Err(TaskQueueError::CircularDependency {
+                cycle: format!("{} already waits on {}", dependency_task_id, task_id),
+            });
+        }
+        if let Some(entry) = self.task_entry(&task_id).await? {
+            let mut guard = entry.write().await;
+            let task = &mut *guard;
             if let Some(correlation_id) = correlation_id {
                 task.add_correlated_dependency(dependency_task_id, task_name, condition, required, correlation_id);
             } else {
@@ -645,7 +2278,7 @@ impl TaskQueueServer {
             }
             
             // Update in storage
-            self.storage.store_task(task).await?;
+            self.persist_task(task).await?;
             
             info!("Dependency added to task: {} -> {} ({})", dependency_task_id, task.name, task_id);
             Ok(())
@@ -658,8 +2291,8 @@ impl TaskQueueServer {
 
     /// Get task dependencies
     pub async fn get_task_dependencies(&self, task_id: uuid::Uuid) -> Result<Vec<crate::core::Dependency>> {
-        let tasks = self.tasks.read().await;
-        if let Some(task) = tasks.get(&task_id) {
+        if let Some(entry) = self.task_entry(&task_id).await? {
+            let task = entry.read().await;
             Ok(task.dependencies.clone())
         } else {
             Err(TaskQueueError::TaskNotFound { 
@@ -670,12 +2303,13 @@ impl TaskQueueServer {
 
     /// Advance task development phase
     pub async fn advance_task_phase(&self, task_id: uuid::Uuid) -> Result<bool> {
-        let mut tasks = self.tasks.write().await;
-        if let Some(task) = tasks.get_mut(&task_id) {
+        if let Some(entry) = self.task_entry(&task_id).await? {
+            let mut guard = entry.write().await;
+            let task = &mut *guard;
             match task.advance_phase() {
                 Ok(()) => {
                     // Update in storage
-                    self.storage.store_task(task).await?;
+                    self.persist_task(task).await?;
                     
                     info!("Task phase advanced: {} ({})", task.name, task_id);
                     Ok(true)
@@ -694,12 +2328,13 @@ impl TaskQueueServer {
 
     /// Set task status with validation
     pub async fn set_task_status(&self, task_id: uuid::Uuid, new_status: TaskStatus) -> Result<()> {
-        let mut tasks = self.tasks.write().await;
-        if let Some(task) = tasks.get_mut(&task_id) {
+        if let Some(entry) = self.task_entry(&task_id).await? {
+            let mut guard = entry.write().await;
+            let task = &mut *guard;
             task.set_status(new_status)?;
             
             // Update in storage
-            self.storage.store_task(task).await?;
+            self.persist_task(task).await?;
             
             info!("Task status updated: {} ({})", task.name, task_id);
             Ok(())
@@ -710,154 +2345,1091 @@ impl TaskQueueServer {
         }
     }
 
-    /// Get task correlations
-    pub async fn get_task_correlations(&self, task_id: uuid::Uuid) -> Result<Vec<String>> {
-        let tasks = self.tasks.read().await;
-        if let Some(task) = tasks.get(&task_id) {
-            let correlations: Vec<String> = task.dependencies.iter()
-                .filter_map(|dep| dep.correlation_id.clone())
-                .collect();
-            Ok(correlations)
-        } else {
-            Err(TaskQueueError::TaskNotFound { 
-                task_id: task_id.to_string() 
-            })
+    /// Mark a task as picked up for execution
+    pub async fn start_task_execution(&self, task_id: uuid::Uuid) -> Result<Task> {
+        let entry = self.task_entry(&task_id).await?.ok_or_else(|| TaskQueueError::TaskNotFound {
+            task_id: task_id.to_string(),
+        })?;
+        let mut guard = entry.write().await;
+        let task = &mut *guard;
+
+        task.status = TaskStatus::Running;
+        task.result = None;
+        task.updated_at = std::time::SystemTime::now();
+        self.persist_task(task).await?;
+
+        info!("Task started: {} ({})", task.name, task_id);
+        Ok(task.clone())
+    }
+
+    /// Move a task from `Pending` to `Running`; `None` if it is no longer pending
+    pub async fn claim_task(&self, task_id: uuid::Uuid) -> Result<Option<Task>> {
+        let entry = self.task_entry(&task_id).await?.ok_or_else(|| TaskQueueError::TaskNotFound {
+            task_id: task_id.to_string(),
+        })?;
+        let mut guard = entry.write().await;
+        let task = &mut *guard;
+        if task.status != TaskStatus::Pending {
+            return Ok(None);
         }
+
+        task.status = TaskStatus::Running;
+        task.result = None;
+        task.updated_at = std::time::SystemTime::now();
+        self.persist_task(task).await?;
+
+        info!("Task claimed for execution: {} ({})", task.name, task_id);
+        Ok(Some(task.clone()))
     }
 
-    /// Cancel a task
-    pub async fn cancel_task(&self, task_id: uuid::Uuid, reason: String) -> Result<()> {
-        let mut tasks = self.tasks.write().await;
-        if let Some(task) = tasks.get_mut(&task_id) {
-            task.status = crate::core::TaskStatus::Cancelled;
-            task.result = Some(crate::core::TaskResult::Cancelled { reason: reason.clone() });
+    /// Move `Scheduled` tasks whose `run_at` has arrived to `Pending`; returns their IDs
+    pub async fn promote_scheduled_tasks(&self, now: chrono::DateTime<chrono::Utc>) -> Result<Vec<uuid::Uuid>> {
+        let mut promoted = Vec::new();
+
+        for task in self.tasks_with_status(&[TaskStatus::Scheduled]).await? {
+            if task.status != TaskStatus::Scheduled || task.run_at().is_some_and(|at| at > now) {
+                continue;
+            }
+            let Some(entry) = self.task_entry(&task.id).await? else {
+                continue;
+            };
+            let mut guard = entry.write().await;
+            let task = &mut *guard;
+            // Rescheduled or cancelled meanwhile
+            if task.status != TaskStatus::Scheduled || task.run_at().is_some_and(|at| at > now) {
+                continue;
+            }
+
+            task.status = TaskStatus::Pending;
             task.updated_at = std::time::SystemTime::now();
-            
-            // Update in storage
-            self.storage.store_task(task).await?;
-            
-            // Update metrics
-            self.metrics.increment_tasks_cancelled();
-            
-            info!("Task cancelled: {} ({})", task.name, task_id);
-            Ok(())
-        } else {
-            Err(TaskQueueError::TaskNotFound { 
-                task_id: task_id.to_string() 
-            })
+            self.persist_task(task).await?;
+            info!("Scheduled task is due: {} ({})", task.name, task.id);
+            promoted.push(task.id);
         }
+
+        Ok(promoted)
     }
 
-    /// Delete a task
-    pub async fn delete_task(&self, task_id: uuid::Uuid) -> Result<()> {
-        let mut tasks = self.tasks.write().await;
-        if let Some(task) = tasks.get(&task_id) {
-            let task_name = task.name.clone();
-            
-            // Remove from memory
-            tasks.remove(&task_id);
-            
-            // Remove from storage
-            self.storage.delete_task(&task_id).await?;
-            
-            info!("Task deleted: {} ({})", task_name, task_id);
-            Ok(())
-        } else {
-            Err(TaskQueueError::TaskNotFound { 
-                task_id: task_id.to_string() 
+    /// Pending tasks whose dependencies are met, in the order they should
+    /// run: highest priority first, first submitted first within a priority
+    pub async fn ready_tasks(&self) -> Result<ReadyQueue> {
+        let pending = self.tasks_with_status(&[TaskStatus::Pending]).await?;
+        // Only the results pending tasks wait on are read
+        let mut results: HashMap<uuid::Uuid, TaskResult> = HashMap::new();
+        let mut waits_on_group = false;
+        for dependency in pending.iter().flat_map(|task| &task.dependencies) {
+            if matches!(dependency.condition, DependencyCondition::Group { .. }) {
+                waits_on_group = true;
+            } else if !results.contains_key(&dependency.task_id)
+                && let Some(result) = self.read_task(&dependency.task_id).await?.and_then(|task| task.result) {
+                    results.insert(dependency.task_id, result);
+                }
+        }
+        let groups = match waits_on_group {
+            true => groups::progress(&self.task_snapshot().await?),
+            false => HashMap::new(),
+        };
+
+        Ok(pending.into_iter()
+            .filter(|task| task.is_ready_in(&results, &groups))
+            .collect())
+    }
+
+    /// Lease the next runnable task to an external worker; `None` if there is
+    /// nothing to run. Registered workers get tasks whose worker selector
+    /// they match, up to their `max_concurrency`; unregistered ones only
+    /// tasks without a selector.
+    pub async fn claim_next_task(&self, worker_id: &str, lease_secs: Option<u64>) -> Result<Option<Task>> {
+        self.claim_next_matching_task(worker_id, lease_secs, &TaskFilter::default()).await
+    }
+
+    /// Like [`Self::claim_next_task`], only among the tasks `filter` matches,
+    /// e.g. those of one project
+    pub async fn claim_next_matching_task(&self, worker_id: &str, lease_secs: Option<u64>, filter: &TaskFilter) -> Result<Option<Task>> {
+        if self.maintenance.is_draining() {
+            return Ok(None);
+        }
+        let now = chrono::Utc::now();
+        self.expire_leases(now).await?;
+
+        let worker = self.storage.load_worker(worker_id).await?;
+        if let Some(mut worker) = worker.clone() {
+            worker.last_seen = now;
+            self.storage.store_worker(&worker).await?;
+            if self.leased_task_count(worker_id).await? >= worker.max_concurrency as usize {
+                return Ok(None);
+            }
+        }
+
+        let mut gate = self.queue_gate().await?;
+        for task in self.ready_tasks().await? {
+            let eligible = match &worker {
+                Some(worker) => worker.matches(&task),
+                None => workers::selector(&task).is_none(),
+            };
+            if !eligible || !filter.matches(&task, &task.status, now) || !gate.admit(&task) {
+                continue;
+            }
+            // Lost the race for this one to another worker or the engine
+            let Some(task) = self.claim_task(task.id).await? else {
+                continue;
+            };
+            let lease = Lease::new(worker_id, lease_secs, now);
+            let task = self.modify_task(task.id, |task| lease.apply(task)).await?;
+            info!("Task leased to worker {} until {}: {} ({})", worker_id, lease.expires_at, task.name, task.id);
+            return Ok(Some(task));
+        }
+        Ok(None)
+    }
+
+    /// Tasks that may be leased to a worker
+    async fn leased_tasks(&self) -> Result<Vec<Task>> {
+        self.tasks_with_status(&[TaskStatus::Running, TaskStatus::Paused]).await
+    }
+
+    async fn leased_task_count(&self, worker_id: &str) -> Result<usize> {
+        Ok(self.leased_tasks().await?.iter().filter(|task| Lease::is_held_by(task, worker_id)).count())
+    }
+
+    /// Register an external worker, or update the one registered under that ID
+    pub async fn register_worker(&self, request: RegisterWorker) -> Result<Worker> {
+        request.validate()?;
+        let now = chrono::Utc::now();
+        let registered_at = self.storage.load_worker(&request.worker_id).await?
+            .map_or(now, |existing| existing.registered_at);
+        let worker = Worker {
+            id: request.worker_id,
+            labels: request.labels,
+            max_concurrency: request.max_concurrency,
+            registered_at,
+            last_seen: now,
+        };
+        self.storage.store_worker(&worker).await?;
+        info!("Worker registered: {} (max {} tasks, labels {:?})", worker.id, worker.max_concurrency, worker.labels);
+        Ok(worker)
+    }
+
+    /// Registered workers with their state and leased task count
+    pub async fn list_workers(&self) -> Result<Vec<WorkerStatus>> {
+        let now = chrono::Utc::now();
+        let tasks = self.leased_tasks().await?;
+        let mut workers: Vec<WorkerStatus> = self.storage.list_workers().await?
+            .into_iter()
+            .map(|worker| {
+                let running = tasks.iter().filter(|task| Lease::is_held_by(task, &worker.id)).count();
+                WorkerStatus::at(worker, running, now)
             })
+            .collect();
+        workers.sort_by(|a, b| a.worker.id.cmp(&b.worker.id));
+        Ok(workers)
+    }
+
+    /// Extend a worker's lease on a task from now
+    pub async fn renew_lease(&self, task_id: uuid::Uuid, worker_id: &str, lease_secs: Option<u64>) -> Result<Lease> {
+        let entry = self.task_entry(&task_id).await?.ok_or_else(|| TaskQueueError::TaskNotFound {
+            task_id: task_id.to_string(),
+        })?;
+        let mut guard = entry.write().await;
+        let task = &mut *guard;
+        Self::check_lease(task, worker_id)?;
+
+        let lease = Lease::new(worker_id, lease_secs, chrono::Utc::now());
+        lease.apply(task);
+        self.defer_task(task).await?;
+        Ok(lease)
+    }
+
+    /// Record the result a worker reports for a task it leased
+    pub async fn complete_leased_task(&self, task_id: uuid::Uuid, worker_id: &str, result: TaskResult) -> Result<Task> {
+        let entry = self.task_entry(&task_id).await?.ok_or_else(|| TaskQueueError::TaskNotFound {
+            task_id: task_id.to_string(),
+        })?;
+        let mut guard = entry.write().await;
+        let task = &mut *guard;
+        Self::check_lease(task, worker_id)?;
+
+        task.metadata.remove(crate::lease::LEASE_KEY);
+        // The worker resolved the task's secrets itself; keep them out of what is stored
+        let redactor = self.resolve_secrets(task).await.map(|(_, redactor)| redactor).unwrap_or_default();
+        self.record_execution(task, redactor.redact_result(result)).await?;
+        Ok(task.clone())
+    }
+
+    fn check_lease(task: &Task, worker_id: &str) -> Result<()> {
+        match Lease::of(task) {
+            Some(lease) if task.status == TaskStatus::Running && lease.worker_id == worker_id => Ok(()),
+            _ => Err(TaskQueueError::LeaseNotHeld {
+                task_id: task.id.to_string(),
+                worker_id: worker_id.to_string(),
+            }),
         }
     }
 
-    /// Update a task
-    pub async fn update_task(
-        &self,
-        task_id: uuid::Uuid,
-        name: Option<String>,
-        command: Option<String>,
-        description: Option<String>,
-        priority: Option<crate::core::TaskPriority>,
-        status: Option<crate::core::TaskStatus>,
-        project_id: Option<Option<uuid::Uuid>>,
-    ) -> Result<crate::core::Task> {
-        let mut tasks = self.tasks.write().await;
-        if let Some(task) = tasks.get_mut(&task_id) {
-            if let Some(name) = name {
-                task.name = name;
+    /// Return leased tasks whose lease ran out to `Pending`; returns their IDs
+    pub async fn expire_leases(&self, now: chrono::DateTime<chrono::Utc>) -> Result<Vec<uuid::Uuid>> {
+        let mut expired = Vec::new();
+
+        for task in self.tasks_with_status(&[TaskStatus::Running]).await? {
+            if task.status != TaskStatus::Running || !Lease::of(&task).is_some_and(|l| l.is_expired(now)) {
+                continue;
+            }
+            let Some(entry) = self.task_entry(&task.id).await? else {
+                continue;
+            };
+            let mut guard = entry.write().await;
+            let task = &mut *guard;
+            // Renewed or completed meanwhile
+            let Some(lease) = Lease::of(task).filter(|l| task.status == TaskStatus::Running && l.is_expired(now)) else {
+                continue;
+            };
+
+            task.metadata.remove(crate::lease::LEASE_KEY);
+            task.update_status(TaskStatus::Pending);
+            self.persist_task(task).await?;
+            warn!("Lease of worker {} expired, task back to pending: {} ({})", lease.worker_id, task.name, task.id);
+            expired.push(task.id);
+        }
+
+        Ok(expired)
+    }
+
+    /// Record the result of a task execution; failures with retries left are
+    /// scheduled to run again
+    pub async fn complete_task_execution(&self, task_id: uuid::Uuid, result: TaskResult) -> Result<()> {
+        let entry = self.task_entry(&task_id).await?.ok_or_else(|| TaskQueueError::TaskNotFound {
+            task_id: task_id.to_string(),
+        })?;
+        let mut guard = entry.write().await;
+        self.record_execution(&mut guard, result).await
+    }
+
+    async fn record_execution(&self, task: &mut Task, result: TaskResult) -> Result<()> {
+        let task_id = task.id;
+        let now = chrono::Utc::now();
+        let retry_at = crate::retry::record_attempt(task, result.clone(), now);
+        self.storage.delete_heartbeat(&task_id).await?;
+        match (&result, retry_at) {
+            (TaskResult::Success { metrics, .. }, _) => {
+                self.metrics.increment_tasks_completed();
+                self.metrics.record_task_execution_time(metrics.execution_time);
             }
-            if let Some(command) = command {
-                task.command = command;
+            (TaskResult::Failure { .. }, None) => self.metrics.increment_tasks_failed(),
+            (TaskResult::Failure { .. }, Some(_)) => {}
+            (TaskResult::Cancelled { .. }, _) => self.metrics.increment_tasks_cancelled(),
+        }
+        // A finished task moves the workflows it is part of along in the same commit
+        task.version += 1;
+        let mut transaction = Transaction::default();
+        transaction.put_task(task);
+        let workflows = match task.status.is_terminal() {
+            true => self.stage_workflow_progress(task, &mut transaction).await?,
+            false => Vec::new(),
+        };
+        self.commit(transaction).await?;
+        self.events.task_changed(task).await;
+        for (mut guard, workflow) in workflows {
+            *guard = workflow;
+            self.events.workflow_changed(&guard).await;
+        }
+
+        match retry_at {
+            Some(at) => info!("Task failed, retrying at {}: {} ({})", at, task.name, task_id),
+            None => info!("Task finished: {} ({}) - {:?}", task.name, task_id, task.status),
+        }
+        if let Some(entry) = DeadLetter::from_task(task, now) {
+            self.storage.store_dead_letter(&entry).await?;
+            warn!("Task moved to the dead-letter queue after {} attempts: {} ({})", entry.attempts, task.name, task_id);
+        }
+        Ok(())
+    }
+
+    /// Stage the workflows `task` is part of with their copy of it brought up
+    /// to date, finishing those whose tasks are now all done; they stay
+    /// locked, to be replaced by what was staged once it is committed
+    async fn stage_workflow_progress(&self, task: &Task, transaction: &mut Transaction) -> Result<Vec<(OwnedRwLockWriteGuard<Workflow>, Workflow)>> {
+        let mut staged = Vec::new();
+        for entry in self.workflow_handles().await? {
+            let guard = entry.write_owned().await;
+            if !guard.tasks.iter().any(|t| t.id == task.id) {
+                continue;
             }
-            if let Some(description) = description {
-                task.description = description;
+            let mut workflow = guard.clone();
+            for embedded in workflow.tasks.iter_mut().filter(|t| t.id == task.id) {
+                *embedded = task.clone();
             }
-            if let Some(priority) = priority {
-                task.priority = priority;
+            let open = matches!(workflow.status, WorkflowStatus::Pending | WorkflowStatus::Running);
+            if open && workflow.tasks.iter().all(|t| t.status.is_terminal()) {
+                let failed = workflow.tasks.iter().any(|t| matches!(t.status, TaskStatus::Failed | TaskStatus::Cancelled));
+                workflow.status = if failed { WorkflowStatus::Failed } else { WorkflowStatus::Completed };
             }
-            if let Some(status) = status {
-                task.set_status(status)?;
+            workflow.updated_at = std::time::SystemTime::now();
+            workflow.version += 1;
+            transaction.put_workflow(&workflow);
+            staged.push((guard, workflow));
+        }
+        Ok(staged)
+    }
+
+    /// Record running tasks whose execution stopped sending heartbeats as
+    /// failed, so they are retried or end `Failed`; returns their IDs
+    pub async fn reap_stale_tasks(&self, now: chrono::DateTime<chrono::Utc>, stale_after: Duration) -> Result<Vec<uuid::Uuid>> {
+        let mut reaped = Vec::new();
+
+        for (task_id, last) in self.storage.list_heartbeats().await? {
+            if !reaper::is_stale(last, now, stale_after) {
+                continue;
             }
-            if let Some(project_id) = project_id {
-                task.project_id = project_id;
+            let Some(entry) = self.task_entry(&task_id).await? else {
+                self.storage.delete_heartbeat(&task_id).await?;
+                continue;
+            };
+            let mut guard = entry.write().await;
+            let task = &mut *guard;
+            // Finished meanwhile, or now held by an external worker
+            if !matches!(task.status, TaskStatus::Running | TaskStatus::Paused) || Lease::of(task).is_some() {
+                self.storage.delete_heartbeat(&task_id).await?;
+                continue;
             }
-            
-            task.updated_at = std::time::SystemTime::now();
-            
-            // Update in storage
-            self.storage.store_task(task).await?;
-            
-            info!("Task updated: {} ({})", task.name, task_id);
-            Ok(task.clone())
-        } else {
-            Err(TaskQueueError::TaskNotFound { 
-                task_id: task_id.to_string() 
-            })
+
+            warn!("Task stopped reporting heartbeats since {}: {} ({})", last, task.name, task_id);
+            self.record_execution(task, failure(reaper::stale_error(last), None, Vec::new())).await?;
+            reaped.push(task_id);
         }
+
+        Ok(reaped)
     }
 
-    /// Upsert a task (create or update by name)
-    pub async fn upsert_task(
-        &self,
-        name: String,
-        command: String,
-        description: String,
-        project_id: uuid::Uuid,
-        priority: crate::core::TaskPriority,
-        technical_specs: Option<String>,
-        acceptance_criteria: Option<Vec<String>>,
-    ) -> Result<crate::core::Task> {
-        let mut tasks = self.tasks.write().await;
-        
-        // Check if task with same name exists
-        let existing_id = tasks.iter().find(|(_, task)| task.name == name).map(|(id, _)| *id);
-        
-        if let Some(existing_id) = existing_id {
-            // Update existing task
-            let task = tasks.get_mut(&existing_id).unwrap();
-            task.command = command;
-            task.description = description;
-            task.priority = priority;
-            task.project_id = Some(project_id);
-            if let Some(specs) = technical_specs {
-                task.technical_specs = Some(specs);
+    /// Permanently failed tasks, oldest first
+    pub async fn list_dead_letters(&self) -> Result<Vec<DeadLetter>> {
+        let mut entries = Vec::new();
+        for entry in self.storage.list_dead_letters().await? {
+            if tenants::current_tenant().is_none() || self.read_task(&entry.task_id).await?.is_some() {
+                entries.push(entry);
             }
-            if let Some(criteria) = acceptance_criteria {
-                task.acceptance_criteria = criteria;
+        }
+        entries.sort_by_key(|entry| (entry.dead_lettered_at, entry.task_id));
+        Ok(entries)
+    }
+
+    /// Put a dead-lettered task back in the queue with a fresh set of
+    /// retries; `None` if the task isn't in the dead-letter queue
+    pub async fn requeue_dead_letter(&self, task_id: uuid::Uuid) -> Result<Option<Task>> {
+        if self.storage.load_dead_letter(&task_id).await?.is_none() {
+            return Ok(None);
+        }
+        let Some(entry) = self.task_entry(&task_id).await? else {
+            if tenants::current_tenant().is_some() {
+                // Another tenant's task, or gone; not ours to clean up either way
+                return Ok(None);
             }
-            task.updated_at = std::time::SystemTime::now();
+            // Deleted behind our back; drop the stale entry
+            self.storage.delete_dead_letter(&task_id).await?;
+            return Err(TaskQueueError::TaskNotFound { task_id: task_id.to_string() });
+        };
+        let mut guard = entry.write().await;
+        let task = &mut *guard;
 
-            // Validate task
-            self.validate_task(task).await?;
+        task.metadata.remove(crate::retry::ATTEMPTS_KEY);
+        task.result = None;
+        task.update_status(TaskStatus::Pending);
+        self.persist_task(task).await?;
+        self.storage.delete_dead_letter(&task_id).await?;
 
-            // Update in storage
-            self.storage.store_task(task).await?;
-            
-            info!("Task upserted (updated): {} ({})", task.name, existing_id);
-            Ok(task.clone())
-        } else {
-            // Create new task
-            let new_task = crate::core::Task {
-                id: uuid::Uuid::new_v4(),
+        info!("Dead-lettered task requeued: {} ({})", task.name, task_id);
+        Ok(Some(task.clone()))
+    }
+
+    fn secret_cipher(&self) -> Result<&SecretCipher> {
+        self.secrets.as_ref().ok_or_else(secrets::unavailable)
+    }
+
+    /// Create or replace a secret of the request's tenant
+    pub async fn set_secret(&self, name: &str, value: &str) -> Result<SecretInfo> {
+        secrets::validate_name(name)?;
+        let now = chrono::Utc::now();
+        let tenant = tenants::current_tenant();
+        let mut secret = self.secret_cipher()?.seal(tenant, name, value, now)?;
+        if let Some(existing) = self.storage.load_secret(tenant, name).await? {
+            secret.created_at = existing.created_at;
+        }
+        self.storage.store_secret(&secret).await?;
+        info!("Secret stored: {}", name);
+        Ok(SecretInfo::from(&secret))
+    }
+
+    /// Names and timestamps of the secrets the request may see, never their values
+    pub async fn list_secrets(&self) -> Result<Vec<SecretInfo>> {
+        let mut secrets: Vec<SecretInfo> = self.storage.list_secrets().await?.iter()
+            .filter(|secret| tenants::visible(secret.tenant_id))
+            .map(SecretInfo::from)
+            .collect();
+        secrets.sort_by(|a, b| (a.tenant_id, &a.name).cmp(&(b.tenant_id, &b.name)));
+        Ok(secrets)
+    }
+
+    /// Delete a secret of the request's tenant
+    pub async fn delete_secret(&self, name: &str) -> Result<bool> {
+        self.storage.delete_secret(tenants::current_tenant(), name).await
+    }
+
+    /// The task with the `{{secret:NAME}}` references in its environment
+    /// replaced by the secrets' values, and the redactor for its output.
+    /// Only the secrets of the task's own tenant are resolved, and HTTP
+    /// request headers only get them for hosts in `execution.secret_hosts`.
+    pub async fn resolve_secrets(&self, task: &Task) -> Result<(Task, Redactor)> {
+        if !tenants::visible(task.tenant_id) {
+            return Err(TaskQueueError::TaskNotFound { task_id: task.id.to_string() });
+        }
+        let names: Vec<&str> = secrets::placeholders(task).flat_map(secrets::references).collect();
+        if names.is_empty() {
+            return Ok((task.clone(), Redactor::default()));
+        }
+        if !secrets::may_send_to(task, &self.secret_hosts) {
+            return Err(TaskQueueError::ValidationError {
+                reason: "Secrets are only sent to the hosts in execution.secret_hosts".to_string(),
+            });
+        }
+        let values = self.secret_values(task.tenant_id, names).await?;
+        Ok(secrets::resolve_task(task, &values))
+    }
+
+    /// Values of the named secrets of `tenant`, by name
+    async fn secret_values(&self, tenant: Option<uuid::Uuid>, mut names: Vec<&str>) -> Result<HashMap<String, String>> {
+        let mut values = HashMap::new();
+        if names.is_empty() {
+            return Ok(values);
+        }
+        names.sort_unstable();
+        names.dedup();
+
+        let cipher = self.secret_cipher()?;
+        for name in names {
+            let secret = self.storage.load_secret(tenant, name).await?.ok_or_else(|| TaskQueueError::ValidationError {
+                reason: format!("Unknown secret '{}'", name),
+            })?;
+            values.insert(name.to_string(), cipher.open(&secret)?);
+        }
+        Ok(values)
+    }
+
+    /// Run a claimed task with the executor it selects: its secret references
+    /// are resolved, its output is captured into the task's log as it is
+    /// produced, and secret values are redacted from both the log and the
+    /// result. Heartbeats are recorded throughout, for the stale task reaper.
+    /// Its whole log is then kept as a log file, and on success its
+    /// artifacts are collected into the blob store.
+    pub async fn execute_task(&self, executors: &ExecutorRegistry, task: &Task) -> TaskResult {
+        let (mut resolved, redactor) = match self.resolve_secrets(task).await {
+            Ok(resolved) => resolved,
+            Err(e) => return failure(format!("Failed to resolve secrets: {}", e), None, Vec::new()),
+        };
+        // Tasks that don't pick a directory get one of their own for the run
+        let workspace = match self.workspace_for(&resolved) {
+            Ok(workspace) => workspace,
+            Err(e) => return failure(format!("Failed to create workspace: {}", e), None, Vec::new()),
+        };
+        if let Some(workspace) = &workspace {
+            resolved.working_directory = Some(workspace.path().to_string_lossy().to_string());
+        }
+        let working_directory = resolved.working_directory.clone();
+
+        let (logs, rx) = LogSink::channel();
+        let writer = task_logs::write_logs(self.storage.as_ref(), task.id, rx, redactor.clone());
+        let execution = async move {
+            let result = executors.execute_logged(&resolved, &logs).await;
+            // Closing the sink lets the writer finish
+            drop(logs);
+            result
+        };
+        let heartbeat = async {
+            let mut ticker = tokio::time::interval(reaper::HEARTBEAT_INTERVAL);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.storage.store_heartbeat(&task.id, chrono::Utc::now()).await {
+                    warn!("Failed to record heartbeat of task {}: {}", task.id, e);
+                }
+            }
+        };
+        let (result, written) = tokio::select! {
+            done = async { tokio::join!(execution, writer) } => done,
+            _ = heartbeat => unreachable!("heartbeats go on until the execution ends"),
+        };
+        match written {
+            Ok(()) => self.store_log_file(task.id).await,
+            Err(e) => warn!("Failed to store output of task {}: {}", task.id, e),
+        }
+        let mut result = redactor.redact_result(result);
+        if let TaskResult::Success { artifacts, .. } = &mut result {
+            artifacts.extend(self.collect_artifacts(task, working_directory).await.iter().map(Artifact::download_url));
+        }
+        result
+    }
+
+    /// A fresh workspace for a run of the task, unless it has a working
+    /// directory or needs none
+    fn workspace_for(&self, task: &Task) -> std::io::Result<Option<Workspace>> {
+        if !self.workspaces.enabled || task.working_directory.is_some() || matches!(task.task_type, TaskType::Http) {
+            return Ok(None);
+        }
+        Workspace::create(&self.workspaces, task).map(Some)
+    }
+
+    /// Keep the task's log, every run so far, as its log file artifact
+    async fn store_log_file(&self, task_id: uuid::Uuid) {
+        let chunks = match self.storage.list_log_chunks(&task_id, None).await {
+            Ok(chunks) => chunks,
+            Err(e) => {
+                warn!("Failed to read log of task {}: {}", task_id, e);
+                return;
+            }
+        };
+        if chunks.is_empty() {
+            return;
+        }
+        let text: String = chunks.iter().map(|chunk| chunk.data.as_str()).collect();
+        if let Err(e) = self.store_artifact(Artifact::log(task_id, text.as_bytes()), text.as_bytes()).await {
+            warn!("Failed to store log file of task {}: {}", task_id, e);
+        }
+    }
+
+    /// Contents first, so a listed artifact can always be downloaded
+    async fn store_artifact(&self, artifact: Artifact, data: &[u8]) -> Result<Artifact> {
+        self.blobs.put(&artifact.object_key(), data).await?;
+        self.storage.store_artifact(&artifact).await?;
+        Ok(artifact)
+    }
+
+    /// Copy the files matching the task's artifact patterns out of the
+    /// directory it ran in into the blob store
+    async fn collect_artifacts(&self, task: &Task, working_directory: Option<String>) -> Vec<Artifact> {
+        let patterns = self.artifacts.patterns_for(task);
+        let Some(dir) = working_directory.filter(|_| !patterns.is_empty()) else {
+            return Vec::new();
+        };
+        let max_file_bytes = self.artifacts.max_file_bytes;
+        let files = tokio::task::spawn_blocking(move || artifacts::collect(dir.as_ref(), &patterns, max_file_bytes))
+            .await
+            .unwrap_or_default();
+
+        let mut stored = Vec::new();
+        for (name, data) in files {
+            match self.store_artifact(Artifact::new(task.id, name.clone(), &data), &data).await {
+                Ok(artifact) => stored.push(artifact),
+                Err(e) => warn!("Failed to store artifact {} of task {}: {}", name, task.id, e),
+            }
+        }
+        stored
+    }
+
+    /// A task's stored artifacts
+    pub async fn list_artifacts(&self, task_id: uuid::Uuid) -> Result<Vec<Artifact>> {
+        if self.task_entry(&task_id).await?.is_none() {
+            return Err(TaskQueueError::TaskNotFound { task_id: task_id.to_string() });
+        }
+        self.storage.list_artifacts(&task_id).await
+    }
+
+    /// A task's artifacts with URLs to download them from until they expire
+    pub async fn artifact_links(&self, task_id: uuid::Uuid) -> Result<Vec<ArtifactLink>> {
+        let ttl = self.artifacts.url_ttl();
+        let expires_at = chrono::Utc::now() + chrono::Duration::from_std(ttl).unwrap_or(chrono::Duration::hours(1));
+        let mut links = Vec::new();
+        for artifact in self.list_artifacts(task_id).await? {
+            let key = artifact.object_key();
+            let url = match self.blobs.presigned_url(&key, ttl).await? {
+                Some(url) => url,
+                None => self.url_signer.url(&key, expires_at),
+            };
+            links.push(ArtifactLink { artifact, url, expires_at });
+        }
+        Ok(links)
+    }
+
+    /// Contents of one of a task's artifacts
+    pub async fn get_artifact(&self, task_id: uuid::Uuid, name: &str) -> Result<Option<Vec<u8>>> {
+        if tenants::current_tenant().is_some() && self.read_task(&task_id).await?.is_none() {
+            return Ok(None);
+        }
+        self.blobs.get(&artifacts::object_key(task_id, ArtifactKind::File, name)).await
+    }
+
+    /// Contents of a blob a signed URL points at, if the signature holds
+    pub async fn get_signed_blob(&self, key: &str, expires: i64, signature: &str) -> Result<Option<Vec<u8>>> {
+        if !self.url_signer.verify(key, expires, signature, chrono::Utc::now()) {
+            return Err(TaskQueueError::PermissionDenied { operation: format!("download {}", key) });
+        }
+        self.blobs.get(key).await
+    }
+
+    /// Delete a task's artifacts and their contents
+    async fn delete_artifacts(&self, task_id: &uuid::Uuid) -> Result<()> {
+        for artifact in self.storage.list_artifacts(task_id).await? {
+            self.blobs.delete(&artifact.object_key()).await?;
+        }
+        self.storage.delete_task_artifacts(task_id).await
+    }
+
+    /// A task's captured output, only the chunks after `after` if given
+    pub async fn get_task_logs(&self, task_id: uuid::Uuid, after: Option<u64>) -> Result<Vec<LogChunk>> {
+        if self.task_entry(&task_id).await?.is_none() {
+            return Err(TaskQueueError::TaskNotFound { task_id: task_id.to_string() });
+        }
+        self.storage.list_log_chunks(&task_id, after).await
+    }
+
+    /// Get task correlations
+    pub async fn get_task_correlations(&self, task_id: uuid::Uuid) -> Result<Vec<String>> {
+        if let Some(entry) = self.task_entry(&task_id).await? {
+            let task = entry.read().await;
+            let correlations: Vec<String> = task.dependencies.iter()
+                .filter_map(|dep| dep.correlation_id.clone())
+                .collect();
+            Ok(correlations)
+        } else {
+            Err(TaskQueueError::TaskNotFound { 
+                task_id: task_id.to_string() 
+            })
+        }
+    }
+
+    /// Cancel a task
+    pub async fn cancel_task(&self, task_id: uuid::Uuid, reason: String) -> Result<()> {
+        if let Some(entry) = self.task_entry(&task_id).await? {
+            let mut guard = entry.write().await;
+            let task = &mut *guard;
+            if task.status == TaskStatus::Paused {
+                // Don't leave the command stopped for good
+                if let Err(e) = crate::executor::resume_execution(task_id).await {
+                    warn!("Failed to resume paused task {} before cancelling it: {}", task_id, e);
+                }
+            }
+            task.status = crate::core::TaskStatus::Cancelled;
+            task.result = Some(crate::core::TaskResult::Cancelled { reason: reason.clone() });
+            task.updated_at = std::time::SystemTime::now();
+            
+            // Update in storage
+            self.persist_task(task).await?;
+            
+            // Update metrics
+            self.metrics.increment_tasks_cancelled();
+            
+            info!("Task cancelled: {} ({})", task.name, task_id);
+            Ok(())
+        } else {
+            Err(TaskQueueError::TaskNotFound { 
+                task_id: task_id.to_string() 
+            })
+        }
+    }
+
+    /// Stop a running task's command where it is, until it is resumed
+    ///
+    /// Only tasks executing in this process can be paused, not those leased
+    /// to external workers.
+    pub async fn pause_task(&self, task_id: uuid::Uuid) -> Result<Task> {
+        self.suspend_task(task_id, TaskStatus::Paused).await
+    }
+
+    /// Continue a task stopped with [`Self::pause_task`]
+    pub async fn resume_task(&self, task_id: uuid::Uuid) -> Result<Task> {
+        self.suspend_task(task_id, TaskStatus::Running).await
+    }
+
+    async fn suspend_task(&self, task_id: uuid::Uuid, status: TaskStatus) -> Result<Task> {
+        let entry = self.task_entry(&task_id).await?.ok_or_else(|| TaskQueueError::TaskNotFound {
+            task_id: task_id.to_string(),
+        })?;
+        let mut guard = entry.write().await;
+        let task = &mut *guard;
+        if !task.can_transition_to(&status) {
+            return Err(TaskQueueError::InvalidStatusTransition(format!(
+                "Cannot change task from {:?} to {:?}", task.status, status
+            )));
+        }
+
+        let signalled = match status {
+            TaskStatus::Paused => crate::executor::pause_execution(task_id).await?,
+            _ => crate::executor::resume_execution(task_id).await?,
+        };
+        if !signalled {
+            return Err(TaskQueueError::InvalidStatusTransition(format!(
+                "Task {} is not executing on this server", task_id
+            )));
+        }
+
+        task.update_status(status);
+        self.persist_task(task).await?;
+        info!("Task {:?}: {} ({})", task.status, task.name, task_id);
+        Ok(task.clone())
+    }
+
+    /// Apply one operation to every task the request picks, reporting the
+    /// outcome of each
+    pub async fn bulk_update(&self, request: BulkRequest) -> Result<BulkReport> {
+        request.validate()?;
+        let task_ids = match &request.filter {
+            Some(filter) => self.visit_tasks(filter, |task, _| task.id).await?,
+            None => request.task_ids.clone(),
+        };
+        if task_ids.len() > crate::bulk::MAX_BULK_TASKS {
+            return Err(TaskQueueError::ValidationError {
+                reason: format!("The filter matches {} tasks, more than {}", task_ids.len(), crate::bulk::MAX_BULK_TASKS),
+            });
+        }
+
+        let mut report = BulkReport { matched: task_ids.len(), ..Default::default() };
+        for task_id in task_ids {
+            let outcome = match &request.operation {
+                BulkOperation::Cancel { reason } => {
+                    let reason = reason.clone().unwrap_or_else(|| "Cancelled in bulk".to_string());
+                    self.cancel_task(task_id, reason).await
+                }
+                BulkOperation::Delete => self.delete_task(task_id).await,
+                BulkOperation::SetPriority { priority } => self.update_task_priority(task_id, priority.clone()).await,
+                BulkOperation::SetStatus { status } => self.set_task_status(task_id, status.clone()).await,
+            };
+            report.record(task_id, outcome);
+        }
+        info!("Bulk {:?}: {} of {} tasks changed", request.operation, report.succeeded, report.matched);
+        Ok(report)
+    }
+
+    /// Delete a task
+    pub async fn delete_task(&self, task_id: uuid::Uuid) -> Result<()> {
+        if self.read_task(&task_id).await?.is_none() {
+            return Err(TaskQueueError::TaskNotFound { task_id: task_id.to_string() });
+        }
+        if let Some(task) = self.remove_task(&task_id).await? {
+            self.storage.delete_dead_letter(&task_id).await?;
+            self.storage.delete_task_logs(&task_id).await?;
+            self.storage.delete_heartbeat(&task_id).await?;
+            self.delete_artifacts(&task_id).await?;
+            info!("Task deleted: {} ({})", task.name, task_id);
+            Ok(())
+        } else {
+            Err(TaskQueueError::TaskNotFound { 
+                task_id: task_id.to_string() 
+            })
+        }
+    }
+
+    /// Archive or delete the finished tasks past their retention at `now`
+    pub async fn apply_retention(&self, now: std::time::SystemTime, dry_run: bool) -> Result<RetentionReport> {
+        let tasks = self.task_snapshot().await?;
+        let expired = retention::expired(&tasks, &self.project_snapshot().await?, self.retention, now);
+        let mut tasks: HashMap<uuid::Uuid, Task> = tasks.into_iter().map(|task| (task.id, task)).collect();
+        let mut report = RetentionReport { dry_run, ..Default::default() };
+
+        for expired_task in expired {
+            let task_id = expired_task.task_id;
+            if !dry_run {
+                if let (RetentionAction::Archive, Some(task)) = (expired_task.action, tasks.remove(&task_id)) {
+                    self.storage.archive_task(&task).await?;
+                }
+                match self.delete_task(task_id).await {
+                    // Deleted by someone else in the meantime
+                    Err(TaskQueueError::TaskNotFound { .. }) => continue,
+                    result => result?,
+                }
+                self.storage.delete_task_revisions(&task_id).await?;
+            }
+            match expired_task.action {
+                RetentionAction::Archive => report.archived += 1,
+                _ => report.deleted += 1,
+            }
+            report.tasks.push(expired_task);
+        }
+        Ok(report)
+    }
+
+    /// Move the completed tasks past the cold storage threshold at `now` out
+    /// of the queue into compressed storage
+    pub async fn move_to_cold_storage(&self, now: std::time::SystemTime, dry_run: bool) -> Result<ColdStorageReport> {
+        let candidates = cold_storage::candidates(&self.task_snapshot().await?, self.cold_storage.threshold(), now);
+        let mut report = ColdStorageReport { dry_run, ..Default::default() };
+
+        for candidate in candidates {
+            let task_id = candidate.task_id;
+            if !dry_run {
+                match &self.shared {
+                    None => {
+                        let Some(entry) = map_entry(&self.tasks, &task_id) else { continue };
+                        // Held until the task left the map, so no change slips in between
+                        let task = entry.write().await;
+                        if task.status != TaskStatus::Completed || !self.storage.freeze_task(&task_id).await? {
+                            continue;
+                        }
+                        self.tasks.remove(&task_id);
+                        self.statuses.remove(&task_id);
+                    }
+                    Some(shared) => {
+                        if !self.storage.freeze_task(&task_id).await? {
+                            continue;
+                        }
+                        shared.tasks.remove(&task_id).await;
+                    }
+                }
+            }
+            report.tasks.push(candidate);
+        }
+        Ok(report)
+    }
+
+    /// Bring the storage metrics up to date
+    pub async fn refresh_storage_metrics(&self) -> Result<StorageStats> {
+        let stats = self.storage.get_stats().await?;
+        self.metrics.update_storage(&stats);
+        Ok(stats)
+    }
+
+    /// Give the space of removed records back to the file system
+    pub async fn compact_storage(&self) -> Result<StorageStats> {
+        self.storage.compact().await?;
+        self.refresh_storage_metrics().await
+    }
+
+    /// Remove what deleted tasks left behind in storage and the blob store,
+    /// then compact storage if anything went
+    pub async fn vacuum_storage(&self, now: chrono::DateTime<chrono::Utc>, dry_run: bool) -> Result<VacuumReport> {
+        let (report, artifacts) = self.storage.vacuum(self.vacuum.tombstones_before(now), dry_run).await?;
+        if dry_run {
+            return Ok(report);
+        }
+        for artifact in &artifacts {
+            self.blobs.delete(&artifact.object_key()).await?;
+        }
+        if report.removed() > 0 {
+            self.compact_storage().await?;
+        }
+        Ok(report)
+    }
+
+    /// Recorded mutations of tasks, projects and workflows
+    pub async fn list_audit(&self, query: &AuditQuery) -> Result<Vec<AuditEntry>> {
+        self.storage.list_audit(query).await
+    }
+
+    /// A task retention moved to the archive
+    pub async fn get_archived_task(&self, task_id: uuid::Uuid) -> Result<Task> {
+        self.storage.load_archived_task(&task_id).await?
+            .filter(|task| tenants::visible(task.tenant_id))
+            .ok_or_else(|| TaskQueueError::TaskNotFound { task_id: task_id.to_string() })
+    }
+
+    /// Check stored records for broken references, repairing or
+    /// quarantining them as `action` says
+    pub async fn check_storage(&self, action: FsckAction) -> Result<FsckReport> {
+        let report = fsck::run(self.storage.as_ref(), action).await?;
+
+        // Reload what the check rewrote or moved out
+        for (collection, key) in report.changed_records() {
+            let Ok(id) = uuid::Uuid::parse_str(&key) else { continue };
+            match (collection, &self.shared) {
+                (Collection::Tasks, None) => match self.storage.load_task(&id).await? {
+                    Some(task) => {
+                        self.statuses.update(&task);
+                        map_insert(&self.tasks, id, task);
+                    }
+                    None => {
+                        self.tasks.remove(&id);
+                        self.statuses.remove(&id);
+                    }
+                },
+                (Collection::Projects, None) => match self.storage.load_project(&id).await? {
+                    Some(project) => map_insert(&self.projects, id, project),
+                    None => {
+                        self.projects.remove(&id);
+                    }
+                },
+                (Collection::Workflows, None) => match self.storage.load_workflow(&id).await? {
+                    Some(workflow) => map_insert(&self.workflows, id, workflow),
+                    None => {
+                        self.workflows.remove(&id);
+                    }
+                },
+                (Collection::Tasks, Some(shared)) => {
+                    shared.tasks.remove(&id).await;
+                }
+                (Collection::Projects, Some(shared)) => {
+                    shared.projects.remove(&id).await;
+                }
+                (Collection::Workflows, Some(shared)) => {
+                    shared.workflows.remove(&id).await;
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Restore tasks, projects and workflows from a backup archive
+    pub async fn restore_backup(&self, archive: BackupArchive, mode: RestoreMode) -> Result<RestoreReport> {
+        let mut report = RestoreReport { mode, ..Default::default() };
+        let projects = backup::plan(PROJECTS_SECTION, &self.project_snapshot().await?, archive.projects, |p| (p.id, p.name.as_str()), &mut report)?;
+        let workflows = backup::plan(WORKFLOWS_SECTION, &self.workflow_snapshot().await?, archive.workflows, |w| (w.id, w.name.as_str()), &mut report)?;
+        let tasks = backup::plan(TASKS_SECTION, &self.task_snapshot().await?, archive.tasks, |t| (t.id, t.name.as_str()), &mut report)?;
+
+        for task_id in tasks.remove {
+            self.delete_task(task_id).await?;
+        }
+        for workflow_id in &workflows.remove {
+            self.remove_workflow(workflow_id).await?;
+        }
+        for project_id in &projects.remove {
+            self.remove_project(project_id).await?;
+        }
+
+        // Projects first, so restored tasks never point at a missing one
+        // Versions only go up, so a restored record never reuses an ETag
+        for (mut project, _) in projects.write {
+            let live = self.read_project(&project.id).await?.map_or(0, |p| p.version);
+            project.version = project.version.max(live);
+            self.insert_project(&project).await?;
+        }
+        for (mut workflow, existed) in workflows.write {
+            if !existed {
+                self.insert_workflow(&workflow).await?;
+                continue;
+            }
+            let live = self.read_workflow(&workflow.id).await?.map_or(0, |w| w.version);
+            workflow.version = workflow.version.max(live) + 1;
+            if self.shared.is_none() {
+                map_insert(&self.workflows, workflow.id, workflow.clone());
+            }
+            self.write_workflow(&workflow).await?;
+            self.events.workflow_changed(&workflow).await;
+        }
+        for (mut task, existed) in tasks.write {
+            if !existed {
+                self.insert_task(&task).await?;
+                continue;
+            }
+            let live = self.read_task(&task.id).await?.map_or(0, |t| t.version);
+            task.version = task.version.max(live) + 1;
+            if self.shared.is_none() {
+                map_insert(&self.tasks, task.id, task.clone());
+            }
+            self.write_task(&task).await?;
+            self.events.task_changed(&task).await;
+        }
+
+        info!(
+            "Restored backup ({:?}): {} written, {} unchanged, {} removed, {} conflicts",
+            mode, report.restored, report.unchanged, report.removed, report.conflicts.len()
+        );
+        Ok(report)
+    }
+
+    /// Update a task
+    #[allow(clippy::too_many_arguments)]
+    pub async fn update_task(
+        &self,
+        task_id: uuid::Uuid,
+        name: Option<String>,
+        command: Option<String>,
+        description: Option<String>,
+        priority: Option<crate::core::TaskPriority>,
+        status: Option<crate::core::TaskStatus>,
+        project_id: Option<Option<uuid::Uuid>>,
+    ) -> Result<crate::core::Task> {
+        let request = crate::core::UpdateTaskRequest {
+            name,
+            command,
+            description,
+            priority,
+            status,
+            project_id,
+            ..Default::default()
+        };
+        self.patch_task(task_id, request, &Precondition::Any).await
+    }
+
+    /// Change the fields of a task given in the request, all at once or none
+    pub async fn patch_task(
+        &self,
+        task_id: uuid::Uuid,
+        request: crate::core::UpdateTaskRequest,
+        precondition: &Precondition,
+    ) -> Result<Task> {
+        let mut errors = request.validate();
+        if let Some(Some(project_id)) = request.project_id
+            && self.get_project(&project_id).await?.is_none()
+        {
+            errors.push(crate::core::FieldError::new("project_id", format!("project {} does not exist", project_id)));
+        }
+        if !errors.is_empty() {
+            return Err(TaskQueueError::InvalidFields { errors });
+        }
+
+        let entry = self.task_entry(&task_id).await?.ok_or_else(|| TaskQueueError::TaskNotFound {
+            task_id: task_id.to_string(),
+        })?;
+        let mut guard = entry.write().await;
+        precondition.check(guard.version)?;
+        let mut task = guard.clone();
+        request.apply(&mut task).map_err(|e| TaskQueueError::InvalidFields { errors: vec![e] })?;
+        task.updated_at = std::time::SystemTime::now();
+        self.persist_task(&mut task).await?;
+        *guard = task.clone();
+
+        info!("Task updated: {} ({})", task.name, task_id);
+        Ok(task)
+    }
+
+    /// Upsert a task (create or update by name)
+    #[allow(clippy::too_many_arguments)]
+    pub async fn upsert_task(
+        &self,
+        name: String,
+        command: String,
+        description: String,
+        project_id: uuid::Uuid,
+        priority: crate::core::TaskPriority,
+        technical_specs: Option<String>,
+        acceptance_criteria: Option<Vec<String>>,
+    ) -> Result<crate::core::Task> {
+        let _upsert = self.upsert_lock.lock().await;
+        
+        // Check if task with same name exists
+        let mut existing = None;
+        let entries = self.task_handles().await?;
+        for entry in entries {
+            if entry.read().await.name == name {
+                existing = Some(entry);
+                break;
+            }
+        }
+        
+        if let Some(entry) = existing {
+            // Update existing task
+            let mut guard = entry.write().await;
+            let task = &mut *guard;
+            let existing_id = task.id;
+            task.command = command;
+            task.description = description;
+            task.priority = priority;
+            task.project_id = Some(project_id);
+            if let Some(specs) = technical_specs {
+                task.technical_specs = Some(specs);
+            }
+            if let Some(criteria) = acceptance_criteria {
+                task.acceptance_criteria = criteria;
+            }
+            task.updated_at = std::time::SystemTime::now();
+
+            // Validate task
+            self.validate_task(task).await?;
+
+            // Update in storage
+            self.persist_task(task).await?;
+            
+            info!("Task upserted (updated): {} ({})", task.name, existing_id);
+            Ok(task.clone())
+        } else {
+            // Create new task
+            let new_task = crate::core::Task {
+                id: uuid::Uuid::new_v4(),
+                version: 0,
+                tenant_id: None,
                 name: name.clone(),
                 command,
                 description,
@@ -903,10 +3475,7 @@ impl TaskQueueServer {
             self.validate_task(&new_task).await?;
 
             let task_id = new_task.id;
-            tasks.insert(task_id, new_task.clone());
-
-            // Store in storage
-            self.storage.store_task(&new_task).await?;
+            self.insert_task(&new_task).await?;
             
             // Update metrics
             self.metrics.increment_tasks_submitted();
@@ -918,17 +3487,19 @@ impl TaskQueueServer {
 
     /// Retry a task
     pub async fn retry_task(&self, task_id: uuid::Uuid, reset_retry_count: bool) -> Result<()> {
-        let mut tasks = self.tasks.write().await;
-        if let Some(task) = tasks.get_mut(&task_id) {
+        if let Some(entry) = self.task_entry(&task_id).await? {
+            let mut guard = entry.write().await;
+            let task = &mut *guard;
             if reset_retry_count {
-                task.retry_attempts = 0;
+                task.metadata.remove(crate::retry::ATTEMPTS_KEY);
             }
             task.status = crate::core::TaskStatus::Pending;
             task.result = None;
             task.updated_at = std::time::SystemTime::now();
             
             // Update in storage
-            self.storage.store_task(task).await?;
+            self.persist_task(task).await?;
+            self.storage.delete_dead_letter(&task_id).await?;
             
             info!("Task retry initiated: {} ({})", task.name, task_id);
             Ok(())
@@ -941,13 +3512,14 @@ impl TaskQueueServer {
 
     /// Update task priority
     pub async fn update_task_priority(&self, task_id: uuid::Uuid, priority: crate::core::TaskPriority) -> Result<()> {
-        let mut tasks = self.tasks.write().await;
-        if let Some(task) = tasks.get_mut(&task_id) {
+        if let Some(entry) = self.task_entry(&task_id).await? {
+            let mut guard = entry.write().await;
+            let task = &mut *guard;
             task.priority = priority;
             task.updated_at = std::time::SystemTime::now();
             
             // Update in storage
-            self.storage.store_task(task).await?;
+            self.persist_task(task).await?;
             
             info!("Task priority updated: {} ({})", task.name, task_id);
             Ok(())
@@ -960,13 +3532,16 @@ impl TaskQueueServer {
 
     /// Cancel workflow
     pub async fn cancel_workflow(&self, workflow_id: uuid::Uuid, _reason: String) -> Result<()> {
-        let mut workflows = self.workflows.write().await;
-        if let Some(workflow) = workflows.get_mut(&workflow_id) {
+        if let Some(entry) = self.workflow_entry(&workflow_id).await? {
+            let mut guard = entry.write().await;
+            let mut updated = guard.clone();
+            let workflow = &mut updated;
             workflow.status = crate::core::WorkflowStatus::Cancelled;
             workflow.updated_at = std::time::SystemTime::now();
             
             // Update in storage
-            self.storage.store_workflow(workflow).await?;
+            self.persist_workflow(workflow).await?;
+            *guard = workflow.clone();
             
             info!("Workflow cancelled: {} ({})", workflow.name, workflow_id);
             Ok(())
@@ -979,13 +3554,16 @@ impl TaskQueueServer {
 
     /// Approve workflow
     pub async fn approve_workflow(&self, workflow_id: uuid::Uuid, _message: String) -> Result<()> {
-        let mut workflows = self.workflows.write().await;
-        if let Some(workflow) = workflows.get_mut(&workflow_id) {
+        if let Some(entry) = self.workflow_entry(&workflow_id).await? {
+            let mut guard = entry.write().await;
+            let mut updated = guard.clone();
+            let workflow = &mut updated;
             workflow.status = crate::core::WorkflowStatus::Running;
             workflow.updated_at = std::time::SystemTime::now();
 
             // Update in storage
-            self.storage.store_workflow(workflow).await?;
+            self.persist_workflow(workflow).await?;
+            *guard = workflow.clone();
 
             info!("Workflow approved: {} ({})", workflow.name, workflow_id);
             Ok(())
@@ -1000,8 +3578,10 @@ impl TaskQueueServer {
 
     /// Advance development workflow to next phase
     pub async fn advance_development_workflow(&self, task_id: uuid::Uuid) -> Result<crate::core::DevelopmentWorkflowStatus> {
-        let mut tasks = self.tasks.write().await;
-        if let Some(task) = tasks.get_mut(&task_id) {
+        if let Some(entry) = self.task_entry(&task_id).await? {
+            let mut guard = entry.write().await;
+            let mut updated = guard.clone();
+            let task = &mut updated;
             if let Some(ref mut workflow) = task.development_workflow {
                 let next_status = match &workflow.workflow_status {
                     crate::core::DevelopmentWorkflowStatus::NotStarted => {
@@ -1031,7 +3611,8 @@ impl TaskQueueServer {
                 task.updated_at = std::time::SystemTime::now();
 
                 // Update in storage
-                self.storage.store_task(task).await?;
+                self.persist_task(task).await?;
+                *guard = task.clone();
 
                 info!("Task {} advanced to workflow status: {:?}", task.name, next_status);
                 Ok(next_status)
@@ -1046,7 +3627,8 @@ impl TaskQueueServer {
                     completed_at: None,
                 });
                 task.updated_at = std::time::SystemTime::now();
-                self.storage.store_task(task).await?;
+                self.persist_task(task).await?;
+                *guard = task.clone();
                 Ok(crate::core::DevelopmentWorkflowStatus::Planning)
             }
         } else {
@@ -1058,12 +3640,13 @@ impl TaskQueueServer {
 
     /// Set technical documentation path for planning phase
     pub async fn set_technical_documentation(&self, task_id: uuid::Uuid, doc_path: String) -> Result<()> {
-        let mut tasks = self.tasks.write().await;
-        if let Some(task) = tasks.get_mut(&task_id) {
+        if let Some(entry) = self.task_entry(&task_id).await? {
+            let mut guard = entry.write().await;
+            let task = &mut *guard;
             if let Some(ref mut workflow) = task.development_workflow {
                 workflow.technical_documentation_path = Some(doc_path.clone());
                 task.updated_at = std::time::SystemTime::now();
-                self.storage.store_task(task).await?;
+                self.persist_task(task).await?;
                 info!("Technical documentation set for task {}: {}", task.name, doc_path);
                 Ok(())
             } else {
@@ -1080,12 +3663,13 @@ impl TaskQueueServer {
 
     /// Set test coverage percentage
     pub async fn set_test_coverage(&self, task_id: uuid::Uuid, coverage: f64) -> Result<()> {
-        let mut tasks = self.tasks.write().await;
-        if let Some(task) = tasks.get_mut(&task_id) {
+        if let Some(entry) = self.task_entry(&task_id).await? {
+            let mut guard = entry.write().await;
+            let task = &mut *guard;
             if let Some(ref mut workflow) = task.development_workflow {
                 workflow.test_coverage_percentage = Some(coverage);
                 task.updated_at = std::time::SystemTime::now();
-                self.storage.store_task(task).await?;
+                self.persist_task(task).await?;
                 info!("Test coverage set for task {}: {}%", task.name, coverage);
                 Ok(())
             } else {
@@ -1102,13 +3686,14 @@ impl TaskQueueServer {
 
     /// Add AI review report
     pub async fn add_ai_review_report(&self, task_id: uuid::Uuid, review: crate::core::AIDevelopmentReview) -> Result<()> {
-        let mut tasks = self.tasks.write().await;
-        if let Some(task) = tasks.get_mut(&task_id) {
+        if let Some(entry) = self.task_entry(&task_id).await? {
+            let mut guard = entry.write().await;
+            let task = &mut *guard;
             if let Some(ref mut workflow) = task.development_workflow {
                 workflow.ai_review_reports.push(review);
                 task.ai_reviews_completed = workflow.ai_review_reports.len() as u32;
                 task.updated_at = std::time::SystemTime::now();
-                self.storage.store_task(task).await?;
+                self.persist_task(task).await?;
                 info!("AI review report added for task {}", task.name);
                 Ok(())
             } else {
@@ -1123,15 +3708,62 @@ impl TaskQueueServer {
         }
     }
 
+    /// Record a file path or URL produced during the task's current phase,
+    /// attributed to the calling agent, if any
+    pub async fn add_phase_artifact(&self, task_id: uuid::Uuid, location: &str, description: Option<String>) -> Result<Task> {
+        if location.trim().is_empty() {
+            return Err(TaskQueueError::ValidationError {
+                reason: "Artifact location can't be empty".to_string(),
+            });
+        }
+        let mut artifact = crate::core::PhaseArtifact::new(location.trim(), description);
+        artifact.agent = crate::agents::current_agent();
+        self.modify_task(task_id, |task| task.add_phase_artifact(artifact)).await
+    }
+
+    /// Keep the latest progress reported on a task and broadcast it on the
+    /// event stream
+    ///
+    /// Agents report often, so the task is written behind like a lease
+    /// renewal; the next write of the task, such as it finishing, takes the
+    /// latest report along.
+    pub async fn report_progress(&self, task_id: uuid::Uuid, percent: f64, note: Option<String>) -> Result<Task> {
+        let mut progress = crate::progress::TaskProgress::new(percent, note)
+            .map_err(|reason| TaskQueueError::ValidationError { reason })?;
+        progress.agent = crate::agents::current_agent();
+        let entry = self.task_entry(&task_id).await?.ok_or_else(|| TaskQueueError::TaskNotFound {
+            task_id: task_id.to_string(),
+        })?;
+        let mut guard = entry.write().await;
+        let mut task = guard.clone();
+        progress.apply(&mut task);
+        task.updated_at = std::time::SystemTime::now();
+        self.defer_task(&mut task).await?;
+        *guard = task.clone();
+        drop(guard);
+        self.events.task_progress(&task, &progress).await;
+        Ok(task)
+    }
+
     /// Update workflow status
-    pub async fn update_workflow_status(&self, workflow_id: uuid::Uuid, status: crate::core::WorkflowStatus, _message: String) -> Result<()> {
-        let mut workflows = self.workflows.write().await;
-        if let Some(workflow) = workflows.get_mut(&workflow_id) {
+    pub async fn update_workflow_status(
+        &self,
+        workflow_id: uuid::Uuid,
+        status: crate::core::WorkflowStatus,
+        _message: String,
+        precondition: &Precondition,
+    ) -> Result<()> {
+        if let Some(entry) = self.workflow_entry(&workflow_id).await? {
+            let mut guard = entry.write().await;
+            precondition.check(guard.version)?;
+            let mut updated = guard.clone();
+            let workflow = &mut updated;
             workflow.status = status;
             workflow.updated_at = std::time::SystemTime::now();
             
             // Update in storage
-            self.storage.store_workflow(workflow).await?;
+            self.persist_workflow(workflow).await?;
+            *guard = workflow.clone();
             
             info!("Workflow status updated: {} ({})", workflow.name, workflow_id);
             Ok(())
@@ -1142,9 +3774,113 @@ impl TaskQueueServer {
         }
     }
 
+    /// Create a tenant for a team to work in
+    pub async fn create_tenant(&self, name: String, description: Option<String>) -> Result<Tenant> {
+        let tenant = Tenant {
+            id: uuid::Uuid::new_v4(),
+            name,
+            description,
+            created_at: chrono::Utc::now(),
+        };
+        self.storage.store_tenant(&tenant).await?;
+        info!("Created tenant {} ({})", tenant.name, tenant.id);
+        Ok(tenant)
+    }
+
+    pub async fn list_tenants(&self) -> Result<Vec<Tenant>> {
+        self.storage.list_tenants().await
+    }
+
+    pub async fn get_tenant(&self, tenant_id: &uuid::Uuid) -> Result<Tenant> {
+        self.storage.load_tenant(tenant_id).await?
+            .ok_or_else(|| TaskQueueError::TenantNotFound { tenant_id: tenant_id.to_string() })
+    }
+
+    /// Issue an API key scoped to a tenant with the given scopes, or all of
+    /// them; the key is only returned here
+    pub async fn issue_tenant_key(&self, tenant_id: &uuid::Uuid, name: String, scopes: Option<Vec<KeyScope>>) -> Result<IssuedKey> {
+        self.get_tenant(tenant_id).await?;
+        let key = tenants::generate_key();
+        let info = TenantKey {
+            id: uuid::Uuid::new_v4(),
+            tenant_id: *tenant_id,
+            name,
+            key_hash: tenants::hash_key(&key),
+            scopes: scopes.unwrap_or_else(KeyScope::all),
+            created_at: chrono::Utc::now(),
+            revoked_at: None,
+        };
+        self.storage.store_tenant_key(&info).await?;
+        info!("Issued API key {} for tenant {}", info.id, tenant_id);
+        Ok(IssuedKey { info, key })
+    }
+
+    pub async fn list_tenant_keys(&self, tenant_id: &uuid::Uuid) -> Result<Vec<TenantKey>> {
+        self.get_tenant(tenant_id).await?;
+        let mut keys = self.storage.list_tenant_keys().await?;
+        keys.retain(|key| key.tenant_id == *tenant_id);
+        keys.sort_by_key(|key| key.created_at);
+        Ok(keys)
+    }
+
+    /// Revoke a tenant's key, returning whether it was found
+    pub async fn revoke_tenant_key(&self, tenant_id: &uuid::Uuid, key_id: &uuid::Uuid) -> Result<bool> {
+        let Some(mut key) = self.list_tenant_keys(tenant_id).await?.into_iter().find(|key| key.id == *key_id) else {
+            return Ok(false);
+        };
+        if key.is_active() {
+            key.revoked_at = Some(chrono::Utc::now());
+            self.storage.store_tenant_key(&key).await?;
+            info!("Revoked API key {} of tenant {}", key_id, tenant_id);
+        }
+        Ok(true)
+    }
+
+    /// Tenant an active API key is scoped to
+    pub async fn tenant_of_key(&self, key: &str) -> Result<Option<uuid::Uuid>> {
+        Ok(self.active_key(key).await?.map(|key| key.tenant_id))
+    }
+
+    async fn active_key(&self, key: &str) -> Result<Option<TenantKey>> {
+        Ok(self.storage.load_tenant_key(&tenants::hash_key(key)).await?.filter(TenantKey::is_active))
+    }
+
+    /// Who the API key a request carries, if any, belongs to, turning away
+    /// unknown keys and the absence of one where the tenancy settings need it
+    pub async fn authenticate(&self, credential: Option<&str>) -> std::result::Result<Principal, (StatusCode, &'static str)> {
+        let tenancy = &self.http.tenancy;
+        match credential {
+            Some(key) => match self.identify(key).await {
+                Ok(Some(principal)) => Ok(principal),
+                Ok(None) => Err((StatusCode::UNAUTHORIZED, "Unknown or revoked API key")),
+                Err(e) => {
+                    error!("Failed to look up API key: {}", e);
+                    Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to look up API key"))
+                }
+            },
+            None if tenancy.required => Err((StatusCode::UNAUTHORIZED, "An API key is required")),
+            None => Ok(Principal::Operator),
+        }
+    }
+
+    /// Principal a key belongs to: the operator for the admin key, or the
+    /// tenant of an active tenant key
+    pub async fn identify(&self, key: &str) -> Result<Option<Principal>> {
+        if self.http.tenancy.is_admin_key(key) {
+            return Ok(Some(Principal::Operator));
+        }
+        Ok(self.active_key(key).await?.map(Principal::from))
+    }
+
+    pub fn tenancy(&self) -> &tenants::TenancyConfig {
+        &self.http.tenancy
+    }
+
     /// List workflows
     pub async fn list_workflows(&self, _project: Option<String>, _status: Option<String>) -> Result<Vec<Workflow>> {
-        self.storage.list_workflows().await
+        let mut workflows = self.storage.list_workflows().await?;
+        workflows.retain(|workflow| tenants::visible(workflow.tenant_id));
+        Ok(workflows)
     }
 }
 
@@ -1155,25 +3891,56 @@ impl Clone for TaskQueueServer {
             vectorizer: self.vectorizer.clone(),
             metrics: self.metrics.clone(),
             tasks: self.tasks.clone(),
+            statuses: self.statuses.clone(),
             workflows: self.workflows.clone(),
             projects: self.projects.clone(),
+            upsert_lock: self.upsert_lock.clone(),
+            recurrence_lock: self.recurrence_lock.clone(),
+            shared: self.shared.clone(),
+            events: self.events.clone(),
+            agents: self.agents.clone(),
+            phase_durations: self.phase_durations.clone(),
+            secrets: self.secrets.clone(),
+            secret_hosts: self.secret_hosts.clone(),
+            http: self.http.clone(),
+            artifacts: self.artifacts.clone(),
+            blobs: self.blobs.clone(),
+            url_signer: self.url_signer.clone(),
+            workspaces: self.workspaces.clone(),
+            retention: self.retention,
+            cold_storage: self.cold_storage.clone(),
+            vacuum: self.vacuum.clone(),
+            write_behind: self.write_behind.clone(),
+            idempotency: self.idempotency.clone(),
+            rate_limits: self.rate_limits.clone(),
+            queue_limiter: self.queue_limiter.clone(),
+            maintenance: self.maintenance.clone(),
         }
     }
 }
 
 // HTTP handlers
 
-pub async fn health_check() -> Json<Value> {
+pub async fn health_check(State(server): State<Arc<TaskQueueServer>>) -> Json<Value> {
+    let (status, storage) = match server.storage.probe().await {
+        Ok(latency) => ("healthy", json!({ "status": "ok", "latency_ms": latency.as_millis() })),
+        Err(e) => {
+            error!("Storage health probe failed: {}", e);
+            ("degraded", json!({ "status": "error", "error": e.to_string() }))
+        }
+    };
+
     Json(json!({
-        "status": "healthy",
+        "status": status,
         "timestamp": chrono::Utc::now(),
-        "version": env!("CARGO_PKG_VERSION")
+        "version": env!("CARGO_PKG_VERSION"),
+        "storage": storage
     }))
 }
 
 pub async fn submit_task(
     State(server): State<Arc<TaskQueueServer>>,
-    Json(task_request): Json<crate::core::CreateTaskRequest>,
+    Valid(task_request): Valid<crate::core::CreateTaskRequest>,
 ) -> std::result::Result<Json<Value>, StatusCode> {
     let task = task_request.to_task();
     match server.submit_task(task).await {
@@ -1188,23 +3955,124 @@ pub async fn submit_task(
     }
 }
 
+/// Submit an array of tasks, all or nothing; 400 with per-item errors if
+/// any is invalid
+pub async fn submit_tasks(
+    State(server): State<Arc<TaskQueueServer>>,
+    Json(requests): Json<Vec<crate::core::CreateTaskRequest>>,
+) -> std::result::Result<(StatusCode, Json<BatchSubmission>), StatusCode> {
+    let tasks = requests.into_iter().map(|request| request.to_task()).collect();
+    match server.submit_tasks(tasks).await {
+        Ok(batch) if batch.accepted => Ok((StatusCode::OK, Json(batch))),
+        Ok(batch) => Ok((StatusCode::BAD_REQUEST, Json(batch))),
+        Err(e) => {
+            error!("Failed to submit task batch: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Cancel, delete or update many tasks at once
+pub async fn bulk_update_tasks(
+    State(server): State<Arc<TaskQueueServer>>,
+    Json(request): Json<BulkRequest>,
+) -> std::result::Result<Json<BulkReport>, StatusCode> {
+    match server.bulk_update(request).await {
+        Ok(report) => Ok(Json(report)),
+        Err(TaskQueueError::ValidationError { .. }) => Err(StatusCode::BAD_REQUEST),
+        Err(e) => {
+            error!("Failed to apply bulk operation: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Fan out a task group with an optional join task
+pub async fn submit_group(
+    State(server): State<Arc<TaskQueueServer>>,
+    Json(request): Json<CreateTaskGroup>,
+) -> std::result::Result<(StatusCode, Json<GroupSubmission>), StatusCode> {
+    match server.submit_group(request).await {
+        Ok(group) if group.accepted => Ok((StatusCode::OK, Json(group))),
+        Ok(group) => Ok((StatusCode::BAD_REQUEST, Json(group))),
+        Err(TaskQueueError::ValidationError { .. }) => Err(StatusCode::BAD_REQUEST),
+        Err(e) => {
+            error!("Failed to submit task group: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Progress of a task group's members
+pub async fn get_group(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(group_id): Path<String>,
+) -> std::result::Result<Json<GroupProgress>, StatusCode> {
+    let group_id = uuid::Uuid::parse_str(&group_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    match server.group_progress(group_id).await {
+        Ok(Some(group)) => Ok(Json(group)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to get task group: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// A task with its completion estimate
+#[derive(Debug, serde::Serialize)]
+pub struct TaskDetail {
+    #[serde(flatten)]
+    pub task: Task,
+    pub estimated_completion_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// The estimate lands after the task's due date
+    pub past_due: bool,
+}
+
 pub async fn get_task(
     State(server): State<Arc<TaskQueueServer>>,
     Path(task_id): Path<String>,
-) -> std::result::Result<Json<Task>, StatusCode> {
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> std::result::Result<Response, StatusCode> {
     let task_id = match uuid::Uuid::parse_str(&task_id) {
         Ok(id) => id,
         Err(_) => return Err(StatusCode::BAD_REQUEST),
     };
-    
-    match server.get_task(task_id).await {
-        Ok(task) => {
-            let mut display_task = task.clone();
-            display_task.status = TaskQueueServer::get_effective_task_status(&task);
-            Ok(Json(display_task))
-        },
-        Err(_) => Err(StatusCode::NOT_FOUND),
+
+    // ?as_of=<RFC 3339 timestamp> returns the task as it was stored then
+    let task = match params.get("as_of") {
+        Some(as_of) => {
+            let at = chrono::DateTime::parse_from_rfc3339(as_of).map_err(|_| StatusCode::BAD_REQUEST)?;
+            server.get_task_as_of(task_id, at.with_timezone(&chrono::Utc)).await
+        }
+        None => server.get_task(task_id).await,
+    };
+
+    let mut task = task.map_err(|_| StatusCode::NOT_FOUND)?;
+    if etag::is_fresh(&headers, task.version) {
+        return Ok(etag::respond(&headers, task.version, ()));
     }
+    task.status = TaskQueueServer::get_effective_task_status(&task);
+
+    // Estimates describe the future, so past revisions get none
+    let estimate = match params.contains_key("as_of") {
+        true => None,
+        false => match server.task_estimate(&task).await {
+            Ok(estimate) => Some(estimate),
+            Err(e) => {
+                warn!("Failed to estimate completion of task {}: {}", task.id, e);
+                None
+            }
+        },
+    };
+
+    let version = task.version;
+    Ok(etag::respond(&headers, version, TaskDetail {
+        estimated_completion_at: estimate.as_ref().and_then(|e| e.estimated_completion_at),
+        past_due: estimate.is_some_and(|e| e.past_due),
+        task,
+    }))
 }
 
 pub async fn get_task_status(
@@ -1222,6 +4090,21 @@ pub async fn get_task_status(
     }
 }
 
+pub async fn get_task_history(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(task_id): Path<String>,
+) -> std::result::Result<Json<StateHistory>, StatusCode> {
+    let task_id = uuid::Uuid::parse_str(&task_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    match server.task_history(task_id).await {
+        Ok(history) => Ok(Json(history)),
+        Err(TaskQueueError::TaskNotFound { .. }) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to get task history: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
 pub async fn get_task_result(
     State(server): State<Arc<TaskQueueServer>>,
     Path(task_id): Path<String>,
@@ -1237,15 +4120,285 @@ pub async fn get_task_result(
     }
 }
 
+/// Longest `timeout` a wait for a task may ask for
+const MAX_TASK_WAIT: Duration = Duration::from_secs(300);
+const DEFAULT_TASK_WAIT: Duration = Duration::from_secs(30);
+/// How often a waiting request reads the task again without an event
+const TASK_WAIT_RECHECK: Duration = Duration::from_secs(1);
+
+/// A wait timeout such as `60s`, `2m` or `500ms`; a bare number is seconds
+fn parse_wait_timeout(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let amount: u64 = value[..split].parse().ok()?;
+    match &value[split..] {
+        "" | "s" => Some(Duration::from_secs(amount)),
+        "ms" => Some(Duration::from_millis(amount)),
+        "m" => Some(Duration::from_secs(amount.checked_mul(60)?)),
+        _ => None,
+    }
+}
+
+/// Block until a task is done or `timeout` (default 30s, at most 5m) passes,
+/// then answer its status and result: `200` once done, `202` if it still
+/// isn't
+pub async fn wait_for_task(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(task_id): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> std::result::Result<Response, StatusCode> {
+    let task_id = uuid::Uuid::parse_str(&task_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let timeout = match params.get("timeout") {
+        Some(timeout) => parse_wait_timeout(timeout).ok_or(StatusCode::BAD_REQUEST)?.min(MAX_TASK_WAIT),
+        None => DEFAULT_TASK_WAIT,
+    };
+    match server.wait_for_task(task_id, timeout).await {
+        Ok((task, finished)) => {
+            let status = if finished { StatusCode::OK } else { StatusCode::ACCEPTED };
+            let body = json!({
+                "task_id": task.id,
+                "status": task.status,
+                "finished": finished,
+                "result": task.result,
+            });
+            Ok((status, Json(body)).into_response())
+        }
+        Err(TaskQueueError::TaskNotFound { .. }) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to wait for task: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// How often `?follow=true` checks for new output
+const LOG_FOLLOW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A task's stored artifacts
+pub async fn list_task_artifacts(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(task_id): Path<String>,
+) -> std::result::Result<Json<Vec<ArtifactLink>>, StatusCode> {
+    let task_id = uuid::Uuid::parse_str(&task_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    match server.artifact_links(task_id).await {
+        Ok(artifacts) => Ok(Json(artifacts)),
+        Err(TaskQueueError::TaskNotFound { .. }) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to list artifacts: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Download one of a task's artifacts
+pub async fn download_task_artifact(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path((task_id, name)): Path<(String, String)>,
+) -> std::result::Result<Response, StatusCode> {
+    let task_id = uuid::Uuid::parse_str(&task_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let data = match server.get_artifact(task_id, &name).await {
+        Ok(Some(data)) => data,
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to load artifact: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    Ok(attachment(&name, data))
+}
+
+/// Download a blob through a URL signed by `GET /tasks/{id}/artifacts`
+pub async fn download_blob(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(key): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+) -> std::result::Result<Response, StatusCode> {
+    let expires = params.get("expires").and_then(|e| e.parse::<i64>().ok()).ok_or(StatusCode::BAD_REQUEST)?;
+    let signature = params.get("signature").ok_or(StatusCode::BAD_REQUEST)?;
+    let data = match server.get_signed_blob(&key, expires, signature).await {
+        Ok(Some(data)) => data,
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(TaskQueueError::PermissionDenied { .. }) => return Err(StatusCode::FORBIDDEN),
+        Err(e) => {
+            error!("Failed to load blob: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    Ok(attachment(&key, data))
+}
+
+/// A file download named after the last segment of `name`
+fn attachment(name: &str, data: Vec<u8>) -> Response {
+    let file_name = name.rsplit('/').next().unwrap_or(name).replace('"', "");
+    let disposition = format!("attachment; filename=\"{}\"", file_name);
+    (
+        [
+            (axum::http::header::CONTENT_TYPE, "application/octet-stream".to_string()),
+            (axum::http::header::CONTENT_DISPOSITION, disposition),
+        ],
+        data,
+    ).into_response()
+}
+
+/// A task's captured output as JSON chunks, or with `follow=true` as a
+/// stream that ends once the task has finished: server-sent events, or the
+/// bare output as chunked text when `format=text` or `Accept: text/plain`
+/// asks for it; `after` skips the chunks up to and including that sequence
+/// number
+pub async fn get_task_logs(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(task_id): Path<String>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> std::result::Result<Response, StatusCode> {
+    use futures_util::StreamExt;
+
+    let task_id = uuid::Uuid::parse_str(&task_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let after = match params.get("after").map(|a| a.parse::<u64>()) {
+        Some(Ok(after)) => Some(after),
+        Some(Err(_)) => return Err(StatusCode::BAD_REQUEST),
+        None => None,
+    };
+    let text = match params.get("format").map(String::as_str) {
+        Some("text") => true,
+        Some("json") | None => headers.get(axum::http::header::ACCEPT)
+            .and_then(|accept| accept.to_str().ok())
+            .is_some_and(|accept| accept.starts_with("text/plain")),
+        Some(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+    let chunks = match server.get_task_logs(task_id, after).await {
+        Ok(chunks) => chunks,
+        Err(TaskQueueError::TaskNotFound { .. }) => return Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to get task logs: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    let plain = [(axum::http::header::CONTENT_TYPE, "text/plain; charset=utf-8")];
+    if params.get("follow").map(String::as_str) != Some("true") {
+        return Ok(match text {
+            true => (plain, chunks.into_iter().map(|chunk| chunk.data).collect::<String>()).into_response(),
+            false => Json(chunks).into_response(),
+        });
+    }
+
+    let pending: std::collections::VecDeque<LogChunk> = chunks.into();
+    let chunks = futures_util::stream::unfold((server, after, pending), move |(server, mut after, mut pending)| async move {
+        loop {
+            if let Some(chunk) = pending.pop_front() {
+                after = Some(chunk.seq);
+                return Some((chunk, (server, after, pending)));
+            }
+            // Output is fully stored before the result is, so once there is
+            // a result nothing more will come
+            let finished = match server.get_task(task_id).await {
+                Ok(task) => task.result.is_some(),
+                Err(_) => true,
+            };
+            match server.get_task_logs(task_id, after).await {
+                Ok(chunks) if !chunks.is_empty() => pending.extend(chunks),
+                Ok(_) if !finished => tokio::time::sleep(LOG_FOLLOW_INTERVAL).await,
+                _ => return None,
+            }
+        }
+    });
+    if text {
+        let body = axum::body::Body::from_stream(chunks.map(|chunk| Ok::<_, std::convert::Infallible>(chunk.data)));
+        // Compressing would hold output back until enough of it piles up
+        let identity = [(axum::http::header::CONTENT_ENCODING, "identity")];
+        return Ok((plain, identity, body).into_response());
+    }
+    let events = chunks.map(|chunk| Event::default().id(chunk.seq.to_string()).json_data(&chunk));
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()).into_response())
+}
+
+/// Task, workflow and project changes as a server-sent event stream,
+/// filtered by `kinds`, `task_id`, `workflow_id` and `project_id`; a
+/// `Last-Event-ID` header or `cursor` resumes after that event
+pub async fn stream_events(
+    State(server): State<Arc<TaskQueueServer>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> std::result::Result<Response, StatusCode> {
+    use futures_util::StreamExt;
+
+    let filter = EventFilter::from_query(&params).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let cursor = match headers.get("last-event-id").and_then(|id| id.to_str().ok()).or(params.get("cursor").map(String::as_str)) {
+        Some(cursor) => Some(cursor.trim().parse::<u64>().map_err(|_| StatusCode::BAD_REQUEST)?),
+        None => None,
+    };
+    // The stream outlives the request's tenant scope
+    let tenant = tenants::current_tenant();
+    let visible = move |event: &TaskEvent| filter.matches(event) && tenant.is_none_or(|tenant| event.tenant_id == Some(tenant));
+
+    // Subscribing before reading the kept events loses none in between
+    let receiver = server.events().subscribe();
+    let (replay, missed) = match cursor.map(|cursor| server.events().since(cursor)) {
+        Some(Some(events)) => (events, false),
+        Some(None) => (Vec::new(), true),
+        None => (Vec::new(), false),
+    };
+    let replayed: HashSet<u64> = replay.iter().map(|event| event.sequence).collect();
+
+    let frame = |event: &TaskEvent| Event::default().id(event.sequence.to_string()).event(event.kind.as_str()).json_data(event);
+    let missed = missed.then(|| Ok(Event::default().comment("some events since the cursor are no longer kept")));
+    let replay: Vec<_> = replay.into_iter().filter(|event| visible(event)).map(|event| frame(&event)).collect();
+    let live = futures_util::stream::unfold(receiver, move |mut receiver| {
+        let replayed = replayed.clone();
+        let visible = visible.clone();
+        async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) if !replayed.contains(&event.sequence) && visible(&event) => {
+                        return Some((frame(&event), receiver));
+                    }
+                    Ok(_) => {}
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Event stream fell behind and skipped {} events", skipped);
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        }
+    });
+    let stream = futures_util::stream::iter(missed.into_iter().chain(replay)).chain(live);
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()).into_response())
+}
+
 pub async fn list_tasks(
     State(server): State<Arc<TaskQueueServer>>,
     Query(params): Query<HashMap<String, String>>,
-) -> std::result::Result<Json<Vec<Task>>, StatusCode> {
-    let project = params.get("project").cloned();
-    let status = params.get("status").cloned();
-    
-    match server.list_tasks(project, status).await {
-        Ok(tasks) => Ok(Json(tasks)),
+) -> std::result::Result<Json<Value>, StatusCode> {
+    let filter = TaskFilter::from_query(&params).map_err(|_| StatusCode::BAD_REQUEST)?;
+    // `view=summary` returns lightweight summaries instead of full tasks
+    let summary = params.get("view").map(String::as_str) == Some("summary");
+
+    // Always paged, so an unfiltered listing stays bounded
+    let number = |param: &str, default: usize| match params.get(param).map(|n| n.parse::<usize>()) {
+        Some(Ok(n)) => Ok(n),
+        Some(Err(_)) => Err(StatusCode::BAD_REQUEST),
+        None => Ok(default),
+    };
+    let sort = match params.get("sort") {
+        Some(sort) => SortField::parse(sort).ok_or(StatusCode::BAD_REQUEST)?,
+        None => SortField::CreatedAt,
+    };
+    let page = PageRequest {
+        cursor: params.get("cursor").cloned(),
+        offset: number("offset", 0)?,
+        limit: number("limit", DEFAULT_PAGE_SIZE)?.min(MAX_PAGE_SIZE),
+        sort,
+    };
+
+    let page = if summary {
+        server.list_task_summaries_page(&filter, page).await.map(|page| json!(page))
+    } else {
+        server.list_tasks_page(&filter, page).await.map(|page| json!(page))
+    };
+
+    match page {
+        Ok(page) => Ok(Json(page)),
+        Err(TaskQueueError::ValidationError { .. }) => Err(StatusCode::BAD_REQUEST),
         Err(e) => {
             error!("Failed to list tasks: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
@@ -1253,9 +4406,26 @@ pub async fn list_tasks(
     }
 }
 
+pub async fn search_tasks(
+    State(server): State<Arc<TaskQueueServer>>,
+    Query(query): Query<SearchQuery>,
+) -> std::result::Result<Json<SearchResults>, StatusCode> {
+    if search::parse_query(&query.q).is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let limit = query.limit.unwrap_or(search::DEFAULT_LIMIT).clamp(1, search::MAX_LIMIT);
+    match server.search_tasks(&query.q, limit).await {
+        Ok(results) => Ok(Json(results)),
+        Err(e) => {
+            error!("Failed to search tasks: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
 pub async fn submit_workflow(
     State(server): State<Arc<TaskQueueServer>>,
-    Json(workflow): Json<Workflow>,
+    Valid(workflow): Valid<Workflow>,
 ) -> std::result::Result<Json<Value>, StatusCode> {
     match server.submit_workflow(workflow).await {
         Ok(workflow_id) => Ok(Json(json!({
@@ -1272,18 +4442,34 @@ pub async fn submit_workflow(
 pub async fn get_workflow(
     State(server): State<Arc<TaskQueueServer>>,
     Path(workflow_id): Path<String>,
-) -> std::result::Result<Json<Workflow>, StatusCode> {
+    headers: HeaderMap,
+) -> std::result::Result<Response, StatusCode> {
     let workflow_id = match uuid::Uuid::parse_str(&workflow_id) {
         Ok(id) => id,
         Err(_) => return Err(StatusCode::BAD_REQUEST),
     };
     
     match server.get_workflow(workflow_id).await {
-        Ok(workflow) => Ok(Json(workflow)),
+        Ok(workflow) => Ok(etag::respond(&headers, workflow.version, workflow)),
         Err(_) => Err(StatusCode::NOT_FOUND),
     }
 }
 
+pub async fn get_workflow_history(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(workflow_id): Path<String>,
+) -> std::result::Result<Json<StateHistory>, StatusCode> {
+    let workflow_id = uuid::Uuid::parse_str(&workflow_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    match server.workflow_history(workflow_id).await {
+        Ok(history) => Ok(Json(history)),
+        Err(TaskQueueError::WorkflowNotFound { .. }) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to get workflow history: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
 pub async fn get_workflow_status(
     State(server): State<Arc<TaskQueueServer>>,
     Path(workflow_id): Path<String>,
@@ -1293,15 +4479,24 @@ pub async fn get_workflow_status(
         Err(_) => return Err(StatusCode::BAD_REQUEST),
     };
     
-    match server.get_workflow_status(workflow_id).await {
-        Ok(status) => Ok(Json(json!({ "status": status }))),
-        Err(_) => Err(StatusCode::NOT_FOUND),
-    }
+    let status = server.get_workflow_status(workflow_id).await.map_err(|_| StatusCode::NOT_FOUND)?;
+    let workflow = server.get_workflow(workflow_id).await.map_err(|_| StatusCode::NOT_FOUND)?;
+    let estimated_completion_at = match server.workflow_estimate(&workflow).await {
+        Ok(estimate) => estimate,
+        Err(e) => {
+            warn!("Failed to estimate completion of workflow {}: {}", workflow_id, e);
+            None
+        }
+    };
+    Ok(Json(json!({ "status": status, "estimated_completion_at": estimated_completion_at })))
 }
 
 pub async fn get_metrics(
     State(server): State<Arc<TaskQueueServer>>,
 ) -> Json<Value> {
+    if let Err(e) = server.refresh_storage_metrics().await {
+        warn!("Failed to read storage statistics: {}", e);
+    }
     Json(server.metrics.get_metrics())
 }
 
@@ -1329,6 +4524,39 @@ pub async fn cancel_task(
     }
 }
 
+/// Pause a running task
+pub async fn pause_task(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(task_id): Path<String>,
+) -> std::result::Result<Json<Task>, StatusCode> {
+    let task_id = uuid::Uuid::parse_str(&task_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    server.pause_task(task_id).await
+        .map(Json)
+        .map_err(suspend_error)
+}
+
+/// Resume a paused task
+pub async fn resume_task(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(task_id): Path<String>,
+) -> std::result::Result<Json<Task>, StatusCode> {
+    let task_id = uuid::Uuid::parse_str(&task_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    server.resume_task(task_id).await
+        .map(Json)
+        .map_err(suspend_error)
+}
+
+fn suspend_error(e: TaskQueueError) -> StatusCode {
+    match e {
+        TaskQueueError::TaskNotFound { .. } => StatusCode::NOT_FOUND,
+        TaskQueueError::InvalidStatusTransition(_) => StatusCode::CONFLICT,
+        e => {
+            error!("Failed to pause or resume task: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
 /// Retry a task
 pub async fn retry_task(
     State(server): State<Arc<TaskQueueServer>>,
@@ -1372,50 +4600,20 @@ pub async fn delete_task(
     }
 }
 
-/// Update a task
+/// Update the fields of a task given in the body; serves both `PUT` and
+/// `PATCH /tasks/{id}`
 pub async fn update_task(
     State(server): State<Arc<TaskQueueServer>>,
     Path(task_id): Path<String>,
+    headers: HeaderMap,
     Json(payload): Json<serde_json::Value>,
-) -> std::result::Result<Json<Value>, StatusCode> {
-    let task_id = match uuid::Uuid::parse_str(&task_id) {
-        Ok(id) => id,
-        Err(_) => return Err(StatusCode::BAD_REQUEST),
-    };
-    
-    let name = payload.get("name").and_then(|n| n.as_str()).map(|s| s.to_string());
-    let command = payload.get("command").and_then(|c| c.as_str()).map(|s| s.to_string());
-    let description = payload.get("description").and_then(|d| d.as_str()).map(|s| s.to_string());
-    let priority = payload.get("priority").and_then(|p| p.as_str()).and_then(|p| match p {
-        "Low" => Some(crate::core::TaskPriority::Low),
-        "Normal" => Some(crate::core::TaskPriority::Normal),
-        "High" => Some(crate::core::TaskPriority::High),
-        "Critical" => Some(crate::core::TaskPriority::Critical),
-        _ => None,
-    });
-    let status = payload.get("status").and_then(|s| s.as_str()).and_then(|s| match s {
-        "Planning" => Some(crate::core::TaskStatus::Planning),
-        "Implementation" => Some(crate::core::TaskStatus::Implementation),
-        "TestCreation" => Some(crate::core::TaskStatus::TestCreation),
-        "Testing" => Some(crate::core::TaskStatus::Testing),
-        "AIReview" => Some(crate::core::TaskStatus::AIReview),
-        "Finalized" => Some(crate::core::TaskStatus::Finalized),
-        "Pending" => Some(crate::core::TaskStatus::Pending),
-        "Running" => Some(crate::core::TaskStatus::Running),
-        "Completed" => Some(crate::core::TaskStatus::Completed),
-        "Failed" => Some(crate::core::TaskStatus::Failed),
-        "Cancelled" => Some(crate::core::TaskStatus::Cancelled),
-        _ => None,
-    });
-    let project_id = payload.get("project_id").and_then(|p| {
-        if p.is_null() {
-            Some(None)
-        } else {
-            p.as_str().and_then(|s| uuid::Uuid::parse_str(s).ok()).map(Some)
-        }
-    });
-    
-    match server.update_task(task_id, name, command, description, priority, status, project_id).await {
+) -> std::result::Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let invalid = |errors: Vec<crate::core::FieldError>| (StatusCode::UNPROCESSABLE_ENTITY, Json(json!({ "errors": errors })));
+    let task_id = uuid::Uuid::parse_str(&task_id)
+        .map_err(|_| invalid(vec![crate::core::FieldError::new("id", "not a valid task ID")]))?;
+    let request = crate::core::UpdateTaskRequest::from_value(payload).map_err(invalid)?;
+
+    match server.patch_task(task_id, request, &Precondition::from_headers(&headers)).await {
         Ok(task) => Ok(Json(json!({
             "message": "Task updated successfully",
             "task": {
@@ -1426,9 +4624,16 @@ pub async fn update_task(
                 "status": format!("{:?}", task.status),
                 "priority": format!("{:?}", task.priority),
                 "updated_at": task.updated_at,
+                "version": task.version,
             }
         }))),
-        Err(_) => Err(StatusCode::NOT_FOUND),
+        Err(TaskQueueError::InvalidFields { errors }) => Err(invalid(errors)),
+        Err(TaskQueueError::TaskNotFound { .. }) => Err((StatusCode::NOT_FOUND, Json(json!({ "error": "Task not found" })))),
+        Err(e @ TaskQueueError::PreconditionFailed { .. }) => Err((StatusCode::PRECONDITION_FAILED, Json(json!({ "error": e.to_string() })))),
+        Err(e) => {
+            error!("Failed to update task: {}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))))
+        }
     }
 }
 
@@ -1527,579 +4732,4381 @@ pub async fn update_task_priority(
     }
 }
 
-/// Get system stats
-pub async fn get_stats(
+/// Point requests to unversioned API paths at their `/api/v1` successor
+pub async fn deprecate_unversioned(request: Request, next: Next) -> Response {
+    let successor = format!("<{}{}>; rel=\"successor-version\"", API_PREFIX, request.uri().path());
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    headers.insert("deprecation", axum::http::HeaderValue::from_static("true"));
+    if let Ok(link) = axum::http::HeaderValue::from_str(&successor) {
+        headers.insert(axum::http::header::LINK, link);
+    }
+    response
+}
+
+/// Audit changes as made over REST, or by the CLI when it says so
+pub async fn track_source(request: Request, next: Next) -> Response {
+    let source = request.headers()
+        .get(audit::SOURCE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(AuditSource::parse)
+        .filter(|source| *source == AuditSource::Cli)
+        .unwrap_or(AuditSource::Rest);
+    audit::with_source(source, next.run(request)).await
+}
+
+/// Scope a request to the tenant of its API key, turning away requests the
+/// tenancy settings don't let in and those needing a scope the key lacks
+pub async fn resolve_tenant(
     State(server): State<Arc<TaskQueueServer>>,
-) -> Json<Value> {
-    let tasks = server.tasks.read().await;
-    let workflows = server.workflows.read().await;
-    
-    let total_tasks = tasks.len();
-    let active_tasks = tasks.values().filter(|t| {
-        let effective_status = TaskQueueServer::get_effective_task_status(t);
-        effective_status == crate::core::TaskStatus::Running
-    }).count();
-    let pending_tasks = tasks.values().filter(|t| {
-        let effective_status = TaskQueueServer::get_effective_task_status(t);
-        effective_status == crate::core::TaskStatus::Pending
-    }).count();
-    let completed_tasks = tasks.values().filter(|t| {
-        info!("Checking task: {}", t.name);
-        let effective_status = TaskQueueServer::get_effective_task_status(t);
-        info!("Task {} effective status: {:?}", t.name, effective_status);
-        let is_completed = effective_status == crate::core::TaskStatus::Completed;
-        info!("Is completed: {}", is_completed);
-        if is_completed {
-            info!("Task {} is completed!", t.name);
-        }
-        is_completed
-    }).count();
-    let failed_tasks = tasks.values().filter(|t| {
-        let effective_status = TaskQueueServer::get_effective_task_status(t);
-        effective_status == crate::core::TaskStatus::Failed
-    }).count();
-    let total_workflows = workflows.len();
-    
-    Json(json!({
-        "total_tasks": total_tasks,
-        "active_tasks": active_tasks,
-        "pending_tasks": pending_tasks,
-        "completed_tasks": completed_tasks,
-        "failed_tasks": failed_tasks,
-        "total_workflows": total_workflows,
-        "cpu_usage_percent": 0.0,
-        "memory_usage_mb": 0.0,
-        "uptime_seconds": 0,
-        "timestamp": chrono::Utc::now().to_rfc3339()
-    }))
-}
-
-/// Add dependency to a task
-pub async fn add_task_dependency(
-    State(server): State<Arc<TaskQueueServer>>,
-    Path(task_id): Path<String>,
-    Json(request): Json<serde_json::Value>,
-) -> std::result::Result<Json<Value>, StatusCode> {
-    let task_id = match uuid::Uuid::parse_str(&task_id) {
-        Ok(id) => id,
-        Err(_) => return Err(StatusCode::BAD_REQUEST),
-    };
-    
-    let dependency_task_id = match request.get("dependency_task_id").and_then(|v| v.as_str()) {
-        Some(id) => match uuid::Uuid::parse_str(id) {
-            Ok(id) => id,
-            Err(_) => return Err(StatusCode::BAD_REQUEST),
+    request: Request,
+    next: Next,
+) -> Response {
+    let reject = |status: StatusCode, error: &str| (status, Json(json!({ "error": error }))).into_response();
+    let credential = tenants::credential(request.headers());
+    if credential.is_none() && request.uri().path() == "/health" {
+        return next.run(request).await;
+    }
+    let identified = request.extensions().get::<Principal>().cloned();
+    let principal = match identified {
+        // Already identified by the rate limiter
+        Some(principal) => principal,
+        None => match server.authenticate(credential).await {
+            Ok(principal) => principal,
+            Err((status, error)) => return reject(status, error),
         },
-        None => return Err(StatusCode::BAD_REQUEST),
-    };
-    
-    let task_name = request.get("task_name").and_then(|v| v.as_str()).map(|s| s.to_string());
-    let condition = match request.get("condition").and_then(|v| v.as_str()) {
-        Some("Success") => crate::core::DependencyCondition::Success,
-        Some("Failure") => crate::core::DependencyCondition::Failure,
-        Some("Completion") => crate::core::DependencyCondition::Completion,
-        _ => crate::core::DependencyCondition::Success,
     };
-    let required = request.get("required").and_then(|v| v.as_bool()).unwrap_or(true);
-    let correlation_id = request.get("correlation_id").and_then(|v| v.as_str()).map(|s| s.to_string());
-    
-    match server.add_task_dependency(task_id, dependency_task_id, task_name, condition, required, correlation_id).await {
-        Ok(_) => Ok(Json(json!({
-            "message": "Dependency added successfully",
-            "task_id": task_id
-        }))),
-        Err(_) => Err(StatusCode::NOT_FOUND),
+    if principal.tenant().is_some() && tenants::is_operator_path(request.uri().path()) {
+        return reject(StatusCode::FORBIDDEN, "Tenant keys can't use operator endpoints");
     }
+    if !principal.allows(KeyScope::for_method(request.method())) {
+        return reject(StatusCode::FORBIDDEN, "The API key lacks the scope this request needs");
+    }
+    tenants::with_tenant(principal.tenant(), next.run(request)).await
 }
 
-/// Get task dependencies
-pub async fn get_task_dependencies(
+/// Answer `503 Service Unavailable` to submissions while in maintenance
+pub async fn refuse_in_maintenance(
     State(server): State<Arc<TaskQueueServer>>,
-    Path(task_id): Path<String>,
-) -> std::result::Result<Json<Value>, StatusCode> {
-    let task_id = match uuid::Uuid::parse_str(&task_id) {
-        Ok(id) => id,
-        Err(_) => return Err(StatusCode::BAD_REQUEST),
-    };
-    
-    match server.get_task_dependencies(task_id).await {
-        Ok(dependencies) => Ok(Json(json!(dependencies))),
-        Err(_) => Err(StatusCode::NOT_FOUND),
+    request: Request,
+    next: Next,
+) -> Response {
+    if !server.maintenance.is_enabled() || !maintenance::is_submission(request.method(), request.uri().path()) {
+        return next.run(request).await;
     }
+    let secs = maintenance::RETRY_AFTER.as_secs();
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(axum::http::header::RETRY_AFTER, secs.to_string())],
+        Json(json!({ "error": "The server is in maintenance and accepts no new tasks", "retry_after_secs": secs })),
+    ).into_response()
 }
 
-/// Advance task development phase
-pub async fn advance_task_phase(
+/// Explain a `413 Payload Too Large` with the limit that was exceeded and
+/// the setting that raises it
+pub async fn explain_too_large(
+    State((limit, setting)): State<(usize, &'static str)>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let response = next.run(request).await;
+    let explained = response.headers()
+        .get(axum::http::header::CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes().starts_with(b"application/json"));
+    if response.status() != StatusCode::PAYLOAD_TOO_LARGE || explained {
+        return response;
+    }
+    too_large(limit, setting)
+}
+
+fn too_large(limit: usize, setting: &str) -> Response {
+    (
+        StatusCode::PAYLOAD_TOO_LARGE,
+        Json(json!({
+            "error": format!("Request body is larger than the limit of {} bytes; split it up or raise server.limits.{}", limit, setting),
+            "limit_bytes": limit,
+        })),
+    ).into_response()
+}
+
+/// Answer `429 Too Many Requests` to a client that has spent its bucket for
+/// the route group
+pub async fn limit_rate(
     State(server): State<Arc<TaskQueueServer>>,
-    Path(task_id): Path<String>,
-) -> std::result::Result<Json<Value>, StatusCode> {
-    let task_id = match uuid::Uuid::parse_str(&task_id) {
-        Ok(id) => id,
-        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let Some(group) = RouteGroup::of(request.method(), request.uri().path()).filter(|_| server.rate_limits.is_enabled()) else {
+        return next.run(request).await;
     };
-    
-    match server.advance_task_phase(task_id).await {
-        Ok(advanced) => Ok(Json(json!({
-            "advanced": advanced,
-            "task_id": task_id
-        }))),
-        Err(_) => Err(StatusCode::NOT_FOUND),
+    let peer = request.extensions().get::<ConnectInfo<std::net::SocketAddr>>().map(|info| info.0);
+    // Unknown keys count against the address they come from
+    let principal = match tenants::credential(request.headers()) {
+        Some(key) => server.identify(key).await.unwrap_or_else(|e| {
+            error!("Failed to look up API key: {}", e);
+            None
+        }),
+        None => None,
+    };
+    let client = rate_limiting::client_id(principal.as_ref(), peer);
+    if let Some(principal) = principal {
+        // Spares `resolve_tenant` looking the key up again
+        request.extensions_mut().insert(principal);
+    }
+    match server.rate_limits.check(group, &client).await {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => {
+            let secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(axum::http::header::RETRY_AFTER, secs.to_string())],
+                Json(json!({ "error": "Rate limit exceeded", "retry_after_secs": secs })),
+            ).into_response()
+        }
     }
 }
 
-/// Set task status
-pub async fn set_task_status(
+/// Answer a request repeated with the same `Idempotency-Key` with the
+/// response to the first one, instead of running it again
+pub async fn replay_idempotent(
     State(server): State<Arc<TaskQueueServer>>,
-    Path(task_id): Path<String>,
-    Json(payload): Json<serde_json::Value>,
-) -> std::result::Result<Json<Value>, StatusCode> {
-    let task_id = match uuid::Uuid::parse_str(&task_id) {
-        Ok(id) => id,
-        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    request: Request,
+    next: Next,
+) -> Response {
+    let key = request.headers()
+        .get(idempotency::IDEMPOTENCY_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().to_string());
+    let Some(key) = key.filter(|_| server.idempotency.is_enabled()) else {
+        return next.run(request).await;
     };
+    let reject = |status: StatusCode, error: &str| (status, Json(json!({ "error": error }))).into_response();
+    if key.is_empty() || key.len() > idempotency::MAX_KEY_LENGTH {
+        return reject(StatusCode::BAD_REQUEST, "Idempotency-Key must be 1 to 255 characters");
+    }
 
-    let status_str = payload.get("status")
-        .and_then(|s| s.as_str())
-        .ok_or(StatusCode::BAD_REQUEST)?;
-
-    let new_status = match status_str {
-        "Planning" => TaskStatus::Planning,
-        "Implementation" => TaskStatus::Implementation,
-        "TestCreation" => TaskStatus::TestCreation,
-        "Testing" => TaskStatus::Testing,
-        "AIReview" => TaskStatus::AIReview,
-        "Finalized" => TaskStatus::Finalized,
-        "Cancelled" => TaskStatus::Cancelled,
-        "Failed" => TaskStatus::Failed,
-        _ => return Err(StatusCode::BAD_REQUEST),
+    let scope = idempotency::scope(tenants::current_tenant(), request.method().as_str(), request.uri().path(), &key);
+    let (parts, body) = request.into_parts();
+    // Routes keep their own, lower, limits when the body is run
+    let Ok(body) = axum::body::to_bytes(body, server.http.limits.max_bulk_bytes).await else {
+        return too_large(server.http.limits.max_bulk_bytes, "max_bulk_bytes");
     };
+    let fingerprint = idempotency::fingerprint(&body);
 
-    match server.set_task_status(task_id, new_status).await {
-        Ok(()) => Ok(Json(json!({
-            "status": "updated",
-            "message": "Task status updated successfully"
-        }))),
+    let Some(_running) = server.idempotency.begin(&scope) else {
+        return reject(StatusCode::CONFLICT, "A request with this Idempotency-Key is still running");
+    };
+    let now = chrono::Utc::now();
+    match server.storage.load_idempotency(&scope).await {
+        Ok(Some(record)) if !record.is_expired(now) => {
+            if record.fingerprint != fingerprint {
+                return reject(StatusCode::UNPROCESSABLE_ENTITY, "Idempotency-Key was already used for a different request");
+            }
+            let mut response = Response::builder()
+                .status(record.status)
+                .header(idempotency::REPLAYED_HEADER, "true");
+            if let Some(content_type) = &record.content_type {
+                response = response.header(axum::http::header::CONTENT_TYPE, content_type);
+            }
+            return response.body(axum::body::Body::from(record.body)).unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response());
+        }
+        Ok(_) => {}
         Err(e) => {
-            error!("Failed to update task status: {}", e);
-            Err(StatusCode::BAD_REQUEST)
+            error!("Failed to load idempotency key: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
         }
     }
-}
 
-/// Get task correlations
-pub async fn get_task_correlations(
-    State(server): State<Arc<TaskQueueServer>>,
-    Path(task_id): Path<String>,
-) -> std::result::Result<Json<Value>, StatusCode> {
-    let task_id = match uuid::Uuid::parse_str(&task_id) {
-        Ok(id) => id,
-        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    let response = next.run(Request::from_parts(parts, axum::body::Body::from(body))).await;
+    if !response.status().is_success() {
+        return response;
+    }
+    let (parts, body) = response.into_parts();
+    let Ok(body) = axum::body::to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
     };
-    
-    match server.get_task_correlations(task_id).await {
-        Ok(correlations) => Ok(Json(json!(correlations))),
-        Err(_) => Err(StatusCode::NOT_FOUND),
+    match String::from_utf8(body.to_vec()) {
+        Ok(text) => {
+            let record = IdempotencyRecord {
+                scope,
+                fingerprint,
+                status: parts.status.as_u16(),
+                content_type: parts.headers.get(axum::http::header::CONTENT_TYPE)
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::to_string),
+                body: text,
+                expires_at: server.idempotency.expires_at(now),
+            };
+            if let Err(e) = server.storage.store_idempotency(&record).await {
+                warn!("Failed to store response for idempotency key {}: {}", key, e);
+            }
+        }
+        Err(_) => warn!("Not storing non-UTF-8 response for idempotency key {}", key),
     }
+    Response::from_parts(parts, axum::body::Body::from(body))
 }
 
-/// Get workflow result
-pub async fn get_workflow_result(
+/// Attribute requests carrying `X-Agent-Id` to that agent
+pub async fn track_agent(
     State(server): State<Arc<TaskQueueServer>>,
-    Path(workflow_id): Path<String>,
-) -> std::result::Result<Json<Value>, StatusCode> {
-    let workflow_id = match uuid::Uuid::parse_str(&workflow_id) {
-        Ok(id) => id,
-        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    request: Request,
+    next: Next,
+) -> Response {
+    let agent = request.headers()
+        .get(agents::AGENT_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| uuid::Uuid::parse_str(value).ok());
+    let Some(agent) = agent else {
+        return next.run(request).await;
     };
-    
-    match server.get_workflow(workflow_id).await {
-        Ok(workflow) => Ok(Json(json!({
-            "workflow_id": workflow.id,
-            "status": workflow.status,
-            "result": "No result field in workflow struct"
-        }))),
-        Err(_) => Err(StatusCode::NOT_FOUND),
+
+    let mutation = !matches!(*request.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    let action = format!("{} {}", request.method(), request.uri().path());
+    let response = agents::with_agent(Some(agent), next.run(request)).await;
+
+    let succeeded = !(response.status().is_client_error() || response.status().is_server_error());
+    if let Err(e) = server.agents.record(&agent, &action, mutation, succeeded).await {
+        warn!("Failed to record activity of agent {}: {}", agent, e);
     }
+    response
 }
 
-/// Cancel workflow
-pub async fn cancel_workflow(
+pub async fn register_agent(
     State(server): State<Arc<TaskQueueServer>>,
-    Path(workflow_id): Path<String>,
-    Json(payload): Json<serde_json::Value>,
-) -> std::result::Result<Json<Value>, StatusCode> {
-    let workflow_id = match uuid::Uuid::parse_str(&workflow_id) {
-        Ok(id) => id,
-        Err(_) => return Err(StatusCode::BAD_REQUEST),
-    };
-    
-    let reason = payload.get("reason")
-        .and_then(|r| r.as_str())
-        .unwrap_or("User requested cancellation");
-    
-    match server.cancel_workflow(workflow_id, reason.to_string()).await {
-        Ok(_) => Ok(Json(json!({
-            "message": "Workflow cancelled successfully",
-            "workflow_id": workflow_id
-        }))),
-        Err(_) => Err(StatusCode::NOT_FOUND),
+    Json(request): Json<RegisterAgent>,
+) -> std::result::Result<Json<AgentStatus>, StatusCode> {
+    match server.agents.register(request).await {
+        Ok(agent) => Ok(Json(AgentStatus::at(agent, chrono::Utc::now()))),
+        Err(TaskQueueError::ValidationError { .. }) => Err(StatusCode::BAD_REQUEST),
+        Err(e) => {
+            error!("Failed to register agent: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
     }
 }
 
-/// Approve workflow
-pub async fn approve_workflow(
+pub async fn list_agents(
     State(server): State<Arc<TaskQueueServer>>,
-    Path(workflow_id): Path<String>,
-    Json(payload): Json<serde_json::Value>,
-) -> std::result::Result<Json<Value>, StatusCode> {
-    let workflow_id = match uuid::Uuid::parse_str(&workflow_id) {
-        Ok(id) => id,
-        Err(_) => return Err(StatusCode::BAD_REQUEST),
-    };
-    
-    let message = payload.get("message")
-        .and_then(|m| m.as_str())
-        .unwrap_or("Workflow approved");
-    
-    match server.approve_workflow(workflow_id, message.to_string()).await {
-        Ok(_) => Ok(Json(json!({
-            "message": "Workflow approved successfully",
-            "workflow_id": workflow_id
-        }))),
-        Err(_) => Err(StatusCode::NOT_FOUND),
+) -> std::result::Result<Json<Vec<AgentStatus>>, StatusCode> {
+    let now = chrono::Utc::now();
+    match server.agents.list().await {
+        Ok(agents) => Ok(Json(agents.into_iter().map(|agent| AgentStatus::at(agent, now)).collect())),
+        Err(e) => {
+            error!("Failed to list agents: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
     }
 }
 
-/// Update workflow status
-pub async fn update_workflow_status(
+pub async fn get_agent(
     State(server): State<Arc<TaskQueueServer>>,
-    Path(workflow_id): Path<String>,
-    Json(payload): Json<serde_json::Value>,
-) -> std::result::Result<Json<Value>, StatusCode> {
-    let workflow_id = match uuid::Uuid::parse_str(&workflow_id) {
-        Ok(id) => id,
-        Err(_) => return Err(StatusCode::BAD_REQUEST),
-    };
-    
-    let status_str = payload.get("status")
-        .and_then(|s| s.as_str())
-        .unwrap_or("Pending");
-    
-    let status = match status_str {
-        "Pending" => crate::core::WorkflowStatus::Pending,
-        "Running" => crate::core::WorkflowStatus::Running,
-        "Completed" => crate::core::WorkflowStatus::Completed,
-        "Failed" => crate::core::WorkflowStatus::Failed,
-        "Cancelled" => crate::core::WorkflowStatus::Cancelled,
-        _ => return Err(StatusCode::BAD_REQUEST),
-    };
-    
-    let message = payload.get("message")
-        .and_then(|m| m.as_str())
-        .unwrap_or("Status updated");
-    
-    match server.update_workflow_status(workflow_id, status, message.to_string()).await {
-        Ok(_) => Ok(Json(json!({
-            "message": "Workflow status updated successfully",
-            "workflow_id": workflow_id
-        }))),
-        Err(_) => Err(StatusCode::NOT_FOUND),
+    Path(agent_id): Path<String>,
+) -> std::result::Result<Json<AgentStatus>, StatusCode> {
+    let agent_id = uuid::Uuid::parse_str(&agent_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    match server.agents.get(&agent_id).await {
+        Ok(Some(agent)) => Ok(Json(AgentStatus::at(agent, chrono::Utc::now()))),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to get agent: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
     }
 }
 
-/// List workflows
-pub async fn list_workflows(
+pub async fn agent_heartbeat(
     State(server): State<Arc<TaskQueueServer>>,
-    Query(params): Query<HashMap<String, String>>,
-) -> std::result::Result<Json<Vec<Workflow>>, StatusCode> {
-    let project = params.get("project").cloned();
-    let status = params.get("status").cloned();
-    
-    match server.list_workflows(project, status).await {
-        Ok(workflows) => Ok(Json(workflows)),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    Path(agent_id): Path<String>,
+) -> std::result::Result<Json<AgentStatus>, StatusCode> {
+    let agent_id = uuid::Uuid::parse_str(&agent_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    match server.agents.heartbeat(&agent_id).await {
+        Ok(agent) => Ok(Json(AgentStatus::at(agent, chrono::Utc::now()))),
+        Err(TaskQueueError::ValidationError { .. }) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to record agent heartbeat: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
     }
 }
 
-/// Serve dashboard HTML
-pub async fn serve_dashboard() -> Html<&'static str> {
-    Html(r#"
-<!DOCTYPE html>
-<html lang="pt-BR">
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>Task Queue Dashboard</title>
-    <style>
-        body {
-            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif;
-            margin: 0;
-            padding: 20px;
-            background: linear-gradient(135deg, #667eea 0%, #764ba2 100%);
-            min-height: 100vh;
-        }
-        .container {
-            max-width: 1200px;
-            margin: 0 auto;
-            background: white;
-            border-radius: 12px;
-            box-shadow: 0 20px 40px rgba(0,0,0,0.1);
-            overflow: hidden;
-        }
-        .header {
-            background: linear-gradient(135deg, #667eea 0%, #764ba2 100%);
-            color: white;
-            padding: 30px;
-            text-align: center;
-        }
-        .header h1 {
-            margin: 0;
-            font-size: 2.5em;
-            font-weight: 300;
-        }
-        .header p {
-            margin: 10px 0 0 0;
-            opacity: 0.9;
-        }
-        .content {
-            padding: 40px;
-        }
-        .grid {
-            display: grid;
-            grid-template-columns: repeat(auto-fit, minmax(300px, 1fr));
-            gap: 30px;
-            margin-bottom: 40px;
-        }
-        .card {
-            background: #f8f9fa;
-            border-radius: 8px;
-            padding: 25px;
-            border-left: 4px solid #667eea;
-        }
-        .card h3 {
-            margin: 0 0 15px 0;
-            color: #333;
-        }
-        .card p {
-            margin: 0 0 20px 0;
-            color: #666;
-            line-height: 1.6;
-        }
-        .btn {
-            display: inline-block;
-            background: #667eea;
-            color: white;
-            padding: 12px 24px;
-            text-decoration: none;
-            border-radius: 6px;
-            font-weight: 500;
-            transition: all 0.3s ease;
-        }
-        .btn:hover {
-            background: #5a6fd8;
-            transform: translateY(-2px);
-        }
-        .api-info {
-            background: #e3f2fd;
-            border-radius: 8px;
-            padding: 20px;
-            margin-top: 30px;
-        }
-        .api-info h3 {
-            margin: 0 0 15px 0;
-            color: #1976d2;
-        }
-        .endpoint {
-            background: white;
-            padding: 10px 15px;
-            margin: 8px 0;
-            border-radius: 4px;
-            font-family: 'Courier New', monospace;
-            font-size: 14px;
-            border-left: 3px solid #1976d2;
+pub async fn list_views(
+    State(server): State<Arc<TaskQueueServer>>,
+) -> std::result::Result<Json<Vec<SavedView>>, StatusCode> {
+    match server.list_views().await {
+        Ok(views) => Ok(Json(views)),
+        Err(e) => {
+            error!("Failed to list views: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
-    </style>
-</head>
-<body>
-    <div class="container">
-        <div class="header">
-            <h1>🚀 Task Queue Dashboard</h1>
-            <p>Sistema de Gerenciamento de Tarefas e Workflows</p>
-        </div>
-        
-        <div class="content">
-            <div class="grid">
-                <div class="card">
-                    <h3>📊 Overview</h3>
-                    <p>Visualize estatísticas gerais do sistema, tarefas recentes e métricas de performance.</p>
-                    <a href="/dashboard/" class="btn">Acessar Dashboard</a>
-                </div>
-                
-                <div class="card">
-                    <h3>📋 Tasks</h3>
-                    <p>Gerencie tarefas individuais, monitore status e execute operações avançadas.</p>
-                    <a href="/dashboard/" class="btn">Gerenciar Tarefas</a>
-                </div>
-                
-                <div class="card">
-                    <h3>🔄 Workflows</h3>
-                    <p>Visualize e controle workflows complexos com dependências e correlações.</p>
-                    <a href="/dashboard/" class="btn">Ver Workflows</a>
-                </div>
-                
-                <div class="card">
-                    <h3>📈 Metrics</h3>
-                    <p>Acompanhe métricas em tempo real, performance e utilização de recursos.</p>
-                    <a href="/dashboard/" class="btn">Ver Métricas</a>
-                </div>
-            </div>
-            
-            <div class="api-info">
-                <h3>🔧 API Endpoints</h3>
-                <div class="endpoint">GET /health - Status do servidor</div>
-                <div class="endpoint">GET /stats - Estatísticas do sistema</div>
-                <div class="endpoint">GET /tasks - Listar tarefas</div>
-                <div class="endpoint">POST /tasks - Criar tarefa</div>
-                <div class="endpoint">GET /workflows - Listar workflows</div>
-                <div class="endpoint">GET /metrics - Métricas detalhadas</div>
-            </div>
-        </div>
-    </div>
-</body>
-</html>
-    "#)
+    }
 }
 
-// Project handlers
-
-/// Create a new project
-async fn create_project(
+pub async fn get_view(
     State(server): State<Arc<TaskQueueServer>>,
-    Json(payload): Json<serde_json::Value>,
-) -> std::result::Result<Json<serde_json::Value>, StatusCode> {
-    let name = payload.get("name")
-        .and_then(|n| n.as_str())
-        .ok_or(StatusCode::BAD_REQUEST)?;
-    
-    let description = payload.get("description")
-        .and_then(|d| d.as_str())
-        .map(|s| s.to_string());
-
-    match server.create_project(name.to_string(), description).await {
-        Ok(project_id) => Ok(Json(json!({
-            "id": project_id,
-            "status": "created"
-        }))),
+    Path(name): Path<String>,
+) -> std::result::Result<Json<SavedView>, StatusCode> {
+    match server.get_view(&name).await {
+        Ok(Some(view)) => Ok(Json(view)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
         Err(e) => {
-            error!("Failed to create project: {}", e);
+            error!("Failed to get view: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
 }
 
-/// List all projects
-async fn list_projects(
+/// Create or replace a saved view
+pub async fn save_view(
     State(server): State<Arc<TaskQueueServer>>,
-) -> std::result::Result<Json<Vec<Project>>, StatusCode> {
-    match server.list_projects().await {
-        Ok(projects) => Ok(Json(projects)),
+    Path(name): Path<String>,
+    Json(request): Json<SaveViewRequest>,
+) -> std::result::Result<Json<SavedView>, StatusCode> {
+    match server.save_view(&name, request).await {
+        Ok(view) => Ok(Json(view)),
+        Err(TaskQueueError::ValidationError { .. }) => Err(StatusCode::BAD_REQUEST),
         Err(e) => {
-            error!("Failed to list projects: {}", e);
+            error!("Failed to save view: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
 }
 
-/// Get project by ID
-async fn get_project(
+pub async fn list_queues(
     State(server): State<Arc<TaskQueueServer>>,
-    Path(project_id): Path<String>,
-) -> std::result::Result<Json<Project>, StatusCode> {
-    let project_id = match uuid::Uuid::parse_str(&project_id) {
-        Ok(id) => id,
-        Err(_) => return Err(StatusCode::BAD_REQUEST),
-    };
-
-    match server.get_project(&project_id).await {
-        Ok(Some(project)) => Ok(Json(project)),
-        Ok(None) => Err(StatusCode::NOT_FOUND),
+) -> std::result::Result<Json<Vec<QueueStatus>>, StatusCode> {
+    match server.list_queues().await {
+        Ok(queues) => Ok(Json(queues)),
         Err(e) => {
-            error!("Failed to get project: {}", e);
+            error!("Failed to list queues: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
 }
 
-/// Update project
-async fn update_project(
+/// Create a named queue; 409 if the name is taken
+pub async fn create_queue(
     State(server): State<Arc<TaskQueueServer>>,
-    Path(project_id): Path<String>,
-    Json(payload): Json<serde_json::Value>,
-) -> std::result::Result<Json<serde_json::Value>, StatusCode> {
-    let project_id = match uuid::Uuid::parse_str(&project_id) {
-        Ok(id) => id,
-        Err(_) => return Err(StatusCode::BAD_REQUEST),
-    };
-
-    let updates = ProjectUpdate {
-        name: payload.get("name").and_then(|n| n.as_str()).map(|s| s.to_string()),
-        description: payload.get("description").and_then(|d| d.as_str()).map(|s| s.to_string()),
-        status: payload.get("status").and_then(|s| s.as_str()).and_then(|s| match s {
-            "Planning" => Some(ProjectStatus::Planning),
-            "Active" => Some(ProjectStatus::Active),
-            "OnHold" => Some(ProjectStatus::OnHold),
-            "Completed" => Some(ProjectStatus::Completed),
-            "Cancelled" => Some(ProjectStatus::Cancelled),
-            _ => None,
-        }),
-        tags: payload.get("tags").and_then(|t| t.as_array()).map(|arr| {
-            arr.iter().filter_map(|v| v.as_str()).map(|s| s.to_string()).collect()
-        }),
-    };
-
-    match server.update_project(&project_id, updates).await {
-        Ok(()) => Ok(Json(json!({"status": "updated"}))),
+    Valid(request): Valid<CreateQueueRequest>,
+) -> std::result::Result<(StatusCode, Json<Queue>), StatusCode> {
+    match server.create_queue(request).await {
+        Ok(Some(queue)) => Ok((StatusCode::CREATED, Json(queue))),
+        Ok(None) => Err(StatusCode::CONFLICT),
         Err(e) => {
-            error!("Failed to update project: {}", e);
+            error!("Failed to create queue: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
 }
 
-/// Delete project
-async fn delete_project(
+pub async fn get_queue(
     State(server): State<Arc<TaskQueueServer>>,
-    Path(project_id): Path<String>,
-) -> std::result::Result<Json<serde_json::Value>, StatusCode> {
-    let project_id = match uuid::Uuid::parse_str(&project_id) {
-        Ok(id) => id,
-        Err(_) => return Err(StatusCode::BAD_REQUEST),
-    };
-
-    match server.delete_project(&project_id).await {
-        Ok(()) => Ok(Json(json!({"status": "deleted"}))),
+    Path(name): Path<String>,
+) -> std::result::Result<Json<QueueStatus>, StatusCode> {
+    match server.get_queue(&name).await {
+        Ok(Some(queue)) => Ok(Json(queue)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
         Err(e) => {
-            error!("Failed to delete project: {}", e);
+            error!("Failed to get queue: {}", e);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
 }
 
-/// Get tasks by project
-async fn get_project_tasks(
+/// Create a queue or replace its settings
+pub async fn save_queue(
     State(server): State<Arc<TaskQueueServer>>,
-    Path(project_id): Path<String>,
-) -> std::result::Result<Json<Vec<Task>>, StatusCode> {
-    let project_id = match uuid::Uuid::parse_str(&project_id) {
-        Ok(id) => id,
-        Err(_) => return Err(StatusCode::BAD_REQUEST),
-    };
+    Path(name): Path<String>,
+    Json(mut request): Json<CreateQueueRequest>,
+) -> std::result::Result<Json<Queue>, Response> {
+    request.name = name;
+    let errors = request.validate();
+    if !errors.is_empty() {
+        return Err(crate::validation::reject(errors));
+    }
+    match server.save_queue(request).await {
+        Ok(queue) => Ok(Json(queue)),
+        Err(e) => {
+            error!("Failed to save queue: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR.into_response())
+        }
+    }
+}
 
-    match server.get_tasks_by_project(&project_id).await {
-        Ok(tasks) => Ok(Json(tasks)),
+pub async fn delete_queue(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(name): Path<String>,
+) -> StatusCode {
+    match server.delete_queue(&name).await {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
         Err(e) => {
-            error!("Failed to get project tasks: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            error!("Failed to delete queue: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
         }
     }
 }
+
+pub async fn delete_view(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(name): Path<String>,
+) -> StatusCode {
+    match server.delete_view(&name).await {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => {
+            error!("Failed to delete view: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// List the tasks a saved view shows
+pub async fn get_view_tasks(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(name): Path<String>,
+) -> std::result::Result<Json<Vec<Task>>, StatusCode> {
+    match server.view_tasks(&name).await {
+        Ok(Some(tasks)) => Ok(Json(tasks)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to list view tasks: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn list_recurrences(
+    State(server): State<Arc<TaskQueueServer>>,
+) -> std::result::Result<Json<Vec<RecurringTask>>, StatusCode> {
+    match server.list_recurrences().await {
+        Ok(recurrences) => Ok(Json(recurrences)),
+        Err(e) => {
+            error!("Failed to list recurrences: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn create_recurrence(
+    State(server): State<Arc<TaskQueueServer>>,
+    Json(request): Json<CreateRecurrenceRequest>,
+) -> std::result::Result<(StatusCode, Json<RecurringTask>), StatusCode> {
+    match server.create_recurrence(request).await {
+        Ok(recurrence) => Ok((StatusCode::CREATED, Json(recurrence))),
+        Err(TaskQueueError::ValidationError { .. } | TaskQueueError::InvalidTaskDefinition { .. }) => Err(StatusCode::BAD_REQUEST),
+        Err(e) => {
+            error!("Failed to create recurrence: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn get_recurrence(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(recurrence_id): Path<String>,
+) -> std::result::Result<Json<RecurringTask>, StatusCode> {
+    let recurrence_id = uuid::Uuid::parse_str(&recurrence_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    match server.get_recurrence(recurrence_id).await {
+        Ok(Some(recurrence)) => Ok(Json(recurrence)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to get recurrence: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn delete_recurrence(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(recurrence_id): Path<String>,
+) -> StatusCode {
+    let Ok(recurrence_id) = uuid::Uuid::parse_str(&recurrence_id) else {
+        return StatusCode::BAD_REQUEST;
+    };
+    match server.delete_recurrence(recurrence_id).await {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => {
+            error!("Failed to delete recurrence: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+async fn set_recurrence_paused(
+    server: Arc<TaskQueueServer>,
+    recurrence_id: String,
+    paused: bool,
+) -> std::result::Result<Json<RecurringTask>, StatusCode> {
+    let recurrence_id = uuid::Uuid::parse_str(&recurrence_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    match server.set_recurrence_paused(recurrence_id, paused).await {
+        Ok(Some(recurrence)) => Ok(Json(recurrence)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to update recurrence: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Stop creating tasks from a recurrence
+pub async fn pause_recurrence(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(recurrence_id): Path<String>,
+) -> std::result::Result<Json<RecurringTask>, StatusCode> {
+    set_recurrence_paused(server, recurrence_id, true).await
+}
+
+/// Resume a paused recurrence from its next run after now
+pub async fn resume_recurrence(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(recurrence_id): Path<String>,
+) -> std::result::Result<Json<RecurringTask>, StatusCode> {
+    set_recurrence_paused(server, recurrence_id, false).await
+}
+
+/// Lease the next runnable task to an external worker; 204 if there is none
+pub async fn claim_task(
+    State(server): State<Arc<TaskQueueServer>>,
+    Json(request): Json<ClaimRequest>,
+) -> std::result::Result<Response, StatusCode> {
+    if request.worker_id.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    match server.claim_next_task(&request.worker_id, request.lease_secs).await {
+        Ok(Some(task)) => Ok(Json(task).into_response()),
+        Ok(None) => Ok(StatusCode::NO_CONTENT.into_response()),
+        Err(e) => {
+            error!("Failed to claim task: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+fn lease_error(e: TaskQueueError) -> StatusCode {
+    match e {
+        TaskQueueError::TaskNotFound { .. } => StatusCode::NOT_FOUND,
+        TaskQueueError::LeaseNotHeld { .. } => StatusCode::CONFLICT,
+        e => {
+            error!("Failed to update task lease: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// Extend a worker's lease on a task
+pub async fn renew_task_lease(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(task_id): Path<String>,
+    Json(request): Json<HeartbeatRequest>,
+) -> std::result::Result<Json<Lease>, StatusCode> {
+    let task_id = uuid::Uuid::parse_str(&task_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    server.renew_lease(task_id, &request.worker_id, request.lease_secs).await
+        .map(Json)
+        .map_err(lease_error)
+}
+
+/// Report the result of a leased task
+pub async fn complete_leased_task(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(task_id): Path<String>,
+    Json(request): Json<CompleteRequest>,
+) -> std::result::Result<Json<Task>, StatusCode> {
+    let task_id = uuid::Uuid::parse_str(&task_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    server.complete_leased_task(task_id, &request.worker_id, request.result).await
+        .map(Json)
+        .map_err(lease_error)
+}
+
+/// Register an external worker and its labels
+pub async fn register_worker(
+    State(server): State<Arc<TaskQueueServer>>,
+    Json(request): Json<RegisterWorker>,
+) -> std::result::Result<Json<Worker>, StatusCode> {
+    match server.register_worker(request).await {
+        Ok(worker) => Ok(Json(worker)),
+        Err(TaskQueueError::ValidationError { .. }) => Err(StatusCode::BAD_REQUEST),
+        Err(e) => {
+            error!("Failed to register worker: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn list_workers(
+    State(server): State<Arc<TaskQueueServer>>,
+) -> std::result::Result<Json<Vec<WorkerStatus>>, StatusCode> {
+    match server.list_workers().await {
+        Ok(workers) => Ok(Json(workers)),
+        Err(e) => {
+            error!("Failed to list workers: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// List permanently failed tasks
+pub async fn list_dead_letters(
+    State(server): State<Arc<TaskQueueServer>>,
+) -> std::result::Result<Json<Vec<DeadLetter>>, StatusCode> {
+    match server.list_dead_letters().await {
+        Ok(entries) => Ok(Json(entries)),
+        Err(e) => {
+            error!("Failed to list dead letters: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Move a dead-lettered task back to `Pending`
+pub async fn requeue_dead_letter(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(task_id): Path<String>,
+) -> std::result::Result<Json<Task>, StatusCode> {
+    let task_id = uuid::Uuid::parse_str(&task_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    match server.requeue_dead_letter(task_id).await {
+        Ok(Some(task)) => Ok(Json(task)),
+        Ok(None) | Err(TaskQueueError::TaskNotFound { .. }) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to requeue dead letter: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+fn secret_error(e: TaskQueueError) -> StatusCode {
+    match e {
+        TaskQueueError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+        TaskQueueError::ConfigurationError(_) => StatusCode::SERVICE_UNAVAILABLE,
+        e => {
+            error!("Failed to update secret: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// List secret names; values are never returned
+pub async fn list_secrets(
+    State(server): State<Arc<TaskQueueServer>>,
+) -> std::result::Result<Json<Vec<SecretInfo>>, StatusCode> {
+    match server.list_secrets().await {
+        Ok(secrets) => Ok(Json(secrets)),
+        Err(e) => {
+            error!("Failed to list secrets: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Create or replace a secret
+pub async fn set_secret(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(name): Path<String>,
+    Json(request): Json<SetSecretRequest>,
+) -> std::result::Result<Json<SecretInfo>, StatusCode> {
+    server.set_secret(&name, &request.value).await
+        .map(Json)
+        .map_err(secret_error)
+}
+
+pub async fn delete_secret(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(name): Path<String>,
+) -> StatusCode {
+    match server.delete_secret(&name).await {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => {
+            error!("Failed to delete secret: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// Get system stats
+/// Task activity per `bucket` over the `window` ending now
+pub async fn get_stats_timeseries(
+    State(server): State<Arc<TaskQueueServer>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> std::result::Result<Json<Timeseries>, Response> {
+    let query = TimeseriesQuery::parse(params.get("window").map(String::as_str), params.get("bucket").map(String::as_str))
+        .map_err(|error| (StatusCode::BAD_REQUEST, Json(json!({ "error": error }))).into_response())?;
+    match server.task_timeseries(&query).await {
+        Ok(series) => Ok(Json(series)),
+        Err(e) => {
+            error!("Failed to compute task time series: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR.into_response())
+        }
+    }
+}
+
+pub async fn get_stats(
+    State(server): State<Arc<TaskQueueServer>>,
+) -> Json<Value> {
+    let tasks = server.task_snapshot().await.unwrap_or_default();
+    let workflows = server.workflow_snapshot().await.unwrap_or_default();
+    
+    let total_tasks = tasks.len();
+    let active_tasks = tasks.iter().filter(|t| {
+        let effective_status = TaskQueueServer::get_effective_task_status(t);
+        effective_status == crate::core::TaskStatus::Running
+    }).count();
+    let pending_tasks = tasks.iter().filter(|t| {
+        let effective_status = TaskQueueServer::get_effective_task_status(t);
+        effective_status == crate::core::TaskStatus::Pending
+    }).count();
+    let completed_tasks = tasks.iter().filter(|t| {
+        info!("Checking task: {}", t.name);
+        let effective_status = TaskQueueServer::get_effective_task_status(t);
+        info!("Task {} effective status: {:?}", t.name, effective_status);
+        let is_completed = effective_status == crate::core::TaskStatus::Completed;
+        info!("Is completed: {}", is_completed);
+        if is_completed {
+            info!("Task {} is completed!", t.name);
+        }
+        is_completed
+    }).count();
+    let failed_tasks = tasks.iter().filter(|t| {
+        let effective_status = TaskQueueServer::get_effective_task_status(t);
+        effective_status == crate::core::TaskStatus::Failed
+    }).count();
+    let total_workflows = workflows.len();
+    
+    Json(json!({
+        "total_tasks": total_tasks,
+        "active_tasks": active_tasks,
+        "pending_tasks": pending_tasks,
+        "completed_tasks": completed_tasks,
+        "failed_tasks": failed_tasks,
+        "total_workflows": total_workflows,
+        "cpu_usage_percent": 0.0,
+        "memory_usage_mb": 0.0,
+        "uptime_seconds": 0,
+        "timestamp": chrono::Utc::now().to_rfc3339()
+    }))
+}
+
+/// Add dependency to a task
+pub async fn add_task_dependency(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(task_id): Path<String>,
+    Json(request): Json<serde_json::Value>,
+) -> std::result::Result<Json<Value>, StatusCode> {
+    let task_id = match uuid::Uuid::parse_str(&task_id) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+    
+    let dependency_task_id = match request.get("dependency_task_id").and_then(|v| v.as_str()) {
+        Some(id) => match uuid::Uuid::parse_str(id) {
+            Ok(id) => id,
+            Err(_) => return Err(StatusCode::BAD_REQUEST),
+        },
+        None => return Err(StatusCode::BAD_REQUEST),
+    };
+    
+    let task_name = request.get("task_name").and_then(|v| v.as_str()).map(|s| s.to_string());
+    let condition = match request.get("condition").and_then(|v| v.as_str()) {
+        Some("Success") => crate::core::DependencyCondition::Success,
+        Some("Failure") => crate::core::DependencyCondition::Failure,
+        Some("Completion") => crate::core::DependencyCondition::Completion,
+        // dependency_task_id names a task group
+        Some("Group") => crate::core::DependencyCondition::Group {
+            quorum: request.get("quorum").and_then(|v| v.as_u64()).map(|q| q as u32),
+        },
+        _ => crate::core::DependencyCondition::Success,
+    };
+    let required = request.get("required").and_then(|v| v.as_bool()).unwrap_or(true);
+    let correlation_id = request.get("correlation_id").and_then(|v| v.as_str()).map(|s| s.to_string());
+    
+    match server.add_task_dependency(task_id, dependency_task_id, task_name, condition, required, correlation_id).await {
+        Ok(_) => Ok(Json(json!({
+            "message": "Dependency added successfully",
+            "task_id": task_id
+        }))),
+        Err(TaskQueueError::CircularDependency { .. }) => Err(StatusCode::BAD_REQUEST),
+        Err(_) => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+/// Get task dependencies
+pub async fn get_task_dependencies(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(task_id): Path<String>,
+) -> std::result::Result<Json<Value>, StatusCode> {
+    let task_id = match uuid::Uuid::parse_str(&task_id) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+    
+    match server.get_task_dependencies(task_id).await {
+        Ok(dependencies) => Ok(Json(json!(dependencies))),
+        Err(_) => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+/// Advance task development phase
+pub async fn advance_task_phase(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(task_id): Path<String>,
+) -> std::result::Result<Json<Value>, StatusCode> {
+    let task_id = match uuid::Uuid::parse_str(&task_id) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+    
+    match server.advance_task_phase(task_id).await {
+        Ok(advanced) => Ok(Json(json!({
+            "advanced": advanced,
+            "task_id": task_id
+        }))),
+        Err(_) => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+/// Set task status
+pub async fn set_task_status(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(task_id): Path<String>,
+    Json(payload): Json<serde_json::Value>,
+) -> std::result::Result<Json<Value>, StatusCode> {
+    let task_id = match uuid::Uuid::parse_str(&task_id) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    let status_str = payload.get("status")
+        .and_then(|s| s.as_str())
+        .ok_or(StatusCode::BAD_REQUEST)?;
+
+    let new_status = match status_str {
+        "Planning" => TaskStatus::Planning,
+        "Implementation" => TaskStatus::Implementation,
+        "TestCreation" => TaskStatus::TestCreation,
+        "Testing" => TaskStatus::Testing,
+        "AIReview" => TaskStatus::AIReview,
+        "Finalized" => TaskStatus::Finalized,
+        "Cancelled" => TaskStatus::Cancelled,
+        "Failed" => TaskStatus::Failed,
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    match server.set_task_status(task_id, new_status).await {
+        Ok(()) => Ok(Json(json!({
+            "status": "updated",
+            "message": "Task status updated successfully"
+        }))),
+        Err(e) => {
+            error!("Failed to update task status: {}", e);
+            Err(StatusCode::BAD_REQUEST)
+        }
+    }
+}
+
+/// Get task correlations
+pub async fn get_task_correlations(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(task_id): Path<String>,
+) -> std::result::Result<Json<Value>, StatusCode> {
+    let task_id = match uuid::Uuid::parse_str(&task_id) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+    
+    match server.get_task_correlations(task_id).await {
+        Ok(correlations) => Ok(Json(json!(correlations))),
+        Err(_) => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+/// Get workflow result
+pub async fn get_workflow_result(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(workflow_id): Path<String>,
+) -> std::result::Result<Json<Value>, StatusCode> {
+    let workflow_id = match uuid::Uuid::parse_str(&workflow_id) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+    
+    match server.get_workflow(workflow_id).await {
+        Ok(workflow) => Ok(Json(json!({
+            "workflow_id": workflow.id,
+            "status": workflow.status,
+            "result": "No result field in workflow struct"
+        }))),
+        Err(_) => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+/// Cancel workflow
+pub async fn cancel_workflow(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(workflow_id): Path<String>,
+    Json(payload): Json<serde_json::Value>,
+) -> std::result::Result<Json<Value>, StatusCode> {
+    let workflow_id = match uuid::Uuid::parse_str(&workflow_id) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+    
+    let reason = payload.get("reason")
+        .and_then(|r| r.as_str())
+        .unwrap_or("User requested cancellation");
+    
+    match server.cancel_workflow(workflow_id, reason.to_string()).await {
+        Ok(_) => Ok(Json(json!({
+            "message": "Workflow cancelled successfully",
+            "workflow_id": workflow_id
+        }))),
+        Err(_) => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+/// Approve workflow
+pub async fn approve_workflow(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(workflow_id): Path<String>,
+    Json(payload): Json<serde_json::Value>,
+) -> std::result::Result<Json<Value>, StatusCode> {
+    let workflow_id = match uuid::Uuid::parse_str(&workflow_id) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+    
+    let message = payload.get("message")
+        .and_then(|m| m.as_str())
+        .unwrap_or("Workflow approved");
+    
+    match server.approve_workflow(workflow_id, message.to_string()).await {
+        Ok(_) => Ok(Json(json!({
+            "message": "Workflow approved successfully",
+            "workflow_id": workflow_id
+        }))),
+        Err(_) => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+/// Update workflow status
+pub async fn update_workflow_status(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(workflow_id): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<serde_json::Value>,
+) -> std::result::Result<Json<Value>, StatusCode> {
+    let workflow_id = match uuid::Uuid::parse_str(&workflow_id) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+    
+    let status_str = payload.get("status")
+        .and_then(|s| s.as_str())
+        .unwrap_or("Pending");
+    
+    let status = match status_str {
+        "Pending" => crate::core::WorkflowStatus::Pending,
+        "Running" => crate::core::WorkflowStatus::Running,
+        "Completed" => crate::core::WorkflowStatus::Completed,
+        "Failed" => crate::core::WorkflowStatus::Failed,
+        "Cancelled" => crate::core::WorkflowStatus::Cancelled,
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+    
+    let message = payload.get("message")
+        .and_then(|m| m.as_str())
+        .unwrap_or("Status updated");
+    
+    match server.update_workflow_status(workflow_id, status, message.to_string(), &Precondition::from_headers(&headers)).await {
+        Ok(_) => Ok(Json(json!({
+            "message": "Workflow status updated successfully",
+            "workflow_id": workflow_id
+        }))),
+        Err(TaskQueueError::PreconditionFailed { .. }) => Err(StatusCode::PRECONDITION_FAILED),
+        Err(_) => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+/// List workflows
+pub async fn list_workflows(
+    State(server): State<Arc<TaskQueueServer>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> std::result::Result<Json<Vec<Workflow>>, StatusCode> {
+    let project = params.get("project").cloned();
+    let status = params.get("status").cloned();
+    
+    match server.list_workflows(project, status).await {
+        Ok(workflows) => Ok(Json(workflows)),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+/// The OpenAPI document of the REST API
+pub async fn get_openapi() -> Json<Value> {
+    Json(openapi::spec())
+}
+
+/// Swagger UI for the OpenAPI document
+pub async fn serve_docs() -> Html<&'static str> {
+    Html(openapi::SWAGGER_UI)
+}
+
+/// Serve dashboard HTML
+pub async fn serve_dashboard() -> Html<&'static str> {
+    Html(r#"
+<!DOCTYPE html>
+<html lang="pt-BR">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Task Queue Dashboard</title>
+    <style>
+        body {
+            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif;
+            margin: 0;
+            padding: 20px;
+            background: linear-gradient(135deg, #667eea 0%, #764ba2 100%);
+            min-height: 100vh;
+        }
+        .container {
+            max-width: 1200px;
+            margin: 0 auto;
+            background: white;
+            border-radius: 12px;
+            box-shadow: 0 20px 40px rgba(0,0,0,0.1);
+            overflow: hidden;
+        }
+        .header {
+            background: linear-gradient(135deg, #667eea 0%, #764ba2 100%);
+            color: white;
+            padding: 30px;
+            text-align: center;
+        }
+        .header h1 {
+            margin: 0;
+            font-size: 2.5em;
+            font-weight: 300;
+        }
+        .header p {
+            margin: 10px 0 0 0;
+            opacity: 0.9;
+        }
+        .content {
+            padding: 40px;
+        }
+        .grid {
+            display: grid;
+            grid-template-columns: repeat(auto-fit, minmax(300px, 1fr));
+            gap: 30px;
+            margin-bottom: 40px;
+        }
+        .card {
+            background: #f8f9fa;
+            border-radius: 8px;
+            padding: 25px;
+            border-left: 4px solid #667eea;
+        }
+        .card h3 {
+            margin: 0 0 15px 0;
+            color: #333;
+        }
+        .card p {
+            margin: 0 0 20px 0;
+            color: #666;
+            line-height: 1.6;
+        }
+        .btn {
+            display: inline-block;
+            background: #667eea;
+            color: white;
+            padding: 12px 24px;
+            text-decoration: none;
+            border-radius: 6px;
+            font-weight: 500;
+            transition: all 0.3s ease;
+        }
+        .btn:hover {
+            background: #5a6fd8;
+            transform: translateY(-2px);
+        }
+        .api-info {
+            background: #e3f2fd;
+            border-radius: 8px;
+            padding: 20px;
+            margin-top: 30px;
+        }
+        .api-info h3 {
+            margin: 0 0 15px 0;
+            color: #1976d2;
+        }
+        .endpoint {
+            background: white;
+            padding: 10px 15px;
+            margin: 8px 0;
+            border-radius: 4px;
+            font-family: 'Courier New', monospace;
+            font-size: 14px;
+            border-left: 3px solid #1976d2;
+        }
+    </style>
+</head>
+<body>
+    <div class="container">
+        <div class="header">
+            <h1>🚀 Task Queue Dashboard</h1>
+            <p>Sistema de Gerenciamento de Tarefas e Workflows</p>
+        </div>
+        
+        <div class="content">
+            <div class="grid">
+                <div class="card">
+                    <h3>📊 Overview</h3>
+                    <p>Visualize estatísticas gerais do sistema, tarefas recentes e métricas de performance.</p>
+                    <a href="/dashboard/" class="btn">Acessar Dashboard</a>
+                </div>
+                
+                <div class="card">
+                    <h3>📋 Tasks</h3>
+                    <p>Gerencie tarefas individuais, monitore status e execute operações avançadas.</p>
+                    <a href="/dashboard/" class="btn">Gerenciar Tarefas</a>
+                </div>
+                
+                <div class="card">
+                    <h3>🔄 Workflows</h3>
+                    <p>Visualize e controle workflows complexos com dependências e correlações.</p>
+                    <a href="/dashboard/" class="btn">Ver Workflows</a>
+                </div>
+                
+                <div class="card">
+                    <h3>📈 Metrics</h3>
+                    <p>Acompanhe métricas em tempo real, performance e utilização de recursos.</p>
+                    <a href="/dashboard/" class="btn">Ver Métricas</a>
+                </div>
+            </div>
+            
+            <div class="api-info">
+                <h3>🔧 API Endpoints</h3>
+                <div class="endpoint">GET /health - Status do servidor</div>
+                <div class="endpoint">GET /stats - Estatísticas do sistema</div>
+                <div class="endpoint">GET /tasks - Listar tarefas</div>
+                <div class="endpoint">POST /tasks - Criar tarefa</div>
+                <div class="endpoint">GET /workflows - Listar workflows</div>
+                <div class="endpoint">GET /metrics - Métricas detalhadas</div>
+            </div>
+        </div>
+    </div>
+</body>
+</html>
+    "#)
+}
+
+// Project handlers
+
+async fn create_tenant(
+    State(server): State<Arc<TaskQueueServer>>,
+    Valid(request): Valid<CreateTenantRequest>,
+) -> std::result::Result<(StatusCode, Json<Tenant>), StatusCode> {
+    match server.create_tenant(request.name, request.description).await {
+        Ok(tenant) => Ok((StatusCode::CREATED, Json(tenant))),
+        Err(e) => {
+            error!("Failed to create tenant: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn list_tenants(
+    State(server): State<Arc<TaskQueueServer>>,
+) -> std::result::Result<Json<Vec<Tenant>>, StatusCode> {
+    match server.list_tenants().await {
+        Ok(tenants) => Ok(Json(tenants)),
+        Err(e) => {
+            error!("Failed to list tenants: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn get_tenant(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(tenant_id): Path<String>,
+) -> std::result::Result<Json<Tenant>, StatusCode> {
+    let tenant_id = uuid::Uuid::parse_str(&tenant_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    match server.get_tenant(&tenant_id).await {
+        Ok(tenant) => Ok(Json(tenant)),
+        Err(TaskQueueError::TenantNotFound { .. }) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to get tenant: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn create_tenant_key(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(tenant_id): Path<String>,
+    Valid(request): Valid<CreateTenantKeyRequest>,
+) -> std::result::Result<(StatusCode, Json<IssuedKey>), StatusCode> {
+    let tenant_id = uuid::Uuid::parse_str(&tenant_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    match server.issue_tenant_key(&tenant_id, request.name, request.scopes).await {
+        Ok(key) => Ok((StatusCode::CREATED, Json(key))),
+        Err(TaskQueueError::TenantNotFound { .. }) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to issue API key: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn list_tenant_keys(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(tenant_id): Path<String>,
+) -> std::result::Result<Json<Vec<TenantKey>>, StatusCode> {
+    let tenant_id = uuid::Uuid::parse_str(&tenant_id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    match server.list_tenant_keys(&tenant_id).await {
+        Ok(keys) => Ok(Json(keys)),
+        Err(TaskQueueError::TenantNotFound { .. }) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to list API keys: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn revoke_tenant_key(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path((tenant_id, key_id)): Path<(String, String)>,
+) -> StatusCode {
+    let (Ok(tenant_id), Ok(key_id)) = (uuid::Uuid::parse_str(&tenant_id), uuid::Uuid::parse_str(&key_id)) else {
+        return StatusCode::BAD_REQUEST;
+    };
+    match server.revoke_tenant_key(&tenant_id, &key_id).await {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) | Err(TaskQueueError::TenantNotFound { .. }) => StatusCode::NOT_FOUND,
+        Err(e) => {
+            error!("Failed to revoke API key: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// Create a new project
+async fn create_project(
+    State(server): State<Arc<TaskQueueServer>>,
+    Valid(request): Valid<crate::core::CreateProjectRequest>,
+) -> std::result::Result<Json<serde_json::Value>, StatusCode> {
+    match server.create_project(request.name, request.description).await {
+        Ok(project_id) => Ok(Json(json!({
+            "id": project_id,
+            "status": "created"
+        }))),
+        Err(e) => {
+            error!("Failed to create project: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// List all projects
+async fn list_projects(
+    State(server): State<Arc<TaskQueueServer>>,
+) -> std::result::Result<Json<Vec<Project>>, StatusCode> {
+    match server.list_projects().await {
+        Ok(projects) => Ok(Json(projects)),
+        Err(e) => {
+            error!("Failed to list projects: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Get project by ID
+async fn get_project(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(project_id): Path<String>,
+    headers: HeaderMap,
+) -> std::result::Result<Response, StatusCode> {
+    let project_id = match uuid::Uuid::parse_str(&project_id) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    match server.get_project(&project_id).await {
+        Ok(Some(project)) => Ok(etag::respond(&headers, project.version, project)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to get project: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Update project
+async fn update_project(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(project_id): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<serde_json::Value>,
+) -> std::result::Result<Json<serde_json::Value>, StatusCode> {
+    let project_id = match uuid::Uuid::parse_str(&project_id) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    let updates = ProjectUpdate {
+        name: payload.get("name").and_then(|n| n.as_str()).map(|s| s.to_string()),
+        description: payload.get("description").and_then(|d| d.as_str()).map(|s| s.to_string()),
+        status: payload.get("status").and_then(|s| s.as_str()).and_then(|s| match s {
+            "Planning" => Some(ProjectStatus::Planning),
+            "Active" => Some(ProjectStatus::Active),
+            "OnHold" => Some(ProjectStatus::OnHold),
+            "Completed" => Some(ProjectStatus::Completed),
+            "Cancelled" => Some(ProjectStatus::Cancelled),
+            _ => None,
+        }),
+        tags: payload.get("tags").and_then(|t| t.as_array()).map(|arr| {
+            arr.iter().filter_map(|v| v.as_str()).map(|s| s.to_string()).collect()
+        }),
+        metadata: payload.get("metadata").and_then(|m| m.as_object()).map(|m| {
+            m.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+        }),
+    };
+
+    match server.update_project(&project_id, updates, &Precondition::from_headers(&headers)).await {
+        Ok(()) => Ok(Json(json!({"status": "updated"}))),
+        Err(TaskQueueError::ProjectNotFound { .. }) => Err(StatusCode::NOT_FOUND),
+        Err(TaskQueueError::PreconditionFailed { .. }) => Err(StatusCode::PRECONDITION_FAILED),
+        Err(e) => {
+            error!("Failed to update project: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Delete project
+async fn delete_project(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(project_id): Path<String>,
+) -> std::result::Result<Json<serde_json::Value>, StatusCode> {
+    let project_id = match uuid::Uuid::parse_str(&project_id) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    match server.delete_project(&project_id).await {
+        Ok(()) => Ok(Json(json!({"status": "deleted"}))),
+        Err(e) => {
+            error!("Failed to delete project: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Get a project's task counts and completion estimate
+async fn get_project_stats(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(project_id): Path<String>,
+) -> std::result::Result<Json<ProjectStats>, StatusCode> {
+    let project_id = match uuid::Uuid::parse_str(&project_id) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    match server.project_stats(&project_id).await {
+        Ok(stats) => Ok(Json(stats)),
+        Err(TaskQueueError::ProjectNotFound { .. }) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to compute project stats: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Get a project's calendar feed
+async fn get_project_calendar(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(project_id): Path<String>,
+) -> std::result::Result<impl IntoResponse, StatusCode> {
+    let project_id = match uuid::Uuid::parse_str(&project_id) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    match server.project_calendar(&project_id).await {
+        Ok(ics) => Ok(([(axum::http::header::CONTENT_TYPE, "text/calendar; charset=utf-8")], ics)),
+        Err(TaskQueueError::ProjectNotFound { .. }) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to build project calendar: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct SnapshotRequest {
+    /// Upload here (e.g. a presigned S3 URL) instead of returning the snapshot
+    pub upload_url: Option<String>,
+}
+
+/// Take a consistent snapshot and download it, or upload it to object storage
+pub async fn create_snapshot(
+    State(server): State<Arc<TaskQueueServer>>,
+    request: Option<Json<SnapshotRequest>>,
+) -> std::result::Result<Response, StatusCode> {
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    let snapshot = Snapshot::capture(server.storage.as_ref()).await.map_err(|e| {
+        error!("Failed to take snapshot: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    if let Some(url) = request.upload_url {
+        return match snapshot.upload(&url).await {
+            Ok(()) => {
+                info!("Uploaded snapshot {}", snapshot.file_name());
+                Ok(Json(snapshot.manifest).into_response())
+            }
+            Err(e) => {
+                error!("Failed to upload snapshot: {}", e);
+                Err(StatusCode::BAD_GATEWAY)
+            }
+        };
+    }
+
+    let body = snapshot.to_vec().map_err(|e| {
+        error!("Failed to encode snapshot: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let disposition = format!("attachment; filename=\"{}\"", snapshot.file_name());
+    Ok((
+        [
+            (axum::http::header::CONTENT_TYPE, "application/json".to_string()),
+            (axum::http::header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    ).into_response())
+}
+
+/// Stream a tar archive of a consistent copy of all tasks, projects and workflows
+pub async fn create_backup(
+    State(server): State<Arc<TaskQueueServer>>,
+) -> std::result::Result<Response, StatusCode> {
+    let backup = Backup::capture(server.storage.as_ref()).await.map_err(|e| {
+        error!("Failed to take backup: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    info!("Streaming backup {}", backup.file_name());
+    let disposition = format!("attachment; filename=\"{}\"", backup.file_name());
+    Ok((
+        [
+            (axum::http::header::CONTENT_TYPE, "application/x-tar".to_string()),
+            (axum::http::header::CONTENT_DISPOSITION, disposition),
+        ],
+        axum::body::Body::from_stream(backup.into_stream()),
+    ).into_response())
+}
+
+/// Import the JSON Lines request body as tasks, a line at a time;
+/// `?dry_run=true` only validates
+pub async fn import_tasks(
+    State(server): State<Arc<TaskQueueServer>>,
+    Query(params): Query<HashMap<String, String>>,
+    body: axum::body::Body,
+) -> std::result::Result<Json<ImportReport>, StatusCode> {
+    use futures_util::TryStreamExt;
+
+    let dry_run = params.get("dry_run").map(String::as_str) == Some("true");
+    let stream = body.into_data_stream().map_err(std::io::Error::other);
+    let reader = tokio::io::BufReader::new(tokio_util::io::StreamReader::new(stream));
+
+    match server.import_tasks(reader, dry_run).await {
+        Ok(report) => Ok(Json(report)),
+        Err(e) => {
+            error!("Failed to import tasks: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Restore a backup archive sent as the request body; `mode` is `merge`
+/// (default) or `replace`
+pub async fn restore_backup(
+    State(server): State<Arc<TaskQueueServer>>,
+    Query(params): Query<HashMap<String, String>>,
+    body: axum::body::Bytes,
+) -> std::result::Result<Json<RestoreReport>, StatusCode> {
+    let mode = match params.get("mode") {
+        Some(mode) => RestoreMode::parse(mode).ok_or(StatusCode::BAD_REQUEST)?,
+        None => RestoreMode::default(),
+    };
+    let archive = BackupArchive::read(body.as_ref()).map_err(|e| {
+        warn!("Rejected backup archive: {}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+
+    match server.restore_backup(archive, mode).await {
+        Ok(report) => Ok(Json(report)),
+        Err(e) => {
+            error!("Failed to restore backup: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Check stored records; `?action=repair` or `?action=quarantine` also
+/// fixes what is broken
+pub async fn check_storage(
+    State(server): State<Arc<TaskQueueServer>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> std::result::Result<Json<FsckReport>, StatusCode> {
+    let action = match params.get("action") {
+        Some(action) => FsckAction::parse(action).ok_or(StatusCode::BAD_REQUEST)?,
+        None => FsckAction::default(),
+    };
+
+    match server.check_storage(action).await {
+        Ok(report) => Ok(Json(report)),
+        Err(e) => {
+            error!("Failed to check storage: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub async fn get_maintenance(
+    State(server): State<Arc<TaskQueueServer>>,
+) -> std::result::Result<Json<MaintenanceStatus>, StatusCode> {
+    match server.maintenance_status().await {
+        Ok(status) => Ok(Json(status)),
+        Err(e) => {
+            error!("Failed to read maintenance status: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Turn away new submissions with `503` until `/admin/resume`
+pub async fn enable_maintenance(
+    State(server): State<Arc<TaskQueueServer>>,
+) -> std::result::Result<Json<MaintenanceStatus>, StatusCode> {
+    server.maintenance().enable();
+    info!("Maintenance mode enabled");
+    get_maintenance(State(server)).await
+}
+
+/// Stop dispatching and wait for the running tasks to finish, up to
+/// `?timeout=` (default 30s, at most 5m): `200` once none runs, `202` if
+/// some still do
+pub async fn drain(
+    State(server): State<Arc<TaskQueueServer>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> std::result::Result<Response, StatusCode> {
+    let timeout = match params.get("timeout") {
+        Some(timeout) => parse_wait_timeout(timeout).ok_or(StatusCode::BAD_REQUEST)?.min(MAX_TASK_WAIT),
+        None => DEFAULT_TASK_WAIT,
+    };
+    info!("Draining: dispatching stopped");
+    match server.drain(timeout).await {
+        Ok(status) => {
+            let code = if status.drained() { StatusCode::OK } else { StatusCode::ACCEPTED };
+            Ok((code, Json(status)).into_response())
+        }
+        Err(e) => {
+            error!("Failed to drain: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Lift maintenance mode and draining
+pub async fn resume(
+    State(server): State<Arc<TaskQueueServer>>,
+) -> std::result::Result<Json<MaintenanceStatus>, StatusCode> {
+    server.maintenance().resume();
+    info!("Maintenance mode and draining lifted");
+    get_maintenance(State(server)).await
+}
+
+/// List recorded mutations; `?entity=` takes `task`, `project`,
+/// `workflow` or an entity ID and `?since=` an RFC 3339 timestamp
+pub async fn list_audit(
+    State(server): State<Arc<TaskQueueServer>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> std::result::Result<Json<Vec<AuditEntry>>, StatusCode> {
+    let mut query = AuditQuery::default();
+    if let Some(entity) = params.get("entity") {
+        query.entity = Some(EntityFilter::parse(entity).ok_or(StatusCode::BAD_REQUEST)?);
+    }
+    if let Some(since) = params.get("since") {
+        let since = chrono::DateTime::parse_from_rfc3339(since).map_err(|_| StatusCode::BAD_REQUEST)?;
+        query.since = Some(since.with_timezone(&chrono::Utc));
+    }
+    if let Some(limit) = params.get("limit") {
+        let limit = limit.parse::<usize>().map_err(|_| StatusCode::BAD_REQUEST)?;
+        query.limit = limit.clamp(1, audit::MAX_AUDIT_LIMIT);
+    }
+
+    match server.list_audit(&query).await {
+        Ok(entries) => Ok(Json(entries)),
+        Err(e) => {
+            error!("Failed to list audit entries: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Apply the retention policies now; `?dry_run=true` only reports
+pub async fn apply_retention(
+    State(server): State<Arc<TaskQueueServer>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> std::result::Result<Json<RetentionReport>, StatusCode> {
+    let dry_run = params.get("dry_run").map(String::as_str) == Some("true");
+
+    match server.apply_retention(std::time::SystemTime::now(), dry_run).await {
+        Ok(report) => Ok(Json(report)),
+        Err(e) => {
+            error!("Failed to apply task retention: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Move old completed tasks to cold storage now; `?dry_run=true` only reports
+pub async fn move_to_cold_storage(
+    State(server): State<Arc<TaskQueueServer>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> std::result::Result<Json<ColdStorageReport>, StatusCode> {
+    let dry_run = params.get("dry_run").map(String::as_str) == Some("true");
+
+    match server.move_to_cold_storage(std::time::SystemTime::now(), dry_run).await {
+        Ok(report) => Ok(Json(report)),
+        Err(e) => {
+            error!("Failed to move tasks to cold storage: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Compact storage now, returning its statistics afterwards
+pub async fn compact_storage(
+    State(server): State<Arc<TaskQueueServer>>,
+) -> std::result::Result<Json<StorageStats>, StatusCode> {
+    match server.compact_storage().await {
+        Ok(stats) => Ok(Json(stats)),
+        Err(e) => {
+            error!("Failed to compact storage: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Vacuum storage now; `?dry_run=true` only reports
+pub async fn vacuum_storage(
+    State(server): State<Arc<TaskQueueServer>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> std::result::Result<Json<VacuumReport>, StatusCode> {
+    let dry_run = params.get("dry_run").map(String::as_str) == Some("true");
+
+    match server.vacuum_storage(chrono::Utc::now(), dry_run).await {
+        Ok(report) => Ok(Json(report)),
+        Err(e) => {
+            error!("Failed to vacuum storage: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Get a task that retention archived
+pub async fn get_archived_task(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(task_id): Path<String>,
+) -> std::result::Result<Json<Task>, StatusCode> {
+    let task_id = match uuid::Uuid::parse_str(&task_id) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    match server.get_archived_task(task_id).await {
+        Ok(task) => Ok(Json(task)),
+        Err(TaskQueueError::TaskNotFound { .. }) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to load archived task: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Get a project's GitHub configuration, without the token
+async fn get_github_config(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(project_id): Path<String>,
+) -> std::result::Result<Json<GitHubConfig>, StatusCode> {
+    let project_id = match uuid::Uuid::parse_str(&project_id) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    match server.github_config(&project_id).await {
+        Ok(Some(config)) => Ok(Json(config.redacted())),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to load GitHub config: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Configure GitHub issue import for a project
+async fn configure_github(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(project_id): Path<String>,
+    Json(config): Json<GitHubConfig>,
+) -> std::result::Result<Json<GitHubConfig>, StatusCode> {
+    let project_id = match uuid::Uuid::parse_str(&project_id) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    match server.configure_github(&project_id, config.clone()).await {
+        Ok(()) => Ok(Json(config.redacted())),
+        Err(TaskQueueError::ProjectNotFound { .. }) => Err(StatusCode::NOT_FOUND),
+        Err(TaskQueueError::ValidationError { .. }) => Err(StatusCode::BAD_REQUEST),
+        // No secrets key to seal the token with
+        Err(TaskQueueError::ConfigurationError(_)) => Err(StatusCode::SERVICE_UNAVAILABLE),
+        Err(e) => {
+            error!("Failed to configure GitHub: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Remove a project's GitHub configuration
+async fn remove_github(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(project_id): Path<String>,
+) -> std::result::Result<Json<serde_json::Value>, StatusCode> {
+    let project_id = match uuid::Uuid::parse_str(&project_id) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    match server.remove_github(&project_id).await {
+        Ok(()) => Ok(Json(json!({"status": "deleted"}))),
+        Err(e) => {
+            error!("Failed to remove GitHub config: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Run a GitHub sync for a project
+async fn sync_github(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(project_id): Path<String>,
+) -> std::result::Result<Json<GitHubSyncReport>, StatusCode> {
+    let project_id = match uuid::Uuid::parse_str(&project_id) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    match server.sync_github(&project_id).await {
+        Ok(report) => Ok(Json(report)),
+        Err(TaskQueueError::ConfigurationError(_)) => Err(StatusCode::NOT_FOUND),
+        Err(TaskQueueError::NetworkError(e)) => {
+            error!("GitHub request failed: {}", e);
+            Err(StatusCode::BAD_GATEWAY)
+        }
+        Err(e) => {
+            error!("GitHub sync failed: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Import issues from Jira
+async fn import_jira(
+    State(server): State<Arc<TaskQueueServer>>,
+    Json(config): Json<JiraImportConfig>,
+) -> std::result::Result<Json<JiraImportReport>, StatusCode> {
+    match server.import_jira(config).await {
+        Ok(report) => Ok(Json(report)),
+        Err(TaskQueueError::ValidationError { .. }) => Err(StatusCode::BAD_REQUEST),
+        Err(TaskQueueError::ProjectNotFound { .. }) => Err(StatusCode::NOT_FOUND),
+        Err(TaskQueueError::NetworkError(e)) => {
+            error!("Jira request failed: {}", e);
+            Err(StatusCode::BAD_GATEWAY)
+        }
+        Err(e) => {
+            error!("Jira import failed: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Get the tracking manifest for a project
+async fn get_project_manifest(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(project_id): Path<String>,
+) -> std::result::Result<Json<ProjectManifest>, StatusCode> {
+    let project_id = match uuid::Uuid::parse_str(&project_id) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    match server.project_manifest(&project_id).await {
+        Ok(manifest) => Ok(Json(manifest)),
+        Err(TaskQueueError::ProjectNotFound { .. }) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to build project manifest: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Get tasks by project
+async fn get_project_tasks(
+    State(server): State<Arc<TaskQueueServer>>,
+    Path(project_id): Path<String>,
+) -> std::result::Result<Json<Vec<Task>>, StatusCode> {
+    let project_id = match uuid::Uuid::parse_str(&project_id) {
+        Ok(id) => id,
+        Err(_) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    match server.get_tasks_by_project(&project_id).await {
+        Ok(tasks) => Ok(Json(tasks)),
+        Err(e) => {
+            error!("Failed to get project tasks: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two stateless servers with storage engines of their own over one store
+    fn replicas() -> (TaskQueueServer, TaskQueueServer) {
+        replicas_caching_for(Duration::ZERO)
+    }
+
+    fn replicas_caching_for(cache_ttl: Duration) -> (TaskQueueServer, TaskQueueServer) {
+        let storage = StorageEngine::temporary().unwrap();
+        let other = Arc::new(storage.reopen().unwrap());
+        let vectorizer = Arc::new(VectorizerIntegration::new_dummy());
+        (
+            TaskQueueServer::stateless(Arc::new(storage), vectorizer.clone(), cache_ttl),
+            TaskQueueServer::stateless(other, vectorizer, cache_ttl),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_stateless_replicas_share_state() {
+        let (a, b) = replicas();
+        let project_id = a.create_project("shared".to_string(), None).await.unwrap();
+
+        let mut task = Task::new("build").with_command("make").build();
+        task.project_id = Some(project_id);
+        let task_id = b.submit_task(task).await.unwrap();
+
+        a.set_task_status(task_id, TaskStatus::Implementation).await.unwrap();
+        assert_eq!(b.get_task_status(task_id).await.unwrap(), TaskStatus::Implementation);
+        assert_eq!(a.list_tasks(None, None).await.unwrap().len(), 1);
+        assert!(a.tasks().is_empty());
+
+        b.delete_task(task_id).await.unwrap();
+        assert!(matches!(a.get_task(task_id).await, Err(TaskQueueError::TaskNotFound { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_stateless_replicas_refuse_stale_writes() {
+        let (a, b) = replicas_caching_for(Duration::from_secs(60));
+        let project_id = a.create_project("shared".to_string(), None).await.unwrap();
+        let mut task = Task::new("build").with_command("make").build();
+        task.project_id = Some(project_id);
+        let task_id = a.submit_task(task).await.unwrap();
+
+        // b caches the task, then a changes it
+        let mut stale = b.get_task(task_id).await.unwrap();
+        a.set_task_status(task_id, TaskStatus::Implementation).await.unwrap();
+
+        // Writing b's copy back would undo a's change
+        stale.name = "stale".to_string();
+        assert!(matches!(b.persist_task(&mut stale).await, Err(TaskQueueError::PreconditionFailed { version: 2 })));
+
+        // The conflict dropped b's cached copy, so b reads and updates a's
+        let task = b.get_task(task_id).await.unwrap();
+        assert_eq!((task.name.as_str(), task.version), ("build", 2));
+        b.set_task_status(task_id, TaskStatus::TestCreation).await.unwrap();
+        assert_eq!(b.get_task(task_id).await.unwrap().version, 3);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_writers_use_per_entry_locks() {
+        let server = Arc::new(TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap());
+        let project_id = server.create_project("locks".to_string(), None).await.unwrap();
+
+        // Racing upserts of one name still create a single task
+        let upserts: Vec<_> = (0..8)
+            .map(|i| {
+                let server = server.clone();
+                tokio::spawn(async move {
+                    server
+                        .upsert_task("deploy".to_string(), format!("deploy --try {}", i), "Deploy".to_string(), project_id, TaskPriority::Normal, None, None)
+                        .await
+                        .unwrap()
+                        .id
+                })
+            })
+            .collect();
+        let mut upserted = std::collections::HashSet::new();
+        for upsert in upserts {
+            upserted.insert(upsert.await.unwrap());
+        }
+        assert_eq!(upserted.len(), 1);
+
+        let mut ids = Vec::new();
+        for i in 0..16 {
+            let mut task = Task::new(&format!("task-{}", i)).with_command("true").build();
+            task.project_id = Some(project_id);
+            ids.push(server.submit_task(task).await.unwrap());
+        }
+        let writers: Vec<_> = ids
+            .iter()
+            .map(|&id| {
+                let server = server.clone();
+                tokio::spawn(async move { server.set_task_status(id, TaskStatus::Implementation).await })
+            })
+            .collect();
+        for writer in writers {
+            writer.await.unwrap().unwrap();
+        }
+        for id in &ids {
+            assert_eq!(server.get_task_status(*id).await.unwrap(), TaskStatus::Implementation);
+        }
+        assert_eq!(server.list_tasks(None, None).await.unwrap().len(), 17);
+
+        // Holding one task's lock doesn't block access to the others
+        let held = server.tasks().get(&ids[0]).unwrap().value().clone();
+        let _guard = held.write().await;
+        let other = tokio::time::timeout(Duration::from_secs(1), server.set_task_status(ids[1], TaskStatus::TestCreation)).await;
+        assert!(matches!(other, Ok(Ok(()))));
+        assert!(tokio::time::timeout(Duration::from_millis(50), server.get_task(ids[0])).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tasks_inherit_project_executor_settings() {
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let project_id = server.create_project("containers".to_string(), None).await.unwrap();
+        let updates = ProjectUpdate {
+            name: None,
+            description: None,
+            status: None,
+            tags: None,
+            metadata: Some(HashMap::from([
+                ("executor".to_string(), json!("docker")),
+                ("docker".to_string(), json!({"image": "alpine:3"})),
+            ])),
+        };
+        server.update_project(&project_id, updates, &Precondition::Any).await.unwrap();
+
+        let mut inherits = Task::new("inherits").with_command("true").build();
+        inherits.project_id = Some(project_id);
+        let inherits = server.get_task(server.submit_task(inherits).await.unwrap()).await.unwrap();
+        assert_eq!(inherits.metadata["executor"], json!("docker"));
+        assert_eq!(inherits.metadata["docker"], json!({"image": "alpine:3"}));
+
+        let mut overrides = Task::new("overrides").with_command("true").with_metadata("executor", json!("shell")).build();
+        overrides.project_id = Some(project_id);
+        let overrides = server.get_task(server.submit_task(overrides).await.unwrap()).await.unwrap();
+        assert_eq!(overrides.metadata["executor"], json!("shell"));
+    }
+
+    #[tokio::test]
+    async fn test_lease_renewals_are_written_behind() {
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap()
+        .with_write_behind(&WriteBehindConfig { enabled: true, ..Default::default() });
+        let project_id = server.create_project("workers".to_string(), None).await.unwrap();
+        let mut task = Task::new("crunch").with_command("crunch").build();
+        task.project_id = Some(project_id);
+        task.status = TaskStatus::Pending;
+        let task_id = server.submit_task(task).await.unwrap();
+        server.claim_next_task("w1", Some(60)).await.unwrap().unwrap();
+        let stored_lease = || async { Lease::of(&server.storage.load_task(&task_id).await.unwrap().unwrap()).unwrap() };
+        let claimed = stored_lease().await;
+
+        let mut renewed = claimed.clone();
+        for _ in 0..3 {
+            renewed = server.renew_lease(task_id, "w1", Some(120)).await.unwrap();
+        }
+        assert_eq!(stored_lease().await, claimed);
+        assert_eq!(server.flush_writes().await.unwrap(), 1);
+        assert_eq!(stored_lease().await, renewed);
+        assert_eq!(server.flush_writes().await.unwrap(), 0);
+
+        server.renew_lease(task_id, "w1", None).await.unwrap();
+        server.cancel_task(task_id, "stop".to_string()).await.unwrap();
+        assert_eq!(server.flush_writes().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_progress_reports_are_written_behind() {
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap()
+        .with_write_behind(&WriteBehindConfig { enabled: true, ..Default::default() });
+        let project_id = server.create_project("db".to_string(), None).await.unwrap();
+        let mut task = Task::new("migrate").with_command("make migrate").build();
+        task.project_id = Some(project_id);
+        task.status = TaskStatus::Pending;
+        let task_id = server.submit_task(task).await.unwrap();
+        let stored_percent = || async {
+            let task = server.storage.load_task(&task_id).await.unwrap().unwrap();
+            crate::progress::progress(&task).map(|progress| progress.percent)
+        };
+
+        // Many reports within a window make a single write of the latest one
+        for percent in [10.0, 20.0, 30.0, 40.0] {
+            server.report_progress(task_id, percent, None).await.unwrap();
+        }
+        assert_eq!(stored_percent().await, None);
+        assert_eq!(crate::progress::progress(&server.get_task(task_id).await.unwrap()).unwrap().percent, 40);
+        assert_eq!(server.flush_writes().await.unwrap(), 1);
+        assert_eq!(stored_percent().await, Some(40));
+        assert_eq!(server.flush_writes().await.unwrap(), 0);
+
+        // Finishing the task writes the pending report along with it
+        server.report_progress(task_id, 90.0, None).await.unwrap();
+        server.cancel_task(task_id, "stop".to_string()).await.unwrap();
+        assert_eq!(stored_percent().await, Some(90));
+        assert_eq!(server.flush_writes().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_leases_expire_back_to_pending() {
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let project_id = server.create_project("workers".to_string(), None).await.unwrap();
+
+        let mut task = Task::new("crunch").with_command("crunch").build();
+        task.project_id = Some(project_id);
+        task.status = TaskStatus::Pending;
+        let task_id = server.submit_task(task).await.unwrap();
+
+        let claimed = server.claim_next_task("w1", Some(60)).await.unwrap().unwrap();
+        assert_eq!((claimed.id, claimed.status), (task_id, TaskStatus::Running));
+        assert!(server.claim_next_task("w2", None).await.unwrap().is_none());
+        assert!(matches!(server.renew_lease(task_id, "w2", None).await, Err(TaskQueueError::LeaseNotHeld { .. })));
+        server.renew_lease(task_id, "w1", Some(60)).await.unwrap();
+
+        let later = chrono::Utc::now() + chrono::Duration::seconds(61);
+        assert_eq!(server.expire_leases(later).await.unwrap(), vec![task_id]);
+        assert_eq!(server.get_task(task_id).await.unwrap().status, TaskStatus::Pending);
+        assert!(matches!(server.renew_lease(task_id, "w1", None).await, Err(TaskQueueError::LeaseNotHeld { .. })));
+
+        assert_eq!(server.claim_next_task("w2", None).await.unwrap().unwrap().id, task_id);
+        let result = TaskResult::Success {
+            output: "done".to_string(),
+            artifacts: Vec::new(),
+            metrics: TaskMetrics::default(),
+        };
+        let task = server.complete_leased_task(task_id, "w2", result).await.unwrap();
+        assert_eq!(task.status, TaskStatus::Completed);
+        assert!(Lease::of(&task).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dead_letter_endpoints() {
+        use axum::body::Body;
+        use tower::ServiceExt;
+
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let project_id = server.create_project("workers".to_string(), None).await.unwrap();
+        let dead_letter = |name: &'static str| {
+            let server = &server;
+            async move {
+                let mut task = Task::new(name).with_command("false").with_retry(0, Duration::from_secs(1)).build();
+                task.project_id = Some(project_id);
+                task.status = TaskStatus::Pending;
+                let task_id = server.submit_task(task).await.unwrap();
+                server.complete_task_execution(task_id, failure("boom", Some(1), Vec::new())).await.unwrap();
+                task_id
+            }
+        };
+        let send = |method: &str, path: String| {
+            let request = axum::http::Request::builder().method(method).uri(path).body(Body::empty()).unwrap();
+            server.rest_router().oneshot(request)
+        };
+        let listed = || async {
+            let response = send("GET", "/api/v1/dead-letter".to_string()).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let entries: Vec<DeadLetter> = serde_json::from_slice(&body).unwrap();
+            entries.into_iter().map(|entry| (entry.task_id, entry.error)).collect::<Vec<_>>()
+        };
+
+        let task_id = dead_letter("flaky").await;
+        assert_eq!(listed().await, [(task_id, "boom".to_string())]);
+
+        let requeue = |id: String| send("POST", format!("/api/v1/dead-letter/{}/requeue", id));
+        assert_eq!(requeue(uuid::Uuid::new_v4().to_string()).await.unwrap().status(), StatusCode::NOT_FOUND);
+        assert_eq!(requeue("not-a-uuid".to_string()).await.unwrap().status(), StatusCode::BAD_REQUEST);
+
+        let response = requeue(task_id.to_string()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let task: Task = serde_json::from_slice(&body).unwrap();
+        assert_eq!((task.id, task.status), (task_id, TaskStatus::Pending));
+        assert!(task.result.is_none());
+        assert!(listed().await.is_empty());
+        assert_eq!(requeue(task_id.to_string()).await.unwrap().status(), StatusCode::NOT_FOUND);
+
+        // Deleting a dead-lettered task drops its entry too
+        let task_id = dead_letter("doomed").await;
+        assert_eq!(listed().await, [(task_id, "boom".to_string())]);
+        let response = send("DELETE", format!("/api/v1/tasks/{}", task_id)).await.unwrap();
+        assert!(response.status().is_success());
+        assert!(listed().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tenants_only_see_their_own_dead_letters_recurrences_and_archive() {
+        use axum::body::Body;
+        use crate::tenants::TenancyConfig;
+        use tower::ServiceExt;
+
+        let storage = Arc::new(StorageEngine::temporary().unwrap());
+        let server = TaskQueueServer::with_components(storage.clone(), Arc::new(VectorizerIntegration::new_dummy()))
+            .await
+            .unwrap()
+            .with_server_config(ServerConfig {
+                tenancy: TenancyConfig { required: true, admin_key: Some("operator".to_string()), ..Default::default() },
+                ..ServerConfig::default()
+            });
+        let (alpha, beta) = (
+            server.create_tenant("Alpha".to_string(), None).await.unwrap(),
+            server.create_tenant("Beta".to_string(), None).await.unwrap(),
+        );
+        let alpha_key = server.issue_tenant_key(&alpha.id, "ci".to_string(), None).await.unwrap().key;
+        let beta_key = server.issue_tenant_key(&beta.id, "ci".to_string(), None).await.unwrap().key;
+
+        let (project_id, dead_id, archived_id) = tenants::with_tenant(Some(alpha.id), async {
+            let project_id = server.create_project("web".to_string(), None).await.unwrap();
+            let mut task = Task::new("flaky").with_command("false").with_retry(0, Duration::from_secs(1)).build();
+            task.project_id = Some(project_id);
+            task.status = TaskStatus::Pending;
+            let dead_id = server.submit_task(task).await.unwrap();
+            server.complete_task_execution(dead_id, failure("boom", Some(1), Vec::new())).await.unwrap();
+
+            let mut old = Task::new("old").with_command("make").build();
+            old.project_id = Some(project_id);
+            old.tenant_id = Some(alpha.id);
+            storage.archive_task(&old).await.unwrap();
+            (project_id, dead_id, old.id)
+        }).await;
+
+        let send = |key: &str, method: &str, path: String, body: Option<Value>| {
+            let request = axum::http::Request::builder()
+                .method(method)
+                .uri(path)
+                .header(tenants::API_KEY_HEADER, key)
+                .header("content-type", "application/json")
+                .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+                .unwrap();
+            server.rest_router().oneshot(request)
+        };
+        let fetch = |key: &'static str, path: &'static str| {
+            let send = &send;
+            let key = if key == "alpha" { alpha_key.clone() } else { beta_key.clone() };
+            async move {
+                let response = send(&key, "GET", path.to_string(), None).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                serde_json::from_slice::<Vec<Value>>(&body).unwrap().len()
+            }
+        };
+
+        let response = send(&alpha_key, "POST", "/api/v1/recurrences".to_string(), Some(json!({
+            "schedule": { "every_secs": 60 },
+            "task": {
+                "name": "nightly", "command": "make", "description": "Nightly build",
+                "technical_specs": null, "acceptance_criteria": null, "project": null,
+                "task_type": "Simple", "priority": "Normal", "project_id": project_id,
+                "estimated_hours": null, "tags": null, "ai_reviews_required": null
+            }
+        }))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let recurrence: RecurringTask = serde_json::from_slice(&body).unwrap();
+        assert_eq!(recurrence.tenant_id, Some(alpha.id));
+
+        // Dead letters
+        assert_eq!(fetch("alpha", "/api/v1/dead-letter").await, 1);
+        assert_eq!(fetch("beta", "/api/v1/dead-letter").await, 0);
+        let requeue = format!("/api/v1/dead-letter/{}/requeue", dead_id);
+        assert_eq!(send(&beta_key, "POST", requeue.clone(), None).await.unwrap().status(), StatusCode::NOT_FOUND);
+        assert_eq!(fetch("alpha", "/api/v1/dead-letter").await, 1);
+
+        // Recurrences
+        assert_eq!(fetch("alpha", "/api/v1/recurrences").await, 1);
+        assert_eq!(fetch("beta", "/api/v1/recurrences").await, 0);
+        let path = format!("/api/v1/recurrences/{}", recurrence.id);
+        assert_eq!(send(&beta_key, "GET", path.clone(), None).await.unwrap().status(), StatusCode::NOT_FOUND);
+        assert_eq!(send(&beta_key, "POST", format!("{}/pause", path), None).await.unwrap().status(), StatusCode::NOT_FOUND);
+        assert_eq!(send(&beta_key, "DELETE", path.clone(), None).await.unwrap().status(), StatusCode::NOT_FOUND);
+        assert_eq!(send(&alpha_key, "GET", path, None).await.unwrap().status(), StatusCode::OK);
+        // Its tasks belong to its tenant, though it runs in the background
+        let created = server.materialize_recurrences(recurrence.starts_at + chrono::Duration::minutes(1)).await.unwrap();
+        assert_eq!(created.len(), 1);
+        assert_eq!(server.get_task(created[0]).await.unwrap().tenant_id, Some(alpha.id));
+
+        // Archive
+        let archived = format!("/api/v1/archive/tasks/{}", archived_id);
+        assert_eq!(send(&beta_key, "GET", archived.clone(), None).await.unwrap().status(), StatusCode::NOT_FOUND);
+        assert_eq!(send(&alpha_key, "GET", archived, None).await.unwrap().status(), StatusCode::OK);
+
+        assert_eq!(send(&alpha_key, "POST", requeue, None).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(fetch("alpha", "/api/v1/dead-letter").await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_tenants_keep_their_own_views_and_leave_shared_stores_to_the_operator() {
+        use axum::body::Body;
+        use crate::tenants::TenancyConfig;
+        use tower::ServiceExt;
+
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap()
+        .with_server_config(ServerConfig {
+            tenancy: TenancyConfig { required: true, admin_key: Some("operator".to_string()), ..Default::default() },
+            ..ServerConfig::default()
+        });
+        let alpha = server.create_tenant("Alpha".to_string(), None).await.unwrap();
+        let beta = server.create_tenant("Beta".to_string(), None).await.unwrap();
+        let alpha_key = server.issue_tenant_key(&alpha.id, "ci".to_string(), None).await.unwrap().key;
+        let beta_key = server.issue_tenant_key(&beta.id, "ci".to_string(), None).await.unwrap().key;
+
+        let send = |key: &str, method: &str, path: &str, body: Option<Value>| {
+            let request = axum::http::Request::builder()
+                .method(method)
+                .uri(path)
+                .header(tenants::API_KEY_HEADER, key)
+                .header("content-type", "application/json")
+                .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+                .unwrap();
+            server.rest_router().oneshot(request)
+        };
+        let read = |response: axum::response::Response| async move {
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<Value>(&body).unwrap()
+        };
+
+        // Both tenants save a view of the same name without touching the other's
+        let mine = json!({ "description": "Alpha's", "filter": {} });
+        read(send(&alpha_key, "PUT", "/api/v1/views/mine", Some(mine)).await.unwrap()).await;
+        let theirs = json!({ "description": "Beta's", "filter": {} });
+        read(send(&beta_key, "PUT", "/api/v1/views/mine", Some(theirs)).await.unwrap()).await;
+        let view = read(send(&alpha_key, "GET", "/api/v1/views/mine", None).await.unwrap()).await;
+        assert_eq!(view["description"], "Alpha's");
+        assert_eq!(read(send(&alpha_key, "GET", "/api/v1/views", None).await.unwrap()).await.as_array().unwrap().len(), 1);
+
+        read(send(&beta_key, "PUT", "/api/v1/views/beta-only", Some(json!({ "filter": {} }))).await.unwrap()).await;
+        assert_eq!(send(&alpha_key, "GET", "/api/v1/views/beta-only", None).await.unwrap().status(), StatusCode::NOT_FOUND);
+        assert_eq!(send(&alpha_key, "DELETE", "/api/v1/views/beta-only", None).await.unwrap().status(), StatusCode::NOT_FOUND);
+        assert_eq!(send(&beta_key, "DELETE", "/api/v1/views/mine", None).await.unwrap().status(), StatusCode::NO_CONTENT);
+        let view = read(send(&alpha_key, "GET", "/api/v1/views/mine", None).await.unwrap()).await;
+        assert_eq!(view["description"], "Alpha's");
+        // The operator sees every tenant's views
+        assert_eq!(read(send("operator", "GET", "/api/v1/views", None).await.unwrap()).await.as_array().unwrap().len(), 2);
+
+        // Queues, workers and agents are shared by every tenant's tasks
+        let queue = json!({ "name": "builds", "max_concurrency": 1 });
+        assert_eq!(send(&alpha_key, "POST", "/api/v1/queues", Some(queue.clone())).await.unwrap().status(), StatusCode::FORBIDDEN);
+        assert_eq!(send("operator", "POST", "/api/v1/queues", Some(queue)).await.unwrap().status(), StatusCode::CREATED);
+        for path in ["/api/v1/queues", "/api/v1/queues/builds", "/api/v1/workers", "/api/v1/agents"] {
+            assert_eq!(send(&beta_key, "GET", path, None).await.unwrap().status(), StatusCode::FORBIDDEN, "{}", path);
+            assert_eq!(send("operator", "GET", path, None).await.unwrap().status(), StatusCode::OK, "{}", path);
+        }
+        assert_eq!(send(&beta_key, "DELETE", "/api/v1/queues/builds", None).await.unwrap().status(), StatusCode::FORBIDDEN);
+        let worker = json!({ "worker_id": "w1", "max_concurrency": 1 });
+        assert_eq!(send(&beta_key, "POST", "/api/v1/workers/register", Some(worker)).await.unwrap().status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_tasks_without_heartbeats_are_reaped() {
+        let storage = Arc::new(StorageEngine::temporary().unwrap());
+        let server = TaskQueueServer::with_components(storage.clone(), Arc::new(VectorizerIntegration::new_dummy()))
+            .await
+            .unwrap();
+        let project_id = server.create_project("reaper".to_string(), None).await.unwrap();
+        let submit = |name: &str, retries: u32| {
+            let mut task = Task::new(name).with_command(name).with_retry(retries, Duration::ZERO).build();
+            task.project_id = Some(project_id);
+            task.status = TaskStatus::Pending;
+            task
+        };
+        let flaky = server.submit_task(submit("flaky", 1)).await.unwrap();
+        let fragile = server.submit_task(submit("fragile", 0)).await.unwrap();
+        let alive = server.submit_task(submit("alive", 0)).await.unwrap();
+
+        let now = chrono::Utc::now();
+        let long_ago = now - chrono::Duration::minutes(5);
+        for task_id in [flaky, fragile, alive] {
+            server.claim_task(task_id).await.unwrap().unwrap();
+        }
+        storage.store_heartbeat(&flaky, long_ago).await.unwrap();
+        storage.store_heartbeat(&fragile, long_ago).await.unwrap();
+        storage.store_heartbeat(&alive, now).await.unwrap();
+
+        let mut reaped = server.reap_stale_tasks(now, Duration::from_secs(60)).await.unwrap();
+        reaped.sort();
+        let mut expected = vec![flaky, fragile];
+        expected.sort();
+        assert_eq!(reaped, expected);
+
+        assert_eq!(server.get_task(flaky).await.unwrap().status, TaskStatus::Scheduled);
+        let fragile = server.get_task(fragile).await.unwrap();
+        assert_eq!(fragile.status, TaskStatus::Failed);
+        assert!(matches!(fragile.result, Some(TaskResult::Failure { ref error, .. }) if error.contains("heartbeats")));
+        assert_eq!(server.get_task(alive).await.unwrap().status, TaskStatus::Running);
+        assert!(server.reap_stale_tasks(now, Duration::from_secs(60)).await.unwrap().is_empty());
+        assert_eq!(storage.list_heartbeats().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_claims_follow_worker_labels_and_concurrency() {
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let project_id = server.create_project("workers".to_string(), None).await.unwrap();
+        let submit = |name: &str, selector: Option<Value>| {
+            let mut task = Task::new(name).with_command(name).build();
+            if let Some(selector) = selector {
+                task.metadata.insert(workers::WORKER_SELECTOR_KEY.to_string(), selector);
+            }
+            task.project_id = Some(project_id);
+            task.status = TaskStatus::Pending;
+            task
+        };
+        let train = server.submit_task(submit("train", Some(json!({"gpu": "true"})))).await.unwrap();
+        let render = server.submit_task(submit("render", Some(json!({"gpu": "true"})))).await.unwrap();
+        let lint = server.submit_task(submit("lint", None)).await.unwrap();
+
+        assert!(server.register_worker(RegisterWorker {
+            worker_id: "gpu-1".to_string(),
+            labels: HashMap::new(),
+            max_concurrency: 0,
+        }).await.is_err());
+        server.register_worker(RegisterWorker {
+            worker_id: "gpu-1".to_string(),
+            labels: HashMap::from([("gpu".to_string(), "true".to_string())]),
+            max_concurrency: 1,
+        }).await.unwrap();
+
+        // Unregistered workers only get tasks that select no one
+        assert_eq!(server.claim_next_task("anyone", None).await.unwrap().unwrap().id, lint);
+        assert!(server.claim_next_task("anyone", None).await.unwrap().is_none());
+
+        let claimed = server.claim_next_task("gpu-1", None).await.unwrap().unwrap().id;
+        assert!(claimed == train || claimed == render);
+        assert!(server.claim_next_task("gpu-1", None).await.unwrap().is_none());
+
+        let workers = server.list_workers().await.unwrap();
+        assert_eq!(workers.len(), 1);
+        assert_eq!((workers[0].worker.id.as_str(), workers[0].running), ("gpu-1", 1));
+        assert_eq!(workers[0].state, workers::WorkerState::Online);
+    }
+
+    #[tokio::test]
+    async fn test_secrets_are_resolved_for_execution_and_redacted_from_results() {
+        let storage = Arc::new(StorageEngine::temporary().unwrap());
+        let server = TaskQueueServer::with_components(storage.clone(), Arc::new(VectorizerIntegration::new_dummy()))
+            .await
+            .unwrap();
+        let server = TaskQueueServer { secrets: None, ..server };
+        assert!(matches!(server.set_secret("DB", "hunter2").await, Err(TaskQueueError::ConfigurationError(_))));
+
+        let server = server.with_secrets_key("test key");
+        assert!(server.set_secret("bad name", "x").await.is_err());
+        server.set_secret("DB", "hunter2").await.unwrap();
+        assert_eq!(server.list_secrets().await.unwrap().iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), vec!["DB"]);
+        let stored = storage.load_secret(None, "DB").await.unwrap().unwrap();
+        assert!(!stored.ciphertext.windows(7).any(|w| w == b"hunter2"));
+
+        let mut task = Task::new("migrate")
+            .with_command("migrate")
+            .with_environment("DB_PASSWORD", "{{secret:DB}}")
+            .build();
+        task.project_id = Some(server.create_project("db".to_string(), None).await.unwrap());
+        task.status = TaskStatus::Pending;
+        let task_id = server.submit_task(task).await.unwrap();
+        let task = server.get_task(task_id).await.unwrap();
+        let (resolved, _) = server.resolve_secrets(&task).await.unwrap();
+        assert_eq!(resolved.environment["DB_PASSWORD"], "hunter2");
+
+        server.claim_next_task("w1", None).await.unwrap().unwrap();
+        let result = TaskResult::Success {
+            output: "connected with hunter2".to_string(),
+            artifacts: Vec::new(),
+            metrics: TaskMetrics::default(),
+        };
+        let task = server.complete_leased_task(task_id, "w1", result).await.unwrap();
+        assert_eq!(task.environment["DB_PASSWORD"], "{{secret:DB}}");
+        assert!(matches!(task.result, Some(TaskResult::Success { ref output, .. }) if output == "connected with [REDACTED]"));
+
+        assert!(server.delete_secret("DB").await.unwrap());
+        assert!(matches!(server.resolve_secrets(&task).await, Err(TaskQueueError::ValidationError { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_tasks_only_resolve_their_own_tenants_secrets() {
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap()
+        .with_secrets_key("test key");
+        let (team, other) = (uuid::Uuid::new_v4(), uuid::Uuid::new_v4());
+        server.set_secret("DB", "operator-pw").await.unwrap();
+        tenants::with_tenant(Some(team), server.set_secret("DB", "team-pw")).await.unwrap();
+        tenants::with_tenant(Some(other), server.set_secret("API", "other-pw")).await.unwrap();
+
+        let task_of = |tenant: Option<uuid::Uuid>, reference: &str| {
+            let mut task = Task::new("migrate").with_command("migrate").with_environment("PASSWORD", reference).build();
+            task.tenant_id = tenant;
+            task
+        };
+        let resolved = |task: Task| {
+            let server = &server;
+            async move { server.resolve_secrets(&task).await.map(|(task, _)| task.environment["PASSWORD"].clone()) }
+        };
+        assert_eq!(resolved(task_of(Some(team), "{{secret:DB}}")).await.unwrap(), "team-pw");
+        assert_eq!(resolved(task_of(None, "{{secret:DB}}")).await.unwrap(), "operator-pw");
+        // Neither the operator's secrets nor another tenant's are visible to a tenant's task
+        assert!(matches!(resolved(task_of(Some(other), "{{secret:DB}}")).await, Err(TaskQueueError::ValidationError { .. })));
+        assert!(matches!(resolved(task_of(Some(team), "{{secret:API}}")).await, Err(TaskQueueError::ValidationError { .. })));
+        assert!(matches!(
+            tenants::with_tenant(Some(other), resolved(task_of(Some(team), "{{secret:DB}}"))).await,
+            Err(TaskQueueError::TaskNotFound { .. })
+        ));
+
+        let names = |tenant| {
+            let server = &server;
+            tenants::with_tenant(tenant, async move {
+                server.list_secrets().await.unwrap().into_iter().map(|s| (s.tenant_id, s.name)).collect::<Vec<_>>()
+            })
+        };
+        assert_eq!(names(Some(team)).await, [(Some(team), "DB".to_string())]);
+        assert_eq!(names(None).await.len(), 3);
+        assert!(!tenants::with_tenant(Some(other), server.delete_secret("DB")).await.unwrap());
+        assert_eq!(resolved(task_of(Some(team), "{{secret:DB}}")).await.unwrap(), "team-pw");
+
+        // HTTP tasks only send secrets to allow-listed hosts
+        let hook = Task::new("hook")
+            .with_type(TaskType::Http)
+            .with_metadata(crate::http_task::HTTP_METADATA_KEY, json!({
+                "url": "https://hooks.example.com/deploy",
+                "headers": {"Authorization": "Bearer {{secret:DB}}"}
+            }))
+            .build();
+        assert!(matches!(server.resolve_secrets(&hook).await, Err(TaskQueueError::ValidationError { .. })));
+        let server = server.with_secret_hosts(vec!["hooks.example.com".to_string()]);
+        let (resolved, _) = server.resolve_secrets(&hook).await.unwrap();
+        assert_eq!(resolved.metadata["http"]["headers"]["Authorization"], "Bearer operator-pw");
+    }
+
+    #[tokio::test]
+    async fn test_batch_submission_is_all_or_nothing() {
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        ).await.unwrap();
+        let project_id = server.create_project("batch".to_string(), None).await.unwrap();
+        let task = |name: &str| {
+            let mut task = Task::new(name).with_command("echo").build();
+            task.project_id = Some(project_id);
+            task
+        };
+
+        let rejected = server.submit_tasks(vec![task("build"), task(""), task("deploy")]).await.unwrap();
+        assert!(!rejected.accepted);
+        assert!(rejected.items.iter().all(|item| item.task_id.is_none()));
+        assert!(rejected.items[1].error.as_deref().unwrap().contains("name cannot be empty"));
+        assert!(rejected.items[0].error.is_none() && rejected.items[2].error.is_none());
+        assert!(server.list_tasks(None, None).await.unwrap().is_empty());
+
+        let accepted = server.submit_tasks(vec![task("build"), task("deploy")]).await.unwrap();
+        assert!(accepted.accepted);
+        for (index, item) in accepted.items.iter().enumerate() {
+            assert_eq!(item.index, index);
+            assert_eq!(server.get_task(item.task_id.unwrap()).await.unwrap().version, 1);
+            assert!(server.storage.load_task(&item.task_id.unwrap()).await.unwrap().is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_finished_tasks_move_their_workflow_along() {
+        let storage = Arc::new(StorageEngine::temporary().unwrap());
+        let server = TaskQueueServer::with_components(storage.clone(), Arc::new(VectorizerIntegration::new_dummy()))
+            .await
+            .unwrap();
+        let project_id = server.create_project("release".to_string(), None).await.unwrap();
+        let step = |name: &str| {
+            let mut task = Task::new(name).with_command("echo").with_retry(0, Duration::from_secs(1)).build();
+            task.project_id = Some(project_id);
+            task.status = TaskStatus::Pending;
+            task
+        };
+        let (build, deploy) = (step("build"), step("deploy"));
+        let workflow_id = server.submit_workflow(Workflow::new("release").add_task(build.clone()).add_task(deploy.clone())).await.unwrap();
+        server.submit_task(build.clone()).await.unwrap();
+        server.submit_task(deploy.clone()).await.unwrap();
+
+        let success = TaskResult::Success { output: "ok".to_string(), artifacts: Vec::new(), metrics: TaskMetrics::default() };
+        server.complete_task_execution(build.id, success).await.unwrap();
+        let stored = storage.load_workflow(&workflow_id).await.unwrap().unwrap();
+        assert_eq!(stored.status, WorkflowStatus::Pending);
+        assert_eq!(stored.tasks[0].status, TaskStatus::Completed);
+        assert_eq!(stored.tasks[0].version, storage.load_task(&build.id).await.unwrap().unwrap().version);
+        assert_eq!(server.get_workflow(workflow_id).await.unwrap().version, stored.version);
+
+        server.complete_task_execution(deploy.id, failure("boom", Some(1), Vec::new())).await.unwrap();
+        let workflow = server.get_workflow(workflow_id).await.unwrap();
+        assert_eq!(workflow.status, WorkflowStatus::Failed);
+        assert_eq!(storage.load_workflow(&workflow_id).await.unwrap().unwrap().status, WorkflowStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_group_join_runs_after_quorum() {
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        ).await.unwrap();
+        let project_id = server.create_project("groups".to_string(), None).await.unwrap();
+        let request = |name: &str| json!({
+            "name": name,
+            "command": "echo",
+            "description": name,
+            "task_type": "Simple",
+            "priority": "Normal",
+            "project_id": project_id,
+        });
+        let group: CreateTaskGroup = serde_json::from_value(json!({
+            "tasks": [request("shard-1"), request("shard-2"), request("shard-3")],
+            "join": request("merge"),
+            "quorum": 2,
+        })).unwrap();
+
+        let group = server.submit_group(group).await.unwrap();
+        assert!(group.accepted);
+        let join = group.join_task_id.unwrap();
+        server.modify_task(join, |task| task.status = TaskStatus::Pending).await.unwrap();
+
+        let success = TaskResult::Success { output: String::new(), artifacts: vec![], metrics: TaskMetrics::default() };
+        server.modify_task(group.items[0].task_id.unwrap(), |task| task.set_result(success.clone())).await.unwrap();
+        assert!(!server.ready_tasks().await.unwrap().any(|task| task.id == join));
+        server.modify_task(group.items[2].task_id.unwrap(), |task| task.set_result(success.clone())).await.unwrap();
+        assert!(server.ready_tasks().await.unwrap().any(|task| task.id == join));
+
+        let progress = server.group_progress(group.group_id).await.unwrap().unwrap();
+        assert_eq!((progress.members.len(), progress.succeeded), (3, 2));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_artifacts_are_collected_after_success() {
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        ).await.unwrap().with_artifacts(ArtifactConfig { patterns: vec!["dist/*".to_string()], ..Default::default() });
+        let project_id = server.create_project("artifacts".to_string(), None).await.unwrap();
+        let dir = std::env::temp_dir().join(format!("task-queue-build-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut task = Task::new("build")
+            .with_command("mkdir -p dist reports && echo bin > dist/app && echo ok > reports/unit.xml && echo log > build.log")
+            .with_working_directory(&dir.to_string_lossy())
+            .with_metadata(artifacts::ARTIFACTS_METADATA_KEY, json!(["reports/*.xml"]))
+            .build();
+        task.project_id = Some(project_id);
+        let task_id = server.submit_task(task.clone()).await.unwrap();
+
+        match server.execute_task(&ExecutorRegistry::new(), &task).await {
+            TaskResult::Success { artifacts, .. } => assert_eq!(artifacts, [
+                format!("/tasks/{}/artifacts/dist/app", task_id),
+                format!("/tasks/{}/artifacts/reports/unit.xml", task_id),
+            ]),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let stored = server.list_artifacts(task_id).await.unwrap();
+        assert_eq!(stored.iter().map(|a| a.size).collect::<Vec<_>>(), [4, 3]);
+        assert_eq!(server.get_artifact(task_id, "dist/app").await.unwrap().unwrap(), b"bin\n");
+
+        server.delete_task(task_id).await.unwrap();
+        assert!(server.get_artifact(task_id, "dist/app").await.unwrap().is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_artifact_links_are_signed_and_logs_kept() {
+        let root = std::env::temp_dir().join(format!("task-queue-blobs-{}", uuid::Uuid::new_v4()));
+        let dir = root.join("work");
+        std::fs::create_dir_all(&dir).unwrap();
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        ).await.unwrap()
+            .with_artifacts(ArtifactConfig { patterns: vec!["app".to_string()], ..Default::default() })
+            .with_blob_store(Arc::new(crate::blobs::LocalBlobs::new(root.join("blobs"))));
+        let project_id = server.create_project("blobs".to_string(), None).await.unwrap();
+        let mut task = Task::new("build")
+            .with_command("echo built && echo bin > app")
+            .with_working_directory(&dir.to_string_lossy())
+            .build();
+        task.project_id = Some(project_id);
+        let task_id = server.submit_task(task.clone()).await.unwrap();
+        server.execute_task(&ExecutorRegistry::new(), &task).await;
+
+        let links = server.artifact_links(task_id).await.unwrap();
+        let kinds: Vec<(ArtifactKind, &str)> = links.iter().map(|l| (l.artifact.kind, l.artifact.name.as_str())).collect();
+        assert_eq!(kinds, [(ArtifactKind::Log, artifacts::LOG_FILE_NAME), (ArtifactKind::File, "app")]);
+        assert!(root.join(format!("blobs/tasks/{}/artifacts/app", task_id)).exists());
+
+        let url = &links[0].url;
+        let (path, query) = url.split_once('?').unwrap();
+        let key = path.strip_prefix("/blobs/").unwrap();
+        let params: HashMap<&str, &str> = query.split('&').filter_map(|p| p.split_once('=')).collect();
+        let expires: i64 = params["expires"].parse().unwrap();
+        let log = server.get_signed_blob(key, expires, params["signature"]).await.unwrap().unwrap();
+        assert!(String::from_utf8(log).unwrap().contains("built"));
+        assert!(matches!(
+            server.get_signed_blob(key, expires + 1, params["signature"]).await,
+            Err(TaskQueueError::PermissionDenied { .. })
+        ));
+
+        server.delete_task(task_id).await.unwrap();
+        assert!(!root.join(format!("blobs/tasks/{}/artifacts/app", task_id)).exists());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_tasks_without_working_directory_run_in_own_workspace() {
+        let root = std::env::temp_dir().join(format!("task-queue-workspaces-{}", uuid::Uuid::new_v4()));
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        ).await.unwrap().with_workspaces(WorkspaceConfig { root: Some(root.clone()), ..Default::default() });
+        let task = Task::new("scratch").with_command("touch scratch && pwd").build();
+
+        let workspace = match server.execute_task(&ExecutorRegistry::new(), &task).await {
+            TaskResult::Success { output, .. } => std::path::PathBuf::from(output.trim()),
+            other => panic!("unexpected result: {:?}", other),
+        };
+        assert!(workspace.starts_with(root.canonicalize().unwrap()));
+        assert!(workspace.file_name().unwrap().to_string_lossy().starts_with(&task.id.to_string()));
+        assert!(!workspace.exists());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_task_history_is_replayed_from_state_changes() {
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let project_id = server.create_project("history".to_string(), None).await.unwrap();
+        let mut task = Task::new("churn").with_command("true").build();
+        task.project_id = Some(project_id);
+        let task_id = server.submit_task(task).await.unwrap();
+
+        server.set_task_status(task_id, TaskStatus::Implementation).await.unwrap();
+        server.set_task_status(task_id, TaskStatus::Cancelled).await.unwrap();
+        let history = server.task_history(task_id).await.unwrap();
+        let transitions: Vec<(Option<&str>, Option<&str>)> = history.changes.iter()
+            .map(|change| (change.from.as_deref(), change.to.as_deref()))
+            .collect();
+        assert_eq!(transitions, [
+            (None, Some("Planning")),
+            (Some("Planning"), Some("Implementation")),
+            (Some("Implementation"), Some("Cancelled")),
+        ]);
+        assert_eq!(history.periods.len(), 3);
+
+        server.delete_task(task_id).await.unwrap();
+        let history = server.task_history(task_id).await.unwrap();
+        assert!(history.deleted && history.status.is_none());
+        assert!(matches!(
+            server.task_history(uuid::Uuid::new_v4()).await,
+            Err(TaskQueueError::TaskNotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_restore_merges_or_replaces_queue_contents() {
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let project_id = server.create_project("restore".to_string(), None).await.unwrap();
+        let task = |name: &str| {
+            let mut task = Task::new(name).with_command("true").build();
+            task.project_id = Some(project_id);
+            task
+        };
+        let kept = server.submit_task(task("kept")).await.unwrap();
+        let mut bytes = Vec::new();
+        Backup::capture(server.storage.as_ref()).await.unwrap().write_to(&mut bytes).unwrap();
+
+        server.update_task_priority(kept, TaskPriority::High).await.unwrap();
+        let added = server.submit_task(task("added")).await.unwrap();
+
+        let archive = BackupArchive::read(bytes.as_slice()).unwrap();
+        let report = server.restore_backup(archive, RestoreMode::Merge).await.unwrap();
+        assert_eq!((report.restored, report.removed), (0, 0));
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].id, kept);
+        assert_eq!(server.get_task(kept).await.unwrap().priority, TaskPriority::High);
+
+        let archive = BackupArchive::read(bytes.as_slice()).unwrap();
+        let report = server.restore_backup(archive, RestoreMode::Replace).await.unwrap();
+        assert_eq!((report.restored, report.removed), (1, 1));
+        assert!(report.conflicts.is_empty());
+        assert_eq!(server.get_task(kept).await.unwrap().priority, TaskPriority::Normal);
+        assert!(server.get_task(added).await.is_err());
+        assert_eq!(server.storage.list_tasks().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retention_archives_finished_tasks() {
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap()
+        .with_retention(RetentionPolicy { max_age_days: 7, action: RetentionAction::Archive });
+        let project_id = server.create_project("retention".to_string(), None).await.unwrap();
+        let task = |name: &str| {
+            let mut task = Task::new(name).with_command("true").build();
+            task.project_id = Some(project_id);
+            task
+        };
+        let done = server.submit_task(task("done")).await.unwrap();
+        server.cancel_task(done, "not needed".to_string()).await.unwrap();
+        let open = server.submit_task(task("open")).await.unwrap();
+        let later = std::time::SystemTime::now() + std::time::Duration::from_secs(8 * 24 * 3600);
+
+        let report = server.apply_retention(std::time::SystemTime::now(), false).await.unwrap();
+        assert!(report.tasks.is_empty());
+
+        let report = server.apply_retention(later, true).await.unwrap();
+        assert_eq!((report.archived, report.deleted), (1, 0));
+        assert!(server.get_task(done).await.is_ok());
+
+        let report = server.apply_retention(later, false).await.unwrap();
+        assert_eq!(report.tasks[0].task_id, done);
+        assert!(server.get_task(done).await.is_err());
+        assert!(server.get_task(open).await.is_ok());
+        assert_eq!(server.get_archived_task(done).await.unwrap().status, TaskStatus::Cancelled);
+        assert!(server.storage.load_task_as_of(&done, chrono::Utc::now()).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cold_storage_keeps_completed_tasks_retrievable() {
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap()
+        .with_cold_storage(ColdStorageConfig { after_days: 30, ..Default::default() });
+        let project_id = server.create_project("cold".to_string(), None).await.unwrap();
+        let task = |name: &str| {
+            let mut task = Task::new(name).with_command("true").build();
+            task.project_id = Some(project_id);
+            task
+        };
+        let done = server.submit_task(task("done")).await.unwrap();
+        server.start_task_execution(done).await.unwrap();
+        let result = TaskResult::Success { output: String::new(), artifacts: vec![], metrics: TaskMetrics::default() };
+        server.complete_task_execution(done, result).await.unwrap();
+        let open = server.submit_task(task("open")).await.unwrap();
+        let later = std::time::SystemTime::now() + std::time::Duration::from_secs(31 * 24 * 3600);
+
+        assert!(server.move_to_cold_storage(std::time::SystemTime::now(), false).await.unwrap().tasks.is_empty());
+        let report = server.move_to_cold_storage(later, true).await.unwrap();
+        assert_eq!(report.tasks[0].task_id, done);
+        assert_eq!(server.storage.list_tasks().await.unwrap().len(), 2);
+
+        server.move_to_cold_storage(later, false).await.unwrap();
+        let stored: Vec<uuid::Uuid> = server.storage.list_tasks().await.unwrap().iter().map(|task| task.id).collect();
+        assert_eq!(stored, [open]);
+        assert_eq!(server.list_tasks(None, None).await.unwrap().len(), 1);
+        assert_eq!(server.get_task(done).await.unwrap().status, TaskStatus::Completed);
+        assert!(server.storage.load_task_as_of(&done, chrono::Utc::now()).await.unwrap().is_some());
+        assert!(server.move_to_cold_storage(later, false).await.unwrap().tasks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_api_is_versioned_and_still_unversioned() {
+        use axum::body::Body;
+        use tower::ServiceExt;
+
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let get = |path: &str| axum::http::Request::get(path).body(Body::empty()).unwrap();
+
+        let current = server.rest_router().oneshot(get("/api/v1/tasks")).await.unwrap();
+        assert_eq!(current.status(), StatusCode::OK);
+        assert!(current.headers().get("deprecation").is_none());
+
+        let legacy = server.rest_router().oneshot(get("/tasks")).await.unwrap();
+        assert_eq!(legacy.status(), StatusCode::OK);
+        assert_eq!(legacy.headers()["deprecation"], "true");
+        assert_eq!(legacy.headers()["link"], "</api/v1/tasks>; rel=\"successor-version\"");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_bodies_list_each_field() {
+        use axum::body::Body;
+        use tower::ServiceExt;
+
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let post = |path: &str, body: Value| {
+            axum::http::Request::post(path)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        let fields = |body: &[u8]| -> Vec<String> {
+            let body: Value = serde_json::from_slice(body).unwrap();
+            body["errors"].as_array().unwrap().iter().map(|e| e["field"].as_str().unwrap().to_string()).collect()
+        };
+
+        let response = server.rest_router().oneshot(post("/api/v1/projects", json!({ "name": " " }))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(fields(&body), ["name"]);
+
+        let task = json!({
+            "name": "x".repeat(crate::validation::MAX_NAME_LENGTH + 1),
+            "command": "",
+            "description": "Too long a name",
+            "task_type": "Simple",
+            "priority": "Normal",
+            "project_id": null,
+            "project": null,
+            "estimated_hours": null,
+            "tags": ["ok", ""],
+            "technical_specs": null,
+            "acceptance_criteria": null,
+            "ai_reviews_required": null
+        });
+        let response = server.rest_router().oneshot(post("/api/v1/tasks", task.clone())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(fields(&body), ["name", "command", "tags[1]"]);
+
+        let mut task = task;
+        task["project_id"] = json!("not-a-uuid");
+        let response = server.rest_router().oneshot(post("/api/v1/tasks", task)).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(fields(&body), ["project_id"]);
+        assert!(server.list_projects().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_answers_429_with_retry_after() {
+        use axum::body::Body;
+        use crate::rate_limiting::RateLimitBucket;
+        use tower::ServiceExt;
+
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap()
+        .with_rate_limits(&HttpRateLimitConfig {
+            write: RateLimitBucket { requests_per_minute: 1, burst: 2 },
+            ..HttpRateLimitConfig::default()
+        });
+        let create = |key: &str, name: &str| {
+            axum::http::Request::post("/api/v1/projects")
+                .header("content-type", "application/json")
+                .header("x-api-key", key)
+                .body(Body::from(json!({ "name": name }).to_string()))
+                .unwrap()
+        };
+
+        let tenant = server.create_tenant("agents".to_string(), None).await.unwrap();
+        let agent_a = server.issue_tenant_key(&tenant.id, "a".to_string(), None).await.unwrap().key;
+        let agent_b = server.issue_tenant_key(&tenant.id, "b".to_string(), None).await.unwrap().key;
+
+        for name in ["First", "Second"] {
+            let response = server.rest_router().oneshot(create(&agent_a, name)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        let response = server.rest_router().oneshot(create(&agent_a, "Third")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = response.headers()["retry-after"].to_str().unwrap().parse().unwrap();
+        assert!((1..=60).contains(&retry_after));
+
+        // Reads and other clients draw from buckets of their own
+        let list = axum::http::Request::get("/api/v1/projects").header("x-api-key", &agent_a).body(Body::empty()).unwrap();
+        assert_eq!(server.rest_router().oneshot(list).await.unwrap().status(), StatusCode::OK);
+        let response = server.rest_router().oneshot(create(&agent_b, "Third")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(server.list_projects().await.unwrap().len(), 3);
+
+        // Keys that identify no one share the bucket of the address they come from
+        for guess in ["guess-1", "guess-2"] {
+            let response = server.rest_router().oneshot(create(guess, guess)).await.unwrap();
+            assert_ne!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        }
+        let response = server.rest_router().oneshot(create("guess-3", "guess-3")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn test_body_limits_and_compression() {
+        use axum::body::Body;
+        use crate::config::RequestLimits;
+        use tower::ServiceExt;
+
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap()
+        .with_server_config(ServerConfig {
+            limits: RequestLimits { max_body_bytes: 256, ..RequestLimits::default() },
+            ..ServerConfig::default()
+        });
+        let post = |path: &str, body: Value| {
+            axum::http::Request::post(path)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        let project = json!({ "name": "Big", "description": "x".repeat(1000) });
+        let response = server.rest_router().oneshot(post("/api/v1/projects", project)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body: Value = serde_json::from_slice(&axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        assert_eq!(body["limit_bytes"], 256);
+        assert!(body["error"].as_str().unwrap().contains("server.limits.max_body_bytes"));
+
+        // Batches answer to the larger bulk limit
+        let batch = json!({ "tasks": [], "padding": "x".repeat(1000) });
+        let response = server.rest_router().oneshot(post("/api/v1/tasks/batch", batch)).await.unwrap();
+        assert_ne!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        for encoding in ["gzip", "br"] {
+            let request = axum::http::Request::get("/api/v1/openapi.json")
+                .header("accept-encoding", encoding)
+                .body(Body::empty())
+                .unwrap();
+            let response = server.rest_router().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()["content-encoding"], encoding);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tenants_only_see_their_own_data() {
+        use axum::body::Body;
+        use crate::tenants::TenancyConfig;
+        use tower::ServiceExt;
+
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap()
+        .with_server_config(ServerConfig {
+            tenancy: TenancyConfig { required: true, admin_key: Some("operator".to_string()), ..Default::default() },
+            ..ServerConfig::default()
+        });
+        let call = |method: &str, path: &str, key: &str, body: Option<Value>| {
+            let request = axum::http::Request::builder()
+                .method(method)
+                .uri(path)
+                .header("x-api-key", key)
+                .header("content-type", "application/json");
+            let response = server.rest_router().oneshot(request.body(body.map_or(Body::empty(), |b| Body::from(b.to_string()))).unwrap());
+            async move {
+                let response = response.await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, serde_json::from_slice::<Value>(&body).unwrap_or(Value::Null))
+            }
+        };
+
+        let mut keys = Vec::new();
+        for team in ["Alpha", "Beta"] {
+            let (status, tenant) = call("POST", "/api/v1/tenants", "operator", Some(json!({ "name": team }))).await;
+            assert_eq!(status, StatusCode::CREATED);
+            let path = format!("/api/v1/tenants/{}/keys", tenant["id"].as_str().unwrap());
+            let (status, key) = call("POST", &path, "operator", Some(json!({ "name": "ci" }))).await;
+            assert_eq!(status, StatusCode::CREATED);
+            keys.push(key["key"].as_str().unwrap().to_string());
+        }
+        let (alpha, beta) = (keys[0].as_str(), keys[1].as_str());
+
+        let (_, project) = call("POST", "/api/v1/projects", alpha, Some(json!({ "name": "Alpha project" }))).await;
+        let project_id = project["id"].as_str().unwrap().to_string();
+        let task = json!({
+            "name": "Build",
+            "command": "make",
+            "description": "Build Alpha",
+            "task_type": "Simple",
+            "priority": "Normal",
+            "project_id": project_id,
+            "project": null,
+            "estimated_hours": null,
+            "tags": null,
+            "technical_specs": null,
+            "acceptance_criteria": null,
+            "ai_reviews_required": null
+        });
+        let (status, created) = call("POST", "/api/v1/tasks", alpha, Some(task.clone())).await;
+        assert!(status.is_success(), "{}", created);
+        let task_id = created["task_id"].as_str().unwrap().to_string();
+
+        // Beta can't see, find or use what Alpha made
+        assert_eq!(call("GET", "/api/v1/projects", beta, None).await.1, json!([]));
+        let (_, tasks) = call("GET", "/api/v1/tasks", beta, None).await;
+        assert!(!tasks.to_string().contains(&task_id));
+        assert_eq!(call("GET", &format!("/api/v1/tasks/{}", task_id), beta, None).await.0, StatusCode::NOT_FOUND);
+        assert_eq!(call("DELETE", &format!("/api/v1/tasks/{}", task_id), beta, None).await.0, StatusCode::NOT_FOUND);
+        assert!(!call("POST", "/api/v1/tasks", beta, Some(task)).await.0.is_success());
+
+        // Alpha and the operator see it
+        assert_eq!(call("GET", &format!("/api/v1/tasks/{}", task_id), alpha, None).await.0, StatusCode::OK);
+        let (_, tasks) = call("GET", "/api/v1/tasks", "operator", None).await;
+        assert!(tasks.to_string().contains(&task_id));
+
+        assert_eq!(call("GET", "/api/v1/tenants", alpha, None).await.0, StatusCode::FORBIDDEN);
+        let import = json!({ "base_url": "http://169.254.169.254", "project_key": "OPS" });
+        assert_eq!(call("POST", "/api/v1/import/jira", alpha, Some(import)).await.0, StatusCode::FORBIDDEN);
+        assert_eq!(call("GET", "/api/v1/tasks", "tq_unknown", None).await.0, StatusCode::UNAUTHORIZED);
+        let anonymous = axum::http::Request::get("/api/v1/tasks").body(Body::empty()).unwrap();
+        assert_eq!(server.rest_router().oneshot(anonymous).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_key_replays_creation() {
+        use axum::body::Body;
+        use tower::ServiceExt;
+
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let create = |name: &str| {
+            axum::http::Request::post("/api/v1/projects")
+                .header("content-type", "application/json")
+                .header("idempotency-key", "create-1")
+                .body(Body::from(json!({ "name": name }).to_string()))
+                .unwrap()
+        };
+
+        let first = server.rest_router().oneshot(create("Retried")).await.unwrap();
+        assert!(first.status().is_success());
+        assert!(first.headers().get("idempotent-replayed").is_none());
+        let first = axum::body::to_bytes(first.into_body(), usize::MAX).await.unwrap();
+
+        let retry = server.rest_router().oneshot(create("Retried")).await.unwrap();
+        assert_eq!(retry.headers()["idempotent-replayed"], "true");
+        assert_eq!(axum::body::to_bytes(retry.into_body(), usize::MAX).await.unwrap(), first);
+        assert_eq!(server.list_projects().await.unwrap().len(), 1);
+
+        let reused = server.rest_router().oneshot(create("Other")).await.unwrap();
+        assert_eq!(reused.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(server.list_projects().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_tenants_sharing_an_idempotency_key_get_their_own_responses() {
+        use axum::body::Body;
+        use crate::tenants::TenancyConfig;
+        use tower::ServiceExt;
+
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap()
+        .with_server_config(ServerConfig {
+            tenancy: TenancyConfig { required: true, ..Default::default() },
+            ..ServerConfig::default()
+        });
+        let alpha = server.create_tenant("Alpha".to_string(), None).await.unwrap();
+        let beta = server.create_tenant("Beta".to_string(), None).await.unwrap();
+        let alpha_key = server.issue_tenant_key(&alpha.id, "ci".to_string(), None).await.unwrap().key;
+        let beta_key = server.issue_tenant_key(&beta.id, "ci".to_string(), None).await.unwrap().key;
+        let create = |key: &str| {
+            let request = axum::http::Request::post("/api/v1/projects")
+                .header("content-type", "application/json")
+                .header(tenants::API_KEY_HEADER, key)
+                .header("idempotency-key", "create-1")
+                .body(Body::from(json!({ "name": "Same" }).to_string()))
+                .unwrap();
+            server.rest_router().oneshot(request)
+        };
+
+        let first = create(&alpha_key).await.unwrap();
+        assert!(first.status().is_success());
+        let first = axum::body::to_bytes(first.into_body(), usize::MAX).await.unwrap();
+        // The same key and body from another tenant runs again instead of
+        // handing back the first tenant's project
+        let second = create(&beta_key).await.unwrap();
+        assert!(second.status().is_success());
+        assert!(second.headers().get("idempotent-replayed").is_none());
+        assert_ne!(axum::body::to_bytes(second.into_body(), usize::MAX).await.unwrap(), first);
+        assert_eq!(tenants::with_tenant(Some(beta.id), server.list_projects()).await.unwrap().len(), 1);
+
+        let retry = create(&alpha_key).await.unwrap();
+        assert_eq!(retry.headers()["idempotent-replayed"], "true");
+        assert_eq!(axum::body::to_bytes(retry.into_body(), usize::MAX).await.unwrap(), first);
+    }
+
+    #[tokio::test]
+    async fn test_etags_follow_task_versions() {
+        use axum::body::Body;
+        use tower::ServiceExt;
+
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let project_id = server.create_project("etags".to_string(), None).await.unwrap();
+        let mut task = Task::new("cached").with_command("true").build();
+        task.project_id = Some(project_id);
+        let task_id = server.submit_task(task).await.unwrap();
+        assert_eq!(server.get_task(task_id).await.unwrap().version, 1);
+
+        let path = format!("/api/v1/tasks/{}", task_id);
+        let response = server.rest_router()
+            .oneshot(axum::http::Request::get(&path).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["etag"], "\"1\"");
+        let cached = axum::http::Request::get(&path).header("if-none-match", "\"1\"").body(Body::empty()).unwrap();
+        let response = server.rest_router().oneshot(cached).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        let rename = |name: &str| crate::core::UpdateTaskRequest { name: Some(name.to_string()), ..Default::default() };
+        let current = Precondition::Versions(vec![1]);
+        let task = server.patch_task(task_id, rename("renamed"), &current).await.unwrap();
+        assert_eq!(task.version, 2);
+        // A second writer still holding version 1 must not overwrite the rename
+        assert!(matches!(
+            server.patch_task(task_id, rename("lost"), &current).await,
+            Err(TaskQueueError::PreconditionFailed { version: 2 })
+        ));
+        assert_eq!(server.get_task(task_id).await.unwrap().name, "renamed");
+    }
+
+    #[tokio::test]
+    async fn test_patch_changes_only_given_fields() {
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let project_id = server.create_project("patch".to_string(), None).await.unwrap();
+        let mut task = Task::new("patched").with_command("true").build();
+        task.project_id = Some(project_id);
+        task.technical_specs = Some("Keep me".to_string());
+        let task_id = server.submit_task(task).await.unwrap();
+
+        let request = crate::core::UpdateTaskRequest {
+            description: Some("Reworded".to_string()),
+            project_id: Some(None),
+            ..Default::default()
+        };
+        let task = server.patch_task(task_id, request, &Precondition::Any).await.unwrap();
+        assert_eq!((task.name.as_str(), task.description.as_str()), ("patched", "Reworded"));
+        assert_eq!(task.project_id, None);
+        assert_eq!(task.technical_specs.as_deref(), Some("Keep me"));
+
+        let missing = uuid::Uuid::new_v4();
+        let request = crate::core::UpdateTaskRequest {
+            name: Some(" ".to_string()),
+            project_id: Some(Some(missing)),
+            ..Default::default()
+        };
+        match server.patch_task(task_id, request, &Precondition::Any).await {
+            Err(TaskQueueError::InvalidFields { errors }) => {
+                let fields: Vec<_> = errors.iter().map(|e| e.field.as_str()).collect();
+                assert_eq!(fields, ["name", "project_id"]);
+            }
+            other => panic!("Expected invalid fields, got {:?}", other),
+        }
+        assert_eq!(server.get_task(task_id).await.unwrap().name, "patched");
+    }
+
+    #[tokio::test]
+    async fn test_bulk_operations_report_each_task() {
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let project_id = server.create_project("bulk".to_string(), None).await.unwrap();
+        let mut ids = Vec::new();
+        for name in ["a", "b", "c"] {
+            let mut task = Task::new(name).with_command("true").build();
+            task.project_id = Some(project_id);
+            task.status = TaskStatus::Pending;
+            ids.push(server.submit_task(task).await.unwrap());
+        }
+        let missing = uuid::Uuid::new_v4();
+
+        let report = server.bulk_update(BulkRequest {
+            operation: BulkOperation::SetPriority { priority: TaskPriority::Critical },
+            task_ids: vec![ids[0], missing],
+            filter: None,
+        }).await.unwrap();
+        assert_eq!((report.matched, report.succeeded, report.failed), (2, 1, 1));
+        assert_eq!(report.items[1].task_id, missing);
+        assert!(report.items[1].error.is_some());
+        assert_eq!(server.get_task(ids[0]).await.unwrap().priority, TaskPriority::Critical);
+
+        let filter = TaskFilter { priorities: vec![TaskPriority::Normal], ..Default::default() };
+        let report = server.bulk_update(BulkRequest {
+            operation: BulkOperation::Cancel { reason: None },
+            task_ids: Vec::new(),
+            filter: Some(filter),
+        }).await.unwrap();
+        assert_eq!((report.matched, report.succeeded), (2, 2));
+        assert_eq!(server.get_task(ids[2]).await.unwrap().status, TaskStatus::Cancelled);
+        assert_eq!(server.get_task(ids[0]).await.unwrap().status, TaskStatus::Pending);
+    }
+
+    #[tokio::test]
+    async fn test_task_pages_sort_and_count() {
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let project_id = server.create_project("pages".to_string(), None).await.unwrap();
+        let mut ids = Vec::new();
+        for priority in [TaskPriority::Low, TaskPriority::Critical, TaskPriority::Normal, TaskPriority::Critical] {
+            let mut task = Task::new("page").with_command("true").build();
+            task.project_id = Some(project_id);
+            task.priority = priority;
+            ids.push(server.submit_task(task).await.unwrap());
+        }
+        let page = |sort, cursor, offset| PageRequest { cursor, offset, limit: 2, sort };
+
+        let first = server.list_tasks_page(&TaskFilter::default(), page(SortField::Priority, None, 0)).await.unwrap();
+        assert_eq!(first.total, 4);
+        assert_eq!(first.tasks.iter().map(|task| task.id).collect::<Vec<_>>(), [ids[1], ids[3]]);
+        let second = server.list_task_summaries_page(&TaskFilter::default(), page(SortField::Priority, first.next_cursor, 0)).await.unwrap();
+        assert_eq!(second.tasks.iter().map(|task| task.id).collect::<Vec<_>>(), [ids[2], ids[0]]);
+        assert!(second.next_cursor.is_none());
+
+        let skipped = server.list_tasks_page(&TaskFilter::default(), page(SortField::CreatedAt, None, 3)).await.unwrap();
+        assert_eq!((skipped.tasks[0].id, skipped.total), (ids[3], 4));
+        assert!(server.list_tasks_page(&TaskFilter::default(), page(SortField::Name, None, 0)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bare_task_listing_is_paged() {
+        use axum::body::Body;
+        use tower::ServiceExt;
+
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let project_id = server.create_project("bare".to_string(), None).await.unwrap();
+        for n in 0..=DEFAULT_PAGE_SIZE {
+            let mut task = Task::new(&format!("task-{}", n)).with_command("true").build();
+            task.project_id = Some(project_id);
+            server.submit_task(task).await.unwrap();
+        }
+        let list = |uri: String| {
+            let server = server.clone();
+            async move {
+                let request = axum::http::Request::get(uri).body(Body::empty()).unwrap();
+                let response = server.rest_router().oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                serde_json::from_slice::<Value>(&body).unwrap()
+            }
+        };
+
+        // No parameters still yields a bounded page and a cursor to the rest
+        let first = list("/api/v1/tasks".to_string()).await;
+        assert_eq!(first["tasks"].as_array().unwrap().len(), DEFAULT_PAGE_SIZE);
+        assert_eq!(first["total"], DEFAULT_PAGE_SIZE + 1);
+        let cursor = first["next_cursor"].as_str().unwrap();
+
+        let rest = list(format!("/api/v1/tasks?cursor={}", cursor)).await;
+        assert_eq!(rest["tasks"].as_array().unwrap().len(), 1);
+        assert!(rest["next_cursor"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_task_summaries_match_full_listings() {
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let alpha = server.create_project("alpha".to_string(), None).await.unwrap();
+        let beta = server.create_project("beta".to_string(), None).await.unwrap();
+        for (name, project_id, phase) in [
+            ("compile", alpha, TaskStatus::Planning),
+            ("link", alpha, TaskStatus::Testing),
+            ("package", alpha, TaskStatus::Planning),
+            ("deploy", beta, TaskStatus::Testing),
+        ] {
+            let mut task = Task::new(name).with_command("true").build();
+            task.project_id = Some(project_id);
+            task.current_phase = phase;
+            server.submit_task(task).await.unwrap();
+        }
+
+        let in_alpha = TaskFilter { project_id: Some(alpha), ..TaskFilter::default() };
+        let mut tasks = server.list_matching_tasks(&in_alpha).await.unwrap();
+        let mut summaries = server.list_matching_task_summaries(&in_alpha).await.unwrap();
+        tasks.sort_by_key(|task| task.id);
+        summaries.sort_by_key(|summary| summary.id);
+        assert_eq!(summaries.len(), 3);
+        for (task, summary) in tasks.iter().zip(&summaries) {
+            assert_eq!((summary.id, &summary.name, &summary.status), (task.id, &task.name, &task.status));
+            assert_eq!(summary.project_id, Some(alpha));
+        }
+
+        // Summaries carry the effective status the filter matched on
+        let testing = server.list_matching_task_summaries(&TaskFilter::of(None, Some("testing".to_string()))).await.unwrap();
+        assert!(testing.iter().all(|summary| summary.status == TaskStatus::Testing));
+        let mut names: Vec<_> = testing.iter().map(|summary| summary.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, ["deploy", "link"]);
+
+        let page = PageRequest { cursor: Some("not-a-cursor".to_string()), limit: 2, ..PageRequest::default() };
+        assert!(matches!(
+            server.list_task_summaries_page(&in_alpha, page).await,
+            Err(TaskQueueError::ValidationError { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_shared_storage_listings_are_scanned_in_batches() {
+        let (a, b) = replicas();
+        let project_id = a.create_project("scan".to_string(), None).await.unwrap();
+        for i in 0..(TASK_SCAN_BATCH + 3) {
+            let mut task = Task::new(&format!("task-{}", i)).with_command("true").build();
+            task.project_id = Some(project_id);
+            a.submit_task(task).await.unwrap();
+        }
+
+        assert_eq!(b.list_task_summaries(None, None).await.unwrap().len(), TASK_SCAN_BATCH + 3);
+        let page = b.list_task_summaries_page(&TaskFilter::default(), PageRequest { limit: 5, ..PageRequest::default() }).await.unwrap();
+        assert_eq!((page.tasks.len(), page.total), (5, TASK_SCAN_BATCH + 3));
+        let rest = b.list_tasks_page(&TaskFilter::default(), PageRequest { cursor: page.next_cursor, limit: TASK_SCAN_BATCH * 2, ..PageRequest::default() }).await.unwrap();
+        assert_eq!(rest.tasks.len(), TASK_SCAN_BATCH - 2);
+        assert!(rest.next_cursor.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_ready_tasks_follow_execution_status() {
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let project_id = server.create_project("ready".to_string(), None).await.unwrap();
+        let mut build = Task::new("build").with_command("make").build();
+        build.project_id = Some(project_id);
+        let mut test = Task::new("test").with_command("make test").build();
+        test.project_id = Some(project_id);
+        test.add_dependency(build.id, None, DependencyCondition::Success, true);
+        build.status = TaskStatus::Pending;
+        test.status = TaskStatus::Pending;
+        let build = server.submit_task(build).await.unwrap();
+        let test = server.submit_task(test).await.unwrap();
+        let ready = |queue: ReadyQueue| queue.map(|task| task.id).collect::<Vec<_>>();
+
+        assert_eq!(ready(server.ready_tasks().await.unwrap()), [build]);
+        server.start_task_execution(build).await.unwrap();
+        assert!(ready(server.ready_tasks().await.unwrap()).is_empty());
+        assert_eq!(server.leased_tasks().await.unwrap().len(), 1);
+
+        let result = TaskResult::Success { output: String::new(), artifacts: vec![], metrics: TaskMetrics::default() };
+        server.complete_task_execution(build, result).await.unwrap();
+        assert_eq!(ready(server.ready_tasks().await.unwrap()), [test]);
+        assert!(server.leased_tasks().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_storage_statistics_reach_the_metrics() {
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        server.create_project("measured".to_string(), None).await.unwrap();
+
+        let stats = server.refresh_storage_metrics().await.unwrap();
+        assert_eq!(stats.records["projects"], 1);
+        assert!(stats.writes >= 1 && stats.last_compaction.is_none());
+        let metrics = server.metrics.get_metrics();
+        assert_eq!(metrics["storage_records{tree=\"projects\"}"], 1.0);
+        assert_eq!(metrics["storage_last_compaction_timestamp_seconds"], 0.0);
+
+        let stats = server.compact_storage().await.unwrap();
+        let compacted_at = stats.last_compaction.unwrap().timestamp() as f64;
+        assert_eq!(server.metrics.get_metrics()["storage_last_compaction_timestamp_seconds"], compacted_at);
+    }
+
+    #[tokio::test]
+    async fn test_import_reports_lines_that_fail() {
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let project_id = server.create_project("imported".to_string(), None).await.unwrap();
+        let line = |name: &str, project_id: uuid::Uuid| json!({
+            "name": name, "command": "make", "description": "from the old queue", "technical_specs": null,
+            "acceptance_criteria": null, "project": null, "task_type": "Simple", "priority": "Normal",
+            "project_id": project_id, "estimated_hours": null, "tags": null, "ai_reviews_required": null,
+        }).to_string();
+        let input = [
+            line("build", project_id),
+            String::new(),
+            "{not json".to_string(),
+            line("orphan", uuid::Uuid::new_v4()),
+            line("deploy", project_id),
+        ].join("\n");
+
+        let report = server.import_tasks(input.as_bytes(), true).await.unwrap();
+        assert_eq!((report.lines, report.imported), (4, 2));
+        assert!(server.storage.list_tasks().await.unwrap().is_empty());
+
+        let report = server.import_tasks(input.as_bytes(), false).await.unwrap();
+        assert_eq!(report.errors.iter().map(|e| e.line).collect::<Vec<_>>(), [3, 4]);
+        assert!(report.errors[1].error.contains("does not exist"));
+        assert_eq!(report.task_ids.len(), 2);
+        assert_eq!(server.get_task(report.task_ids[1]).await.unwrap().name, "deploy");
+    }
+
+    #[tokio::test]
+    async fn test_mutations_are_audited() {
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let agent = uuid::Uuid::new_v4();
+        let project_id = server.create_project("audited".to_string(), None).await.unwrap();
+        let mut task = Task::new("audited").with_command("true").build();
+        task.project_id = Some(project_id);
+
+        let task_id = audit::with_source(AuditSource::Rest, agents::with_agent(Some(agent), server.submit_task(task)))
+            .await
+            .unwrap();
+        let since = chrono::Utc::now();
+        audit::with_source(AuditSource::Mcp, server.cancel_task(task_id, "not needed".to_string())).await.unwrap();
+        server.delete_task(task_id).await.unwrap();
+
+        let query = AuditQuery { entity: Some(EntityFilter::Id(task_id)), ..Default::default() };
+        let entries = server.list_audit(&query).await.unwrap();
+        let (first, last) = (entries.first().unwrap(), entries.last().unwrap());
+        assert_eq!((first.action, first.source, first.actor), (audit::AuditAction::Create, AuditSource::Rest, Some(agent)));
+        assert_eq!((last.action, last.source, last.actor), (audit::AuditAction::Delete, AuditSource::Internal, None));
+        let cancel = entries.iter().find(|entry| entry.action == audit::AuditAction::Cancel).unwrap();
+        assert_eq!(cancel.source, AuditSource::Mcp);
+        let status = cancel.changes.iter().find(|change| change.field == "status").unwrap();
+        assert_eq!(status.after, Some(json!("Cancelled")));
+
+        let query = AuditQuery { entity: EntityFilter::parse("task"), since: Some(since), ..Default::default() };
+        let entries = server.list_audit(&query).await.unwrap();
+        assert!(entries.iter().all(|entry| entry.entity_id == task_id && entry.at >= since));
+        assert!(entries.iter().all(|entry| entry.action != audit::AuditAction::Create));
+        let query = AuditQuery { entity: EntityFilter::parse("project"), ..Default::default() };
+        assert_eq!(server.list_audit(&query).await.unwrap()[0].entity_id, project_id);
+    }
+
+    #[tokio::test]
+    async fn test_deleting_a_project_unlinks_its_tasks_in_one_commit() {
+        let storage = Arc::new(StorageEngine::temporary().unwrap());
+        let server = TaskQueueServer::with_components(storage.clone(), Arc::new(VectorizerIntegration::new_dummy())).await.unwrap();
+        let project_id = server.create_project("Release".to_string(), None).await.unwrap();
+        let mut task = Task::new("Build").with_command("cargo build").build();
+        task.project_id = Some(project_id);
+        let task_id = server.submit_task(task).await.unwrap();
+
+        server.delete_project(&project_id).await.unwrap();
+        assert!(server.get_project(&project_id).await.unwrap().is_none());
+        assert_eq!(server.get_task(task_id).await.unwrap().project_id, None);
+        assert_eq!(storage.load_task(&task_id).await.unwrap().unwrap().project_id, None);
+
+        let entries = server.list_audit(&AuditQuery::default()).await.unwrap();
+        let (deleted, unlinked) = (&entries[entries.len() - 2], &entries[entries.len() - 1]);
+        assert_eq!((deleted.entity_id, deleted.action), (project_id, crate::audit::AuditAction::Delete));
+        assert_eq!((unlinked.entity_id, unlinked.action), (task_id, crate::audit::AuditAction::Update));
+        assert!(matches!(server.delete_project(&project_id).await, Err(TaskQueueError::ProjectNotFound { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_failed_project_updates_leave_the_project_as_stored() {
+        let storage = Arc::new(StorageEngine::temporary().unwrap());
+        let server = TaskQueueServer::with_components(storage.clone(), Arc::new(VectorizerIntegration::new_dummy())).await.unwrap();
+        let project_id = server.create_project("Release".to_string(), None).await.unwrap();
+        let rename = || ProjectUpdate {
+            name: Some("Renamed".to_string()),
+            description: None,
+            status: None,
+            tags: None,
+            metadata: None,
+        };
+
+        storage.fail_commits(true);
+        assert!(server.update_project(&project_id, rename(), &Precondition::Any).await.is_err());
+        let project = server.get_project(&project_id).await.unwrap().unwrap();
+        assert_eq!((project.name.as_str(), project.version), ("Release", 1));
+
+        // The version was not spent, so a retry expecting it succeeds
+        storage.fail_commits(false);
+        server.update_project(&project_id, rename(), &Precondition::Versions(vec![1])).await.unwrap();
+        let project = server.get_project(&project_id).await.unwrap().unwrap();
+        assert_eq!((project.name.as_str(), project.version), ("Renamed", 2));
+        assert_eq!(storage.load_project(&project_id).await.unwrap().unwrap().name, "Renamed");
+    }
+
+    #[tokio::test]
+    async fn test_storage_check_repairs_tasks_in_memory_too() {
+        let storage = Arc::new(StorageEngine::temporary().unwrap());
+        let server = TaskQueueServer::with_components(storage.clone(), Arc::new(VectorizerIntegration::new_dummy())).await.unwrap();
+        let project_id = server.create_project("Release".to_string(), None).await.unwrap();
+        let mut task = Task::new("Build").with_command("cargo build").build();
+        task.project_id = Some(project_id);
+        let task_id = server.submit_task(task).await.unwrap();
+        // Gone from storage behind the server's back
+        storage.delete_project(&project_id).await.unwrap();
+
+        let report = server.check_storage(FsckAction::Check).await.unwrap();
+        assert_eq!(report.count(fsck::Resolution::Reported), 1);
+        assert!(server.get_task(task_id).await.unwrap().project_id.is_some());
+
+        server.check_storage(FsckAction::Repair).await.unwrap();
+        assert_eq!(server.get_task(task_id).await.unwrap().project_id, None);
+        server.check_storage(FsckAction::Quarantine).await.unwrap();
+        assert!(server.check_storage(FsckAction::Check).await.unwrap().is_clean());
+    }
+
+    #[tokio::test]
+    async fn test_search_ranks_and_follows_edits() {
+        use axum::body::Body;
+        use tower::ServiceExt;
+
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let project_id = server.create_project("web".to_string(), None).await.unwrap();
+        let task = |name: &str| {
+            let mut task = Task::new(name).with_command("make").build();
+            task.project_id = Some(project_id);
+            task
+        };
+        let mut in_specs = task("Tidy the repository");
+        in_specs.technical_specs = Some("Cache the deployment layers".to_string());
+        let in_specs = server.submit_task(in_specs).await.unwrap();
+        let in_name = server.submit_task(task("Deploy to staging")).await.unwrap();
+        let other = server.submit_task(task("Write release notes")).await.unwrap();
+
+        let results = server.search_tasks("deploy*", 10).await.unwrap();
+        let ids: Vec<_> = results.results.iter().map(|hit| hit.task.id).collect();
+        assert_eq!(ids, [in_name, in_specs]);
+        assert_eq!(results.results[0].highlights[&SearchField::Name], "<mark>Deploy</mark> to staging");
+        assert_eq!(server.search_tasks("deploy staging", 10).await.unwrap().total, 1);
+
+        // Edits and deletes reach the index
+        server.update_task(other, None, None, Some("Deploy notes".to_string()), None, None, None).await.unwrap();
+        server.delete_task(in_name).await.unwrap();
+        let ids: Vec<_> = server.search_tasks("deploy", 10).await.unwrap().results.into_iter().map(|hit| hit.task.id).collect();
+        assert_eq!(ids, [other]);
+
+        let response = server.rest_router()
+            .oneshot(axum::http::Request::builder().uri("/api/v1/tasks/search?q=release").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = server.rest_router()
+            .oneshot(axum::http::Request::builder().uri("/api/v1/tasks/search?q=%20").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_event_stream_filters_and_resumes() {
+        use axum::body::Body;
+        use crate::events::SseParser;
+        use futures_util::StreamExt;
+        use tower::ServiceExt;
+
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let open = |path: &str, last_event: Option<u64>| {
+            let mut request = axum::http::Request::builder().uri(path);
+            if let Some(last_event) = last_event {
+                request = request.header("last-event-id", last_event.to_string());
+            }
+            server.rest_router().oneshot(request.body(Body::empty()).unwrap())
+        };
+        async fn next_frames(body: &mut axum::body::BodyDataStream, count: usize) -> Vec<crate::events::SseFrame> {
+            let mut parser = SseParser::new();
+            let mut frames = Vec::new();
+            while frames.len() < count {
+                let chunk = tokio::time::timeout(Duration::from_secs(5), body.next()).await.unwrap().unwrap().unwrap();
+                frames.extend(parser.feed(&chunk));
+            }
+            frames
+        }
+
+        let response = open("/api/v1/events?kinds=project_created,task_created", None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let mut body = response.into_body().into_data_stream();
+
+        let project_id = server.create_project("web".to_string(), None).await.unwrap();
+        let mut task = Task::new("Build").with_command("make").build();
+        task.project_id = Some(project_id);
+        let task_id = server.submit_task(task).await.unwrap();
+        server.update_project(&project_id, ProjectUpdate {
+            name: None,
+            description: Some("Website".to_string()),
+            status: None,
+            tags: None,
+            metadata: None,
+        }, &Precondition::Any).await.unwrap();
+
+        let frames = next_frames(&mut body, 2).await;
+        assert_eq!(frames[0].event.as_deref(), Some("project_created"));
+        assert_eq!(frames[1].event.as_deref(), Some("task_created"));
+        let created: TaskEvent = serde_json::from_str(&frames[1].data).unwrap();
+        assert_eq!(created.task_id, Some(task_id));
+
+        // Reconnecting after the project event replays what followed it
+        let response = open("/api/v1/events", frames[0].id.as_deref().map(|id| id.parse().unwrap())).await.unwrap();
+        let frames = next_frames(&mut response.into_body().into_data_stream(), 2).await;
+        let kinds: Vec<_> = frames.iter().map(|frame| frame.event.as_deref().unwrap()).collect();
+        assert_eq!(kinds, ["task_created", "project_updated"]);
+
+        assert_eq!(open("/api/v1/events?kinds=nonsense", None).await.unwrap().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_wait_returns_once_the_task_is_done() {
+        use axum::body::Body;
+        use tower::ServiceExt;
+
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let project_id = server.create_project("web".to_string(), None).await.unwrap();
+        let mut task = Task::new("Build").with_command("make").build();
+        task.project_id = Some(project_id);
+        let task_id = server.submit_task(task).await.unwrap();
+        let wait = |timeout: &str| {
+            let uri = format!("/api/v1/tasks/{}/wait?timeout={}", task_id, timeout);
+            let response = server.rest_router().oneshot(axum::http::Request::builder().uri(uri).body(Body::empty()).unwrap());
+            async move {
+                let response = response.await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, serde_json::from_slice::<Value>(&body).unwrap_or(Value::Null))
+            }
+        };
+
+        let (status, body) = wait("50ms").await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert_eq!(body["finished"], json!(false));
+        assert_eq!(wait("soon").await.0, StatusCode::BAD_REQUEST);
+
+        let waiting = tokio::spawn(wait("10s"));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        server.cancel_task(task_id, "not needed".to_string()).await.unwrap();
+        let (status, body) = tokio::time::timeout(Duration::from_secs(5), waiting).await.unwrap().unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!((body["status"].clone(), body["finished"].clone()), (json!("Cancelled"), json!(true)));
+
+        assert_eq!(parse_wait_timeout("2m"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_wait_timeout("15"), Some(Duration::from_secs(15)));
+    }
+
+    #[tokio::test]
+    async fn test_logs_stream_as_plain_text() {
+        use axum::body::Body;
+        use tower::ServiceExt;
+
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let project_id = server.create_project("logs".to_string(), None).await.unwrap();
+        let mut task = Task::new("build").with_command("echo compiling && echo linking").build();
+        task.project_id = Some(project_id);
+        let task_id = server.submit_task(task.clone()).await.unwrap();
+        let result = server.execute_task(&ExecutorRegistry::new(), &task).await;
+        server.complete_task_execution(task_id, result).await.unwrap();
+
+        let logs = |query: &str, accept: &str| {
+            let request = axum::http::Request::builder()
+                .uri(format!("/api/v1/tasks/{}/logs?{}", task_id, query))
+                .header("accept", accept)
+                .header("accept-encoding", "gzip")
+                .body(Body::empty())
+                .unwrap();
+            let response = server.rest_router().oneshot(request);
+            async move {
+                let response = response.await.unwrap();
+                let headers = response.headers().clone();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX);
+                let body = tokio::time::timeout(Duration::from_secs(5), body).await.unwrap().unwrap();
+                (headers, String::from_utf8(body.to_vec()).unwrap())
+            }
+        };
+
+        // The task has finished, so following ends after its output
+        let (headers, body) = logs("follow=true", "text/plain").await;
+        assert_eq!(body, "compiling\nlinking\n");
+        assert_eq!(headers["content-type"], "text/plain; charset=utf-8");
+        assert_eq!(headers["content-encoding"], "identity");
+        assert_eq!(logs("format=text", "*/*").await.1, "compiling\nlinking\n");
+        assert!(logs("follow=true", "text/event-stream").await.1.starts_with("id: 0\n"));
+    }
+
+    #[tokio::test]
+    async fn test_timeseries_counts_recorded_activity() {
+        use axum::body::Body;
+        use tower::ServiceExt;
+
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let project_id = server.create_project("stats".to_string(), None).await.unwrap();
+        for name in ["Build", "Deploy"] {
+            let mut task = Task::new(name).with_command("make").build();
+            task.project_id = Some(project_id);
+            server.submit_task(task).await.unwrap();
+        }
+        let mut task = Task::new("Cleanup").with_command("make").build();
+        task.project_id = Some(project_id);
+        let task_id = server.submit_task(task).await.unwrap();
+        server.cancel_task(task_id, "not needed".to_string()).await.unwrap();
+        // Deleted tasks still count
+        server.delete_task(task_id).await.unwrap();
+
+        let get = |query: &str| {
+            let request = axum::http::Request::builder().uri(format!("/api/v1/stats/timeseries?{}", query)).body(Body::empty()).unwrap();
+            let response = server.rest_router().oneshot(request);
+            async move {
+                let response = response.await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, serde_json::from_slice::<Value>(&body).unwrap())
+            }
+        };
+        let (status, series) = get("window=1h&bucket=15m").await;
+        assert_eq!(status, StatusCode::OK);
+        let buckets = series["buckets"].as_array().unwrap();
+        assert_eq!(buckets.len(), 4);
+        // The requests may straddle two buckets
+        let total = |field: &str| buckets.iter().map(|bucket| bucket[field].as_u64().unwrap()).sum::<u64>();
+        assert_eq!((total("created"), total("cancelled"), total("throughput"), total("completed")), (3, 1, 1, 0));
+        assert!(buckets.iter().any(|bucket| bucket["completion_rate"] == json!(0.0)));
+
+        let (status, error) = get("window=1y").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(error["error"].as_str().unwrap().contains("window"));
+    }
+
+    #[tokio::test]
+    async fn test_queues_limit_their_tasks_and_set_retries() {
+        use axum::body::Body;
+        use tower::ServiceExt;
+
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let project_id = server.create_project("queues".to_string(), None).await.unwrap();
+        let request = |method: &str, path: &str, body: Value| {
+            axum::http::Request::builder()
+                .method(method)
+                .uri(path)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        let ci = json!({ "name": "ci", "max_concurrency": 1, "retry": { "attempts": 0, "delay_secs": 5 } });
+
+        let response = server.rest_router().oneshot(request("POST", "/api/v1/queues", ci.clone())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let response = server.rest_router().oneshot(request("POST", "/api/v1/queues", ci)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let invalid = json!({ "max_concurrency": 0 });
+        let response = server.rest_router().oneshot(request("PUT", "/api/v1/queues/review", invalid)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let task = |queue: &str| json!({
+            "name": "build",
+            "command": "make",
+            "description": "Build it",
+            "task_type": "Simple",
+            "priority": "Normal",
+            "project_id": project_id,
+            "project": null,
+            "estimated_hours": null,
+            "tags": null,
+            "technical_specs": null,
+            "acceptance_criteria": null,
+            "ai_reviews_required": null,
+            "queue": queue
+        });
+        let response = server.rest_router().oneshot(request("POST", "/api/v1/tasks", task("nope"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = server.rest_router().oneshot(request("POST", "/api/v1/tasks", task("ci"))).await.unwrap();
+        let body: Value = serde_json::from_slice(&axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        let submitted = server.get_task(body["task_id"].as_str().unwrap().parse().unwrap()).await.unwrap();
+        assert_eq!(queues::queue_name(&submitted), Some("ci"));
+        assert_eq!((submitted.retry_attempts, submitted.retry_delay), (0, Duration::from_secs(5)));
+
+        // Only one task of the queue runs at a time
+        for name in ["first", "second"] {
+            let mut task = Task::new(name).with_command("make").build();
+            task.project_id = Some(project_id);
+            task.status = TaskStatus::Pending;
+            task.metadata.insert(queues::QUEUE_KEY.to_string(), json!("ci"));
+            server.submit_task(task).await.unwrap();
+        }
+        assert!(server.claim_next_task("w1", None).await.unwrap().is_some());
+        assert!(server.claim_next_task("w2", None).await.unwrap().is_none());
+        let status = server.get_queue("ci").await.unwrap().unwrap();
+        assert_eq!((status.pending, status.running), (1, 1));
+
+        // Without the queue its tasks run unlimited
+        assert!(server.delete_queue("ci").await.unwrap());
+        assert!(server.claim_next_task("w2", None).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_refuses_submissions_and_drain_stops_claims() {
+        use axum::body::Body;
+        use tower::ServiceExt;
+
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let project_id = server.create_project("maintenance".to_string(), None).await.unwrap();
+        let post = |path: &str| {
+            axum::http::Request::builder()
+                .method("POST")
+                .uri(path)
+                .header("content-type", "application/json")
+                .body(Body::from(json!({ "name": "build", "command": "make", "project_id": project_id }).to_string()))
+                .unwrap()
+        };
+        for name in ["first", "second"] {
+            let mut task = Task::new(name).with_command("make").build();
+            task.project_id = Some(project_id);
+            task.status = TaskStatus::Pending;
+            server.submit_task(task).await.unwrap();
+        }
+
+        let response = server.rest_router().oneshot(post("/api/v1/admin/maintenance")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = server.rest_router().oneshot(post("/api/v1/tasks")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[axum::http::header::RETRY_AFTER], "30");
+
+        // Queued tasks still dispatch in maintenance, until draining
+        let running = server.claim_next_task("w1", None).await.unwrap().unwrap();
+        let response = server.rest_router().oneshot(post("/api/v1/admin/drain?timeout=0s")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        assert!(server.claim_next_task("w1", None).await.unwrap().is_none());
+
+        let result = TaskResult::Success { output: String::new(), artifacts: vec![], metrics: TaskMetrics::default() };
+        server.complete_leased_task(running.id, "w1", result).await.unwrap();
+        let response = server.rest_router().oneshot(post("/api/v1/admin/drain?timeout=1s")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = server.rest_router().oneshot(post("/api/v1/admin/resume")).await.unwrap();
+        let status: MaintenanceStatus = serde_json::from_slice(&axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+        assert_eq!(status, MaintenanceStatus { maintenance: false, draining: false, running: 0 });
+        assert!(server.claim_next_task("w1", None).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_keys_and_keyless_mcp_clients_are_turned_away() {
+        use crate::config::McpTransport;
+        use crate::tenants::TenancyConfig;
+        use axum::body::Body;
+        use tower::ServiceExt;
+
+        // Tenancy isn't required, so REST still lets keyless requests in
+        let server = Arc::new(TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap()
+        .with_server_config(ServerConfig {
+            tenancy: TenancyConfig { admin_key: Some("operator".to_string()), ..Default::default() },
+            ..ServerConfig::default()
+        }));
+        let list = |key: Option<&str>| {
+            let mut request = axum::http::Request::builder().uri("/api/v1/projects");
+            if let Some(key) = key {
+                request = request.header(tenants::API_KEY_HEADER, key);
+            }
+            server.rest_router().oneshot(request.body(Body::empty()).unwrap())
+        };
+        assert_eq!(list(None).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(list(Some("operator")).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(list(Some("made-up")).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        assert!(matches!(server.authenticate(Some("operator")).await, Ok(Principal::Operator)));
+        assert!(server.authenticate(Some("operatorx")).await.is_err());
+
+        let router = create_mcp_router(server.clone(), McpTransport::StreamableHttp).await;
+        let initialize = |key: Option<&str>| {
+            let mut request = axum::http::Request::post(crate::mcp::MCP_STREAMABLE_PATH)
+                .header("content-type", "application/json")
+                .header("accept", "application/json, text/event-stream");
+            if let Some(key) = key {
+                request = request.header("authorization", format!("Bearer {}", key));
+            }
+            let body = json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "initialize",
+                "params": { "protocolVersion": "2025-03-26", "capabilities": {}, "clientInfo": { "name": "test", "version": "1.0" } }
+            });
+            router.clone().oneshot(request.body(Body::from(body.to_string())).unwrap())
+        };
+        assert_eq!(initialize(None).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(initialize(Some("made-up")).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(initialize(Some("operator")).await.unwrap().status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_keyless_callers_cannot_submit_when_execution_is_enabled() {
+        use crate::config::Config;
+        use crate::tenants::TenancyConfig;
+        use axum::body::Body;
+        use tower::ServiceExt;
+
+        // An admin key alone leaves keyless requests acting as the operator
+        let mut config = Config::default();
+        config.execution.enabled = true;
+        config.server.tenancy = TenancyConfig { admin_key: Some("operator".to_string()), ..Default::default() };
+        assert!(config.check_execution().is_err());
+
+        config.server.tenancy.required = true;
+        assert!(config.check_execution().is_ok());
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap()
+        .with_server_config(config.server.clone());
+        let submit = |key: Option<&str>| {
+            let mut request = axum::http::Request::post("/api/v1/tasks").header("content-type", "application/json");
+            if let Some(key) = key {
+                request = request.header(tenants::API_KEY_HEADER, key);
+            }
+            let body = json!({ "name": "rm", "command": "rm -rf /tmp/nothing", "project_id": uuid::Uuid::new_v4() });
+            server.rest_router().oneshot(request.body(Body::from(body.to_string())).unwrap())
+        };
+        assert_eq!(submit(None).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        assert_ne!(submit(Some("operator")).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_mcp_serves_the_configured_transports() {
+        use crate::config::McpTransport;
+        use crate::tenants::TenancyConfig;
+        use axum::body::Body;
+        use tower::ServiceExt;
+
+        let server = Arc::new(TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap()
+        .with_server_config(ServerConfig {
+            tenancy: TenancyConfig { mcp_anonymous: true, ..Default::default() },
+            ..ServerConfig::default()
+        }));
+        let initialize = || {
+            axum::http::Request::builder()
+                .method("POST")
+                .uri(crate::mcp::MCP_STREAMABLE_PATH)
+                .header("content-type", "application/json")
+                .header("accept", "application/json, text/event-stream")
+                .body(Body::from(json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "initialize",
+                    "params": {
+                        "protocolVersion": "2025-03-26",
+                        "capabilities": {},
+                        "clientInfo": { "name": "test", "version": "1.0" }
+                    }
+                }).to_string()))
+                .unwrap()
+        };
+        let sse = || axum::http::Request::builder().uri("/mcp/sse").body(Body::empty()).unwrap();
+
+        let router = create_mcp_router(server.clone(), McpTransport::StreamableHttp).await;
+        let response = router.clone().oneshot(initialize()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().contains_key("mcp-session-id"));
+        assert_eq!(router.oneshot(sse()).await.unwrap().status(), StatusCode::NOT_FOUND);
+
+        let router = create_mcp_router(server, McpTransport::Sse).await;
+        assert_eq!(router.oneshot(initialize()).await.unwrap().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_mcp_clients_are_notified_of_changes() {
+        use crate::config::McpTransport;
+        use crate::events::SseParser;
+        use crate::tenants::TenancyConfig;
+        use axum::body::Body;
+        use futures_util::StreamExt;
+        use tower::ServiceExt;
+
+        let server = Arc::new(TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap()
+        .with_server_config(ServerConfig {
+            tenancy: TenancyConfig { mcp_anonymous: true, ..Default::default() },
+            ..ServerConfig::default()
+        }));
+        let router = create_mcp_router(server.clone(), McpTransport::StreamableHttp).await;
+        let request = |method: &str, session: Option<&str>, body: Option<Value>| {
+            let mut request = axum::http::Request::builder()
+                .method(method)
+                .uri(crate::mcp::MCP_STREAMABLE_PATH)
+                .header("content-type", "application/json")
+                .header("accept", "application/json, text/event-stream");
+            if let Some(session) = session {
+                request = request.header("mcp-session-id", session);
+            }
+            request.body(body.map_or_else(Body::empty, |body| Body::from(body.to_string()))).unwrap()
+        };
+
+        let initialize = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": { "protocolVersion": "2025-03-26", "capabilities": {}, "clientInfo": { "name": "test", "version": "1.0" } }
+        });
+        let response = router.clone().oneshot(request("POST", None, Some(initialize))).await.unwrap();
+        let session = response.headers()["mcp-session-id"].to_str().unwrap().to_string();
+        let initialized = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        let response = router.clone().oneshot(request("POST", Some(&session), Some(initialized))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let response = router.oneshot(request("GET", Some(&session), None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let mut body = response.into_body().into_data_stream();
+
+        // The client starts listening for changes once initialized
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        server.create_project("web".to_string(), None).await.unwrap();
+
+        let mut parser = SseParser::new();
+        let mut methods = Vec::new();
+        while methods.len() < 2 {
+            let chunk = tokio::time::timeout(Duration::from_secs(5), body.next()).await.unwrap().unwrap().unwrap();
+            for frame in parser.feed(&chunk) {
+                if let Ok(message) = serde_json::from_str::<Value>(&frame.data) {
+                    methods.push(message["method"].as_str().unwrap_or_default().to_string());
+                }
+            }
+        }
+        assert_eq!(methods, ["notifications/resources/list_changed", "notifications/message"]);
+    }
+
+    #[tokio::test]
+    async fn test_mcp_requires_a_key_with_the_scope_a_tool_needs() {
+        use crate::config::McpTransport;
+        use crate::events::SseParser;
+        use crate::tenants::TenancyConfig;
+        use axum::body::Body;
+        use futures_util::StreamExt;
+        use tower::ServiceExt;
+
+        let server = Arc::new(TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap()
+        .with_server_config(ServerConfig {
+            tenancy: TenancyConfig { required: true, admin_key: Some("operator".to_string()), ..Default::default() },
+            ..ServerConfig::default()
+        }));
+        let tenant = server.create_tenant("Alpha".to_string(), None).await.unwrap();
+        let reader = server.issue_tenant_key(&tenant.id, "dashboard".to_string(), Some(vec![KeyScope::Read])).await.unwrap().key;
+        let admin = server.issue_tenant_key(&tenant.id, "ci".to_string(), None).await.unwrap().key;
+        let task_id = tenants::with_tenant(Some(tenant.id), async {
+            let project_id = server.create_project("web".to_string(), None).await.unwrap();
+            let mut task = Task::new("build").with_command("make").build();
+            task.project_id = Some(project_id);
+            server.submit_task(task).await.unwrap()
+        }).await;
+
+        let router = create_mcp_router(server.clone(), McpTransport::StreamableHttp).await;
+        let request = |key: Option<&str>, session: Option<&str>, body: Value| {
+            let mut request = axum::http::Request::post(crate::mcp::MCP_STREAMABLE_PATH)
+                .header("content-type", "application/json")
+                .header("accept", "application/json, text/event-stream");
+            if let Some(key) = key {
+                request = request.header("authorization", format!("Bearer {}", key));
+            }
+            if let Some(session) = session {
+                request = request.header("mcp-session-id", session);
+            }
+            request.body(Body::from(body.to_string())).unwrap()
+        };
+        let initialize = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": { "protocolVersion": "2025-03-26", "capabilities": {}, "clientInfo": { "name": "test", "version": "1.0" } }
+        });
+        let delete = json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "tools/call",
+            "params": { "name": "delete_task", "arguments": { "task_id": task_id } }
+        });
+
+        let anonymous = router.clone().oneshot(request(None, None, initialize.clone())).await.unwrap();
+        assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+        let unknown = router.clone().oneshot(request(Some("tq_unknown"), None, initialize.clone())).await.unwrap();
+        assert_eq!(unknown.status(), StatusCode::UNAUTHORIZED);
+
+        let mut answers = Vec::new();
+        for key in [reader.as_str(), admin.as_str()] {
+            let response = router.clone().oneshot(request(Some(key), None, initialize.clone())).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let session = response.headers()["mcp-session-id"].to_str().unwrap().to_string();
+            let initialized = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+            router.clone().oneshot(request(Some(key), Some(&session), initialized)).await.unwrap();
+
+            let response = router.clone().oneshot(request(Some(key), Some(&session), delete.clone())).await.unwrap();
+            let mut body = response.into_body().into_data_stream();
+            let mut parser = SseParser::new();
+            let answer = loop {
+                let chunk = tokio::time::timeout(Duration::from_secs(5), body.next()).await.unwrap().unwrap().unwrap();
+                let answer = parser.feed(&chunk).into_iter()
+                    .filter_map(|frame| serde_json::from_str::<Value>(&frame.data).ok())
+                    .find(|message| message["id"] == 2);
+                if let Some(answer) = answer {
+                    break answer;
+                }
+            };
+            answers.push(answer);
+        }
+
+        // The read-only key can't delete, so the task is still there for the
+        // key with every scope to delete
+        assert!(answers[0]["error"]["message"].as_str().unwrap().contains("Permission denied"), "{}", answers[0]);
+        assert!(answers[1]["error"].is_null(), "{}", answers[1]);
+        assert!(server.get_task(task_id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_claim_next_matching_task_takes_the_highest_priority_match() {
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let frontend = server.create_project("frontend".to_string(), None).await.unwrap();
+        let backend = server.create_project("backend".to_string(), None).await.unwrap();
+        for (name, project_id, priority, tags) in [
+            ("css", frontend, TaskPriority::Low, json!(["ui"])),
+            ("api", backend, TaskPriority::Critical, json!(["rust"])),
+            ("forms", frontend, TaskPriority::High, json!(["ui", "a11y"])),
+        ] {
+            let mut task = Task::new(name).with_command("make").build();
+            task.project_id = Some(project_id);
+            task.priority = priority;
+            task.status = TaskStatus::Pending;
+            task.metadata.insert("tags".to_string(), tags);
+            server.submit_task(task).await.unwrap();
+        }
+
+        let ui = TaskFilter { project_id: Some(frontend), tags: vec!["ui".to_string()], ..Default::default() };
+        let task = server.claim_next_matching_task("agent", None, &ui).await.unwrap().unwrap();
+        assert_eq!((task.name.as_str(), &task.status), ("forms", &TaskStatus::Running));
+        assert!(Lease::is_held_by(&task, "agent"));
+        let task = server.claim_next_matching_task("agent", None, &ui).await.unwrap().unwrap();
+        assert_eq!(task.name, "css");
+        assert!(server.claim_next_matching_task("agent", None, &ui).await.unwrap().is_none());
+        assert_eq!(server.claim_next_task("agent", None).await.unwrap().unwrap().name, "api");
+    }
+
+    #[tokio::test]
+    async fn test_report_progress_is_kept_and_broadcast() {
+        let server = TaskQueueServer::with_components(
+            Arc::new(StorageEngine::temporary().unwrap()),
+            Arc::new(VectorizerIntegration::new_dummy()),
+        )
+        .await
+        .unwrap();
+        let project_id = server.create_project("db".to_string(), None).await.unwrap();
+        let mut task = Task::new("migrate").with_command("make migrate").build();
+        task.project_id = Some(project_id);
+        task.status = TaskStatus::Pending;
+        let task_id = server.submit_task(task).await.unwrap();
+
+        let mut events = server.events().subscribe();
+        let task = server.report_progress(task_id, 40.0, Some("Copied 4 of 10 tables".to_string())).await.unwrap();
+        assert_eq!(crate::progress::progress(&task).unwrap().percent, 40);
+        let stored = server.get_task(task_id).await.unwrap();
+        assert_eq!(crate::progress::progress(&stored).unwrap().note.as_deref(), Some("Copied 4 of 10 tables"));
+
+        let event = loop {
+            let event = events.recv().await.unwrap();
+            if event.kind == crate::events::TaskEventKind::TaskProgress {
+                break event;
+            }
+        };
+        assert_eq!(event.task_id, Some(task_id));
+        assert_eq!(event.data["percent"], 40);
+        assert!(matches!(
+            server.report_progress(task_id, 120.0, None).await,
+            Err(TaskQueueError::ValidationError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_mcp_tool_schemas_follow_the_argument_types() {
+        let tools = crate::mcp::TaskQueueMcpServer::tools();
+        let schema = |name: &str| tools.iter().find(|tool| tool.name == name).unwrap().input_schema.clone();
+        for tool in &tools {
+            assert_eq!(tool.input_schema["type"], "object", "{}", tool.name);
+            assert!(tool.input_schema["properties"].get("agent_id").is_some(), "{}", tool.name);
+        }
+
+        let submit = schema("submit_task");
+        assert_eq!(submit["required"], json!(["name", "command", "project_id"]));
+        assert_eq!(submit["properties"]["project_id"]["format"], "uuid");
+        assert_eq!(submit["definitions"]["TaskPriority"]["enum"], json!(["Low", "Normal", "High", "Critical"]));
+        assert_eq!(submit["definitions"]["ScriptInterpreter"]["enum"], json!(["bash", "python", "pwsh"]));
+
+        // Optional and defaulted arguments aren't required
+        assert!(schema("list_tasks").get("required").is_none());
+        assert_eq!(schema("add_task_dependency")["required"], json!(["task_id", "dependency_task_id"]));
+        assert_eq!(schema("list_tasks")["properties"]["limit"]["maximum"], 1000);
+        assert_eq!(schema("report_progress")["properties"]["note"]["maxLength"], crate::progress::MAX_NOTE_LENGTH);
+        // save_view takes the whole view as REST does, filter fields included
+        assert!(schema("save_view")["definitions"]["TaskFilter"]["properties"].get("min_priority").is_some());
+    }
+}
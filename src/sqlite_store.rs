@@ -0,0 +1,310 @@
+//! SQLite Store Module
+//!
+//! Storage trees in a single SQLite database, for single-node deployments
+//! that want transactions, crash safety and the ability to query the data
+//! with plain SQL. Every record is a row of `records (tree, key, value)`;
+//! views named after the main collections (`tasks`, `workflows`, `projects`)
+//! expose them directly, and with `storage.codec: json` their values can be
+//! queried with SQLite's JSON functions, e.g.
+//!
+//! ```sql
+//! SELECT id, json_extract(value, '$.status') FROM tasks;
+//! ```
+//!
+//! The schema is created and upgraded by the migrations embedded below when
+//! the database is opened; `PRAGMA user_version` records how many ran.
+//!
+//! Needs the `sqlite` feature.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::error::{TaskQueueError, Result};
+use crate::kv::{prefix_end, TreeWrite};
+use rusqlite::types::Value;
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use std::ops::Bound;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tracing::info;
+
+/// Schema changes in the order they are applied; never edit one that shipped
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE records (
+        tree TEXT NOT NULL,
+        key BLOB NOT NULL,
+        value BLOB NOT NULL,
+        PRIMARY KEY (tree, key)
+    ) WITHOUT ROWID;",
+    "CREATE VIEW tasks AS SELECT CAST(key AS TEXT) AS id, CAST(value AS TEXT) AS value FROM records WHERE tree = 'tasks';
+     CREATE VIEW workflows AS SELECT CAST(key AS TEXT) AS id, CAST(value AS TEXT) AS value FROM records WHERE tree = 'workflows';
+     CREATE VIEW projects AS SELECT CAST(key AS TEXT) AS id, CAST(value AS TEXT) AS value FROM records WHERE tree = 'projects';",
+];
+
+/// Bring the schema up to date, returning its version
+fn migrate(connection: &mut Connection) -> Result<usize> {
+    let applied: usize = connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if applied > MIGRATIONS.len() {
+        return Err(TaskQueueError::ConfigurationError(format!(
+            "SQLite schema version {} is newer than the {} this build knows",
+            applied,
+            MIGRATIONS.len()
+        )));
+    }
+    for (version, migration) in MIGRATIONS.iter().enumerate().skip(applied) {
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(migration)?;
+        transaction.pragma_update(None, "user_version", version + 1)?;
+        transaction.commit()?;
+        info!("Applied SQLite schema migration {}", version + 1);
+    }
+    Ok(MIGRATIONS.len())
+}
+
+/// A SQLite database holding the storage trees
+#[derive(Clone)]
+pub struct SqliteStore {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStore {
+    /// Open or create the database at `path` and migrate it
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    /// A database that lives only in memory
+    pub fn in_memory() -> Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(mut connection: Connection) -> Result<Self> {
+        // WAL keeps readers going during writes; FULL syncs every commit
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.pragma_update(None, "synchronous", "FULL")?;
+        connection.busy_timeout(std::time::Duration::from_secs(5))?;
+        migrate(&mut connection)?;
+        Ok(Self { connection: Arc::new(Mutex::new(connection)) })
+    }
+
+    pub fn tree(&self, name: &str) -> SqliteTree {
+        SqliteTree {
+            connection: self.connection.clone(),
+            name: name.to_string(),
+        }
+    }
+
+    /// Set or remove keys of several trees in one transaction, provided the
+    /// `expected` keys still hold their values
+    pub fn apply(&self, writes: &[TreeWrite<'_, SqliteTree>], expected: &[TreeWrite<'_, SqliteTree>]) -> Result<()> {
+        let mut connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        // Taking the write lock up front keeps other processes from changing
+        // the expected keys between checking and writing them
+        let transaction = connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        for (tree, key, value) in expected {
+            let stored: Option<Vec<u8>> = transaction.query_row(
+                "SELECT value FROM records WHERE tree = ?1 AND key = ?2",
+                params![tree.name, key],
+                |row| row.get(0),
+            ).optional()?;
+            if stored.as_deref() != *value {
+                return Err(crate::kv::conflict(key));
+            }
+        }
+        for (tree, key, value) in writes {
+            match value {
+                Some(value) => transaction.execute(
+                    "INSERT INTO records (tree, key, value) VALUES (?1, ?2, ?3)
+                     ON CONFLICT (tree, key) DO UPDATE SET value = excluded.value",
+                    params![tree.name, key, value],
+                )?,
+                None => transaction.execute(
+                    "DELETE FROM records WHERE tree = ?1 AND key = ?2",
+                    params![tree.name, key],
+                )?,
+            };
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Schema version the database is at
+    pub fn schema_version(&self) -> Result<usize> {
+        let connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(connection.pragma_query_value(None, "user_version", |row| row.get(0))?)
+    }
+
+    /// Size of the database in bytes
+    pub fn size(&self) -> Result<u64> {
+        let connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        let pages: u64 = connection.pragma_query_value(None, "page_count", |row| row.get(0))?;
+        let page_size: u64 = connection.pragma_query_value(None, "page_size", |row| row.get(0))?;
+        Ok(pages * page_size)
+    }
+
+    /// Rebuild the database file without the pages of removed rows
+    pub fn vacuum(&self) -> Result<()> {
+        let connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        connection.execute_batch("VACUUM")?;
+        Ok(())
+    }
+}
+
+/// One storage tree: the rows of `records` with its name
+#[derive(Clone)]
+pub struct SqliteTree {
+    connection: Arc<Mutex<Connection>>,
+    name: String,
+}
+
+impl SqliteTree {
+    fn run<T>(&self, run: impl FnOnce(&mut Connection) -> rusqlite::Result<T>) -> Result<T> {
+        let mut connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(run(&mut connection)?)
+    }
+
+    /// Set a key, returning its previous value
+    pub fn insert(&self, key: &[u8], value: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.run(|connection| {
+            let transaction = connection.transaction()?;
+            let previous = transaction
+                .query_row(
+                    "SELECT value FROM records WHERE tree = ?1 AND key = ?2",
+                    params![self.name, key],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            transaction.execute(
+                "INSERT INTO records (tree, key, value) VALUES (?1, ?2, ?3)
+                 ON CONFLICT (tree, key) DO UPDATE SET value = excluded.value",
+                params![self.name, key, value],
+            )?;
+            transaction.commit()?;
+            Ok(previous)
+        })
+    }
+
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.run(|connection| {
+            connection
+                .query_row(
+                    "SELECT value FROM records WHERE tree = ?1 AND key = ?2",
+                    params![self.name, key],
+                    |row| row.get(0),
+                )
+                .optional()
+        })
+    }
+
+    /// Remove a key, returning its value
+    pub fn remove(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.run(|connection| {
+            connection
+                .query_row(
+                    "DELETE FROM records WHERE tree = ?1 AND key = ?2 RETURNING value",
+                    params![self.name, key],
+                    |row| row.get(0),
+                )
+                .optional()
+        })
+    }
+
+    pub fn len(&self) -> Result<usize> {
+        self.run(|connection| {
+            connection.query_row("SELECT COUNT(*) FROM records WHERE tree = ?1", params![self.name], |row| row.get(0))
+        })
+    }
+
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// The record with the highest key
+    pub fn last(&self) -> Result<Option<(Vec<u8>, Vec<u8>)>> {
+        self.run(|connection| {
+            connection
+                .query_row(
+                    "SELECT key, value FROM records WHERE tree = ?1 ORDER BY key DESC LIMIT 1",
+                    params![self.name],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()
+        })
+    }
+
+    /// Records with keys between the bounds, in key order, up to `limit` of them
+    pub fn entries(&self, start: Bound<&[u8]>, end: Bound<&[u8]>, limit: Option<usize>) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let mut sql = "SELECT key, value FROM records WHERE tree = ?1".to_string();
+        let mut values = vec![Value::Text(self.name.clone())];
+        for (bound, inclusive, exclusive) in [(start, ">=", ">"), (end, "<=", "<")] {
+            let (operator, key) = match bound {
+                Bound::Included(key) => (inclusive, key),
+                Bound::Excluded(key) => (exclusive, key),
+                Bound::Unbounded => continue,
+            };
+            values.push(Value::Blob(key.to_vec()));
+            sql.push_str(&format!(" AND key {} ?{}", operator, values.len()));
+        }
+        sql.push_str(" ORDER BY key");
+        if let Some(limit) = limit {
+            values.push(Value::Integer(limit.try_into().unwrap_or(i64::MAX)));
+            sql.push_str(&format!(" LIMIT ?{}", values.len()));
+        }
+
+        self.run(|connection| {
+            let mut statement = connection.prepare_cached(&sql)?;
+            let rows = statement.query_map(rusqlite::params_from_iter(values), |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect()
+        })
+    }
+
+    /// Records whose keys start with the prefix, in key order
+    pub fn scan_prefix(&self, prefix: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let end = prefix_end(prefix);
+        let end = end.as_deref().map_or(Bound::Unbounded, Bound::Excluded);
+        self.entries(Bound::Included(prefix), end, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::Codec;
+    use crate::core::*;
+    use crate::storage::StorageEngine;
+    use crate::task_logs::{LogChunk, LogStream};
+
+    #[tokio::test]
+    async fn test_records_survive_reopening_the_database() {
+        let path = std::env::temp_dir().join(format!("task-queue-{}.sqlite", uuid::Uuid::new_v4()));
+        let task = Task::new("Build").with_command("cargo build").build();
+        {
+            let storage = StorageEngine::open_sqlite(&path).unwrap().with_codec(Codec::Json);
+            storage.store_task(&task).await.unwrap();
+            let chunk = LogChunk { task_id: task.id, seq: 7, stream: LogStream::Stdout, data: "compiling".to_string(), at: chrono::Utc::now() };
+            storage.append_log_chunk(&chunk).await.unwrap();
+        }
+
+        let store = SqliteStore::open(&path).unwrap();
+        assert_eq!(store.schema_version().unwrap(), MIGRATIONS.len());
+        let storage = StorageEngine::open_sqlite(&path).unwrap();
+        assert_eq!(storage.load_task(&task.id).await.unwrap().unwrap().name, "Build");
+        assert_eq!(storage.list_tasks().await.unwrap().len(), 1);
+        let (page, next) = storage.list_tasks_page(None, 1).await.unwrap();
+        assert!(page.len() == 1 && next.is_none());
+        assert_eq!(storage.last_log_seq(&task.id).await.unwrap(), Some(7));
+        assert!(storage.load_task_as_of(&task.id, chrono::Utc::now()).await.unwrap().is_some());
+
+        let connection = store.connection.lock().unwrap();
+        let status: String = connection
+            .query_row("SELECT json_extract(value, '$.status') FROM tasks WHERE id = ?1", [task.id.to_string()], |row| row.get(0))
+            .unwrap();
+        assert_eq!(status, "Planning");
+        drop(connection);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
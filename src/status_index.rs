@@ -0,0 +1,93 @@
+//! Status Index Module
+//!
+//! The executor asks for the pending tasks several times a second, and the
+//! lease reaper, the scheduler and worker listings each look for the few
+//! tasks in one execution status. Rather than cloning every in-memory task
+//! to find them, a server that keeps its tasks in memory also keeps their IDs
+//! by `status`, updated on every write. The storage indexes can't serve these
+//! lookups, as they index the status a task shows, which follows its
+//! development phase. Readers re-check the status of what the index returns,
+//! since a write may be between updating the task and the index.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::{Task, TaskStatus};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// Task IDs by execution status
+#[derive(Debug, Default)]
+pub struct StatusIndex {
+    inner: Mutex<Entries>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    status: HashMap<Uuid, TaskStatus>,
+    ids: HashMap<TaskStatus, HashSet<Uuid>>,
+}
+
+impl StatusIndex {
+    /// File a task under its current status
+    pub fn update(&self, task: &Task) {
+        let mut entries = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        match entries.status.insert(task.id, task.status.clone()) {
+            Some(previous) if previous == task.status => return,
+            Some(previous) => {
+                if let Some(ids) = entries.ids.get_mut(&previous) {
+                    ids.remove(&task.id);
+                }
+            }
+            None => {}
+        }
+        entries.ids.entry(task.status.clone()).or_default().insert(task.id);
+    }
+
+    pub fn remove(&self, task_id: &Uuid) {
+        let mut entries = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(previous) = entries.status.remove(task_id)
+            && let Some(ids) = entries.ids.get_mut(&previous)
+        {
+            ids.remove(task_id);
+        }
+    }
+
+    /// IDs of the tasks in any of `statuses`
+    pub fn ids(&self, statuses: &[TaskStatus]) -> Vec<Uuid> {
+        let entries = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        statuses.iter()
+            .filter_map(|status| entries.ids.get(status))
+            .flat_map(|ids| ids.iter().copied())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tasks_move_between_statuses() {
+        let index = StatusIndex::default();
+        let mut task = Task::new("Build").with_command("make").build();
+        task.status = TaskStatus::Pending;
+        let other = Task::new("Test").with_command("make test").build();
+        index.update(&task);
+        index.update(&other);
+        assert_eq!(index.ids(&[TaskStatus::Pending]), [task.id]);
+
+        task.status = TaskStatus::Running;
+        index.update(&task);
+        assert!(index.ids(&[TaskStatus::Pending]).is_empty());
+        assert_eq!(index.ids(&[TaskStatus::Running, TaskStatus::Paused]), [task.id]);
+
+        index.remove(&task.id);
+        assert!(index.ids(&[TaskStatus::Running]).is_empty());
+        assert_eq!(index.ids(std::slice::from_ref(&other.status)), [other.id]);
+    }
+}
@@ -5,27 +5,501 @@
 #![allow(dead_code)]
 #![allow(unused_mut)]
 
+use crate::artifacts::{Artifact, ArtifactKind};
+use crate::audit::{AuditEntity, AuditEntry, AuditQuery};
+use crate::codec::Codec;
+use crate::config::StorageBackend;
 use crate::core::*;
+use crate::dead_letter::DeadLetter;
+use crate::events::TaskEvent;
+use crate::fsck::{Collection, UndecodableRecord};
+use crate::history::StateChange;
+use crate::kv::{Backend, Batch, Tree};
+use crate::recurrence::RecurringTask;
+use crate::secrets::StoredSecret;
+use crate::search::{self, Posting, TermCounts};
+use crate::task_index::TaskIndex;
+use crate::task_logs::LogChunk;
+use crate::vacuum::VacuumReport;
+use crate::workers::Worker;
+use crate::views::SavedView;
+use crate::idempotency::IdempotencyRecord;
+use crate::queues::Queue;
+use crate::tenants::{Tenant, TenantKey};
 use crate::error::{TaskQueueError, Result as TaskQueueResult};
-use sled::{Db, Tree};
+use chrono::{DateTime, Utc};
+use futures_util::future::BoxFuture;
 use std::sync::Arc;
+use tracing::info;
 
-/// Storage engine using Sled embedded database
+/// Last heartbeat of each task being executed
+pub type Heartbeats = Vec<(uuid::Uuid, DateTime<Utc>)>;
+
+/// One page of tasks in ID order and the ID to continue after, if more remain
+pub type TaskPage = (Vec<Task>, Option<uuid::Uuid>);
+
+/// Tasks, projects and workflows read together
+pub type ConsistentRead = (Vec<Task>, Vec<Project>, Vec<Workflow>);
+
+/// Persistent storage for tasks, workflows, projects and everything kept
+/// alongside them
+///
+/// The server only talks to storage through this trait, so backends that
+/// don't fit the key-value trees of [`StorageEngine`] (e.g. Postgres) can be
+/// plugged in without touching server code.
+pub trait Storage: Send + Sync {
+    /// Format newly written records are encoded in
+    fn codec(&self) -> Codec;
+
+    /// Whether servers in several processes can share this storage
+    fn is_shared(&self) -> bool;
+
+    /// Commit task, workflow and project writes together, or none of them
+    fn commit<'a>(&'a self, transaction: &'a Transaction) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    /// Store a task
+    fn store_task<'a>(&'a self, task: &'a Task) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    /// The task as it was stored at the given moment
+    fn load_task_as_of<'a>(&'a self, task_id: &'a uuid::Uuid, at: DateTime<Utc>) -> BoxFuture<'a, TaskQueueResult<Option<Task>>>;
+
+    /// Drop every stored revision of a task
+    fn delete_task_revisions<'a>(&'a self, task_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    /// Keep a task's final state in the archive
+    fn archive_task<'a>(&'a self, task: &'a Task) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    /// Load an archived task by ID
+    fn load_archived_task<'a>(&'a self, task_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<Option<Task>>>;
+
+    /// Move a task to cold storage; returns whether it was still stored
+    fn freeze_task<'a>(&'a self, task_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<bool>>;
+
+    /// Load a task from cold storage by ID
+    fn load_cold_task<'a>(&'a self, task_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<Option<Task>>>;
+
+    /// Append a task or workflow state change to the history log
+    fn append_state_change<'a>(&'a self, change: &'a StateChange) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    /// Recorded mutations matching `query`, oldest first
+    fn list_audit<'a>(&'a self, query: &'a AuditQuery) -> BoxFuture<'a, TaskQueueResult<Vec<AuditEntry>>>;
+
+    /// Every recorded state change of a task or workflow, oldest first
+    fn list_state_changes<'a>(&'a self, entity_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<Vec<StateChange>>>;
+
+    /// Every state change recorded before `until`, by entity and then time
+    fn list_state_changes_before(&self, until: DateTime<Utc>) -> BoxFuture<'_, TaskQueueResult<Vec<StateChange>>>;
+
+    /// Load a task by ID
+    fn load_task<'a>(&'a self, task_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<Option<Task>>>;
+
+    /// Store a workflow
+    fn store_workflow<'a>(&'a self, workflow: &'a Workflow) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    /// Load a workflow by ID
+    fn load_workflow<'a>(&'a self, workflow_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<Option<Workflow>>>;
+
+    /// List all tasks
+    fn list_tasks(&self) -> BoxFuture<'_, TaskQueueResult<Vec<Task>>>;
+
+    /// List up to `limit` tasks with IDs after `after`
+    fn list_tasks_page(&self, after: Option<uuid::Uuid>, limit: usize) -> BoxFuture<'_, TaskQueueResult<TaskPage>>;
+
+    /// IDs of the tasks with an indexed value
+    fn find_task_ids<'a>(&'a self, index: &'a TaskIndex) -> BoxFuture<'a, TaskQueueResult<Vec<uuid::Uuid>>>;
+
+    /// Tasks having a search term, or with `prefix` any term it starts
+    fn find_postings<'a>(&'a self, term: &'a str, prefix: bool) -> BoxFuture<'a, TaskQueueResult<Vec<Posting>>>;
+
+    /// Number of stored tasks
+    fn count_tasks(&self) -> BoxFuture<'_, TaskQueueResult<usize>>;
+
+    /// List all workflows
+    fn list_workflows(&self) -> BoxFuture<'_, TaskQueueResult<Vec<Workflow>>>;
+
+    /// Delete a task
+    fn delete_task<'a>(&'a self, task_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    /// Delete a workflow
+    fn delete_workflow<'a>(&'a self, workflow_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    /// Get storage statistics
+    fn get_stats(&self) -> BoxFuture<'_, TaskQueueResult<StorageStats>>;
+
+    /// Remove data of tasks that are gone, returning the removed artifacts too
+    fn vacuum(&self, tombstones_before: DateTime<Utc>, dry_run: bool) -> BoxFuture<'_, TaskQueueResult<(VacuumReport, Vec<Artifact>)>>;
+
+    /// Give the space of removed records back; returns when it finished
+    fn compact(&self) -> BoxFuture<'_, TaskQueueResult<DateTime<Utc>>>;
+
+    /// Write, read back and flush a probe record, returning how long it took
+    fn probe(&self) -> BoxFuture<'_, TaskQueueResult<std::time::Duration>>;
+
+    /// Store a project
+    fn store_project<'a>(&'a self, project: &'a Project) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    /// Load a project by ID
+    fn load_project<'a>(&'a self, project_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<Option<Project>>>;
+
+    /// List all projects
+    fn list_projects(&self) -> BoxFuture<'_, TaskQueueResult<Vec<Project>>>;
+
+    /// Delete a project
+    fn delete_project<'a>(&'a self, project_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    /// Store an agent
+    fn store_agent<'a>(&'a self, agent: &'a Agent) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    /// List all registered agents
+    fn list_agents(&self) -> BoxFuture<'_, TaskQueueResult<Vec<Agent>>>;
+
+    /// Store a worker under its worker ID
+    fn store_worker<'a>(&'a self, worker: &'a Worker) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    fn load_worker<'a>(&'a self, worker_id: &'a str) -> BoxFuture<'a, TaskQueueResult<Option<Worker>>>;
+
+    fn list_workers(&self) -> BoxFuture<'_, TaskQueueResult<Vec<Worker>>>;
+
+    /// Record that a task's execution is alive at `at`
+    fn store_heartbeat<'a>(&'a self, task_id: &'a uuid::Uuid, at: DateTime<Utc>) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    /// Last heartbeat of every task with one
+    fn list_heartbeats(&self) -> BoxFuture<'_, TaskQueueResult<Heartbeats>>;
+
+    fn delete_heartbeat<'a>(&'a self, task_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    /// Store an artifact's description, replacing one of the same kind and name
+    fn store_artifact<'a>(&'a self, artifact: &'a Artifact) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    /// A task's artifacts: its log file, then files by name
+    fn list_artifacts<'a>(&'a self, task_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<Vec<Artifact>>>;
+
+    /// Delete the descriptions of a task's artifacts
+    fn delete_task_artifacts<'a>(&'a self, task_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    /// Store the contents of a blob, for the database blob store
+    fn store_blob<'a>(&'a self, key: &'a str, data: &'a [u8]) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    fn load_blob<'a>(&'a self, key: &'a str) -> BoxFuture<'a, TaskQueueResult<Option<Vec<u8>>>>;
+
+    fn delete_blob<'a>(&'a self, key: &'a str) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    /// Store a saved view under its [`SavedView::key`]
+    fn store_view<'a>(&'a self, view: &'a SavedView) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    fn load_view<'a>(&'a self, key: &'a str) -> BoxFuture<'a, TaskQueueResult<Option<SavedView>>>;
+
+    /// List saved views in key order
+    fn list_views(&self) -> BoxFuture<'_, TaskQueueResult<Vec<SavedView>>>;
+
+    /// Delete a saved view, returning whether it existed
+    fn delete_view<'a>(&'a self, key: &'a str) -> BoxFuture<'a, TaskQueueResult<bool>>;
+
+    /// Store the response to a request made with an `Idempotency-Key`
+    fn store_idempotency<'a>(&'a self, record: &'a IdempotencyRecord) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    fn load_idempotency<'a>(&'a self, scope: &'a str) -> BoxFuture<'a, TaskQueueResult<Option<IdempotencyRecord>>>;
+
+    fn store_tenant<'a>(&'a self, tenant: &'a Tenant) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    fn load_tenant<'a>(&'a self, tenant_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<Option<Tenant>>>;
+
+    fn list_tenants(&self) -> BoxFuture<'_, TaskQueueResult<Vec<Tenant>>>;
+
+    /// Store a tenant's API key under its hash
+    fn store_tenant_key<'a>(&'a self, key: &'a TenantKey) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    fn load_tenant_key<'a>(&'a self, key_hash: &'a str) -> BoxFuture<'a, TaskQueueResult<Option<TenantKey>>>;
+
+    /// API keys of every tenant
+    fn list_tenant_keys(&self) -> BoxFuture<'_, TaskQueueResult<Vec<TenantKey>>>;
+
+    /// Store a queue under its name
+    fn store_queue<'a>(&'a self, queue: &'a Queue) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    fn load_queue<'a>(&'a self, name: &'a str) -> BoxFuture<'a, TaskQueueResult<Option<Queue>>>;
+
+    fn list_queues(&self) -> BoxFuture<'_, TaskQueueResult<Vec<Queue>>>;
+
+    /// Delete a queue, returning whether it existed
+    fn delete_queue<'a>(&'a self, name: &'a str) -> BoxFuture<'a, TaskQueueResult<bool>>;
+
+    /// Store a recurring task definition
+    fn store_recurrence<'a>(&'a self, recurrence: &'a RecurringTask) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    fn load_recurrence<'a>(&'a self, recurrence_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<Option<RecurringTask>>>;
+
+    fn list_recurrences(&self) -> BoxFuture<'_, TaskQueueResult<Vec<RecurringTask>>>;
+
+    /// Delete a recurring task definition, returning whether it existed
+    fn delete_recurrence<'a>(&'a self, recurrence_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<bool>>;
+
+    /// Record a permanently failed task, replacing any earlier entry for it
+    fn store_dead_letter<'a>(&'a self, entry: &'a DeadLetter) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    fn load_dead_letter<'a>(&'a self, task_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<Option<DeadLetter>>>;
+
+    fn list_dead_letters(&self) -> BoxFuture<'_, TaskQueueResult<Vec<DeadLetter>>>;
+
+    /// Remove a task's dead-letter entry, returning whether it had one
+    fn delete_dead_letter<'a>(&'a self, task_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<bool>>;
+
+    fn store_secret<'a>(&'a self, secret: &'a StoredSecret) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    /// A secret of `tenant`, or of the operator for `None`
+    fn load_secret<'a>(&'a self, tenant: Option<uuid::Uuid>, name: &'a str) -> BoxFuture<'a, TaskQueueResult<Option<StoredSecret>>>;
+
+    fn list_secrets(&self) -> BoxFuture<'_, TaskQueueResult<Vec<StoredSecret>>>;
+
+    /// Delete a secret, returning whether it existed
+    fn delete_secret<'a>(&'a self, tenant: Option<uuid::Uuid>, name: &'a str) -> BoxFuture<'a, TaskQueueResult<bool>>;
+
+    fn append_log_chunk<'a>(&'a self, chunk: &'a LogChunk) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    /// A task's log chunks in order, only those after `after` if given
+    fn list_log_chunks<'a>(&'a self, task_id: &'a uuid::Uuid, after: Option<u64>) -> BoxFuture<'a, TaskQueueResult<Vec<LogChunk>>>;
+
+    /// Number of the last chunk in a task's log
+    fn last_log_seq<'a>(&'a self, task_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<Option<u64>>>;
+
+    /// Delete a task's log
+    fn delete_task_logs<'a>(&'a self, task_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    /// Store a project's configuration for an external integration
+    fn store_integration<'a>(&'a self, project_id: &'a uuid::Uuid, kind: &'a str, config: serde_json::Value) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    /// Load a project's configuration for an external integration
+    fn load_integration<'a>(&'a self, project_id: &'a uuid::Uuid, kind: &'a str) -> BoxFuture<'a, TaskQueueResult<Option<serde_json::Value>>>;
+
+    /// Remove a project's configuration for an external integration
+    fn delete_integration<'a>(&'a self, project_id: &'a uuid::Uuid, kind: &'a str) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    /// Record an event awaiting delivery to an external broker
+    fn append_outbox<'a>(&'a self, event: &'a TaskEvent) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    /// Oldest undelivered events, in sequence order
+    fn outbox_batch<'a>(&'a self, limit: usize) -> BoxFuture<'a, TaskQueueResult<Vec<TaskEvent>>>;
+
+    /// Drop an event once the broker acknowledged it
+    fn ack_outbox<'a>(&'a self, sequence: u64) -> BoxFuture<'a, TaskQueueResult<()>>;
+
+    /// Highest sequence number still in the outbox, or 0
+    fn last_outbox_sequence(&self) -> TaskQueueResult<u64>;
+
+    /// Task, project and workflow records that no longer decode
+    fn find_undecodable_records(&self) -> BoxFuture<'_, TaskQueueResult<Vec<UndecodableRecord>>>;
+
+    /// Move a record out of its collection into quarantine, keeping its
+    /// stored bytes; false if there was no such record
+    fn quarantine_record<'a>(&'a self, collection: Collection, key: &'a str) -> BoxFuture<'a, TaskQueueResult<bool>>;
+
+    /// Stored bytes of a quarantined record
+    fn load_quarantined<'a>(&'a self, collection: Collection, key: &'a str) -> BoxFuture<'a, TaskQueueResult<Option<Vec<u8>>>>;
+
+    /// Read tasks, projects and workflows with writes held off, so the three
+    /// agree with each other
+    fn consistent_read(&self) -> BoxFuture<'_, TaskQueueResult<ConsistentRead>>;
+
+    /// Copy the stored task, project and workflow records as they are, with
+    /// writes held off only for the copy
+    fn export_records(&self) -> BoxFuture<'_, TaskQueueResult<ExportedRecords>>;
+}
+
+/// One write of a [`Transaction`]
+#[derive(Debug, Clone)]
+pub enum RecordWrite {
+    Task(Box<Task>),
+    DeleteTask(uuid::Uuid),
+    Workflow(Workflow),
+    DeleteWorkflow(uuid::Uuid),
+    Project(Project),
+    DeleteProject(uuid::Uuid),
+}
+
+impl RecordWrite {
+    /// Kind and ID of the record written
+    pub fn target(&self) -> (AuditEntity, uuid::Uuid) {
+        match self {
+            Self::Task(task) => (AuditEntity::Task, task.id),
+            Self::DeleteTask(id) => (AuditEntity::Task, *id),
+            Self::Workflow(workflow) => (AuditEntity::Workflow, workflow.id),
+            Self::DeleteWorkflow(id) => (AuditEntity::Workflow, *id),
+            Self::Project(project) => (AuditEntity::Project, project.id),
+            Self::DeleteProject(id) => (AuditEntity::Project, *id),
+        }
+    }
+}
+
+/// Task, workflow and project writes that land together
+///
+/// Committing stores every record along with its index entries, revisions
+/// and audit entries in one backend transaction, so a crash halfway leaves
+/// none of them written. A later write to the same record replaces the
+/// earlier one.
+#[derive(Debug, Clone, Default)]
+pub struct Transaction {
+    writes: Vec<RecordWrite>,
+    /// Refuse records written over a version at least as new
+    check_versions: bool,
+}
+
+impl Transaction {
+    pub fn put_task(&mut self, task: &Task) -> &mut Self {
+        self.push(RecordWrite::Task(Box::new(task.clone())))
+    }
+
+    pub fn delete_task(&mut self, task_id: uuid::Uuid) -> &mut Self {
+        self.push(RecordWrite::DeleteTask(task_id))
+    }
+
+    pub fn put_workflow(&mut self, workflow: &Workflow) -> &mut Self {
+        self.push(RecordWrite::Workflow(workflow.clone()))
+    }
+
+    pub fn delete_workflow(&mut self, workflow_id: uuid::Uuid) -> &mut Self {
+        self.push(RecordWrite::DeleteWorkflow(workflow_id))
+    }
+
+    pub fn put_project(&mut self, project: &Project) -> &mut Self {
+        self.push(RecordWrite::Project(project.clone()))
+    }
+
+    pub fn delete_project(&mut self, project_id: uuid::Uuid) -> &mut Self {
+        self.push(RecordWrite::DeleteProject(project_id))
+    }
+
+    /// Fail with `PreconditionFailed` if a record is stored at the version
+    /// being written or a later one, for writers whose copy may be stale;
+    /// records being created are not checked
+    pub fn check_versions(&mut self) -> &mut Self {
+        self.check_versions = true;
+        self
+    }
+
+    pub fn checks_versions(&self) -> bool {
+        self.check_versions
+    }
+
+    pub fn writes(&self) -> &[RecordWrite] {
+        &self.writes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.writes.is_empty()
+    }
+
+    fn push(&mut self, write: RecordWrite) -> &mut Self {
+        let target = write.target();
+        self.writes.retain(|earlier| earlier.target() != target);
+        self.writes.push(write);
+        self
+    }
+}
+
+/// Stored records of the main collections, still encoded, copied together
+#[derive(Debug, Clone, Default)]
+pub struct ExportedRecords {
+    pub tasks: Vec<sled::IVec>,
+    pub projects: Vec<sled::IVec>,
+    pub workflows: Vec<sled::IVec>,
+}
+
+/// Storage engine using Sled embedded database, Redis or SQLite
 pub struct StorageEngine {
-    db: Arc<Db>,
+    db: Backend,
     tasks_tree: Tree,
+    /// Task IDs by status, project and priority
+    task_index_tree: Tree,
+    /// Words of the tasks' texts, for full-text search
+    search_index_tree: Tree,
     workflows_tree: Tree,
     projects_tree: Tree,
+    integrations_tree: Tree,
+    outbox_tree: Tree,
+    /// Every stored revision of every task, for point-in-time reads
+    history_tree: Tree,
+    /// Tasks removed from the queue by retention
+    archived_tasks_tree: Tree,
+    /// Old completed tasks, compressed and out of listings
+    cold_tasks_tree: Tree,
+    /// Append-only log of task and workflow state changes
+    state_changes_tree: Tree,
+    /// Every create, update and delete of a task, project or workflow, by time
+    audit_tree: Tree,
+    /// Records the integrity check moved out of their collection
+    quarantine_tree: Tree,
+    agents_tree: Tree,
+    views_tree: Tree,
+    recurrences_tree: Tree,
+    dead_letters_tree: Tree,
+    /// Secret values, only ever stored encrypted
+    secrets_tree: Tree,
+    /// Captured task output, keyed by task ID and chunk number
+    task_logs_tree: Tree,
+    workers_tree: Tree,
+    /// Last heartbeat of each task this or another server is executing
+    heartbeats_tree: Tree,
+    /// Artifacts kept from task runs, keyed by task ID, kind and name
+    artifacts_tree: Tree,
+    /// Blob contents, when the blob store is the database
+    artifact_data_tree: Tree,
+    /// Responses to requests made with an `Idempotency-Key`, by scope
+    idempotency_tree: Tree,
+    tenants_tree: Tree,
+    /// Tenants' API keys, by key hash
+    tenant_keys_tree: Tree,
+    queues_tree: Tree,
+    codec: Codec,
+    /// Held shared by writers and exclusively while taking a snapshot
+    write_gate: tokio::sync::RwLock<()>,
+    /// Held while a transaction reads the records it replaces and commits
+    commit_lock: tokio::sync::Mutex<()>,
+    /// Time spent writing batches since the storage was opened
+    write_timing: std::sync::Mutex<WriteTiming>,
+    /// Refuse every commit, to exercise callers' error paths
+    #[cfg(test)]
+    failing_commits: std::sync::atomic::AtomicBool,
+}
+
+/// Batches written and how long writing them took
+#[derive(Debug, Clone, Copy, Default)]
+struct WriteTiming {
+    writes: u64,
+    total: std::time::Duration,
+    last: std::time::Duration,
 }
 
+/// Times a commit is staged before giving up on records other processes
+/// keep changing
+const COMMIT_ATTEMPTS: usize = 3;
+
+/// Default tree key holding the format the storage was last migrated to
+const CODEC_KEY: &str = "codec";
+
+/// Default tree key holding when the storage was last compacted
+const LAST_COMPACTION_KEY: &str = "last_compaction";
+
 impl StorageEngine {
     /// Create a new storage engine
     pub async fn new() -> TaskQueueResult<Self> {
+        let config = crate::config::Config::installed().storage;
+        match &config.backend {
+            StorageBackend::Sled => {}
+            StorageBackend::Redis { url, prefix } => {
+                println!("Using Redis storage at: {}", url);
+                return Ok(Self::connect_redis(url, prefix)?.with_configured_codec(config.codec));
+            }
+            StorageBackend::Sqlite { path } => {
+                println!("Opening SQLite database at: {}", path);
+                return Ok(Self::open_sqlite(path)?.with_configured_codec(config.codec));
+            }
+        }
+
         // Try to create data directory, fallback to temp if it fails
-        let data_dir = std::env::current_dir().unwrap_or_else(|_| std::env::temp_dir()).join("task-queue-data");
-        let _ = std::fs::create_dir_all(&data_dir);
-        
-        let db_path = data_dir.join("task-queue.db");
+        let db_path = std::env::current_dir().unwrap_or_else(|_| std::env::temp_dir()).join(&config.database_path);
+        if let Some(data_dir) = db_path.parent() {
+            let _ = std::fs::create_dir_all(data_dir);
+        }
         println!("Opening database at: {:?}", db_path);
         
         // Try to open database, fallback to in-memory if it fails
@@ -37,138 +511,849 @@ impl StorageEngine {
             }
         };
         
+        Ok(Self::from_db(db)?.with_configured_codec(config.codec))
+    }
+
+    /// Open a storage engine at the given database path
+    pub fn open(path: impl AsRef<std::path::Path>) -> TaskQueueResult<Self> {
+        Self::from_db(Arc::new(sled::open(path)?))
+    }
+
+    /// Create a storage engine that lives only in memory
+    pub fn temporary() -> TaskQueueResult<Self> {
+        Self::from_backend(Backend::Memory(Default::default()))
+    }
+
+    /// Make every commit fail until called again with `false`
+    #[cfg(test)]
+    pub(crate) fn fail_commits(&self, fail: bool) {
+        self.failing_commits.store(fail, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    /// Another engine over the same backend, as a second server process
+    /// would open it
+    #[cfg(test)]
+    pub(crate) fn reopen(&self) -> TaskQueueResult<Self> {
+        Self::from_backend(self.db.clone())
+    }
+
+    /// Keep everything in Redis, under keys starting with `prefix`
+    #[cfg(feature = "redis")]
+    pub fn connect_redis(url: &str, prefix: &str) -> TaskQueueResult<Self> {
+        Self::from_backend(Backend::Redis(crate::redis_store::RedisStore::connect(url, prefix)?))
+    }
+
+    #[cfg(not(feature = "redis"))]
+    pub fn connect_redis(url: &str, prefix: &str) -> TaskQueueResult<Self> {
+        Err(TaskQueueError::ConfigurationError(
+            "The Redis storage backend needs a build with the `redis` feature".to_string(),
+        ))
+    }
+
+    /// Open or create a SQLite database at the given path, migrating its schema
+    #[cfg(feature = "sqlite")]
+    pub fn open_sqlite(path: impl AsRef<std::path::Path>) -> TaskQueueResult<Self> {
+        Self::from_backend(Backend::Sqlite(crate::sqlite_store::SqliteStore::open(path)?))
+    }
+
+    #[cfg(not(feature = "sqlite"))]
+    pub fn open_sqlite(path: impl AsRef<std::path::Path>) -> TaskQueueResult<Self> {
+        Err(TaskQueueError::ConfigurationError(
+            "The SQLite storage backend needs a build with the `sqlite` feature".to_string(),
+        ))
+    }
+
+    fn from_db(db: Arc<sled::Db>) -> TaskQueueResult<Self> {
+        Self::from_backend(Backend::Sled(db))
+    }
+
+    fn from_backend(db: Backend) -> TaskQueueResult<Self> {
         let tasks_tree = db.open_tree("tasks")?;
+        let task_index_tree = db.open_tree("task_index")?;
+        let search_index_tree = db.open_tree("search_index")?;
         let workflows_tree = db.open_tree("workflows")?;
         let projects_tree = db.open_tree("projects")?;
+        let integrations_tree = db.open_tree("integrations")?;
+        let outbox_tree = db.open_tree("outbox")?;
+        let history_tree = db.open_tree("task_history")?;
+        let archived_tasks_tree = db.open_tree("archived_tasks")?;
+        let cold_tasks_tree = db.open_tree("cold_tasks")?;
+        let state_changes_tree = db.open_tree("state_changes")?;
+        let audit_tree = db.open_tree("audit")?;
+        let quarantine_tree = db.open_tree("quarantine")?;
+        let agents_tree = db.open_tree("agents")?;
+        let views_tree = db.open_tree("views")?;
+        let recurrences_tree = db.open_tree("recurrences")?;
+        let dead_letters_tree = db.open_tree("dead_letters")?;
+        let secrets_tree = db.open_tree("secrets")?;
+        let task_logs_tree = db.open_tree("task_logs")?;
+        let workers_tree = db.open_tree("workers")?;
+        let heartbeats_tree = db.open_tree("heartbeats")?;
+        let artifacts_tree = db.open_tree("artifacts")?;
+        let artifact_data_tree = db.open_tree("artifact_data")?;
+        let idempotency_tree = db.open_tree("idempotency")?;
+        let tenants_tree = db.open_tree("tenants")?;
+        let tenant_keys_tree = db.open_tree("tenant_keys")?;
+        let queues_tree = db.open_tree("queues")?;
+        let codec = Self::migrated_codec(&db)?;
         
-        Ok(Self {
+        let storage = Self {
             db,
             tasks_tree,
+            task_index_tree,
+            search_index_tree,
             workflows_tree,
             projects_tree,
-        })
+            integrations_tree,
+            outbox_tree,
+            history_tree,
+            archived_tasks_tree,
+            cold_tasks_tree,
+            state_changes_tree,
+            audit_tree,
+            quarantine_tree,
+            agents_tree,
+            views_tree,
+            recurrences_tree,
+            dead_letters_tree,
+            secrets_tree,
+            task_logs_tree,
+            workers_tree,
+            heartbeats_tree,
+            artifacts_tree,
+            artifact_data_tree,
+            idempotency_tree,
+            tenants_tree,
+            tenant_keys_tree,
+            queues_tree,
+            codec,
+            write_gate: tokio::sync::RwLock::new(()),
+            commit_lock: tokio::sync::Mutex::new(()),
+            write_timing: std::sync::Mutex::new(WriteTiming::default()),
+            #[cfg(test)]
+            failing_commits: std::sync::atomic::AtomicBool::new(false),
+        };
+        // Databases from before the indexes get them built once
+        if (storage.task_index_tree.is_empty() || storage.search_index_tree.is_empty()) && !storage.tasks_tree.is_empty() {
+            storage.rebuild_task_index()?;
+        }
+        storage.migrate_artifact_data()?;
+        Ok(storage)
     }
 
-    /// Store a task
-    pub async fn store_task(&self, task: &Task) -> TaskQueueResult<()> {
-        let key = task.id.to_string();
-        let value = serde_json::to_vec(task)?;
-        
-        self.tasks_tree.insert(key, value)?;
-        self.tasks_tree.flush_async().await?;
-        
-        Ok(())
+    /// Every tree by name, for copying the storage as a whole
+    pub(crate) fn trees(&self) -> Vec<(&'static str, &Tree)> {
+        vec![
+            ("tasks", &self.tasks_tree),
+            ("task_index", &self.task_index_tree),
+            ("search_index", &self.search_index_tree),
+            ("workflows", &self.workflows_tree),
+            ("projects", &self.projects_tree),
+            ("integrations", &self.integrations_tree),
+            ("outbox", &self.outbox_tree),
+            ("task_history", &self.history_tree),
+            ("archived_tasks", &self.archived_tasks_tree),
+            ("cold_tasks", &self.cold_tasks_tree),
+            ("state_changes", &self.state_changes_tree),
+            ("audit", &self.audit_tree),
+            ("quarantine", &self.quarantine_tree),
+            ("agents", &self.agents_tree),
+            ("views", &self.views_tree),
+            ("recurrences", &self.recurrences_tree),
+            ("dead_letters", &self.dead_letters_tree),
+            ("secrets", &self.secrets_tree),
+            ("task_logs", &self.task_logs_tree),
+            ("workers", &self.workers_tree),
+            ("heartbeats", &self.heartbeats_tree),
+            ("artifacts", &self.artifacts_tree),
+            ("artifact_data", &self.artifact_data_tree),
+            ("idempotency", &self.idempotency_tree),
+            ("tenants", &self.tenants_tree),
+            ("tenant_keys", &self.tenant_keys_tree),
+            ("queues", &self.queues_tree),
+        ]
     }
 
-    /// Load a task by ID
-    pub async fn load_task(&self, task_id: &uuid::Uuid) -> TaskQueueResult<Option<Task>> {
-        let key = task_id.to_string();
-        
-        if let Some(value) = self.tasks_tree.get(key)? {
-            let task: Task = serde_json::from_slice(&value)?;
-            Ok(Some(task))
-        } else {
-            Ok(None)
-        }
+    pub(crate) fn backend(&self) -> &Backend {
+        &self.db
     }
 
-    /// Store a workflow
-    pub async fn store_workflow(&self, workflow: &Workflow) -> TaskQueueResult<()> {
-        let key = workflow.id.to_string();
-        let value = serde_json::to_vec(workflow)?;
-        
-        self.workflows_tree.insert(key, value)?;
-        self.workflows_tree.flush_async().await?;
-        
-        Ok(())
+    /// Write new records in the given format
+    pub fn with_codec(mut self, codec: Codec) -> Self {
+        self.codec = codec;
+        self
     }
 
-    /// Load a workflow by ID
-    pub async fn load_workflow(&self, workflow_id: &uuid::Uuid) -> TaskQueueResult<Option<Workflow>> {
-        let key = workflow_id.to_string();
-        
-        if let Some(value) = self.workflows_tree.get(key)? {
-            let workflow: Workflow = serde_json::from_slice(&value)?;
-            Ok(Some(workflow))
-        } else {
-            Ok(None)
+    pub fn codec(&self) -> Codec {
+        self.codec
+    }
+
+    /// Write in the configured format, if one is set
+    fn with_configured_codec(self, codec: Option<Codec>) -> Self {
+        match codec {
+            Some(codec) => self.with_codec(codec),
+            None => self,
         }
     }
 
-    /// List all tasks
-    pub async fn list_tasks(&self) -> TaskQueueResult<Vec<Task>> {
-        let mut tasks = Vec::new();
-        
-        for result in self.tasks_tree.iter() {
-            let (_, value) = result?;
-            let task: Task = serde_json::from_slice(&value.to_vec())?;
-            tasks.push(task);
+    /// Format the storage was last migrated to, JSON if it never was
+    fn migrated_codec(db: &Backend) -> TaskQueueResult<Codec> {
+        match db.default_tree().get(CODEC_KEY)? {
+            Some(value) => Ok(serde_json::from_slice(&value)?),
+            None => Ok(Codec::Json),
         }
-        
-        Ok(tasks)
     }
 
-    /// List all workflows
-    pub async fn list_workflows(&self) -> TaskQueueResult<Vec<Workflow>> {
-        let mut workflows = Vec::new();
-        
-        for result in self.workflows_tree.iter() {
-            let (_, value) = result?;
-            let workflow: Workflow = serde_json::from_slice(&value.to_vec())?;
-            workflows.push(workflow);
+    /// Commit every write of the transaction, or none of them
+    pub async fn commit(&self, transaction: &Transaction) -> TaskQueueResult<()> {
+        #[cfg(test)]
+        if self.failing_commits.load(std::sync::atomic::Ordering::SeqCst) {
+            return Err(std::io::Error::other("commits are failing").into());
+        }
+        let _gate = self.write_gate.read().await;
+        let mut attempts = 1;
+        loop {
+            // Index and audit entries depend on the records being replaced, so
+            // those must not change between reading them and committing
+            let commit = self.commit_lock.lock().await;
+            let batch = self.stage_transaction(transaction)?;
+            match self.write_batch(batch, commit).await {
+                // Another process wrote a record after it was staged; staging
+                // again checks the version it wrote
+                Err(TaskQueueError::WriteConflict { .. }) if attempts < COMMIT_ATTEMPTS => attempts += 1,
+                result => return result,
+            }
         }
-        
-        Ok(workflows)
     }
 
-    /// Delete a task
-    pub async fn delete_task(&self, task_id: &uuid::Uuid) -> TaskQueueResult<()> {
-        let key = task_id.to_string();
-        self.tasks_tree.remove(key)?;
-        self.tasks_tree.flush_async().await?;
-        Ok(())
+    fn stage_transaction(&self, transaction: &Transaction) -> TaskQueueResult<Batch> {
+        let mut batch = Batch::default();
+        let checked = transaction.checks_versions();
+
+        for write in transaction.writes() {
+            match write {
+                RecordWrite::Task(task) => {
+                    let previous = self.stage_task(&mut batch, &task.id, Some(task))?;
+                    if checked {
+                        Self::expect_older(&mut batch, &self.tasks_tree, &task.id, previous, task.version, |t: Task| t.version)?;
+                    }
+                }
+                RecordWrite::DeleteTask(task_id) => {
+                    self.stage_task(&mut batch, task_id, None)?;
+                }
+                RecordWrite::Workflow(workflow) => {
+                    let previous = self.stage_record(&mut batch, &self.workflows_tree, AuditEntity::Workflow, &workflow.id, Some(workflow))?;
+                    if checked {
+                        Self::expect_older(&mut batch, &self.workflows_tree, &workflow.id, previous, workflow.version, |w: Workflow| w.version)?;
+                    }
+                }
+                RecordWrite::DeleteWorkflow(workflow_id) => {
+                    self.stage_record::<Workflow>(&mut batch, &self.workflows_tree, AuditEntity::Workflow, workflow_id, None)?;
+                }
+                RecordWrite::Project(project) => {
+                    let previous = self.stage_record(&mut batch, &self.projects_tree, AuditEntity::Project, &project.id, Some(project))?;
+                    if checked {
+                        Self::expect_older(&mut batch, &self.projects_tree, &project.id, previous, project.version, |p: Project| p.version)?;
+                    }
+                }
+                RecordWrite::DeleteProject(project_id) => {
+                    self.stage_record::<Project>(&mut batch, &self.projects_tree, AuditEntity::Project, project_id, None)?;
+                }
+            }
+        }
+        Ok(batch)
     }
 
-    /// Delete a workflow
-    pub async fn delete_workflow(&self, workflow_id: &uuid::Uuid) -> TaskQueueResult<()> {
-        let key = workflow_id.to_string();
-        self.workflows_tree.remove(key)?;
-        self.workflows_tree.flush_async().await?;
+    /// Fail unless the stored record is older than the version being written,
+    /// and have the batch apply only while it stays as read
+    fn expect_older<T: serde::de::DeserializeOwned>(batch: &mut Batch, tree: &Tree, id: &uuid::Uuid, previous: Option<sled::IVec>, version: u64, stored_version: fn(T) -> u64) -> TaskQueueResult<()> {
+        if let Some(previous) = &previous {
+            let stored = stored_version(Codec::decode(previous)?);
+            if stored >= version {
+                return Err(TaskQueueError::PreconditionFailed { version: stored });
+            }
+        }
+        batch.expect(tree, id.to_string(), previous);
         Ok(())
     }
 
-    /// Get storage statistics
-    pub async fn get_stats(&self) -> TaskQueueResult<StorageStats> {
-        let task_count = self.tasks_tree.len();
-        let workflow_count = self.workflows_tree.len();
-        let project_count = self.projects_tree.len();
-        let db_size = self.db.size_on_disk()?;
-        
-        Ok(StorageStats {
-            task_count,
-            workflow_count,
-            project_count,
-            db_size_bytes: db_size,
-        })
-    }
+    /// Apply a batch staged under the commit lock, then release the lock and
+    /// flush, timing how long it took
+    async fn write_batch(&self, batch: Batch, commit: tokio::sync::MutexGuard<'_, ()>) -> TaskQueueResult<()> {
+        let start = std::time::Instant::now();
+        self.db.apply(batch)?;
+        // Once applied, the next commit can stage against what it wrote
+        drop(commit);
+        self.db.flush_async().await?;
+        let elapsed = start.elapsed();
 
-    /// Store a project
-    pub async fn store_project(&self, project: &Project) -> TaskQueueResult<()> {
-        let key = project.id.to_string();
-        let value = serde_json::to_vec(project)?;
-        
-        self.projects_tree.insert(key, value)?;
-        self.projects_tree.flush_async().await?;
-        
+        let mut timing = self.write_timing.lock().unwrap_or_else(|e| e.into_inner());
+        timing.writes += 1;
+        timing.total += elapsed;
+        timing.last = elapsed;
         Ok(())
     }
 
-    /// Load a project by ID
+    /// Stage replacing a record, or removing it when `record` is unset, along
+    /// with its audit entry; returns the record being replaced
+    fn stage_record<T>(&self, batch: &mut Batch, tree: &Tree, entity: AuditEntity, id: &uuid::Uuid, record: Option<&T>) -> TaskQueueResult<Option<sled::IVec>>
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        let key = id.to_string();
+        let previous = tree.get(&key)?;
+        match record {
+            Some(record) => batch.insert(tree, &key, self.codec.encode(record)?),
+            None => batch.remove(tree, &key),
+        }
+        self.record_audit(batch, entity, id, previous.as_deref(), record)?;
+        Ok(previous)
+    }
+
+    /// Stage a task write with its index entries and revision; returns the
+    /// task being replaced
+    fn stage_task(&self, batch: &mut Batch, task_id: &uuid::Uuid, task: Option<&Task>) -> TaskQueueResult<Option<sled::IVec>> {
+        let previous = self.stage_record(batch, &self.tasks_tree, AuditEntity::Task, task_id, task)?;
+        self.reindex_task(batch, task_id, previous.as_deref(), task)?;
+        self.record_revision(batch, task_id, task)?;
+        Ok(previous)
+    }
+
+    /// Store a task
+    pub async fn store_task(&self, task: &Task) -> TaskQueueResult<()> {
+        self.commit(Transaction::default().put_task(task)).await
+    }
+
+    /// Key of a task revision: the task ID followed by the big-endian write time
+    fn revision_key(task_id: &uuid::Uuid, at: DateTime<Utc>) -> [u8; 24] {
+        let nanos = at.timestamp_nanos_opt().unwrap_or(i64::MAX).max(0) as u64;
+        let mut key = [0u8; 24];
+        key[..16].copy_from_slice(task_id.as_bytes());
+        key[16..].copy_from_slice(&nanos.to_be_bytes());
+        key
+    }
+
+    /// Append a revision; `None` marks the task as deleted from then on
+    fn record_revision(&self, batch: &mut Batch, task_id: &uuid::Uuid, task: Option<&Task>) -> TaskQueueResult<()> {
+        let key = Self::revision_key(task_id, Utc::now());
+        batch.insert(&self.history_tree, key, self.codec.encode(&task)?);
+        Ok(())
+    }
+
+    /// The task as it was stored at the given moment
+    pub async fn load_task_as_of(&self, task_id: &uuid::Uuid, at: DateTime<Utc>) -> TaskQueueResult<Option<Task>> {
+        let start = Self::revision_key(task_id, DateTime::<Utc>::UNIX_EPOCH);
+        let end = Self::revision_key(task_id, at);
+
+        match self.history_tree.range(start..=end).next_back() {
+            Some(result) => {
+                let (_, value) = result?;
+                Ok(Codec::decode::<Option<Task>>(&value)?)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Drop every stored revision of a task
+    pub async fn delete_task_revisions(&self, task_id: &uuid::Uuid) -> TaskQueueResult<()> {
+        let _gate = self.write_gate.read().await;
+        for result in self.history_tree.scan_prefix(task_id.as_bytes()) {
+            let (key, _) = result?;
+            self.history_tree.remove(key)?;
+        }
+        self.history_tree.flush_async().await?;
+        Ok(())
+    }
+
+    /// Keep a task's final state in the archive
+    pub async fn archive_task(&self, task: &Task) -> TaskQueueResult<()> {
+        let _gate = self.write_gate.read().await;
+        self.archived_tasks_tree.insert(task.id.to_string(), self.codec.encode(task)?)?;
+        self.archived_tasks_tree.flush_async().await?;
+        Ok(())
+    }
+
+    /// Load an archived task by ID
+    pub async fn load_archived_task(&self, task_id: &uuid::Uuid) -> TaskQueueResult<Option<Task>> {
+        match self.archived_tasks_tree.get(task_id.to_string())? {
+            Some(value) => Ok(Some(Codec::decode(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Move a task to cold storage: out of the task collection and its
+    /// indexes, keeping its revisions; returns whether it was still stored
+    pub async fn freeze_task(&self, task_id: &uuid::Uuid) -> TaskQueueResult<bool> {
+        let _gate = self.write_gate.read().await;
+        let commit = self.commit_lock.lock().await;
+        let key = task_id.to_string();
+        let Some(stored) = self.tasks_tree.get(&key)? else {
+            return Ok(false);
+        };
+        let mut batch = Batch::default();
+        batch.remove(&self.tasks_tree, &key);
+        self.reindex_task(&mut batch, task_id, Some(&stored), None)?;
+        batch.insert(&self.cold_tasks_tree, &key, crate::cold_storage::compress(&stored)?);
+        self.write_batch(batch, commit).await?;
+        Ok(true)
+    }
+
+    /// Load a task from cold storage by ID
+    pub async fn load_cold_task(&self, task_id: &uuid::Uuid) -> TaskQueueResult<Option<Task>> {
+        match self.cold_tasks_tree.get(task_id.to_string())? {
+            Some(value) => Ok(Some(Codec::decode(&crate::cold_storage::decompress(&value)?)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Log replacing the stored `previous` record of an entity with `current`
+    fn record_audit<T>(&self, batch: &mut Batch, entity: AuditEntity, id: &uuid::Uuid, previous: Option<&[u8]>, current: Option<&T>) -> TaskQueueResult<()>
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        // A record that no longer decodes shows up as created anew
+        let before = previous.and_then(|value| Codec::decode::<T>(value).ok()).map(|record| serde_json::to_value(record)).transpose()?;
+        let after = current.map(serde_json::to_value).transpose()?;
+        let Some(entry) = AuditEntry::record(entity, *id, before, after) else {
+            return Ok(());
+        };
+        // Write time, then entry ID, so entries list in order
+        let nanos = entry.at.timestamp_nanos_opt().unwrap_or(i64::MAX).max(0) as u64;
+        let key = [&nanos.to_be_bytes()[..], entry.id.as_bytes()].concat();
+        batch.insert(&self.audit_tree, key, self.codec.encode(&entry)?);
+        Ok(())
+    }
+
+    /// Recorded mutations matching `query`, oldest first
+    pub async fn list_audit(&self, query: &AuditQuery) -> TaskQueueResult<Vec<AuditEntry>> {
+        let since = query.since.and_then(|since| since.timestamp_nanos_opt()).unwrap_or(0).max(0) as u64;
+        let mut entries = Vec::new();
+
+        for result in self.audit_tree.range(since.to_be_bytes()..) {
+            let (_, value) = result?;
+            let entry: AuditEntry = Codec::decode(&value)?;
+            if query.matches(&entry) {
+                entries.push(entry);
+                if entries.len() >= query.limit {
+                    break;
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Append a task or workflow state change to the history log
+    pub async fn append_state_change(&self, change: &StateChange) -> TaskQueueResult<()> {
+        let _gate = self.write_gate.read().await;
+        // Entity ID, then write time and event sequence so changes list in order
+        let nanos = change.at.timestamp_nanos_opt().unwrap_or(i64::MAX).max(0) as u64;
+        let mut key = [0u8; 32];
+        key[..16].copy_from_slice(change.entity_id.as_bytes());
+        key[16..24].copy_from_slice(&nanos.to_be_bytes());
+        key[24..].copy_from_slice(&change.sequence.to_be_bytes());
+
+        self.state_changes_tree.insert(key, self.codec.encode(change)?)?;
+        self.state_changes_tree.flush_async().await?;
+        Ok(())
+    }
+
+    /// Every recorded state change of a task or workflow, oldest first
+    pub async fn list_state_changes(&self, entity_id: &uuid::Uuid) -> TaskQueueResult<Vec<StateChange>> {
+        let mut changes = Vec::new();
+
+        for result in self.state_changes_tree.scan_prefix(entity_id.as_bytes()) {
+            let (_, value) = result?;
+            changes.push(Codec::decode(&value)?);
+        }
+
+        Ok(changes)
+    }
+
+    /// Every state change recorded before `until`, by entity and then time
+    pub async fn list_state_changes_before(&self, until: DateTime<Utc>) -> TaskQueueResult<Vec<StateChange>> {
+        let until = until.timestamp_nanos_opt().unwrap_or(i64::MAX).max(0) as u64;
+        let mut changes = Vec::new();
+        for result in self.state_changes_tree.iter() {
+            let (key, value) = result?;
+            // The write time in the key spares decoding the later changes
+            let at = key.get(16..24).and_then(|nanos| nanos.try_into().ok()).map(u64::from_be_bytes);
+            if at.is_some_and(|at| at < until) {
+                changes.push(Codec::decode(&value)?);
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Load a task by ID
+    pub async fn load_task(&self, task_id: &uuid::Uuid) -> TaskQueueResult<Option<Task>> {
+        let key = task_id.to_string();
+        
+        if let Some(value) = self.tasks_tree.get(key)? {
+            let task: Task = Codec::decode(&value)?;
+            Ok(Some(task))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Store a workflow
+    pub async fn store_workflow(&self, workflow: &Workflow) -> TaskQueueResult<()> {
+        self.commit(Transaction::default().put_workflow(workflow)).await
+    }
+
+    /// Load a workflow by ID
+    pub async fn load_workflow(&self, workflow_id: &uuid::Uuid) -> TaskQueueResult<Option<Workflow>> {
+        let key = workflow_id.to_string();
+        
+        if let Some(value) = self.workflows_tree.get(key)? {
+            let workflow: Workflow = Codec::decode(&value)?;
+            Ok(Some(workflow))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// List all tasks
+    pub async fn list_tasks(&self) -> TaskQueueResult<Vec<Task>> {
+        let mut tasks = Vec::new();
+        
+        for result in self.tasks_tree.iter() {
+            let (_, value) = result?;
+            let task: Task = Codec::decode(&value)?;
+            tasks.push(task);
+        }
+        
+        Ok(tasks)
+    }
+
+    /// List up to `limit` tasks with IDs after `after`, reading only that page
+    pub async fn list_tasks_page(&self, after: Option<uuid::Uuid>, limit: usize) -> TaskQueueResult<TaskPage> {
+        let after = after.map(|id| id.to_string());
+        let limit = limit.max(1);
+        let mut tasks = Vec::with_capacity(limit);
+
+        // One extra record tells whether another page follows
+        for result in self.tasks_tree.page(after.as_deref().map(str::as_bytes), limit + 1) {
+            let (_, value) = result?;
+            tasks.push(Codec::decode::<Task>(&value)?);
+        }
+
+        let next = if tasks.len() > limit {
+            tasks.truncate(limit);
+            tasks.last().map(|task| task.id)
+        } else {
+            None
+        };
+        Ok((tasks, next))
+    }
+
+    /// IDs of the tasks with an indexed value, without reading the tasks
+    pub async fn find_task_ids(&self, index: &TaskIndex) -> TaskQueueResult<Vec<uuid::Uuid>> {
+        let mut ids = Vec::new();
+        for result in self.task_index_tree.scan_prefix(index.prefix()) {
+            let (key, _) = result?;
+            ids.extend(TaskIndex::task_id(&key));
+        }
+        Ok(ids)
+    }
+
+    /// Tasks having a search term, or with `prefix` any term it starts, with
+    /// the term and how often it occurs in each field
+    pub async fn find_postings(&self, term: &str, prefix: bool) -> TaskQueueResult<Vec<Posting>> {
+        let mut postings = Vec::new();
+        for result in self.search_index_tree.scan_prefix(search::term_prefix(term, !prefix)) {
+            let (key, value) = result?;
+            let Some(task_id) = TaskIndex::task_id(&key) else { continue };
+            let term = String::from_utf8_lossy(&key[..key.len() - 17]).into_owned();
+            postings.push(Posting { task_id, term, counts: TermCounts::decode(&value) });
+        }
+        Ok(postings)
+    }
+
+    /// Number of stored tasks
+    pub async fn count_tasks(&self) -> TaskQueueResult<usize> {
+        Ok(self.tasks_tree.len())
+    }
+
+    /// Replace a task's index entries, given its previous stored record
+    fn reindex_task(&self, batch: &mut Batch, task_id: &uuid::Uuid, previous: Option<&[u8]>, task: Option<&Task>) -> TaskQueueResult<()> {
+        let current = task.map(TaskIndex::of).unwrap_or_default();
+        // A record that no longer decodes cannot say what to remove
+        let previous = previous.and_then(|value| Codec::decode::<Task>(value).ok());
+        let stale = previous.as_ref().map(TaskIndex::of);
+        for index in stale.unwrap_or_default() {
+            if !current.contains(&index) {
+                batch.remove(&self.task_index_tree, index.key(task_id));
+            }
+        }
+        for index in current {
+            batch.insert(&self.task_index_tree, index.key(task_id), Vec::new());
+        }
+
+        let terms = task.map(search::terms).unwrap_or_default();
+        let stale = previous.as_ref().map(search::terms);
+        for term in stale.unwrap_or_default().into_keys() {
+            if !terms.contains_key(&term) {
+                batch.remove(&self.search_index_tree, search::index_key(&term, task_id));
+            }
+        }
+        for (term, counts) in terms {
+            batch.insert(&self.search_index_tree, search::index_key(&term, task_id), counts.encode());
+        }
+        Ok(())
+    }
+
+    /// Move artifact contents stored under task ID and name, from before the
+    /// blob store, to their blob keys
+    fn migrate_artifact_data(&self) -> TaskQueueResult<()> {
+        // Blob keys all start with `tasks/`
+        let legacy = self.artifact_data_tree.range(..&b"tasks/"[..])
+            .chain(self.artifact_data_tree.range(&b"tasks0"[..]..));
+        let mut moved = 0;
+        for result in legacy.collect::<Vec<_>>() {
+            let (key, data) = result?;
+            let task_id = key.get(..16).and_then(|id| uuid::Uuid::from_slice(id).ok());
+            let name = key.get(16..).and_then(|name| std::str::from_utf8(name).ok());
+            if let (Some(task_id), Some(name)) = (task_id, name) {
+                self.artifact_data_tree.insert(crate::artifacts::object_key(task_id, ArtifactKind::File, name), data)?;
+            }
+            self.artifact_data_tree.remove(key)?;
+            moved += 1;
+        }
+        if moved > 0 {
+            info!("Moved {} stored artifacts to blob keys", moved);
+        }
+        Ok(())
+    }
+
+    /// Index every stored task from scratch
+    pub fn rebuild_task_index(&self) -> TaskQueueResult<usize> {
+        for tree in [&self.task_index_tree, &self.search_index_tree] {
+            for result in tree.iter() {
+                let (key, _) = result?;
+                tree.remove(key)?;
+            }
+        }
+        let mut indexed = 0;
+        for result in self.tasks_tree.iter() {
+            let (_, value) = result?;
+            let task: Task = Codec::decode(&value)?;
+            let mut batch = Batch::default();
+            self.reindex_task(&mut batch, &task.id, None, Some(&task))?;
+            self.db.apply(batch)?;
+            indexed += 1;
+        }
+        info!("Indexed {} stored tasks", indexed);
+        Ok(indexed)
+    }
+
+    /// List all workflows
+    pub async fn list_workflows(&self) -> TaskQueueResult<Vec<Workflow>> {
+        let mut workflows = Vec::new();
+        
+        for result in self.workflows_tree.iter() {
+            let (_, value) = result?;
+            let workflow: Workflow = Codec::decode(&value)?;
+            workflows.push(workflow);
+        }
+        
+        Ok(workflows)
+    }
+
+    /// Delete a task
+    pub async fn delete_task(&self, task_id: &uuid::Uuid) -> TaskQueueResult<()> {
+        self.commit(Transaction::default().delete_task(*task_id)).await
+    }
+
+    /// Delete a workflow
+    pub async fn delete_workflow(&self, workflow_id: &uuid::Uuid) -> TaskQueueResult<()> {
+        self.commit(Transaction::default().delete_workflow(*workflow_id)).await
+    }
+
+    /// Get storage statistics
+    pub async fn get_stats(&self) -> TaskQueueResult<StorageStats> {
+        let task_count = self.tasks_tree.len();
+        let workflow_count = self.workflows_tree.len();
+        let project_count = self.projects_tree.len();
+        let db_size = self.db.size_on_disk()?;
+        let records = self.trees().into_iter().map(|(name, tree)| (name.to_string(), tree.len())).collect();
+        let timing = *self.write_timing.lock().unwrap_or_else(|e| e.into_inner());
+        let last_compaction = match self.db.default_tree().get(LAST_COMPACTION_KEY)? {
+            Some(value) => Some(Codec::decode(&value)?),
+            None => None,
+        };
+        
+        Ok(StorageStats {
+            task_count,
+            workflow_count,
+            project_count,
+            db_size_bytes: db_size,
+            records,
+            writes: timing.writes,
+            mean_write_seconds: if timing.writes == 0 { 0.0 } else { timing.total.as_secs_f64() / timing.writes as f64 },
+            last_write_seconds: timing.last.as_secs_f64(),
+            last_compaction,
+        })
+    }
+
+    /// Remove data of tasks that are gone: revisions of tasks deleted before
+    /// `tombstones_before`, and logs, artifacts, blobs, heartbeats, dead
+    /// letters and index entries of tasks stored neither hot nor cold, and
+    /// idempotency keys past their window.
+    /// Returns the removed artifacts too, for contents kept elsewhere
+    pub async fn vacuum(&self, tombstones_before: DateTime<Utc>, dry_run: bool) -> TaskQueueResult<(VacuumReport, Vec<Artifact>)> {
+        let _gate = self.write_gate.read().await;
+        // A task committed after listing the live ones would lose its index
+        let commit = self.commit_lock.lock().await;
+        let mut live = std::collections::HashSet::new();
+        for tree in [&self.tasks_tree, &self.cold_tasks_tree] {
+            for result in tree.iter() {
+                let (key, _) = result?;
+                if let Ok(id) = std::str::from_utf8(&key).map(uuid::Uuid::parse_str) {
+                    live.extend(id);
+                }
+            }
+        }
+        let gone = |id: Option<uuid::Uuid>| id.is_some_and(|id| !live.contains(&id));
+        let by_id_bytes = |key: &[u8]| key.get(..16).and_then(|id| uuid::Uuid::from_slice(id).ok());
+        let mut report = VacuumReport { dry_run, ..Default::default() };
+        let mut batch = Batch::default();
+        let mut artifacts = Vec::new();
+
+        // Revisions, grouped by task in key order; the last one of a deleted task is its tombstone
+        let mut revisions: Vec<sled::IVec> = Vec::new();
+        let flush = |revisions: &mut Vec<sled::IVec>, batch: &mut Batch, report: &mut VacuumReport| -> TaskQueueResult<()> {
+            let Some(last) = revisions.last() else { return Ok(()) };
+            let deleted_at = last.get(16..24).and_then(|nanos| nanos.try_into().ok()).map(|nanos| {
+                DateTime::<Utc>::from_timestamp_nanos(u64::from_be_bytes(nanos) as i64)
+            });
+            if gone(by_id_bytes(last)) && deleted_at.is_some_and(|at| at < tombstones_before) {
+                let tombstone = self.history_tree.get(last)?.map(|value| Codec::decode::<Option<Task>>(&value)).transpose()?;
+                if matches!(tombstone, Some(None)) {
+                    report.revisions += revisions.len();
+                    for key in revisions.iter() {
+                        batch.remove(&self.history_tree, key);
+                    }
+                }
+            }
+            revisions.clear();
+            Ok(())
+        };
+        for result in self.history_tree.iter() {
+            let (key, _) = result?;
+            if revisions.last().is_some_and(|last| last.get(..16) != key.get(..16)) {
+                flush(&mut revisions, &mut batch, &mut report)?;
+            }
+            revisions.push(key);
+        }
+        flush(&mut revisions, &mut batch, &mut report)?;
+
+        for result in self.task_index_tree.iter() {
+            let (key, _) = result?;
+            if gone(TaskIndex::task_id(&key)) {
+                batch.remove(&self.task_index_tree, key);
+                report.index_entries += 1;
+            }
+        }
+        for result in self.search_index_tree.iter() {
+            let (key, _) = result?;
+            if gone(TaskIndex::task_id(&key)) {
+                batch.remove(&self.search_index_tree, key);
+                report.index_entries += 1;
+            }
+        }
+        for result in self.task_logs_tree.iter() {
+            let (key, _) = result?;
+            if gone(by_id_bytes(&key)) {
+                batch.remove(&self.task_logs_tree, key);
+                report.log_chunks += 1;
+            }
+        }
+        for result in self.artifacts_tree.iter() {
+            let (key, value) = result?;
+            if gone(by_id_bytes(&key)) {
+                batch.remove(&self.artifacts_tree, key);
+                // Contents of artifacts that no longer decode stay for the blob scan below
+                artifacts.extend(Codec::decode::<Artifact>(&value).ok());
+                report.artifacts += 1;
+            }
+        }
+        for result in self.artifact_data_tree.scan_prefix(b"tasks/") {
+            let (key, _) = result?;
+            let id = std::str::from_utf8(&key).ok()
+                .and_then(|key| key.strip_prefix("tasks/")?.split('/').next())
+                .and_then(|id| uuid::Uuid::parse_str(id).ok());
+            if gone(id) {
+                batch.remove(&self.artifact_data_tree, key);
+                report.blobs += 1;
+            }
+        }
+        for result in self.heartbeats_tree.iter() {
+            let (key, _) = result?;
+            if gone(uuid::Uuid::from_slice(&key).ok()) {
+                batch.remove(&self.heartbeats_tree, key);
+                report.heartbeats += 1;
+            }
+        }
+        for result in self.dead_letters_tree.iter() {
+            let (key, _) = result?;
+            if gone(std::str::from_utf8(&key).ok().and_then(|id| uuid::Uuid::parse_str(id).ok())) {
+                batch.remove(&self.dead_letters_tree, key);
+                report.dead_letters += 1;
+            }
+        }
+        let now = Utc::now();
+        for result in self.idempotency_tree.iter() {
+            let (key, value) = result?;
+            if Codec::decode::<IdempotencyRecord>(&value).is_ok_and(|record| record.is_expired(now)) {
+                batch.remove(&self.idempotency_tree, key);
+                report.idempotency_keys += 1;
+            }
+        }
+
+        if !dry_run && !batch.is_empty() {
+            self.write_batch(batch, commit).await?;
+        }
+        Ok((report, artifacts))
+    }
+
+    /// Give the space of removed records back, then remember when
+    pub async fn compact(&self) -> TaskQueueResult<DateTime<Utc>> {
+        let _gate = self.write_gate.write().await;
+        self.db.compact().await?;
+        let now = Utc::now();
+        self.db.default_tree().insert(LAST_COMPACTION_KEY, self.codec.encode(&now)?)?;
+        self.db.flush_async().await?;
+        info!("Compacted storage, now {} bytes", self.db.size_on_disk()?);
+        Ok(now)
+    }
+
+    /// Write, read back and flush a probe record, returning how long it took
+    pub async fn probe(&self) -> TaskQueueResult<std::time::Duration> {
+        let start = std::time::Instant::now();
+        let value = chrono::Utc::now().to_rfc3339();
+
+        let tree = self.db.default_tree();
+        tree.insert("health_probe", value.as_bytes())?;
+        if tree.get("health_probe")?.as_deref() != Some(value.as_bytes()) {
+            return Err(TaskQueueError::InternalError("Storage probe read back a different value".to_string()));
+        }
+        self.db.flush_async().await?;
+
+        Ok(start.elapsed())
+    }
+
+    /// Store a project
+    pub async fn store_project(&self, project: &Project) -> TaskQueueResult<()> {
+        self.commit(Transaction::default().put_project(project)).await
+    }
+
+    /// Load a project by ID
     pub async fn load_project(&self, project_id: &uuid::Uuid) -> TaskQueueResult<Option<Project>> {
         let key = project_id.to_string();
         
         if let Some(value) = self.projects_tree.get(key)? {
-            let project: Project = serde_json::from_slice(&value)?;
+            let project: Project = Codec::decode(&value)?;
             Ok(Some(project))
         } else {
             Ok(None)
@@ -181,7 +1366,7 @@ impl StorageEngine {
         
         for result in self.projects_tree.iter() {
             let (_, value) = result?;
-            let project: Project = serde_json::from_slice(&value)?;
+            let project: Project = Codec::decode(&value)?;
             projects.push(project);
         }
         
@@ -190,18 +1375,1253 @@ impl StorageEngine {
 
     /// Delete a project
     pub async fn delete_project(&self, project_id: &uuid::Uuid) -> TaskQueueResult<()> {
-        let key = project_id.to_string();
-        self.projects_tree.remove(key)?;
-        self.projects_tree.flush_async().await?;
+        self.commit(Transaction::default().delete_project(*project_id)).await
+    }
+
+    /// Store an agent
+    pub async fn store_agent(&self, agent: &Agent) -> TaskQueueResult<()> {
+        let key = agent.id.to_string();
+        let value = self.codec.encode(agent)?;
+
+        self.agents_tree.insert(key, value)?;
+        self.agents_tree.flush_async().await?;
+
         Ok(())
     }
-}
 
-/// Storage statistics
-#[derive(Debug, Clone)]
-pub struct StorageStats {
-    pub task_count: usize,
-    pub workflow_count: usize,
-    pub project_count: usize,
-    pub db_size_bytes: u64,
+    /// List all registered agents
+    pub async fn list_agents(&self) -> TaskQueueResult<Vec<Agent>> {
+        let mut agents = Vec::new();
+
+        for result in self.agents_tree.iter() {
+            let (_, value) = result?;
+            agents.push(Codec::decode(&value)?);
+        }
+
+        Ok(agents)
+    }
+
+    /// Store a worker under its worker ID
+    pub async fn store_worker(&self, worker: &Worker) -> TaskQueueResult<()> {
+        self.workers_tree.insert(worker.id.as_bytes(), self.codec.encode(worker)?)?;
+        self.workers_tree.flush_async().await?;
+        Ok(())
+    }
+
+    pub async fn load_worker(&self, worker_id: &str) -> TaskQueueResult<Option<Worker>> {
+        match self.workers_tree.get(worker_id.as_bytes())? {
+            Some(value) => Ok(Some(Codec::decode(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn list_workers(&self) -> TaskQueueResult<Vec<Worker>> {
+        let mut workers = Vec::new();
+
+        for result in self.workers_tree.iter() {
+            let (_, value) = result?;
+            workers.push(Codec::decode(&value)?);
+        }
+
+        Ok(workers)
+    }
+
+    /// Record that a task's execution is alive at `at`
+    pub async fn store_heartbeat(&self, task_id: &uuid::Uuid, at: DateTime<Utc>) -> TaskQueueResult<()> {
+        self.heartbeats_tree.insert(task_id.as_bytes(), self.codec.encode(&at)?)?;
+        self.heartbeats_tree.flush_async().await?;
+        Ok(())
+    }
+
+    /// Last heartbeat of every task with one
+    pub async fn list_heartbeats(&self) -> TaskQueueResult<Heartbeats> {
+        let mut heartbeats = Vec::new();
+
+        for result in self.heartbeats_tree.iter() {
+            let (key, value) = result?;
+            let Ok(task_id) = uuid::Uuid::from_slice(&key) else {
+                continue;
+            };
+            heartbeats.push((task_id, Codec::decode(&value)?));
+        }
+
+        Ok(heartbeats)
+    }
+
+    pub async fn delete_heartbeat(&self, task_id: &uuid::Uuid) -> TaskQueueResult<()> {
+        self.heartbeats_tree.remove(task_id.as_bytes())?;
+        self.heartbeats_tree.flush_async().await?;
+        Ok(())
+    }
+
+    /// Key of an artifact: the task ID followed by the artifact name, which
+    /// a NUL byte no file name has precedes for log files
+    fn artifact_key(artifact: &Artifact) -> Vec<u8> {
+        let mut key = artifact.task_id.as_bytes().to_vec();
+        if artifact.kind == ArtifactKind::Log {
+            key.push(0);
+        }
+        key.extend_from_slice(artifact.name.as_bytes());
+        key
+    }
+
+    /// Store an artifact's description, replacing one of the same kind and name
+    pub async fn store_artifact(&self, artifact: &Artifact) -> TaskQueueResult<()> {
+        self.artifacts_tree.insert(Self::artifact_key(artifact), self.codec.encode(artifact)?)?;
+        self.artifacts_tree.flush_async().await?;
+        Ok(())
+    }
+
+    /// A task's artifacts: its log file, then files by name
+    pub async fn list_artifacts(&self, task_id: &uuid::Uuid) -> TaskQueueResult<Vec<Artifact>> {
+        let mut artifacts = Vec::new();
+
+        for result in self.artifacts_tree.scan_prefix(task_id.as_bytes()) {
+            let (_, value) = result?;
+            artifacts.push(Codec::decode(&value)?);
+        }
+
+        Ok(artifacts)
+    }
+
+    /// Delete the descriptions of a task's artifacts
+    pub async fn delete_task_artifacts(&self, task_id: &uuid::Uuid) -> TaskQueueResult<()> {
+        for result in self.artifacts_tree.scan_prefix(task_id.as_bytes()) {
+            let (key, _) = result?;
+            self.artifacts_tree.remove(key)?;
+        }
+        self.artifacts_tree.flush_async().await?;
+        Ok(())
+    }
+
+    /// Store the contents of a blob, for the database blob store
+    pub async fn store_blob(&self, key: &str, data: &[u8]) -> TaskQueueResult<()> {
+        self.artifact_data_tree.insert(key, data)?;
+        self.artifact_data_tree.flush_async().await?;
+        Ok(())
+    }
+
+    pub async fn load_blob(&self, key: &str) -> TaskQueueResult<Option<Vec<u8>>> {
+        Ok(self.artifact_data_tree.get(key)?.map(|data| data.to_vec()))
+    }
+
+    pub async fn delete_blob(&self, key: &str) -> TaskQueueResult<()> {
+        self.artifact_data_tree.remove(key)?;
+        self.artifact_data_tree.flush_async().await?;
+        Ok(())
+    }
+
+    /// Store a saved view under its [`SavedView::key`]
+    pub async fn store_view(&self, view: &SavedView) -> TaskQueueResult<()> {
+        self.views_tree.insert(view.key().as_bytes(), self.codec.encode(view)?)?;
+        self.views_tree.flush_async().await?;
+        Ok(())
+    }
+
+    pub async fn load_view(&self, key: &str) -> TaskQueueResult<Option<SavedView>> {
+        match self.views_tree.get(key.as_bytes())? {
+            Some(value) => Ok(Some(Codec::decode(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// List saved views in key order
+    pub async fn list_views(&self) -> TaskQueueResult<Vec<SavedView>> {
+        let mut views = Vec::new();
+
+        for result in self.views_tree.iter() {
+            let (_, value) = result?;
+            views.push(Codec::decode(&value)?);
+        }
+
+        Ok(views)
+    }
+
+    /// Store the response to a request made with an `Idempotency-Key`
+    pub async fn store_idempotency(&self, record: &IdempotencyRecord) -> TaskQueueResult<()> {
+        self.idempotency_tree.insert(record.scope.as_bytes(), self.codec.encode(record)?)?;
+        self.idempotency_tree.flush_async().await?;
+        Ok(())
+    }
+
+    pub async fn load_idempotency(&self, scope: &str) -> TaskQueueResult<Option<IdempotencyRecord>> {
+        match self.idempotency_tree.get(scope.as_bytes())? {
+            Some(value) => Ok(Some(Codec::decode(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn store_tenant(&self, tenant: &Tenant) -> TaskQueueResult<()> {
+        self.tenants_tree.insert(tenant.id.as_bytes(), self.codec.encode(tenant)?)?;
+        self.tenants_tree.flush_async().await?;
+        Ok(())
+    }
+
+    pub async fn load_tenant(&self, tenant_id: &uuid::Uuid) -> TaskQueueResult<Option<Tenant>> {
+        match self.tenants_tree.get(tenant_id.as_bytes())? {
+            Some(value) => Ok(Some(Codec::decode(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn list_tenants(&self) -> TaskQueueResult<Vec<Tenant>> {
+        let mut tenants = Vec::new();
+        for result in self.tenants_tree.iter() {
+            let (_, value) = result?;
+            tenants.push(Codec::decode(&value)?);
+        }
+        Ok(tenants)
+    }
+
+    /// Store a tenant's API key under its hash
+    pub async fn store_tenant_key(&self, key: &TenantKey) -> TaskQueueResult<()> {
+        self.tenant_keys_tree.insert(key.key_hash.as_bytes(), self.codec.encode(key)?)?;
+        self.tenant_keys_tree.flush_async().await?;
+        Ok(())
+    }
+
+    pub async fn load_tenant_key(&self, key_hash: &str) -> TaskQueueResult<Option<TenantKey>> {
+        match self.tenant_keys_tree.get(key_hash.as_bytes())? {
+            Some(value) => Ok(Some(Codec::decode(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// API keys of every tenant
+    pub async fn list_tenant_keys(&self) -> TaskQueueResult<Vec<TenantKey>> {
+        let mut keys = Vec::new();
+        for result in self.tenant_keys_tree.iter() {
+            let (_, value) = result?;
+            keys.push(Codec::decode(&value)?);
+        }
+        Ok(keys)
+    }
+
+    /// Delete a saved view, returning whether it existed
+    pub async fn delete_view(&self, key: &str) -> TaskQueueResult<bool> {
+        let existed = self.views_tree.remove(key.as_bytes())?.is_some();
+        self.views_tree.flush_async().await?;
+        Ok(existed)
+    }
+
+    /// Store a recurring task definition
+    /// Store a queue under its name
+    pub async fn store_queue(&self, queue: &Queue) -> TaskQueueResult<()> {
+        self.queues_tree.insert(queue.name.as_bytes(), self.codec.encode(queue)?)?;
+        self.queues_tree.flush_async().await?;
+        Ok(())
+    }
+
+    pub async fn load_queue(&self, name: &str) -> TaskQueueResult<Option<Queue>> {
+        match self.queues_tree.get(name.as_bytes())? {
+            Some(value) => Ok(Some(Codec::decode(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn list_queues(&self) -> TaskQueueResult<Vec<Queue>> {
+        let mut queues = Vec::new();
+        for result in self.queues_tree.iter() {
+            let (_, value) = result?;
+            queues.push(Codec::decode(&value)?);
+        }
+        Ok(queues)
+    }
+
+    /// Delete a queue, returning whether it existed
+    pub async fn delete_queue(&self, name: &str) -> TaskQueueResult<bool> {
+        let existed = self.queues_tree.remove(name.as_bytes())?.is_some();
+        self.queues_tree.flush_async().await?;
+        Ok(existed)
+    }
+
+    pub async fn store_recurrence(&self, recurrence: &RecurringTask) -> TaskQueueResult<()> {
+        self.recurrences_tree.insert(recurrence.id.to_string(), self.codec.encode(recurrence)?)?;
+        self.recurrences_tree.flush_async().await?;
+        Ok(())
+    }
+
+    pub async fn load_recurrence(&self, recurrence_id: &uuid::Uuid) -> TaskQueueResult<Option<RecurringTask>> {
+        match self.recurrences_tree.get(recurrence_id.to_string())? {
+            Some(value) => Ok(Some(Codec::decode(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn list_recurrences(&self) -> TaskQueueResult<Vec<RecurringTask>> {
+        let mut recurrences = Vec::new();
+
+        for result in self.recurrences_tree.iter() {
+            let (_, value) = result?;
+            recurrences.push(Codec::decode(&value)?);
+        }
+
+        Ok(recurrences)
+    }
+
+    /// Delete a recurring task definition, returning whether it existed
+    pub async fn delete_recurrence(&self, recurrence_id: &uuid::Uuid) -> TaskQueueResult<bool> {
+        let existed = self.recurrences_tree.remove(recurrence_id.to_string())?.is_some();
+        self.recurrences_tree.flush_async().await?;
+        Ok(existed)
+    }
+
+    /// Record a permanently failed task, replacing any earlier entry for it
+    pub async fn store_dead_letter(&self, entry: &DeadLetter) -> TaskQueueResult<()> {
+        self.dead_letters_tree.insert(entry.task_id.to_string(), self.codec.encode(entry)?)?;
+        self.dead_letters_tree.flush_async().await?;
+        Ok(())
+    }
+
+    pub async fn load_dead_letter(&self, task_id: &uuid::Uuid) -> TaskQueueResult<Option<DeadLetter>> {
+        match self.dead_letters_tree.get(task_id.to_string())? {
+            Some(value) => Ok(Some(Codec::decode(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn list_dead_letters(&self) -> TaskQueueResult<Vec<DeadLetter>> {
+        let mut entries = Vec::new();
+
+        for result in self.dead_letters_tree.iter() {
+            let (_, value) = result?;
+            entries.push(Codec::decode(&value)?);
+        }
+
+        Ok(entries)
+    }
+
+    /// Remove a task's dead-letter entry, returning whether it had one
+    pub async fn delete_dead_letter(&self, task_id: &uuid::Uuid) -> TaskQueueResult<bool> {
+        let existed = self.dead_letters_tree.remove(task_id.to_string())?.is_some();
+        self.dead_letters_tree.flush_async().await?;
+        Ok(existed)
+    }
+
+    pub async fn store_secret(&self, secret: &StoredSecret) -> TaskQueueResult<()> {
+        self.secrets_tree.insert(crate::secrets::key(secret.tenant_id, &secret.name).as_bytes(), self.codec.encode(secret)?)?;
+        self.secrets_tree.flush_async().await?;
+        Ok(())
+    }
+
+    pub async fn load_secret(&self, tenant: Option<uuid::Uuid>, name: &str) -> TaskQueueResult<Option<StoredSecret>> {
+        match self.secrets_tree.get(crate::secrets::key(tenant, name).as_bytes())? {
+            Some(value) => Ok(Some(Codec::decode(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn list_secrets(&self) -> TaskQueueResult<Vec<StoredSecret>> {
+        let mut secrets = Vec::new();
+
+        for result in self.secrets_tree.iter() {
+            let (_, value) = result?;
+            secrets.push(Codec::decode(&value)?);
+        }
+
+        Ok(secrets)
+    }
+
+    /// Delete a secret, returning whether it existed
+    pub async fn delete_secret(&self, tenant: Option<uuid::Uuid>, name: &str) -> TaskQueueResult<bool> {
+        let existed = self.secrets_tree.remove(crate::secrets::key(tenant, name).as_bytes())?.is_some();
+        self.secrets_tree.flush_async().await?;
+        Ok(existed)
+    }
+
+    fn log_key(task_id: &uuid::Uuid, seq: u64) -> [u8; 24] {
+        let mut key = [0u8; 24];
+        key[..16].copy_from_slice(task_id.as_bytes());
+        key[16..].copy_from_slice(&seq.to_be_bytes());
+        key
+    }
+
+    pub async fn append_log_chunk(&self, chunk: &LogChunk) -> TaskQueueResult<()> {
+        self.task_logs_tree.insert(Self::log_key(&chunk.task_id, chunk.seq), self.codec.encode(chunk)?)?;
+        self.task_logs_tree.flush_async().await?;
+        Ok(())
+    }
+
+    /// A task's log chunks in order, only those after `after` if given
+    pub async fn list_log_chunks(&self, task_id: &uuid::Uuid, after: Option<u64>) -> TaskQueueResult<Vec<LogChunk>> {
+        let start = match after {
+            Some(u64::MAX) => return Ok(Vec::new()),
+            Some(seq) => seq + 1,
+            None => 0,
+        };
+        let mut chunks = Vec::new();
+
+        for result in self.task_logs_tree.range(Self::log_key(task_id, start)..=Self::log_key(task_id, u64::MAX)) {
+            let (_, value) = result?;
+            chunks.push(Codec::decode(&value)?);
+        }
+
+        Ok(chunks)
+    }
+
+    /// Number of the last chunk in a task's log
+    pub async fn last_log_seq(&self, task_id: &uuid::Uuid) -> TaskQueueResult<Option<u64>> {
+        match self.task_logs_tree.scan_prefix(task_id.as_bytes()).next_back() {
+            Some(result) => {
+                let (key, _) = result?;
+                Ok(key[16..].try_into().ok().map(u64::from_be_bytes))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Delete a task's log
+    pub async fn delete_task_logs(&self, task_id: &uuid::Uuid) -> TaskQueueResult<()> {
+        for result in self.task_logs_tree.scan_prefix(task_id.as_bytes()) {
+            let (key, _) = result?;
+            self.task_logs_tree.remove(key)?;
+        }
+        self.task_logs_tree.flush_async().await?;
+        Ok(())
+    }
+
+    /// Store a project's configuration for an external integration
+    ///
+    /// Kept apart from the project itself so tokens never show up in
+    /// project responses.
+    pub async fn store_integration<T: serde::Serialize>(
+        &self,
+        project_id: &uuid::Uuid,
+        kind: &str,
+        config: &T,
+    ) -> TaskQueueResult<()> {
+        let key = format!("{}:{}", kind, project_id);
+        let value = self.codec.encode(config)?;
+
+        self.integrations_tree.insert(key, value)?;
+        self.integrations_tree.flush_async().await?;
+
+        Ok(())
+    }
+
+    /// Load a project's configuration for an external integration
+    pub async fn load_integration<T: serde::de::DeserializeOwned>(
+        &self,
+        project_id: &uuid::Uuid,
+        kind: &str,
+    ) -> TaskQueueResult<Option<T>> {
+        let key = format!("{}:{}", kind, project_id);
+
+        if let Some(value) = self.integrations_tree.get(key)? {
+            Ok(Some(Codec::decode(&value)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Remove a project's configuration for an external integration
+    pub async fn delete_integration(&self, project_id: &uuid::Uuid, kind: &str) -> TaskQueueResult<()> {
+        let key = format!("{}:{}", kind, project_id);
+        self.integrations_tree.remove(key)?;
+        self.integrations_tree.flush_async().await?;
+        Ok(())
+    }
+}
+
+impl StorageEngine {
+    /// Record an event awaiting delivery to an external broker
+    pub async fn append_outbox(&self, event: &TaskEvent) -> TaskQueueResult<()> {
+        let value = self.codec.encode(event)?;
+
+        self.outbox_tree.insert(event.sequence.to_be_bytes(), value)?;
+        self.outbox_tree.flush_async().await?;
+
+        Ok(())
+    }
+
+    /// Oldest undelivered events, in sequence order
+    pub async fn outbox_batch(&self, limit: usize) -> TaskQueueResult<Vec<TaskEvent>> {
+        let mut events = Vec::new();
+
+        for result in self.outbox_tree.iter().take(limit) {
+            let (_, value) = result?;
+            events.push(Codec::decode(&value)?);
+        }
+
+        Ok(events)
+    }
+
+    /// Drop an event once the broker acknowledged it
+    pub async fn ack_outbox(&self, sequence: u64) -> TaskQueueResult<()> {
+        self.outbox_tree.remove(sequence.to_be_bytes())?;
+        self.outbox_tree.flush_async().await?;
+        Ok(())
+    }
+
+    /// Highest sequence number still in the outbox, or 0
+    pub fn last_outbox_sequence(&self) -> TaskQueueResult<u64> {
+        Ok(match self.outbox_tree.last()? {
+            Some((key, _)) => key.as_ref().try_into().map(u64::from_be_bytes).unwrap_or(0),
+            None => 0,
+        })
+    }
+
+    fn collection_tree(&self, collection: Collection) -> &Tree {
+        match collection {
+            Collection::Tasks => &self.tasks_tree,
+            Collection::Projects => &self.projects_tree,
+            Collection::Workflows => &self.workflows_tree,
+        }
+    }
+
+    /// Task, project and workflow records that no longer decode
+    pub async fn find_undecodable_records(&self) -> TaskQueueResult<Vec<UndecodableRecord>> {
+        let mut records = Vec::new();
+        for collection in Collection::ALL {
+            for result in self.collection_tree(collection).iter() {
+                let (key, value) = result?;
+                let decoded = match collection {
+                    Collection::Tasks => Codec::decode::<Task>(&value).map(drop),
+                    Collection::Projects => Codec::decode::<Project>(&value).map(drop),
+                    Collection::Workflows => Codec::decode::<Workflow>(&value).map(drop),
+                };
+                if let Err(e) = decoded {
+                    let key = String::from_utf8_lossy(&key).into_owned();
+                    records.push(UndecodableRecord { collection, key, error: e.to_string() });
+                }
+            }
+        }
+        Ok(records)
+    }
+
+    /// Move a record out of its collection into quarantine, keeping its
+    /// stored bytes; false if there was no such record
+    pub async fn quarantine_record(&self, collection: Collection, key: &str) -> TaskQueueResult<bool> {
+        let _gate = self.write_gate.read().await;
+        let commit = self.commit_lock.lock().await;
+        let tree = self.collection_tree(collection);
+        let Some(value) = tree.get(key)? else {
+            return Ok(false);
+        };
+
+        let mut batch = Batch::default();
+        batch.insert(&self.quarantine_tree, format!("{}/{}", collection.name(), key), value);
+        // Records under their ID leave the same way a delete does
+        match uuid::Uuid::parse_str(key).ok().filter(|id| id.to_string() == key) {
+            Some(id) => match collection {
+                Collection::Tasks => {
+                    self.stage_task(&mut batch, &id, None)?;
+                }
+                Collection::Projects => {
+                    self.stage_record::<Project>(&mut batch, tree, AuditEntity::Project, &id, None)?;
+                }
+                Collection::Workflows => {
+                    self.stage_record::<Workflow>(&mut batch, tree, AuditEntity::Workflow, &id, None)?;
+                }
+            },
+            None => batch.remove(tree, key),
+        }
+        self.write_batch(batch, commit).await?;
+        info!("Quarantined {} record {}", collection.name(), key);
+        Ok(true)
+    }
+
+    /// Stored bytes of a quarantined record
+    pub async fn load_quarantined(&self, collection: Collection, key: &str) -> TaskQueueResult<Option<Vec<u8>>> {
+        Ok(self.quarantine_tree.get(format!("{}/{}", collection.name(), key))?.map(|value| value.to_vec()))
+    }
+
+    /// Read tasks, projects and workflows with writes held off, so the three
+    /// agree with each other
+    pub async fn consistent_read(&self) -> TaskQueueResult<ConsistentRead> {
+        let _gate = self.write_gate.write().await;
+        Ok((self.list_tasks().await?, self.list_projects().await?, self.list_workflows().await?))
+    }
+
+    /// Copy the stored task, project and workflow records without decoding
+    /// them, holding writes off only while copying
+    pub async fn export_records(&self) -> TaskQueueResult<ExportedRecords> {
+        let values = |tree: &Tree| tree.iter().map(|entry| Ok(entry?.1)).collect::<TaskQueueResult<Vec<_>>>();
+        let _gate = self.write_gate.write().await;
+        Ok(ExportedRecords {
+            tasks: values(&self.tasks_tree)?,
+            projects: values(&self.projects_tree)?,
+            workflows: values(&self.workflows_tree)?,
+        })
+    }
+}
+
+/// Outcome of re-encoding stored records
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct CodecMigrationReport {
+    pub converted: usize,
+    pub unchanged: usize,
+}
+
+impl StorageEngine {
+    /// Rewrite every record not yet in `codec`, then keep writing in it
+    pub async fn migrate_codec(&mut self, codec: Codec) -> TaskQueueResult<CodecMigrationReport> {
+        let mut report = CodecMigrationReport::default();
+        Self::migrate_tree::<Task>(&self.tasks_tree, codec, &mut report)?;
+        Self::migrate_tree::<Workflow>(&self.workflows_tree, codec, &mut report)?;
+        Self::migrate_tree::<Project>(&self.projects_tree, codec, &mut report)?;
+        Self::migrate_tree::<serde_json::Value>(&self.integrations_tree, codec, &mut report)?;
+        Self::migrate_tree::<TaskEvent>(&self.outbox_tree, codec, &mut report)?;
+        Self::migrate_tree::<Option<Task>>(&self.history_tree, codec, &mut report)?;
+        Self::migrate_tree::<Task>(&self.archived_tasks_tree, codec, &mut report)?;
+        Self::migrate_tree::<StateChange>(&self.state_changes_tree, codec, &mut report)?;
+        Self::migrate_tree::<AuditEntry>(&self.audit_tree, codec, &mut report)?;
+        Self::migrate_tree::<Agent>(&self.agents_tree, codec, &mut report)?;
+        Self::migrate_tree::<SavedView>(&self.views_tree, codec, &mut report)?;
+        Self::migrate_tree::<RecurringTask>(&self.recurrences_tree, codec, &mut report)?;
+        Self::migrate_tree::<DeadLetter>(&self.dead_letters_tree, codec, &mut report)?;
+        Self::migrate_tree::<StoredSecret>(&self.secrets_tree, codec, &mut report)?;
+        Self::migrate_tree::<LogChunk>(&self.task_logs_tree, codec, &mut report)?;
+        Self::migrate_tree::<Worker>(&self.workers_tree, codec, &mut report)?;
+        Self::migrate_tree::<DateTime<Utc>>(&self.heartbeats_tree, codec, &mut report)?;
+        Self::migrate_tree::<Artifact>(&self.artifacts_tree, codec, &mut report)?;
+        Self::migrate_tree::<IdempotencyRecord>(&self.idempotency_tree, codec, &mut report)?;
+        Self::migrate_tree::<Tenant>(&self.tenants_tree, codec, &mut report)?;
+        Self::migrate_tree::<TenantKey>(&self.tenant_keys_tree, codec, &mut report)?;
+        Self::migrate_tree::<Queue>(&self.queues_tree, codec, &mut report)?;
+        self.db.default_tree().insert(CODEC_KEY, serde_json::to_vec(&codec)?)?;
+        self.db.flush_async().await?;
+
+        self.codec = codec;
+        Ok(report)
+    }
+
+    fn migrate_tree<T: serde::Serialize + serde::de::DeserializeOwned>(
+        tree: &Tree,
+        codec: Codec,
+        report: &mut CodecMigrationReport,
+    ) -> TaskQueueResult<()> {
+        for result in tree.iter() {
+            let (key, value) = result?;
+            if Codec::detect(&value) == codec {
+                report.unchanged += 1;
+                continue;
+            }
+
+            let record: T = Codec::decode(&value)?;
+            tree.insert(key, codec.encode(&record)?)?;
+            report.converted += 1;
+        }
+        Ok(())
+    }
+}
+
+impl Storage for StorageEngine {
+    fn codec(&self) -> Codec {
+        StorageEngine::codec(self)
+    }
+
+    fn is_shared(&self) -> bool {
+        self.db.is_shared()
+    }
+
+    fn commit<'a>(&'a self, transaction: &'a Transaction) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::commit(self, transaction))
+    }
+
+    fn store_task<'a>(&'a self, task: &'a Task) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::store_task(self, task))
+    }
+
+    fn load_task_as_of<'a>(&'a self, task_id: &'a uuid::Uuid, at: DateTime<Utc>) -> BoxFuture<'a, TaskQueueResult<Option<Task>>> {
+        Box::pin(StorageEngine::load_task_as_of(self, task_id, at))
+    }
+
+    fn delete_task_revisions<'a>(&'a self, task_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::delete_task_revisions(self, task_id))
+    }
+
+    fn archive_task<'a>(&'a self, task: &'a Task) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::archive_task(self, task))
+    }
+
+    fn load_archived_task<'a>(&'a self, task_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<Option<Task>>> {
+        Box::pin(StorageEngine::load_archived_task(self, task_id))
+    }
+
+    fn freeze_task<'a>(&'a self, task_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<bool>> {
+        Box::pin(StorageEngine::freeze_task(self, task_id))
+    }
+
+    fn load_cold_task<'a>(&'a self, task_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<Option<Task>>> {
+        Box::pin(StorageEngine::load_cold_task(self, task_id))
+    }
+
+    fn append_state_change<'a>(&'a self, change: &'a StateChange) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::append_state_change(self, change))
+    }
+
+    fn list_audit<'a>(&'a self, query: &'a AuditQuery) -> BoxFuture<'a, TaskQueueResult<Vec<AuditEntry>>> {
+        Box::pin(StorageEngine::list_audit(self, query))
+    }
+
+    fn list_state_changes<'a>(&'a self, entity_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<Vec<StateChange>>> {
+        Box::pin(StorageEngine::list_state_changes(self, entity_id))
+    }
+
+    fn list_state_changes_before(&self, until: DateTime<Utc>) -> BoxFuture<'_, TaskQueueResult<Vec<StateChange>>> {
+        Box::pin(StorageEngine::list_state_changes_before(self, until))
+    }
+
+    fn load_task<'a>(&'a self, task_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<Option<Task>>> {
+        Box::pin(StorageEngine::load_task(self, task_id))
+    }
+
+    fn store_workflow<'a>(&'a self, workflow: &'a Workflow) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::store_workflow(self, workflow))
+    }
+
+    fn load_workflow<'a>(&'a self, workflow_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<Option<Workflow>>> {
+        Box::pin(StorageEngine::load_workflow(self, workflow_id))
+    }
+
+    fn list_tasks(&self) -> BoxFuture<'_, TaskQueueResult<Vec<Task>>> {
+        Box::pin(StorageEngine::list_tasks(self))
+    }
+
+    fn list_tasks_page(&self, after: Option<uuid::Uuid>, limit: usize) -> BoxFuture<'_, TaskQueueResult<TaskPage>> {
+        Box::pin(StorageEngine::list_tasks_page(self, after, limit))
+    }
+
+    fn find_task_ids<'a>(&'a self, index: &'a TaskIndex) -> BoxFuture<'a, TaskQueueResult<Vec<uuid::Uuid>>> {
+        Box::pin(StorageEngine::find_task_ids(self, index))
+    }
+
+    fn find_postings<'a>(&'a self, term: &'a str, prefix: bool) -> BoxFuture<'a, TaskQueueResult<Vec<Posting>>> {
+        Box::pin(StorageEngine::find_postings(self, term, prefix))
+    }
+
+    fn count_tasks(&self) -> BoxFuture<'_, TaskQueueResult<usize>> {
+        Box::pin(StorageEngine::count_tasks(self))
+    }
+
+    fn list_workflows(&self) -> BoxFuture<'_, TaskQueueResult<Vec<Workflow>>> {
+        Box::pin(StorageEngine::list_workflows(self))
+    }
+
+    fn delete_task<'a>(&'a self, task_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::delete_task(self, task_id))
+    }
+
+    fn delete_workflow<'a>(&'a self, workflow_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::delete_workflow(self, workflow_id))
+    }
+
+    fn get_stats(&self) -> BoxFuture<'_, TaskQueueResult<StorageStats>> {
+        Box::pin(StorageEngine::get_stats(self))
+    }
+
+    fn vacuum(&self, tombstones_before: DateTime<Utc>, dry_run: bool) -> BoxFuture<'_, TaskQueueResult<(VacuumReport, Vec<Artifact>)>> {
+        Box::pin(StorageEngine::vacuum(self, tombstones_before, dry_run))
+    }
+
+    fn compact(&self) -> BoxFuture<'_, TaskQueueResult<DateTime<Utc>>> {
+        Box::pin(StorageEngine::compact(self))
+    }
+
+    fn probe(&self) -> BoxFuture<'_, TaskQueueResult<std::time::Duration>> {
+        Box::pin(StorageEngine::probe(self))
+    }
+
+    fn store_project<'a>(&'a self, project: &'a Project) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::store_project(self, project))
+    }
+
+    fn load_project<'a>(&'a self, project_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<Option<Project>>> {
+        Box::pin(StorageEngine::load_project(self, project_id))
+    }
+
+    fn list_projects(&self) -> BoxFuture<'_, TaskQueueResult<Vec<Project>>> {
+        Box::pin(StorageEngine::list_projects(self))
+    }
+
+    fn delete_project<'a>(&'a self, project_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::delete_project(self, project_id))
+    }
+
+    fn store_agent<'a>(&'a self, agent: &'a Agent) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::store_agent(self, agent))
+    }
+
+    fn list_agents(&self) -> BoxFuture<'_, TaskQueueResult<Vec<Agent>>> {
+        Box::pin(StorageEngine::list_agents(self))
+    }
+
+    fn store_worker<'a>(&'a self, worker: &'a Worker) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::store_worker(self, worker))
+    }
+
+    fn load_worker<'a>(&'a self, worker_id: &'a str) -> BoxFuture<'a, TaskQueueResult<Option<Worker>>> {
+        Box::pin(StorageEngine::load_worker(self, worker_id))
+    }
+
+    fn list_workers(&self) -> BoxFuture<'_, TaskQueueResult<Vec<Worker>>> {
+        Box::pin(StorageEngine::list_workers(self))
+    }
+
+    fn store_heartbeat<'a>(&'a self, task_id: &'a uuid::Uuid, at: DateTime<Utc>) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::store_heartbeat(self, task_id, at))
+    }
+
+    fn list_heartbeats(&self) -> BoxFuture<'_, TaskQueueResult<Heartbeats>> {
+        Box::pin(StorageEngine::list_heartbeats(self))
+    }
+
+    fn delete_heartbeat<'a>(&'a self, task_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::delete_heartbeat(self, task_id))
+    }
+
+    fn store_artifact<'a>(&'a self, artifact: &'a Artifact) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::store_artifact(self, artifact))
+    }
+
+    fn list_artifacts<'a>(&'a self, task_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<Vec<Artifact>>> {
+        Box::pin(StorageEngine::list_artifacts(self, task_id))
+    }
+
+    fn delete_task_artifacts<'a>(&'a self, task_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::delete_task_artifacts(self, task_id))
+    }
+
+    fn store_blob<'a>(&'a self, key: &'a str, data: &'a [u8]) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::store_blob(self, key, data))
+    }
+
+    fn load_blob<'a>(&'a self, key: &'a str) -> BoxFuture<'a, TaskQueueResult<Option<Vec<u8>>>> {
+        Box::pin(StorageEngine::load_blob(self, key))
+    }
+
+    fn delete_blob<'a>(&'a self, key: &'a str) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::delete_blob(self, key))
+    }
+
+    fn store_view<'a>(&'a self, view: &'a SavedView) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::store_view(self, view))
+    }
+
+    fn load_view<'a>(&'a self, key: &'a str) -> BoxFuture<'a, TaskQueueResult<Option<SavedView>>> {
+        Box::pin(StorageEngine::load_view(self, key))
+    }
+
+    fn list_views(&self) -> BoxFuture<'_, TaskQueueResult<Vec<SavedView>>> {
+        Box::pin(StorageEngine::list_views(self))
+    }
+
+    fn delete_view<'a>(&'a self, key: &'a str) -> BoxFuture<'a, TaskQueueResult<bool>> {
+        Box::pin(StorageEngine::delete_view(self, key))
+    }
+
+    fn store_idempotency<'a>(&'a self, record: &'a IdempotencyRecord) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::store_idempotency(self, record))
+    }
+
+    fn load_idempotency<'a>(&'a self, scope: &'a str) -> BoxFuture<'a, TaskQueueResult<Option<IdempotencyRecord>>> {
+        Box::pin(StorageEngine::load_idempotency(self, scope))
+    }
+
+    fn store_tenant<'a>(&'a self, tenant: &'a Tenant) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::store_tenant(self, tenant))
+    }
+
+    fn load_tenant<'a>(&'a self, tenant_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<Option<Tenant>>> {
+        Box::pin(StorageEngine::load_tenant(self, tenant_id))
+    }
+
+    fn list_tenants(&self) -> BoxFuture<'_, TaskQueueResult<Vec<Tenant>>> {
+        Box::pin(StorageEngine::list_tenants(self))
+    }
+
+    fn store_tenant_key<'a>(&'a self, key: &'a TenantKey) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::store_tenant_key(self, key))
+    }
+
+    fn load_tenant_key<'a>(&'a self, key_hash: &'a str) -> BoxFuture<'a, TaskQueueResult<Option<TenantKey>>> {
+        Box::pin(StorageEngine::load_tenant_key(self, key_hash))
+    }
+
+    fn list_tenant_keys(&self) -> BoxFuture<'_, TaskQueueResult<Vec<TenantKey>>> {
+        Box::pin(StorageEngine::list_tenant_keys(self))
+    }
+
+    fn store_queue<'a>(&'a self, queue: &'a Queue) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::store_queue(self, queue))
+    }
+
+    fn load_queue<'a>(&'a self, name: &'a str) -> BoxFuture<'a, TaskQueueResult<Option<Queue>>> {
+        Box::pin(StorageEngine::load_queue(self, name))
+    }
+
+    fn list_queues(&self) -> BoxFuture<'_, TaskQueueResult<Vec<Queue>>> {
+        Box::pin(StorageEngine::list_queues(self))
+    }
+
+    fn delete_queue<'a>(&'a self, name: &'a str) -> BoxFuture<'a, TaskQueueResult<bool>> {
+        Box::pin(StorageEngine::delete_queue(self, name))
+    }
+
+    fn store_recurrence<'a>(&'a self, recurrence: &'a RecurringTask) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::store_recurrence(self, recurrence))
+    }
+
+    fn load_recurrence<'a>(&'a self, recurrence_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<Option<RecurringTask>>> {
+        Box::pin(StorageEngine::load_recurrence(self, recurrence_id))
+    }
+
+    fn list_recurrences(&self) -> BoxFuture<'_, TaskQueueResult<Vec<RecurringTask>>> {
+        Box::pin(StorageEngine::list_recurrences(self))
+    }
+
+    fn delete_recurrence<'a>(&'a self, recurrence_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<bool>> {
+        Box::pin(StorageEngine::delete_recurrence(self, recurrence_id))
+    }
+
+    fn store_dead_letter<'a>(&'a self, entry: &'a DeadLetter) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::store_dead_letter(self, entry))
+    }
+
+    fn load_dead_letter<'a>(&'a self, task_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<Option<DeadLetter>>> {
+        Box::pin(StorageEngine::load_dead_letter(self, task_id))
+    }
+
+    fn list_dead_letters(&self) -> BoxFuture<'_, TaskQueueResult<Vec<DeadLetter>>> {
+        Box::pin(StorageEngine::list_dead_letters(self))
+    }
+
+    fn delete_dead_letter<'a>(&'a self, task_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<bool>> {
+        Box::pin(StorageEngine::delete_dead_letter(self, task_id))
+    }
+
+    fn store_secret<'a>(&'a self, secret: &'a StoredSecret) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::store_secret(self, secret))
+    }
+
+    fn load_secret<'a>(&'a self, tenant: Option<uuid::Uuid>, name: &'a str) -> BoxFuture<'a, TaskQueueResult<Option<StoredSecret>>> {
+        Box::pin(StorageEngine::load_secret(self, tenant, name))
+    }
+
+    fn list_secrets(&self) -> BoxFuture<'_, TaskQueueResult<Vec<StoredSecret>>> {
+        Box::pin(StorageEngine::list_secrets(self))
+    }
+
+    fn delete_secret<'a>(&'a self, tenant: Option<uuid::Uuid>, name: &'a str) -> BoxFuture<'a, TaskQueueResult<bool>> {
+        Box::pin(StorageEngine::delete_secret(self, tenant, name))
+    }
+
+    fn append_log_chunk<'a>(&'a self, chunk: &'a LogChunk) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::append_log_chunk(self, chunk))
+    }
+
+    fn list_log_chunks<'a>(&'a self, task_id: &'a uuid::Uuid, after: Option<u64>) -> BoxFuture<'a, TaskQueueResult<Vec<LogChunk>>> {
+        Box::pin(StorageEngine::list_log_chunks(self, task_id, after))
+    }
+
+    fn last_log_seq<'a>(&'a self, task_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<Option<u64>>> {
+        Box::pin(StorageEngine::last_log_seq(self, task_id))
+    }
+
+    fn delete_task_logs<'a>(&'a self, task_id: &'a uuid::Uuid) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::delete_task_logs(self, task_id))
+    }
+
+    fn store_integration<'a>(&'a self, project_id: &'a uuid::Uuid, kind: &'a str, config: serde_json::Value) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(async move { StorageEngine::store_integration(self, project_id, kind, &config).await })
+    }
+
+    fn load_integration<'a>(&'a self, project_id: &'a uuid::Uuid, kind: &'a str) -> BoxFuture<'a, TaskQueueResult<Option<serde_json::Value>>> {
+        Box::pin(StorageEngine::load_integration(self, project_id, kind))
+    }
+
+    fn delete_integration<'a>(&'a self, project_id: &'a uuid::Uuid, kind: &'a str) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::delete_integration(self, project_id, kind))
+    }
+
+    fn append_outbox<'a>(&'a self, event: &'a TaskEvent) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::append_outbox(self, event))
+    }
+
+    fn outbox_batch<'a>(&'a self, limit: usize) -> BoxFuture<'a, TaskQueueResult<Vec<TaskEvent>>> {
+        Box::pin(StorageEngine::outbox_batch(self, limit))
+    }
+
+    fn ack_outbox<'a>(&'a self, sequence: u64) -> BoxFuture<'a, TaskQueueResult<()>> {
+        Box::pin(StorageEngine::ack_outbox(self, sequence))
+    }
+
+    fn last_outbox_sequence(&self) -> TaskQueueResult<u64> {
+        StorageEngine::last_outbox_sequence(self)
+    }
+
+    fn find_undecodable_records(&self) -> BoxFuture<'_, TaskQueueResult<Vec<UndecodableRecord>>> {
+        Box::pin(StorageEngine::find_undecodable_records(self))
+    }
+
+    fn quarantine_record<'a>(&'a self, collection: Collection, key: &'a str) -> BoxFuture<'a, TaskQueueResult<bool>> {
+        Box::pin(StorageEngine::quarantine_record(self, collection, key))
+    }
+
+    fn load_quarantined<'a>(&'a self, collection: Collection, key: &'a str) -> BoxFuture<'a, TaskQueueResult<Option<Vec<u8>>>> {
+        Box::pin(StorageEngine::load_quarantined(self, collection, key))
+    }
+
+    fn consistent_read(&self) -> BoxFuture<'_, TaskQueueResult<ConsistentRead>> {
+        Box::pin(StorageEngine::consistent_read(self))
+    }
+
+    fn export_records(&self) -> BoxFuture<'_, TaskQueueResult<ExportedRecords>> {
+        Box::pin(StorageEngine::export_records(self))
+    }
+}
+
+/// Storage statistics
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct StorageStats {
+    pub task_count: usize,
+    pub workflow_count: usize,
+    pub project_count: usize,
+    pub db_size_bytes: u64,
+    /// Records in every tree, by tree name
+    pub records: std::collections::BTreeMap<String, usize>,
+    /// Batches written since the storage was opened
+    pub writes: u64,
+    pub mean_write_seconds: f64,
+    pub last_write_seconds: f64,
+    pub last_compaction: Option<DateTime<Utc>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_migrate_codec_converts_json_records() {
+        let mut storage = StorageEngine::temporary().unwrap().with_codec(Codec::Json);
+        let task = Task::new("Build").with_command("cargo build").build();
+        let project = Project::new("Release");
+        storage.store_task(&task).await.unwrap();
+        storage.store_project(&project).await.unwrap();
+
+        // The task, its first revision, the project and both audit entries
+        let report = storage.migrate_codec(Codec::Binary).await.unwrap();
+        assert_eq!(report.converted, 5);
+        assert_eq!(storage.codec(), Codec::Binary);
+
+        let raw = storage.tasks_tree.get(task.id.to_string()).unwrap().unwrap();
+        assert_eq!(Codec::detect(&raw), Codec::Binary);
+        assert_eq!(storage.load_task(&task.id).await.unwrap().unwrap().name, "Build");
+        assert_eq!(storage.list_projects().await.unwrap()[0].id, project.id);
+
+        let report = storage.migrate_codec(Codec::Binary).await.unwrap();
+        assert_eq!((report.converted, report.unchanged), (0, 5));
+    }
+
+    #[tokio::test]
+    async fn test_storage_keeps_json_until_migrated() {
+        let mut storage = StorageEngine::temporary().unwrap();
+        assert_eq!(storage.codec(), Codec::Json);
+        assert_eq!(storage.reopen().unwrap().codec(), Codec::Json);
+
+        // Opening the storage again after a migration keeps the new format
+        storage.migrate_codec(Codec::Binary).await.unwrap();
+        let reopened = storage.reopen().unwrap();
+        assert_eq!(reopened.codec(), Codec::Binary);
+        let task = Task::new("Build").with_command("cargo build").build();
+        reopened.store_task(&task).await.unwrap();
+        assert_eq!(Codec::detect(&reopened.tasks_tree.get(task.id.to_string()).unwrap().unwrap()), Codec::Binary);
+    }
+
+    #[tokio::test]
+    async fn test_task_revisions_reconstruct_past_state() {
+        let storage = StorageEngine::temporary().unwrap();
+        let before = Utc::now();
+        let mut task = Task::new("Build").with_command("cargo build").build();
+        storage.store_task(&task).await.unwrap();
+        let planned = Utc::now();
+
+        task.status = TaskStatus::Implementation;
+        storage.store_task(&task).await.unwrap();
+        let implementing = Utc::now();
+        storage.delete_task(&task.id).await.unwrap();
+
+        let as_of = |at| storage.load_task_as_of(&task.id, at);
+        assert!(as_of(before).await.unwrap().is_none());
+        assert_eq!(as_of(planned).await.unwrap().unwrap().status, TaskStatus::Planning);
+        assert_eq!(as_of(implementing).await.unwrap().unwrap().status, TaskStatus::Implementation);
+        assert!(as_of(Utc::now()).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tasks_are_listed_page_by_page() {
+        let sled = sled::Config::new().temporary(true).open().unwrap();
+        for storage in [StorageEngine::temporary().unwrap(), StorageEngine::from_db(Arc::new(sled)).unwrap()] {
+            for i in 0..5 {
+                storage.store_task(&Task::new(&format!("task-{}", i)).with_command("true").build()).await.unwrap();
+            }
+
+            let mut seen = Vec::new();
+            let mut after = None;
+            loop {
+                let (tasks, next) = storage.list_tasks_page(after, 2).await.unwrap();
+                assert!(tasks.len() <= 2);
+                seen.extend(tasks.iter().map(|task| task.id));
+                match next {
+                    Some(next) => after = Some(next),
+                    None => break,
+                }
+            }
+            let mut all: Vec<_> = storage.list_tasks().await.unwrap().iter().map(|task| task.id).collect();
+            all.sort();
+            assert_eq!(seen, all);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_task_index_follows_writes_and_deletes() {
+        let storage = StorageEngine::temporary().unwrap();
+        let mut task = Task::new("Build").with_command("cargo build").with_project("web").build();
+        storage.store_task(&task).await.unwrap();
+        let planning = TaskIndex::Status(TaskStatus::Planning);
+        assert_eq!(storage.find_task_ids(&planning).await.unwrap(), [task.id]);
+        assert_eq!(storage.find_task_ids(&TaskIndex::Project("web".to_string())).await.unwrap(), [task.id]);
+
+        task.current_phase = TaskStatus::Implementation;
+        task.priority = TaskPriority::High;
+        storage.store_task(&task).await.unwrap();
+        assert!(storage.find_task_ids(&planning).await.unwrap().is_empty());
+        assert_eq!(storage.find_task_ids(&TaskIndex::Priority(TaskPriority::High)).await.unwrap(), [task.id]);
+        assert!(storage.find_task_ids(&TaskIndex::Priority(TaskPriority::Normal)).await.unwrap().is_empty());
+
+        storage.task_index_tree.insert(TaskIndex::Project("stale".to_string()).key(&task.id), Vec::new()).unwrap();
+        assert_eq!(storage.rebuild_task_index().unwrap(), 1);
+        assert!(storage.find_task_ids(&TaskIndex::Project("stale".to_string())).await.unwrap().is_empty());
+
+        storage.delete_task(&task.id).await.unwrap();
+        assert!(storage.task_index_tree.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_legacy_artifact_data_moves_to_blob_keys() {
+        let storage = StorageEngine::temporary().unwrap();
+        let task_id = uuid::Uuid::new_v4();
+        let legacy = [task_id.as_bytes().as_slice(), b"dist/app"].concat();
+        storage.artifact_data_tree.insert(legacy.clone(), &b"bin"[..]).unwrap();
+        storage.store_blob("tasks/other/logs/output.log", b"log").await.unwrap();
+
+        storage.migrate_artifact_data().unwrap();
+        let key = crate::artifacts::object_key(task_id, ArtifactKind::File, "dist/app");
+        assert_eq!(storage.load_blob(&key).await.unwrap().unwrap(), b"bin");
+        assert!(storage.artifact_data_tree.get(legacy).unwrap().is_none());
+        assert_eq!(storage.load_blob("tasks/other/logs/output.log").await.unwrap().unwrap(), b"log");
+    }
+
+    #[tokio::test]
+    async fn test_transactions_commit_every_record_together() {
+        let sled = sled::Config::new().temporary(true).open().unwrap();
+        for storage in [StorageEngine::temporary().unwrap(), StorageEngine::from_db(Arc::new(sled)).unwrap()] {
+            let project = Project::new("Release");
+            let stale = Task::new("Stale").with_command("true").build();
+            storage.store_task(&stale).await.unwrap();
+
+            let mut task = Task::new("Build").with_command("cargo build").build();
+            task.project_id = Some(project.id);
+            let mut transaction = Transaction::default();
+            transaction.put_task(&task).put_project(&project).delete_task(stale.id);
+            task.priority = TaskPriority::High;
+            transaction.put_task(&task);
+            assert_eq!(transaction.writes().len(), 3);
+            storage.commit(&transaction).await.unwrap();
+
+            assert_eq!(storage.load_task(&task.id).await.unwrap().unwrap().priority, TaskPriority::High);
+            assert!(storage.load_project(&project.id).await.unwrap().is_some());
+            assert!(storage.load_task(&stale.id).await.unwrap().is_none());
+            assert_eq!(storage.find_task_ids(&TaskIndex::Priority(TaskPriority::High)).await.unwrap(), [task.id]);
+            assert!(storage.load_task_as_of(&stale.id, Utc::now()).await.unwrap().is_none());
+            // The stale task's creation and deletion, then the new task and project
+            assert_eq!(storage.list_audit(&AuditQuery::default()).await.unwrap().len(), 4);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_checked_transactions_refuse_stale_versions() {
+        let storage = StorageEngine::temporary().unwrap();
+        let other = storage.reopen().unwrap();
+        let mut task = Task::new("Build").with_command("cargo build").build();
+        task.version = 1;
+        let mut create = Transaction::default();
+        storage.commit(create.put_task(&task).check_versions()).await.unwrap();
+
+        // Both engines start from version 1; only the first write of 2 lands
+        task.version = 2;
+        let mut first = Transaction::default();
+        storage.commit(first.put_task(&task).check_versions()).await.unwrap();
+        let mut stale = task.clone();
+        stale.name = "Stale".to_string();
+        let mut second = Transaction::default();
+        second.put_task(&stale).check_versions();
+        assert!(matches!(other.commit(&second).await, Err(TaskQueueError::PreconditionFailed { version: 2 })));
+        assert_eq!(other.load_task(&task.id).await.unwrap().unwrap().name, "Build");
+
+        stale.version = 3;
+        let mut third = Transaction::default();
+        other.commit(third.put_task(&stale).check_versions()).await.unwrap();
+        assert_eq!(storage.load_task(&task.id).await.unwrap().unwrap().name, "Stale");
+    }
+
+    #[tokio::test]
+    async fn test_undecodable_records_move_to_quarantine() {
+        let storage = StorageEngine::temporary().unwrap();
+        let task = Task::new("Build").with_command("cargo build").build();
+        storage.store_task(&task).await.unwrap();
+        storage.tasks_tree.insert("broken", &b"{not json"[..]).unwrap();
+        assert!(storage.list_tasks().await.is_err());
+
+        let records = storage.find_undecodable_records().await.unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!((records[0].collection, records[0].key.as_str()), (Collection::Tasks, "broken"));
+        assert!(storage.quarantine_record(Collection::Tasks, "broken").await.unwrap());
+        assert!(!storage.quarantine_record(Collection::Tasks, "broken").await.unwrap());
+
+        assert_eq!(storage.list_tasks().await.unwrap().len(), 1);
+        assert_eq!(storage.load_quarantined(Collection::Tasks, "broken").await.unwrap().unwrap(), b"{not json");
+    }
+
+    #[tokio::test]
+    async fn test_vacuum_removes_what_deleted_tasks_left() {
+        let storage = StorageEngine::temporary().unwrap();
+        let kept = Task::new("Kept").with_command("true").build();
+        let deleted = Task::new("Deleted").with_command("true").build();
+        for task in [&kept, &deleted] {
+            storage.store_task(task).await.unwrap();
+            let chunk = LogChunk { task_id: task.id, seq: 0, stream: crate::task_logs::LogStream::Stdout, data: "ok".to_string(), at: Utc::now() };
+            storage.append_log_chunk(&chunk).await.unwrap();
+            storage.store_heartbeat(&task.id, Utc::now()).await.unwrap();
+            let artifact = Artifact::new(task.id, "out.txt".to_string(), b"out");
+            storage.store_artifact(&artifact).await.unwrap();
+            storage.store_blob(&artifact.object_key(), b"out").await.unwrap();
+        }
+        let stored_at = Utc::now();
+        storage.delete_task(&deleted.id).await.unwrap();
+        // Left by a crash between the record and its index
+        storage.task_index_tree.insert(TaskIndex::Status(TaskStatus::Pending).key(&deleted.id), Vec::new()).unwrap();
+
+        let yesterday = Utc::now() - chrono::Duration::days(1);
+        let (report, artifacts) = storage.vacuum(yesterday, true).await.unwrap();
+        assert_eq!((report.index_entries, report.log_chunks, report.artifacts, report.blobs, report.heartbeats), (1, 1, 1, 1, 1));
+        assert_eq!(report.revisions, 0);
+        assert_eq!(artifacts[0].task_id, deleted.id);
+        assert_eq!(storage.list_log_chunks(&deleted.id, None).await.unwrap().len(), 1);
+
+        storage.vacuum(yesterday, false).await.unwrap();
+        assert!(storage.list_log_chunks(&deleted.id, None).await.unwrap().is_empty());
+        assert!(storage.list_artifacts(&deleted.id).await.unwrap().is_empty());
+        assert_eq!(storage.list_log_chunks(&kept.id, None).await.unwrap().len(), 1);
+        assert_eq!(storage.load_blob(&artifacts[0].object_key()).await.unwrap(), None);
+        assert!(storage.find_task_ids(&TaskIndex::Status(TaskStatus::Pending)).await.unwrap().is_empty());
+        assert_eq!(storage.find_task_ids(&TaskIndex::of(&kept)[0]).await.unwrap(), [kept.id]);
+        assert!(storage.load_task_as_of(&deleted.id, stored_at).await.unwrap().is_some());
+
+        let (report, _) = storage.vacuum(Utc::now() + chrono::Duration::seconds(1), false).await.unwrap();
+        assert_eq!((report.revisions, report.removed()), (2, 2));
+        assert!(storage.load_task_as_of(&deleted.id, stored_at).await.unwrap().is_none());
+        assert!(storage.load_task_as_of(&kept.id, Utc::now()).await.unwrap().is_some());
+    }
 }
@@ -0,0 +1,87 @@
+//! Task Index Module
+//!
+//! Secondary indexes over the stored tasks, so listings filtered by status,
+//! project or priority read the matching task IDs instead of decoding every
+//! task. Each entry is a key `{field}\0{value}\0{task id}` with an empty
+//! value; storage keeps them in step with the tasks on every write and
+//! delete, and builds them for databases created before they existed.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::{Task, TaskPriority, TaskStatus};
+use uuid::Uuid;
+
+/// An indexed field of a task, with the value to look up
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaskIndex {
+    /// Effective status, as shown and filtered on in listings
+    Status(TaskStatus),
+    /// Project name
+    Project(String),
+    ProjectId(Uuid),
+    Priority(TaskPriority),
+}
+
+impl TaskIndex {
+    /// Every index entry a task has
+    pub fn of(task: &Task) -> Vec<Self> {
+        let mut indexes = vec![
+            Self::Status(task.effective_status()),
+            Self::Priority(task.priority.clone()),
+        ];
+        if let Some(project) = &task.project {
+            indexes.push(Self::Project(project.clone()));
+        }
+        if let Some(project_id) = task.project_id {
+            indexes.push(Self::ProjectId(project_id));
+        }
+        indexes
+    }
+
+    /// Prefix shared by the keys of every task with this value
+    pub fn prefix(&self) -> Vec<u8> {
+        let (field, value): (&[u8], Vec<u8>) = match self {
+            Self::Status(status) => (b"status", format!("{:?}", status).into_bytes()),
+            Self::Project(name) => (b"project", name.as_bytes().to_vec()),
+            Self::ProjectId(id) => (b"project_id", id.as_bytes().to_vec()),
+            // A single byte, so keys order by priority
+            Self::Priority(priority) => (b"priority", vec![priority.clone() as u8]),
+        };
+        [field, b"\0", &value, b"\0"].concat()
+    }
+
+    /// Key of a task's entry
+    pub fn key(&self, task_id: &Uuid) -> Vec<u8> {
+        [self.prefix().as_slice(), task_id.as_bytes()].concat()
+    }
+
+    /// Task ID an entry's key points at
+    pub fn task_id(key: &[u8]) -> Option<Uuid> {
+        let id = key.len().checked_sub(16).map(|start| &key[start..])?;
+        Uuid::from_slice(id).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_keys_separate_fields_and_values() {
+        let task = Task::new("Build").with_command("cargo build").with_project("web").build();
+        let indexes = TaskIndex::of(&task);
+        assert!(indexes.contains(&TaskIndex::Status(TaskStatus::Planning)));
+        assert!(indexes.contains(&TaskIndex::Project("web".to_string())));
+
+        let key = TaskIndex::Project("web".to_string()).key(&task.id);
+        assert!(key.starts_with(&TaskIndex::Project("web".to_string()).prefix()));
+        assert!(!key.starts_with(&TaskIndex::Project("we".to_string()).prefix()));
+        assert_eq!(TaskIndex::task_id(&key), Some(task.id));
+
+        assert!(TaskIndex::Priority(TaskPriority::Low).prefix() < TaskIndex::Priority(TaskPriority::High).prefix());
+    }
+}
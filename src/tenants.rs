@@ -0,0 +1,349 @@
+//! Tenants Module
+//!
+//! One server can be shared by several teams, each working in a tenant of
+//! its own. Every task, project, workflow, recurrence and saved view belongs
+//! to the tenant of the request that created it, and a request made with one of a
+//! tenant's API keys (`X-API-Key` or `Authorization: Bearer`) only sees and
+//! changes that tenant's data, dead letters and archived tasks included:
+//! lookups of anything else answer `404` and listings leave it out. Tenants
+//! and their keys are managed under `/tenants` by the operator, whose
+//! requests carry `server.tenancy.admin_key` or, unless
+//! `server.tenancy.required` is set, no key at all; those requests see every
+//! tenant's data. Any other key is turned away with `401`, and MCP clients
+//! need a key unless `server.tenancy.mcp_anonymous` is set. Tenant keys
+//! can't reach operator endpoints such as `/admin`, `/audit` or `/import`,
+//! whose importers fetch from URLs the caller chooses, nor the queues,
+//! workers and agents every tenant's tasks share. Under `/secrets` each
+//! tenant manages secrets of its own, which only its tasks can resolve.
+//!
+//! Each key is issued with the scopes it may use: `read` to look, `write`
+//! to submit and change, `delete` to remove. A request needing a scope its
+//! key lacks is answered `403`, whether over REST, gRPC or MCP, where a
+//! tool's annotations say which scope it needs. Keys issued without scopes
+//! get all three. Scopes are checked as the [`Permission`]s of
+//! [`crate::auth`]: `TaskRead`, `TaskUpdate` and `TaskDelete`, all implied by
+//! the operator's `SystemAdmin`.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::auth::{permissions, ApiKeyManager};
+use crate::core::{Project, Task, Workflow};
+use crate::models::Permission;
+use axum::http::{header, HeaderMap, Method};
+use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::future::Future;
+use subtle::ConstantTimeEq;
+use uuid::Uuid;
+
+/// Request header carrying an API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+/// Prefix of the keys handed out to tenants
+pub const KEY_PREFIX: &str = "tq_";
+
+/// Paths only the operator may use
+const OPERATOR_PATHS: &[&str] = &["/admin", "/tenants", "/audit", "/import", "/queues", "/workers", "/agents"];
+
+tokio::task_local! {
+    static CURRENT_TENANT: Uuid;
+}
+
+/// Tenant the current request is scoped to; `None` for the operator and
+/// background jobs, which see every tenant
+pub fn current_tenant() -> Option<Uuid> {
+    CURRENT_TENANT.try_with(|tenant| *tenant).ok()
+}
+
+/// Run `future` scoped to `tenant`
+pub async fn with_tenant<F: Future>(tenant: Option<Uuid>, future: F) -> F::Output {
+    match tenant {
+        Some(tenant) => CURRENT_TENANT.scope(tenant, future).await,
+        None => future.await,
+    }
+}
+
+/// Whether the current request may see something owned by `owner`
+pub fn visible(owner: Option<Uuid>) -> bool {
+    match current_tenant() {
+        Some(tenant) => owner == Some(tenant),
+        None => true,
+    }
+}
+
+/// Owner of something being stored: the request's tenant, whatever the
+/// body claimed, or else the owner it already had
+pub fn owner(existing: Option<Uuid>) -> Option<Uuid> {
+    current_tenant().or(existing)
+}
+
+/// Something that belongs to a tenant
+pub trait Owned {
+    fn tenant_id(&self) -> Option<Uuid>;
+}
+
+impl Owned for Task {
+    fn tenant_id(&self) -> Option<Uuid> {
+        self.tenant_id
+    }
+}
+
+impl Owned for Project {
+    fn tenant_id(&self) -> Option<Uuid> {
+        self.tenant_id
+    }
+}
+
+impl Owned for Workflow {
+    fn tenant_id(&self) -> Option<Uuid> {
+        self.tenant_id
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TenancyConfig {
+    /// Reject requests without a key instead of treating them as the operator's
+    pub required: bool,
+    /// Key that identifies the operator
+    pub admin_key: Option<String>,
+    /// Let MCP clients in without a key, as the operator, when `required`
+    /// isn't set; off by default, as MCP tools can change and delete anything
+    pub mcp_anonymous: bool,
+}
+
+impl TenancyConfig {
+    /// Whether `key` is the admin key, compared in constant time
+    pub fn is_admin_key(&self, key: &str) -> bool {
+        self.admin_key.as_deref().is_some_and(|admin_key| {
+            Sha256::digest(key.as_bytes()).ct_eq(&Sha256::digest(admin_key.as_bytes())).into()
+        })
+    }
+}
+
+/// A team's share of the server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Tenant {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What a tenant key may be used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyScope {
+    Read,
+    Write,
+    Delete,
+}
+
+impl KeyScope {
+    pub fn all() -> Vec<KeyScope> {
+        vec![KeyScope::Read, KeyScope::Write, KeyScope::Delete]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeyScope::Read => "read",
+            KeyScope::Write => "write",
+            KeyScope::Delete => "delete",
+        }
+    }
+
+    /// Permission a key with this scope is granted
+    pub fn permission(&self) -> Permission {
+        match self {
+            KeyScope::Read => Permission::TaskRead,
+            KeyScope::Write => Permission::TaskUpdate,
+            KeyScope::Delete => Permission::TaskDelete,
+        }
+    }
+
+    /// Scope a REST request with this method needs
+    pub fn for_method(method: &Method) -> KeyScope {
+        match *method {
+            Method::GET | Method::HEAD | Method::OPTIONS => KeyScope::Read,
+            Method::DELETE => KeyScope::Delete,
+            _ => KeyScope::Write,
+        }
+    }
+}
+
+/// An API key scoped to a tenant; only its hash is kept
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TenantKey {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub name: String,
+    /// SHA-256 of the key
+    pub key_hash: String,
+    /// What the key may be used for; keys stored before scopes have all of them
+    #[serde(default = "KeyScope::all")]
+    pub scopes: Vec<KeyScope>,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl TenantKey {
+    pub fn is_active(&self) -> bool {
+        self.revoked_at.is_none()
+    }
+}
+
+/// Who a request is made by, as told by its API key
+#[derive(Debug, Clone, PartialEq)]
+pub enum Principal {
+    /// The admin key, or no key where none is required
+    Operator,
+    Tenant { tenant_id: Uuid, key_id: Uuid, scopes: Vec<KeyScope> },
+}
+
+impl Principal {
+    /// Tenant the requests are scoped to; `None` for the operator
+    pub fn tenant(&self) -> Option<Uuid> {
+        match self {
+            Principal::Operator => None,
+            Principal::Tenant { tenant_id, .. } => Some(*tenant_id),
+        }
+    }
+
+    /// Permissions granted: all of them for the operator, or those of the
+    /// key's scopes
+    pub fn permissions(&self) -> Vec<Permission> {
+        match self {
+            Principal::Operator => vec![Permission::SystemAdmin],
+            Principal::Tenant { scopes, .. } => scopes.iter().map(KeyScope::permission).collect(),
+        }
+    }
+
+    pub fn allows(&self, scope: KeyScope) -> bool {
+        permissions::validate_permission_hierarchy(&self.permissions(), scope.permission())
+    }
+}
+
+impl From<TenantKey> for Principal {
+    fn from(key: TenantKey) -> Self {
+        Principal::Tenant { tenant_id: key.tenant_id, key_id: key.id, scopes: key.scopes }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CreateTenantRequest {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CreateTenantKeyRequest {
+    pub name: String,
+    /// What the key may be used for; all scopes if left out
+    #[serde(default)]
+    pub scopes: Option<Vec<KeyScope>>,
+}
+
+/// A newly issued key; the key itself is only ever shown here
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct IssuedKey {
+    #[serde(flatten)]
+    pub info: TenantKey,
+    pub key: String,
+}
+
+/// A fresh random key
+pub fn generate_key() -> String {
+    let mut bytes = [0u8; 24];
+    OsRng.fill_bytes(&mut bytes);
+    let random: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("{}{}", KEY_PREFIX, random)
+}
+
+pub fn hash_key(key: &str) -> String {
+    ApiKeyManager::hash_api_key(key)
+}
+
+/// The API key or bearer token a request carries
+pub fn credential(headers: &HeaderMap) -> Option<&str> {
+    headers.get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .or_else(|| headers.get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer ")))
+        .map(str::trim)
+        .filter(|credential| !credential.is_empty())
+}
+
+/// Whether a path relative to the API root is for the operator only
+pub fn is_operator_path(path: &str) -> bool {
+    OPERATOR_PATHS.iter().any(|prefix| {
+        path.strip_prefix(prefix).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scope_limits_what_is_visible() {
+        let (team, other) = (Uuid::new_v4(), Uuid::new_v4());
+        assert!(visible(Some(team)) && visible(None));
+        assert_eq!(owner(Some(other)), Some(other));
+
+        with_tenant(Some(team), async {
+            assert!(visible(Some(team)));
+            assert!(!visible(Some(other)));
+            assert!(!visible(None));
+            assert_eq!(owner(Some(other)), Some(team));
+        })
+        .await;
+
+        let key = generate_key();
+        assert!(key.starts_with(KEY_PREFIX));
+        assert_ne!(hash_key(&key), hash_key(&generate_key()));
+        // Keys stored before hashing moved to `auth` still match
+        assert_eq!(hash_key("abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert!(is_operator_path("/tenants") && is_operator_path("/admin/backup"));
+        assert!(is_operator_path("/import/jira") && is_operator_path("/queues/builds"));
+        assert!(is_operator_path("/workers/register") && is_operator_path("/agents"));
+        assert!(!is_operator_path("/tasks") && !is_operator_path("/tenantsx"));
+    }
+
+    #[test]
+    fn test_key_scopes_limit_what_a_principal_may_do() {
+        let stored = serde_json::json!({
+            "id": Uuid::new_v4(),
+            "tenant_id": Uuid::new_v4(),
+            "name": "ci",
+            "key_hash": hash_key("tq_old"),
+            "created_at": Utc::now(),
+            "revoked_at": null
+        });
+        let key: TenantKey = serde_json::from_value(stored).unwrap();
+        assert_eq!(key.scopes, KeyScope::all());
+
+        let reader = Principal::from(TenantKey { scopes: vec![KeyScope::Read], ..key.clone() });
+        assert_eq!(reader.tenant(), Some(key.tenant_id));
+        assert!(reader.allows(KeyScope::Read) && !reader.allows(KeyScope::Delete));
+        assert!(Principal::Operator.allows(KeyScope::Delete) && Principal::Operator.tenant().is_none());
+        assert_eq!(reader.permissions(), [Permission::TaskRead]);
+        assert_eq!(Principal::from(key).permissions(), [Permission::TaskRead, Permission::TaskUpdate, Permission::TaskDelete]);
+
+        let tenancy = TenancyConfig { admin_key: Some("operator".to_string()), ..Default::default() };
+        assert!(tenancy.is_admin_key("operator"));
+        assert!(!tenancy.is_admin_key("operator ") && !tenancy.is_admin_key(""));
+        assert!(!TenancyConfig::default().is_admin_key(""));
+
+        assert_eq!(KeyScope::for_method(&Method::GET), KeyScope::Read);
+        assert_eq!(KeyScope::for_method(&Method::PATCH), KeyScope::Write);
+        assert_eq!(KeyScope::for_method(&Method::DELETE), KeyScope::Delete);
+    }
+}
@@ -0,0 +1,206 @@
+//! Time Series Module
+//!
+//! Queue activity over time for dashboard charts, computed from the
+//! persisted state history rather than the tasks as they are now, so it
+//! still counts tasks that were deleted since. `GET /stats/timeseries`
+//! splits a `window` (default `7d`) ending now into buckets of `bucket`
+//! (default `1h`) and reports for each how many tasks were created and
+//! finished, what share of the finished ones completed or failed, and how
+//! long they took.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::TaskStatus;
+use crate::events::TaskEventKind;
+use crate::history::{status_name, StateChange};
+use chrono::{DateTime, Duration, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+pub const DEFAULT_WINDOW: &str = "7d";
+pub const DEFAULT_BUCKET: &str = "1h";
+/// Longest window that may be asked for
+pub const MAX_WINDOW_DAYS: i64 = 90;
+/// Most buckets a window may be split into
+pub const MAX_BUCKETS: i64 = 2_000;
+
+/// A span such as `30m`, `1h`, `7d` or `2w`; a bare number is seconds
+pub fn parse_span(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let amount: i64 = value[..split].parse().ok()?;
+    let unit = match &value[split..] {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    Duration::try_seconds(amount.checked_mul(unit)?).filter(|span| *span > Duration::zero())
+}
+
+/// Window and bucket size of a request
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeseriesQuery {
+    pub window: Duration,
+    pub bucket: Duration,
+}
+
+impl TimeseriesQuery {
+    /// Parse `window` and `bucket`, explaining what is wrong with them
+    pub fn parse(window: Option<&str>, bucket: Option<&str>) -> Result<Self, String> {
+        let window = window.unwrap_or(DEFAULT_WINDOW);
+        let bucket = bucket.unwrap_or(DEFAULT_BUCKET);
+        let window = parse_span(window).ok_or_else(|| format!("window {} is not a span such as 7d", window))?;
+        let bucket = parse_span(bucket).ok_or_else(|| format!("bucket {} is not a span such as 1h", bucket))?;
+        if window > Duration::days(MAX_WINDOW_DAYS) {
+            return Err(format!("window must be at most {}d", MAX_WINDOW_DAYS));
+        }
+        if bucket > window {
+            return Err("bucket must not be longer than the window".to_string());
+        }
+        if window.num_seconds() / bucket.num_seconds() > MAX_BUCKETS {
+            return Err(format!("window must split into at most {} buckets", MAX_BUCKETS));
+        }
+        Ok(Self { window, bucket })
+    }
+
+    /// Start and end of the buckets ending at `now`; buckets line up on
+    /// multiples of their size, the last one holding `now`
+    pub fn range(&self, now: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+        let size = self.bucket.num_seconds();
+        let end = DateTime::from_timestamp((now.timestamp().div_euclid(size) + 1) * size, 0).unwrap_or(now);
+        let count = (self.window.num_seconds() + size - 1) / size;
+        (end - Duration::seconds(count * size), end)
+    }
+}
+
+/// Activity in one bucket
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Bucket {
+    pub start: DateTime<Utc>,
+    pub created: u64,
+    pub completed: u64,
+    pub failed: u64,
+    pub cancelled: u64,
+    /// Tasks that finished, whichever way
+    pub throughput: u64,
+    /// Share of the finished tasks that completed; unset without any
+    pub completion_rate: Option<f64>,
+    pub failure_rate: Option<f64>,
+    /// Mean time the finished tasks took from starting to run, or from
+    /// being created if they never ran
+    pub average_duration_secs: Option<f64>,
+    #[serde(skip)]
+    #[schemars(skip)]
+    total_duration_secs: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Timeseries {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub bucket_secs: i64,
+    pub buckets: Vec<Bucket>,
+}
+
+/// Bucket the task changes of `changes`, ordered by entity and then time,
+/// that fall in `query`'s window ending at `now`
+pub fn compute(changes: &[StateChange], query: &TimeseriesQuery, now: DateTime<Utc>) -> Timeseries {
+    let (from, to) = query.range(now);
+    let size = query.bucket.num_seconds();
+    let mut buckets: Vec<Bucket> = (0..(to - from).num_seconds() / size)
+        .map(|index| Bucket { start: from + Duration::seconds(index * size), ..Bucket::default() })
+        .collect();
+    let running = status_name(&TaskStatus::Running);
+
+    // When the current entity was created and last started running
+    let mut entity = None;
+    let (mut created_at, mut started_at) = (None, None);
+    for change in changes {
+        if entity != Some(change.entity_id) {
+            entity = Some(change.entity_id);
+            (created_at, started_at) = (None, None);
+        }
+        match change.kind {
+            TaskEventKind::TaskCreated => created_at = Some(change.at),
+            _ if change.to == running => started_at = Some(change.at),
+            _ => {}
+        }
+        if change.at < from || change.at >= to {
+            continue;
+        }
+        let bucket = &mut buckets[((change.at - from).num_seconds() / size) as usize];
+        match change.kind {
+            TaskEventKind::TaskCreated => bucket.created += 1,
+            TaskEventKind::TaskCompleted => bucket.completed += 1,
+            TaskEventKind::TaskFailed => bucket.failed += 1,
+            TaskEventKind::TaskCancelled => bucket.cancelled += 1,
+            _ => continue,
+        }
+        if matches!(change.kind, TaskEventKind::TaskCompleted | TaskEventKind::TaskFailed | TaskEventKind::TaskCancelled) {
+            bucket.throughput += 1;
+            if let Some(since) = started_at.or(created_at) {
+                bucket.total_duration_secs += (change.at - since).num_milliseconds().max(0) as f64 / 1000.0;
+            }
+        }
+    }
+
+    for bucket in &mut buckets {
+        if bucket.throughput > 0 {
+            let finished = bucket.throughput as f64;
+            bucket.completion_rate = Some(bucket.completed as f64 / finished);
+            bucket.failure_rate = Some(bucket.failed as f64 / finished);
+            bucket.average_duration_secs = Some(bucket.total_duration_secs / finished);
+        }
+    }
+    Timeseries { from, to, bucket_secs: size, buckets }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(entity_id: Uuid, kind: TaskEventKind, to: Option<&str>, at: DateTime<Utc>) -> StateChange {
+        StateChange { entity_id, sequence: 0, kind, from: None, to: to.map(str::to_string), agent: None, at }
+    }
+
+    #[test]
+    fn test_changes_are_bucketed_with_rates_and_durations() {
+        assert_eq!(parse_span("7d"), Some(Duration::days(7)));
+        assert!(parse_span("0h").is_none() && parse_span("1y").is_none());
+        assert!(TimeseriesQuery::parse(Some("1d"), Some("1s")).is_err());
+        assert!(TimeseriesQuery::parse(Some("1h"), Some("1d")).is_err());
+
+        let query = TimeseriesQuery::parse(Some("2h"), Some("1h")).unwrap();
+        let now = DateTime::parse_from_rfc3339("2026-03-01T10:30:00Z").unwrap().with_timezone(&Utc);
+        let at = |minutes: i64| now - Duration::minutes(minutes);
+        let (built, broken) = (Uuid::from_u128(1), Uuid::from_u128(2));
+        let changes = [
+            // Created before the window, so only its completion counts
+            change(built, TaskEventKind::TaskCreated, Some("Pending"), at(300)),
+            change(built, TaskEventKind::TaskStatusChanged, Some("Running"), at(20)),
+            change(built, TaskEventKind::TaskCompleted, Some("Completed"), at(10)),
+            change(broken, TaskEventKind::TaskCreated, Some("Pending"), at(80)),
+            change(broken, TaskEventKind::TaskFailed, Some("Failed"), at(5)),
+        ];
+
+        let series = compute(&changes, &query, now);
+        assert_eq!(series.to - series.from, Duration::hours(2));
+        assert_eq!(series.buckets.len(), 2);
+        let (earlier, latest) = (&series.buckets[0], &series.buckets[1]);
+        assert_eq!((earlier.created, earlier.throughput), (1, 0));
+        assert_eq!(earlier.completion_rate, None);
+        assert_eq!((latest.completed, latest.failed, latest.throughput), (1, 1, 2));
+        assert_eq!(latest.failure_rate, Some(0.5));
+        // Ten minutes of running and 75 minutes since creation
+        assert_eq!(latest.average_duration_secs, Some((600.0 + 4500.0) / 2.0));
+    }
+}
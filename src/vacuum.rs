@@ -0,0 +1,105 @@
+//! Storage Vacuum Module
+//!
+//! Deleting a task leaves data behind that nothing reads any more: the
+//! revision history ending in its deletion, and whatever a crash or an older
+//! release failed to remove along with it — captured logs, artifacts and
+//! their contents, heartbeats, dead-letter entries and task index entries.
+//! When `storage.vacuum.enabled` is set, a background job removes that data
+//! every `interval_secs`. Revision histories are kept for `tombstone_days`
+//! after the deletion, so the task can still be read as of earlier moments
+//! for a while. Tasks in cold storage count as present; state changes and the
+//! audit log are never vacuumed. Once something was removed the storage is
+//! compacted. `POST /admin/vacuum?dry_run=true` reports what would be removed,
+//! and without `dry_run` removes it on demand.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::server::TaskQueueServer;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info};
+
+/// The background vacuum job
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VacuumConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    /// Days the revisions of a deleted task are kept
+    #[serde(default = "default_tombstone_days")]
+    pub tombstone_days: u32,
+}
+
+fn default_interval_secs() -> u64 {
+    6 * 3600
+}
+
+fn default_tombstone_days() -> u32 {
+    30
+}
+
+impl Default for VacuumConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: default_interval_secs(),
+            tombstone_days: default_tombstone_days(),
+        }
+    }
+}
+
+impl VacuumConfig {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs.max(1))
+    }
+
+    /// Revisions of tasks deleted before this moment are removed
+    pub fn tombstones_before(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now - chrono::Duration::days(i64::from(self.tombstone_days))
+    }
+}
+
+/// Records removed, or that would be, by kind
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VacuumReport {
+    pub dry_run: bool,
+    /// Stored revisions of deleted tasks
+    pub revisions: usize,
+    pub index_entries: usize,
+    pub log_chunks: usize,
+    pub artifacts: usize,
+    /// Artifact and log file contents kept in the database
+    pub blobs: usize,
+    pub heartbeats: usize,
+    pub dead_letters: usize,
+    /// Stored responses to idempotent requests past their window
+    pub idempotency_keys: usize,
+}
+
+impl VacuumReport {
+    pub fn removed(&self) -> usize {
+        self.revisions + self.index_entries + self.log_chunks + self.artifacts + self.blobs + self.heartbeats + self.dead_letters + self.idempotency_keys
+    }
+}
+
+/// Vacuum storage every `interval_secs` in the background
+pub fn spawn(server: Arc<TaskQueueServer>, config: VacuumConfig) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(config.interval());
+        loop {
+            ticker.tick().await;
+            match server.vacuum_storage(Utc::now(), false).await {
+                Ok(report) if report.removed() > 0 => info!("Vacuum removed {} orphaned records", report.removed()),
+                Ok(_) => {}
+                Err(e) => error!("Failed to vacuum storage: {}", e),
+            }
+        }
+    })
+}
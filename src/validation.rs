@@ -0,0 +1,310 @@
+//! Validation Module
+//!
+//! Request bodies that create tasks, workflows and projects are checked
+//! field by field before anything runs: lengths, formats and values out of
+//! range. The [`Valid`] extractor parses the body, naming the field when a
+//! UUID, date or enum value doesn't parse, then runs the type's
+//! [`Validate`] checks; any failure answers `422 Unprocessable Entity` with
+//! `{"errors": [{"field", "message"}]}`, one entry per offending field.
+//! Nested fields are named by path, e.g. `tasks[2].name`.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::*;
+use crate::queues::{self, CreateQueueRequest};
+use crate::tenants::{CreateTenantKeyRequest, CreateTenantRequest};
+use axum::extract::{FromRequest, Request};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+
+pub const MAX_NAME_LENGTH: usize = 200;
+pub const MAX_COMMAND_LENGTH: usize = 10_000;
+pub const MAX_DESCRIPTION_LENGTH: usize = 20_000;
+pub const MAX_SPECS_LENGTH: usize = 50_000;
+pub const MAX_LIST_ITEMS: usize = 100;
+pub const MAX_ITEM_LENGTH: usize = 1_000;
+pub const MAX_AI_REVIEWS: u32 = 10;
+pub const MAX_WORKFLOW_TASKS: usize = 1_000;
+
+/// Checks a parsed request runs before it is acted on
+pub trait Validate {
+    /// Every field with a value the request can't have
+    fn validate(&self) -> Vec<FieldError>;
+}
+
+/// Collects the errors of one request
+#[derive(Debug, Default)]
+pub struct Checks {
+    errors: Vec<FieldError>,
+}
+
+impl Checks {
+    pub fn fail(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.errors.push(FieldError::new(field, message));
+    }
+
+    /// A required text that isn't blank and fits `max` characters
+    pub fn text(&mut self, field: &str, value: &str, max: usize) {
+        if value.trim().is_empty() {
+            self.fail(field, "must not be empty");
+        } else {
+            self.optional_text(field, Some(value), max);
+        }
+    }
+
+    pub fn optional_text(&mut self, field: &str, value: Option<&str>, max: usize) {
+        if let Some(value) = value
+            && value.chars().count() > max
+        {
+            self.fail(field, format!("must be at most {} characters", max));
+        }
+    }
+
+    /// A list of at most `MAX_LIST_ITEMS` non-blank items
+    pub fn list(&mut self, field: &str, items: &[String]) {
+        if items.len() > MAX_LIST_ITEMS {
+            self.fail(field, format!("must have at most {} items", MAX_LIST_ITEMS));
+        }
+        for (index, item) in items.iter().enumerate() {
+            self.text(&format!("{}[{}]", field, index), item, MAX_ITEM_LENGTH);
+        }
+    }
+
+    /// Errors of a nested value, named under `prefix`
+    pub fn nested(&mut self, prefix: &str, value: &impl Validate) {
+        self.errors.extend(value.validate().into_iter().map(|e| FieldError::new(format!("{}.{}", prefix, e.field), e.message)));
+    }
+
+    pub fn finish(self) -> Vec<FieldError> {
+        self.errors
+    }
+}
+
+impl Validate for CreateTaskRequest {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut checks = Checks::default();
+        checks.text("name", &self.name, MAX_NAME_LENGTH);
+        checks.text("command", &self.command, MAX_COMMAND_LENGTH);
+        checks.text("description", &self.description, MAX_DESCRIPTION_LENGTH);
+        checks.optional_text("technical_specs", self.technical_specs.as_deref(), MAX_SPECS_LENGTH);
+        checks.optional_text("project", self.project.as_deref(), MAX_NAME_LENGTH);
+        checks.list("acceptance_criteria", self.acceptance_criteria.as_deref().unwrap_or_default());
+        checks.list("tags", self.tags.as_deref().unwrap_or_default());
+        if self.ai_reviews_required.is_some_and(|reviews| reviews > MAX_AI_REVIEWS) {
+            checks.fail("ai_reviews_required", format!("must be at most {}", MAX_AI_REVIEWS));
+        }
+        if self.timeout_secs == Some(0) {
+            checks.fail("timeout_secs", "must be positive");
+        }
+        if self.cpu.is_some_and(|cpu| !cpu.is_finite() || cpu <= 0.0) {
+            checks.fail("cpu", "must be a positive number");
+        }
+        if self.memory_mb == Some(0) {
+            checks.fail("memory_mb", "must be positive");
+        }
+        if self.queue.as_deref().is_some_and(|queue| !queues::is_valid_name(queue)) {
+            checks.fail("queue", "must be a queue name");
+        }
+        checks.finish()
+    }
+}
+
+impl Validate for UpdateTaskRequest {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut checks = Checks::default();
+        if let Some(name) = &self.name {
+            checks.text("name", name, MAX_NAME_LENGTH);
+        }
+        if let Some(command) = &self.command {
+            checks.text("command", command, MAX_COMMAND_LENGTH);
+        }
+        if let Some(description) = &self.description {
+            checks.text("description", description, MAX_DESCRIPTION_LENGTH);
+        }
+        if let Some(specs) = &self.technical_specs {
+            checks.optional_text("technical_specs", specs.as_deref(), MAX_SPECS_LENGTH);
+        }
+        if let Some(criteria) = &self.acceptance_criteria {
+            checks.list("acceptance_criteria", criteria);
+        }
+        checks.finish()
+    }
+}
+
+impl Validate for Task {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut checks = Checks::default();
+        checks.text("name", &self.name, MAX_NAME_LENGTH);
+        checks.text("command", &self.command, MAX_COMMAND_LENGTH);
+        checks.optional_text("description", Some(&self.description), MAX_DESCRIPTION_LENGTH);
+        checks.optional_text("technical_specs", self.technical_specs.as_deref(), MAX_SPECS_LENGTH);
+        checks.list("acceptance_criteria", &self.acceptance_criteria);
+        checks.finish()
+    }
+}
+
+impl Validate for Workflow {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut checks = Checks::default();
+        checks.text("name", &self.name, MAX_NAME_LENGTH);
+        checks.optional_text("description", self.description.as_deref(), MAX_DESCRIPTION_LENGTH);
+        if self.tasks.is_empty() {
+            checks.fail("tasks", "must have at least one task");
+        } else if self.tasks.len() > MAX_WORKFLOW_TASKS {
+            checks.fail("tasks", format!("must have at most {} tasks", MAX_WORKFLOW_TASKS));
+        }
+        for (index, task) in self.tasks.iter().enumerate() {
+            checks.nested(&format!("tasks[{}]", index), task);
+        }
+        let ids: HashSet<_> = self.tasks.iter().map(|task| task.id).collect();
+        for (index, dependency) in self.dependencies.iter().enumerate() {
+            for (end, id) in [("from_task", dependency.from_task), ("to_task", dependency.to_task)] {
+                if !ids.contains(&id) {
+                    checks.fail(format!("dependencies[{}].{}", index, end), "must be the ID of a task in the workflow");
+                }
+            }
+        }
+        checks.finish()
+    }
+}
+
+impl Validate for CreateProjectRequest {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut checks = Checks::default();
+        checks.text("name", &self.name, MAX_NAME_LENGTH);
+        checks.optional_text("description", self.description.as_deref(), MAX_DESCRIPTION_LENGTH);
+        checks.finish()
+    }
+}
+
+impl Validate for CreateQueueRequest {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut checks = Checks::default();
+        if !queues::is_valid_name(&self.name) {
+            checks.fail("name", format!("must be at most {} letters, digits, '-', '_' or '.'", queues::MAX_QUEUE_NAME_LENGTH));
+        }
+        checks.optional_text("description", self.description.as_deref(), MAX_DESCRIPTION_LENGTH);
+        if self.max_concurrency == Some(0) {
+            checks.fail("max_concurrency", "must be positive");
+        }
+        if let Some(limit) = &self.rate_limit {
+            if limit.tasks_per_minute == 0 {
+                checks.fail("rate_limit.tasks_per_minute", "must be positive");
+            }
+            if limit.burst == 0 {
+                checks.fail("rate_limit.burst", "must be positive");
+            }
+        }
+        checks.finish()
+    }
+}
+
+impl Validate for CreateTenantRequest {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut checks = Checks::default();
+        checks.text("name", &self.name, MAX_NAME_LENGTH);
+        checks.optional_text("description", self.description.as_deref(), MAX_DESCRIPTION_LENGTH);
+        checks.finish()
+    }
+}
+
+impl Validate for CreateTenantKeyRequest {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut checks = Checks::default();
+        checks.text("name", &self.name, MAX_NAME_LENGTH);
+        if self.scopes.as_ref().is_some_and(Vec::is_empty) {
+            checks.fail("scopes", "must not be empty");
+        }
+        checks.finish()
+    }
+}
+
+/// `422 Unprocessable Entity` listing the offending fields
+pub fn reject(errors: Vec<FieldError>) -> Response {
+    (StatusCode::UNPROCESSABLE_ENTITY, Json(json!({ "errors": errors }))).into_response()
+}
+
+/// Parse a value, naming the field that didn't parse
+pub fn parse<T: DeserializeOwned>(value: Value) -> Result<T, Vec<FieldError>> {
+    serde_path_to_error::deserialize(value).map_err(|e| {
+        let field = match e.path().to_string() {
+            path if path == "." => String::new(),
+            path => path,
+        };
+        vec![FieldError::new(field, e.into_inner().to_string())]
+    })
+}
+
+/// A JSON body that parsed and passed its [`Validate`] checks
+#[derive(Debug)]
+pub struct Valid<T>(pub T);
+
+impl<S, T> FromRequest<S> for Valid<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned + Validate,
+{
+    type Rejection = Response;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        // Bodies that aren't JSON at all keep the extractor's own answer
+        let Json(value) = Json::<Value>::from_request(request, state).await.map_err(IntoResponse::into_response)?;
+        let value: T = parse(value).map_err(reject)?;
+        match value.validate() {
+            errors if errors.is_empty() => Ok(Valid(value)),
+            errors => Err(reject(errors)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_errors_name_each_field() {
+        let errors = parse::<CreateTaskRequest>(json!({
+            "name": "Build",
+            "command": "make",
+            "description": "Build it",
+            "task_type": "Simple",
+            "priority": "Urgent",
+            "project_id": null,
+            "project": null,
+            "estimated_hours": null,
+            "tags": null,
+            "technical_specs": null,
+            "acceptance_criteria": null,
+            "ai_reviews_required": null
+        }))
+        .unwrap_err();
+        assert_eq!(errors[0].field, "priority");
+
+        let workflow = Workflow {
+            id: uuid::Uuid::new_v4(),
+            version: 0,
+            tenant_id: None,
+            name: "Release".to_string(),
+            description: None,
+            tasks: vec![Task::new(" ").with_command("make").build()],
+            dependencies: vec![WorkflowDependency {
+                from_task: uuid::Uuid::new_v4(),
+                to_task: uuid::Uuid::new_v4(),
+                condition: DependencyCondition::Success,
+            }],
+            created_at: std::time::SystemTime::now(),
+            updated_at: std::time::SystemTime::now(),
+            status: WorkflowStatus::Pending,
+        };
+        let fields: Vec<_> = workflow.validate().into_iter().map(|e| e.field).collect();
+        assert_eq!(fields, ["tasks[0].name", "dependencies[0].from_task", "dependencies[0].to_task"]);
+    }
+}
@@ -26,7 +26,7 @@ impl VectorizerIntegration {
         
         // Test connection (optional - don't fail if vectorizer is not available)
         let response = client
-            .get(&format!("{}/health", base_url))
+            .get(format!("{}/health", base_url))
             .timeout(std::time::Duration::from_secs(5))
             .send()
             .await;
@@ -56,6 +56,14 @@ impl VectorizerIntegration {
         }
     }
 
+    /// Create an integration talking to the vectorizer at `base_url`
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+            ..Self::new_dummy()
+        }
+    }
+
     /// Store task context in vectorizer
     pub async fn store_task_context(&self, context: &TaskContext) -> Result<()> {
         // Create a rich text representation of the task context
@@ -88,7 +96,7 @@ impl VectorizerIntegration {
         // Try the MCP-style endpoint first
         let response = self
             .client
-            .post(&format!("{}/insert_texts", self.base_url))
+            .post(format!("{}/insert_texts", self.base_url))
             .json(&payload)
             .send()
             .await;
@@ -123,7 +131,7 @@ impl VectorizerIntegration {
 
         let response = self
             .client
-            .post(&format!("{}/collections/{}/search", self.base_url, self.collection))
+            .post(format!("{}/collections/{}/search", self.base_url, self.collection))
             .json(&payload)
             .send()
             .await?;
@@ -187,7 +195,7 @@ impl VectorizerIntegration {
                     execution_time_estimate: metadata
                         .get("execution_time_ms")
                         .and_then(|t| t.as_u64())
-                        .map(|t| std::time::Duration::from_millis(t))
+                        .map(std::time::Duration::from_millis)
                         .unwrap_or_default(),
                 };
                 recommendations.push(recommendation);
@@ -205,6 +213,15 @@ impl VectorizerIntegration {
             text.push_str(&format!("Project: {}\n", project));
         }
         
+        // What the task is about, e.g. its name and description, so natural
+        // language queries find it
+        let mut parameters: Vec<_> = context.parameters.iter().collect();
+        parameters.sort_by_key(|(key, _)| key.as_str());
+        for (key, value) in parameters {
+            let value = value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string());
+            text.push_str(&format!("{}: {}\n", key, value));
+        }
+
         text.push_str(&format!("Execution Time: {}ms\n", context.execution_time.as_millis()));
         
         text.push_str("Dependencies: ");
@@ -231,7 +248,7 @@ impl VectorizerIntegration {
                 text.push_str(&format!("Artifacts: {:?}\n", artifacts));
                 text.push_str(&format!("Metrics: {:?}\n", metrics));
             }
-            TaskResult::Failure { error, exit_code, logs } => {
+            TaskResult::Failure { error, exit_code, logs, .. } => {
                 text.push_str(&format!("Result: FAILURE\nError: {}\n", error));
                 if let Some(code) = exit_code {
                     text.push_str(&format!("Exit Code: {}\n", code));
@@ -350,5 +367,6 @@ mod tests {
         assert!(text.contains("test-project"));
         assert!(text.contains("Test completed"));
         assert!(text.contains("SUCCESS"));
+        assert!(text.contains("test: true"));
     }
 }
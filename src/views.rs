@@ -0,0 +1,355 @@
+//! Saved Views Module
+//!
+//! Named filter and sort combinations stored on the server, so the dashboard,
+//! the CLI and MCP clients can all ask for "my overdue criticals" by name
+//! instead of repeating the filter everywhere.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::*;
+use crate::error::{TaskQueueError, Result};
+use crate::estimates;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Values accepted in `statuses`, as understood by the `status` list filter
+pub const STATUS_FILTERS: [&str; 12] = [
+    "planning",
+    "scheduled",
+    "pending",
+    "running",
+    "paused",
+    "completed",
+    "failed",
+    "cancelled",
+    "implementation",
+    "testcreation",
+    "testing",
+    "aireview",
+];
+
+/// Which tasks a view shows; every field set must match
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TaskFilter {
+    /// Project name
+    pub project: Option<String>,
+    pub project_id: Option<Uuid>,
+    /// Any of these statuses
+    #[serde(default)]
+    pub statuses: Vec<String>,
+    /// Any of these priorities
+    #[serde(default)]
+    pub priorities: Vec<TaskPriority>,
+    /// Lowest priority, inclusive
+    pub min_priority: Option<TaskPriority>,
+    /// Highest priority, inclusive
+    pub max_priority: Option<TaskPriority>,
+    /// Tags the task must all have
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    /// Only open tasks past their due date
+    #[serde(default)]
+    pub overdue: bool,
+    /// Case-insensitive substring of the task name
+    pub name_contains: Option<String>,
+    /// Case-insensitive substring of the task name or description
+    pub text: Option<String>,
+    /// Metadata entries the task must have, e.g. `{"component": "frontend"}`
+    #[serde(default)]
+    pub metadata: HashMap<String, serde_json::Value>,
+}
+
+impl TaskFilter {
+    /// Tasks of a project with a status, both optional
+    pub fn of(project: Option<String>, status: Option<String>) -> Self {
+        Self {
+            project,
+            statuses: status.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Filter from the query of `GET /tasks`, where `status`, `priority` and
+    /// `tags` take comma-separated lists and `q` searches name and description
+    pub fn from_query(params: &HashMap<String, String>) -> Result<Self> {
+        let invalid = |param: &str, value: &str| TaskQueueError::ValidationError {
+            reason: format!("Invalid {}: {}", param, value),
+        };
+        let list = |param: &str| -> Vec<String> {
+            params.get(param)
+                .map(|values| values.split(',').map(str::trim).filter(|v| !v.is_empty()).map(str::to_string).collect())
+                .unwrap_or_default()
+        };
+        let priority = |value: &str| parse_priority(value).ok_or_else(|| invalid("priority", value));
+        let time = |param: &str| -> Result<Option<DateTime<Utc>>> {
+            params.get(param)
+                .map(|value| DateTime::parse_from_rfc3339(value).map(|t| t.with_timezone(&Utc)).map_err(|_| invalid(param, value)))
+                .transpose()
+        };
+
+        let filter = Self {
+            project: params.get("project").cloned(),
+            project_id: params.get("project_id")
+                .map(|id| id.parse().map_err(|_| invalid("project_id", id)))
+                .transpose()?,
+            statuses: list("status"),
+            priorities: list("priority").iter().map(|p| priority(p)).collect::<Result<_>>()?,
+            min_priority: params.get("min_priority").map(|p| priority(p)).transpose()?,
+            max_priority: params.get("max_priority").map(|p| priority(p)).transpose()?,
+            tags: list("tags"),
+            created_after: time("created_after")?,
+            created_before: time("created_before")?,
+            overdue: params.get("overdue").is_some_and(|overdue| overdue == "true"),
+            name_contains: params.get("name").cloned(),
+            text: params.get("q").cloned(),
+            metadata: HashMap::new(),
+        };
+        filter.validate()?;
+        Ok(filter)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if let Some(status) = self.statuses.iter().find(|s| !STATUS_FILTERS.contains(&s.as_str())) {
+            return Err(TaskQueueError::ValidationError {
+                reason: format!("Unknown status '{}', expected one of {}", status, STATUS_FILTERS.join(", ")),
+            });
+        }
+        Ok(())
+    }
+
+    /// Whether a task, shown with `status`, belongs in the view
+    pub fn matches(&self, task: &Task, status: &TaskStatus, now: DateTime<Utc>) -> bool {
+        if self.project.is_some() && task.project != self.project {
+            return false;
+        }
+        if self.project_id.is_some() && task.project_id != self.project_id {
+            return false;
+        }
+        if !self.statuses.is_empty() && !self.statuses.iter().any(|s| status.matches_filter(s)) {
+            return false;
+        }
+        if !self.priorities.is_empty() && !self.priorities.contains(&task.priority) {
+            return false;
+        }
+        if self.min_priority.as_ref().is_some_and(|min| task.priority < *min)
+            || self.max_priority.as_ref().is_some_and(|max| task.priority > *max)
+        {
+            return false;
+        }
+        if !self.tags.is_empty() {
+            let tags = task.metadata.get("tags").and_then(|tags| tags.as_array());
+            let has = |tag: &String| tags.is_some_and(|tags| tags.iter().any(|t| t.as_str() == Some(tag.as_str())));
+            if !self.tags.iter().all(has) {
+                return false;
+            }
+        }
+        let created_at = DateTime::<Utc>::from(task.created_at);
+        if self.created_after.is_some_and(|after| created_at <= after)
+            || self.created_before.is_some_and(|before| created_at >= before)
+        {
+            return false;
+        }
+        if self.overdue {
+            let closed = matches!(
+                status,
+                TaskStatus::Completed | TaskStatus::Finalized | TaskStatus::Failed | TaskStatus::Cancelled
+            );
+            if closed || estimates::due_date(task).is_none_or(|due| due >= now) {
+                return false;
+            }
+        }
+        if let Some(needle) = &self.name_contains
+            && !task.name.to_lowercase().contains(&needle.to_lowercase())
+        {
+            return false;
+        }
+        if let Some(text) = &self.text {
+            let text = text.to_lowercase();
+            if !task.name.to_lowercase().contains(&text) && !task.description.to_lowercase().contains(&text) {
+                return false;
+            }
+        }
+        self.metadata.iter().all(|(key, value)| task.metadata.get(key) == Some(value))
+    }
+}
+
+/// A priority by name, in any case
+fn parse_priority(priority: &str) -> Option<TaskPriority> {
+    match priority.to_lowercase().as_str() {
+        "low" => Some(TaskPriority::Low),
+        "normal" => Some(TaskPriority::Normal),
+        "high" => Some(TaskPriority::High),
+        "critical" => Some(TaskPriority::Critical),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SortField {
+    CreatedAt,
+    UpdatedAt,
+    Priority,
+    Name,
+    DueDate,
+}
+
+impl SortField {
+    /// Parse a `sort` query parameter such as `created_at`
+    pub fn parse(field: &str) -> Option<Self> {
+        serde_json::from_value(serde_json::Value::String(field.to_string())).ok()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct TaskSort {
+    pub field: SortField,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+impl TaskSort {
+    fn compare(&self, a: &Task, b: &Task) -> Ordering {
+        let ordering = match self.field {
+            SortField::CreatedAt => a.created_at.cmp(&b.created_at),
+            SortField::UpdatedAt => a.updated_at.cmp(&b.updated_at),
+            SortField::Priority => a.priority.cmp(&b.priority),
+            SortField::Name => a.name.cmp(&b.name),
+            // Tasks without a due date go last either way
+            SortField::DueDate => {
+                return match (estimates::due_date(a), estimates::due_date(b)) {
+                    (Some(a), Some(b)) if self.descending => b.cmp(&a),
+                    (Some(a), Some(b)) => a.cmp(&b),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                };
+            }
+        };
+        if self.descending { ordering.reverse() } else { ordering }
+    }
+
+    pub fn apply(&self, tasks: &mut [Task]) {
+        tasks.sort_by(|a, b| self.compare(a, b));
+    }
+}
+
+/// A saved view
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SavedView {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub filter: TaskFilter,
+    pub sort: Option<TaskSort>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Tenant it belongs to; `None` for the operator's views and those from
+    /// before tenants
+    #[serde(default)]
+    pub tenant_id: Option<Uuid>,
+}
+
+impl SavedView {
+    /// Key a view is stored under, so that tenants can use the same names
+    pub fn storage_key(tenant: Option<Uuid>, name: &str) -> String {
+        match tenant {
+            Some(tenant) => format!("{}/{}", tenant, name),
+            None => name.to_string(),
+        }
+    }
+
+    pub fn key(&self) -> String {
+        Self::storage_key(self.tenant_id, &self.name)
+    }
+}
+
+/// Body of `PUT /views/{name}`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SaveViewRequest {
+    pub description: Option<String>,
+    #[serde(default)]
+    pub filter: TaskFilter,
+    pub sort: Option<TaskSort>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::DUE_DATE_KEY;
+    use serde_json::json;
+
+    #[test]
+    fn test_filter_and_sort() {
+        let now = Utc::now();
+        let task = |name: &str, priority: TaskPriority, due: Option<&str>| {
+            let mut task = Task::new(name).with_priority(priority).build();
+            task.project = Some("frontend".to_string());
+            if let Some(due) = due {
+                task.metadata.insert(DUE_DATE_KEY.to_string(), json!(due));
+            }
+            task
+        };
+        let mut tasks = vec![
+            task("login page", TaskPriority::Critical, Some("2000-01-02")),
+            task("navbar", TaskPriority::Critical, Some("2000-01-01")),
+            task("footer", TaskPriority::Low, Some("2000-01-01")),
+            task("signup", TaskPriority::Critical, None),
+        ];
+
+        let filter: TaskFilter = serde_json::from_value(json!({
+            "project": "frontend",
+            "priorities": ["Critical"],
+            "overdue": true
+        }))
+        .unwrap();
+        tasks.retain(|t| filter.matches(t, &TaskStatus::Planning, now));
+        TaskSort { field: SortField::DueDate, descending: false }.apply(&mut tasks);
+        let names: Vec<&str> = tasks.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["navbar", "login page"]);
+
+        assert!(!filter.matches(&tasks[0], &TaskStatus::Completed, now));
+        assert!(TaskFilter { statuses: vec!["bogus".to_string()], ..Default::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_filter_from_query() {
+        let now = Utc::now();
+        let query = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+        };
+        let mut task = Task::new("Login page").with_priority(TaskPriority::High).build();
+        task.description = "OAuth sign-in flow".to_string();
+        task.metadata.insert("tags".to_string(), json!(["frontend", "auth"]));
+
+        let filter = TaskFilter::from_query(&query(&[
+            ("status", "pending,planning"),
+            ("min_priority", "normal"),
+            ("max_priority", "High"),
+            ("tags", "auth,frontend"),
+            ("created_after", "2000-01-01T00:00:00Z"),
+            ("q", "oauth"),
+        ]))
+        .unwrap();
+        assert_eq!(filter.statuses, ["pending", "planning"]);
+        assert!(filter.matches(&task, &TaskStatus::Planning, now));
+        assert!(!filter.matches(&task, &TaskStatus::Running, now));
+
+        let before = TaskFilter::from_query(&query(&[("created_before", "2000-01-01T00:00:00Z")])).unwrap();
+        assert!(!before.matches(&task, &TaskStatus::Planning, now));
+        let tagged = TaskFilter::from_query(&query(&[("tags", "backend")])).unwrap();
+        assert!(!tagged.matches(&task, &TaskStatus::Planning, now));
+        assert!(TaskFilter::from_query(&query(&[("priority", "urgent")])).is_err());
+        assert!(TaskFilter::from_query(&query(&[("created_after", "yesterday")])).is_err());
+    }
+}
@@ -0,0 +1,324 @@
+//! WASM Sandbox Module
+//!
+//! Executor running tasks compiled to WASI (preview 1) inside wasmtime. A task
+//! gets no filesystem or network access unless it asks for it in its `wasm`
+//! metadata and the executor's policy allows it, which makes this a safe
+//! default for running generated code on shared machines.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::*;
+use crate::executor::Executor;
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::info;
+use wasmtime::{Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+use wasmtime_wasi::p1::WasiP1Ctx;
+use wasmtime_wasi::p2::pipe::MemoryOutputPipe;
+use wasmtime_wasi::{FsPerms, I32Exit, WasiCtxBuilder};
+
+/// Executor name
+pub const WASM_EXECUTOR: &str = "wasm";
+/// Task metadata key holding the capabilities a task requests
+pub const CAPABILITIES_METADATA_KEY: &str = "wasm";
+
+/// Host directory tasks may mount
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllowedDir {
+    pub host: PathBuf,
+    #[serde(default)]
+    pub writable: bool,
+}
+
+/// Directory a task asks to see inside the sandbox
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mount {
+    pub host: PathBuf,
+    pub guest: String,
+    #[serde(default)]
+    pub writable: bool,
+}
+
+/// Capabilities requested through the task's `wasm` metadata
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Capabilities {
+    #[serde(default)]
+    pub mounts: Vec<Mount>,
+    #[serde(default)]
+    pub network: bool,
+    /// Lower the executor's fuel limit for this task
+    pub fuel: Option<u64>,
+}
+
+/// What the sandbox may grant
+#[derive(Debug, Clone)]
+pub struct WasmPolicy {
+    pub allowed_dirs: Vec<AllowedDir>,
+    pub allow_network: bool,
+    /// Instructions a task may consume before it is stopped
+    pub fuel: u64,
+    pub max_memory_bytes: usize,
+    /// Cap on captured stdout and stderr, each
+    pub max_output_bytes: usize,
+}
+
+impl Default for WasmPolicy {
+    fn default() -> Self {
+        Self {
+            allowed_dirs: Vec::new(),
+            allow_network: false,
+            fuel: 10_000_000_000,
+            max_memory_bytes: 256 * 1024 * 1024,
+            max_output_bytes: 1024 * 1024,
+        }
+    }
+}
+
+struct SandboxState {
+    wasi: WasiP1Ctx,
+    limits: StoreLimits,
+}
+
+/// Runs WASI modules; the task's `command` is the module path followed by its arguments
+#[derive(Clone)]
+pub struct WasmExecutor {
+    engine: Engine,
+    policy: WasmPolicy,
+}
+
+impl WasmExecutor {
+    pub fn new(policy: WasmPolicy) -> wasmtime::Result<Self> {
+        let mut config = wasmtime::Config::new();
+        config.consume_fuel(true);
+        Ok(Self {
+            engine: Engine::new(&config)?,
+            policy,
+        })
+    }
+
+    pub fn policy(&self) -> &WasmPolicy {
+        &self.policy
+    }
+
+    /// Check a mount against the allowed directories
+    fn authorize(&self, mount: &Mount) -> std::result::Result<FsPerms, String> {
+        let host = mount.host.canonicalize()
+            .map_err(|e| format!("Cannot mount {}: {}", mount.host.display(), e))?;
+
+        for allowed in &self.policy.allowed_dirs {
+            let Ok(root) = allowed.host.canonicalize() else {
+                continue;
+            };
+            if !host.starts_with(&root) {
+                continue;
+            }
+            if mount.writable && !allowed.writable {
+                return Err(format!("{} may only be mounted read-only", mount.host.display()));
+            }
+            return Ok(if mount.writable { FsPerms::ReadWrite } else { FsPerms::ReadOnly });
+        }
+
+        Err(format!("{} is outside the sandbox's allowed directories", mount.host.display()))
+    }
+
+    fn capabilities(task: &Task) -> std::result::Result<Capabilities, String> {
+        match task.metadata.get(CAPABILITIES_METADATA_KEY) {
+            Some(value) => serde_json::from_value(value.clone())
+                .map_err(|e| format!("Invalid wasm capabilities: {}", e)),
+            None => Ok(Capabilities::default()),
+        }
+    }
+
+    /// Instantiate and run the module, blocking the current thread
+    fn run_blocking(&self, task: &Task) -> TaskResult {
+        let fail = |error: String| TaskResult::Failure {
+            error,
+            exit_code: None,
+            logs: Vec::new(),
+            metrics: None,
+        };
+
+        let mut words = task.command.split_whitespace();
+        let Some(module_path) = words.next() else {
+            return fail("Task has no WASM module to execute".to_string());
+        };
+        let capabilities = match Self::capabilities(task) {
+            Ok(capabilities) => capabilities,
+            Err(e) => return fail(e),
+        };
+        if capabilities.network && !self.policy.allow_network {
+            return fail("Network access is not allowed in this sandbox".to_string());
+        }
+
+        let stdout = MemoryOutputPipe::new(self.policy.max_output_bytes);
+        let stderr = MemoryOutputPipe::new(self.policy.max_output_bytes);
+        let mut wasi = WasiCtxBuilder::new();
+        wasi.arg(module_path)
+            .args(&words.collect::<Vec<_>>())
+            .stdout(stdout.clone())
+            .stderr(stderr.clone());
+        for (key, value) in &task.environment {
+            wasi.env(key, value);
+        }
+        for mount in &capabilities.mounts {
+            let perms = match self.authorize(mount) {
+                Ok(perms) => perms,
+                Err(e) => return fail(e),
+            };
+            if let Err(e) = wasi.preopened_dir(&mount.host, &mount.guest, perms) {
+                return fail(format!("Cannot mount {}: {}", mount.host.display(), e));
+            }
+        }
+        if capabilities.network {
+            wasi.inherit_network().allow_ip_name_lookup(true);
+        }
+
+        let state = SandboxState {
+            wasi: wasi.build_p1(),
+            limits: StoreLimitsBuilder::new().memory_size(self.policy.max_memory_bytes).build(),
+        };
+        let mut store = Store::new(&self.engine, state);
+        store.limiter(|state| &mut state.limits);
+        let fuel = capabilities.fuel.map_or(self.policy.fuel, |fuel| fuel.min(self.policy.fuel));
+        if let Err(e) = store.set_fuel(fuel) {
+            return fail(e.to_string());
+        }
+
+        let start = Instant::now();
+        let outcome = (|| -> wasmtime::Result<()> {
+            let module = Module::from_file(&self.engine, module_path)?;
+            let mut linker: Linker<SandboxState> = Linker::new(&self.engine);
+            wasmtime_wasi::p1::add_to_linker_sync(&mut linker, |state| &mut state.wasi)?;
+            let instance = linker.instantiate(&mut store, &module)?;
+            instance.get_typed_func::<(), ()>(&mut store, "_start")?.call(&mut store, ())
+        })();
+        let execution_time = start.elapsed();
+
+        let output = String::from_utf8_lossy(&stdout.contents()).to_string();
+        let errors = String::from_utf8_lossy(&stderr.contents()).to_string();
+        let exit_code = match &outcome {
+            Ok(()) => Some(0),
+            Err(e) => e.downcast_ref::<I32Exit>().map(|exit| exit.0),
+        };
+
+        if exit_code == Some(0) {
+            return TaskResult::Success {
+                output,
+                artifacts: Vec::new(),
+                metrics: TaskMetrics {
+                    execution_time,
+                    memory_usage: 0,
+                    cpu_usage: 0.0,
+                    disk_usage: 0,
+                    network_io: 0,
+                },
+            };
+        }
+
+        TaskResult::Failure {
+            error: match (exit_code, &outcome) {
+                (Some(code), _) => format!("Module exited with code {}", code),
+                (None, Err(e)) => format!("Module trapped: {:#}", e),
+                (None, Ok(())) => "Module failed".to_string(),
+            },
+            exit_code,
+            logs: output.lines().chain(errors.lines()).map(str::to_string).collect(),
+            metrics: None,
+        }
+    }
+}
+
+impl Executor for WasmExecutor {
+    fn name(&self) -> &str {
+        WASM_EXECUTOR
+    }
+
+    fn execute<'a>(&'a self, task: &'a Task) -> BoxFuture<'a, TaskResult> {
+        Box::pin(async move {
+            info!("Executing task {} ({}) in WASM sandbox", task.name, task.id);
+            let executor = self.clone();
+            let task = task.clone();
+            tokio::task::spawn_blocking(move || executor.run_blocking(&task))
+                .await
+                .unwrap_or_else(|e| TaskResult::Failure {
+                    error: format!("Sandbox thread failed: {}", e),
+                    exit_code: None,
+                    logs: Vec::new(),
+                    metrics: None,
+                })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const HELLO: &str = r#"
+        (module
+          (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 16) "hello\n")
+          (func (export "_start")
+            (i32.store (i32.const 0) (i32.const 16))
+            (i32.store (i32.const 4) (i32.const 6))
+            (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))))
+    "#;
+
+    const EXIT_3: &str = r#"
+        (module
+          (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+          (memory (export "memory") 1)
+          (func (export "_start") (call $proc_exit (i32.const 3))))
+    "#;
+
+    fn module(source: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("task-queue-{}.wat", uuid::Uuid::new_v4()));
+        std::fs::write(&path, source).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn test_runs_module_and_captures_output() {
+        let executor = WasmExecutor::new(WasmPolicy::default()).unwrap();
+        let hello = module(HELLO);
+        let task = Task::new("hello").with_command(&hello.display().to_string()).build();
+
+        match executor.execute(&task).await {
+            TaskResult::Success { output, .. } => assert_eq!(output, "hello\n"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let exit = module(EXIT_3);
+        let task = Task::new("exit").with_command(&exit.display().to_string()).build();
+        assert!(matches!(executor.execute(&task).await, TaskResult::Failure { exit_code: Some(3), .. }));
+
+        std::fs::remove_file(hello).unwrap();
+        std::fs::remove_file(exit).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_denies_capabilities_outside_policy() {
+        let executor = WasmExecutor::new(WasmPolicy::default()).unwrap();
+        let hello = module(HELLO);
+
+        let mut task = Task::new("mount").with_command(&hello.display().to_string()).build();
+        task.metadata.insert(CAPABILITIES_METADATA_KEY.to_string(), json!({
+            "mounts": [{ "host": std::env::temp_dir(), "guest": "/tmp" }]
+        }));
+        assert!(matches!(executor.execute(&task).await, TaskResult::Failure { error, .. } if error.contains("outside")));
+
+        task.metadata.insert(CAPABILITIES_METADATA_KEY.to_string(), json!({ "network": true }));
+        assert!(matches!(executor.execute(&task).await, TaskResult::Failure { error, .. } if error.contains("Network")));
+
+        std::fs::remove_file(hello).unwrap();
+    }
+}
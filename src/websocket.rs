@@ -202,12 +202,12 @@ impl WebSocketManager {
     pub async fn send_to_client(&self, client_id: &str, message: WebSocketMessage) -> Result<(), String> {
         let clients = self.clients.read().await;
         
-        if let Some(client) = clients.get(client_id) {
-            if client.state == ConnectionState::Connected {
-                // In a real implementation, this would send through the WebSocket connection
-                debug!("Sending message to client {}: {:?}", client_id, message);
-                return Ok(());
-            }
+        if let Some(client) = clients.get(client_id)
+            && client.state == ConnectionState::Connected
+        {
+            // In a real implementation, this would send through the WebSocket connection
+            debug!("Sending message to client {}: {:?}", client_id, message);
+            return Ok(());
         }
         
         Err("Client not found or not connected".to_string())
@@ -0,0 +1,148 @@
+//! Worker Registry Module
+//!
+//! External worker processes register under the `worker_id` they claim tasks
+//! with, describing themselves with labels and how many tasks they run at
+//! once. A task can ask for particular workers with a label selector in its
+//! `worker_selector` metadata (e.g. `{"gpu": "true"}`); such tasks are only
+//! handed to registered workers carrying every selected label, and never run
+//! by the built-in execution engine.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::*;
+use crate::error::{TaskQueueError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Task metadata key holding the labels a worker must have to run the task
+pub const WORKER_SELECTOR_KEY: &str = "worker_selector";
+/// Workers not seen for longer than this are reported as offline
+pub const OFFLINE_AFTER_SECS: i64 = 300;
+
+/// A registered worker process
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Worker {
+    /// The `worker_id` the worker claims tasks with
+    pub id: String,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+    /// Most tasks leased to the worker at once
+    pub max_concurrency: u32,
+    pub registered_at: DateTime<Utc>,
+    /// Last registration or claim
+    pub last_seen: DateTime<Utc>,
+}
+
+/// Body of `POST /workers/register`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegisterWorker {
+    pub worker_id: String,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+    #[serde(default = "default_max_concurrency")]
+    pub max_concurrency: u32,
+}
+
+fn default_max_concurrency() -> u32 {
+    1
+}
+
+impl RegisterWorker {
+    pub fn validate(&self) -> Result<()> {
+        if self.worker_id.trim().is_empty() {
+            return Err(TaskQueueError::ValidationError {
+                reason: "Worker ID cannot be empty".to_string(),
+            });
+        }
+        if self.max_concurrency == 0 {
+            return Err(TaskQueueError::ValidationError {
+                reason: "max_concurrency must be at least 1".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkerState {
+    Online,
+    Offline,
+}
+
+/// Worker as reported by `GET /workers`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkerStatus {
+    #[serde(flatten)]
+    pub worker: Worker,
+    pub state: WorkerState,
+    /// Tasks currently leased to the worker
+    pub running: usize,
+}
+
+impl WorkerStatus {
+    pub fn at(worker: Worker, running: usize, now: DateTime<Utc>) -> Self {
+        let state = if (now - worker.last_seen).num_seconds() > OFFLINE_AFTER_SECS {
+            WorkerState::Offline
+        } else {
+            WorkerState::Online
+        };
+        Self { worker, state, running }
+    }
+}
+
+/// The labels a task requires of its worker; `None` if any worker will do
+pub fn selector(task: &Task) -> Option<HashMap<String, String>> {
+    let value = task.metadata.get(WORKER_SELECTOR_KEY)?;
+    let selector = value.as_object()?
+        .iter()
+        .map(|(key, value)| {
+            let value = match value {
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            (key.clone(), value)
+        })
+        .collect::<HashMap<_, _>>();
+    (!selector.is_empty()).then_some(selector)
+}
+
+impl Worker {
+    /// Whether the worker carries every label the task selects
+    pub fn matches(&self, task: &Task) -> bool {
+        selector(task).is_none_or(|selector| {
+            selector.iter().all(|(key, value)| self.labels.get(key) == Some(value))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_workers_match_task_selectors() {
+        let now = Utc::now();
+        let worker = Worker {
+            id: "gpu-1".to_string(),
+            labels: HashMap::from([("gpu".to_string(), "true".to_string()), ("region".to_string(), "eu".to_string())]),
+            max_concurrency: 2,
+            registered_at: now,
+            last_seen: now,
+        };
+
+        assert!(worker.matches(&Task::new("any").build()));
+        assert!(worker.matches(&Task::new("train").with_metadata(WORKER_SELECTOR_KEY, json!({"gpu": true})).build()));
+        assert!(!worker.matches(&Task::new("train").with_metadata(WORKER_SELECTOR_KEY, json!({"gpu": "true", "region": "us"})).build()));
+        assert_eq!(selector(&Task::new("any").with_metadata(WORKER_SELECTOR_KEY, json!({})).build()), None);
+
+        let later = now + chrono::Duration::seconds(OFFLINE_AFTER_SECS + 1);
+        assert_eq!(WorkerStatus::at(worker, 0, later).state, WorkerState::Offline);
+    }
+}
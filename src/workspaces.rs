@@ -0,0 +1,172 @@
+//! Task Workspaces Module
+//!
+//! Tasks that don't set a `working_directory` run in a fresh directory of
+//! their own under `execution.workspaces.root` instead of the server's
+//! working directory, so runs can't trip over each other's files. The
+//! directory is removed when the run ends, or kept for debugging with
+//! `retain` and removed by the retention job once older than
+//! `retention_hours`.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::core::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{error, info, warn};
+
+/// How often the retention job looks for expired workspaces
+pub const RETENTION_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Where and for how long per-run workspaces live
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkspaceConfig {
+    /// Give tasks without a working directory one of their own
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Defaults to `task-queue-workspaces` in the system temp directory
+    pub root: Option<PathBuf>,
+    /// Keep workspaces after the run instead of removing them right away
+    #[serde(default)]
+    pub retain: bool,
+    /// Age at which the retention job removes kept workspaces
+    #[serde(default = "default_retention_hours")]
+    pub retention_hours: u64,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_retention_hours() -> u64 {
+    24
+}
+
+impl Default for WorkspaceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            root: None,
+            retain: false,
+            retention_hours: default_retention_hours(),
+        }
+    }
+}
+
+impl WorkspaceConfig {
+    pub fn root(&self) -> PathBuf {
+        self.root.clone().unwrap_or_else(|| std::env::temp_dir().join("task-queue-workspaces"))
+    }
+
+    pub fn retention(&self) -> Duration {
+        Duration::from_secs(self.retention_hours * 3600)
+    }
+}
+
+/// The directory a task runs in; removed on drop unless retained
+#[derive(Debug)]
+pub struct Workspace {
+    path: PathBuf,
+    retain: bool,
+}
+
+impl Workspace {
+    /// Create a fresh workspace for a run of the task
+    pub fn create(config: &WorkspaceConfig, task: &Task) -> std::io::Result<Self> {
+        let stamp = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_millis();
+        let path = config.root().join(format!("{}-{}", task.id, stamp));
+        std::fs::create_dir_all(&path)?;
+        Ok(Self { path, retain: config.retain })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        if self.retain {
+            return;
+        }
+        if let Err(e) = std::fs::remove_dir_all(&self.path) {
+            warn!("Failed to remove workspace {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Remove the workspaces under `root` last modified before `now - max_age`;
+/// returns how many were removed
+pub fn remove_expired(root: &Path, max_age: Duration, now: SystemTime) -> std::io::Result<usize> {
+    let entries = match std::fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        let expired = meta.modified().is_ok_and(|modified| {
+            now.duration_since(modified).is_ok_and(|age| age >= max_age)
+        });
+        if meta.is_dir() && expired {
+            match std::fs::remove_dir_all(entry.path()) {
+                Ok(()) => removed += 1,
+                Err(e) => warn!("Failed to remove workspace {}: {}", entry.path().display(), e),
+            }
+        }
+    }
+    Ok(removed)
+}
+
+/// Remove expired workspaces every [`RETENTION_INTERVAL`] in the background
+pub fn spawn(config: WorkspaceConfig) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(RETENTION_INTERVAL);
+        loop {
+            ticker.tick().await;
+            let (root, retention) = (config.root(), config.retention());
+            match tokio::task::spawn_blocking(move || remove_expired(&root, retention, SystemTime::now())).await {
+                Ok(Ok(removed)) if removed > 0 => info!("Removed {} expired task workspaces", removed),
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => error!("Failed to remove expired task workspaces: {}", e),
+                Err(e) => error!("Workspace retention job failed: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workspaces_are_removed_unless_retained() {
+        let root = std::env::temp_dir().join(format!("task-queue-workspaces-{}", uuid::Uuid::new_v4()));
+        let task = Task::new("build").build();
+        let config = WorkspaceConfig { root: Some(root.clone()), ..Default::default() };
+
+        let workspace = Workspace::create(&config, &task).unwrap();
+        let path = workspace.path().to_path_buf();
+        assert!(path.is_dir() && path.starts_with(&root));
+        drop(workspace);
+        assert!(!path.exists());
+
+        let retained = Workspace::create(&WorkspaceConfig { retain: true, ..config }, &task).unwrap();
+        let path = retained.path().to_path_buf();
+        drop(retained);
+        assert!(path.is_dir());
+        assert_eq!(remove_expired(&root, Duration::from_secs(3600), SystemTime::now()).unwrap(), 0);
+        let later = SystemTime::now() + Duration::from_secs(3600);
+        assert_eq!(remove_expired(&root, Duration::from_secs(3600), later).unwrap(), 1);
+        assert!(!path.exists());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
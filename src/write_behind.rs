@@ -0,0 +1,121 @@
+//! Write-Behind Module
+//!
+//! Workers renew their lease on a task every few seconds, and each renewal
+//! used to be a synchronous write of the whole task. When
+//! `storage.write_behind.enabled` is set, a server keeping its tasks in memory
+//! only marks such tasks as dirty and a background job writes them every
+//! `window_ms`, all in one commit. Progress reports from agents are deferred
+//! the same way. A task renewed many times within the window is written once,
+//! as it is by then. Any other write of the task, or its
+//! deletion, takes the mark away, as the task is written with it. A crash
+//! loses at most the last `window_ms` of renewals; the remaining ones are
+//! written when the server shuts down. Stateless servers always write
+//! synchronously, as other replicas read the task from storage.
+//!
+
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+#![allow(dead_code)]
+#![allow(unused_mut)]
+
+use crate::server::TaskQueueServer;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::error;
+use uuid::Uuid;
+
+/// The background write-behind job
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WriteBehindConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Longest a deferred write waits
+    #[serde(default = "default_window_ms")]
+    pub window_ms: u64,
+}
+
+fn default_window_ms() -> u64 {
+    1000
+}
+
+impl Default for WriteBehindConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_ms: default_window_ms(),
+        }
+    }
+}
+
+impl WriteBehindConfig {
+    pub fn window(&self) -> Duration {
+        Duration::from_millis(self.window_ms.max(1))
+    }
+}
+
+/// Tasks changed in memory but not yet written
+#[derive(Debug, Default)]
+pub struct WriteBehind {
+    enabled: bool,
+    dirty: Mutex<HashSet<Uuid>>,
+}
+
+impl WriteBehind {
+    pub fn new(config: &WriteBehindConfig) -> Self {
+        Self {
+            enabled: config.enabled,
+            dirty: Mutex::default(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn mark(&self, task_id: Uuid) {
+        self.dirty.lock().unwrap_or_else(|e| e.into_inner()).insert(task_id);
+    }
+
+    /// Take the mark away once the task was written some other way
+    pub fn clear(&self, task_id: &Uuid) {
+        self.dirty.lock().unwrap_or_else(|e| e.into_inner()).remove(task_id);
+    }
+
+    pub fn pending(&self) -> Vec<Uuid> {
+        self.dirty.lock().unwrap_or_else(|e| e.into_inner()).iter().copied().collect()
+    }
+}
+
+/// Write deferred task updates every `window_ms` in the background
+pub fn spawn(server: Arc<TaskQueueServer>, config: WriteBehindConfig) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(config.window());
+        loop {
+            ticker.tick().await;
+            if let Err(e) = server.flush_writes().await {
+                error!("Failed to write deferred task updates: {}", e);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_marks_coalesce_per_task() {
+        let write_behind = WriteBehind::new(&WriteBehindConfig { enabled: true, ..Default::default() });
+        let (renewed, completed) = (Uuid::new_v4(), Uuid::new_v4());
+        for _ in 0..3 {
+            write_behind.mark(renewed);
+        }
+        write_behind.mark(completed);
+        write_behind.clear(&completed);
+
+        assert!(write_behind.is_enabled());
+        assert_eq!(write_behind.pending(), [renewed]);
+    }
+}